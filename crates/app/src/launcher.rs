@@ -38,7 +38,10 @@ impl Launcher {
 
     pub fn add_dynamic_plugin(&self, name: &str, path: &Path) {
         let app = &mut self.app.write().unwrap();
-        app.add_dynamic_plugin(path);
+        if let Err(error) = app.add_dynamic_plugin(path) {
+            eprintln!("Unable to load plugin {name}: {error}");
+            return;
+        }
         app.load_config_on_plugin_systems(name);
     }
 
@@ -69,4 +72,8 @@ impl Launcher {
     pub fn update(&self) -> bool {
         self.app.write().unwrap().run()
     }
+    #[allow(clippy::await_holding_lock)]
+    pub async fn update_async(&self) -> bool {
+        self.app.write().unwrap().run_async().await
+    }
 }
@@ -65,17 +65,20 @@ pub fn main_update(launcher: Arc<Launcher>) {
     unsafe {
         MESSAGE_HUB = Some(launcher.message_hub());
     }
-    let can_continue = launcher.update();
-    if can_continue {
-        let cb = Closure::wrap(Box::new(move || {
-            main_update(launcher.clone());
-        }) as Box<dyn FnMut()>);
-        web_sys::window()
-            .unwrap()
-            .request_animation_frame(cb.as_ref().unchecked_ref())
-            .ok();
-        cb.forget();
-    }
+    //The browser handles its events between the phases of the frame
+    wasm_bindgen_futures::spawn_local(async move {
+        let can_continue = launcher.update_async().await;
+        if can_continue {
+            let cb = Closure::wrap(Box::new(move || {
+                main_update(launcher.clone());
+            }) as Box<dyn FnMut()>);
+            web_sys::window()
+                .unwrap()
+                .request_animation_frame(cb.as_ref().unchecked_ref())
+                .ok();
+            cb.forget();
+        }
+    });
 }
 
 fn hook(info: &std::panic::PanicInfo) {
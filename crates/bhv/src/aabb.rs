@@ -1,4 +1,4 @@
-use inox_math::{VecBase, Vector3};
+use inox_math::{Mat4Ops, Matrix4, VecBase, Vector3};
 
 pub const AXIS_COUNT: usize = 3;
 pub(crate) const INVALID_INDEX: i32 = -1;
@@ -59,8 +59,73 @@ impl AABB {
     pub fn size(&self) -> Vector3 {
         self.max - self.min
     }
+    pub fn surface_area(&self) -> f32 {
+        let size = self.size();
+        2. * (size.x * size.y + size.y * size.z + size.z * size.x)
+    }
     pub fn expand_to_include(&mut self, other: &AABB) {
         self.max = self.max.max(other.max).max(other.min);
         self.min = self.min.min(other.min).min(other.max);
     }
+    //Transforming only min and max doesn't enclose the box once it's rotated,
+    //so the result encloses all the eight transformed corners
+    pub fn transformed(&self, matrix: &Matrix4) -> Self {
+        let mut aabb = Self::empty();
+        aabb.index = self.index;
+        for i in 0..8 {
+            let corner = Vector3::new(
+                if i & 1 == 0 { self.min.x } else { self.max.x },
+                if i & 2 == 0 { self.min.y } else { self.max.y },
+                if i & 4 == 0 { self.min.z } else { self.max.z },
+            );
+            let point = matrix.rotate_point(corner);
+            aabb.min = aabb.min.min(point);
+            aabb.max = aabb.max.max(point);
+        }
+        aabb
+    }
+}
+
+#[allow(dead_code)]
+fn test_transformed() {
+    use inox_math::{Degrees, NewAngle, VecBaseFloat};
+
+    let is_near = |a: Vector3, b: Vector3| (a - b).length() < 1e-5;
+    let aabb = AABB::create(
+        Vector3::new(-0.5, -0.5, -0.5),
+        Vector3::new(0.5, 0.5, 0.5),
+        3,
+    );
+    let matrix = Matrix4::from_angle_y(Degrees::new(45.));
+    let transformed = aabb.transformed(&matrix);
+    //The diagonal of the unit square on xz becomes the extent along x and z
+    let half_diagonal = 0.5 * 2f32.sqrt();
+    assert!(is_near(
+        transformed.min(),
+        Vector3::new(-half_diagonal, -0.5, -half_diagonal)
+    ));
+    assert!(is_near(
+        transformed.max(),
+        Vector3::new(half_diagonal, 0.5, half_diagonal)
+    ));
+    assert_eq!(transformed.index(), 3);
+
+    //Rotating only min and max gives an undersized box: a 45 degrees rotation around y
+    //brings both corners on the z = 0 plane, flattening z while x keeps the full diagonal
+    let min = matrix.rotate_point(aabb.min());
+    let max = matrix.rotate_point(aabb.max());
+    assert!((max.z - min.z).abs() < 1e-5);
+    assert!(((max.x - min.x) - 2. * half_diagonal).abs() < 1e-5);
+    assert!(transformed.size().z > 2. * half_diagonal - 1e-5);
+
+    //Translation and scale move and grow the box
+    let matrix = Matrix4::from_translation(Vector3::new(1., 2., 3.)) * Matrix4::from_scale(2.);
+    let transformed = aabb.transformed(&matrix);
+    assert!(is_near(transformed.min(), Vector3::new(0., 1., 2.)));
+    assert!(is_near(transformed.max(), Vector3::new(2., 3., 4.)));
+}
+
+#[test]
+fn test() {
+    test_transformed();
 }
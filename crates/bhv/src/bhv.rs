@@ -1,4 +1,4 @@
-use std::fmt::Debug;
+use std::{collections::HashMap, fmt::Debug};
 
 use inox_math::Vector3;
 
@@ -62,6 +62,15 @@ impl Default for BHVNode {
     }
 }
 
+#[derive(Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BHVBuildQuality {
+    //Fast to build, good for trees built every frame
+    #[default]
+    Fast,
+    //Binned surface area heuristic, slower to build but faster to traverse
+    SurfaceAreaHeuristic,
+}
+
 #[derive(Default, Clone)]
 pub struct BHVTree {
     nodes: Vec<BHVNode>,
@@ -69,25 +78,94 @@ pub struct BHVTree {
 
 impl BHVTree {
     pub fn new(list: &[AABB]) -> Self {
+        Self::build(list, BHVBuildQuality::Fast)
+    }
+    pub fn new_sah(list: &[AABB]) -> Self {
+        Self::build(list, BHVBuildQuality::SurfaceAreaHeuristic)
+    }
+    pub fn build(list: &[AABB], quality: BHVBuildQuality) -> Self {
         let mut tree = Self::default();
         let aabb = AABB::compute_aabb(list);
         let root_node = BHVNode::create(&aabb, INVALID_NODE);
         tree.nodes.push(root_node);
         if list.len() > 1 {
-            tree.add(&aabb, tree.nodes.len() - 1, list);
+            tree.add(
+                &aabb,
+                tree.nodes.len() - 1,
+                list,
+                quality,
+                0,
+                &mut Vec::new(),
+            );
         } else {
             tree.nodes[0].set_aabb_index(0);
         }
         tree
     }
+    //The top of the tree is split until the groups have at most task_size aabbs,
+    //then build_subtrees builds a tree for each group, even in parallel,
+    //and they're merged in the same order of the serial build so that the tree is identical
+    pub fn build_in_tasks<F>(
+        list: &[AABB],
+        quality: BHVBuildQuality,
+        task_size: usize,
+        build_subtrees: F,
+    ) -> Self
+    where
+        F: FnOnce(Vec<Vec<AABB>>) -> Vec<BHVTree>,
+    {
+        if list.len() <= task_size.max(1) {
+            return Self::build(list, quality);
+        }
+        let mut top = Self::default();
+        let aabb = AABB::compute_aabb(list);
+        top.nodes.push(BHVNode::create(&aabb, INVALID_NODE));
+        let mut tasks = Vec::new();
+        top.add(&aabb, 0, list, quality, task_size, &mut tasks);
+
+        let (task_nodes, groups): (Vec<usize>, Vec<Vec<AABB>>) = tasks.into_iter().unzip();
+        let subtrees = build_subtrees(groups);
+        debug_assert!(subtrees.len() == task_nodes.len());
+        let mut subtrees = task_nodes.into_iter().zip(subtrees).collect();
+
+        let mut tree = Self::default();
+        tree.nodes.push(BHVNode::create(&aabb, INVALID_NODE));
+        tree.append_children(0, &top, 0, &mut subtrees);
+        tree
+    }
     pub fn nodes(&self) -> &[BHVNode] {
         &self.nodes
     }
     pub fn nodes_mut(&mut self) -> &mut [BHVNode] {
         &mut self.nodes
     }
-    fn add(&mut self, parent_aabb: &AABB, parent_index: usize, list: &[AABB]) {
-        let (mut left_group, mut right_group) = Partition::compute(parent_aabb, list);
+    //Expected cost of tracing a ray through the tree: the surface area of a node
+    //relative to the root is the probability of a ray hitting the root to hit it too
+    pub fn sah_cost(&self) -> f32 {
+        let root_area = self.nodes.first().map_or(0., |n| n.aabb.surface_area());
+        if root_area <= 0. {
+            return 0.;
+        }
+        self.nodes
+            .iter()
+            .map(|n| n.aabb.surface_area() / root_area)
+            .sum()
+    }
+    fn add(
+        &mut self,
+        parent_aabb: &AABB,
+        parent_index: usize,
+        list: &[AABB],
+        quality: BHVBuildQuality,
+        task_size: usize,
+        tasks: &mut Vec<(usize, Vec<AABB>)>,
+    ) {
+        let (mut left_group, mut right_group) = match quality {
+            BHVBuildQuality::Fast => Partition::compute(parent_aabb, list),
+            BHVBuildQuality::SurfaceAreaHeuristic => {
+                Partition::compute_binned_sah(parent_aabb, list)
+            }
+        };
 
         let left_aabb = if left_group.len() > 1 {
             AABB::compute_aabb(&left_group)
@@ -100,8 +178,17 @@ impl BHVTree {
         let left_index = self.nodes.len() - 1;
         self.nodes[parent_index].left = left_index as _;
 
-        if left_group.len() > 1 {
-            self.add(&left_aabb, left_index, &left_group);
+        if left_group.len() > task_size.max(1) {
+            self.add(
+                &left_aabb,
+                left_index,
+                &left_group,
+                quality,
+                task_size,
+                tasks,
+            );
+        } else if left_group.len() > 1 {
+            tasks.push((left_index, left_group));
         }
 
         let right_aabb = if right_group.len() > 1 {
@@ -115,10 +202,65 @@ impl BHVTree {
         let right_index = self.nodes.len() - 1;
         self.nodes[parent_index].right = right_index as _;
 
-        if right_group.len() > 1 {
-            self.add(&right_aabb, right_index, &right_group);
+        if right_group.len() > task_size.max(1) {
+            self.add(
+                &right_aabb,
+                right_index,
+                &right_group,
+                quality,
+                task_size,
+                tasks,
+            );
+        } else if right_group.len() > 1 {
+            tasks.push((right_index, right_group));
         }
     }
+    fn append_children(
+        &mut self,
+        parent_index: usize,
+        top: &BHVTree,
+        top_index: usize,
+        subtrees: &mut HashMap<usize, BHVTree>,
+    ) {
+        let top_node = top.nodes[top_index];
+        let left_index = self.append_child(parent_index, top, top_node.left as _, subtrees);
+        self.nodes[parent_index].left = left_index as _;
+        let right_index = self.append_child(parent_index, top, top_node.right as _, subtrees);
+        self.nodes[parent_index].right = right_index as _;
+    }
+    fn append_child(
+        &mut self,
+        parent_index: usize,
+        top: &BHVTree,
+        top_index: usize,
+        subtrees: &mut HashMap<usize, BHVTree>,
+    ) -> usize {
+        let index = self.nodes.len();
+        if let Some(subtree) = subtrees.remove(&top_index) {
+            let offset = index as u32;
+            self.nodes
+                .extend(subtree.nodes.into_iter().enumerate().map(|(i, mut node)| {
+                    if !node.is_leaf() {
+                        node.left += offset;
+                        node.right += offset;
+                    }
+                    node.parent = if i == 0 {
+                        parent_index as _
+                    } else {
+                        node.parent + offset as i32
+                    };
+                    node
+                }));
+        } else {
+            let top_node = top.nodes[top_index];
+            self.nodes
+                .push(BHVNode::create(&top_node.aabb, parent_index as _));
+            if !top_node.is_leaf() {
+                self.append_children(index, top, top_index, subtrees);
+            }
+        }
+        index
+    }
     pub fn insert_at(&mut self, position: usize, tree: BHVTree) -> &mut Self {
         if position < self.nodes.len() {
             let mut index = position;
@@ -167,3 +309,122 @@ impl Debug for BHVTree {
         Ok(())
     }
 }
+
+#[allow(dead_code)]
+fn clustered_aabbs() -> Vec<AABB> {
+    //Two distant clusters of boxes with a few big boxes in between
+    let mut list = Vec::new();
+    for cluster in 0..2 {
+        let offset = cluster as f32 * 100.;
+        for i in 0..64 {
+            let min = Vector3::new(
+                offset + (i % 4) as f32,
+                ((i / 4) % 4) as f32,
+                (i / 16) as f32,
+            );
+            let aabb = AABB::create(min, min + Vector3::new(0.5, 0.5, 0.5), list.len() as _);
+            list.push(aabb);
+        }
+    }
+    for i in 0..4 {
+        let min = Vector3::new(20. + i as f32 * 15., 0., 0.);
+        let aabb = AABB::create(min, min + Vector3::new(10., 10., 10.), list.len() as _);
+        list.push(aabb);
+    }
+    list
+}
+
+#[allow(dead_code)]
+fn test_builds_enclose_aabbs() {
+    let list = clustered_aabbs();
+    let contains = |outer: &AABB, inner: &AABB| {
+        (0..3).all(|axis| {
+            outer.min_axis(axis) <= inner.min_axis(axis)
+                && outer.max_axis(axis) >= inner.max_axis(axis)
+        })
+    };
+    [BHVBuildQuality::Fast, BHVBuildQuality::SurfaceAreaHeuristic]
+        .iter()
+        .for_each(|quality| {
+            let tree = BHVTree::build(&list, *quality);
+            let nodes = tree.nodes();
+            //Every input is a leaf of a binary tree
+            assert_eq!(nodes.len(), 2 * list.len() - 1);
+            let mut leaves = nodes
+                .iter()
+                .filter(|n| n.is_leaf())
+                .map(|n| n.aabb_index())
+                .collect::<Vec<_>>();
+            leaves.sort();
+            assert_eq!(leaves, (0..list.len() as i32).collect::<Vec<_>>());
+            nodes.iter().for_each(|n| {
+                if n.is_leaf() {
+                    assert!(contains(&n.aabb, &list[n.aabb_index() as usize]));
+                } else {
+                    assert!(contains(&n.aabb, &nodes[n.left() as usize].aabb));
+                    assert!(contains(&n.aabb, &nodes[n.right() as usize].aabb));
+                }
+            });
+            list.iter()
+                .for_each(|aabb| assert!(contains(&nodes[0].aabb, aabb)));
+        });
+}
+
+#[allow(dead_code)]
+fn test_sah_cost() {
+    let list = clustered_aabbs();
+    let fast = BHVTree::new(&list);
+    let sah = BHVTree::new_sah(&list);
+    assert_eq!(fast.nodes().len(), sah.nodes().len());
+    assert!(sah.sah_cost() <= fast.sah_cost());
+
+    let single = BHVTree::new_sah(&list[..1]);
+    assert_eq!(single.nodes().len(), 1);
+    assert_eq!(single.nodes()[0].aabb_index(), 0);
+}
+
+#[allow(dead_code)]
+fn test_build_in_tasks() {
+    let list = clustered_aabbs();
+    let is_same_tree = |a: &BHVTree, b: &BHVTree| {
+        a.nodes().len() == b.nodes().len()
+            && a.nodes().iter().zip(b.nodes()).all(|(a, b)| {
+                a.min() == b.min()
+                    && a.max() == b.max()
+                    && a.aabb_index() == b.aabb_index()
+                    && a.left() == b.left()
+                    && a.right() == b.right()
+                    && a.parent() == b.parent()
+            })
+    };
+    [BHVBuildQuality::Fast, BHVBuildQuality::SurfaceAreaHeuristic]
+        .iter()
+        .for_each(|&quality| {
+            let serial = BHVTree::build(&list, quality);
+            [1, 5, 16, 64, list.len()].iter().for_each(|&task_size| {
+                let mut groups_count = 0;
+                let parallel = BHVTree::build_in_tasks(&list, quality, task_size, |groups| {
+                    groups_count = groups.len();
+                    std::thread::scope(|s| {
+                        let handles = groups
+                            .iter()
+                            .map(|group| s.spawn(move || BHVTree::build(group, quality)))
+                            .collect::<Vec<_>>();
+                        handles.into_iter().map(|h| h.join().unwrap()).collect()
+                    })
+                });
+                assert_eq!(groups_count > 0, task_size > 1 && task_size < list.len());
+                assert!(
+                    is_same_tree(&serial, &parallel),
+                    "Mismatch with tasks of {task_size} aabbs"
+                );
+            });
+        });
+}
+
+#[test]
+fn test() {
+    test_builds_enclose_aabbs();
+    test_sah_cost();
+    test_build_in_tasks();
+}
@@ -3,6 +3,7 @@ use inox_math::{VecBase, Vector3};
 use crate::{AABB, AXIS_COUNT};
 
 const SPLIT_COUNT: usize = 2;
+const SAH_BIN_COUNT: usize = 12;
 
 #[derive(Default)]
 pub struct Partition {
@@ -53,6 +54,73 @@ impl Partition {
             (left_group, right_group)
         }
     }
+    //Binned surface area heuristic: the centers are sorted in bins along each axis
+    //and the split between bins with the lowest cost is chosen,
+    //where the cost of each side is its count of nodes by its surface area
+    pub fn compute_binned_sah(container: &AABB, list: &[AABB]) -> (Vec<AABB>, Vec<AABB>) {
+        let mut min = Vector3::default_value(f32::INFINITY);
+        let mut max = Vector3::default_value(f32::NEG_INFINITY);
+        list.iter().for_each(|aabb| {
+            min = min.min(aabb.center());
+            max = max.max(aabb.center());
+        });
+        let extent = max - min;
+        let bin_index = |aabb: &AABB, axis: usize| {
+            let t = (aabb.center()[axis] - min[axis]) / extent[axis];
+            ((t * SAH_BIN_COUNT as f32) as usize).min(SAH_BIN_COUNT - 1)
+        };
+
+        let mut best_split: Option<(f32, usize, usize)> = None;
+        (0..AXIS_COUNT).for_each(|axis| {
+            if extent[axis] <= f32::EPSILON {
+                return;
+            }
+            let mut bins = [(AABB::empty(), 0_usize); SAH_BIN_COUNT];
+            list.iter().for_each(|aabb| {
+                let bin = &mut bins[bin_index(aabb, axis)];
+                bin.0.expand_to_include(aabb);
+                bin.1 += 1;
+            });
+            //Area and count of all the bins after each split
+            let mut right_sides = [(0_f32, 0_usize); SAH_BIN_COUNT - 1];
+            let mut right_aabb = AABB::empty();
+            let mut right_count = 0;
+            for split in (0..SAH_BIN_COUNT - 1).rev() {
+                let (bin_aabb, bin_count) = &bins[split + 1];
+                if *bin_count > 0 {
+                    right_aabb.expand_to_include(bin_aabb);
+                    right_count += bin_count;
+                }
+                right_sides[split] = (right_aabb.surface_area(), right_count);
+            }
+            let mut left_aabb = AABB::empty();
+            let mut left_count = 0;
+            for split in 0..SAH_BIN_COUNT - 1 {
+                let (bin_aabb, bin_count) = &bins[split];
+                if *bin_count > 0 {
+                    left_aabb.expand_to_include(bin_aabb);
+                    left_count += bin_count;
+                }
+                let (right_area, right_count) = right_sides[split];
+                if left_count == 0 || right_count == 0 {
+                    continue;
+                }
+                let cost =
+                    left_count as f32 * left_aabb.surface_area() + right_count as f32 * right_area;
+                if !matches!(best_split, Some((best_cost, _, _)) if best_cost <= cost) {
+                    best_split = Some((cost, axis, split));
+                }
+            }
+        });
+        match best_split {
+            Some((_, axis, split)) => list
+                .iter()
+                .copied()
+                .partition(|aabb| bin_index(aabb, axis) <= split),
+            //All the centers are in the same point
+            None => Self::compute(container, list),
+        }
+    }
     fn validate_partition(partition: &[[Partition; SPLIT_COUNT]; AXIS_COUNT]) -> bool {
         let mut is_valid = true;
         (0..SPLIT_COUNT).for_each(|split| {
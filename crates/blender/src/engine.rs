@@ -82,7 +82,9 @@ impl INOXEngine {
             );
             let mut plugin_path = app_dir.clone();
             plugin_path = plugin_path.join(plugin);
-            app.add_dynamic_plugin(plugin_path.as_path());
+            if let Err(error) = app.add_dynamic_plugin(plugin_path.as_path()) {
+                eprintln!("[Blender] Unable to load plugin: {error}");
+            }
         });
 
         Self {
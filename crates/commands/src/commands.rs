@@ -6,6 +6,42 @@ struct Command {
     values: Vec<String>,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommandParserError {
+    MissingCommand(String),
+    MissingValue(String),
+    InvalidValue {
+        command: String,
+        value: String,
+        type_name: String,
+    },
+}
+
+impl std::fmt::Display for CommandParserError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CommandParserError::MissingCommand(command) => {
+                write!(f, "Command {command} not found")
+            }
+            CommandParserError::MissingValue(command) => {
+                write!(f, "Command {command} has no value")
+            }
+            CommandParserError::InvalidValue {
+                command,
+                value,
+                type_name,
+            } => {
+                write!(
+                    f,
+                    "Unable to parse value {value} of command {command} as {type_name}"
+                )
+            }
+        }
+    }
+}
+
+//Commands are written as -name, --name or --name=value followed by their values.
+//A command repeated more times accumulates the values of every occurrence
 #[derive(Default)]
 pub struct CommandParser {
     commands: Vec<Command>,
@@ -46,17 +82,55 @@ impl CommandParser {
         values
     }
 
+    //First value of the command, failing when it's missing or of another type
+    pub fn get_value_of<T>(&self, command_name: &str) -> Result<T, CommandParserError>
+    where
+        T: FromStr,
+    {
+        if !self.has(command_name) {
+            return Err(CommandParserError::MissingCommand(command_name.to_string()));
+        }
+        let value = self
+            .commands
+            .iter()
+            .filter(|c| c.name.as_str() == command_name)
+            .flat_map(|c| c.values.iter())
+            .next()
+            .ok_or_else(|| CommandParserError::MissingValue(command_name.to_string()))?;
+        value
+            .parse::<T>()
+            .map_err(|_| CommandParserError::InvalidValue {
+                command: command_name.to_string(),
+                value: value.clone(),
+                type_name: std::any::type_name::<T>().to_string(),
+            })
+    }
+
+    //Negative numbers start with a dash too but they're values
+    fn command_name(arg: &str) -> Option<&str> {
+        let name = arg.strip_prefix("--").or_else(|| arg.strip_prefix('-'))?;
+        if name.starts_with(|c: char| c.is_alphabetic() || c == '_') {
+            Some(name)
+        } else {
+            None
+        }
+    }
+
     fn from_strings(args: Vec<String>) -> Self {
-        let mut commands = Vec::new();
+        let mut commands: Vec<Command> = Vec::new();
         for a in args {
-            if a.starts_with('-') {
-                let mut name = a.clone();
-                name.remove(0);
+            if let Some(name) = Self::command_name(a.as_str()) {
+                //A single argument can hold the command with all its values
                 let mut values: Vec<String> =
                     name.split_whitespace().map(|s| s.to_string()).collect();
-                let name = values.remove(0);
+                let command = values.remove(0);
+                let (name, value) = command.split_once('=').unwrap_or((command.as_str(), ""));
+                values.insert(0, value.to_string());
                 values.retain(|s| !s.is_empty());
-                commands.push(Command { name, values });
+                commands.push(Command {
+                    name: name.to_string(),
+                    values,
+                });
             } else if let Some(command) = commands.last_mut() {
                 command.values.push(a);
             } else {
@@ -66,3 +140,62 @@ impl CommandParser {
         Self { commands }
     }
 }
+
+#[allow(dead_code)]
+fn test_command_parser() {
+    let command_parser = CommandParser::from_string("--load_file=models/a.scene --vsync");
+    assert_eq!(
+        command_parser.get_value_of::<String>("load_file"),
+        Ok("models/a.scene".to_string())
+    );
+    assert!(command_parser.has("vsync"));
+    assert_eq!(
+        command_parser.get_value_of::<bool>("vsync"),
+        Err(CommandParserError::MissingValue("vsync".to_string()))
+    );
+    assert_eq!(
+        command_parser.get_value_of::<u32>("width"),
+        Err(CommandParserError::MissingCommand("width".to_string()))
+    );
+
+    //Every syntax can be mixed and repeated, values are kept in order
+    let command_parser =
+        CommandParser::from_string("-load_file a.scene --load_file b.scene --load_file=c.scene");
+    assert_eq!(
+        command_parser.get_values_of::<String>("load_file"),
+        vec!["a.scene", "b.scene", "c.scene"]
+    );
+    assert_eq!(
+        command_parser.get_value_of::<String>("load_file"),
+        Ok("a.scene".to_string())
+    );
+
+    let command_parser = CommandParser::from_string("--width=wide -mouse_move -0.5 0.25");
+    assert_eq!(
+        command_parser.get_value_of::<u32>("width"),
+        Err(CommandParserError::InvalidValue {
+            command: "width".to_string(),
+            value: "wide".to_string(),
+            type_name: "u32".to_string(),
+        })
+    );
+    assert_eq!(
+        command_parser.get_values_of::<f32>("mouse_move"),
+        vec![-0.5, 0.25]
+    );
+
+    //Arguments of the command line can hold the values of the command too
+    let command_parser = CommandParser::from_strings(vec![
+        "-load_file models/a.scene".to_string(),
+        "-".to_string(),
+    ]);
+    assert_eq!(
+        command_parser.get_values_of::<String>("load_file"),
+        vec!["models/a.scene", "-"]
+    );
+}
+
+#[test]
+fn test() {
+    test_command_parser();
+}
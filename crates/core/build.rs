@@ -28,6 +28,23 @@ fn move_all_files_with_extension(src_path: PathBuf, target_path: PathBuf, extens
 }
 
 fn main() {
+    //Plugins built by a different compiler than the host can't be loaded
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let rustc_version = std::process::Command::new(rustc)
+        .arg("--version")
+        .output()
+        .ok()
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .unwrap_or_default();
+    println!(
+        "cargo:rustc-env=INOX_RUSTC_VERSION={}",
+        rustc_version.trim()
+    );
+    println!(
+        "cargo:rustc-env=INOX_TARGET={}",
+        env::var("TARGET").unwrap_or_default()
+    );
+
     let out_dir = env::var("OUT_DIR").unwrap();
     let out_dir = Path::new(&out_dir)
         .join("..\\..\\..\\")
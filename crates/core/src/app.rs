@@ -12,14 +12,16 @@ use inox_resources::DeserializeFunction;
 use inox_uid::generate_uid_from_string;
 
 use crate::{
-    ContextRc, JobHandlerTrait, JobPriority, PluginHolder, PluginId, PluginManager, System,
-    SystemEvent,
+    ContextRc, JobHandlerTrait, JobPriority, PluginError, PluginHolder, PluginId, PluginManager,
+    SchedulerMode, System, SystemEvent,
 };
 
 pub struct App {
     context: ContextRc,
     is_profiling: bool,
     is_enabled: Arc<AtomicBool>,
+    is_focused: bool,
+    is_step_requested: bool,
     listener: Listener,
     plugin_manager: PluginManager,
 }
@@ -33,12 +35,22 @@ impl Default for App {
 
         listener
             .register::<KeyEvent>()
-            .register::<WindowEvent>()
+            .register_filtered(|e: &WindowEvent| {
+                matches!(
+                    e,
+                    WindowEvent::Show
+                        | WindowEvent::Hide
+                        | WindowEvent::FocusGained
+                        | WindowEvent::FocusLost
+                )
+            })
             .register::<SystemEvent>();
 
         Self {
             is_enabled: Arc::new(AtomicBool::new(true)),
+            is_focused: true,
             is_profiling: false,
+            is_step_requested: false,
             plugin_manager: PluginManager::default(),
             context,
             listener,
@@ -68,7 +80,26 @@ impl Drop for App {
 }
 
 impl App {
+    //Zero workers runs every job on the main thread, useful to debug them
+    pub fn with_worker_threads(num_worker_threads: usize) -> Self {
+        let app = Self::default();
+        app.context
+            .job_handler()
+            .write()
+            .unwrap()
+            .set_num_workers(num_worker_threads);
+        app
+    }
+
     pub fn start(&mut self) -> &mut Self {
+        //Jobs are run between the phases instead
+        if self.context.scheduler_mut().mode() == SchedulerMode::SingleThreaded {
+            self.context
+                .job_handler()
+                .write()
+                .unwrap()
+                .set_num_workers(0);
+        }
         self.context.global_timer_mut().update();
         self.context.job_handler().start(&self.is_enabled);
         self.context.scheduler_mut().start();
@@ -80,6 +111,9 @@ impl App {
 
         let mut is_profiling = self.is_profiling;
         let mut is_enabled = self.is_enabled.load(Ordering::SeqCst);
+        let mut is_paused = self.context.is_paused();
+        let mut is_step_requested = self.is_step_requested;
+        let mut is_focused = self.is_focused;
 
         self.listener
             .process_messages(|e: &KeyEvent| {
@@ -92,6 +126,11 @@ impl App {
                         inox_profiler::stop_profiler!();
                         inox_profiler::write_profile_file!();
                     }
+                } else if e.code == Key::F10 && e.state == InputState::JustPressed {
+                    is_paused = !is_paused;
+                } else if e.code == Key::F11 && e.state == InputState::JustPressed {
+                    is_paused = true;
+                    is_step_requested = true;
                 }
             })
             .process_messages(|e: &WindowEvent| match e {
@@ -101,8 +140,20 @@ impl App {
                 WindowEvent::Hide => {
                     is_enabled = false;
                 }
+                WindowEvent::FocusGained => {
+                    is_enabled = true;
+                    is_focused = true;
+                }
+                WindowEvent::FocusLost => {
+                    is_enabled = false;
+                    is_focused = false;
+                }
                 _ => {}
             });
+        if is_focused != self.is_focused {
+            self.is_focused = is_focused;
+            self.execute_on_systems(move |s| s.on_focus_changed(is_focused));
+        }
         self.context
             .shared_data()
             .handle_events(|load_fn: Box<dyn DeserializeFunction>| {
@@ -120,10 +171,16 @@ impl App {
                 );
             });
 
+        self.context
+            .shared_data()
+            .update_config_watchers(self.context.message_hub());
+
         //flush messages between frames
         self.context.message_hub().flush();
 
         self.is_profiling = is_profiling;
+        self.is_step_requested = is_step_requested;
+        self.set_paused(is_paused);
 
         self.context
             .job_handler()
@@ -133,17 +190,65 @@ impl App {
     pub fn run(&mut self) -> bool {
         inox_profiler::scoped_profile!("app::run_frame");
 
-        self.context.global_timer_mut().update();
-
+        let (fixed_steps, is_paused) = self.begin_frame();
         let can_continue = self.context.scheduler_mut().run_once(
             self.is_enabled.load(Ordering::SeqCst),
+            fixed_steps,
+            is_paused,
             self.context.job_handler(),
         );
+        self.end_frame(can_continue)
+    }
+
+    //Same as run but the caller's executor gets the control back between the phases.
+    //Only single threaded apps use it: nothing else can take the scheduler in the meantime
+    #[allow(clippy::await_holding_lock)]
+    pub async fn run_async(&mut self) -> bool {
+        let (fixed_steps, is_paused) = self.begin_frame();
+        let can_continue = self
+            .context
+            .scheduler_mut()
+            .run_once_async(
+                self.is_enabled.load(Ordering::SeqCst),
+                fixed_steps,
+                is_paused,
+                self.context.job_handler(),
+            )
+            .await;
+        self.end_frame(can_continue)
+    }
+
+    fn begin_frame(&mut self) -> (u32, bool) {
+        let fixed_steps = self.context.global_timer_mut().update().fixed_steps();
+
+        //While paused only a requested step advances the simulation, by exactly one fixed step
+        let is_stepping = self.context.is_paused() && self.is_step_requested;
+        self.is_step_requested = false;
+        self.context.set_stepping(is_stepping);
+        let is_paused = self.context.is_paused() && !is_stepping;
+        let fixed_steps = if is_stepping {
+            1
+        } else if is_paused {
+            0
+        } else {
+            fixed_steps
+        };
+        (fixed_steps, is_paused)
+    }
+
+    fn end_frame(&mut self, can_continue: bool) -> bool {
+        //Reloaded systems had a whole frame to take the resources restored by the handoff
+        if self.context.shared_data().is_handoff_active() {
+            self.context.shared_data().end_handoff();
+        }
 
         self.update_events();
 
         if !self.is_enabled.load(Ordering::SeqCst) {
             let plugins_to_remove = self.plugin_manager.update();
+            if !plugins_to_remove.is_empty() {
+                self.context.shared_data().begin_handoff();
+            }
             let plugins_to_reload = self.update_dynamic_plugins(plugins_to_remove);
             if !plugins_to_reload.is_empty() {
                 self.context
@@ -163,15 +268,40 @@ impl App {
         can_continue
     }
 
+    pub fn set_paused(&mut self, is_paused: bool) -> &mut Self {
+        if self.context.is_paused() != is_paused {
+            inox_log::debug_log!(
+                "Simulation {}",
+                if is_paused { "paused" } else { "resumed" }
+            );
+            self.context.set_paused(is_paused);
+        }
+        if !is_paused {
+            self.is_step_requested = false;
+        }
+        self
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.context.is_paused()
+    }
+
+    //Pauses the simulation if needed and advances it by a single frame
+    pub fn step_once(&mut self) -> &mut Self {
+        self.set_paused(true);
+        self.is_step_requested = true;
+        self
+    }
+
     pub fn add_static_plugin(&mut self, plugin_holder: PluginHolder) -> PluginId {
         PluginManager::load_config_plugin_holder(&plugin_holder, self.context());
         PluginManager::prepare_plugin_holder(&plugin_holder, self.context());
         self.plugin_manager.add_static_plugin(plugin_holder)
     }
 
-    pub fn add_dynamic_plugin(&mut self, lib_path: &Path) -> PluginId {
-        let plugin_data = PluginManager::create_plugin_data(lib_path, self.context());
-        self.plugin_manager.add_dynamic_plugin(plugin_data)
+    pub fn add_dynamic_plugin(&mut self, lib_path: &Path) -> Result<PluginId, PluginError> {
+        let plugin_data = PluginManager::create_plugin_data(lib_path, self.context())?;
+        Ok(self.plugin_manager.add_dynamic_plugin(plugin_data))
     }
 
     pub fn load_config_on_plugin_systems(&mut self, plugin_name: &str) {
@@ -192,13 +322,23 @@ impl App {
         }
     }
 
-    fn update_dynamic_plugins(&mut self, plugins_to_remove: Vec<PluginId>) -> Vec<PathBuf> {
+    //Plugins save their state before being unloaded, to get it back once reloaded
+    fn update_dynamic_plugins(
+        &mut self,
+        plugins_to_remove: Vec<PluginId>,
+    ) -> Vec<(PathBuf, Option<String>)> {
         let mut plugins_to_reload = Vec::new();
         for id in plugins_to_remove.iter() {
             if let Some(plugin_data) = self.plugin_manager.remove_dynamic_plugin(id) {
                 let lib_path = plugin_data.original_path.clone();
+                let state = plugin_data
+                    .plugin_holder
+                    .as_ref()
+                    .and_then(|plugin_holder| {
+                        PluginManager::save_plugin_holder_state(plugin_holder, self.context())
+                    });
                 PluginManager::clear_plugin_data(plugin_data, self.context());
-                plugins_to_reload.push(lib_path);
+                plugins_to_reload.push((lib_path, state));
             }
         }
         plugins_to_reload
@@ -210,10 +350,21 @@ impl App {
         }
     }
 
-    fn reload_dynamic_plugins(&mut self, plugins_to_reload: Vec<PathBuf>) {
-        for lib_path in plugins_to_reload.into_iter() {
+    fn reload_dynamic_plugins(&mut self, plugins_to_reload: Vec<(PathBuf, Option<String>)>) {
+        for (lib_path, state) in plugins_to_reload.into_iter() {
             let reloaded_plugin_data =
-                PluginManager::create_plugin_data(lib_path.as_path(), self.context());
+                match PluginManager::create_plugin_data(lib_path.as_path(), self.context()) {
+                    Ok(plugin_data) => plugin_data,
+                    Err(error) => {
+                        eprintln!("Unable to reload plugin: {error}");
+                        continue;
+                    }
+                };
+            if let (Some(plugin_holder), Some(state)) =
+                (reloaded_plugin_data.plugin_holder.as_ref(), state)
+            {
+                PluginManager::restore_plugin_holder_state(plugin_holder, self.context(), &state);
+            }
             self.plugin_manager.add_dynamic_plugin(reloaded_plugin_data);
         }
     }
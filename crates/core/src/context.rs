@@ -1,18 +1,43 @@
-use std::sync::{Arc, RwLockReadGuard, RwLockWriteGuard};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, RwLockReadGuard, RwLockWriteGuard,
+};
 
 use inox_messenger::MessageHubRc;
 use inox_resources::SharedDataRc;
 use inox_time::{Timer, TimerRw};
 
-use crate::{JobHandlerRw, Phases, Scheduler, SchedulerRw, System, SystemEvent, SystemId};
+use crate::{
+    JobHandlerRw, Phases, Scheduler, SchedulerRw, System, SystemEvent, SystemId, SystemTimings,
+    SystemTimingsRw,
+};
 
-#[derive(Default)]
 pub struct Context {
     shared_data: SharedDataRc,
     message_hub: MessageHubRc,
     global_timer: TimerRw,
     job_handler: JobHandlerRw,
     scheduler: SchedulerRw,
+    system_timings: SystemTimingsRw,
+    is_paused: AtomicBool,
+    is_stepping: AtomicBool,
+}
+
+impl Default for Context {
+    fn default() -> Self {
+        let scheduler = Scheduler::default();
+        let system_timings = scheduler.system_timings().clone();
+        Self {
+            shared_data: SharedDataRc::default(),
+            message_hub: MessageHubRc::default(),
+            global_timer: TimerRw::default(),
+            job_handler: JobHandlerRw::default(),
+            scheduler: SchedulerRw::new(scheduler),
+            system_timings,
+            is_paused: AtomicBool::default(),
+            is_stepping: AtomicBool::default(),
+        }
+    }
 }
 
 impl Context {
@@ -31,16 +56,39 @@ impl Context {
     pub(crate) fn scheduler_mut(&self) -> RwLockWriteGuard<Scheduler> {
         self.scheduler.write().unwrap()
     }
+    //Time spent by each system in its last runs, readable while the scheduler is running
+    pub fn system_timings(&self) -> RwLockReadGuard<SystemTimings> {
+        self.system_timings.read().unwrap()
+    }
     pub fn job_handler(&self) -> &JobHandlerRw {
         &self.job_handler
     }
+    //While paused the simulation phases are skipped but input and rendering keep running
+    pub fn is_paused(&self) -> bool {
+        self.is_paused.load(Ordering::SeqCst)
+    }
+    pub(crate) fn set_paused(&self, is_paused: bool) {
+        self.is_paused.store(is_paused, Ordering::SeqCst);
+    }
+    //True during the single frame that is advanced while paused
+    pub fn is_stepping(&self) -> bool {
+        self.is_stepping.load(Ordering::SeqCst)
+    }
+    pub(crate) fn set_stepping(&self, is_stepping: bool) {
+        self.is_stepping.store(is_stepping, Ordering::SeqCst);
+    }
     pub fn add_system<S>(&self, phase: Phases, system: S, dependencies: Option<&[SystemId]>)
     where
         S: System,
     {
         let id = S::system_id();
-        self.scheduler_mut()
-            .add_system(phase, system, dependencies, &self.job_handler);
+        let result =
+            self.scheduler_mut()
+                .add_system(phase, system, dependencies, &self.job_handler);
+        if let Err(e) = result {
+            inox_log::debug_log!("Unable to add system - {e}");
+            return;
+        }
         self.message_hub.send_event(SystemEvent::Added(id, phase));
     }
     pub fn remove_system(&self, phase: Phases, system_id: &SystemId) {
@@ -1,9 +1,11 @@
 use std::any::Any;
 
-use inox_uid::{generate_uid_from_string, Uid};
+use inox_uid::{generate_static_uid64_from_string, generate_uid_from_string, Uid, Uid64};
 
 use crate::ContextRc;
 
+pub const PLUGIN_ABI_FUNCTION_NAME: &str = "plugin_abi";
+pub type PfnPluginAbi = ::std::option::Option<unsafe extern "C" fn() -> PluginAbi>;
 pub const CREATE_PLUGIN_FUNCTION_NAME: &str = "create_plugin";
 pub type PfnCreatePlugin = ::std::option::Option<unsafe fn(context: &ContextRc) -> PluginHolder>;
 pub const DESTROY_PLUGIN_FUNCTION_NAME: &str = "destroy_plugin";
@@ -14,14 +16,52 @@ pub const PREPARE_PLUGIN_FUNCTION_NAME: &str = "prepare_plugin";
 pub type PfnPreparePlugin = ::std::option::Option<unsafe fn(context: &ContextRc)>;
 pub const UNPREPARE_PLUGIN_FUNCTION_NAME: &str = "unprepare_plugin";
 pub type PfnUnpreparePlugin = ::std::option::Option<unsafe fn(context: &ContextRc)>;
+pub const SAVE_STATE_PLUGIN_FUNCTION_NAME: &str = "save_state_plugin";
+pub type PfnSaveStatePlugin =
+    ::std::option::Option<unsafe fn(context: &ContextRc) -> Option<String>>;
+pub const RESTORE_STATE_PLUGIN_FUNCTION_NAME: &str = "restore_state_plugin";
+pub type PfnRestoreStatePlugin = ::std::option::Option<unsafe fn(context: &ContextRc, state: &str)>;
 
 pub type PluginId = Uid;
 
+//To be increased when the functions exported by the plugins or the PluginHolder change
+pub const PLUGIN_ABI_VERSION: u32 = 1;
+
+//Host and plugins exchange rust types with no stable layout:
+//a plugin can be loaded only if built by the same compiler with the same inox_core
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PluginAbi {
+    pub version: u32,
+    pub holder_size: u32,
+    pub fingerprint: Uid64,
+}
+
+impl PluginAbi {
+    pub const fn current() -> Self {
+        Self {
+            version: PLUGIN_ABI_VERSION,
+            holder_size: std::mem::size_of::<PluginHolder>() as _,
+            fingerprint: generate_static_uid64_from_string(concat!(
+                env!("INOX_RUSTC_VERSION"),
+                env!("INOX_TARGET"),
+                env!("CARGO_PKG_VERSION"),
+            )),
+        }
+    }
+}
+
 pub trait Plugin: Any + Send + Sync {
     fn create(context: &ContextRc) -> Self;
     fn prepare(&mut self, context: &ContextRc);
     fn unprepare(&mut self, context: &ContextRc);
     fn load_config(&mut self, context: &ContextRc);
+    //Called before the plugin library is unloaded to be reloaded:
+    //the returned state is given back to restore_state once the new library is prepared
+    fn save_state(&mut self, _context: &ContextRc) -> Option<String> {
+        None
+    }
+    fn restore_state(&mut self, _context: &ContextRc, _state: &str) {}
     fn id(&self) -> PluginId {
         generate_uid_from_string(self.name())
     }
@@ -36,6 +76,8 @@ pub struct PluginHolder {
     pub prepare_fn: PfnPreparePlugin,
     pub load_config_fn: PfnLoadConfigPlugin,
     pub unprepare_fn: PfnUnpreparePlugin,
+    pub save_state_fn: PfnSaveStatePlugin,
+    pub restore_state_fn: PfnRestoreStatePlugin,
 }
 
 impl PluginHolder {
@@ -47,6 +89,8 @@ impl PluginHolder {
             prepare_fn: None,
             load_config_fn: None,
             unprepare_fn: None,
+            save_state_fn: None,
+            restore_state_fn: None,
         }
     }
     pub fn id(&self) -> PluginId {
@@ -57,6 +101,11 @@ impl PluginHolder {
 #[macro_export]
 macro_rules! define_dynamic_plugin {
     ($Type:ident) => {
+        #[no_mangle]
+        pub extern "C" fn plugin_abi() -> $crate::PluginAbi {
+            $crate::PluginAbi::current()
+        }
+
         #[no_mangle]
         pub extern "C" fn create_plugin(context: &$crate::ContextRc) -> $crate::PluginHolder {
             static_plugin::create_plugin(context)
@@ -81,6 +130,16 @@ macro_rules! define_dynamic_plugin {
         pub extern "C" fn unprepare_plugin(context: &$crate::ContextRc) {
             static_plugin::unprepare_plugin(context)
         }
+
+        #[no_mangle]
+        pub extern "C" fn save_state_plugin(context: &$crate::ContextRc) -> Option<String> {
+            static_plugin::save_state_plugin(context)
+        }
+
+        #[no_mangle]
+        pub extern "C" fn restore_state_plugin(context: &$crate::ContextRc, state: &str) {
+            static_plugin::restore_state_plugin(context, state)
+        }
     };
 }
 
@@ -140,6 +199,28 @@ macro_rules! define_static_plugin {
                     PLUGIN.as_mut().unwrap().unprepare(context);
                 }
             }
+
+            pub fn save_state_plugin(context: &$crate::ContextRc) -> Option<String> {
+                unsafe {
+                    debug_assert!(
+                        PLUGIN.is_some(),
+                        "Trying to save state of {:?} plugin never created",
+                        PLUGIN.as_ref().unwrap().name()
+                    );
+                    PLUGIN.as_mut().unwrap().save_state(context)
+                }
+            }
+
+            pub fn restore_state_plugin(context: &$crate::ContextRc, state: &str) {
+                unsafe {
+                    debug_assert!(
+                        PLUGIN.is_some(),
+                        "Trying to restore state of {:?} plugin never created",
+                        PLUGIN.as_ref().unwrap().name()
+                    );
+                    PLUGIN.as_mut().unwrap().restore_state(context, state);
+                }
+            }
         }
     };
 }
@@ -7,15 +7,54 @@ use inox_filesystem::{delete_file, library, Library};
 use inox_platform::{FileEvent, FileWatcher};
 
 use crate::{
-    ContextRc, PfnCreatePlugin, PfnDestroyPlugin, PfnLoadConfigPlugin, PfnPreparePlugin,
-    PfnUnpreparePlugin, PluginHolder, PluginId, CREATE_PLUGIN_FUNCTION_NAME,
-    DESTROY_PLUGIN_FUNCTION_NAME, LOAD_CONFIG_PLUGIN_FUNCTION_NAME, PREPARE_PLUGIN_FUNCTION_NAME,
+    ContextRc, PfnCreatePlugin, PfnDestroyPlugin, PfnLoadConfigPlugin, PfnPluginAbi,
+    PfnPreparePlugin, PfnRestoreStatePlugin, PfnSaveStatePlugin, PfnUnpreparePlugin, PluginAbi,
+    PluginHolder, PluginId, CREATE_PLUGIN_FUNCTION_NAME, DESTROY_PLUGIN_FUNCTION_NAME,
+    LOAD_CONFIG_PLUGIN_FUNCTION_NAME, PLUGIN_ABI_FUNCTION_NAME, PREPARE_PLUGIN_FUNCTION_NAME,
+    RESTORE_STATE_PLUGIN_FUNCTION_NAME, SAVE_STATE_PLUGIN_FUNCTION_NAME,
     UNPREPARE_PLUGIN_FUNCTION_NAME,
 };
 
 pub static IN_USE_PREFIX: &str = "in_use";
 static mut UNIQUE_LIB_INDEX: u32 = 0;
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PluginError {
+    MissingFunction {
+        path: PathBuf,
+        function: String,
+    },
+    AbiMismatch {
+        path: PathBuf,
+        host: PluginAbi,
+        plugin: PluginAbi,
+    },
+}
+
+impl std::fmt::Display for PluginError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PluginError::MissingFunction { path, function } => {
+                write!(f, "Unable to find function {function} in plugin {path:?}")
+            }
+            PluginError::AbiMismatch { path, host, plugin } if host.version != plugin.version => {
+                write!(
+                    f,
+                    "Plugin {path:?} uses plugin ABI version {} while the host uses version {}",
+                    plugin.version, host.version
+                )
+            }
+            PluginError::AbiMismatch { path, .. } => {
+                write!(
+                    f,
+                    "Plugin {path:?} was built with a different compiler or inox_core \
+                    than the host and needs to be rebuilt"
+                )
+            }
+        }
+    }
+}
+
 pub struct PluginData {
     lib: Box<Library>,
     pub plugin_holder: Option<PluginHolder>,
@@ -106,31 +145,79 @@ impl PluginManager {
         }
     }
 
+    //Calling any other function of a plugin built with a different layout of the shared types
+    //would be undefined behaviour, so the check uses only plain C types
+    fn check_plugin_abi(path: &Path, plugin_abi_fn: PfnPluginAbi) -> Result<(), PluginError> {
+        let Some(plugin_abi_fn) = plugin_abi_fn else {
+            return Err(PluginError::MissingFunction {
+                path: path.to_path_buf(),
+                function: PLUGIN_ABI_FUNCTION_NAME.to_string(),
+            });
+        };
+        let plugin = unsafe { plugin_abi_fn() };
+        let host = PluginAbi::current();
+        if plugin != host {
+            return Err(PluginError::AbiMismatch {
+                path: path.to_path_buf(),
+                host,
+                plugin,
+            });
+        }
+        Ok(())
+    }
+
     fn load_dynamic_plugin(
         fullpath: PathBuf,
+        original_path: &Path,
+        context: &ContextRc,
+    ) -> Result<(library::Library, PluginHolder), PluginError> {
+        let mut lib = library::Library::new(fullpath.to_str().unwrap());
+        let create_fn = Self::check_plugin_abi(
+            original_path,
+            lib.get::<PfnPluginAbi>(PLUGIN_ABI_FUNCTION_NAME).flatten(),
+        )
+        .and_then(|_| {
+            lib.get::<PfnCreatePlugin>(CREATE_PLUGIN_FUNCTION_NAME)
+                .flatten()
+                .ok_or_else(|| PluginError::MissingFunction {
+                    path: original_path.to_path_buf(),
+                    function: CREATE_PLUGIN_FUNCTION_NAME.to_string(),
+                })
+        });
+        let create_fn = match create_fn {
+            Ok(create_fn) => create_fn,
+            Err(error) => {
+                lib.close();
+                return Err(error);
+            }
+        };
+        let mut plugin_holder = unsafe { create_fn(context) };
+        plugin_holder.destroy_fn = lib
+            .get::<PfnDestroyPlugin>(DESTROY_PLUGIN_FUNCTION_NAME)
+            .unwrap();
+        plugin_holder.load_config_fn = lib
+            .get::<PfnLoadConfigPlugin>(LOAD_CONFIG_PLUGIN_FUNCTION_NAME)
+            .unwrap();
+        plugin_holder.prepare_fn = lib
+            .get::<PfnPreparePlugin>(PREPARE_PLUGIN_FUNCTION_NAME)
+            .unwrap();
+        plugin_holder.unprepare_fn = lib
+            .get::<PfnUnpreparePlugin>(UNPREPARE_PLUGIN_FUNCTION_NAME)
+            .unwrap();
+        plugin_holder.save_state_fn = lib
+            .get::<PfnSaveStatePlugin>(SAVE_STATE_PLUGIN_FUNCTION_NAME)
+            .flatten();
+        plugin_holder.restore_state_fn = lib
+            .get::<PfnRestoreStatePlugin>(RESTORE_STATE_PLUGIN_FUNCTION_NAME)
+            .flatten();
+        Ok((lib, plugin_holder))
+    }
+
+    //Plugins built with a different compiler, inox_core or plugin ABI version are refused
+    pub fn create_plugin_data(
+        lib_path: &Path,
         context: &ContextRc,
-    ) -> (library::Library, Option<PluginHolder>) {
-        let lib = library::Library::new(fullpath.to_str().unwrap());
-        if let Some(create_fn) = lib.get::<PfnCreatePlugin>(CREATE_PLUGIN_FUNCTION_NAME) {
-            let mut plugin_holder = unsafe { create_fn.unwrap()(context) };
-            plugin_holder.destroy_fn = lib
-                .get::<PfnDestroyPlugin>(DESTROY_PLUGIN_FUNCTION_NAME)
-                .unwrap();
-            plugin_holder.load_config_fn = lib
-                .get::<PfnLoadConfigPlugin>(LOAD_CONFIG_PLUGIN_FUNCTION_NAME)
-                .unwrap();
-            plugin_holder.prepare_fn = lib
-                .get::<PfnPreparePlugin>(PREPARE_PLUGIN_FUNCTION_NAME)
-                .unwrap();
-            plugin_holder.unprepare_fn = lib
-                .get::<PfnUnpreparePlugin>(UNPREPARE_PLUGIN_FUNCTION_NAME)
-                .unwrap();
-            return (lib, Some(plugin_holder));
-        }
-        (lib, None)
-    }
-
-    pub fn create_plugin_data(lib_path: &Path, context: &ContextRc) -> PluginData {
+    ) -> Result<PluginData, PluginError> {
         let (path, filename) = library::compute_folder_and_filename(lib_path);
         let fullpath = path.join(filename);
         if !fullpath.exists() && fullpath.is_file() {
@@ -151,8 +238,18 @@ impl PluginManager {
             in_use_fullpath = fullpath.clone();
         }
 
-        let (lib, plugin_holder) =
-            PluginManager::load_dynamic_plugin(in_use_fullpath.clone(), context);
+        let loaded = Self::register_plugin_types(context, || {
+            PluginManager::load_dynamic_plugin(in_use_fullpath.clone(), fullpath.as_path(), context)
+        });
+        let (lib, plugin_holder) = match loaded {
+            Ok(loaded) => loaded,
+            Err(error) => {
+                if in_use_fullpath != fullpath {
+                    delete_file(in_use_fullpath);
+                }
+                return Err(error);
+            }
+        };
         /*
         debug_log!(
             "Loaded plugin {}",
@@ -160,16 +257,31 @@ impl PluginManager {
         );
         */
 
-        Self::load_config_plugin_holder(plugin_holder.as_ref().unwrap(), context);
-        Self::prepare_plugin_holder(plugin_holder.as_ref().unwrap(), context);
+        Self::prepare_dynamic_plugin_holder(&plugin_holder, context);
 
-        PluginData {
+        Ok(PluginData {
             lib: Box::new(lib),
-            plugin_holder,
+            plugin_holder: Some(plugin_holder),
             filewatcher: FileWatcher::new(fullpath.clone()),
             original_path: fullpath,
             in_use_path: in_use_fullpath,
-        }
+        })
+    }
+
+    //Resource types registered by a dynamic plugin have their code in its library:
+    //their storages must not be kept by a handoff beyond the library unload
+    fn register_plugin_types<R>(context: &ContextRc, f: impl FnOnce() -> R) -> R {
+        context.shared_data().set_registering_plugin_types(true);
+        let result = f();
+        context.shared_data().set_registering_plugin_types(false);
+        result
+    }
+
+    fn prepare_dynamic_plugin_holder(plugin_holder: &PluginHolder, context: &ContextRc) {
+        Self::register_plugin_types(context, || {
+            Self::load_config_plugin_holder(plugin_holder, context);
+            Self::prepare_plugin_holder(plugin_holder, context);
+        });
     }
 
     pub fn load_config_plugin_holder(plugin_holder: &PluginHolder, context: &ContextRc) {
@@ -184,6 +296,26 @@ impl PluginManager {
         }
     }
 
+    pub fn save_plugin_holder_state(
+        plugin_holder: &PluginHolder,
+        context: &ContextRc,
+    ) -> Option<String> {
+        plugin_holder
+            .save_state_fn
+            .as_ref()
+            .and_then(|save_state_fn| unsafe { save_state_fn(context) })
+    }
+
+    pub fn restore_plugin_holder_state(
+        plugin_holder: &PluginHolder,
+        context: &ContextRc,
+        state: &str,
+    ) {
+        if let Some(restore_state_fn) = plugin_holder.restore_state_fn.as_ref() {
+            unsafe { restore_state_fn(context, state) };
+        }
+    }
+
     pub fn release_plugin_holder(plugin_holder: PluginHolder, context: &ContextRc) {
         if let Some(unprepare_fn) = plugin_holder.unprepare_fn.as_ref() {
             unsafe { unprepare_fn(context) };
@@ -247,3 +379,135 @@ impl PluginManager {
         None
     }
 }
+
+#[allow(dead_code)]
+fn test_plugin_abi() {
+    use crate::PLUGIN_ABI_VERSION;
+    use inox_uid::generate_static_uid64_from_string;
+
+    unsafe extern "C" fn matching_plugin_abi() -> PluginAbi {
+        PluginAbi::current()
+    }
+    unsafe extern "C" fn newer_plugin_abi() -> PluginAbi {
+        PluginAbi {
+            version: PLUGIN_ABI_VERSION + 1,
+            ..PluginAbi::current()
+        }
+    }
+    unsafe extern "C" fn other_compiler_plugin_abi() -> PluginAbi {
+        PluginAbi {
+            fingerprint: generate_static_uid64_from_string("rustc 0.0.0"),
+            ..PluginAbi::current()
+        }
+    }
+
+    let path = Path::new("stub_plugin");
+    assert!(PluginManager::check_plugin_abi(path, Some(matching_plugin_abi)).is_ok());
+
+    let error = PluginManager::check_plugin_abi(path, Some(newer_plugin_abi)).unwrap_err();
+    assert_eq!(
+        error,
+        PluginError::AbiMismatch {
+            path: path.to_path_buf(),
+            host: PluginAbi::current(),
+            plugin: unsafe { newer_plugin_abi() },
+        }
+    );
+    assert!(error
+        .to_string()
+        .contains(&format!("version {}", PLUGIN_ABI_VERSION + 1)));
+
+    let error = PluginManager::check_plugin_abi(path, Some(other_compiler_plugin_abi)).unwrap_err();
+    assert!(matches!(error, PluginError::AbiMismatch { .. }));
+    assert!(error.to_string().contains("rebuilt"));
+
+    //Libraries built before the check existed don't export the function
+    assert_eq!(
+        PluginManager::check_plugin_abi(path, None),
+        Err(PluginError::MissingFunction {
+            path: path.to_path_buf(),
+            function: PLUGIN_ABI_FUNCTION_NAME.to_string(),
+        })
+    );
+}
+
+#[allow(dead_code)]
+fn test_plugin_reload() {
+    use inox_resources::ResourceTrait;
+    use inox_uid::{generate_random_resource_uid, generate_random_uid};
+
+    #[derive(Clone)]
+    struct HostResource;
+    impl ResourceTrait for HostResource {
+        fn is_initialized(&self) -> bool {
+            true
+        }
+        fn invalidate(&mut self) -> &mut Self {
+            self
+        }
+    }
+    #[derive(Clone)]
+    struct PluginResource;
+    impl ResourceTrait for PluginResource {
+        fn is_initialized(&self) -> bool {
+            true
+        }
+        fn invalidate(&mut self) -> &mut Self {
+            self
+        }
+    }
+    unsafe fn prepare_plugin(context: &ContextRc) {
+        context
+            .shared_data()
+            .register_type::<PluginResource>(context.message_hub());
+    }
+    unsafe fn unprepare_plugin(context: &ContextRc) {
+        context
+            .shared_data()
+            .unregister_type::<PluginResource>(context.message_hub());
+    }
+    let create_plugin_holder = || {
+        let mut plugin_holder = PluginHolder::new(generate_random_uid(), "test_plugin");
+        plugin_holder.prepare_fn = Some(prepare_plugin);
+        plugin_holder.unprepare_fn = Some(unprepare_plugin);
+        plugin_holder
+    };
+
+    let context = ContextRc::default();
+    let shared_data = context.shared_data();
+    let message_hub = context.message_hub();
+    shared_data.register_type::<HostResource>(message_hub);
+    let mut plugin_holder = create_plugin_holder();
+    PluginManager::prepare_dynamic_plugin_holder(&plugin_holder, &context);
+
+    let host_id = generate_random_resource_uid();
+    let _host = shared_data.add_resource(message_hub, host_id, HostResource);
+    let plugin_id = generate_random_resource_uid();
+    let mut plugin = shared_data.add_resource(message_hub, plugin_id, PluginResource);
+
+    for _ in 0..2 {
+        //The plugin library is unloaded and loaded again
+        shared_data.begin_handoff();
+        PluginManager::release_plugin_holder(plugin_holder, &context);
+        plugin_holder = create_plugin_holder();
+        PluginManager::prepare_dynamic_plugin_holder(&plugin_holder, &context);
+        shared_data.end_handoff();
+        shared_data.flush_resources(message_hub);
+
+        //Only the storages of the types registered by the host are kept
+        assert!(shared_data.has::<HostResource>(&host_id));
+        assert!(!shared_data.has::<PluginResource>(&plugin_id));
+        assert!(plugin.get().is_initialized());
+
+        //Plugin types registered again are still dropped by the next reload
+        plugin = shared_data.add_resource(message_hub, plugin_id, PluginResource);
+    }
+    PluginManager::release_plugin_holder(plugin_holder, &context);
+    shared_data.unregister_type::<HostResource>(message_hub);
+}
+
+#[test]
+fn test() {
+    test_plugin_abi();
+    test_plugin_reload();
+}
@@ -12,9 +12,16 @@ use inox_uid::Uid;
 use crate::Worker;
 
 #[cfg(target_arch = "wasm32")]
-const NUM_WORKER_THREADS: usize = 0;
-#[cfg(all(not(target_arch = "wasm32")))]
-const NUM_WORKER_THREADS: usize = 5;
+pub fn default_num_workers() -> usize {
+    0
+}
+//A worker for each core not used by the main thread
+#[cfg(not(target_arch = "wasm32"))]
+pub fn default_num_workers() -> usize {
+    std::thread::available_parallelism()
+        .map_or(1, |n| n.get().saturating_sub(1))
+        .max(1)
+}
 
 pub type JobId = Uid;
 pub const INDEPENDENT_JOB_ID: JobId = inox_uid::generate_static_uid_from_string("IndependentJob");
@@ -109,11 +116,22 @@ impl Default for PrioChannel {
     }
 }
 
-#[derive(Default)]
 pub struct JobHandler {
     channel: [PrioChannel; JobPriority::Count as usize],
     pending_jobs: HashMap<JobId, Arc<AtomicUsize>>,
     workers: HashMap<String, Worker>,
+    num_workers: usize,
+}
+
+impl Default for JobHandler {
+    fn default() -> Self {
+        Self {
+            channel: Default::default(),
+            pending_jobs: HashMap::new(),
+            workers: HashMap::new(),
+            num_workers: default_num_workers(),
+        }
+    }
 }
 
 unsafe impl Sync for JobHandler {}
@@ -134,14 +152,14 @@ impl JobHandler {
         inox_profiler::scoped_profile!("JobReceiver::get_job_with_priority[{:?}]", job_priority);
         self.channel[job_priority as usize].receiver.get_job()
     }
-    #[inline]
-    fn execute_all_jobs(&self) {
-        inox_profiler::scoped_profile!("JobHandler::execute_all_jobs");
-        for i in 0..JobPriority::Count as usize {
-            while let Some(job) = self.get_job_with_priority(JobPriority::from(i)) {
-                job.execute();
-            }
-        }
+    pub fn num_workers(&self) -> usize {
+        self.num_workers
+    }
+    //Applied when the workers are started.
+    //Without workers the jobs are executed by the main thread while running the scheduler
+    pub fn set_num_workers(&mut self, num_workers: usize) -> &mut Self {
+        self.num_workers = num_workers;
+        self
     }
 
     fn add_worker(&mut self, name: &str, can_continue: &Arc<AtomicBool>) -> &mut Worker {
@@ -162,10 +180,8 @@ impl JobHandler {
 
     #[inline]
     fn setup_worker_threads(&mut self, can_continue: &Arc<AtomicBool>) {
-        if NUM_WORKER_THREADS > 0 {
-            for i in 1..NUM_WORKER_THREADS + 1 {
-                self.add_worker(format!("Worker{i}").as_str(), can_continue);
-            }
+        for i in 1..self.num_workers + 1 {
+            self.add_worker(format!("Worker{i}").as_str(), can_continue);
         }
     }
 
@@ -209,6 +225,8 @@ pub trait JobHandlerTrait {
         F: FnOnce() + Send + Sync + 'static;
     fn get_job_with_priority(&self, job_priority: JobPriority) -> Option<Job>;
     fn has_pending_jobs(&self, job_category: &JobId) -> bool;
+    fn num_workers(&self) -> usize;
+    fn execute_all_jobs(&self);
     fn update_workers(&self, can_continue: &Arc<AtomicBool>, is_enabled: bool);
     fn start(&self, can_continue: &Arc<AtomicBool>);
     fn stop(&self);
@@ -234,6 +252,19 @@ impl JobHandlerTrait for JobHandlerRw {
         self.read().unwrap().get_job_with_priority(job_priority)
     }
     #[inline]
+    fn num_workers(&self) -> usize {
+        self.read().unwrap().num_workers()
+    }
+    //Jobs are executed without holding the lock, so that they can add other jobs
+    fn execute_all_jobs(&self) {
+        inox_profiler::scoped_profile!("JobHandler::execute_all_jobs");
+        while let Some(job) = (0..JobPriority::Count as usize)
+            .find_map(|i| self.get_job_with_priority(JobPriority::from(i)))
+        {
+            job.execute();
+        }
+    }
+    #[inline]
     fn start(&self, can_continue: &Arc<AtomicBool>) {
         self.write().unwrap().setup_worker_threads(can_continue);
     }
@@ -243,9 +274,9 @@ impl JobHandlerTrait for JobHandlerRw {
     }
 
     fn update_workers(&self, can_continue: &Arc<AtomicBool>, is_enabled: bool) {
-        if NUM_WORKER_THREADS == 0 {
+        if self.num_workers() == 0 {
             //no workers - need to handle events ourself
-            self.read().unwrap().execute_all_jobs();
+            self.execute_all_jobs();
         }
         if can_continue.load(Ordering::SeqCst) && !is_enabled {
             can_continue.store(is_enabled, Ordering::SeqCst);
@@ -272,3 +303,58 @@ impl JobReceiverTrait for JobReceiverRw {
         None
     }
 }
+
+#[allow(dead_code)]
+fn test_no_workers() {
+    use crate::Scheduler;
+
+    let job_handler = JobHandlerRw::default();
+    job_handler.write().unwrap().set_num_workers(0);
+    job_handler.start(&Arc::new(AtomicBool::new(true)));
+
+    let executed = Arc::new(AtomicUsize::new(0));
+    let add_counting_job = |job_handler: &JobHandlerRw, priority: JobPriority| {
+        let executed = executed.clone();
+        job_handler.add_job(&INDEPENDENT_JOB_ID, "CountingJob", priority, move || {
+            executed.fetch_add(1, Ordering::SeqCst);
+        });
+    };
+    add_counting_job(&job_handler, JobPriority::Low);
+    add_counting_job(&job_handler, JobPriority::High);
+    //Jobs added by other jobs are executed in the same run
+    {
+        let job_handler_rc = job_handler.clone();
+        let executed = executed.clone();
+        job_handler.add_job(
+            &INDEPENDENT_JOB_ID,
+            "AddingJob",
+            JobPriority::Medium,
+            move || {
+                job_handler_rc.add_job(
+                    &INDEPENDENT_JOB_ID,
+                    "AddedJob",
+                    JobPriority::High,
+                    move || {
+                        executed.fetch_add(1, Ordering::SeqCst);
+                    },
+                );
+            },
+        );
+    }
+    assert!(job_handler.has_pending_jobs(&INDEPENDENT_JOB_ID));
+    assert_eq!(executed.load(Ordering::SeqCst), 0);
+
+    let mut scheduler = Scheduler::default();
+    scheduler.start();
+    scheduler.run_once(true, 0, false, &job_handler);
+    assert_eq!(executed.load(Ordering::SeqCst), 3);
+    assert!(!job_handler.has_pending_jobs(&INDEPENDENT_JOB_ID));
+
+    #[cfg(not(target_arch = "wasm32"))]
+    assert!(default_num_workers() >= 1);
+}
+
+#[test]
+fn test() {
+    test_no_workers();
+}
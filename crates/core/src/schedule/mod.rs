@@ -4,6 +4,7 @@ pub use self::phases::*;
 pub use self::scheduler::*;
 pub use self::system::*;
 pub use self::system_runner::*;
+pub use self::system_timings::*;
 pub use self::worker::*;
 
 pub mod job;
@@ -12,4 +13,5 @@ pub mod phases;
 pub mod scheduler;
 pub mod system;
 pub mod system_runner;
+pub mod system_timings;
 pub mod worker;
@@ -1,14 +1,17 @@
 use downcast_rs::{impl_downcast, Downcast};
 
 use std::{
-    collections::{hash_map::Entry, HashMap},
+    collections::{hash_map::Entry, HashMap, HashSet},
     sync::{
         atomic::{AtomicBool, Ordering},
         Arc,
     },
 };
 
-use crate::{JobHandlerRw, JobHandlerTrait, JobId, System, SystemId, SystemRunner};
+use crate::{
+    JobHandlerRw, JobHandlerTrait, JobId, SchedulerError, System, SystemId, SystemRunner,
+    SystemTimingsRw,
+};
 
 pub trait Phase: Downcast + Send + Sync {
     fn get_name(&self) -> &str;
@@ -23,21 +26,29 @@ impl_downcast!(Phase);
 pub struct PhaseWithSystems {
     name: String,
     systems_runners: HashMap<SystemId, SystemRunner>,
+    systems_dependencies: HashMap<SystemId, Vec<SystemId>>,
     systems_running: Vec<SystemId>,
     systems_to_add: Vec<SystemId>,
     systems_to_remove: Vec<SystemId>,
     can_continue: Arc<AtomicBool>,
+    is_paused: bool,
+    is_parallel: bool,
+    system_timings: SystemTimingsRw,
 }
 
 impl PhaseWithSystems {
-    pub fn new(name: &str) -> Self {
+    pub fn new(name: &str, system_timings: SystemTimingsRw) -> Self {
         Self {
             name: String::from(name),
             systems_runners: HashMap::new(),
+            systems_dependencies: HashMap::new(),
             systems_running: Vec::new(),
             systems_to_add: Vec::new(),
             systems_to_remove: Vec::new(),
             can_continue: Arc::new(AtomicBool::new(true)),
+            is_paused: false,
+            is_parallel: true,
+            system_timings,
         }
     }
     pub fn execute_on_systems<F>(&mut self, f: &mut F)
@@ -64,23 +75,113 @@ impl PhaseWithSystems {
         system: S,
         dependencies: Option<&[SystemId]>,
         job_handler: &JobHandlerRw,
-    ) -> &mut Self
+    ) -> Result<&mut Self, SchedulerError>
     where
         S: System,
     {
         let id = S::system_id();
+        if let Some(dependencies) = dependencies {
+            //Cycles are checked before adding, so that the faulty system is rejected
+            //while the ones already in the phase keep running in a valid order
+            let mut systems_dependencies = self.systems_dependencies.clone();
+            systems_dependencies.insert(id, dependencies.to_vec());
+            let systems = self
+                .systems_running
+                .iter()
+                .chain(self.systems_to_add.iter())
+                .chain(std::iter::once(&id))
+                .copied()
+                .collect::<Vec<_>>();
+            if let Err(cycle) = sort_systems_by_dependencies(&systems, &systems_dependencies) {
+                return Err(SchedulerError::DependencyCycle {
+                    phase: self.name.clone(),
+                    systems: cycle
+                        .iter()
+                        .map(|s| {
+                            if *s == id {
+                                system.name().to_string()
+                            } else {
+                                self.system_name_or_id(s)
+                            }
+                        })
+                        .collect(),
+                });
+            }
+        }
         self.add_system(system, job_handler);
         if let Some(dependencies) = dependencies {
-            let mut dependencies_states = HashMap::new();
-            dependencies.iter().for_each(|system_id| {
-                if let Some(system_data) = self.systems_runners.get_mut(system_id) {
-                    dependencies_states.insert(*system_id, system_data.state());
-                }
-            });
-            self.systems_runners
-                .get_mut(&id)
-                .unwrap()
-                .add_dependencies(dependencies_states);
+            self.systems_dependencies.insert(id, dependencies.to_vec());
+        }
+        Ok(self)
+    }
+
+    pub fn set_paused(&mut self, is_paused: bool) -> &mut Self {
+        self.is_paused = is_paused;
+        self
+    }
+    //Systems that are not run in parallel are executed one after the other on the calling thread
+    pub fn set_parallel(&mut self, is_parallel: bool) -> &mut Self {
+        self.is_parallel = is_parallel;
+        self
+    }
+
+    pub fn has_system(&self, system_id: &SystemId) -> bool {
+        self.systems_runners.contains_key(system_id)
+    }
+
+    pub fn system_name(&self, system_id: &SystemId) -> Option<&str> {
+        self.systems_runners.get(system_id).map(|s| s.name())
+    }
+
+    fn system_name_or_id(&self, system_id: &SystemId) -> String {
+        self.system_name(system_id)
+            .map(String::from)
+            .unwrap_or_else(|| system_id.to_string())
+    }
+
+    pub fn dependencies(&self, system_id: &SystemId) -> &[SystemId] {
+        self.systems_dependencies
+            .get(system_id)
+            .map(|d| d.as_slice())
+            .unwrap_or_default()
+    }
+
+    pub fn dependent_systems(&self, system_id: &SystemId) -> Vec<SystemId> {
+        self.systems_dependencies
+            .iter()
+            .filter(|(_, dependencies)| dependencies.contains(system_id))
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
+    fn sort_by_dependencies(&self, systems: &[SystemId]) -> Vec<SystemId> {
+        match sort_systems_by_dependencies(systems, &self.systems_dependencies) {
+            Ok(sorted) => sorted,
+            Err(cycle) => panic!(
+                "{}",
+                dependency_cycle_message(&self.name, &cycle, |id| self.system_name_or_id(id))
+            ),
+        }
+    }
+
+    //Systems are executed in dependencies order and each one waits for the states
+    //of the systems it depends on that are in this phase
+    fn update_execution_order(&mut self) -> &mut Self {
+        self.systems_running = self.sort_by_dependencies(&self.systems_running);
+        for id in self.systems_running.iter() {
+            let dependencies_states = self
+                .systems_dependencies
+                .get(id)
+                .map(|dependencies| {
+                    dependencies
+                        .iter()
+                        .filter_map(|d| self.systems_runners.get(d).map(|s| (*d, s.state())))
+                        .collect::<HashMap<_, _>>()
+                })
+                .unwrap_or_default();
+            if let Some(system_runner) = self.systems_runners.get_mut(id) {
+                system_runner.set_dependencies(dependencies_states);
+            }
         }
         self
     }
@@ -92,7 +193,11 @@ impl PhaseWithSystems {
         let id = S::system_id();
         if let Entry::Vacant(e) = self.systems_runners.entry(id) {
             self.systems_to_add.push(id);
-            e.insert(SystemRunner::new(system, job_handler.clone()));
+            e.insert(SystemRunner::new(
+                system,
+                job_handler.clone(),
+                self.system_timings.clone(),
+            ));
         } else {
             eprintln!("Trying to add twice a System with id {id} in this Phase");
         }
@@ -138,7 +243,9 @@ impl PhaseWithSystems {
                         should_wait = true;
                     } else if !system_runner.is_executed() {
                         should_wait = true;
-                        if execute_in_parallel {
+                        if self.is_paused && !system_runner.should_run_when_paused() {
+                            system_runner.skip();
+                        } else if execute_in_parallel {
                             system_runner.execute_as_job(self.can_continue.clone(), is_focused);
                         } else {
                             system_runner.execute(self.can_continue.clone(), is_focused);
@@ -160,14 +267,14 @@ impl PhaseWithSystems {
     }
 
     fn execute(&mut self, is_focused: bool, job_handler: &JobHandlerRw) -> bool {
-        #[cfg(target_arch = "wasm32")]
-        let execute_in_parallel = false;
-        #[cfg(all(not(target_arch = "wasm32")))]
-        let execute_in_parallel = self.systems_running.len() > 1;
+        let execute_in_parallel = self.is_parallel && self.systems_running.len() > 1;
         self.execute_systems(is_focused, execute_in_parallel, job_handler)
     }
 
     fn remove_pending_systems_from_execution(&mut self) -> &mut Self {
+        if self.systems_to_remove.is_empty() {
+            return self;
+        }
         for id in self.systems_to_remove.iter() {
             if let Some(index) = self.systems_running.iter().position(|s| s == id) {
                 self.systems_running.remove(index);
@@ -175,19 +282,24 @@ impl PhaseWithSystems {
             if let Some(mut system_runner) = self.systems_runners.remove(id) {
                 system_runner.uninit();
             }
+            self.systems_dependencies.remove(id);
+            self.system_timings.write().unwrap().remove(id);
         }
         self.systems_to_remove.clear();
-        self
+        self.update_execution_order()
     }
 
     fn add_pending_systems_into_execution(&mut self) -> &mut Self {
+        if self.systems_to_add.is_empty() {
+            return self;
+        }
         for id in self.systems_to_add.iter() {
             if let Some(system_runner) = self.systems_runners.get_mut(id) {
                 system_runner.init();
             }
         }
         self.systems_running.append(&mut self.systems_to_add);
-        self
+        self.update_execution_order()
     }
 }
 
@@ -230,3 +342,94 @@ impl Phase for PhaseWithSystems {
 
 unsafe impl Send for PhaseWithSystems {}
 unsafe impl Sync for PhaseWithSystems {}
+
+//Orders the systems so that each one comes after the systems it depends on,
+//keeping the insertion order otherwise. Dependencies on systems not in the list are ignored.
+//On failure returns the systems forming the cycle, starting and ending with the same one
+pub fn sort_systems_by_dependencies(
+    systems: &[SystemId],
+    dependencies: &HashMap<SystemId, Vec<SystemId>>,
+) -> Result<Vec<SystemId>, Vec<SystemId>> {
+    fn visit(
+        id: &SystemId,
+        systems: &[SystemId],
+        dependencies: &HashMap<SystemId, Vec<SystemId>>,
+        visited: &mut HashSet<SystemId>,
+        stack: &mut Vec<SystemId>,
+        sorted: &mut Vec<SystemId>,
+    ) -> Result<(), Vec<SystemId>> {
+        if visited.contains(id) {
+            return Ok(());
+        }
+        if let Some(index) = stack.iter().position(|s| s == id) {
+            let mut cycle = stack[index..].to_vec();
+            cycle.push(*id);
+            return Err(cycle);
+        }
+        stack.push(*id);
+        if let Some(dependencies_of_id) = dependencies.get(id) {
+            for d in dependencies_of_id.iter().filter(|d| systems.contains(d)) {
+                visit(d, systems, dependencies, visited, stack, sorted)?;
+            }
+        }
+        stack.pop();
+        visited.insert(*id);
+        sorted.push(*id);
+        Ok(())
+    }
+
+    let mut visited = HashSet::new();
+    let mut stack = Vec::new();
+    let mut sorted = Vec::new();
+    for id in systems.iter() {
+        visit(
+            id,
+            systems,
+            dependencies,
+            &mut visited,
+            &mut stack,
+            &mut sorted,
+        )?;
+    }
+    Ok(sorted)
+}
+
+pub fn dependency_cycle_message<F>(phase_name: &str, cycle: &[SystemId], system_name: F) -> String
+where
+    F: Fn(&SystemId) -> String,
+{
+    SchedulerError::DependencyCycle {
+        phase: phase_name.to_string(),
+        systems: cycle.iter().map(system_name).collect(),
+    }
+    .to_string()
+}
+
+#[allow(dead_code)]
+fn test_dependencies() {
+    let a = inox_uid::generate_uid_from_string("A");
+    let b = inox_uid::generate_uid_from_string("B");
+    let c = inox_uid::generate_uid_from_string("C");
+    let names = HashMap::from([(a, "A"), (b, "B"), (c, "C")]);
+
+    let mut dependencies = HashMap::new();
+    dependencies.insert(a, vec![b]);
+    dependencies.insert(c, vec![a]);
+    assert_eq!(
+        sort_systems_by_dependencies(&[c, a, b], &dependencies),
+        Ok(vec![b, a, c])
+    );
+
+    dependencies.insert(b, vec![a]);
+    let cycle = sort_systems_by_dependencies(&[a, b, c], &dependencies).unwrap_err();
+    assert_eq!(cycle, vec![a, b, a]);
+    assert_eq!(
+        dependency_cycle_message("Update", &cycle, |id| names[id].to_string()),
+        "Dependency cycle between systems in phase Update: A -> B -> A"
+    );
+}
+
+#[test]
+fn test() {
+    test_dependencies();
+}
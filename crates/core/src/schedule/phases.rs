@@ -3,6 +3,7 @@ pub enum Phases {
     StartFrame = 0,
     PlatformUpdate,
     PreUpdate,
+    FixedUpdate,
     Update,
     PostUpdate,
     PreRender,
@@ -12,11 +13,16 @@ pub enum Phases {
 }
 
 impl Phases {
+    //Phases that advance the simulation: while paused only systems that run when paused execute
+    pub fn is_simulation(&self) -> bool {
+        matches!(self, Phases::FixedUpdate | Phases::Update)
+    }
     pub fn iterator() -> impl Iterator<Item = Phases> {
         [
             Phases::StartFrame,
             Phases::PlatformUpdate,
             Phases::PreUpdate,
+            Phases::FixedUpdate,
             Phases::Update,
             Phases::PostUpdate,
             Phases::PreRender,
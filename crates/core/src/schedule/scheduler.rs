@@ -1,25 +1,147 @@
-use crate::{JobHandlerRw, JobHandlerTrait, Phase, PhaseWithSystems, Phases, System, SystemId};
+use crate::{
+    JobHandlerRw, JobHandlerTrait, Phase, PhaseWithSystems, Phases, System, SystemId,
+    SystemTimingsRw,
+};
 use std::{collections::HashMap, sync::RwLock};
+#[cfg(not(target_arch = "wasm32"))]
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
 
 pub type SchedulerRw = RwLock<Scheduler>;
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchedulerError {
+    //Systems names, starting and ending with the same one
+    DependencyCycle {
+        phase: String,
+        systems: Vec<String>,
+    },
+    //A system can't run after a system of a later phase
+    DependencyInLaterPhase {
+        system: String,
+        phase: String,
+        dependency: String,
+        dependency_phase: String,
+    },
+}
+
+impl std::fmt::Display for SchedulerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SchedulerError::DependencyCycle { phase, systems } => {
+                write!(
+                    f,
+                    "Dependency cycle between systems in phase {phase}: {}",
+                    systems.join(" -> ")
+                )
+            }
+            SchedulerError::DependencyInLaterPhase {
+                system,
+                phase,
+                dependency,
+                dependency_phase,
+            } => {
+                write!(
+                    f,
+                    "System {system} in phase {phase} can't run after {dependency} \
+                    that is in later phase {dependency_phase}"
+                )
+            }
+        }
+    }
+}
+
+//Single threaded runs the systems and the jobs of each phase on the calling thread,
+//before starting the next phase. It's the only one available where there are no threads
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchedulerMode {
+    MultiThreaded,
+    SingleThreaded,
+}
+
+impl Default for SchedulerMode {
+    #[cfg(target_arch = "wasm32")]
+    fn default() -> Self {
+        SchedulerMode::SingleThreaded
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    fn default() -> Self {
+        SchedulerMode::MultiThreaded
+    }
+}
+
+//Pending only the first time it's polled, to let the executor run something else
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Default)]
+struct YieldNow {
+    is_yielded: bool,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Future for YieldNow {
+    type Output = ();
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if self.is_yielded {
+            return Poll::Ready(());
+        }
+        self.is_yielded = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+async fn yield_now() {
+    YieldNow::default().await;
+}
+
+//The browser runs its tasks, events and rendering included, only once the current one ends:
+//a future woken right away would be polled again in the same task, so a timeout is awaited
+#[cfg(target_arch = "wasm32")]
+async fn yield_now() {
+    let promise = js_sys::Promise::new(&mut |resolve, _reject| {
+        let is_scheduled = web_sys::window().is_some_and(|window| {
+            window
+                .set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, 0)
+                .is_ok()
+        });
+        if !is_scheduled {
+            let _ = resolve.call0(&wasm_bindgen::JsValue::UNDEFINED);
+        }
+    });
+    let _ = wasm_bindgen_futures::JsFuture::from(promise).await;
+}
+
 pub struct Scheduler {
     is_running: bool,
     is_started: bool,
+    mode: SchedulerMode,
     phases: HashMap<Phases, PhaseWithSystems>,
+    system_timings: SystemTimingsRw,
 }
 
 impl Default for Scheduler {
     fn default() -> Self {
+        let system_timings = SystemTimingsRw::default();
         let mut phases = HashMap::new();
         for p in Phases::iterator() {
-            phases.insert(p, PhaseWithSystems::new(format!("{p:?}").as_str()));
+            phases.insert(
+                p,
+                PhaseWithSystems::new(format!("{p:?}").as_str(), system_timings.clone()),
+            );
         }
-        Self {
+        let mut scheduler = Self {
             is_running: true,
             is_started: false,
+            mode: SchedulerMode::MultiThreaded,
             phases,
-        }
+            system_timings,
+        };
+        scheduler.set_mode(SchedulerMode::default());
+        scheduler
     }
 }
 
@@ -45,6 +167,21 @@ impl Scheduler {
         self.is_running = false;
     }
 
+    pub fn mode(&self) -> SchedulerMode {
+        self.mode
+    }
+    //Shared with every phase, so that it can be read without locking the scheduler
+    pub fn system_timings(&self) -> &SystemTimingsRw {
+        &self.system_timings
+    }
+    pub fn set_mode(&mut self, mode: SchedulerMode) -> &mut Self {
+        self.mode = mode;
+        self.phases.values_mut().for_each(|phase| {
+            phase.set_parallel(mode == SchedulerMode::MultiThreaded);
+        });
+        self
+    }
+
     pub fn uninit(&mut self) {
         self.cancel();
         for p in Phases::iterator() {
@@ -54,47 +191,120 @@ impl Scheduler {
         }
     }
 
-    pub fn run_once(&mut self, is_focused: bool, job_handler: &JobHandlerRw) -> bool {
+    pub fn run_once(
+        &mut self,
+        is_focused: bool,
+        fixed_steps: u32,
+        is_paused: bool,
+        job_handler: &JobHandlerRw,
+    ) -> bool {
         if !self.is_started {
             return self.is_running;
         }
         inox_profiler::scoped_profile!("scheduler::run_once");
         let mut can_continue = self.is_running;
         for p in Phases::iterator() {
-            if let Some(phase) = self.phases.get_mut(&p) {
-                let ok = if is_focused || phase.should_run_when_not_focused() {
-                    inox_profiler::scoped_profile!("{}[{:?}]", "scheduler::run_phase", p);
-                    let ok = phase.run(is_focused, job_handler);
+            can_continue &=
+                self.run_phase_steps(p, is_focused, fixed_steps, is_paused, job_handler);
+        }
+        if job_handler.num_workers() == 0 {
+            job_handler.execute_all_jobs();
+        }
+        can_continue
+    }
+
+    //Gives the control back to the executor between phases,
+    //so that a single threaded app doesn't block the browser for a whole frame
+    pub async fn run_once_async(
+        &mut self,
+        is_focused: bool,
+        fixed_steps: u32,
+        is_paused: bool,
+        job_handler: &JobHandlerRw,
+    ) -> bool {
+        if !self.is_started {
+            return self.is_running;
+        }
+        let mut can_continue = self.is_running;
+        for p in Phases::iterator() {
+            can_continue &=
+                self.run_phase_steps(p, is_focused, fixed_steps, is_paused, job_handler);
+            yield_now().await;
+        }
+        if job_handler.num_workers() == 0 {
+            job_handler.execute_all_jobs();
+        }
+        can_continue
+    }
+
+    fn run_phase_steps(
+        &mut self,
+        p: Phases,
+        is_focused: bool,
+        fixed_steps: u32,
+        is_paused: bool,
+        job_handler: &JobHandlerRw,
+    ) -> bool {
+        let mode = self.mode;
+        let Some(phase) = self.phases.get_mut(&p) else {
+            return true;
+        };
+        phase.set_paused(is_paused && p.is_simulation());
+        let steps = if p == Phases::FixedUpdate {
+            fixed_steps
+        } else {
+            1
+        };
+        let mut can_continue = true;
+        for _ in 0..steps {
+            can_continue &= Self::run_phase(p, phase, is_focused, job_handler);
+        }
+        //Jobs added during a phase are done before the next one starts
+        if mode == SchedulerMode::SingleThreaded {
+            job_handler.execute_all_jobs();
+        }
+        can_continue
+    }
+
+    fn run_phase(
+        p: Phases,
+        phase: &mut PhaseWithSystems,
+        is_focused: bool,
+        job_handler: &JobHandlerRw,
+    ) -> bool {
+        if !is_focused && !phase.should_run_when_not_focused() {
+            return true;
+        }
+        inox_profiler::scoped_profile!("{}[{:?}]", "scheduler::run_phase", p);
+        let ok = phase.run(is_focused, job_handler);
+        {
+            inox_profiler::scoped_profile!("{}[{:?}]", "scheduler::wait_jobs", p);
+            let jobs_id_to_wait = phase.get_jobs_id_to_wait();
+            let mut should_wait = true;
+            while should_wait {
+                should_wait = false;
+                jobs_id_to_wait.iter().for_each(|job_id| {
+                    should_wait |= job_handler.has_pending_jobs(job_id);
+                });
+                if should_wait {
+                    if let Some(job) = job_handler.get_job_with_priority(crate::JobPriority::High) {
+                        job.execute();
+                    } else if let Some(job) =
+                        job_handler.get_job_with_priority(crate::JobPriority::Medium)
                     {
-                        inox_profiler::scoped_profile!("{}[{:?}]", "scheduler::wait_jobs", p);
-                        let jobs_id_to_wait = phase.get_jobs_id_to_wait();
-                        let mut should_wait = true;
-                        while should_wait {
-                            should_wait = false;
-                            jobs_id_to_wait.iter().for_each(|job_id| {
-                                should_wait |= job_handler.has_pending_jobs(job_id);
-                            });
-                            if should_wait {
-                                if let Some(job) =
-                                    job_handler.get_job_with_priority(crate::JobPriority::High)
-                                {
-                                    job.execute();
-                                } else if let Some(job) =
-                                    job_handler.get_job_with_priority(crate::JobPriority::Medium)
-                                {
-                                    job.execute();
-                                }
-                            }
+                        job.execute();
+                    } else if job_handler.num_workers() == 0 {
+                        //Nobody else would execute them without workers
+                        if let Some(job) =
+                            job_handler.get_job_with_priority(crate::JobPriority::Low)
+                        {
+                            job.execute();
                         }
                     }
-                    ok
-                } else {
-                    true
-                };
-                can_continue &= ok;
+                }
             }
         }
-        can_continue
+        ok
     }
 
     pub fn add_system<S>(
@@ -103,17 +313,68 @@ impl Scheduler {
         system: S,
         dependencies: Option<&[SystemId]>,
         job_handler: &JobHandlerRw,
-    ) where
+    ) -> Result<(), SchedulerError>
+    where
         S: System,
     {
+        let phase = if system.uses_fixed_timestep() {
+            Phases::FixedUpdate
+        } else {
+            phase
+        };
+        self.check_dependencies_phases(
+            phase,
+            &S::system_id(),
+            system.name(),
+            dependencies.unwrap_or_default(),
+        )?;
         if let Some(phase) = self.phases.get_mut(&phase) {
-            phase.add_system_with_dependencies(system, dependencies, job_handler);
+            phase.add_system_with_dependencies(system, dependencies, job_handler)?;
         }
+        Ok(())
+    }
+    //Dependencies between systems of different phases are satisfied by the phases order:
+    //a system can depend on systems of its own phase or of the previous ones only
+    fn check_dependencies_phases(
+        &self,
+        phase: Phases,
+        system_id: &SystemId,
+        system_name: &str,
+        dependencies: &[SystemId],
+    ) -> Result<(), SchedulerError> {
+        for p in Phases::iterator().filter(|p| *p != phase) {
+            if let Some(other_phase) = self.phases.get(&p) {
+                if p as u8 > phase as u8 {
+                    if let Some(id) = dependencies.iter().find(|id| other_phase.has_system(id)) {
+                        return Err(SchedulerError::DependencyInLaterPhase {
+                            system: system_name.to_string(),
+                            phase: format!("{phase:?}"),
+                            dependency: other_phase.system_name(id).unwrap_or_default().to_string(),
+                            dependency_phase: format!("{p:?}"),
+                        });
+                    }
+                } else if let Some(id) = other_phase.dependent_systems(system_id).first() {
+                    return Err(SchedulerError::DependencyInLaterPhase {
+                        system: other_phase.system_name(id).unwrap_or_default().to_string(),
+                        phase: format!("{p:?}"),
+                        dependency: system_name.to_string(),
+                        dependency_phase: format!("{phase:?}"),
+                    });
+                }
+            }
+        }
+        Ok(())
     }
     pub fn remove_system(&mut self, phase: Phases, system_id: &SystemId) {
         if let Some(phase) = self.phases.get_mut(&phase) {
             phase.remove_system(system_id);
         }
+        //System could have been moved there when added
+        if phase != Phases::FixedUpdate {
+            if let Some(phase) = self.phases.get_mut(&Phases::FixedUpdate) {
+                phase.remove_system(system_id);
+            }
+        }
     }
     pub fn execute_on_systems<F>(&mut self, mut f: F)
     where
@@ -133,3 +394,251 @@ impl Scheduler {
         });
     }
 }
+
+#[allow(dead_code)]
+fn test_single_threaded() {
+    use crate::{implement_unique_system_uid, SystemUID, INDEPENDENT_JOB_ID};
+    use std::sync::{atomic::AtomicBool, Arc, Mutex};
+
+    type ExecutionOrder = Arc<Mutex<Vec<&'static str>>>;
+    macro_rules! ordered_system {
+        ($Type:ident) => {
+            struct $Type(ExecutionOrder);
+            implement_unique_system_uid!($Type);
+            impl System for $Type {
+                fn read_config(&mut self, _plugin_name: &str) {}
+                fn should_run_when_not_focused(&self) -> bool {
+                    false
+                }
+                fn init(&mut self) {}
+                fn run(&mut self) -> bool {
+                    self.0.lock().unwrap().push(stringify!($Type));
+                    true
+                }
+                fn uninit(&mut self) {}
+            }
+        };
+    }
+    ordered_system!(InputSystem);
+    ordered_system!(LogicSystem);
+    ordered_system!(PhysicsSystem);
+    ordered_system!(RenderSystem);
+
+    let job_handler = JobHandlerRw::default();
+    job_handler.write().unwrap().set_num_workers(0);
+    job_handler.start(&Arc::new(AtomicBool::new(true)));
+
+    let order = ExecutionOrder::default();
+    let mut scheduler = Scheduler::default();
+    scheduler.set_mode(SchedulerMode::SingleThreaded);
+    assert_eq!(scheduler.mode(), SchedulerMode::SingleThreaded);
+    //Added out of order: phases and dependencies decide the execution order
+    scheduler
+        .add_system(
+            Phases::Update,
+            PhysicsSystem(order.clone()),
+            Some(&[LogicSystem::system_id()]),
+            &job_handler,
+        )
+        .unwrap();
+    scheduler
+        .add_system(
+            Phases::Render,
+            RenderSystem(order.clone()),
+            None,
+            &job_handler,
+        )
+        .unwrap();
+    scheduler
+        .add_system(
+            Phases::Update,
+            LogicSystem(order.clone()),
+            None,
+            &job_handler,
+        )
+        .unwrap();
+    scheduler
+        .add_system(
+            Phases::PreUpdate,
+            InputSystem(order.clone()),
+            None,
+            &job_handler,
+        )
+        .unwrap();
+    {
+        let order = order.clone();
+        job_handler.add_job(
+            &INDEPENDENT_JOB_ID,
+            "OrderedJob",
+            crate::JobPriority::Low,
+            move || {
+                order.lock().unwrap().push("OrderedJob");
+            },
+        );
+    }
+
+    scheduler.start();
+    assert!(scheduler.run_once(true, 0, false, &job_handler));
+    //Pending jobs are done by the end of the first phase
+    assert_eq!(
+        *order.lock().unwrap(),
+        vec![
+            "OrderedJob",
+            "InputSystem",
+            "LogicSystem",
+            "PhysicsSystem",
+            "RenderSystem"
+        ]
+    );
+}
+
+#[allow(dead_code)]
+fn test_system_timings_of_sleeping_system() {
+    use crate::{implement_unique_system_uid, SystemUID};
+    use std::{
+        sync::{atomic::AtomicBool, Arc},
+        time::Duration,
+    };
+
+    const SLEEP_MS: u64 = 20;
+    struct SleepingSystem;
+    implement_unique_system_uid!(SleepingSystem);
+    impl System for SleepingSystem {
+        fn read_config(&mut self, _plugin_name: &str) {}
+        fn should_run_when_not_focused(&self) -> bool {
+            false
+        }
+        fn init(&mut self) {}
+        fn run(&mut self) -> bool {
+            std::thread::sleep(Duration::from_millis(SLEEP_MS));
+            true
+        }
+        fn uninit(&mut self) {}
+    }
+
+    let job_handler = JobHandlerRw::default();
+    job_handler.write().unwrap().set_num_workers(0);
+    job_handler.start(&Arc::new(AtomicBool::new(true)));
+
+    let mut scheduler = Scheduler::default();
+    scheduler.set_mode(SchedulerMode::SingleThreaded);
+    scheduler
+        .add_system(Phases::Update, SleepingSystem, None, &job_handler)
+        .unwrap();
+    scheduler.start();
+    assert!(scheduler.run_once(true, 0, false, &job_handler));
+
+    let timings = scheduler.system_timings().read().unwrap();
+    let timing = timings.get(&SleepingSystem::system_id()).unwrap();
+    assert_eq!(timing.name, SleepingSystem.name());
+    //Sleeping never takes less than requested, but a busy machine could make it way longer
+    assert!(timing.last_ms >= SLEEP_MS as f32);
+    assert!(timing.last_ms < (SLEEP_MS * 25) as f32);
+}
+
+#[allow(dead_code)]
+fn test_dependency_cycle() {
+    use crate::{implement_unique_system_uid, SystemUID};
+
+    macro_rules! empty_system {
+        ($Type:ident) => {
+            struct $Type;
+            implement_unique_system_uid!($Type);
+            impl System for $Type {
+                fn read_config(&mut self, _plugin_name: &str) {}
+                fn should_run_when_not_focused(&self) -> bool {
+                    false
+                }
+                fn init(&mut self) {}
+                fn run(&mut self) -> bool {
+                    true
+                }
+                fn uninit(&mut self) {}
+            }
+        };
+    }
+    empty_system!(SystemA);
+    empty_system!(SystemB);
+    empty_system!(SystemC);
+    empty_system!(SystemD);
+
+    let job_handler = JobHandlerRw::default();
+    let mut scheduler = Scheduler::default();
+    scheduler
+        .add_system(
+            Phases::Update,
+            SystemA,
+            Some(&[SystemB::system_id()]),
+            &job_handler,
+        )
+        .unwrap();
+    assert_eq!(
+        scheduler.add_system(
+            Phases::Update,
+            SystemB,
+            Some(&[SystemA::system_id()]),
+            &job_handler,
+        ),
+        Err(SchedulerError::DependencyCycle {
+            phase: "Update".to_string(),
+            systems: vec![
+                SystemA.name().to_string(),
+                SystemB.name().to_string(),
+                SystemA.name().to_string()
+            ],
+        })
+    );
+    //The rejected system is not added and the other one is left untouched
+    let phase = &scheduler.phases[&Phases::Update];
+    assert!(phase.has_system(&SystemA::system_id()));
+    assert!(!phase.has_system(&SystemB::system_id()));
+    assert_eq!(
+        phase.dependencies(&SystemA::system_id()),
+        &[SystemB::system_id()]
+    );
+
+    //Systems of later phases run after the ones of the earlier phases whatever their dependencies
+    scheduler
+        .add_system(Phases::Render, SystemC, None, &job_handler)
+        .unwrap();
+    assert_eq!(
+        scheduler.add_system(
+            Phases::Update,
+            SystemD,
+            Some(&[SystemC::system_id()]),
+            &job_handler,
+        ),
+        Err(SchedulerError::DependencyInLaterPhase {
+            system: SystemD.name().to_string(),
+            phase: "Update".to_string(),
+            dependency: SystemC.name().to_string(),
+            dependency_phase: "Render".to_string(),
+        })
+    );
+    //The same when the system depending on it was added first
+    assert_eq!(
+        scheduler.add_system(Phases::PostUpdate, SystemB, None, &job_handler),
+        Err(SchedulerError::DependencyInLaterPhase {
+            system: SystemA.name().to_string(),
+            phase: "Update".to_string(),
+            dependency: SystemB.name().to_string(),
+            dependency_phase: "PostUpdate".to_string(),
+        })
+    );
+    assert!(!scheduler.phases[&Phases::PostUpdate].has_system(&SystemB::system_id()));
+    scheduler
+        .add_system(
+            Phases::Render,
+            SystemD,
+            Some(&[SystemA::system_id(), SystemC::system_id()]),
+            &job_handler,
+        )
+        .unwrap();
+}
+
+#[test]
+fn test() {
+    test_single_threaded();
+    test_system_timings_of_sleeping_system();
+    test_dependency_cycle();
+}
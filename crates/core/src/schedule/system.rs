@@ -43,6 +43,18 @@ pub trait System: Downcast + Send + Sync + Any + SystemUID {
     }
     fn read_config(&mut self, plugin_name: &str);
     fn should_run_when_not_focused(&self) -> bool;
+    //Systems that return true are moved into Phases::FixedUpdate and run zero or more times
+    //per frame, reading the step duration from Timer::fixed_dt()
+    fn uses_fixed_timestep(&self) -> bool {
+        false
+    }
+    //Systems of the simulation phases are skipped while the app is paused unless they return true
+    fn should_run_when_paused(&self) -> bool {
+        false
+    }
+    //Called when the window gains or loses the focus, before the systems are skipped or resumed.
+    //Input that is held has to be released here: the release events are sent to other windows
+    fn on_focus_changed(&mut self, _is_focused: bool) {}
     fn init(&mut self);
     fn run(&mut self) -> bool;
     fn uninit(&mut self);
@@ -6,7 +6,14 @@ use std::{
     },
 };
 
-use crate::{JobHandlerRw, JobHandlerTrait, JobPriority, System, SystemId, SystemRw};
+#[cfg(target_arch = "wasm32")]
+use inox_time::Instant;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+
+use crate::{
+    JobHandlerRw, JobHandlerTrait, JobPriority, System, SystemId, SystemRw, SystemTimingsRw,
+};
 
 const STATE_READY: u8 = 0;
 const STATE_WAITING: u8 = 1;
@@ -20,10 +27,11 @@ pub struct SystemRunner {
     dependencies: HashMap<SystemId, Arc<AtomicU8>>,
     state: Arc<AtomicU8>,
     job_handler: JobHandlerRw,
+    timings: SystemTimingsRw,
 }
 
 impl SystemRunner {
-    pub fn new<S>(system: S, job_handler: JobHandlerRw) -> Self
+    pub fn new<S>(system: S, job_handler: JobHandlerRw, timings: SystemTimingsRw) -> Self
     where
         S: System + 'static,
     {
@@ -34,6 +42,7 @@ impl SystemRunner {
             dependencies: HashMap::new(),
             state: Arc::new(AtomicU8::new(STATE_READY)),
             job_handler,
+            timings,
         }
     }
     pub fn init(&mut self) {
@@ -42,6 +51,9 @@ impl SystemRunner {
     pub fn should_run_when_not_focused(&self) -> bool {
         self.system.read().unwrap().should_run_when_not_focused()
     }
+    pub fn should_run_when_paused(&self) -> bool {
+        self.system.read().unwrap().should_run_when_paused()
+    }
     pub fn uninit(&mut self) {
         self.system.write().unwrap().uninit();
     }
@@ -49,10 +61,12 @@ impl SystemRunner {
         self.state.clone()
     }
 
-    pub fn add_dependencies(&mut self, dependencies: HashMap<SystemId, Arc<AtomicU8>>) {
-        for (id, state) in dependencies {
-            self.dependencies.insert(id, state);
-        }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn set_dependencies(&mut self, dependencies: HashMap<SystemId, Arc<AtomicU8>>) {
+        self.dependencies = dependencies;
     }
 
     pub fn call_fn<F>(&mut self, f: &mut F)
@@ -98,12 +112,18 @@ impl SystemRunner {
         let should_run_when_not_focused = self.should_run_when_not_focused();
         if is_focused || should_run_when_not_focused {
             self.state.store(STATE_RUNNING, Ordering::SeqCst);
-            let result = can_continue.load(Ordering::SeqCst) && self.system.write().unwrap().run();
+            let result = can_continue.load(Ordering::SeqCst)
+                && Self::run_system(&self.system, &self.system_id, &self.name, &self.timings);
             can_continue.store(result, Ordering::SeqCst);
         }
         self.state.store(STATE_EXECUTED, Ordering::SeqCst);
     }
 
+    //Marks the system as executed without running it so that its dependents are not blocked
+    pub fn skip(&mut self) {
+        self.state.store(STATE_EXECUTED, Ordering::SeqCst);
+    }
+
     pub fn execute_as_job(&mut self, can_continue: Arc<AtomicBool>, is_focused: bool) {
         if self.is_executed() || self.is_running() {
             return;
@@ -113,13 +133,16 @@ impl SystemRunner {
             self.state.store(STATE_RUNNING, Ordering::SeqCst);
             let state = self.state.clone();
             let system = self.system.clone();
+            let system_id = self.system_id;
+            let name = self.name.clone();
+            let timings = self.timings.clone();
             self.job_handler.add_job(
                 &self.system_id,
                 format!("execute_system[{}]", self.name).as_str(),
                 JobPriority::High,
                 move || {
-                    let result =
-                        can_continue.load(Ordering::SeqCst) && system.write().unwrap().run();
+                    let result = can_continue.load(Ordering::SeqCst)
+                        && Self::run_system(&system, &system_id, &name, &timings);
                     can_continue.store(result, Ordering::SeqCst);
 
                     state.store(STATE_EXECUTED, Ordering::SeqCst);
@@ -130,6 +153,22 @@ impl SystemRunner {
         }
     }
 
+    fn run_system(
+        system: &SystemRw,
+        system_id: &SystemId,
+        name: &str,
+        timings: &SystemTimingsRw,
+    ) -> bool {
+        inox_profiler::scoped_profile!("system::run[{}]", name);
+        let start = Instant::now();
+        let result = system.write().unwrap().run();
+        timings
+            .write()
+            .unwrap()
+            .add(system_id, name, start.elapsed());
+        result
+    }
+
     pub fn is_ready(&self) -> bool {
         self.state.load(Ordering::SeqCst) == STATE_READY
     }
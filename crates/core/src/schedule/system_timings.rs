@@ -0,0 +1,96 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use crate::SystemId;
+
+pub type SystemTimingsRw = Arc<RwLock<SystemTimings>>;
+
+//Weight of the last run in the running average, to keep it readable in the UI
+const AVERAGE_WEIGHT: f32 = 0.1;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SystemTiming {
+    pub name: String,
+    pub last_ms: f32,
+    pub average_ms: f32,
+}
+
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct SystemTimings {
+    timings: HashMap<SystemId, SystemTiming>,
+}
+
+impl SystemTimings {
+    pub fn add(&mut self, id: &SystemId, name: &str, duration: Duration) -> &mut Self {
+        let ms = duration.as_micros() as f32 / 1000.;
+        self.timings
+            .entry(*id)
+            .and_modify(|timing| {
+                timing.last_ms = ms;
+                timing.average_ms += (ms - timing.average_ms) * AVERAGE_WEIGHT;
+            })
+            .or_insert_with(|| SystemTiming {
+                name: name.to_string(),
+                last_ms: ms,
+                average_ms: ms,
+            });
+        self
+    }
+    pub fn get(&self, id: &SystemId) -> Option<&SystemTiming> {
+        self.timings.get(id)
+    }
+    pub fn remove(&mut self, id: &SystemId) -> &mut Self {
+        self.timings.remove(id);
+        self
+    }
+    pub fn clear(&mut self) -> &mut Self {
+        self.timings.clear();
+        self
+    }
+    pub fn is_empty(&self) -> bool {
+        self.timings.is_empty()
+    }
+    //Sorted from the slowest to the fastest on average
+    pub fn slowest(&self) -> Vec<&SystemTiming> {
+        let mut timings = self.timings.values().collect::<Vec<_>>();
+        timings.sort_by(|a, b| b.average_ms.total_cmp(&a.average_ms));
+        timings
+    }
+}
+
+#[allow(dead_code)]
+fn test_system_timings() {
+    let fast = inox_uid::generate_random_uid();
+    let slow = inox_uid::generate_random_uid();
+
+    let mut timings = SystemTimings::default();
+    assert!(timings.is_empty());
+    timings
+        .add(&fast, "FastSystem", Duration::from_millis(1))
+        .add(&slow, "SlowSystem", Duration::from_millis(10));
+    assert_eq!(timings.get(&fast).unwrap().name, "FastSystem");
+    assert_eq!(timings.get(&slow).unwrap().average_ms, 10.);
+
+    timings.add(&slow, "SlowSystem", Duration::from_millis(20));
+    let timing = timings.get(&slow).unwrap();
+    assert_eq!(timing.last_ms, 20.);
+    assert!(timing.average_ms > 10. && timing.average_ms < 20.);
+
+    let slowest = timings.slowest();
+    assert_eq!(slowest.len(), 2);
+    assert_eq!(slowest[0].name, "SlowSystem");
+    assert_eq!(slowest[1].name, "FastSystem");
+
+    timings.remove(&slow);
+    assert!(timings.get(&slow).is_none());
+    timings.clear();
+    assert!(timings.is_empty());
+}
+
+#[test]
+fn test() {
+    test_system_timings();
+}
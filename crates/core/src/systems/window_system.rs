@@ -31,7 +31,7 @@ impl WindowSystem {
     fn handle_events(&mut self) {
         self.listener
             .process_messages(|e: &ConfigEvent<Config>| match e {
-                ConfigEvent::Loaded(filename, config) => {
+                ConfigEvent::Loaded(filename, config) | ConfigEvent::Reloaded(filename, config) => {
                     if filename == self.config.get_filename() {
                         self.config = config.clone();
                         self.message_hub
@@ -0,0 +1,145 @@
+use std::path::{Path, PathBuf};
+
+use inox_serialize::{Deserialize, Serialize, SerializeFile};
+
+use crate::{BufferId, TextureId};
+
+//Resources bound and commands recorded by a pass in a captured frame.
+//Indirect draws count the maximum number of commands, the real one is known only by the gpu
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq, Eq)]
+#[serde(crate = "inox_serialize")]
+pub struct CapturedPass {
+    pub name: String,
+    pub pipelines: Vec<PathBuf>,
+    pub draw_count: u32,
+    pub dispatch_count: u32,
+    pub buffers: Vec<BufferId>,
+    pub textures: Vec<TextureId>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq, Eq)]
+#[serde(crate = "inox_serialize")]
+pub struct FrameCaptureData {
+    pub passes: Vec<CapturedPass>,
+}
+
+impl SerializeFile for FrameCaptureData {
+    fn extension() -> &'static str {
+        "json"
+    }
+}
+
+//Records the passes of a single frame in the order they are encoded.
+//A pass drawn for more than one view is recorded once for each of them
+#[derive(Default)]
+pub struct FrameCapture {
+    path: Option<PathBuf>,
+    data: FrameCaptureData,
+}
+
+impl FrameCapture {
+    pub fn begin_frame(&mut self, path: &Path) {
+        self.path = Some(path.to_path_buf());
+        self.data = FrameCaptureData::default();
+    }
+    pub fn is_capturing(&self) -> bool {
+        self.path.is_some()
+    }
+    pub fn begin_pass(
+        &mut self,
+        name: &str,
+        pipelines: Vec<PathBuf>,
+        buffers: Vec<BufferId>,
+        textures: Vec<TextureId>,
+    ) {
+        if !self.is_capturing() {
+            return;
+        }
+        self.data.passes.push(CapturedPass {
+            name: name.to_string(),
+            pipelines,
+            buffers,
+            textures,
+            ..Default::default()
+        });
+    }
+    pub fn add_draws(&mut self, count: u32) {
+        if let Some(pass) = self.current_pass() {
+            pass.draw_count += count;
+        }
+    }
+    pub fn add_dispatches(&mut self, count: u32) {
+        if let Some(pass) = self.current_pass() {
+            pass.dispatch_count += count;
+        }
+    }
+    //Returns where to write the frame together with what has been recorded
+    pub fn end_frame(&mut self) -> Option<(PathBuf, FrameCaptureData)> {
+        let path = self.path.take()?;
+        Some((path, std::mem::take(&mut self.data)))
+    }
+    fn current_pass(&mut self) -> Option<&mut CapturedPass> {
+        if !self.is_capturing() {
+            return None;
+        }
+        self.data.passes.last_mut()
+    }
+}
+
+#[allow(dead_code)]
+fn test_frame_capture() {
+    use inox_resources::SharedDataRc;
+    use inox_serialize::{deserialize, serialize_to_file};
+    use inox_uid::generate_random_resource_uid;
+
+    let shared_data = SharedDataRc::default();
+    let texture_id = generate_random_resource_uid();
+    let path = std::env::temp_dir()
+        .join("inox_frame_capture")
+        .join("frame.json");
+    std::fs::remove_file(path.as_path()).ok();
+
+    //Nothing is recorded outside of a captured frame
+    let mut frame_capture = FrameCapture::default();
+    frame_capture.begin_pass("GBufferPass", Vec::new(), vec![1], Vec::new());
+    frame_capture.add_draws(10);
+    assert!(frame_capture.end_frame().is_none());
+
+    frame_capture.begin_frame(path.as_path());
+    frame_capture.begin_pass(
+        "ComputeCullingPass",
+        vec![PathBuf::from("pipelines/compute_culling.compute_pipeline")],
+        vec![1, 2],
+        Vec::new(),
+    );
+    frame_capture.add_dispatches(1);
+    frame_capture.begin_pass(
+        "GBufferPass",
+        vec![PathBuf::from("pipelines/gbuffer.render_pipeline")],
+        vec![2, 3],
+        vec![texture_id],
+    );
+    frame_capture.add_draws(12);
+    frame_capture.add_draws(1);
+    let (capture_path, data) = frame_capture.end_frame().unwrap();
+    assert!(!frame_capture.is_capturing());
+    assert_eq!(capture_path, path);
+
+    serialize_to_file(&data, path.as_path(), shared_data.serializable_registry());
+    let json = std::fs::read_to_string(path.as_path()).unwrap();
+    let data: FrameCaptureData = deserialize(&json, shared_data.serializable_registry()).unwrap();
+    assert_eq!(
+        data.passes
+            .iter()
+            .map(|p| (p.name.as_str(), p.draw_count, p.dispatch_count))
+            .collect::<Vec<_>>(),
+        vec![("ComputeCullingPass", 0, 1), ("GBufferPass", 13, 0)]
+    );
+    assert_eq!(data.passes[1].buffers, vec![2, 3]);
+    assert_eq!(data.passes[1].textures, vec![texture_id]);
+}
+
+#[test]
+fn test() {
+    test_frame_capture();
+}
@@ -1,18 +1,25 @@
 pub use super::as_binding::*;
 pub use super::binding_buffers::*;
+pub use super::frame_capture::*;
 pub use super::gpu_buffer::*;
+pub use super::pass_timings::*;
 pub use super::passes::*;
 pub use super::render_buffers::*;
 pub use super::render_commands::*;
 pub use super::render_context::*;
+pub use super::render_graph::*;
+pub use super::render_scale::*;
 pub use super::renderer::*;
 pub use super::shapes2d::*;
 pub use super::shapes3d::*;
 pub use super::textures::*;
+pub use super::viewport::*;
 
 pub mod as_binding;
 pub mod binding_buffers;
+pub mod frame_capture;
 pub mod gpu_buffer;
+pub mod pass_timings;
 pub mod shapes2d;
 pub mod shapes3d;
 pub mod utils;
@@ -20,7 +27,10 @@ pub mod utils;
 pub mod render_buffers;
 pub mod render_commands;
 pub mod render_context;
+pub mod render_graph;
+pub mod render_scale;
 pub mod renderer;
+pub mod viewport;
 
 pub mod passes;
 pub mod textures;
@@ -0,0 +1,266 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use inox_resources::to_slice;
+
+use crate::RenderCoreContext;
+
+pub const MAX_TIMESTAMPED_PASSES: u32 = 128;
+//Timestamps of a frame are read back while the next ones are rendered
+const FRAMES_IN_FLIGHT: usize = 3;
+//Weight of the last frame in the rolling average
+const AVERAGE_WEIGHT: f32 = 0.1;
+
+//Rolling average of the gpu milliseconds spent by each pass.
+//Results arrive some frames later and are kept only if newer than the last added ones
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct PassTimings {
+    last_frame: Option<u64>,
+    averages: Vec<(String, f32)>,
+}
+
+impl PassTimings {
+    pub fn add_frame(&mut self, frame: u64, timings: &[(String, f32)]) -> bool {
+        if self.last_frame.map_or(false, |last| frame <= last) {
+            return false;
+        }
+        self.last_frame = Some(frame);
+        //Per view passes run once for each view and their times are summed
+        let mut frame_timings: Vec<(String, f32)> = Vec::new();
+        timings.iter().for_each(|(name, ms)| {
+            match frame_timings.iter_mut().find(|(n, _)| n == name) {
+                Some((_, total)) => *total += ms,
+                None => frame_timings.push((name.clone(), *ms)),
+            }
+        });
+        self.averages
+            .retain(|(name, _)| frame_timings.iter().any(|(n, _)| n == name));
+        frame_timings.into_iter().for_each(|(name, ms)| {
+            match self.averages.iter_mut().find(|(n, _)| *n == name) {
+                Some((_, average)) => *average += (ms - *average) * AVERAGE_WEIGHT,
+                None => self.averages.push((name, ms)),
+            }
+        });
+        true
+    }
+    pub fn last_frame(&self) -> Option<u64> {
+        self.last_frame
+    }
+    pub fn get(&self, name: &str) -> Option<f32> {
+        self.averages
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, ms)| *ms)
+    }
+    pub fn timings(&self) -> &[(String, f32)] {
+        &self.averages
+    }
+    pub fn clear(&mut self) {
+        self.last_frame = None;
+        self.averages.clear();
+    }
+}
+
+struct TimestampsReadback {
+    frame: u64,
+    passes: Vec<String>,
+    resolve_buffer: wgpu::Buffer,
+    read_buffer: wgpu::Buffer,
+    is_mapped: Arc<AtomicBool>,
+    is_pending: bool,
+}
+
+//Writes a timestamp before and after each pass and reads them back without waiting the gpu.
+//A frame is skipped when its readback buffer is still in use by an older one
+#[derive(Default)]
+pub struct GpuTimestamps {
+    query_set: Option<wgpu::QuerySet>,
+    period: f32,
+    readbacks: Vec<TimestampsReadback>,
+    current: usize,
+    frame: u64,
+    passes: Vec<String>,
+    is_recording: bool,
+    timings: PassTimings,
+}
+
+impl GpuTimestamps {
+    //Devices without timestamp queries never have timings
+    pub fn init(&mut self, core: &RenderCoreContext) {
+        if self.query_set.is_some()
+            || !core
+                .device
+                .features()
+                .contains(wgpu::Features::TIMESTAMP_QUERY)
+        {
+            return;
+        }
+        let count = MAX_TIMESTAMPED_PASSES * 2;
+        let size = count as u64 * std::mem::size_of::<u64>() as u64;
+        self.query_set = Some(core.device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("Pass Timestamps"),
+            ty: wgpu::QueryType::Timestamp,
+            count,
+        }));
+        self.period = core.queue.get_timestamp_period();
+        self.readbacks = (0..FRAMES_IN_FLIGHT)
+            .map(|_| TimestampsReadback {
+                frame: 0,
+                passes: Vec::new(),
+                resolve_buffer: core.device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("Pass Timestamps Resolve"),
+                    size,
+                    usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+                    mapped_at_creation: false,
+                }),
+                read_buffer: core.device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("Pass Timestamps Read"),
+                    size,
+                    usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                }),
+                is_mapped: Arc::new(AtomicBool::new(false)),
+                is_pending: false,
+            })
+            .collect();
+    }
+
+    //Adds the frames finished by the gpu to the timings with the frame they were recorded on
+    pub fn begin_frame(&mut self, device: &wgpu::Device) {
+        if self.query_set.is_none() {
+            return;
+        }
+        device.poll(wgpu::Maintain::Poll);
+        let period = self.period;
+        self.readbacks
+            .iter_mut()
+            .filter(|r| r.is_pending && r.is_mapped.load(Ordering::SeqCst))
+            .for_each(|readback| {
+                let timings = {
+                    let view = readback.read_buffer.slice(..).get_mapped_range();
+                    let data: &[u8] = &view;
+                    let timestamps: &[u64] = to_slice(data);
+                    readback
+                        .passes
+                        .iter()
+                        .enumerate()
+                        .map(|(i, name)| {
+                            let ticks = timestamps[i * 2 + 1].saturating_sub(timestamps[i * 2]);
+                            (name.clone(), ticks as f32 * period / 1_000_000.)
+                        })
+                        .collect::<Vec<_>>()
+                };
+                readback.read_buffer.unmap();
+                readback.is_mapped.store(false, Ordering::SeqCst);
+                readback.is_pending = false;
+                self.timings.add_frame(readback.frame, &timings);
+            });
+        self.passes.clear();
+        self.is_recording = !self.readbacks[self.current].is_pending;
+    }
+    pub fn begin_pass(&mut self, name: &str, encoder: &mut wgpu::CommandEncoder) -> Option<u32> {
+        let query_set = self.query_set.as_ref().filter(|_| self.is_recording)?;
+        if self.passes.len() as u32 >= MAX_TIMESTAMPED_PASSES {
+            return None;
+        }
+        let index = self.passes.len() as u32 * 2;
+        encoder.write_timestamp(query_set, index);
+        self.passes.push(name.to_string());
+        Some(index)
+    }
+    pub fn end_pass(&self, index: Option<u32>, encoder: &mut wgpu::CommandEncoder) {
+        if let (Some(query_set), Some(index)) = (&self.query_set, index) {
+            encoder.write_timestamp(query_set, index + 1);
+        }
+    }
+    //Has to be recorded in the last command buffer of the frame
+    pub fn resolve(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        let Some(query_set) = &self.query_set else {
+            return;
+        };
+        if !self.is_recording || self.passes.is_empty() {
+            return;
+        }
+        let readback = &mut self.readbacks[self.current];
+        let count = self.passes.len() as u32 * 2;
+        encoder.resolve_query_set(query_set, 0..count, &readback.resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(
+            &readback.resolve_buffer,
+            0,
+            &readback.read_buffer,
+            0,
+            count as u64 * std::mem::size_of::<u64>() as u64,
+        );
+        readback.frame = self.frame;
+        readback.passes = std::mem::take(&mut self.passes);
+        readback.is_pending = true;
+    }
+    //The readback buffer can be mapped only once the frame has been submitted
+    pub fn end_frame(&mut self) {
+        if self.query_set.is_none() {
+            return;
+        }
+        let readback = &self.readbacks[self.current];
+        if self.is_recording && readback.is_pending {
+            let is_mapped = readback.is_mapped.clone();
+            readback
+                .read_buffer
+                .slice(..)
+                .map_async(wgpu::MapMode::Read, move |result| {
+                    is_mapped.store(result.is_ok(), Ordering::SeqCst);
+                });
+            self.current = (self.current + 1) % self.readbacks.len();
+        }
+        self.is_recording = false;
+        self.frame += 1;
+    }
+    pub fn timings(&self) -> &PassTimings {
+        &self.timings
+    }
+}
+
+#[allow(dead_code)]
+fn test_pass_timings() {
+    let frame = |timings: &[(&str, f32)]| {
+        timings
+            .iter()
+            .map(|(name, ms)| (name.to_string(), *ms))
+            .collect::<Vec<_>>()
+    };
+    let mut pass_timings = PassTimings::default();
+    assert!(pass_timings.add_frame(2, &frame(&[("culling", 1.), ("pbr", 4.)])));
+    assert_eq!(pass_timings.get("pbr"), Some(4.));
+
+    //Results of a frame older than the last added one arrived too late
+    assert!(!pass_timings.add_frame(1, &frame(&[("pbr", 100.)])));
+    assert!(!pass_timings.add_frame(2, &frame(&[("pbr", 100.)])));
+    assert_eq!(pass_timings.get("pbr"), Some(4.));
+    assert_eq!(pass_timings.last_frame(), Some(2));
+
+    //A pass run for two views takes the time of both
+    assert!(pass_timings.add_frame(4, &frame(&[("culling", 1.), ("culling", 1.), ("pbr", 14.)])));
+    assert!((pass_timings.get("culling").unwrap() - 1.1).abs() < 1e-4);
+    assert!((pass_timings.get("pbr").unwrap() - 5.).abs() < 1e-4);
+    assert_eq!(
+        pass_timings
+            .timings()
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>(),
+        vec!["culling", "pbr"]
+    );
+
+    //Passes not run anymore are dropped
+    assert!(pass_timings.add_frame(5, &frame(&[("pbr", 5.)])));
+    assert_eq!(pass_timings.get("culling"), None);
+    pass_timings.clear();
+    assert!(pass_timings.timings().is_empty());
+    assert!(pass_timings.add_frame(0, &frame(&[("pbr", 5.)])));
+}
+
+#[test]
+fn test() {
+    test_pass_timings();
+}
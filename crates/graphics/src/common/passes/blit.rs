@@ -3,12 +3,12 @@ use std::path::PathBuf;
 use crate::{
     BindingData, BindingInfo, CommandBuffer, DrawCommandType, MeshFlags, OutputRenderPass, Pass,
     RenderContext, RenderPass, RenderPassBeginData, RenderPassData, RenderTarget, ShaderStage,
-    StoreOperation, TextureId, TextureView,
+    StoreOperation, TextureAccess, TextureId, TextureView,
 };
 
 use inox_core::ContextRc;
 use inox_resources::{DataTypeResource, Resource, ResourceTrait};
-use inox_uid::{generate_random_uid, INVALID_UID};
+use inox_uid::{generate_random_resource_uid, INVALID_RESOURCE_UID};
 
 pub const BLIT_PIPELINE: &str = "pipelines/Blit.render_pipeline";
 pub const BLIT_PASS_NAME: &str = "BlitPass";
@@ -56,18 +56,18 @@ impl Pass for BlitPass {
             render_pass: RenderPass::new_resource(
                 context.shared_data(),
                 context.message_hub(),
-                generate_random_uid(),
+                generate_random_resource_uid(),
                 &data,
                 None,
             ),
             binding_data: BindingData::new(render_context, BLIT_PASS_NAME),
-            source_texture_id: INVALID_UID,
+            source_texture_id: INVALID_RESOURCE_UID,
         }
     }
     fn init(&mut self, render_context: &RenderContext) {
         inox_profiler::scoped_profile!("blit_pass::init");
 
-        if self.source_texture_id.is_nil() {
+        if self.source_texture_id == INVALID_RESOURCE_UID {
             return;
         }
 
@@ -93,7 +93,7 @@ impl Pass for BlitPass {
     ) {
         inox_profiler::scoped_profile!("blit_pass::update");
 
-        if self.source_texture_id.is_nil() {
+        if self.source_texture_id == INVALID_RESOURCE_UID {
             return;
         }
 
@@ -111,6 +111,7 @@ impl Pass for BlitPass {
             render_targets: render_targets.as_slice(),
             surface_view,
             command_buffer,
+            view_submission: render_context.current_view(),
         };
         let mut render_pass = pass.begin(&mut self.binding_data, &pipeline, render_pass_begin_data);
         {
@@ -122,6 +123,14 @@ impl Pass for BlitPass {
             pass.draw(render_context, render_pass, 0..3, 0..1);
         }
     }
+    fn read_textures(&self) -> Vec<(TextureId, TextureAccess)> {
+        let mut textures = self.render_pass.get().read_textures();
+        textures.push((self.source_texture_id, TextureAccess::Sampled));
+        textures
+    }
+    fn written_textures(&self) -> Vec<(TextureId, TextureAccess)> {
+        self.render_pass.get().written_textures()
+    }
 }
 
 impl OutputRenderPass for BlitPass {
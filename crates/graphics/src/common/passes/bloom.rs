@@ -0,0 +1,738 @@
+use std::path::PathBuf;
+
+use crate::{
+    declare_as_binding_vector, AsBinding, BindingData, BindingFlags, BindingInfo, CommandBuffer,
+    ComputePass, ComputePassData, DrawCommandType, GpuBuffer, MeshFlags, Pass, RenderContext,
+    RenderCoreContext, ShaderStage, Texture, TextureAccess, TextureId, TextureView,
+};
+
+use inox_core::ContextRc;
+use inox_resources::{DataTypeResource, Handle, Resource};
+use inox_uid::generate_random_resource_uid;
+
+pub const BLOOM_DOWNSAMPLE_PIPELINE: &str = "pipelines/ComputeBloomDownsample.compute_pipeline";
+pub const BLOOM_COMPOSITE_PIPELINE: &str = "pipelines/ComputeBloomComposite.compute_pipeline";
+pub const BLOOM_PASS_NAME: &str = "BloomPass";
+pub const BLOOM_DOWNSAMPLE_PASS_NAME: &str = "BloomDownsamplePass";
+pub const BLOOM_COMPOSITE_PASS_NAME: &str = "BloomCompositePass";
+//Only the radiance brighter than white blooms
+pub const DEFAULT_BLOOM_THRESHOLD: f32 = 1.;
+pub const DEFAULT_BLOOM_INTENSITY: f32 = 0.5;
+
+const BLOOM_WORKGROUP_SIZE: u32 = 8;
+//Limits how far the light spreads, each texel of the last level covers 64x64 pixels
+const BLOOM_MAX_LEVELS: u32 = 6;
+const BLOOM_TENT_WEIGHTS: [f32; 4] = [1. / 8., 3. / 8., 3. / 8., 1. / 8.];
+
+declare_as_binding_vector!(VecBloom, f32);
+
+//Chain of blurred levels of the radiance brighter than the threshold, stored level after level
+//in one buffer as rgba texels. Level 0 is half the resolution of the radiance and each level
+//halves the one below, as the HiZ of the culling
+struct BloomData {
+    is_dirty: bool,
+    dimensions: [u32; 2],
+    levels_count: u32,
+    threshold: f32,
+    intensity: f32,
+    padding: u32,
+}
+
+impl Default for BloomData {
+    fn default() -> Self {
+        Self {
+            is_dirty: true,
+            dimensions: [0, 0],
+            levels_count: 0,
+            threshold: DEFAULT_BLOOM_THRESHOLD,
+            intensity: DEFAULT_BLOOM_INTENSITY,
+            padding: 0,
+        }
+    }
+}
+
+impl AsBinding for BloomData {
+    fn is_dirty(&self) -> bool {
+        self.is_dirty
+    }
+    fn set_dirty(&mut self, is_dirty: bool) {
+        self.is_dirty = is_dirty;
+    }
+    fn size(&self) -> u64 {
+        std::mem::size_of_val(&self.dimensions) as u64
+            + std::mem::size_of_val(&self.levels_count) as u64
+            + std::mem::size_of_val(&self.threshold) as u64
+            + std::mem::size_of_val(&self.intensity) as u64
+            + std::mem::size_of_val(&self.padding) as u64
+    }
+    fn fill_buffer(&self, render_core_context: &RenderCoreContext, buffer: &mut GpuBuffer) {
+        buffer.add_to_gpu_buffer(render_core_context, &[self.dimensions]);
+        buffer.add_to_gpu_buffer(render_core_context, &[self.levels_count]);
+        buffer.add_to_gpu_buffer(render_core_context, &[self.threshold]);
+        buffer.add_to_gpu_buffer(render_core_context, &[self.intensity]);
+        buffer.add_to_gpu_buffer(render_core_context, &[self.padding]);
+    }
+}
+
+//The levels are dispatched one after the other in the same command buffer,
+//so each of them is bound with its own uniform to know which level it writes
+struct BloomLevel {
+    is_dirty: bool,
+    level: u32,
+}
+
+impl BloomLevel {
+    fn new(level: u32) -> Self {
+        Self {
+            is_dirty: true,
+            level,
+        }
+    }
+}
+
+impl AsBinding for BloomLevel {
+    fn is_dirty(&self) -> bool {
+        self.is_dirty
+    }
+    fn set_dirty(&mut self, is_dirty: bool) {
+        self.is_dirty = is_dirty;
+    }
+    fn size(&self) -> u64 {
+        std::mem::size_of_val(&self.level) as u64
+    }
+    fn fill_buffer(&self, render_core_context: &RenderCoreContext, buffer: &mut GpuBuffer) {
+        buffer.add_to_gpu_buffer(render_core_context, &[self.level]);
+    }
+}
+
+impl BloomData {
+    fn set_dimensions(&mut self, dimensions: [u32; 2]) {
+        if self.dimensions != dimensions {
+            self.dimensions = dimensions;
+            self.levels_count = self.max_levels_count();
+            self.set_dirty(true);
+        }
+    }
+    fn level_size(&self, level: u32) -> [u32; 2] {
+        let texel_size = 2u32 << level;
+        self.dimensions.map(|d| d.div_ceil(texel_size).max(1))
+    }
+    fn max_levels_count(&self) -> u32 {
+        if self.dimensions.contains(&0) {
+            return 0;
+        }
+        let mut level = 0;
+        while level + 1 < BLOOM_MAX_LEVELS && self.level_size(level) != [1, 1] {
+            level += 1;
+        }
+        level + 1
+    }
+    fn level_offset(&self, level: u32) -> usize {
+        (0..level)
+            .map(|l| {
+                let size = self.level_size(l);
+                (size[0] * size[1]) as usize
+            })
+            .sum()
+    }
+    fn len(&self) -> usize {
+        self.level_offset(self.levels_count)
+    }
+    //Same filters of compute_bloom_downsample.wgsl and compute_bloom_composite.wgsl
+    #[allow(dead_code)]
+    fn apply(&self, radiance: &[[f32; 4]]) -> Vec<[f32; 4]> {
+        let prefilter = |color: &[f32; 4]| {
+            let luminance = 0.2126 * color[0] + 0.7152 * color[1] + 0.0722 * color[2];
+            let scale = (luminance - self.threshold).max(0.) / luminance.max(0.0001);
+            [color[0] * scale, color[1] * scale, color[2] * scale]
+        };
+        let mut chain = vec![[0f32; 3]; self.len()];
+        let mut source_size = self.dimensions;
+        let mut source_offset = 0;
+        for level in 0..self.levels_count {
+            let size = self.level_size(level);
+            let offset = self.level_offset(level);
+            for y in 0..size[1] {
+                for x in 0..size[0] {
+                    let mut color = [0f32; 3];
+                    for i in 0..16 {
+                        let tap = [i & 3, i >> 2];
+                        let texel = [x, y].map(|t| t as i32 * 2 - 1);
+                        let texel = [0, 1].map(|a| {
+                            (texel[a] + tap[a] as i32).clamp(0, source_size[a] as i32 - 1) as u32
+                        });
+                        let index = (texel[1] * source_size[0] + texel[0]) as usize;
+                        let source = if level == 0 {
+                            prefilter(&radiance[index])
+                        } else {
+                            chain[source_offset + index]
+                        };
+                        let weight = BLOOM_TENT_WEIGHTS[tap[0]] * BLOOM_TENT_WEIGHTS[tap[1]];
+                        (0..3).for_each(|c| color[c] += source[c] * weight);
+                    }
+                    chain[offset + (y * size[0] + x) as usize] = color;
+                }
+            }
+            source_size = size;
+            source_offset = offset;
+        }
+
+        let texel = |level: u32, x: i32, y: i32| {
+            let size = self.level_size(level);
+            let x = x.clamp(0, size[0] as i32 - 1) as u32;
+            let y = y.clamp(0, size[1] as i32 - 1) as u32;
+            chain[self.level_offset(level) + (y * size[0] + x) as usize]
+        };
+        let lerp = |a: [f32; 3], b: [f32; 3], t: f32| [0, 1, 2].map(|c| a[c] + (b[c] - a[c]) * t);
+        let mut result = radiance.to_vec();
+        for y in 0..self.dimensions[1] {
+            for x in 0..self.dimensions[0] {
+                let mut color = [0f32; 3];
+                for level in 0..self.levels_count {
+                    let texel_size = (2u32 << level) as f32;
+                    let position = [x, y].map(|p| (p as f32 + 0.5) / texel_size - 0.5);
+                    let [tx, ty] = position.map(|p| p.floor() as i32);
+                    let [fx, fy] = position.map(|p| p - p.floor());
+                    let top = lerp(texel(level, tx, ty), texel(level, tx + 1, ty), fx);
+                    let bottom = lerp(texel(level, tx, ty + 1), texel(level, tx + 1, ty + 1), fx);
+                    let sample = lerp(top, bottom, fy);
+                    (0..3).for_each(|c| color[c] += sample[c]);
+                }
+                let pixel = &mut result[(y * self.dimensions[0] + x) as usize];
+                (0..3).for_each(|c| pixel[c] += color[c] * self.intensity);
+            }
+        }
+        result
+    }
+    //Runs the shaders of the pass with its same bindings on a device without a surface,
+    //none when the system has no adapter able to run them
+    #[allow(dead_code)]
+    #[cfg(not(target_arch = "wasm32"))]
+    fn gpu_apply(&self, radiance: &[[f32; 4]]) -> Option<Vec<[f32; 4]>> {
+        use inox_math::{decode_half, quantize_half};
+        use inox_resources::to_slice;
+        use std::num::NonZeroU32;
+        use wgpu::util::DeviceExt;
+
+        let format = wgpu::TextureFormat::Rgba16Float;
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+        let adapter = futures::executor::block_on(
+            instance.request_adapter(&wgpu::RequestAdapterOptions::default()),
+        )?;
+        let features = wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES;
+        if !adapter.features().contains(features)
+            || !adapter
+                .get_texture_format_features(format)
+                .flags
+                .contains(wgpu::TextureFormatFeatureFlags::STORAGE_READ_WRITE)
+        {
+            return None;
+        }
+        let (device, queue) = futures::executor::block_on(adapter.request_device(
+            &wgpu::DeviceDescriptor {
+                label: None,
+                features,
+                limits: adapter.limits(),
+            },
+            None,
+        ))
+        .ok()?;
+
+        let shaders_folder =
+            std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../../data_raw/shaders/wgsl");
+        let pipelines = [
+            "compute_bloom_downsample.wgsl",
+            "compute_bloom_composite.wgsl",
+        ]
+        .map(|file| {
+            let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some(file),
+                source: wgpu::ShaderSource::Wgsl(
+                    crate::wgsl_with_imports(&shaders_folder.join(file)).into(),
+                ),
+            });
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(file),
+                layout: None,
+                module: &module,
+                entry_point: "main",
+            })
+        });
+
+        let mut bloom_data = Vec::<u8>::new();
+        bloom_data.extend_from_slice(to_slice(&[self.dimensions]));
+        bloom_data.extend_from_slice(to_slice(&[self.levels_count]));
+        bloom_data.extend_from_slice(to_slice(&[self.threshold, self.intensity]));
+        bloom_data.extend_from_slice(to_slice(&[self.padding]));
+        let uniform = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: &bloom_data,
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let chain = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: to_slice(&vec![[0f32; 4]; self.len().max(1)]),
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+        let size = wgpu::Extent3d {
+            width: self.dimensions[0],
+            height: self.dimensions[1],
+            depth_or_array_layers: 1,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::STORAGE_BINDING
+                | wgpu::TextureUsages::COPY_SRC
+                | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[format],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        //The rows are copied as they are, so the width has to fill the copy alignment
+        //Four half floats for each texel
+        let bytes_per_row = self.dimensions[0] * 8;
+        assert_eq!(bytes_per_row % wgpu::COPY_BYTES_PER_ROW_ALIGNMENT, 0);
+        let pixels = radiance
+            .iter()
+            .flat_map(|p| p.map(quantize_half))
+            .collect::<Vec<_>>();
+        let pixels = to_slice::<u16, u8>(&pixels);
+        queue.write_texture(
+            texture.as_image_copy(),
+            pixels,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: NonZeroU32::new(bytes_per_row),
+                rows_per_image: None,
+            },
+            size,
+        );
+
+        //Each level of the downsample is bound with its own uniform, as in the pass
+        let levels = (0..self.levels_count)
+            .map(|level| {
+                device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: None,
+                    contents: to_slice(&[level]),
+                    usage: wgpu::BufferUsages::UNIFORM,
+                })
+            })
+            .collect::<Vec<_>>();
+        //Pipelines with an implicit layout don't share their bind groups
+        let create_bind_groups = |pipeline: &wgpu::ComputePipeline,
+                                  level: Option<&wgpu::Buffer>| {
+            let mut entries = vec![
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: chain.as_entire_binding(),
+                },
+            ];
+            if let Some(level) = level {
+                entries.push(wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: level.as_entire_binding(),
+                });
+            }
+            [
+                device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: None,
+                    layout: &pipeline.get_bind_group_layout(0),
+                    entries: &entries,
+                }),
+                device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: None,
+                    layout: &pipeline.get_bind_group_layout(1),
+                    entries: &[wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&view),
+                    }],
+                }),
+            ]
+        };
+        let dispatch = |encoder: &mut wgpu::CommandEncoder,
+                        pipeline: &wgpu::ComputePipeline,
+                        bind_groups: &[wgpu::BindGroup],
+                        workgroups: [u32; 2]| {
+            let mut compute_pass =
+                encoder.begin_compute_pass(&wgpu::ComputePassDescriptor::default());
+            compute_pass.set_pipeline(pipeline);
+            bind_groups
+                .iter()
+                .enumerate()
+                .for_each(|(i, bind_group)| compute_pass.set_bind_group(i as _, bind_group, &[]));
+            compute_pass.dispatch_workgroups(workgroups[0], workgroups[1], 1);
+        };
+
+        let read_back = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: pixels.len() as u64,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        for (level, level_buffer) in levels.iter().enumerate() {
+            let size = self.level_size(level as _);
+            let bind_groups = create_bind_groups(&pipelines[0], Some(level_buffer));
+            let workgroups = size.map(|s| s.div_ceil(BLOOM_WORKGROUP_SIZE));
+            dispatch(&mut encoder, &pipelines[0], &bind_groups, workgroups);
+        }
+        let bind_groups = create_bind_groups(&pipelines[1], None);
+        let workgroups = self.dimensions.map(|d| d.div_ceil(BLOOM_WORKGROUP_SIZE));
+        dispatch(&mut encoder, &pipelines[1], &bind_groups, workgroups);
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &read_back,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: NonZeroU32::new(bytes_per_row),
+                    rows_per_image: None,
+                },
+            },
+            size,
+        );
+        queue.submit(Some(encoder.finish()));
+
+        let slice = read_back.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |_| {});
+        device.poll(wgpu::Maintain::Wait);
+        let pixels = slice.get_mapped_range();
+        Some(
+            to_slice::<u8, u16>(&pixels)
+                .chunks(4)
+                .map(|p| [0, 1, 2, 3].map(|c| decode_half(p[c])))
+                .collect(),
+        )
+    }
+}
+
+//Spreads the light of the brightest pixels of the radiance to the ones around them,
+//writing the result back to the same texture.
+//The radiance is an Rgba16Float texture, so lights brighter than white bloom more
+pub struct BloomPass {
+    downsample_pass: Resource<ComputePass>,
+    composite_pass: Resource<ComputePass>,
+    binding_data: BindingData,
+    data: BloomData,
+    bloom_buffer: VecBloom,
+    levels: Vec<BloomLevel>,
+    source_texture: Handle<Texture>,
+}
+unsafe impl Send for BloomPass {}
+unsafe impl Sync for BloomPass {}
+
+impl Pass for BloomPass {
+    fn name(&self) -> &str {
+        BLOOM_PASS_NAME
+    }
+    fn static_name() -> &'static str {
+        BLOOM_PASS_NAME
+    }
+    fn is_active(&self, _render_context: &RenderContext) -> bool {
+        true
+    }
+    fn mesh_flags(&self) -> MeshFlags {
+        MeshFlags::None
+    }
+    fn draw_commands_type(&self) -> DrawCommandType {
+        DrawCommandType::PerMeshlet
+    }
+    fn create(context: &ContextRc, render_context: &RenderContext) -> Self
+    where
+        Self: Sized,
+    {
+        let downsample_data = ComputePassData {
+            name: BLOOM_DOWNSAMPLE_PASS_NAME.to_string(),
+            pipelines: vec![PathBuf::from(BLOOM_DOWNSAMPLE_PIPELINE)],
+        };
+        let composite_data = ComputePassData {
+            name: BLOOM_COMPOSITE_PASS_NAME.to_string(),
+            pipelines: vec![PathBuf::from(BLOOM_COMPOSITE_PIPELINE)],
+        };
+        Self {
+            downsample_pass: ComputePass::new_resource(
+                context.shared_data(),
+                context.message_hub(),
+                generate_random_resource_uid(),
+                &downsample_data,
+                None,
+            ),
+            composite_pass: ComputePass::new_resource(
+                context.shared_data(),
+                context.message_hub(),
+                generate_random_resource_uid(),
+                &composite_data,
+                None,
+            ),
+            binding_data: BindingData::new(render_context, BLOOM_PASS_NAME),
+            data: BloomData::default(),
+            bloom_buffer: VecBloom::default(),
+            levels: vec![BloomLevel::new(0)],
+            source_texture: None,
+        }
+    }
+    fn init(&mut self, render_context: &RenderContext) {
+        inox_profiler::scoped_profile!("bloom_pass::init");
+
+        let Some(source_texture) = &self.source_texture else {
+            return;
+        };
+        let dimensions = source_texture.get().dimensions();
+        self.data.set_dimensions([dimensions.0, dimensions.1]);
+
+        let len = self.data.len().max(1) * 4;
+        if self.bloom_buffer.size() != (len * std::mem::size_of::<f32>()) as u64 {
+            self.bloom_buffer.set(vec![0.; len]);
+        }
+        //Buffers are bound by address, so the levels are all created again with their count
+        let levels_count = self.data.levels_count.max(1);
+        if self.levels.len() != levels_count as usize {
+            self.levels = (0..levels_count).map(BloomLevel::new).collect();
+        }
+
+        self.binding_data
+            .add_uniform_buffer(
+                &mut self.data,
+                Some("BloomData"),
+                BindingInfo {
+                    group_index: 0,
+                    binding_index: 0,
+                    stage: ShaderStage::Compute,
+                    ..Default::default()
+                },
+            )
+            .add_storage_buffer(
+                &mut self.bloom_buffer,
+                Some("Bloom"),
+                BindingInfo {
+                    group_index: 0,
+                    binding_index: 1,
+                    stage: ShaderStage::Compute,
+                    flags: BindingFlags::ReadWrite,
+                },
+            )
+            .add_uniform_buffer(
+                &mut self.levels[0],
+                Some("BloomLevel"),
+                BindingInfo {
+                    group_index: 0,
+                    binding_index: 2,
+                    stage: ShaderStage::Compute,
+                    ..Default::default()
+                },
+            )
+            .add_texture(
+                source_texture.id(),
+                BindingInfo {
+                    group_index: 1,
+                    binding_index: 0,
+                    stage: ShaderStage::Compute,
+                    flags: BindingFlags::ReadWrite | BindingFlags::Storage,
+                },
+            );
+
+        self.downsample_pass
+            .get_mut()
+            .init(render_context, &mut self.binding_data);
+        self.composite_pass
+            .get_mut()
+            .init(render_context, &mut self.binding_data);
+    }
+
+    fn update(
+        &mut self,
+        render_context: &RenderContext,
+        _surface_view: &TextureView,
+        command_buffer: &mut CommandBuffer,
+    ) {
+        inox_profiler::scoped_profile!("bloom_pass::update");
+
+        if self.source_texture.is_none() || self.data.levels_count == 0 {
+            return;
+        }
+
+        //The levels read the ones below, so each of them is a dispatch on its own
+        let pass = self.downsample_pass.get();
+        for level in 0..self.data.levels_count {
+            let size = self.data.level_size(level);
+            self.binding_data.add_uniform_buffer(
+                &mut self.levels[level as usize],
+                Some("BloomLevel"),
+                BindingInfo {
+                    group_index: 0,
+                    binding_index: 2,
+                    stage: ShaderStage::Compute,
+                    ..Default::default()
+                },
+            );
+            let mut downsample_pass =
+                pass.begin(render_context, &mut self.binding_data, command_buffer);
+            {
+                inox_profiler::gpu_scoped_profile!(
+                    &mut downsample_pass,
+                    &render_context.core.device,
+                    "bloom_downsample_pass",
+                );
+                pass.dispatch(
+                    render_context,
+                    downsample_pass,
+                    size[0].div_ceil(BLOOM_WORKGROUP_SIZE),
+                    size[1].div_ceil(BLOOM_WORKGROUP_SIZE),
+                    1,
+                );
+            }
+        }
+
+        let pass = self.composite_pass.get();
+        let mut composite_pass = pass.begin(render_context, &mut self.binding_data, command_buffer);
+        {
+            inox_profiler::gpu_scoped_profile!(
+                &mut composite_pass,
+                &render_context.core.device,
+                "bloom_composite_pass",
+            );
+            pass.dispatch(
+                render_context,
+                composite_pass,
+                self.data.dimensions[0].div_ceil(BLOOM_WORKGROUP_SIZE),
+                self.data.dimensions[1].div_ceil(BLOOM_WORKGROUP_SIZE),
+                1,
+            );
+        }
+    }
+    fn read_textures(&self) -> Vec<(TextureId, TextureAccess)> {
+        self.written_textures()
+    }
+    fn written_textures(&self) -> Vec<(TextureId, TextureAccess)> {
+        self.source_texture
+            .iter()
+            .map(|t| (*t.id(), TextureAccess::Storage))
+            .collect()
+    }
+}
+
+impl BloomPass {
+    //The texture is both read and written, so it needs to be a storage one
+    pub fn set_source(&mut self, texture: &Resource<Texture>) -> &mut Self {
+        self.source_texture = Some(texture.clone());
+        self
+    }
+    pub fn threshold(&self) -> f32 {
+        self.data.threshold
+    }
+    //Luminance above which the pixels start to bloom
+    pub fn set_threshold(&mut self, threshold: f32) -> &mut Self {
+        self.data.threshold = threshold.max(0.);
+        self.data.set_dirty(true);
+        self
+    }
+    pub fn intensity(&self) -> f32 {
+        self.data.intensity
+    }
+    //Scale of the light added around the bright pixels, zero leaves the radiance untouched
+    pub fn set_intensity(&mut self, intensity: f32) -> &mut Self {
+        self.data.intensity = intensity.max(0.);
+        self.data.set_dirty(true);
+        self
+    }
+}
+
+#[allow(dead_code)]
+fn test_bloom_spread() {
+    let luminance = |p: &[f32; 4]| 0.2126 * p[0] + 0.7152 * p[1] + 0.0722 * p[2];
+    let width = 64;
+    let pixel = |x: usize, y: usize| y * width + x;
+    let mut data = BloomData {
+        threshold: 0.5,
+        intensity: 1.,
+        ..Default::default()
+    };
+    data.set_dimensions([width as u32, width as u32]);
+    assert_eq!(data.levels_count, BLOOM_MAX_LEVELS);
+    assert_eq!(data.level_size(0), [32, 32]);
+    assert_eq!(data.level_size(BLOOM_MAX_LEVELS - 1), [1, 1]);
+
+    //A single emissive pixel in the middle of a dark image
+    let center = (32, 32);
+    let mut radiance = vec![[0., 0., 0., 1.]; width * width];
+    radiance[pixel(center.0, center.1)] = [1., 1., 1., 1.];
+    //A dim one far from it that stays under the threshold
+    let dim = (8, 56);
+    radiance[pixel(dim.0, dim.1)] = [0.4, 0.4, 0.4, 1.];
+
+    let check = |result: &[[f32; 4]], tolerance: f32| {
+        assert_eq!(result.len(), radiance.len());
+        let around = |distance: usize| {
+            [
+                pixel(center.0 - distance, center.1),
+                pixel(center.0 + distance, center.1),
+                pixel(center.0, center.1 - distance),
+                pixel(center.0, center.1 + distance),
+            ]
+            .map(|i| luminance(&result[i]))
+        };
+        let near = around(1);
+        let far = around(4);
+        near.iter().zip(far.iter()).for_each(|(near, far)| {
+            assert!(*near > tolerance, "{near}");
+            assert!(near > far, "{near} {far}");
+        });
+        //The radiance isn't clamped, the emissive pixel gets brighter with its own light
+        assert!(luminance(&result[pixel(center.0, center.1)]) > 1.);
+        assert!((luminance(&result[pixel(dim.0, dim.1)]) - 0.4).abs() <= tolerance);
+        assert!(luminance(&result[pixel(0, 0)]) <= tolerance);
+    };
+
+    //The widest levels spread a faint light over the whole image
+    let expected = data.apply(&radiance);
+    check(&expected, 1. / 255.);
+    //Without intensity nothing is added
+    data.intensity = 0.;
+    assert_eq!(data.apply(&radiance), radiance);
+    data.intensity = 1.;
+    //A threshold above the brightest pixel leaves the radiance as it is
+    data.threshold = 1.5;
+    assert_eq!(data.apply(&radiance), radiance);
+    data.threshold = 0.5;
+    //A pixel brighter than white spreads more light than a white one
+    let mut bright = radiance.clone();
+    bright[pixel(center.0, center.1)] = [4., 4., 4., 1.];
+    let near = |result: &[[f32; 4]]| luminance(&result[pixel(center.0 + 1, center.1)]);
+    let expected_bright = data.apply(&bright);
+    assert!(near(&expected_bright) > near(&expected) * 2.);
+
+    #[cfg(not(target_arch = "wasm32"))]
+    match (data.gpu_apply(&radiance), data.gpu_apply(&bright)) {
+        (Some(result), Some(result_bright)) => {
+            //The radiance is stored as half floats
+            let tolerance = 2. / 255.;
+            check(&result, tolerance);
+            [(&result, &expected), (&result_bright, &expected_bright)]
+                .iter()
+                .for_each(|(result, expected)| {
+                    result.iter().zip(expected.iter()).for_each(|(r, e)| {
+                        (0..4).for_each(|c| {
+                            assert!(
+                                (r[c] - e[c]).abs() <= tolerance * e[c].max(1.),
+                                "{r:?} {e:?}"
+                            )
+                        });
+                    });
+                });
+        }
+        _ => println!("No GPU adapter available, bloom checked only on CPU"),
+    }
+}
+
+#[test]
+fn test() {
+    test_bloom_spread();
+}
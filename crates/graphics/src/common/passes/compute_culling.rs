@@ -1,22 +1,69 @@
 use std::path::PathBuf;
 
 use crate::{
-    AsBinding, BHVBuffer, BindingData, BindingFlags, BindingInfo, CommandBuffer, CommandsBuffer,
-    ComputePass, ComputePassData, ConstantDataRw, CullingResults, DrawCommandType, GpuBuffer,
-    MeshFlags, MeshesBuffer, MeshesFlagsBuffer, MeshletsBuffer, MeshletsCullingBuffer, Pass,
-    RenderContext, RenderCoreContext, ShaderStage, TextureView, NUM_COMMANDS_PER_GROUP,
+    culling_dispatch_count, culling_result_len, declare_as_binding_vector,
+    utils::unpack_cone_axis_cutoff, AsBinding, BHVBuffer, BindingData, BindingFlags, BindingInfo,
+    CommandBuffer, CommandsBuffer, ComputePass, ComputePassData, ConeCulling, ConstantDataRw,
+    CullingResults, DrawBHVNode, DrawCommandType, DrawMaterial, DrawMesh, GpuBuffer, MaterialFlags,
+    MaterialsBuffer, MeshFlags, MeshesBuffer, MeshesFlagsBuffer, MeshletsBuffer,
+    MeshletsCullingBuffer, Pass, RenderContext, RenderCoreContext, ShaderStage, Texture,
+    TextureView, CULLING_RESULT_BITS, DEFAULT_CULLING_WORKGROUP_SIZE, INVALID_INDEX,
 };
 
 use inox_commands::CommandParser;
 use inox_core::ContextRc;
+use inox_math::{matrix4_to_array, InnerSpace, Matrix4, NewAngle, Vector3, Vector4};
 use inox_messenger::{implement_message, Listener};
-use inox_resources::{DataTypeResource, Resource};
-use inox_uid::generate_random_uid;
+use inox_resources::{DataTypeResource, Handle, Resource};
+use inox_uid::generate_random_resource_uid;
 
 pub const CULLING_PIPELINE: &str = "pipelines/ComputeCulling.compute_pipeline";
 pub const COMPACTION_PIPELINE: &str = "pipelines/ComputeCompact.compute_pipeline";
+pub const HI_Z_PIPELINE: &str = "pipelines/ComputeHiZ.compute_pipeline";
 pub const CULLING_PASS_NAME: &str = "CullingPass";
 pub const COMPACTION_PASS_NAME: &str = "CompactionPass";
+pub const HI_Z_PASS_NAME: &str = "HiZPass";
+
+const HI_Z_WORKGROUP_SIZE: u32 = 8;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum CullingError {
+    NotPowerOfTwo(u32),
+    ExceedsDeviceLimit { size: u32, max_size: u32 },
+}
+
+impl std::fmt::Display for CullingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CullingError::NotPowerOfTwo(size) => {
+                write!(f, "Culling workgroup size {size} is not a power of two")
+            }
+            CullingError::ExceedsDeviceLimit { size, max_size } => {
+                write!(
+                    f,
+                    "Culling workgroup size {size} exceeds the device limit of {max_size}"
+                )
+            }
+        }
+    }
+}
+
+//A power of two either splits evenly a word of the culling results or spans whole words
+pub fn validate_culling_workgroup_size(size: u32, max_size: u32) -> Result<u32, CullingError> {
+    if !size.is_power_of_two() {
+        return Err(CullingError::NotPowerOfTwo(size));
+    }
+    if size > max_size {
+        return Err(CullingError::ExceedsDeviceLimit { size, max_size });
+    }
+    Ok(size)
+}
+
+//Largest power of two allowed by the device, up to the default one
+pub fn preferred_culling_workgroup_size(max_size: u32) -> u32 {
+    let largest = 1 << (u32::BITS - 1 - max_size.max(1).leading_zeros());
+    DEFAULT_CULLING_WORKGROUP_SIZE.min(largest)
+}
 
 #[derive(Debug, PartialOrd, PartialEq, Eq, Clone)]
 pub enum CullingEvent {
@@ -47,8 +94,10 @@ impl CullingEvent {
 struct CullingData {
     is_dirty: bool,
     view: [[f32; 4]; 4],
+    previous_view_proj: [[f32; 4]; 4],
     mesh_flags: u32,
-    _padding: [u32; 3],
+    hi_z_levels_count: u32,
+    depth_dimensions: [u32; 2],
 }
 
 impl AsBinding for CullingData {
@@ -60,19 +109,285 @@ impl AsBinding for CullingData {
     }
     fn size(&self) -> u64 {
         std::mem::size_of_val(&self.view) as u64
+            + std::mem::size_of_val(&self.previous_view_proj) as u64
             + std::mem::size_of_val(&self.mesh_flags) as u64
-            + std::mem::size_of_val(&self._padding) as u64
+            + std::mem::size_of_val(&self.hi_z_levels_count) as u64
+            + std::mem::size_of_val(&self.depth_dimensions) as u64
     }
     fn fill_buffer(&self, render_core_context: &RenderCoreContext, buffer: &mut GpuBuffer) {
         buffer.add_to_gpu_buffer(render_core_context, &[self.view]);
+        buffer.add_to_gpu_buffer(render_core_context, &[self.previous_view_proj]);
         buffer.add_to_gpu_buffer(render_core_context, &[self.mesh_flags]);
-        buffer.add_to_gpu_buffer(render_core_context, &[self._padding]);
+        buffer.add_to_gpu_buffer(render_core_context, &[self.hi_z_levels_count]);
+        buffer.add_to_gpu_buffer(render_core_context, &[self.depth_dimensions]);
+    }
+}
+
+declare_as_binding_vector!(VecHiZ, f32);
+
+//The levels are dispatched one after the other in the same command buffer,
+//so each of them is bound with its own uniform to know which level it writes
+struct HiZLevel {
+    is_dirty: bool,
+    level: u32,
+}
+
+impl HiZLevel {
+    fn new(level: u32) -> Self {
+        Self {
+            is_dirty: true,
+            level,
+        }
     }
 }
 
+impl AsBinding for HiZLevel {
+    fn is_dirty(&self) -> bool {
+        self.is_dirty
+    }
+    fn set_dirty(&mut self, is_dirty: bool) {
+        self.is_dirty = is_dirty;
+    }
+    fn size(&self) -> u64 {
+        std::mem::size_of_val(&self.level) as u64
+    }
+    fn fill_buffer(&self, render_core_context: &RenderCoreContext, buffer: &mut GpuBuffer) {
+        buffer.add_to_gpu_buffer(render_core_context, &[self.level]);
+    }
+}
+
+//Pyramid of the farthest depth of the previous frame, stored level after level in one buffer.
+//Level 0 is half the resolution of the depth buffer and each texel of a level covers
+//the 2x2 texels of the level below, up to a single texel
+#[derive(Default)]
+struct HiZ {
+    dimensions: [u32; 2],
+    view_proj: Option<[[f32; 4]; 4]>,
+}
+
+impl HiZ {
+    //Returns the view projection the depth of the previous frame was rendered with,
+    //none when there is no history to test against
+    fn next_frame(
+        &mut self,
+        view_proj: [[f32; 4]; 4],
+        dimensions: [u32; 2],
+    ) -> Option<[[f32; 4]; 4]> {
+        if self.dimensions != dimensions {
+            self.dimensions = dimensions;
+            self.view_proj = None;
+        }
+        self.view_proj.replace(view_proj)
+    }
+    fn reset(&mut self) {
+        self.view_proj = None;
+    }
+    fn level_size(&self, level: u32) -> [u32; 2] {
+        let texel_size = 2u32 << level;
+        self.dimensions.map(|d| d.div_ceil(texel_size).max(1))
+    }
+    fn levels_count(&self) -> u32 {
+        if self.dimensions.contains(&0) {
+            return 0;
+        }
+        let mut level = 0;
+        while self.level_size(level) != [1, 1] {
+            level += 1;
+        }
+        level + 1
+    }
+    fn level_offset(&self, level: u32) -> usize {
+        (0..level)
+            .map(|l| {
+                let size = self.level_size(l);
+                (size[0] * size[1]) as usize
+            })
+            .sum()
+    }
+    fn len(&self) -> usize {
+        self.level_offset(self.levels_count())
+    }
+    //Same reduction of compute_hi_z.wgsl
+    #[allow(dead_code)]
+    fn build(&self, depth: &[f32]) -> Vec<f32> {
+        let mut pyramid = vec![0.; self.len()];
+        let mut source_size = self.dimensions;
+        let mut source_offset = 0;
+        for level in 0..self.levels_count() {
+            let size = self.level_size(level);
+            let offset = self.level_offset(level);
+            for y in 0..size[1] {
+                for x in 0..size[0] {
+                    let mut farthest_depth = 0f32;
+                    for i in 0..4 {
+                        let source_x = (x * 2 + (i & 1)).min(source_size[0] - 1);
+                        let source_y = (y * 2 + (i >> 1)).min(source_size[1] - 1);
+                        let index = (source_y * source_size[0] + source_x) as usize;
+                        farthest_depth = farthest_depth.max(if level == 0 {
+                            depth[index]
+                        } else {
+                            pyramid[source_offset + index]
+                        });
+                    }
+                    pyramid[offset + (y * size[0] + x) as usize] = farthest_depth;
+                }
+            }
+            source_size = size;
+            source_offset = offset;
+        }
+        pyramid
+    }
+    //Same test of is_box_occluded in compute_culling.wgsl
+    #[allow(dead_code)]
+    fn is_occluded(
+        &self,
+        pyramid: &[f32],
+        view_proj: &Matrix4,
+        min: Vector3,
+        max: Vector3,
+    ) -> bool {
+        let levels_count = self.levels_count();
+        if levels_count == 0 {
+            return false;
+        }
+        let mut uv_min = [1f32; 2];
+        let mut uv_max = [0f32; 2];
+        let mut nearest_depth = 1f32;
+        for i in 0..8 {
+            let corner = Vector4::new(
+                if i & 1 != 0 { max.x } else { min.x },
+                if i & 2 != 0 { max.y } else { min.y },
+                if i & 4 != 0 { max.z } else { min.z },
+                1.,
+            );
+            let clip = *view_proj * corner;
+            if clip.w <= 0. {
+                return false;
+            }
+            let uv = [clip.x / clip.w * 0.5 + 0.5, 0.5 - clip.y / clip.w * 0.5];
+            for axis in 0..2 {
+                uv_min[axis] = uv_min[axis].min(uv[axis]);
+                uv_max[axis] = uv_max[axis].max(uv[axis]);
+            }
+            nearest_depth = nearest_depth.min(clip.z / clip.w);
+        }
+        if nearest_depth <= 0. {
+            return false;
+        }
+        let pixels_min = [0, 1].map(|a| uv_min[a].clamp(0., 1.) * self.dimensions[a] as f32);
+        let pixels_max = [0, 1].map(|a| uv_max[a].clamp(0., 1.) * self.dimensions[a] as f32);
+        let extent = (pixels_max[0] - pixels_min[0]).max(pixels_max[1] - pixels_min[1]);
+        let level = ((extent.max(1.).log2().ceil() - 1.).max(0.) as u32).min(levels_count - 1);
+        let size = self.level_size(level);
+        let offset = self.level_offset(level);
+        let texel_size = (2u32 << level) as f32;
+        let texel_min = [0, 1].map(|a| ((pixels_min[a] / texel_size) as u32).min(size[a] - 1));
+        let texel_max = [0, 1].map(|a| ((pixels_max[a] / texel_size) as u32).min(size[a] - 1));
+        let mut farthest_depth = 0f32;
+        for y in texel_min[1]..=texel_max[1] {
+            for x in texel_min[0]..=texel_max[0] {
+                farthest_depth = farthest_depth.max(pyramid[offset + (y * size[0] + x) as usize]);
+            }
+        }
+        nearest_depth > farthest_depth
+    }
+}
+
+//Same frustum and cone tests of compute_culling.wgsl, the occlusion one excluded
+#[allow(dead_code)]
+fn is_meshlet_visible(
+    view: &Matrix4,
+    proj: &Matrix4,
+    mesh: &DrawMesh,
+    node: &DrawBHVNode,
+    cone: &ConeCulling,
+    materials: &[DrawMaterial],
+) -> bool {
+    let orientation = Vector3::from([
+        mesh.orientation[0],
+        mesh.orientation[1],
+        mesh.orientation[2],
+    ]);
+    let rotate =
+        |v: Vector3| v + 2. * orientation.cross(orientation.cross(v) + mesh.orientation[3] * v);
+    let transform = |v: [f32; 3]| {
+        let v = rotate(v.into());
+        Vector3::new(
+            v.x * mesh.scale[0] + mesh.position[0],
+            v.y * mesh.scale[1] + mesh.position[1],
+            v.z * mesh.scale[2] + mesh.position[2],
+        )
+    };
+    //Bounds of all the transformed corners, a rotation can swap min and max
+    let (min, max) = (1..8usize)
+        .map(|i| {
+            transform([0, 1, 2].map(|a| {
+                if i & (1 << a) != 0 {
+                    node.max[a]
+                } else {
+                    node.min[a]
+                }
+            }))
+        })
+        .fold(
+            (transform(node.min), transform(node.min)),
+            |(min, max), corner| {
+                use inox_math::VecBase;
+                (VecBase::min(min, corner), VecBase::max(max, corner))
+            },
+        );
+    let d = (max - min) * 0.5;
+    let center = min + d;
+    let radius = d.magnitude();
+
+    let mvp = proj * view;
+    let row = |r: usize| Vector4::new(mvp[0][r], mvp[1][r], mvp[2][r], mvp[3][r]);
+    let frustum = [
+        row(3) + row(0),
+        row(3) - row(0),
+        row(3) + row(1),
+        row(3) - row(1),
+    ]
+    .map(|plane| plane / plane.truncate().magnitude());
+    let distance = |plane: &Vector4, p: Vector3| plane.truncate().dot(p) + plane.w;
+    if frustum
+        .iter()
+        .any(|plane| distance(plane, center) + radius <= 0.)
+    {
+        return false;
+    }
+    let points = (0..8).map(|i| {
+        Vector3::new(
+            if i & 1 != 0 { max.x } else { min.x },
+            if i & 2 != 0 { max.y } else { min.y },
+            if i & 4 != 0 { max.z } else { min.z },
+        )
+    });
+    if !frustum
+        .iter()
+        .any(|plane| points.clone().any(|p| distance(plane, p) > 0.))
+    {
+        return false;
+    }
+
+    let is_double_sided = mesh.material_index >= 0
+        && materials[mesh.material_index as usize].flags & u32::from(MaterialFlags::DoubleSided)
+            != 0;
+    let [x, y, z, cone_cutoff] = unpack_cone_axis_cutoff(cone.cone_axis_cutoff);
+    let cone_axis = rotate(Vector3::new(x, y, z));
+    let camera_position = -Vector3::new(
+        view[0].truncate().dot(view[3].truncate()),
+        view[1].truncate().dot(view[3].truncate()),
+        view[2].truncate().dot(view[3].truncate()),
+    );
+    let direction = center - camera_position;
+    is_double_sided || direction.dot(cone_axis) < cone_cutoff * direction.magnitude() + radius
+}
+
 pub struct CullingPass {
     compute_pass: Resource<ComputePass>,
     compact_pass: Resource<ComputePass>,
+    hi_z_pass: Resource<ComputePass>,
     binding_data: BindingData,
     constant_data: ConstantDataRw,
     commands: CommandsBuffer,
@@ -81,8 +396,15 @@ pub struct CullingPass {
     meshlets: MeshletsBuffer,
     meshlets_culling: MeshletsCullingBuffer,
     bhv: BHVBuffer,
+    materials: MaterialsBuffer,
     culling_data: CullingData,
     culling_result: CullingResults,
+    workgroup_size: u32,
+    max_workgroup_size: u32,
+    hi_z: HiZ,
+    hi_z_buffer: VecHiZ,
+    hi_z_levels: Vec<HiZLevel>,
+    depth_texture: Handle<Texture>,
     listener: Listener,
     update_camera: bool,
 }
@@ -117,25 +439,41 @@ impl Pass for CullingPass {
             name: COMPACTION_PASS_NAME.to_string(),
             pipelines: vec![PathBuf::from(COMPACTION_PIPELINE)],
         };
+        let hi_z_data = ComputePassData {
+            name: HI_Z_PASS_NAME.to_string(),
+            pipelines: vec![PathBuf::from(HI_Z_PIPELINE)],
+        };
 
         let listener = Listener::new(context.message_hub());
         listener.register::<CullingEvent>();
 
-        Self {
+        let limits = render_context.core.device.limits();
+        let max_workgroup_size = limits
+            .max_compute_invocations_per_workgroup
+            .min(limits.max_compute_workgroup_size_x);
+
+        let mut pass = Self {
             compute_pass: ComputePass::new_resource(
                 context.shared_data(),
                 context.message_hub(),
-                generate_random_uid(),
+                generate_random_resource_uid(),
                 &compute_data,
                 None,
             ),
             compact_pass: ComputePass::new_resource(
                 context.shared_data(),
                 context.message_hub(),
-                generate_random_uid(),
+                generate_random_resource_uid(),
                 &compact_data,
                 None,
             ),
+            hi_z_pass: ComputePass::new_resource(
+                context.shared_data(),
+                context.message_hub(),
+                generate_random_resource_uid(),
+                &hi_z_data,
+                None,
+            ),
             constant_data: render_context.constant_data.clone(),
             commands: render_context.render_buffers.commands.clone(),
             meshes: render_context.render_buffers.meshes.clone(),
@@ -143,12 +481,21 @@ impl Pass for CullingPass {
             meshlets: render_context.render_buffers.meshlets.clone(),
             meshlets_culling: render_context.render_buffers.meshlets_culling.clone(),
             bhv: render_context.render_buffers.bhv.clone(),
+            materials: render_context.render_buffers.materials.clone(),
             binding_data: BindingData::new(render_context, CULLING_PASS_NAME),
             culling_data: CullingData::default(),
             culling_result: render_context.render_buffers.culling_result.clone(),
+            workgroup_size: DEFAULT_CULLING_WORKGROUP_SIZE,
+            max_workgroup_size,
+            hi_z: HiZ::default(),
+            hi_z_buffer: VecHiZ::default(),
+            hi_z_levels: vec![HiZLevel::new(0)],
+            depth_texture: None,
             listener,
             update_camera: true,
-        }
+        };
+        pass.apply_workgroup_size(preferred_culling_workgroup_size(max_workgroup_size));
+        pass
     }
     fn init(&mut self, render_context: &RenderContext) {
         inox_profiler::scoped_profile!("compute_culling_pass::init");
@@ -174,6 +521,8 @@ impl Pass for CullingPass {
             }
         }
 
+        self.update_hi_z();
+
         let draw_command_type = self.draw_commands_type();
 
         if let Some(commands) = self.commands.write().unwrap().get_mut(&mesh_flags) {
@@ -184,9 +533,10 @@ impl Pass for CullingPass {
             commands.counter.count = 0;
 
             let num_meshlets = self.meshlets.read().unwrap().item_count();
-            let count = ((num_meshlets as u32 + NUM_COMMANDS_PER_GROUP - 1)
-                / NUM_COMMANDS_PER_GROUP) as usize;
-            self.culling_result.write().unwrap().set(vec![0u32; count]);
+            self.culling_result
+                .write()
+                .unwrap()
+                .set(vec![0u32; culling_result_len(num_meshlets)]);
 
             self.binding_data
                 .add_uniform_buffer(
@@ -259,6 +609,16 @@ impl Pass for CullingPass {
                         ..Default::default()
                     },
                 )
+                .add_storage_buffer(
+                    &mut *self.materials.write().unwrap(),
+                    Some("Materials"),
+                    BindingInfo {
+                        group_index: 0,
+                        binding_index: 7,
+                        stage: ShaderStage::Compute,
+                        ..Default::default()
+                    },
+                )
                 .add_storage_buffer(
                     &mut commands.counter,
                     Some("Counter"),
@@ -288,8 +648,43 @@ impl Pass for CullingPass {
                         stage: ShaderStage::Compute,
                         flags: BindingFlags::ReadWrite | BindingFlags::Indirect,
                     },
+                )
+                .add_storage_buffer(
+                    &mut self.hi_z_buffer,
+                    Some("HiZ"),
+                    BindingInfo {
+                        group_index: 1,
+                        binding_index: 3,
+                        stage: ShaderStage::Compute,
+                        flags: BindingFlags::ReadWrite,
+                    },
                 );
 
+            if let Some(depth_texture) = &self.depth_texture {
+                self.binding_data
+                    .add_texture(
+                        depth_texture.id(),
+                        BindingInfo {
+                            group_index: 2,
+                            binding_index: 0,
+                            stage: ShaderStage::Compute,
+                            ..Default::default()
+                        },
+                    )
+                    .add_uniform_buffer(
+                        &mut self.hi_z_levels[0],
+                        Some("HiZLevel"),
+                        BindingInfo {
+                            group_index: 2,
+                            binding_index: 1,
+                            stage: ShaderStage::Compute,
+                            ..Default::default()
+                        },
+                    );
+                let mut pass = self.hi_z_pass.get_mut();
+                pass.init(render_context, &mut self.binding_data);
+            }
+
             let mut pass = self.compute_pass.get_mut();
             pass.init(render_context, &mut self.binding_data);
 
@@ -316,7 +711,39 @@ impl Pass for CullingPass {
             if commands.commands.is_empty() {
                 return;
             }
-            let count = (num_meshlets as u32 + NUM_COMMANDS_PER_GROUP - 1) / NUM_COMMANDS_PER_GROUP;
+            let count = culling_dispatch_count(num_meshlets, self.workgroup_size);
+
+            //The levels read the ones below, so each of them is a dispatch on its own
+            let pass = self.hi_z_pass.get();
+            for level in 0..self.culling_data.hi_z_levels_count {
+                let size = self.hi_z.level_size(level);
+                self.binding_data.add_uniform_buffer(
+                    &mut self.hi_z_levels[level as usize],
+                    Some("HiZLevel"),
+                    BindingInfo {
+                        group_index: 2,
+                        binding_index: 1,
+                        stage: ShaderStage::Compute,
+                        ..Default::default()
+                    },
+                );
+                let mut hi_z_pass =
+                    pass.begin(render_context, &mut self.binding_data, command_buffer);
+                {
+                    inox_profiler::gpu_scoped_profile!(
+                        &mut hi_z_pass,
+                        &render_context.core.device,
+                        "compute_hi_z_pass",
+                    );
+                    pass.dispatch(
+                        render_context,
+                        hi_z_pass,
+                        size[0].div_ceil(HI_Z_WORKGROUP_SIZE),
+                        size[1].div_ceil(HI_Z_WORKGROUP_SIZE),
+                        1,
+                    );
+                }
+            }
 
             let pass = self.compute_pass.get();
             let mut compute_pass =
@@ -346,6 +773,73 @@ impl Pass for CullingPass {
 }
 
 impl CullingPass {
+    pub fn workgroup_size(&self) -> u32 {
+        self.workgroup_size
+    }
+    //Tunes the meshlets culled and compacted by each workgroup for the platform
+    pub fn set_workgroup_size(&mut self, size: u32) -> Result<&mut Self, CullingError> {
+        let size = validate_culling_workgroup_size(size, self.max_workgroup_size)?;
+        self.apply_workgroup_size(size);
+        Ok(self)
+    }
+    fn apply_workgroup_size(&mut self, size: u32) {
+        self.workgroup_size = size;
+        self.compute_pass.get_mut().set_workgroup_size([size, 1, 1]);
+        self.compact_pass.get_mut().set_workgroup_size([size, 1, 1]);
+    }
+    //The depth is read before being rendered again, so it's the one of the previous frame
+    //and it's not a dependency of the pass in the render graph
+    pub fn set_depth_texture(&mut self, texture: &Resource<Texture>) -> &mut Self {
+        self.depth_texture = Some(texture.clone());
+        self.hi_z.reset();
+        self
+    }
+    fn update_hi_z(&mut self) {
+        let depth_dimensions = self
+            .depth_texture
+            .as_ref()
+            .map(|t| t.get().dimensions())
+            .unwrap_or_default();
+        let depth_dimensions = [depth_dimensions.0, depth_dimensions.1];
+
+        //A frozen camera keeps culling with a view that is not the one of the depth
+        let previous_view_proj = if self.update_camera && self.depth_texture.is_some() {
+            let constant_data = self.constant_data.read().unwrap();
+            let view_proj =
+                Matrix4::from(constant_data.proj()) * Matrix4::from(constant_data.view());
+            self.hi_z
+                .next_frame(matrix4_to_array(view_proj), depth_dimensions)
+        } else {
+            self.hi_z.reset();
+            None
+        };
+
+        //Without a previous frame every meshlet passes the occlusion test
+        let levels_count = previous_view_proj.map_or(0, |_| self.hi_z.levels_count());
+        if self.culling_data.hi_z_levels_count != levels_count
+            || self.culling_data.depth_dimensions != depth_dimensions
+        {
+            self.culling_data.hi_z_levels_count = levels_count;
+            self.culling_data.depth_dimensions = depth_dimensions;
+            self.culling_data.set_dirty(true);
+        }
+        if let Some(previous_view_proj) = previous_view_proj {
+            if self.culling_data.previous_view_proj != previous_view_proj {
+                self.culling_data.previous_view_proj = previous_view_proj;
+                self.culling_data.set_dirty(true);
+            }
+        }
+
+        let len = self.hi_z.len().max(1);
+        if self.hi_z_buffer.size() != (len * std::mem::size_of::<f32>()) as u64 {
+            self.hi_z_buffer.set(vec![0.; len]);
+        }
+        //Buffers are bound by address, so the levels are all created again with their count
+        let pyramid_levels_count = self.hi_z.levels_count().max(1);
+        if self.hi_z_levels.len() != pyramid_levels_count as usize {
+            self.hi_z_levels = (0..pyramid_levels_count).map(HiZLevel::new).collect();
+        }
+    }
     fn process_messages(&mut self) {
         self.listener
             .process_messages(|event: &CullingEvent| match event {
@@ -358,3 +852,483 @@ impl CullingPass {
             });
     }
 }
+
+#[allow(dead_code)]
+fn test_hi_z_occlusion() {
+    let view_proj = inox_math::perspective(inox_math::Degrees::new(60.), 1., 0.1, 100.);
+    let dimensions = [64, 64];
+    let behind_quad = (Vector3::new(-3., -0.5, -6.), Vector3::new(-2., 0.5, -5.));
+    let beside_quad = (Vector3::new(2., -0.5, -6.), Vector3::new(3., 0.5, -5.));
+    let before_quad = (
+        Vector3::new(-0.3, -0.1, -0.6),
+        Vector3::new(-0.2, 0.1, -0.5),
+    );
+
+    //First frame has no depth to test against
+    let mut hi_z = HiZ::default();
+    assert!(hi_z
+        .next_frame(matrix4_to_array(view_proj), dimensions)
+        .is_none());
+
+    //Near quad covering the left half of the screen
+    let near = view_proj * Vector4::new(0., 0., -1., 1.);
+    let near_depth = near.z / near.w;
+    let mut depth = vec![1f32; (dimensions[0] * dimensions[1]) as usize];
+    depth.chunks_mut(dimensions[0] as usize).for_each(|row| {
+        row[..dimensions[0] as usize / 2].fill(near_depth);
+    });
+
+    let previous_view_proj = hi_z.next_frame(matrix4_to_array(view_proj), dimensions);
+    assert_eq!(previous_view_proj, Some(matrix4_to_array(view_proj)));
+    assert_eq!(hi_z.levels_count(), 6);
+    let pyramid = hi_z.build(&depth);
+    assert_eq!(pyramid.len(), hi_z.len());
+    assert_eq!(*pyramid.last().unwrap(), 1.);
+
+    let view_proj = Matrix4::from(previous_view_proj.unwrap());
+    assert!(hi_z.is_occluded(&pyramid, &view_proj, behind_quad.0, behind_quad.1));
+    assert!(!hi_z.is_occluded(&pyramid, &view_proj, beside_quad.0, beside_quad.1));
+    assert!(!hi_z.is_occluded(&pyramid, &view_proj, before_quad.0, before_quad.1));
+
+    //A different resolution drops the history
+    assert!(hi_z
+        .next_frame(matrix4_to_array(view_proj), [32, 32])
+        .is_none());
+}
+
+//Meshlets made of a unit box each, with the camera in the given position looking down -z
+#[allow(dead_code)]
+struct CullingScene {
+    view: Matrix4,
+    proj: Matrix4,
+    meshes: Vec<DrawMesh>,
+    meshlets: Vec<crate::DrawMeshlet>,
+    cones: Vec<ConeCulling>,
+    bhv: Vec<DrawBHVNode>,
+    materials: Vec<DrawMaterial>,
+}
+
+#[allow(dead_code)]
+impl CullingScene {
+    fn new(camera_position: Vector3) -> Self {
+        let mut double_sided = DrawMaterial::default();
+        double_sided.flags = MaterialFlags::DoubleSided.into();
+        Self {
+            view: Matrix4::from_translation(-camera_position),
+            proj: inox_math::perspective(inox_math::Degrees::new(60.), 1., 0.1, 100.),
+            meshes: Vec::new(),
+            meshlets: Vec::new(),
+            cones: Vec::new(),
+            bhv: Vec::new(),
+            materials: vec![double_sided],
+        }
+    }
+    fn add_meshlet(
+        &mut self,
+        position: [f32; 3],
+        cone_axis: Vector3,
+        cone_cutoff: f32,
+        is_double_sided: bool,
+    ) -> &mut Self {
+        let index = self.meshes.len() as u32;
+        self.meshes.push(DrawMesh {
+            material_index: if is_double_sided { 0 } else { INVALID_INDEX },
+            bhv_index: index,
+            position,
+            meshlets_offset: index,
+            meshlets_count: 1,
+            flags: (MeshFlags::Visible | MeshFlags::Opaque).into(),
+            ..Default::default()
+        });
+        self.meshlets.push(crate::DrawMeshlet {
+            mesh_index: index,
+            indices_offset: 0,
+            indices_count: 3,
+            bvh_index: 0,
+        });
+        self.cones.push(ConeCulling {
+            center: [0.; 3],
+            cone_axis_cutoff: crate::utils::pack_cone_axis_cutoff(cone_axis, cone_cutoff),
+        });
+        self.bhv.push(DrawBHVNode {
+            min: [-0.5; 3],
+            max: [0.5; 3],
+            miss: INVALID_INDEX,
+            reference: 0,
+        });
+        self
+    }
+    fn cpu_visibility(&self) -> Vec<bool> {
+        self.meshlets
+            .iter()
+            .enumerate()
+            .map(|(i, meshlet)| {
+                let mesh = &self.meshes[meshlet.mesh_index as usize];
+                is_meshlet_visible(
+                    &self.view,
+                    &self.proj,
+                    mesh,
+                    &self.bhv[(mesh.bhv_index + meshlet.bvh_index) as usize],
+                    &self.cones[i],
+                    &self.materials,
+                )
+            })
+            .collect()
+    }
+}
+
+#[allow(dead_code)]
+fn test_culling_reference() {
+    let facing_camera = Vector3::new(0., 0., 1.);
+    //Negative components check the sign of the packed axis as well
+    let facing_away = Vector3::new(-0.3, -0.4, -0.8).normalize();
+    //Away from the origin, the cones have to be tested from the camera position
+    let camera = Vector3::new(2., 1., 10.);
+    let in_front = [camera.x, camera.y, camera.z - 5.];
+    let outside = [camera.x + 50., camera.y, camera.z - 5.];
+    let behind = [camera.x, camera.y, camera.z + 5.];
+    let meshlets = [
+        (in_front, facing_camera, false),
+        (in_front, facing_away, false),
+        (outside, facing_camera, false),
+        (behind, facing_camera, false),
+        (in_front, facing_away, true),
+    ];
+    let expected = vec![true, false, false, false, true];
+
+    let mut scene = CullingScene::new(camera);
+    meshlets
+        .iter()
+        .for_each(|(position, cone_axis, is_double_sided)| {
+            scene.add_meshlet(*position, *cone_axis, 0.5, *is_double_sided);
+        });
+    assert_eq!(scene.cpu_visibility(), expected);
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let Some(mut headless) = HeadlessCulling::new() else {
+            println!("No GPU adapter available, culling checked only on CPU");
+            return;
+        };
+        headless.set_camera(scene.view, scene.proj);
+        meshlets
+            .iter()
+            .for_each(|(position, cone_axis, is_double_sided)| {
+                let rect = Vector4::new(
+                    position[0] - 0.5,
+                    position[1] - 0.5,
+                    position[0] + 0.5,
+                    position[1] + 0.5,
+                );
+                headless.add_quad(rect, position[2], Some((*cone_axis, 0.5)), *is_double_sided);
+            });
+        //First frames load the pipelines and upload the meshes
+        (0..10).for_each(|_| headless.frame());
+        for workgroup_size in [DEFAULT_CULLING_WORKGROUP_SIZE, 4, 64] {
+            headless.set_culling_workgroup_size(workgroup_size);
+            //A new workgroup size recompiles the shaders of the pass
+            (0..3).for_each(|_| headless.frame());
+            //Only frustum and cones, without the occlusion of the previous frames
+            headless.reset_history();
+            headless.frame();
+            assert_eq!(headless.visibility(), expected);
+        }
+        headless.uninit();
+    }
+}
+
+#[allow(dead_code)]
+fn test_culling_workgroup_size() {
+    let max_size = 256u32;
+    for num_meshlets in [0, 1, 31, 32, 33, 1000] {
+        let words = culling_result_len(num_meshlets);
+        assert!(words * CULLING_RESULT_BITS as usize >= num_meshlets);
+        assert!(words == 0 || (words - 1) * (CULLING_RESULT_BITS as usize) < num_meshlets);
+        for size in (0..=max_size.trailing_zeros()).map(|i| 1u32 << i) {
+            assert_eq!(validate_culling_workgroup_size(size, max_size), Ok(size));
+            //The results stay packed the same way whatever the size of the workgroups
+            assert_eq!(culling_result_len(num_meshlets), words);
+            let count = culling_dispatch_count(num_meshlets, size) as usize;
+            assert!(count * size as usize >= num_meshlets);
+            assert!(count == 0 || (count - 1) * (size as usize) < num_meshlets);
+        }
+    }
+
+    assert_eq!(
+        validate_culling_workgroup_size(0, max_size),
+        Err(CullingError::NotPowerOfTwo(0))
+    );
+    assert_eq!(
+        validate_culling_workgroup_size(48, max_size),
+        Err(CullingError::NotPowerOfTwo(48))
+    );
+    assert_eq!(
+        validate_culling_workgroup_size(512, max_size),
+        Err(CullingError::ExceedsDeviceLimit {
+            size: 512,
+            max_size
+        })
+    );
+
+    assert_eq!(
+        preferred_culling_workgroup_size(1024),
+        DEFAULT_CULLING_WORKGROUP_SIZE
+    );
+    assert_eq!(preferred_culling_workgroup_size(48), 32);
+    assert_eq!(preferred_culling_workgroup_size(24), 16);
+    assert_eq!(preferred_culling_workgroup_size(0), 1);
+
+    let code = "@compute\n@workgroup_size(32, 1, 1)\nfn main() {}";
+    assert_eq!(
+        crate::wgsl_with_workgroup_size(code, [64, 1, 1]),
+        "@compute\n@workgroup_size(64, 1, 1)\nfn main() {}"
+    );
+}
+
+//Headless renderer drawing the depth of its meshes in a VisibilityBufferPass
+//after culling them with the CullingPass
+#[cfg(not(target_arch = "wasm32"))]
+#[allow(dead_code)]
+struct HeadlessCulling {
+    context: ContextRc,
+    renderer: crate::RendererRw,
+    update_system: crate::UpdateSystem,
+    rendering_system: crate::RenderingSystem,
+    meshes: Vec<Resource<crate::Mesh>>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[allow(dead_code)]
+impl HeadlessCulling {
+    //None when the system has no adapter able to render
+    fn new() -> Option<Self> {
+        use crate::{
+            OutputRenderPass, RenderTarget, Renderer, RenderingSystem, TextureFormat, UpdateSystem,
+            VisibilityBufferPass, DEFAULT_HEIGHT, DEFAULT_WIDTH,
+        };
+        use inox_core::{Context, System};
+
+        let context = ContextRc::new(Context::default());
+        let context_rc = context.clone();
+        let renderer = Renderer::new_headless(&context, move |renderer| {
+            let culling_pass = CullingPass::create(&context_rc, &renderer.render_context());
+            renderer.add_pass(culling_pass, true);
+            let visibility_pass =
+                VisibilityBufferPass::create(&context_rc, &renderer.render_context());
+            visibility_pass
+                .render_pass()
+                .get_mut()
+                .add_render_target(RenderTarget::Texture {
+                    width: DEFAULT_WIDTH,
+                    height: DEFAULT_HEIGHT,
+                    format: TextureFormat::Rgba8UnormSrgb,
+                    read_back: false,
+                })
+                .add_depth_target(RenderTarget::Texture {
+                    width: DEFAULT_WIDTH,
+                    height: DEFAULT_HEIGHT,
+                    format: TextureFormat::Depth32Float,
+                    read_back: false,
+                });
+            if let Some(depth_texture) = visibility_pass.render_pass().get().depth_texture() {
+                if let Some(culling_pass) = renderer.pass_mut::<CullingPass>() {
+                    culling_pass.set_depth_texture(depth_texture);
+                }
+            }
+            renderer.add_pass(visibility_pass, true);
+        })?;
+        let mut update_system = UpdateSystem::new(renderer.clone(), &context);
+        let mut rendering_system = RenderingSystem::new(renderer.clone(), &context);
+        update_system.init();
+        rendering_system.init();
+        Some(Self {
+            context,
+            renderer,
+            update_system,
+            rendering_system,
+            meshes: Vec::new(),
+        })
+    }
+    //Quad on the xy plane facing +z, its only meshlet has the given cone
+    fn add_quad(
+        &mut self,
+        rect: Vector4,
+        z: f32,
+        cone: Option<(Vector3, f32)>,
+        is_double_sided: bool,
+    ) -> &mut Self {
+        let mut mesh_data = crate::create_quad(rect, z);
+        if let Some((cone_axis, cone_cutoff)) = cone {
+            let meshlet = &mut mesh_data.meshlets[0];
+            meshlet.cone_center = (meshlet.aabb_min + meshlet.aabb_max) * 0.5;
+            meshlet.cone_axis = cone_axis;
+            meshlet.cone_angle = cone_cutoff;
+        }
+        let id = generate_random_resource_uid();
+        let mesh = self.context.shared_data().add_resource(
+            self.context.message_hub(),
+            id,
+            crate::Mesh::new(id, self.context.shared_data(), self.context.message_hub()),
+        );
+        if is_double_sided {
+            let material_data = crate::MaterialData {
+                double_sided: true,
+                ..Default::default()
+            };
+            let material = crate::Material::new_resource(
+                self.context.shared_data(),
+                self.context.message_hub(),
+                generate_random_resource_uid(),
+                &material_data,
+                None,
+            );
+            //The flags of the material are uploaded on its changes
+            material.get().mark_as_dirty();
+            mesh.get_mut().set_material(material);
+        }
+        mesh.get_mut().set_mesh_data(mesh_data);
+        self.meshes.push(mesh);
+        self
+    }
+    fn set_last_matrix(&mut self, matrix: Matrix4) -> &mut Self {
+        if let Some(mesh) = self.meshes.last() {
+            mesh.get_mut().set_matrix(matrix);
+        }
+        self
+    }
+    fn set_camera(&self, view: Matrix4, proj: Matrix4) {
+        let view_resource = crate::View::find_from_view_index(self.context.shared_data(), 0);
+        view_resource
+            .unwrap()
+            .get_mut()
+            .update_view(view)
+            .update_proj(proj);
+    }
+    fn frame(&mut self) {
+        use inox_core::{JobHandlerTrait, System};
+
+        self.context.message_hub().flush();
+        self.update_system.run();
+        self.rendering_system.run();
+        self.context.job_handler().execute_all_jobs();
+        self.context
+            .shared_data()
+            .flush_resources(self.context.message_hub());
+    }
+    fn set_culling_workgroup_size(&self, workgroup_size: u32) {
+        let mut renderer = self.renderer.write().unwrap();
+        let culling_pass = renderer.pass_mut::<CullingPass>().unwrap();
+        culling_pass.set_workgroup_size(workgroup_size).unwrap();
+    }
+    //Forgets the depth of the previous frames, as on the first frame
+    fn reset_history(&self) {
+        let mut renderer = self.renderer.write().unwrap();
+        renderer.pass_mut::<CullingPass>().unwrap().hi_z.reset();
+    }
+    //Culling result of the meshlets of the last frame, in the order the meshes have been added
+    fn visibility(&self) -> Vec<bool> {
+        use inox_resources::to_slice;
+
+        let renderer = self.renderer.read().unwrap();
+        let render_context = renderer.render_context();
+        let core = &render_context.core;
+        let id = render_context
+            .render_buffers
+            .culling_result
+            .read()
+            .unwrap()
+            .id();
+        let buffers = render_context.binding_data_buffer.buffers.read().unwrap();
+        let culling_result = buffers.get(&id).and_then(|b| b.gpu_buffer()).unwrap();
+        let read_back = core.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: culling_result.size(),
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let mut encoder = core
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        encoder.copy_buffer_to_buffer(culling_result, 0, &read_back, 0, read_back.size());
+        core.queue.submit(Some(encoder.finish()));
+        let slice = read_back.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |_| {});
+        core.device.poll(wgpu::Maintain::Wait);
+        let bits = to_slice::<u8, u32>(&slice.get_mapped_range()).to_vec();
+
+        let bits_count = CULLING_RESULT_BITS as usize;
+        let meshes = render_context.render_buffers.meshes.read().unwrap();
+        self.meshes
+            .iter()
+            .flat_map(|mesh| {
+                let draw_mesh = meshes.get(mesh.id()).unwrap();
+                let first = draw_mesh.meshlets_offset as usize;
+                (first..first + draw_mesh.meshlets_count as usize)
+                    .map(|i| bits[i / bits_count] & (1 << (i % bits_count)) != 0)
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+    fn uninit(mut self) {
+        use inox_core::System;
+
+        self.meshes.clear();
+        self.rendering_system.uninit();
+        self.update_system.uninit();
+    }
+}
+
+#[allow(dead_code)]
+fn test_hi_z_gpu_occlusion() {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        use inox_math::MatBase;
+
+        let Some(mut headless) = HeadlessCulling::new() else {
+            println!("No GPU adapter available, occlusion checked only on CPU");
+            return;
+        };
+        headless.set_camera(
+            Matrix4::default_identity(),
+            inox_math::perspective(inox_math::Degrees::new(60.), 1., 0.1, 100.),
+        );
+        //Near quad covering the left half of the screen, with a quad behind it
+        //and another one beside it
+        headless
+            .add_quad(Vector4::new(-4., -4., 0., 4.), -3., None, false)
+            .add_quad(Vector4::new(-5., -0.5, -4., 0.5), -10., None, false)
+            .add_quad(Vector4::new(4., -0.5, 5., 0.5), -10., None, false);
+        //First frames load the pipelines and upload the meshes
+        (0..10).for_each(|_| headless.frame());
+
+        //Without the depth of a previous frame everything passes the occlusion test
+        headless.reset_history();
+        headless.frame();
+        assert_eq!(headless.visibility(), vec![true, true, true]);
+        //The next frame tests against the depth drawn by the first one
+        headless.frame();
+        assert_eq!(headless.visibility(), vec![true, false, true]);
+
+        //A square rotated 45 degrees behind the edge of the near quad: the transformed
+        //min and max alone would span a vertical line hidden by it, while its corners
+        //stick out on the right half of the screen
+        headless
+            .add_quad(Vector4::new(-0.2, -0.2, 0.2, 0.2), 0., None, false)
+            .set_last_matrix(
+                Matrix4::from_translation(Vector3::new(-0.2, 0., -10.))
+                    * Matrix4::from_angle_z(inox_math::Degrees::new(45.)),
+            );
+        (0..10).for_each(|_| headless.frame());
+        assert_eq!(headless.visibility(), vec![true, false, true, true]);
+
+        headless.uninit();
+    }
+}
+
+#[test]
+fn test() {
+    test_hi_z_occlusion();
+    test_hi_z_gpu_occlusion();
+    test_culling_reference();
+    test_culling_workgroup_size();
+}
@@ -4,18 +4,19 @@ use crate::{
     AsBinding, BHVBuffer, BindingData, BindingFlags, BindingInfo, CommandBuffer, ComputePass,
     ComputePassData, ConstantDataRw, DrawCommandType, GpuBuffer, IndicesBuffer, LightsBuffer,
     MaterialsBuffer, MeshFlags, MeshesBuffer, MeshletsBuffer, OutputPass, Pass, RenderContext,
-    RenderCoreContext, ShaderStage, Texture, TextureFormat, TextureId, TextureUsage, TextureView,
-    TexturesBuffer, VertexColorsBuffer, VertexNormalsBuffer, VertexPositionsBuffer,
+    RenderCoreContext, ShaderStage, Texture, TextureAccess, TextureFormat, TextureId, TextureUsage,
+    TextureView, TexturesBuffer, VertexColorsBuffer, VertexNormalsBuffer, VertexPositionsBuffer,
     VertexUVsBuffer, VerticesBuffer, DEFAULT_HEIGHT, DEFAULT_WIDTH,
 };
 
 use inox_core::ContextRc;
 use inox_resources::{DataTypeResource, Handle, Resource};
-use inox_uid::{generate_random_uid, INVALID_UID};
+use inox_uid::{generate_random_resource_uid, INVALID_RESOURCE_UID};
 
 pub const COMPUTE_PBR_PIPELINE: &str = "pipelines/ComputePbr.compute_pipeline";
 pub const COMPUTE_PBR_PASS_NAME: &str = "ComputePbrPass";
-const COMPUTE_PBR_TEXTURE_FORMAT: TextureFormat = TextureFormat::Rgba8Unorm;
+//Radiance in HDR, clamped only when blitted on the screen
+const COMPUTE_PBR_TEXTURE_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
 
 #[derive(Default)]
 struct ComputePbrPassData {
@@ -96,7 +97,7 @@ impl Pass for ComputePbrPass {
             compute_pass: ComputePass::new_resource(
                 context.shared_data(),
                 context.message_hub(),
-                generate_random_uid(),
+                generate_random_resource_uid(),
                 &data,
                 None,
             ),
@@ -114,7 +115,7 @@ impl Pass for ComputePbrPass {
             vertex_normals: render_context.render_buffers.vertex_normals.clone(),
             vertex_uvs: render_context.render_buffers.vertex_uvs.clone(),
             binding_data: BindingData::new(render_context, COMPUTE_PBR_PASS_NAME),
-            visibility_buffer_id: INVALID_UID,
+            visibility_buffer_id: INVALID_RESOURCE_UID,
             data: ComputePbrPassData {
                 dimensions: [DEFAULT_WIDTH, DEFAULT_HEIGHT],
                 ..Default::default()
@@ -130,14 +131,12 @@ impl Pass for ComputePbrPass {
         }
 
         let resolution = self.render_target.as_ref().unwrap().get().dimensions();
-        if self.render_target.is_none()
-            && (self.data.dimensions[0] != resolution.0 || self.data.dimensions[1] != resolution.1)
-        {
+        if self.data.dimensions[0] != resolution.0 || self.data.dimensions[1] != resolution.1 {
             self.data.dimensions = [resolution.0, resolution.1];
             self.data.set_dirty(true);
         }
 
-        if self.visibility_buffer_id.is_nil()
+        if self.visibility_buffer_id == INVALID_RESOURCE_UID
             || self.textures.read().unwrap().is_empty()
             || self.meshes.read().unwrap().is_empty()
             || self.meshlets.read().unwrap().is_empty()
@@ -287,7 +286,7 @@ impl Pass for ComputePbrPass {
                     ..Default::default()
                 },
             )
-            .add_default_sampler(BindingInfo {
+            .add_material_samplers(BindingInfo {
                 group_index: 2,
                 binding_index: 0,
                 stage: ShaderStage::Compute,
@@ -330,7 +329,7 @@ impl Pass for ComputePbrPass {
     ) {
         inox_profiler::scoped_profile!("compute_pbr_pass::update");
 
-        if self.visibility_buffer_id.is_nil()
+        if self.visibility_buffer_id == INVALID_RESOURCE_UID
             || self.textures.read().unwrap().is_empty()
             || self.meshes.read().unwrap().is_empty()
             || self.meshlets.read().unwrap().is_empty()
@@ -360,6 +359,15 @@ impl Pass for ComputePbrPass {
             pass.dispatch(render_context, compute_pass, x, y, 1);
         }
     }
+    fn read_textures(&self) -> Vec<(TextureId, TextureAccess)> {
+        vec![(self.visibility_buffer_id, TextureAccess::Sampled)]
+    }
+    fn written_textures(&self) -> Vec<(TextureId, TextureAccess)> {
+        self.render_target
+            .iter()
+            .map(|t| (*t.id(), TextureAccess::Storage))
+            .collect()
+    }
 }
 
 impl OutputPass for ComputePbrPass {
@@ -4,13 +4,14 @@ use crate::{
     BHVBuffer, BindingData, BindingInfo, CommandBuffer, ConstantDataRw, DrawCommandType,
     DrawVertex, IndicesBuffer, MaterialsBuffer, MeshFlags, MeshesBuffer, MeshletsBuffer,
     OutputRenderPass, Pass, RenderContext, RenderPass, RenderPassBeginData, RenderPassData,
-    RenderTarget, ShaderStage, StoreOperation, TextureView, TexturesBuffer, VertexColorsBuffer,
-    VertexNormalsBuffer, VertexPositionsBuffer, VertexUVsBuffer, VerticesBuffer,
+    RenderTarget, ShaderStage, StoreOperation, TextureAccess, TextureId, TextureView,
+    TexturesBuffer, VertexColorsBuffer, VertexNormalsBuffer, VertexPositionsBuffer,
+    VertexUVsBuffer, VerticesBuffer,
 };
 
 use inox_core::ContextRc;
 use inox_resources::{DataTypeResource, Resource, ResourceTrait};
-use inox_uid::generate_random_uid;
+use inox_uid::generate_random_resource_uid;
 
 pub const GBUFFER_PIPELINE: &str = "pipelines/GBuffer.render_pipeline";
 pub const GBUFFER_PASS_NAME: &str = "GBufferPass";
@@ -69,7 +70,7 @@ impl Pass for GBufferPass {
             render_pass: RenderPass::new_resource(
                 context.shared_data(),
                 context.message_hub(),
-                generate_random_uid(),
+                generate_random_resource_uid(),
                 &data,
                 None,
             ),
@@ -206,7 +207,7 @@ impl Pass for GBufferPass {
                     ..Default::default()
                 },
             )
-            .add_default_sampler(BindingInfo {
+            .add_material_samplers(BindingInfo {
                 group_index: 2,
                 binding_index: 0,
                 stage: ShaderStage::Fragment,
@@ -252,6 +253,7 @@ impl Pass for GBufferPass {
             render_targets: render_targets.as_slice(),
             surface_view,
             command_buffer,
+            view_submission: render_context.current_view(),
         };
         let mut render_pass = pass.begin(&mut self.binding_data, &pipeline, render_pass_begin_data);
         {
@@ -263,6 +265,12 @@ impl Pass for GBufferPass {
             pass.indirect_indexed_draw(render_context, &buffers, draw_commands_type, render_pass);
         }
     }
+    fn read_textures(&self) -> Vec<(TextureId, TextureAccess)> {
+        self.render_pass.get().read_textures()
+    }
+    fn written_textures(&self) -> Vec<(TextureId, TextureAccess)> {
+        self.render_pass.get().written_textures()
+    }
 }
 
 impl OutputRenderPass for GBufferPass {
@@ -1,4 +1,5 @@
 pub use self::blit::*;
+pub use self::bloom::*;
 pub use self::compute_culling::*;
 pub use self::compute_pbr::*;
 pub use self::gbuffer::*;
@@ -6,10 +7,12 @@ pub use self::pass::*;
 pub use self::pbr::*;
 pub use self::raytracing_generate_ray::*;
 pub use self::raytracing_visibility::*;
+pub use self::transparent::*;
 pub use self::visibility::*;
 pub use self::wireframe::*;
 
 pub mod blit;
+pub mod bloom;
 pub mod compute_culling;
 pub mod compute_pbr;
 pub mod gbuffer;
@@ -17,5 +20,6 @@ pub mod pass;
 pub mod pbr;
 pub mod raytracing_generate_ray;
 pub mod raytracing_visibility;
+pub mod transparent;
 pub mod visibility;
 pub mod wireframe;
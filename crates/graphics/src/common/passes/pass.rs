@@ -2,7 +2,8 @@ use inox_core::ContextRc;
 use inox_resources::Resource;
 
 use crate::{
-    CommandBuffer, DrawCommandType, MeshFlags, RenderContext, RenderPass, TextureId, TextureView,
+    CommandBuffer, DrawCommandType, MeshFlags, RenderContext, RenderPass, TextureAccess, TextureId,
+    TextureView,
 };
 use downcast_rs::{impl_downcast, Downcast};
 
@@ -24,6 +25,18 @@ pub trait Pass: Downcast + Send + Sync + 'static {
         surface_view: &TextureView,
         command_buffer: &mut CommandBuffer,
     );
+    //Passes drawn once for every View in its viewport, the others are drawn after all the views
+    //on the whole surface
+    fn is_per_view(&self) -> bool {
+        true
+    }
+    //Textures declared through the pass setters, used by the RenderGraph to order the passes
+    fn read_textures(&self) -> Vec<(TextureId, TextureAccess)> {
+        Vec::new()
+    }
+    fn written_textures(&self) -> Vec<(TextureId, TextureAccess)> {
+        Vec::new()
+    }
 }
 
 pub trait OutputPass: Pass {
@@ -4,12 +4,12 @@ use crate::{
     BindingData, BindingInfo, CommandBuffer, ConstantDataRw, DrawCommandType, LightsBuffer,
     MaterialsBuffer, MeshFlags, MeshesBuffer, MeshletsBuffer, OutputRenderPass, Pass,
     RenderContext, RenderPass, RenderPassBeginData, RenderPassData, RenderTarget, ShaderStage,
-    StoreOperation, TextureId, TextureView, TexturesBuffer,
+    StoreOperation, TextureAccess, TextureId, TextureView, TexturesBuffer,
 };
 
 use inox_core::ContextRc;
 use inox_resources::{DataTypeResource, Resource, ResourceTrait};
-use inox_uid::{generate_random_uid, INVALID_UID};
+use inox_uid::{generate_random_resource_uid, INVALID_RESOURCE_UID};
 
 pub const PBR_PIPELINE: &str = "pipelines/PBR.render_pipeline";
 pub const PBR_PASS_NAME: &str = "PBRPass";
@@ -64,7 +64,7 @@ impl Pass for PBRPass {
             render_pass: RenderPass::new_resource(
                 context.shared_data(),
                 context.message_hub(),
-                generate_random_uid(),
+                generate_random_resource_uid(),
                 &data,
                 None,
             ),
@@ -76,15 +76,18 @@ impl Pass for PBRPass {
             meshlets: render_context.render_buffers.meshlets.clone(),
             binding_data: BindingData::new(render_context, PBR_PASS_NAME),
             gbuffer_textures: Vec::new(),
-            depth_texture: INVALID_UID,
+            depth_texture: INVALID_RESOURCE_UID,
         }
     }
     fn init(&mut self, render_context: &RenderContext) {
         inox_profiler::scoped_profile!("pbr_pass::init");
 
-        if self.gbuffer_textures.iter().any(|t| t.is_nil())
+        if self
+            .gbuffer_textures
+            .iter()
+            .any(|t| *t == INVALID_RESOURCE_UID)
             || self.gbuffer_textures.is_empty()
-            || self.depth_texture.is_nil()
+            || self.depth_texture == INVALID_RESOURCE_UID
             || self.textures.read().unwrap().is_empty()
             || self.meshes.read().unwrap().is_empty()
             || self.meshlets.read().unwrap().is_empty()
@@ -182,7 +185,7 @@ impl Pass for PBRPass {
         );
 
         self.binding_data
-            .add_default_sampler(BindingInfo {
+            .add_material_samplers(BindingInfo {
                 group_index: 2,
                 binding_index: 0,
                 stage: ShaderStage::Fragment,
@@ -195,6 +198,33 @@ impl Pass for PBRPass {
                 ..Default::default()
             });
 
+        let (prefiltered_environment, brdf_lut) = render_context.texture_handler.ibl_textures();
+        self.binding_data
+            .add_environment_sampler(BindingInfo {
+                group_index: 3,
+                binding_index: 0,
+                stage: ShaderStage::Fragment,
+                ..Default::default()
+            })
+            .add_texture(
+                &prefiltered_environment,
+                BindingInfo {
+                    group_index: 3,
+                    binding_index: 1,
+                    stage: ShaderStage::Fragment,
+                    ..Default::default()
+                },
+            )
+            .add_texture(
+                &brdf_lut,
+                BindingInfo {
+                    group_index: 3,
+                    binding_index: 2,
+                    stage: ShaderStage::Fragment,
+                    ..Default::default()
+                },
+            );
+
         pass.init(render_context, &mut self.binding_data, None, None);
     }
     fn update(
@@ -205,9 +235,12 @@ impl Pass for PBRPass {
     ) {
         inox_profiler::scoped_profile!("pbr_pass::update");
 
-        if self.gbuffer_textures.iter().any(|t| t.is_nil())
+        if self
+            .gbuffer_textures
+            .iter()
+            .any(|t| *t == INVALID_RESOURCE_UID)
             || self.gbuffer_textures.is_empty()
-            || self.depth_texture.is_nil()
+            || self.depth_texture == INVALID_RESOURCE_UID
             || self.textures.read().unwrap().is_empty()
             || self.meshes.read().unwrap().is_empty()
             || self.meshlets.read().unwrap().is_empty()
@@ -229,6 +262,7 @@ impl Pass for PBRPass {
             render_targets: render_targets.as_slice(),
             surface_view,
             command_buffer,
+            view_submission: render_context.current_view(),
         };
         let mut render_pass = pass.begin(&mut self.binding_data, &pipeline, render_pass_begin_data);
         {
@@ -240,6 +274,17 @@ impl Pass for PBRPass {
             pass.draw(render_context, render_pass, 0..3, 0..1);
         }
     }
+    fn read_textures(&self) -> Vec<(TextureId, TextureAccess)> {
+        let mut textures = self.render_pass.get().read_textures();
+        self.gbuffer_textures
+            .iter()
+            .chain(std::iter::once(&self.depth_texture))
+            .for_each(|id| textures.push((*id, TextureAccess::Sampled)));
+        textures
+    }
+    fn written_textures(&self) -> Vec<(TextureId, TextureAccess)> {
+        self.render_pass.get().written_textures()
+    }
 }
 
 impl OutputRenderPass for PBRPass {
@@ -258,3 +303,272 @@ impl PBRPass {
         self
     }
 }
+
+//Shades a surface point in front of the camera with the compute_brdf of pbr_utils.inc,
+//one invocation for each material
+#[allow(dead_code)]
+#[cfg(not(target_arch = "wasm32"))]
+const BRDF_SHADER: &str = r#"
+#import "utils.inc"
+#import "common.inc"
+
+@group(0) @binding(0)
+var<uniform> constant_data: ConstantData;
+@group(0) @binding(1)
+var<storage, read> materials: Materials;
+@group(0) @binding(2)
+var<storage, read> textures: Textures;
+@group(0) @binding(3)
+var<storage, read> lights: Lights;
+
+@group(1) @binding(0)
+var<storage, read_write> colors: array<vec4<f32>>;
+
+#import "texture_utils.inc"
+#import "material_utils.inc"
+#import "matrix_utils.inc"
+#import "ambient_utils.inc"
+#import "pbr_utils.inc"
+
+@compute
+@workgroup_size(1, 1, 1)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let world_pos = vec3<f32>(0., 0., -5.);
+    let normal = vec3<f32>(0., 0., 1.);
+    colors[id.x] = compute_brdf(world_pos, normal, id.x, vec4<f32>(1.), vec4<u32>(0u));
+}
+"#;
+
+//Runs BRDF_SHADER on a device without a surface once for each set of lights,
+//none when the system has no adapter able to run it
+#[allow(dead_code)]
+#[cfg(not(target_arch = "wasm32"))]
+fn gpu_compute_brdf(
+    materials: &[crate::DrawMaterial],
+    texel: [u8; 4],
+    lights: &[Vec<crate::LightData>],
+) -> Option<Vec<Vec<[f32; 4]>>> {
+    use inox_resources::to_slice;
+    use std::num::NonZeroU32;
+    use wgpu::util::DeviceExt;
+
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+    let adapter = futures::executor::block_on(
+        instance.request_adapter(&wgpu::RequestAdapterOptions::default()),
+    )?;
+    let (device, queue) = futures::executor::block_on(adapter.request_device(
+        &wgpu::DeviceDescriptor {
+            label: None,
+            features: wgpu::Features::empty(),
+            limits: adapter.limits(),
+        },
+        None,
+    ))
+    .ok()?;
+
+    let shaders_folder =
+        std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../../data_raw/shaders/wgsl");
+    let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::Wgsl(
+            crate::wgsl_code_with_imports(BRDF_SHADER, &shaders_folder.join("pbr.wgsl")).into(),
+        ),
+    });
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: None,
+        layout: None,
+        module: &module,
+        entry_point: "main",
+    });
+
+    //A zeroed view matrix keeps the camera in the origin
+    let constant_data = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: None,
+        contents: &[0u8; 256],
+        usage: wgpu::BufferUsages::UNIFORM,
+    });
+    let materials_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: None,
+        contents: to_slice(materials),
+        usage: wgpu::BufferUsages::STORAGE,
+    });
+    //The only texture fills a single texel of the first atlas
+    let textures_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: None,
+        contents: to_slice(&[crate::TextureInfo::new(0, 0, [0., 0., 1., 1.], (1, 1))]),
+        usage: wgpu::BufferUsages::STORAGE,
+    });
+    let size = wgpu::Extent3d {
+        width: 1,
+        height: 1,
+        depth_or_array_layers: 1,
+    };
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: None,
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba8Unorm,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[wgpu::TextureFormat::Rgba8Unorm],
+    });
+    queue.write_texture(
+        texture.as_image_copy(),
+        &texel,
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: NonZeroU32::new(4),
+            rows_per_image: None,
+        },
+        size,
+    );
+    let view = texture.create_view(&wgpu::TextureViewDescriptor {
+        dimension: Some(wgpu::TextureViewDimension::D2Array),
+        ..Default::default()
+    });
+    let sampler = device.create_sampler(&wgpu::SamplerDescriptor::default());
+    //Every atlas of the shader is bound to the same texture
+    let mut textures_entries = vec![wgpu::BindGroupEntry {
+        binding: 0,
+        resource: wgpu::BindingResource::Sampler(&sampler),
+    }];
+    (1..8).for_each(|binding| {
+        textures_entries.push(wgpu::BindGroupEntry {
+            binding,
+            resource: wgpu::BindingResource::TextureView(&view),
+        })
+    });
+    let textures_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &pipeline.get_bind_group_layout(2),
+        entries: &textures_entries,
+    });
+
+    let colors_size = (materials.len() * std::mem::size_of::<[f32; 4]>()) as u64;
+    let results = lights
+        .iter()
+        .map(|lights| {
+            let lights_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: None,
+                contents: to_slice(lights),
+                usage: wgpu::BufferUsages::STORAGE,
+            });
+            let data_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: None,
+                layout: &pipeline.get_bind_group_layout(0),
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: constant_data.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: materials_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: textures_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: lights_buffer.as_entire_binding(),
+                    },
+                ],
+            });
+            let colors = device.create_buffer(&wgpu::BufferDescriptor {
+                label: None,
+                size: colors_size,
+                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            });
+            let colors_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: None,
+                layout: &pipeline.get_bind_group_layout(1),
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: colors.as_entire_binding(),
+                }],
+            });
+            let read_back = device.create_buffer(&wgpu::BufferDescriptor {
+                label: None,
+                size: colors_size,
+                usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+            let mut encoder =
+                device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+            {
+                let mut compute_pass =
+                    encoder.begin_compute_pass(&wgpu::ComputePassDescriptor::default());
+                compute_pass.set_pipeline(&pipeline);
+                compute_pass.set_bind_group(0, &data_bind_group, &[]);
+                compute_pass.set_bind_group(1, &colors_bind_group, &[]);
+                compute_pass.set_bind_group(2, &textures_bind_group, &[]);
+                compute_pass.dispatch_workgroups(materials.len() as _, 1, 1);
+            }
+            encoder.copy_buffer_to_buffer(&colors, 0, &read_back, 0, colors_size);
+            queue.submit(Some(encoder.finish()));
+
+            let slice = read_back.slice(..);
+            slice.map_async(wgpu::MapMode::Read, |_| {});
+            device.poll(wgpu::Maintain::Wait);
+            let colors = to_slice::<u8, [f32; 4]>(&slice.get_mapped_range()).to_vec();
+            read_back.unmap();
+            colors
+        })
+        .collect();
+    Some(results)
+}
+
+#[allow(dead_code)]
+fn test_unlit_shading() {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        use crate::{DrawMaterial, LightData, LightType, MaterialFlags, TextureType};
+
+        //Both materials have a green emissive texture
+        let mut lit = DrawMaterial::default();
+        lit.base_color = [0.5, 0.25, 0.125, 1.];
+        lit.roughness_factor = 0.5;
+        lit.textures_indices[TextureType::Emissive as usize] = 0;
+        let mut unlit = lit;
+        unlit.flags = MaterialFlags::Unlit.into();
+
+        //A light in front of the surface and one at its side
+        let light = |position: [f32; 3]| {
+            vec![
+                LightData {
+                    position,
+                    light_type: LightType::Point as _,
+                    color: [1.; 4],
+                    intensity: 10.,
+                    range: 10.,
+                    ..Default::default()
+                },
+                LightData::default(),
+            ]
+        };
+        let lights = [light([0., 0., -3.]), light([3., 0., -5.])];
+        let Some(colors) = gpu_compute_brdf(&[unlit, lit], [0, 255, 0, 255], &lights) else {
+            println!("No GPU adapter available, unlit shading not checked");
+            return;
+        };
+        let [front, side] = [&colors[0], &colors[1]];
+        //The lights change the lit material only
+        assert!(
+            (0..3).any(|c| (front[1][c] - side[1][c]).abs() > 0.01),
+            "{front:?} {side:?}"
+        );
+        //The unlit one shows its base color with its emission
+        let expected = [0.5, 1.25, 0.125, 1.];
+        [front[0], side[0]].iter().for_each(|color| {
+            (0..4).for_each(|c| assert!((color[c] - expected[c]).abs() < 1e-5, "{color:?}"));
+        });
+    }
+}
+
+#[test]
+fn test() {
+    test_unlit_shading();
+}
@@ -3,26 +3,27 @@ use std::path::PathBuf;
 use crate::{
     BindingData, BindingFlags, BindingInfo, CommandBuffer, ComputePass, ComputePassData,
     ConstantDataRw, DrawCommandType, DrawRay, MeshFlags, OutputPass, Pass, RaysBuffer,
-    RenderContext, ShaderStage, Texture, TextureId, TextureView,
+    RenderContext, ShaderStage, Texture, TextureAccess, TextureId, TextureView,
 };
 
 use inox_core::ContextRc;
-use inox_resources::{DataTypeResource, Resource};
-use inox_uid::{generate_random_uid, generate_static_uid_from_string, Uid, INVALID_UID};
+use inox_resources::{DataTypeResource, Handle, Resource, ResourceId};
+use inox_uid::{
+    generate_random_resource_uid, generate_static_resource_uid_from_string, INVALID_RESOURCE_UID,
+};
 
 pub const RAYTRACING_GENERATE_RAY_PIPELINE: &str =
     "pipelines/RayTracingGenerateRay.compute_pipeline";
 pub const RAYTRACING_GENERATE_RAY_NAME: &str = "RayTracingGenerateRayPass";
 
-const RAYS_UID: Uid = generate_static_uid_from_string("RAYS");
+const RAYS_UID: ResourceId = generate_static_resource_uid_from_string("RAYS");
 
 pub struct RayTracingGenerateRayPass {
     compute_pass: Resource<ComputePass>,
     binding_data: BindingData,
     constant_data: ConstantDataRw,
     render_target_id: TextureId,
-    width: u32,
-    height: u32,
+    render_target: Handle<Texture>,
     rays: RaysBuffer,
 }
 unsafe impl Send for RayTracingGenerateRayPass {}
@@ -57,22 +58,21 @@ impl Pass for RayTracingGenerateRayPass {
             compute_pass: ComputePass::new_resource(
                 context.shared_data(),
                 context.message_hub(),
-                generate_random_uid(),
+                generate_random_resource_uid(),
                 &data,
                 None,
             ),
             constant_data: render_context.constant_data.clone(),
             binding_data: BindingData::new(render_context, RAYTRACING_GENERATE_RAY_NAME),
             rays: render_context.render_buffers.rays.clone(),
-            width: 0,
-            height: 0,
-            render_target_id: INVALID_UID,
+            render_target: None,
+            render_target_id: INVALID_RESOURCE_UID,
         }
     }
     fn init(&mut self, render_context: &RenderContext) {
         inox_profiler::scoped_profile!("raytracing_generate_ray_pass::init");
 
-        if self.render_target_id.is_nil() {
+        if self.render_target_id == INVALID_RESOURCE_UID {
             return;
         }
 
@@ -117,19 +117,25 @@ impl Pass for RayTracingGenerateRayPass {
         _surface_view: &TextureView,
         command_buffer: &mut CommandBuffer,
     ) {
-        if self.render_target_id.is_nil() || self.rays.read().unwrap().is_empty() {
+        if self.render_target_id == INVALID_RESOURCE_UID || self.rays.read().unwrap().is_empty() {
             return;
         }
 
         inox_profiler::scoped_profile!("raytracing_generate_ray_pass::update");
 
+        //Render targets can be resized after the rays have been allocated for them
+        let (width, height) = self
+            .render_target
+            .as_ref()
+            .map(|t| t.get().dimensions())
+            .unwrap_or_default();
         let pass = self.compute_pass.get();
         let x_pixels_managed_in_shader = 16;
         let y_pixels_managed_in_shader = 16;
         let max_cluster_size = x_pixels_managed_in_shader.max(y_pixels_managed_in_shader);
-        let x = (max_cluster_size * ((self.width + max_cluster_size - 1) / max_cluster_size))
+        let x = (max_cluster_size * ((width + max_cluster_size - 1) / max_cluster_size))
             / x_pixels_managed_in_shader;
-        let y = (max_cluster_size * ((self.height + max_cluster_size - 1) / max_cluster_size))
+        let y = (max_cluster_size * ((height + max_cluster_size - 1) / max_cluster_size))
             / y_pixels_managed_in_shader;
 
         let mut compute_pass = pass.begin(render_context, &mut self.binding_data, command_buffer);
@@ -142,6 +148,9 @@ impl Pass for RayTracingGenerateRayPass {
             pass.dispatch(render_context, compute_pass, x, y, 1);
         }
     }
+    fn written_textures(&self) -> Vec<(TextureId, TextureAccess)> {
+        vec![(self.render_target_id, TextureAccess::Storage)]
+    }
 }
 
 impl OutputPass for RayTracingGenerateRayPass {
@@ -153,11 +162,11 @@ impl OutputPass for RayTracingGenerateRayPass {
 impl RayTracingGenerateRayPass {
     pub fn use_render_target(&mut self, texture: &Resource<Texture>) -> &mut Self {
         self.render_target_id = *texture.id();
-        self.width = texture.get().width();
-        self.height = texture.get().height();
+        self.render_target = Some(texture.clone());
         {
+            let (width, height) = texture.get().dimensions();
             let mut rays = self.rays.write().unwrap();
-            let ray_data = vec![DrawRay::default(); (self.width * self.height * 4) as usize];
+            let ray_data = vec![DrawRay::default(); (width * height * 4) as usize];
             rays.allocate(&RAYS_UID, ray_data.as_ref());
         }
         self
@@ -4,13 +4,13 @@ use crate::{
     BHVBuffer, BindingData, BindingFlags, BindingInfo, CommandBuffer, ComputePass, ComputePassData,
     ConstantDataRw, CullingResults, DrawCommandType, IndicesBuffer, MeshFlags, MeshesBuffer,
     MeshesInverseMatrixBuffer, MeshletsBuffer, MeshletsCullingBuffer, OutputPass, Pass, RaysBuffer,
-    RenderContext, ShaderStage, Texture, TextureFormat, TextureId, TextureUsage, TextureView,
-    VertexPositionsBuffer, VerticesBuffer,
+    RenderContext, ShaderStage, Texture, TextureAccess, TextureFormat, TextureId, TextureUsage,
+    TextureView, VertexPositionsBuffer, VerticesBuffer,
 };
 
 use inox_core::ContextRc;
 use inox_resources::{DataTypeResource, Handle, Resource};
-use inox_uid::generate_random_uid;
+use inox_uid::generate_random_resource_uid;
 
 pub const RAYTRACING_VISIBILITY_PIPELINE: &str = "pipelines/RayTracingVisibility.compute_pipeline";
 pub const RAYTRACING_VISIBILITY_NAME: &str = "RayTracingVisibilityPass";
@@ -67,7 +67,7 @@ impl Pass for RayTracingVisibilityPass {
             compute_pass: ComputePass::new_resource(
                 context.shared_data(),
                 context.message_hub(),
-                generate_random_uid(),
+                generate_random_resource_uid(),
                 &data,
                 None,
             ),
@@ -264,6 +264,12 @@ impl Pass for RayTracingVisibilityPass {
             }
         }
     }
+    fn written_textures(&self) -> Vec<(TextureId, TextureAccess)> {
+        self.render_target
+            .iter()
+            .map(|t| (*t.id(), TextureAccess::Storage))
+            .collect()
+    }
 }
 
 impl OutputPass for RayTracingVisibilityPass {
@@ -0,0 +1,537 @@
+use std::path::PathBuf;
+
+use crate::{
+    BHVBuffer, BindingData, BindingInfo, CommandBuffer, ConstantDataRw, DrawBHVNode,
+    DrawCommandType, DrawIndexedCommand, DrawMesh, DrawMeshlet, DrawVertex, IndicesBuffer,
+    LightsBuffer, LoadOperation, MaterialsBuffer, MeshFlags, MeshesBuffer, MeshletsBuffer,
+    OutputRenderPass, Pass, RenderContext, RenderPass, RenderPassBeginData, RenderPassData,
+    RenderTarget, ShaderStage, StoreOperation, TextureAccess, TextureId, TextureView,
+    TexturesBuffer, VertexColorsBuffer, VertexNormalsBuffer, VertexPositionsBuffer,
+    VertexUVsBuffer, VerticesBuffer,
+};
+
+use inox_core::ContextRc;
+use inox_math::{compute_frustum_from_view_proj, Frustum, InnerSpace, Mat4Ops, Matrix4, Vector3};
+use inox_resources::{DataTypeResource, Resource, ResourceTrait};
+use inox_uid::generate_random_resource_uid;
+
+pub const TRANSPARENT_PIPELINE: &str = "pipelines/Transparent.render_pipeline";
+pub const TRANSPARENT_PASS_NAME: &str = "TransparentPass";
+
+pub struct TransparentPass {
+    render_pass: Resource<RenderPass>,
+    binding_data: BindingData,
+    constant_data: ConstantDataRw,
+    textures: TexturesBuffer,
+    materials: MaterialsBuffer,
+    lights: LightsBuffer,
+    meshes: MeshesBuffer,
+    bhv: BHVBuffer,
+    meshlets: MeshletsBuffer,
+    vertices: VerticesBuffer,
+    indices: IndicesBuffer,
+    vertex_positions: VertexPositionsBuffer,
+    vertex_colors: VertexColorsBuffer,
+    vertex_normals: VertexNormalsBuffer,
+    vertex_uvs: VertexUVsBuffer,
+}
+unsafe impl Send for TransparentPass {}
+unsafe impl Sync for TransparentPass {}
+
+impl Pass for TransparentPass {
+    fn name(&self) -> &str {
+        TRANSPARENT_PASS_NAME
+    }
+    fn static_name() -> &'static str {
+        TRANSPARENT_PASS_NAME
+    }
+    fn is_active(&self, render_context: &RenderContext) -> bool {
+        render_context.has_commands(&self.draw_commands_type(), &self.mesh_flags())
+    }
+    fn mesh_flags(&self) -> MeshFlags {
+        MeshFlags::Visible | MeshFlags::Transparent
+    }
+    fn draw_commands_type(&self) -> DrawCommandType {
+        DrawCommandType::PerMeshlet
+    }
+    fn create(context: &ContextRc, render_context: &RenderContext) -> Self
+    where
+        Self: Sized,
+    {
+        inox_profiler::scoped_profile!("transparent_pass::create");
+
+        let data = RenderPassData {
+            name: TRANSPARENT_PASS_NAME.to_string(),
+            load_color: LoadOperation::Load,
+            load_depth: LoadOperation::Load,
+            store_color: StoreOperation::Store,
+            store_depth: StoreOperation::Store,
+            render_target: RenderTarget::Screen,
+            pipeline: PathBuf::from(TRANSPARENT_PIPELINE),
+            ..Default::default()
+        };
+
+        Self {
+            render_pass: RenderPass::new_resource(
+                context.shared_data(),
+                context.message_hub(),
+                generate_random_resource_uid(),
+                &data,
+                None,
+            ),
+            constant_data: render_context.constant_data.clone(),
+            textures: render_context.render_buffers.textures.clone(),
+            materials: render_context.render_buffers.materials.clone(),
+            lights: render_context.render_buffers.lights.clone(),
+            meshes: render_context.render_buffers.meshes.clone(),
+            bhv: render_context.render_buffers.bhv.clone(),
+            meshlets: render_context.render_buffers.meshlets.clone(),
+            vertices: render_context.render_buffers.vertices.clone(),
+            indices: render_context.render_buffers.indices.clone(),
+            vertex_positions: render_context.render_buffers.vertex_positions.clone(),
+            vertex_colors: render_context.render_buffers.vertex_colors.clone(),
+            vertex_normals: render_context.render_buffers.vertex_normals.clone(),
+            vertex_uvs: render_context.render_buffers.vertex_uvs.clone(),
+            binding_data: BindingData::new(render_context, TRANSPARENT_PASS_NAME),
+        }
+    }
+    fn init(&mut self, render_context: &RenderContext) {
+        inox_profiler::scoped_profile!("transparent_pass::init");
+
+        if self.textures.read().unwrap().is_empty()
+            || self.meshes.read().unwrap().is_empty()
+            || self.meshlets.read().unwrap().is_empty()
+            || self.materials.read().unwrap().is_empty()
+            || self.lights.read().unwrap().is_empty()
+            || self.vertex_positions.read().unwrap().is_empty()
+            || self.vertex_normals.read().unwrap().is_empty()
+            || self.vertex_colors.read().unwrap().is_empty()
+            || self.vertex_uvs.read().unwrap().is_empty()
+        {
+            return;
+        }
+
+        let mut pass = self.render_pass.get_mut();
+
+        self.binding_data
+            .add_uniform_buffer(
+                &mut *self.constant_data.write().unwrap(),
+                Some("ConstantData"),
+                BindingInfo {
+                    group_index: 0,
+                    binding_index: 0,
+                    stage: ShaderStage::VertexAndFragment,
+                    ..Default::default()
+                },
+            )
+            .add_storage_buffer(
+                &mut *self.vertex_positions.write().unwrap(),
+                Some("VertexPositions"),
+                BindingInfo {
+                    group_index: 0,
+                    binding_index: 1,
+                    stage: ShaderStage::Vertex,
+                    ..Default::default()
+                },
+            )
+            .add_storage_buffer(
+                &mut *self.vertex_colors.write().unwrap(),
+                Some("VertexColors"),
+                BindingInfo {
+                    group_index: 0,
+                    binding_index: 2,
+                    stage: ShaderStage::Vertex,
+                    ..Default::default()
+                },
+            )
+            .add_storage_buffer(
+                &mut *self.vertex_normals.write().unwrap(),
+                Some("VertexNormals"),
+                BindingInfo {
+                    group_index: 0,
+                    binding_index: 3,
+                    stage: ShaderStage::Vertex,
+                    ..Default::default()
+                },
+            )
+            .add_storage_buffer(
+                &mut *self.vertex_uvs.write().unwrap(),
+                Some("VertexUVs"),
+                BindingInfo {
+                    group_index: 0,
+                    binding_index: 4,
+                    stage: ShaderStage::Vertex,
+                    ..Default::default()
+                },
+            )
+            .add_storage_buffer(
+                &mut *self.meshes.write().unwrap(),
+                Some("Meshes"),
+                BindingInfo {
+                    group_index: 1,
+                    binding_index: 0,
+                    stage: ShaderStage::VertexAndFragment,
+                    ..Default::default()
+                },
+            )
+            .add_storage_buffer(
+                &mut *self.materials.write().unwrap(),
+                Some("Materials"),
+                BindingInfo {
+                    group_index: 1,
+                    binding_index: 1,
+                    stage: ShaderStage::Fragment,
+                    ..Default::default()
+                },
+            )
+            .add_storage_buffer(
+                &mut *self.textures.write().unwrap(),
+                Some("Textures"),
+                BindingInfo {
+                    group_index: 1,
+                    binding_index: 2,
+                    stage: ShaderStage::Fragment,
+                    ..Default::default()
+                },
+            )
+            .add_storage_buffer(
+                &mut *self.meshlets.write().unwrap(),
+                Some("Meshlets"),
+                BindingInfo {
+                    group_index: 1,
+                    binding_index: 3,
+                    stage: ShaderStage::Vertex,
+                    ..Default::default()
+                },
+            )
+            .add_storage_buffer(
+                &mut *self.bhv.write().unwrap(),
+                Some("BHV"),
+                BindingInfo {
+                    group_index: 1,
+                    binding_index: 4,
+                    stage: ShaderStage::Vertex,
+                    ..Default::default()
+                },
+            )
+            .add_storage_buffer(
+                &mut *self.lights.write().unwrap(),
+                Some("Lights"),
+                BindingInfo {
+                    group_index: 1,
+                    binding_index: 5,
+                    stage: ShaderStage::Fragment,
+                    ..Default::default()
+                },
+            )
+            .add_material_samplers(BindingInfo {
+                group_index: 2,
+                binding_index: 0,
+                stage: ShaderStage::Fragment,
+                ..Default::default()
+            })
+            .add_material_textures(BindingInfo {
+                group_index: 2,
+                binding_index: 1,
+                stage: ShaderStage::Fragment,
+                ..Default::default()
+            });
+
+        let (prefiltered_environment, brdf_lut) = render_context.texture_handler.ibl_textures();
+        self.binding_data
+            .add_environment_sampler(BindingInfo {
+                group_index: 3,
+                binding_index: 0,
+                stage: ShaderStage::Fragment,
+                ..Default::default()
+            })
+            .add_texture(
+                &prefiltered_environment,
+                BindingInfo {
+                    group_index: 3,
+                    binding_index: 1,
+                    stage: ShaderStage::Fragment,
+                    ..Default::default()
+                },
+            )
+            .add_texture(
+                &brdf_lut,
+                BindingInfo {
+                    group_index: 3,
+                    binding_index: 2,
+                    stage: ShaderStage::Fragment,
+                    ..Default::default()
+                },
+            )
+            .set_vertex_buffer(0, &mut *self.vertices.write().unwrap(), Some("Vertices"))
+            .set_index_buffer(&mut *self.indices.write().unwrap(), Some("Indices"));
+
+        let vertex_layout = DrawVertex::descriptor(0);
+        pass.init(
+            render_context,
+            &mut self.binding_data,
+            Some(vertex_layout),
+            None,
+        );
+    }
+    fn update(
+        &mut self,
+        render_context: &RenderContext,
+        surface_view: &TextureView,
+        command_buffer: &mut CommandBuffer,
+    ) {
+        inox_profiler::scoped_profile!("transparent_pass::update");
+
+        let pass = self.render_pass.get();
+        let pipeline = pass.pipeline().get();
+        if !pipeline.is_initialized() {
+            return;
+        }
+
+        //Sorting always uses the render camera and not the culling one:
+        //constant data keeps following the view even while the culling camera is frozen
+        //and blending has to be resolved from the point of view that is actually rendered
+        let (origin, frustum) = {
+            let constant_data = self.constant_data.read().unwrap();
+            let view = constant_data.view();
+            let frustum =
+                compute_frustum_from_view_proj(&view.into(), &constant_data.proj().into());
+            (camera_position(view), frustum)
+        };
+        let draws = self.sorted_draws(
+            render_context,
+            &pipeline.data().mesh_flags,
+            origin,
+            &frustum,
+        );
+        if draws.is_empty() {
+            return;
+        }
+
+        let buffers = render_context.buffers();
+        let render_targets = render_context.texture_handler.render_targets();
+
+        let render_pass_begin_data = RenderPassBeginData {
+            render_core_context: &render_context.core,
+            buffers: &buffers,
+            render_targets: render_targets.as_slice(),
+            surface_view,
+            command_buffer,
+            view_submission: render_context.current_view(),
+        };
+        let mut render_pass = pass.begin(&mut self.binding_data, &pipeline, render_pass_begin_data);
+        {
+            inox_profiler::gpu_scoped_profile!(
+                &mut render_pass,
+                &render_context.core.device,
+                "transparent_pass",
+            );
+            draws.iter().for_each(|draw| {
+                render_pass.draw_indexed(
+                    draw.base_index..draw.base_index + draw.vertex_count,
+                    draw.vertex_offset,
+                    draw.base_instance..draw.base_instance + draw.instance_count,
+                );
+            });
+        }
+    }
+    fn read_textures(&self) -> Vec<(TextureId, TextureAccess)> {
+        self.render_pass.get().read_textures()
+    }
+    fn written_textures(&self) -> Vec<(TextureId, TextureAccess)> {
+        self.render_pass.get().written_textures()
+    }
+}
+
+impl OutputRenderPass for TransparentPass {
+    fn render_pass(&self) -> &Resource<RenderPass> {
+        &self.render_pass
+    }
+}
+
+impl TransparentPass {
+    fn sorted_draws(
+        &self,
+        render_context: &RenderContext,
+        mesh_flags: &MeshFlags,
+        origin: Vector3,
+        frustum: &Frustum,
+    ) -> Vec<DrawIndexedCommand> {
+        inox_profiler::scoped_profile!("transparent_pass::sorted_draws");
+
+        let mut transparent_meshes = Vec::new();
+        {
+            let meshes_flags = render_context.render_buffers.meshes_flags.read().unwrap();
+            let bhv = self.bhv.read().unwrap();
+            let bhv = bhv.data();
+            self.meshes.read().unwrap().for_each_id(|mesh_id, _, mesh| {
+                if meshes_flags.get(mesh_id) == Some(mesh_flags) && is_mesh_visible(frustum, mesh) {
+                    transparent_meshes.push((*mesh, compute_aabb_center(mesh, bhv)));
+                }
+            });
+        }
+        let meshlets = self.meshlets.read().unwrap();
+        create_back_to_front_draws(origin, &mut transparent_meshes, meshlets.data())
+    }
+}
+
+//The view matrix holds the inverse of the camera transform
+pub fn camera_position(view: [[f32; 4]; 4]) -> Vector3 {
+    Matrix4::from(view).inverse().translation()
+}
+
+//Transparent meshes are not culled on the gpu, the ones out of the view are skipped here
+pub fn is_mesh_visible(frustum: &Frustum, mesh: &DrawMesh) -> bool {
+    let (center, radius) = mesh.world_bounding_sphere();
+    frustum.is_sphere_visible(center, radius)
+}
+
+pub fn compute_aabb_center(mesh: &DrawMesh, bhv: &[DrawBHVNode]) -> Vector3 {
+    let node = &bhv[mesh.bhv_index as usize];
+    let matrix = mesh.transform();
+    let min = matrix.rotate_point(node.min.into());
+    let max = matrix.rotate_point(node.max.into());
+    (min + max) * 0.5
+}
+
+//Sorts the meshes by distance from origin of their aabb center, farthest first,
+//and emits one draw per meshlet with the meshlet index as instance like draw_meshlets does
+pub fn create_back_to_front_draws(
+    origin: Vector3,
+    meshes: &mut [(DrawMesh, Vector3)],
+    meshlets: &[DrawMeshlet],
+) -> Vec<DrawIndexedCommand> {
+    meshes.sort_by(|(_, a), (_, b)| {
+        let distance_a = (*a - origin).magnitude2();
+        let distance_b = (*b - origin).magnitude2();
+        distance_b.total_cmp(&distance_a)
+    });
+    let mut draws = Vec::new();
+    meshes.iter().for_each(|(mesh, _)| {
+        for i in mesh.meshlet_indices() {
+            let meshlet = &meshlets[i as usize];
+            draws.push(DrawIndexedCommand {
+                vertex_count: meshlet.indices_count,
+                instance_count: 1,
+                base_index: mesh.indices_offset + meshlet.indices_offset,
+                vertex_offset: mesh.vertex_offset as _,
+                base_instance: i,
+            });
+        }
+    });
+    draws
+}
+
+#[allow(dead_code)]
+fn test_back_to_front_draws() {
+    use inox_math::{matrix4_to_array, Degrees, NewAngle};
+
+    //Two overlapping quads along the view direction, the near one registered first
+    let bhv = [DrawBHVNode {
+        min: [-1., -1., 0.],
+        max: [1., 1., 0.],
+        ..Default::default()
+    }];
+    let meshlets = [
+        DrawMeshlet {
+            mesh_index: 0,
+            indices_offset: 0,
+            indices_count: 6,
+            bvh_index: 0,
+        },
+        DrawMeshlet {
+            mesh_index: 1,
+            indices_offset: 0,
+            indices_count: 6,
+            bvh_index: 0,
+        },
+    ];
+    let near_quad = DrawMesh {
+        position: [0., 0., -2.],
+        meshlets_offset: 0,
+        meshlets_count: 1,
+        ..Default::default()
+    };
+    let far_quad = DrawMesh {
+        vertex_offset: 4,
+        indices_offset: 6,
+        position: [0.5, 0., -5.],
+        meshlets_offset: 1,
+        meshlets_count: 1,
+        ..Default::default()
+    };
+    let mut meshes = [near_quad, far_quad]
+        .iter()
+        .map(|m| (*m, compute_aabb_center(m, &bhv)))
+        .collect::<Vec<_>>();
+
+    let draws = create_back_to_front_draws(Vector3::new(0., 0., 0.), &mut meshes, &meshlets);
+    assert_eq!(draws.len(), 2);
+    assert_eq!(draws[0].base_instance, 1);
+    assert_eq!(draws[0].base_index, 6);
+    assert_eq!(draws[0].vertex_offset, 4);
+    assert_eq!(draws[1].base_instance, 0);
+    assert_eq!(draws[1].base_index, 0);
+
+    //Moving behind the far quad reverses the order
+    let draws = create_back_to_front_draws(Vector3::new(0., 0., -10.), &mut meshes, &meshlets);
+    assert_eq!(draws[0].base_instance, 0);
+    assert_eq!(draws[1].base_instance, 1);
+
+    //Same position with the camera turned sideways, the sort follows the camera position
+    let camera = Matrix4::from_translation(Vector3::new(0., 0., -10.))
+        * Matrix4::from_angle_y(Degrees::new(90.));
+    let origin = camera_position(matrix4_to_array(camera.inverse()));
+    let draws = create_back_to_front_draws(origin, &mut meshes, &meshlets);
+    assert_eq!(draws[0].base_instance, 0);
+    assert_eq!(draws[1].base_instance, 1);
+}
+
+#[allow(dead_code)]
+fn test_camera_position() {
+    use inox_math::{matrix4_to_array, Degrees, NewAngle};
+
+    //Camera moved behind the far quad and turned to look back at the near one
+    let position = Vector3::new(1., 2., -10.);
+    let camera = Matrix4::from_translation(position) * Matrix4::from_angle_y(Degrees::new(150.));
+    let view = matrix4_to_array(camera.inverse());
+    let origin = camera_position(view);
+    assert!((origin - position).magnitude() < 1e-4);
+    //The translation of the view is not the camera position once it's rotated
+    assert!((Vector3::new(view[3][0], view[3][1], view[3][2]) - position).magnitude() > 1.);
+}
+
+#[allow(dead_code)]
+fn test_mesh_visibility() {
+    use inox_math::{matrix4_to_array, perspective, Degrees, NewAngle};
+
+    let camera = Matrix4::from_translation(Vector3::new(0., 0., 10.));
+    let view = camera.inverse();
+    let proj = perspective(Degrees::new(45.), 1., 0.1, 100.);
+    let frustum = compute_frustum_from_view_proj(&view, &proj);
+    let quad = |position: [f32; 3], scale: f32| DrawMesh {
+        position,
+        scale: [scale; 3],
+        bounding_sphere: [0., 0., 0., 1.],
+        ..Default::default()
+    };
+
+    assert!(is_mesh_visible(&frustum, &quad([0., 0., 0.], 1.)));
+    assert!(!is_mesh_visible(&frustum, &quad([0., 0., 20.], 1.)));
+    assert!(!is_mesh_visible(&frustum, &quad([50., 0., 0.], 1.)));
+    //The sphere grows with the scale of the mesh
+    assert!(is_mesh_visible(&frustum, &quad([50., 0., 0.], 50.)));
+    //The frustum follows the view matrices as they're stored in the constant data
+    let frustum = compute_frustum_from_view_proj(
+        &matrix4_to_array(view).into(),
+        &matrix4_to_array(proj).into(),
+    );
+    assert!(is_mesh_visible(&frustum, &quad([0., 0., 0.], 1.)));
+}
+
+#[test]
+fn test() {
+    test_back_to_front_draws();
+    test_camera_position();
+    test_mesh_visibility();
+}
@@ -4,12 +4,12 @@ use crate::{
     BHVBuffer, BindingData, BindingInfo, CommandBuffer, ConstantDataRw, DrawCommandType,
     DrawVertex, IndicesBuffer, MeshFlags, MeshesBuffer, MeshletsBuffer, OutputRenderPass, Pass,
     RenderContext, RenderPass, RenderPassBeginData, RenderPassData, RenderTarget, ShaderStage,
-    StoreOperation, TextureView, VertexPositionsBuffer, VerticesBuffer,
+    StoreOperation, TextureAccess, TextureId, TextureView, VertexPositionsBuffer, VerticesBuffer,
 };
 
 use inox_core::ContextRc;
 use inox_resources::{DataTypeResource, Resource, ResourceTrait};
-use inox_uid::generate_random_uid;
+use inox_uid::generate_random_resource_uid;
 
 pub const VISIBILITY_BUFFER_PIPELINE: &str = "pipelines/VisibilityBuffer.render_pipeline";
 pub const VISIBILITY_BUFFER_PASS_NAME: &str = "VisibilityBufferPass";
@@ -63,7 +63,7 @@ impl Pass for VisibilityBufferPass {
             render_pass: RenderPass::new_resource(
                 context.shared_data(),
                 context.message_hub(),
-                generate_random_uid(),
+                generate_random_resource_uid(),
                 &data,
                 None,
             ),
@@ -171,6 +171,7 @@ impl Pass for VisibilityBufferPass {
             render_targets: render_targets.as_slice(),
             surface_view,
             command_buffer,
+            view_submission: render_context.current_view(),
         };
         let mut render_pass = pass.begin(&mut self.binding_data, &pipeline, render_pass_begin_data);
         {
@@ -182,6 +183,12 @@ impl Pass for VisibilityBufferPass {
             pass.indirect_indexed_draw(render_context, &buffers, draw_commands_type, render_pass);
         }
     }
+    fn read_textures(&self) -> Vec<(TextureId, TextureAccess)> {
+        self.render_pass.get().read_textures()
+    }
+    fn written_textures(&self) -> Vec<(TextureId, TextureAccess)> {
+        self.render_pass.get().written_textures()
+    }
 }
 
 impl OutputRenderPass for VisibilityBufferPass {
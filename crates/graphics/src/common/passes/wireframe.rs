@@ -2,25 +2,33 @@ use std::path::PathBuf;
 
 use crate::{
     create_arrow, create_circumference, create_colored_quad, create_line, create_sphere,
-    declare_as_binding_vector, AsBinding, BindingData, BindingInfo, CommandBuffer, ConstantDataRw,
-    DrawCommandType, DrawEvent, LoadOperation, MeshData, MeshFlags, OutputRenderPass, Pass,
-    RenderContext, RenderPass, RenderPassBeginData, RenderPassData, RenderTarget, ShaderStage,
-    StoreOperation, TextureView, VertexBufferLayoutBuilder, VertexFormat, View,
+    declare_as_binding_vector, AsBinding, BindingData, BindingInfo, CommandBuffer, CompareFunction,
+    ConstantDataRw, DrawCommandType, DrawEvent, GpuBuffer, LoadOperation, Mesh, MeshData,
+    MeshFlags, MeshId, OutputRenderPass, Pass, RenderBuffers, RenderContext, RenderCoreContext,
+    RenderPass, RenderPassBeginData, RenderPassData, RenderTarget, ShaderStage, StoreOperation,
+    TextureAccess, TextureId, TextureView, VertexBufferLayoutBuilder, VertexFormat, View,
 };
 
 use inox_core::ContextRc;
-use inox_math::{Mat4Ops, Matrix4};
+use inox_math::{Mat4Ops, Matrix4, Vector3, Vector4};
 use inox_messenger::Listener;
-use inox_resources::{DataTypeResource, Resource, ResourceTrait};
-use inox_uid::generate_random_uid;
+use inox_resources::{DataTypeResource, DataTypeResourceEvent, Resource, ResourceTrait};
+use inox_uid::generate_random_resource_uid;
 
 pub const WIREFRAME_PIPELINE: &str = "pipelines/Wireframe.render_pipeline";
 pub const WIREFRAME_PASS_NAME: &str = "WireframePass";
 
+pub const DEFAULT_WIREFRAME_THICKNESS: f32 = 1.;
+
+//Vertices are not shared between triangles, so that the corner of each one can be
+//computed from the vertex index and used as barycentric coordinate to find the edges.
+//Lines are quads with both triangles degenerate in world space and expanded in screen space,
+//using the opposite endpoint stored in each vertex
 #[derive(Default, Clone, Copy, PartialEq)]
 pub struct DebugVertex {
     position: [f32; 3],
     color: u32,
+    line_end: [f32; 3],
 }
 impl DebugVertex {
     pub fn descriptor<'a>(starting_location: u32) -> VertexBufferLayoutBuilder<'a> {
@@ -28,6 +36,7 @@ impl DebugVertex {
         layout_builder.starting_location(starting_location);
         layout_builder.add_attribute::<[f32; 3]>(VertexFormat::Float32x3.into());
         layout_builder.add_attribute::<u32>(VertexFormat::Uint32.into());
+        layout_builder.add_attribute::<[f32; 3]>(VertexFormat::Float32x3.into());
         layout_builder
     }
 }
@@ -52,6 +61,106 @@ impl DebugInstance {
     }
 }
 
+pub struct WireframeData {
+    is_dirty: bool,
+    is_overlay: bool,
+    is_xray: bool,
+    color: [f32; 4],
+    thickness: f32,
+    _padding: [f32; 3],
+}
+
+impl Default for WireframeData {
+    fn default() -> Self {
+        Self {
+            is_dirty: true,
+            is_overlay: true,
+            is_xray: false,
+            color: [1.; 4],
+            thickness: DEFAULT_WIREFRAME_THICKNESS,
+            _padding: [0.; 3],
+        }
+    }
+}
+
+impl AsBinding for WireframeData {
+    fn is_dirty(&self) -> bool {
+        self.is_dirty
+    }
+    fn set_dirty(&mut self, is_dirty: bool) {
+        self.is_dirty = is_dirty;
+    }
+    fn size(&self) -> u64 {
+        std::mem::size_of_val(&self.color) as u64
+            + std::mem::size_of_val(&self.thickness) as u64
+            + std::mem::size_of_val(&self._padding) as u64
+    }
+    fn fill_buffer(&self, render_core_context: &RenderCoreContext, buffer: &mut GpuBuffer) {
+        buffer.add_to_gpu_buffer(render_core_context, &[self.color]);
+        buffer.add_to_gpu_buffer(render_core_context, &[self.thickness]);
+        buffer.add_to_gpu_buffer(render_core_context, &[self._padding]);
+    }
+}
+
+impl WireframeData {
+    //Multiplied by the color of each debug shape
+    pub fn color(&self) -> Vector4 {
+        self.color.into()
+    }
+    pub fn set_color(&mut self, color: Vector4) -> &mut Self {
+        if self.color != <[f32; 4]>::from(color) {
+            self.color = color.into();
+            self.set_dirty(true);
+        }
+        self
+    }
+    //Edges width in pixels
+    pub fn thickness(&self) -> f32 {
+        self.thickness
+    }
+    pub fn set_thickness(&mut self, thickness: f32) -> &mut Self {
+        let thickness = thickness.max(0.);
+        if self.thickness != thickness {
+            self.thickness = thickness;
+            self.set_dirty(true);
+        }
+        self
+    }
+    //When overlaid the wireframe is drawn on top of the shaded geometry instead of replacing it
+    pub fn is_overlay(&self) -> bool {
+        self.is_overlay
+    }
+    pub fn set_overlay(&mut self, is_overlay: bool) -> &mut Self {
+        self.is_overlay = is_overlay;
+        self
+    }
+    //In x-ray mode edges hidden by other geometry are not occluded
+    pub fn is_xray(&self) -> bool {
+        self.is_xray
+    }
+    pub fn set_xray(&mut self, is_xray: bool) -> &mut Self {
+        self.is_xray = is_xray;
+        self
+    }
+    pub fn depth_compare(&self) -> CompareFunction {
+        if self.is_xray {
+            CompareFunction::Always
+        } else if self.is_overlay {
+            //Edges lying on the shaded surfaces have the same depth
+            CompareFunction::LessEqual
+        } else {
+            CompareFunction::Less
+        }
+    }
+    pub fn load_color(&self) -> LoadOperation {
+        if self.is_overlay {
+            LoadOperation::Load
+        } else {
+            LoadOperation::Clear
+        }
+    }
+}
+
 declare_as_binding_vector!(VecDebugVertex, DebugVertex);
 declare_as_binding_vector!(VecDebugIndex, u32);
 declare_as_binding_vector!(VecDebugInstance, DebugInstance);
@@ -61,9 +170,13 @@ pub struct WireframePass {
     render_pass: Resource<RenderPass>,
     binding_data: BindingData,
     constant_data: ConstantDataRw,
+    wireframe_data: WireframeData,
     vertices: VecDebugVertex,
     indices: VecDebugIndex,
     instances: VecDebugInstance,
+    selection: Vec<MeshId>,
+    //Geometry of the selected meshes, read back once and moved with their transform
+    selected_meshes: Vec<(MeshId, MeshData)>,
     listener: Listener,
 }
 unsafe impl Send for WireframePass {}
@@ -103,21 +216,26 @@ impl Pass for WireframePass {
         };
 
         let listener = Listener::new(context.message_hub());
-        listener.register::<DrawEvent>();
+        listener
+            .register::<DrawEvent>()
+            .register::<DataTypeResourceEvent<Mesh>>();
 
         Self {
             context: context.clone(),
             render_pass: RenderPass::new_resource(
                 context.shared_data(),
                 context.message_hub(),
-                generate_random_uid(),
+                generate_random_resource_uid(),
                 &data,
                 None,
             ),
             constant_data: render_context.constant_data.clone(),
+            wireframe_data: WireframeData::default(),
             vertices: VecDebugVertex::default(),
             indices: VecDebugIndex::default(),
             instances: VecDebugInstance::default(),
+            selection: Vec::new(),
+            selected_meshes: Vec::new(),
             listener,
             binding_data: BindingData::new(render_context, WIREFRAME_PASS_NAME),
         }
@@ -126,6 +244,7 @@ impl Pass for WireframePass {
         inox_profiler::scoped_profile!("wireframe_pass::init");
 
         self.process_messages();
+        self.add_selected_meshes(render_context);
 
         if self.instances.data.is_empty()
             || self.vertices.data.is_empty()
@@ -135,6 +254,14 @@ impl Pass for WireframePass {
         }
 
         let mut pass = self.render_pass.get_mut();
+        pass.set_load_color_operation(self.wireframe_data.load_color());
+        {
+            let depth_compare = self.wireframe_data.depth_compare();
+            let mut pipeline = pass.pipeline().get_mut();
+            if pipeline.data().depth_compare != depth_compare {
+                pipeline.set_depth_compare(depth_compare);
+            }
+        }
 
         self.binding_data
             .add_uniform_buffer(
@@ -147,6 +274,16 @@ impl Pass for WireframePass {
                     ..Default::default()
                 },
             )
+            .add_uniform_buffer(
+                &mut self.wireframe_data,
+                Some("WireframeData"),
+                BindingInfo {
+                    group_index: 0,
+                    binding_index: 1,
+                    stage: ShaderStage::VertexAndFragment,
+                    ..Default::default()
+                },
+            )
             .set_vertex_buffer(0, &mut self.vertices, Some("DebugVertices"))
             .set_vertex_buffer(1, &mut self.instances, Some("DebugInstances"))
             .set_index_buffer(&mut self.indices, Some("DebugIndices"));
@@ -189,6 +326,7 @@ impl Pass for WireframePass {
             render_targets: render_targets.as_slice(),
             surface_view,
             command_buffer,
+            view_submission: render_context.current_view(),
         };
         let mut render_pass = pass.begin(&mut self.binding_data, &pipeline, render_pass_begin_data);
         {
@@ -210,6 +348,12 @@ impl Pass for WireframePass {
                 });
         }
     }
+    fn read_textures(&self) -> Vec<(TextureId, TextureAccess)> {
+        self.render_pass.get().read_textures()
+    }
+    fn written_textures(&self) -> Vec<(TextureId, TextureAccess)> {
+        self.render_pass.get().written_textures()
+    }
 }
 
 impl OutputRenderPass for WireframePass {
@@ -219,25 +363,147 @@ impl OutputRenderPass for WireframePass {
 }
 
 impl WireframePass {
+    pub fn wireframe_data(&self) -> &WireframeData {
+        &self.wireframe_data
+    }
+    pub fn set_color(&mut self, color: Vector4) -> &mut Self {
+        self.wireframe_data.set_color(color);
+        self
+    }
+    pub fn set_thickness(&mut self, thickness: f32) -> &mut Self {
+        self.wireframe_data.set_thickness(thickness);
+        self
+    }
+    pub fn set_overlay(&mut self, is_overlay: bool) -> &mut Self {
+        self.wireframe_data.set_overlay(is_overlay);
+        self
+    }
+    pub fn set_xray(&mut self, is_xray: bool) -> &mut Self {
+        self.wireframe_data.set_xray(is_xray);
+        self
+    }
+    //Meshes drawn in wireframe together with the debug shapes
+    pub fn selection(&self) -> &[MeshId] {
+        &self.selection
+    }
+    pub fn set_selection(&mut self, meshes: &[MeshId]) -> &mut Self {
+        if self.selection != meshes {
+            self.selection = meshes.to_vec();
+            self.selected_meshes.retain(|(id, _)| meshes.contains(id));
+        }
+        self
+    }
+    //Geometry of a mesh in its local space, with the vertex offsets relative to the mesh
+    fn mesh_data_from_buffers(
+        render_buffers: &RenderBuffers,
+        mesh_id: &MeshId,
+        aabb_min: Vector3,
+        aabb_max: Vector3,
+    ) -> Option<MeshData> {
+        let positions = render_buffers.vertex_positions.read().unwrap();
+        let colors = render_buffers.vertex_colors.read().unwrap();
+        let vertices = render_buffers.vertices.read().unwrap();
+        let indices = render_buffers.indices.read().unwrap();
+        let position_offset = positions.get(mesh_id)?.range().start as u32;
+        let mut mesh_data = MeshData {
+            aabb_min,
+            aabb_max,
+            positions: positions.items(mesh_id)?.to_vec(),
+            colors: colors.items(mesh_id)?.to_vec(),
+            vertices: vertices.items(mesh_id)?.to_vec(),
+            indices: indices.items(mesh_id)?.to_vec(),
+            ..Default::default()
+        };
+        mesh_data.vertices.iter_mut().for_each(|v| {
+            v.position_and_color_offset -= position_offset;
+        });
+        Some(mesh_data)
+    }
+    fn add_selected_meshes(&mut self, render_context: &RenderContext) {
+        inox_profiler::scoped_profile!("WireframePass::add_selected_meshes");
+
+        let shared_data = self.context.shared_data();
+        self.selection.iter().for_each(|id| {
+            let Some(mesh) = shared_data.get_resource::<Mesh>(id) else {
+                return;
+            };
+            let mesh = mesh.get();
+            if !self.selected_meshes.iter().any(|(i, _)| i == id) {
+                if let Some(mesh_data) = Self::mesh_data_from_buffers(
+                    &render_context.render_buffers,
+                    id,
+                    *mesh.min(),
+                    *mesh.max(),
+                ) {
+                    self.selected_meshes.push((*id, mesh_data));
+                }
+            }
+            let Some((_, mesh_data)) = self.selected_meshes.iter().find(|(i, _)| i == id) else {
+                return;
+            };
+            let vertex_start = self.vertices.data.len();
+            Self::add_mesh(
+                &mut self.instances,
+                &mut self.vertices,
+                &mut self.indices,
+                mesh_data,
+            );
+            let matrix = mesh.matrix();
+            self.vertices.data[vertex_start..].iter_mut().for_each(|v| {
+                v.position = matrix.rotate_point(v.position.into()).into();
+                v.line_end = matrix.rotate_point(v.line_end.into()).into();
+            });
+        });
+    }
     fn add_mesh(
         instances: &mut VecDebugInstance,
         vertices: &mut VecDebugVertex,
         indices: &mut VecDebugIndex,
-        mesh_data: MeshData,
+        mesh_data: &MeshData,
     ) {
+        let vertex_start = vertices.data.len();
+        let vertex = |i: u32, line_end: Option<u32>| {
+            let v = &mesh_data.vertices[i as usize];
+            let position: [f32; 3] = mesh_data.position(v.position_and_color_offset as _).into();
+            DebugVertex {
+                position,
+                color: mesh_data.colors[v.position_and_color_offset as usize],
+                line_end: line_end.map_or(position, |e| {
+                    let e = &mesh_data.vertices[e as usize];
+                    mesh_data.position(e.position_and_color_offset as _).into()
+                }),
+            }
+        };
+        mesh_data.indices.chunks_exact(3).for_each(|t| {
+            let [p0, p1, p2] = [t[0], t[1], t[2]].map(|i| vertex(i, None).position);
+            //Lines are triangles with two coincident vertices
+            let line = if p0 == p1 {
+                Some((t[0], t[2]))
+            } else if p1 == p2 || p0 == p2 {
+                Some((t[0], t[1]))
+            } else {
+                None
+            };
+            match line {
+                Some((start, end)) => {
+                    let (start, end) = (vertex(start, Some(end)), vertex(end, Some(start)));
+                    if start.position != end.position {
+                        vertices
+                            .data
+                            .extend_from_slice(&[start, start, end, end, end, start]);
+                    }
+                }
+                None => vertices.data.extend(t.iter().map(|&i| vertex(i, None))),
+            }
+        });
+        let vertex_count = vertices.data.len() - vertex_start;
         instances.data.push(DebugInstance {
             index_start: indices.data.len() as _,
-            index_count: mesh_data.indices.len() as _,
-            vertex_start: vertices.data.len() as _,
+            index_count: vertex_count as _,
+            vertex_start: vertex_start as _,
             instance_index: instances.data.len() as _,
         });
-        mesh_data.vertices.iter().for_each(|v| {
-            vertices.data.push(DebugVertex {
-                position: mesh_data.position(v.position_and_color_offset as _).into(),
-                color: mesh_data.colors[v.position_and_color_offset as usize],
-            });
-        });
-        indices.data.extend_from_slice(&mesh_data.indices);
+        indices.data.extend(0..vertex_count as u32);
         instances.set_dirty(true);
         indices.set_dirty(true);
         vertices.set_dirty(true);
@@ -273,7 +539,7 @@ impl WireframePass {
                         &mut self.instances,
                         &mut self.vertices,
                         &mut self.indices,
-                        mesh_data,
+                        &mesh_data,
                     );
                 }
                 DrawEvent::BoundingBox(min, max, color) => {
@@ -304,7 +570,7 @@ impl WireframePass {
                         [max.x, max.y, max.z].into(),
                         color,
                     );
-                    mesh_data.into_iter().for_each(|mesh_data| {
+                    mesh_data.iter().for_each(|mesh_data| {
                         Self::add_mesh(
                             &mut self.instances,
                             &mut self.vertices,
@@ -322,7 +588,7 @@ impl WireframePass {
                         &mut self.instances,
                         &mut self.vertices,
                         &mut self.indices,
-                        mesh_data,
+                        &mesh_data,
                     );
                 }
                 DrawEvent::Arrow(position, direction, color, _is_wireframe) => {
@@ -333,7 +599,7 @@ impl WireframePass {
                         &mut self.instances,
                         &mut self.vertices,
                         &mut self.indices,
-                        mesh_data,
+                        &mesh_data,
                     );
                 }
                 DrawEvent::Sphere(position, radius, color, _is_wireframe) => {
@@ -344,7 +610,7 @@ impl WireframePass {
                         &mut self.instances,
                         &mut self.vertices,
                         &mut self.indices,
-                        mesh_data,
+                        &mesh_data,
                     );
                 }
                 DrawEvent::Circle(position, radius, color, _is_wireframe) => {
@@ -362,9 +628,101 @@ impl WireframePass {
                         &mut self.instances,
                         &mut self.vertices,
                         &mut self.indices,
-                        mesh_data,
+                        &mesh_data,
                     );
                 }
+            })
+            .process_messages(|e: &DataTypeResourceEvent<Mesh>| {
+                //A selected mesh loaded again is drawn with its new geometry
+                let DataTypeResourceEvent::Loaded(id, mesh_data) = e;
+                if let Some((_, data)) = self.selected_meshes.iter_mut().find(|(i, _)| i == id) {
+                    *data = mesh_data.clone();
+                }
             });
     }
 }
+
+#[allow(dead_code)]
+fn test_wireframe_data() {
+    let mut wireframe_data = WireframeData::default();
+    wireframe_data.set_dirty(false);
+
+    wireframe_data.set_color(Vector4::new(1., 0., 0., 1.));
+    assert!(wireframe_data.is_dirty());
+    assert_eq!(wireframe_data.color, [1., 0., 0., 1.]);
+    wireframe_data.set_dirty(false);
+    wireframe_data.set_color(Vector4::new(1., 0., 0., 1.));
+    assert!(!wireframe_data.is_dirty());
+    wireframe_data.set_thickness(3.);
+    assert!(wireframe_data.is_dirty());
+    assert_eq!(wireframe_data.thickness, 3.);
+
+    assert!(wireframe_data.is_overlay());
+    assert_eq!(wireframe_data.depth_compare(), CompareFunction::LessEqual);
+    assert_eq!(wireframe_data.load_color(), LoadOperation::Load);
+    wireframe_data.set_overlay(false);
+    assert_eq!(wireframe_data.depth_compare(), CompareFunction::Less);
+    assert_eq!(wireframe_data.load_color(), LoadOperation::Clear);
+
+    //X-ray ignores the occlusion in both modes
+    wireframe_data.set_xray(true);
+    assert_eq!(wireframe_data.depth_compare(), CompareFunction::Always);
+    wireframe_data.set_overlay(true);
+    assert_eq!(wireframe_data.depth_compare(), CompareFunction::Always);
+}
+
+#[allow(dead_code)]
+fn test_mesh_data_from_buffers() {
+    use crate::create_cube;
+    use inox_uid::generate_random_resource_uid;
+
+    let render_buffers = RenderBuffers::default();
+    let cube = create_cube(Vector3::new(1., 2., 3.), [1., 0., 0., 1.].into());
+    let (first, second) = (
+        generate_random_resource_uid(),
+        generate_random_resource_uid(),
+    );
+    render_buffers.add_mesh(&first, &cube).unwrap();
+    render_buffers.add_mesh(&second, &cube).unwrap();
+
+    //Vertices of the second mesh start after the ones of the first in the shared buffers
+    let mesh_data = WireframePass::mesh_data_from_buffers(
+        &render_buffers,
+        &second,
+        cube.aabb_min,
+        cube.aabb_max,
+    )
+    .unwrap();
+    assert_eq!(mesh_data.indices, cube.indices);
+    assert_eq!(mesh_data.positions, cube.positions);
+    assert_eq!(mesh_data.colors, cube.colors);
+    mesh_data
+        .vertices
+        .iter()
+        .zip(cube.vertices.iter())
+        .for_each(|(v, c)| {
+            assert_eq!(v.position_and_color_offset, c.position_and_color_offset);
+        });
+
+    let mut instances = VecDebugInstance::default();
+    let mut vertices = VecDebugVertex::default();
+    let mut indices = VecDebugIndex::default();
+    WireframePass::add_mesh(&mut instances, &mut vertices, &mut indices, &mesh_data);
+    assert_eq!(instances.data.len(), 1);
+    assert_eq!(vertices.data.len(), cube.indices.len());
+
+    render_buffers.remove_mesh(&second, false);
+    assert!(WireframePass::mesh_data_from_buffers(
+        &render_buffers,
+        &second,
+        cube.aabb_min,
+        cube.aabb_max
+    )
+    .is_none());
+}
+
+#[test]
+fn test() {
+    test_wireframe_data();
+    test_mesh_data_from_buffers();
+}
@@ -4,17 +4,21 @@ use std::{
     sync::{Arc, RwLock},
 };
 
-use inox_bhv::{BHVTree, AABB};
-use inox_math::{quantize_snorm, InnerSpace, Mat4Ops, MatBase, Matrix4};
-use inox_resources::{to_slice, Buffer, HashBuffer};
-use inox_uid::{generate_static_uid_from_string, Uid};
+use inox_bhv::{BHVBuildQuality, BHVTree, AABB};
+use inox_core::JobHandlerRw;
+use inox_math::{InnerSpace, Mat4Ops, MatBase, Matrix4};
+use inox_resources::{to_slice, Buffer, BufferError, HashBuffer, ResourceId};
+use inox_uid::{generate_resource_uid_from_string, generate_static_resource_uid_from_string};
 
 use crate::{
-    declare_as_binding_vector, utils::create_linearized_bhv, AsBinding, BindingDataBuffer,
-    ConeCulling, DrawBHVNode, DrawMaterial, DrawMesh, DrawMeshlet, DrawRay, DrawVertex, Light,
-    LightData, LightId, Material, MaterialAlphaMode, MaterialData, MaterialId, Mesh, MeshData,
-    MeshFlags, MeshId, RenderCommandsPerType, RenderCoreContext, TextureId, TextureInfo,
-    TextureType, INVALID_INDEX, MAX_TEXTURE_COORDS_SETS,
+    declare_as_binding_vector,
+    utils::{
+        create_bhv_in_jobs, create_linearized_bhv, pack_cone_axis_cutoff, PARALLEL_BHV_MIN_AABBS,
+    },
+    AsBinding, BindingDataBuffer, ConeCulling, DrawBHVNode, DrawMaterial, DrawMesh, DrawMeshlet,
+    DrawRay, DrawVertex, Light, LightData, LightId, Material, MaterialAlphaMode, MaterialData,
+    MaterialFlags, MaterialId, Mesh, MeshData, MeshFlags, MeshId, RenderCommandsPerType,
+    RenderCoreContext, TextureId, TextureInfo, TextureType, INVALID_INDEX, MAX_TEXTURE_COORDS_SETS,
 };
 
 declare_as_binding_vector!(VecVisibleDrawData, u32);
@@ -25,6 +29,8 @@ pub type MaterialsBuffer = Arc<RwLock<HashBuffer<MaterialId, DrawMaterial, 0>>>;
 pub type CommandsBuffer = Arc<RwLock<HashMap<MeshFlags, RenderCommandsPerType>>>;
 pub type MeshesBuffer = Arc<RwLock<HashBuffer<MeshId, DrawMesh, 0>>>;
 pub type MeshesFlagsBuffer = Arc<RwLock<HashBuffer<MeshId, MeshFlags, 0>>>;
+pub type MeshesLayerMaskBuffer = Arc<RwLock<HashMap<MeshId, u32>>>;
+pub type MeshesInstancesBuffer = Arc<RwLock<HashMap<MeshId, Vec<MeshId>>>>; //MeshId <-> [MeshId]
 pub type MeshesInverseMatrixBuffer = Arc<RwLock<HashBuffer<MeshId, [[f32; 4]; 4], 0>>>;
 pub type MeshletsBuffer = Arc<RwLock<Buffer<DrawMeshlet>>>; //MeshId <-> [DrawMeshlet]
 pub type MeshletsCullingBuffer = Arc<RwLock<Buffer<ConeCulling>>>; //MeshId <-> [DrawMeshlet]
@@ -38,10 +44,25 @@ pub type VertexUVsBuffer = Arc<RwLock<Buffer<u32>>>; //MeshId <-> [u32] (2 half)
 pub type RaysBuffer = Arc<RwLock<Buffer<DrawRay>>>;
 pub type CullingResults = Arc<RwLock<VecVisibleDrawData>>;
 
-const TLAS_UID: Uid = generate_static_uid_from_string("TLAS");
-pub const NUM_COMMANDS_PER_GROUP: u32 = 32;
+const TLAS_UID: ResourceId = generate_static_resource_uid_from_string("TLAS");
+//Visibility of the meshlets is packed as one bit each in the words of the culling results
+pub const CULLING_RESULT_BITS: u32 = u32::BITS;
+pub const DEFAULT_CULLING_WORKGROUP_SIZE: u32 = 32;
 
-//Alignment should be 4, 8, 16 or 32 bytes
+pub fn culling_result_len(num_meshlets: usize) -> usize {
+    num_meshlets.div_ceil(CULLING_RESULT_BITS as usize)
+}
+pub fn culling_dispatch_count(num_meshlets: usize, workgroup_size: u32) -> u32 {
+    num_meshlets.div_ceil(workgroup_size as usize) as u32
+}
+
+fn instance_id(mesh_id: &MeshId, instance_index: usize) -> MeshId {
+    generate_resource_uid_from_string(&format!("{mesh_id}_instance_{instance_index}"))
+}
+
+//Alignment should be 4, 8, 16 or 32 bytes.
+//Resources are added, updated and removed following their events in send order:
+//an update of a resource not added yet or already removed is ignored
 #[derive(Default)]
 pub struct RenderBuffers {
     pub textures: TexturesBuffer,
@@ -50,6 +71,13 @@ pub struct RenderBuffers {
     pub commands: CommandsBuffer,
     pub meshes: MeshesBuffer,
     pub meshes_flags: MeshesFlagsBuffer,
+    pub meshes_layer_mask: MeshesLayerMaskBuffer,
+    //DrawMeshes of the other instances of a mesh, the mesh itself is the first one
+    pub meshes_instances: MeshesInstancesBuffer,
+    //Layers not in the mask of the active view, their meshes have no commands
+    hidden_layers: Arc<RwLock<u32>>,
+    //Used to build the bhv of big meshes in parallel
+    job_handler: Arc<RwLock<Option<JobHandlerRw>>>,
     pub meshes_inverse_matrix: MeshesInverseMatrixBuffer,
     pub meshlets: MeshletsBuffer,
     pub meshlets_culling: MeshletsCullingBuffer,
@@ -66,12 +94,34 @@ pub struct RenderBuffers {
 }
 
 impl RenderBuffers {
+    //Meshes data is bound as storage buffers, so each buffer can't grow past the binding size
+    pub fn set_limits(&self, limits: &wgpu::Limits) -> &Self {
+        fn max_len<T>(buffer: &RwLock<Buffer<T>>, limits: &wgpu::Limits) {
+            let max_len =
+                limits.max_storage_buffer_binding_size as usize / std::mem::size_of::<T>();
+            buffer.write().unwrap().set_max_len(Some(max_len));
+        }
+        max_len(&self.meshlets, limits);
+        max_len(&self.meshlets_culling, limits);
+        max_len(&self.bhv, limits);
+        max_len(&self.vertices, limits);
+        max_len(&self.indices, limits);
+        max_len(&self.vertex_positions, limits);
+        max_len(&self.vertex_colors, limits);
+        max_len(&self.vertex_normals, limits);
+        max_len(&self.vertex_uvs, limits);
+        self
+    }
+    pub fn set_job_handler(&self, job_handler: &JobHandlerRw) -> &Self {
+        *self.job_handler.write().unwrap() = Some(job_handler.clone());
+        self
+    }
     fn extract_meshlets(
         &self,
         mesh_data: &MeshData,
         mesh_id: &MeshId,
         mesh_index: u32,
-    ) -> (usize, usize) {
+    ) -> Result<(usize, usize), BufferError> {
         inox_profiler::scoped_profile!("render_buffers::extract_meshlets");
 
         let mut meshlets = Vec::new();
@@ -92,16 +142,12 @@ impl RenderBuffers {
                     bvh_index: i as _,
                 };
                 meshlets[i] = meshlet;
-                let cone_axis = meshlet_data.cone_axis.normalize();
-                let cone_axis_cutoff = [
-                    quantize_snorm(cone_axis.x, 8) as i8,
-                    quantize_snorm(cone_axis.y, 8) as i8,
-                    quantize_snorm(cone_axis.z, 8) as i8,
-                    quantize_snorm(meshlet_data.cone_angle, 8) as i8,
-                ];
                 meshlets_cones[i] = ConeCulling {
                     center: meshlet_data.cone_center.into(),
-                    cone_axis_cutoff,
+                    cone_axis_cutoff: pack_cone_axis_cutoff(
+                        meshlet_data.cone_axis.normalize(),
+                        meshlet_data.cone_angle,
+                    ),
                 };
                 meshlets_aabbs[i] =
                     AABB::create(meshlet_data.aabb_min, meshlet_data.aabb_max, i as _);
@@ -110,48 +156,58 @@ impl RenderBuffers {
             inox_log::debug_log!("No meshlet data for mesh {:?}", mesh_id);
         }
 
-        let bhv = BHVTree::new(&meshlets_aabbs);
+        //Built once per mesh, so it's worth the slower build of a faster tree to traverse
+        let bhv = match self.job_handler.read().unwrap().as_ref() {
+            Some(job_handler) if meshlets_aabbs.len() > PARALLEL_BHV_MIN_AABBS => {
+                create_bhv_in_jobs(
+                    &meshlets_aabbs,
+                    BHVBuildQuality::SurfaceAreaHeuristic,
+                    job_handler,
+                )
+            }
+            _ => BHVTree::new_sah(&meshlets_aabbs),
+        };
         let linearized_bhv = create_linearized_bhv(&bhv);
         let mesh_bhv_range = self
             .bhv
             .write()
             .unwrap()
-            .allocate(mesh_id, &linearized_bhv)
+            .try_allocate(mesh_id, &linearized_bhv)?
             .1;
         self.meshlets_culling
             .write()
             .unwrap()
-            .allocate(mesh_id, meshlets_cones.as_slice());
+            .try_allocate(mesh_id, meshlets_cones.as_slice())?;
         let meshlet_range = self
             .meshlets
             .write()
             .unwrap()
-            .allocate(mesh_id, meshlets.as_slice())
+            .try_allocate(mesh_id, meshlets.as_slice())?
             .1;
-        (mesh_bhv_range.start, meshlet_range.start)
+        Ok((mesh_bhv_range.start, meshlet_range.start))
     }
     fn add_vertex_data(
         &self,
         mesh_id: &MeshId,
         mesh_data: &MeshData,
         mesh_index: u32,
-    ) -> (u32, u32) {
+    ) -> Result<(u32, u32), BufferError> {
         inox_profiler::scoped_profile!("render_buffers::add_vertex_data");
 
         if mesh_data.vertices.is_empty() {
             inox_log::debug_log!("No vertices for mesh {:?}", mesh_id);
-            return (0, 0);
+            return Ok((0, 0));
         }
         if mesh_data.indices.is_empty() {
             inox_log::debug_log!("No indices for mesh {:?}", mesh_id);
-            return (0, 0);
+            return Ok((0, 0));
         }
 
         let position_range = self
             .vertex_positions
             .write()
             .unwrap()
-            .allocate(mesh_id, to_slice(mesh_data.positions.as_slice()))
+            .try_allocate(mesh_id, to_slice(mesh_data.positions.as_slice()))?
             .1;
         //We're expecting positions and colors to be always present
         if mesh_data.colors.is_empty() {
@@ -159,12 +215,12 @@ impl RenderBuffers {
             self.vertex_colors
                 .write()
                 .unwrap()
-                .allocate(mesh_id, to_slice(colors.as_slice()));
+                .try_allocate(mesh_id, to_slice(colors.as_slice()))?;
         } else {
             self.vertex_colors
                 .write()
                 .unwrap()
-                .allocate(mesh_id, to_slice(mesh_data.colors.as_slice()));
+                .try_allocate(mesh_id, to_slice(mesh_data.colors.as_slice()))?;
         }
 
         let mut normal_range = Range::<usize>::default();
@@ -173,7 +229,7 @@ impl RenderBuffers {
                 .vertex_normals
                 .write()
                 .unwrap()
-                .allocate(mesh_id, to_slice(mesh_data.normals.as_slice()))
+                .try_allocate(mesh_id, to_slice(mesh_data.normals.as_slice()))?
                 .1;
         }
 
@@ -183,7 +239,7 @@ impl RenderBuffers {
                 .vertex_uvs
                 .write()
                 .unwrap()
-                .allocate(mesh_id, to_slice(mesh_data.uvs.as_slice()))
+                .try_allocate(mesh_id, to_slice(mesh_data.uvs.as_slice()))?
                 .1;
         }
 
@@ -200,23 +256,24 @@ impl RenderBuffers {
             .vertices
             .write()
             .unwrap()
-            .allocate(mesh_id, vertices.as_slice())
+            .try_allocate(mesh_id, vertices.as_slice())?
             .1
             .start;
         let indices_offset = self
             .indices
             .write()
             .unwrap()
-            .allocate(mesh_id, mesh_data.indices.as_slice())
+            .try_allocate(mesh_id, mesh_data.indices.as_slice())?
             .1
             .start;
-        (vertex_offset as _, indices_offset as _)
+        Ok((vertex_offset as _, indices_offset as _))
     }
-    pub fn add_mesh(&self, mesh_id: &MeshId, mesh_data: &MeshData) {
+    //On failure the mesh is not added and its data already allocated is released
+    pub fn add_mesh(&self, mesh_id: &MeshId, mesh_data: &MeshData) -> Result<(), BufferError> {
         inox_profiler::scoped_profile!("render_buffers::add_mesh");
         self.remove_mesh(mesh_id, false);
         if mesh_data.vertex_count() == 0 {
-            return;
+            return Ok(());
         }
         let mesh_index = self
             .meshes
@@ -228,10 +285,19 @@ impl RenderBuffers {
             .unwrap()
             .insert(mesh_id, Matrix4::default_identity().inverse().into());
 
-        let (vertex_offset, indices_offset) =
-            self.add_vertex_data(mesh_id, mesh_data, mesh_index as _);
-        let (bhv_index, meshlet_offset) =
-            self.extract_meshlets(mesh_data, mesh_id, mesh_index as _);
+        let offsets = self
+            .add_vertex_data(mesh_id, mesh_data, mesh_index as _)
+            .and_then(|vertex_data| {
+                self.extract_meshlets(mesh_data, mesh_id, mesh_index as _)
+                    .map(|meshlets| (vertex_data, meshlets))
+            });
+        let ((vertex_offset, indices_offset), (bhv_index, meshlet_offset)) = match offsets {
+            Ok(offsets) => offsets,
+            Err(e) => {
+                self.remove_mesh(mesh_id, false);
+                return Err(e);
+            }
+        };
 
         {
             let mut meshes = self.meshes.write().unwrap();
@@ -241,18 +307,19 @@ impl RenderBuffers {
             mesh.bhv_index = bhv_index as _;
             mesh.meshlets_offset = meshlet_offset as _;
             mesh.meshlets_count = mesh_data.meshlets.len() as _;
+            let (center, radius) = mesh_data.bounding_sphere();
+            mesh.bounding_sphere = [center.x, center.y, center.z, radius];
         }
         self.recreate_tlas();
         self.update_culling_data();
+        Ok(())
     }
     fn update_culling_data(&self) {
         let num_meshlets = self.meshlets.read().unwrap().item_count();
-        let count =
-            ((num_meshlets as u32 + NUM_COMMANDS_PER_GROUP - 1) / NUM_COMMANDS_PER_GROUP) as usize;
         self.culling_result
             .write()
             .unwrap()
-            .set(vec![u32::MAX; count]);
+            .set(vec![u32::MAX; culling_result_len(num_meshlets)]);
     }
     fn recreate_tlas(&self) {
         inox_profiler::scoped_profile!("render_buffers::recreate_tlas");
@@ -268,10 +335,8 @@ impl RenderBuffers {
                     mesh.orientation.into(),
                     mesh.scale.into(),
                 );
-                let min = matrix.rotate_point(node.min.into());
-                let max = matrix.rotate_point(node.max.into());
-                let aabb = AABB::create(min, max, i as _);
-                meshes_aabbs.push(aabb);
+                let aabb = AABB::create(node.min.into(), node.max.into(), i as _);
+                meshes_aabbs.push(aabb.transformed(&matrix));
             });
         }
         let bhv = BHVTree::new(&meshes_aabbs);
@@ -279,10 +344,9 @@ impl RenderBuffers {
         let mut tlas = self.tlas.write().unwrap();
         tlas.allocate(&TLAS_UID, &linearized_bhv);
     }
-    fn update_transform(&self, mesh: &mut Mesh, m: &mut DrawMesh) -> bool {
+    fn update_transform(&self, matrix: Matrix4, m: &mut DrawMesh) -> bool {
         inox_profiler::scoped_profile!("render_buffers::update_transform");
 
-        let matrix = mesh.matrix();
         let new_pos = matrix.translation();
         let new_orientation = matrix.orientation();
         let new_scale = matrix.scale();
@@ -297,34 +361,190 @@ impl RenderBuffers {
         }
         false
     }
+    //Instances are copies of the DrawMesh with the same geometry and their own transform.
+    //Their meshlets are interleaved, so that the copies of a meshlet are contiguous
+    //and a single command draws all of them
+    fn resize_instances(
+        &self,
+        mesh_id: &MeshId,
+        instance_count: usize,
+        meshes: &mut HashBuffer<MeshId, DrawMesh, 0>,
+    ) -> Result<bool, BufferError> {
+        inox_profiler::scoped_profile!("render_buffers::resize_instances");
+
+        let Some(draw_mesh) = meshes.get(mesh_id).copied() else {
+            return Ok(false);
+        };
+        if draw_mesh.instances_count as usize == instance_count {
+            return Ok(false);
+        }
+        let mut meshes_instances = self.meshes_instances.write().unwrap();
+        let instances = meshes_instances.entry(*mesh_id).or_default();
+        {
+            let mut meshes_flags = self.meshes_flags.write().unwrap();
+            let mut meshes_inverse_matrix = self.meshes_inverse_matrix.write().unwrap();
+            let removed = instances.split_off(instances.len().min(instance_count - 1));
+            removed.iter().for_each(|id| {
+                meshes.remove(id);
+                meshes_flags.remove(id);
+                meshes_inverse_matrix.remove(id);
+            });
+            while instances.len() < instance_count - 1 {
+                let id = instance_id(mesh_id, instances.len() + 1);
+                meshes.insert(&id, draw_mesh);
+                meshes_inverse_matrix.insert(&id, draw_mesh.transform().inverse().into());
+                instances.push(id);
+            }
+        }
+
+        let mesh_indices = std::iter::once(mesh_id)
+            .chain(instances.iter())
+            .map(|id| meshes.index_of(id).unwrap() as u32)
+            .collect::<Vec<_>>();
+        let mut meshlets = Vec::with_capacity(draw_mesh.meshlets_count as usize * instance_count);
+        let mut meshlets_cones = Vec::with_capacity(meshlets.capacity());
+        {
+            let old_meshlets = self.meshlets.read().unwrap();
+            let old_meshlets_culling = self.meshlets_culling.read().unwrap();
+            let old_meshlets = old_meshlets.items(mesh_id).unwrap_or(&[]);
+            let old_cones = old_meshlets_culling.items(mesh_id).unwrap_or(&[]);
+            old_meshlets
+                .iter()
+                .zip(old_cones)
+                .step_by(draw_mesh.instances_count as usize)
+                .for_each(|(meshlet, cone)| {
+                    mesh_indices.iter().for_each(|&mesh_index| {
+                        meshlets.push(DrawMeshlet {
+                            mesh_index,
+                            ..*meshlet
+                        });
+                        meshlets_cones.push(*cone);
+                    });
+                });
+        }
+        let meshlets_range = self
+            .meshlets
+            .write()
+            .unwrap()
+            .try_allocate(mesh_id, meshlets.as_slice())?
+            .1;
+        self.meshlets_culling
+            .write()
+            .unwrap()
+            .try_allocate(mesh_id, meshlets_cones.as_slice())?;
+
+        std::iter::once(mesh_id)
+            .chain(instances.iter())
+            .enumerate()
+            .for_each(|(i, id)| {
+                if let Some(m) = meshes.get_mut(id) {
+                    m.meshlets_offset = (meshlets_range.start + i) as _;
+                    m.instances_count = instance_count as _;
+                    m.instance_index = i as _;
+                }
+            });
+        Ok(true)
+    }
+    //Instances share everything with the mesh but the transform
+    fn update_instances(
+        &self,
+        mesh_id: &MeshId,
+        mesh: &Mesh,
+        meshes: &mut HashBuffer<MeshId, DrawMesh, 0>,
+    ) -> bool {
+        inox_profiler::scoped_profile!("render_buffers::update_instances");
+
+        let Some(draw_mesh) = meshes.get(mesh_id).copied() else {
+            return false;
+        };
+        let meshes_instances = self.meshes_instances.read().unwrap();
+        let Some(instances) = meshes_instances.get(mesh_id) else {
+            return false;
+        };
+        let mesh_flags = mesh.effective_flags();
+        let mut meshes_flags = self.meshes_flags.write().unwrap();
+        let mut meshes_inverse_matrix = self.meshes_inverse_matrix.write().unwrap();
+        let mut is_matrix_changed = false;
+        instances
+            .iter()
+            .zip(mesh.instances())
+            .for_each(|(id, matrix)| {
+                if let Some(m) = meshes.get_mut(id) {
+                    *m = DrawMesh {
+                        position: m.position,
+                        orientation: m.orientation,
+                        scale: m.scale,
+                        meshlets_offset: m.meshlets_offset,
+                        instance_index: m.instance_index,
+                        ..draw_mesh
+                    };
+                    if self.update_transform(*matrix, m) {
+                        if let Some(mat) = meshes_inverse_matrix.get_mut(id) {
+                            *mat = matrix.inverse().into();
+                        }
+                        is_matrix_changed = true;
+                    }
+                    meshes_flags.insert(id, mesh_flags);
+                }
+            });
+        is_matrix_changed
+    }
     pub fn change_mesh(&self, mesh_id: &MeshId, mesh: &mut Mesh) {
         inox_profiler::scoped_profile!("render_buffers::change_mesh");
         let mut is_matrix_changed = false;
         {
             let mut meshes = self.meshes.write().unwrap();
+            match self.resize_instances(mesh_id, mesh.instance_count(), &mut meshes) {
+                Ok(is_resized) => {
+                    if is_resized {
+                        self.update_culling_data();
+                        is_matrix_changed = true;
+                    }
+                }
+                Err(e) => {
+                    inox_log::debug_log!("Unable to add instances of mesh {:?}: {}", mesh_id, e);
+                    drop(meshes);
+                    self.remove_mesh(mesh_id, true);
+                    return;
+                }
+            }
             if let Some(m) = meshes.get_mut(mesh_id) {
+                let mut is_transparent =
+                    mesh.has_flags(MeshFlags::UseVertexColor) && mesh.has_vertex_alpha();
                 if let Some(material) = mesh.material() {
-                    if let Some(index) = self.materials.read().unwrap().index_of(material.id()) {
+                    let materials = self.materials.read().unwrap();
+                    if let Some(index) = materials.index_of(material.id()) {
                         m.material_index = index as _;
                     }
-                    if let Some(material) = self.materials.write().unwrap().get_mut(material.id()) {
+                    if let Some(material) = materials.get(material.id()) {
                         let blend_alpha_mode: u32 = MaterialAlphaMode::Blend.into();
-                        if material.alpha_mode == blend_alpha_mode || material.base_color[3] < 1. {
-                            mesh.remove_flag(MeshFlags::Opaque);
-                            mesh.add_flag(MeshFlags::Tranparent);
-                        }
+                        //Transmission is approximated blending the surface with what's behind
+                        is_transparent |= material.alpha_mode == blend_alpha_mode
+                            || material.base_color[3] < 1.
+                            || material.transmission_factor > 0.;
                     }
                 }
+                //Recomputed at every change, the material can become opaque again
+                if mesh.has_flags(MeshFlags::Opaque) || mesh.has_flags(MeshFlags::Transparent) {
+                    let (removed, added) = if is_transparent {
+                        (MeshFlags::Opaque, MeshFlags::Transparent)
+                    } else {
+                        (MeshFlags::Transparent, MeshFlags::Opaque)
+                    };
+                    mesh.remove_flag(removed);
+                    mesh.add_flag(added);
+                }
 
-                is_matrix_changed = self.update_transform(mesh, m);
-                if is_matrix_changed {
+                if self.update_transform(mesh.matrix(), m) {
+                    is_matrix_changed = true;
                     let mut meshes_inverse_matrix = self.meshes_inverse_matrix.write().unwrap();
                     if let Some(mat) = meshes_inverse_matrix.get_mut(mesh_id) {
                         *mat = mesh.matrix().inverse().into();
                     }
                 }
 
-                let mesh_flags = mesh.flags();
+                m.flags = (*mesh.flags()).into();
+                let mesh_flags = mesh.effective_flags();
                 {
                     let mut commands = self.commands.write().unwrap();
                     let mut meshes_flags = self.meshes_flags.write().unwrap();
@@ -332,27 +552,83 @@ impl RenderBuffers {
                         let entry = commands.entry(*flags).or_default();
                         entry.remove_commands(mesh_id);
 
-                        *flags = *mesh_flags;
+                        *flags = mesh_flags;
                     } else {
-                        meshes_flags.insert(mesh_id, *mesh_flags);
+                        meshes_flags.insert(mesh_id, mesh_flags);
                     }
                     meshes_flags.set_dirty(true);
+                    self.meshes_layer_mask
+                        .write()
+                        .unwrap()
+                        .insert(*mesh_id, mesh.layer_mask());
 
-                    let entry = commands.entry(*mesh_flags).or_default();
-                    entry.add_commands(mesh_id, m, &self.meshlets.read().unwrap());
+                    let entry = commands.entry(mesh_flags).or_default();
+                    if self.is_layer_drawn(mesh.layer_mask()) {
+                        entry.add_commands(mesh_id, m, &self.meshlets.read().unwrap());
+                    } else {
+                        entry.remove_commands(mesh_id);
+                    }
                 }
-
-                meshes.set_dirty(true);
             }
+            if self.update_instances(mesh_id, mesh, &mut meshes) {
+                is_matrix_changed = true;
+            }
+            meshes.set_dirty(true);
         }
         if is_matrix_changed {
             self.recreate_tlas();
         }
     }
+    fn is_layer_drawn(&self, layer_mask: u32) -> bool {
+        layer_mask & !*self.hidden_layers.read().unwrap() != 0
+    }
+    pub fn layer_mask(&self) -> u32 {
+        !*self.hidden_layers.read().unwrap()
+    }
+    //Rebuilds the commands with only the meshes on the layers of the view mask.
+    //Returns false when the mask is the same and nothing changed
+    pub fn set_layer_mask(&self, layer_mask: u32) -> bool {
+        inox_profiler::scoped_profile!("render_buffers::set_layer_mask");
+
+        if self.layer_mask() == layer_mask {
+            return false;
+        }
+        *self.hidden_layers.write().unwrap() = !layer_mask;
+
+        let meshes = self.meshes.read().unwrap();
+        let meshes_flags = self.meshes_flags.read().unwrap();
+        let meshlets = self.meshlets.read().unwrap();
+        let mut commands = self.commands.write().unwrap();
+        self.meshes_layer_mask
+            .read()
+            .unwrap()
+            .iter()
+            .for_each(|(mesh_id, mesh_layer_mask)| {
+                if let (Some(m), Some(flags)) = (meshes.get(mesh_id), meshes_flags.get(mesh_id)) {
+                    let entry = commands.entry(*flags).or_default();
+                    if mesh_layer_mask & layer_mask != 0 {
+                        entry.add_commands(mesh_id, m, &meshlets);
+                    } else {
+                        entry.remove_commands(mesh_id);
+                    }
+                }
+            });
+        true
+    }
     pub fn remove_mesh(&self, mesh_id: &MeshId, recreate_tlas: bool) {
         inox_profiler::scoped_profile!("render_buffers::remove_mesh");
 
         if self.meshes.write().unwrap().remove(mesh_id).is_some() {
+            if let Some(instances) = self.meshes_instances.write().unwrap().remove(mesh_id) {
+                let mut meshes = self.meshes.write().unwrap();
+                let mut meshes_flags = self.meshes_flags.write().unwrap();
+                let mut meshes_inverse_matrix = self.meshes_inverse_matrix.write().unwrap();
+                instances.iter().for_each(|id| {
+                    meshes.remove(id);
+                    meshes_flags.remove(id);
+                    meshes_inverse_matrix.remove(id);
+                });
+            }
             self.commands
                 .write()
                 .unwrap()
@@ -361,6 +637,7 @@ impl RenderBuffers {
                     entry.remove_commands(mesh_id);
                 });
             self.meshes_flags.write().unwrap().remove(mesh_id);
+            self.meshes_layer_mask.write().unwrap().remove(mesh_id);
             self.meshes_inverse_matrix.write().unwrap().remove(mesh_id);
             self.meshlets.write().unwrap().remove(mesh_id);
             self.meshlets_culling.write().unwrap().remove(mesh_id);
@@ -395,13 +672,9 @@ impl RenderBuffers {
         if let Some(m) = materials.get_mut(material_id) {
             m.textures_indices = textures_indices;
         } else {
-            let index = materials.insert(
-                material_id,
-                DrawMaterial {
-                    textures_indices,
-                    ..Default::default()
-                },
-            );
+            let mut draw_material = DrawMaterial::default();
+            draw_material.textures_indices = textures_indices;
+            let index = materials.insert(material_id, draw_material);
             material.set_material_index(index as _);
         }
         materials.set_dirty(true);
@@ -421,6 +694,24 @@ impl RenderBuffers {
             material.alpha_mode = material_data.alpha_mode.into();
             material.base_color = material_data.base_color.into();
             material.emissive_color = material_data.emissive_color.into();
+            //Negative strengths would make the surfaces absorb light
+            material.emissive_strength = material_data.emissive_strength.max(0.);
+            material.transmission_factor = material_data.transmission_factor.clamp(0., 1.);
+            material.thickness_factor = material_data.thickness_factor.max(0.);
+            material.ior = material_data.ior;
+            material.attenuation_color = material_data.attenuation_color.into();
+            material.attenuation_distance = material_data.attenuation_distance;
+            material.clearcoat_factor = material_data.clearcoat_factor.clamp(0., 1.);
+            material.clearcoat_roughness_factor =
+                material_data.clearcoat_roughness_factor.clamp(0., 1.);
+            let mut flags = MaterialFlags::None;
+            if material_data.double_sided {
+                flags |= MaterialFlags::DoubleSided;
+            }
+            if material_data.unlit {
+                flags |= MaterialFlags::Unlit;
+            }
+            material.flags = flags.into();
             material.occlusion_strength = material_data.occlusion_strength;
             material.diffuse_color = material_data.diffuse_color.into();
             material.specular_color = material_data.specular_color.into();
@@ -519,3 +810,296 @@ impl RenderBuffers {
             });
     }
 }
+
+#[allow(dead_code)]
+fn test_layer_mask() {
+    use crate::{create_cube, DrawCommandType, LAYER_MASK_ALL};
+    use inox_math::Vector3;
+    use inox_messenger::MessageHub;
+    use inox_resources::{DataTypeResource, SharedDataRc};
+    use inox_uid::generate_random_resource_uid;
+
+    let shared_data = SharedDataRc::default();
+    let message_hub = Arc::new(MessageHub::default());
+    let render_buffers = RenderBuffers::default();
+    let mut meshes = (0..2)
+        .map(|_| {
+            let id = generate_random_resource_uid();
+            render_buffers
+                .add_mesh(
+                    &id,
+                    &create_cube(Vector3::new(1., 1., 1.), [1., 1., 1., 1.].into()),
+                )
+                .unwrap();
+            (id, Mesh::new(id, &shared_data, &message_hub))
+        })
+        .collect::<Vec<_>>();
+    let drawn_meshes = || {
+        let commands = render_buffers.commands.read().unwrap();
+        commands
+            .get(&(MeshFlags::Visible | MeshFlags::Opaque))
+            .and_then(|entry| entry.map.get(&DrawCommandType::PerMeshlet))
+            .map_or(0, |entry| entry.commands.item_count())
+    };
+
+    //The second mesh is only on layer 2
+    meshes[1].1.set_layer_mask(1 << 2);
+    meshes.iter_mut().for_each(|(id, mesh)| {
+        render_buffers.change_mesh(id, mesh);
+    });
+    assert_eq!(render_buffers.layer_mask(), LAYER_MASK_ALL);
+    assert_eq!(drawn_meshes(), 2);
+
+    //Views without layer 2 skip it
+    assert!(render_buffers.set_layer_mask(1));
+    assert!(!render_buffers.set_layer_mask(1));
+    assert_eq!(drawn_meshes(), 1);
+    let commands = render_buffers.commands.read().unwrap();
+    let entry =
+        &commands[&(MeshFlags::Visible | MeshFlags::Opaque)].map[&DrawCommandType::PerMeshlet];
+    assert!(entry.commands.get(&meshes[0].0).is_some());
+    assert!(entry.commands.get(&meshes[1].0).is_none());
+    drop(commands);
+
+    //Changes of a mesh hidden by the view mask don't add its commands
+    render_buffers.change_mesh(&meshes[1].0, &mut meshes[1].1);
+    assert_eq!(drawn_meshes(), 1);
+
+    assert!(render_buffers.set_layer_mask(1 << 2 | 1 << 5));
+    assert_eq!(drawn_meshes(), 2);
+    assert!(render_buffers.set_layer_mask(1 << 5));
+    assert_eq!(drawn_meshes(), 1);
+    assert!(render_buffers.set_layer_mask(LAYER_MASK_ALL));
+    assert_eq!(drawn_meshes(), 2);
+}
+
+#[allow(dead_code)]
+fn test_procedural_shapes() {
+    use crate::{create_cone, create_cube, create_cylinder, create_plane, create_sphere};
+    use inox_math::{Vector2, Vector3, Vector4};
+    use inox_uid::generate_random_resource_uid;
+
+    let render_buffers = RenderBuffers::default();
+    let color = Vector4::new(1., 1., 1., 1.);
+    [
+        create_cube(Vector3::new(1., 1., 1.), color),
+        create_sphere(Vector3::new(0., 0., 0.), 1., 32, 16, color),
+        create_cylinder(1., 1., 16, 2., 4, color),
+        create_cone(1., 16, 2., color),
+        create_plane(Vector2::new(1., 1.), 8, color),
+    ]
+    .iter()
+    .for_each(|mesh_data| {
+        let id = generate_random_resource_uid();
+        render_buffers.add_mesh(&id, mesh_data).unwrap();
+        let meshes = render_buffers.meshes.read().unwrap();
+        let mesh = meshes.get(&id).unwrap();
+        let meshlets = render_buffers.meshlets.read().unwrap();
+        let mesh_meshlets = meshlets.items(&id).unwrap();
+        assert_eq!(
+            mesh.meshlets_offset as usize,
+            meshlets.get(&id).unwrap().range().start
+        );
+        assert_eq!(mesh.meshlets_count as usize, mesh_meshlets.len());
+        let indices_count = mesh_meshlets
+            .iter()
+            .map(|m| m.indices_count as usize)
+            .sum::<usize>();
+        assert_eq!(indices_count, mesh_data.index_count());
+        let (center, radius) = mesh_data.bounding_sphere();
+        assert_eq!(mesh.bounding_sphere, [center.x, center.y, center.z, radius]);
+        assert_eq!(mesh.world_bounding_sphere(), (center, radius));
+        assert!(render_buffers.bhv.read().unwrap().get(&id).is_some());
+    });
+}
+
+#[allow(dead_code)]
+fn test_instances() {
+    use crate::{create_cube, DrawCommandType};
+    use inox_math::{Vector3, Vector4};
+    use inox_messenger::MessageHub;
+    use inox_resources::{DataTypeResource, SharedDataRc};
+    use inox_uid::generate_random_resource_uid;
+
+    let shared_data = SharedDataRc::default();
+    let message_hub = Arc::new(MessageHub::default());
+    let render_buffers = RenderBuffers::default();
+    let id = generate_random_resource_uid();
+    let mesh_data = create_cube(Vector3::new(1., 1., 1.), Vector4::new(1., 1., 1., 1.));
+    render_buffers.add_mesh(&id, &mesh_data).unwrap();
+    let mut mesh = Mesh::new(id, &shared_data, &message_hub);
+    let meshlets_count = mesh_data.meshlets.len();
+
+    let transforms = |count: usize| {
+        (1..count)
+            .map(|i| Matrix4::from_translation([i as f32 * 2., 0., 0.].into()))
+            .collect::<Vec<_>>()
+    };
+    let check_instances = |instance_count: usize| {
+        let commands = render_buffers.commands.read().unwrap();
+        let commands = &commands[&(MeshFlags::Visible | MeshFlags::Opaque)].map
+            [&DrawCommandType::PerMeshlet]
+            .commands;
+        //A command for each meshlet, whatever the number of instances
+        assert_eq!(commands.item_count(), meshlets_count);
+        let meshes = render_buffers.meshes.read().unwrap();
+        assert_eq!(meshes.item_count(), instance_count);
+        let meshlets = render_buffers.meshlets.read().unwrap();
+        let mesh_meshlets = meshlets.items(&id).unwrap();
+        assert_eq!(mesh_meshlets.len(), meshlets_count * instance_count);
+        let mesh = meshes.get(&id).unwrap();
+        commands.items(&id).unwrap().iter().for_each(|command| {
+            assert_eq!(command.instance_count as usize, instance_count);
+            //The instances drawn by a command are the copies of the same meshlet
+            let first = command.base_instance as usize;
+            (first..first + instance_count).for_each(|i| {
+                let meshlet = &meshlets.data()[i];
+                let instance = meshes.at(meshlet.mesh_index as _);
+                assert_eq!(instance.instance_index as usize, i - first);
+                assert_eq!(
+                    meshlet.indices_offset,
+                    meshlets.data()[first].indices_offset
+                );
+            });
+        });
+        //Each instance still reaches all its meshlets
+        meshes.for_each_entry(|i, m| {
+            assert_eq!(m.meshlets_count, mesh.meshlets_count);
+            m.meshlet_indices().for_each(|meshlet_index| {
+                assert_eq!(meshlets.data()[meshlet_index as usize].mesh_index, i as u32);
+            });
+        });
+    };
+
+    render_buffers.change_mesh(&id, &mut mesh);
+    check_instances(1);
+
+    mesh.set_instances(transforms(1000));
+    render_buffers.change_mesh(&id, &mut mesh);
+    check_instances(1000);
+    {
+        let meshes = render_buffers.meshes.read().unwrap();
+        let instance = meshes.get(&instance_id(&id, 10)).unwrap();
+        assert_eq!(instance.position, [20., 0., 0.]);
+        assert_eq!(
+            render_buffers.meshes_flags.read().unwrap().item_count(),
+            1000
+        );
+    }
+
+    mesh.set_instances(transforms(10));
+    render_buffers.change_mesh(&id, &mut mesh);
+    check_instances(10);
+
+    render_buffers.remove_mesh(&id, true);
+    assert!(render_buffers.meshes.read().unwrap().is_empty());
+    assert!(render_buffers.meshes_flags.read().unwrap().is_empty());
+    assert!(render_buffers.meshes_instances.read().unwrap().is_empty());
+}
+
+#[allow(dead_code)]
+fn test_transparency() {
+    use crate::{create_cube, register_resource_types, unregister_resource_types};
+    use inox_math::{Vector3, Vector4};
+    use inox_messenger::MessageHub;
+    use inox_resources::{DataTypeResource, SharedDataRc};
+    use inox_uid::generate_random_resource_uid;
+
+    let shared_data = SharedDataRc::default();
+    let message_hub = Arc::new(MessageHub::default());
+    register_resource_types(&shared_data, &message_hub);
+    let render_buffers = RenderBuffers::default();
+    let id = generate_random_resource_uid();
+    render_buffers
+        .add_mesh(
+            &id,
+            &create_cube(Vector3::new(1., 1., 1.), Vector4::new(1., 1., 1., 1.)),
+        )
+        .unwrap();
+    let material_id = generate_random_resource_uid();
+    let material = shared_data.add_resource(
+        &message_hub,
+        material_id,
+        Material::new(material_id, &shared_data, &message_hub),
+    );
+    render_buffers.add_material(&material_id, &mut material.get_mut());
+    let mut mesh = Mesh::new(id, &shared_data, &message_hub);
+    mesh.set_material(material.clone());
+    let set_alpha = |alpha: f32| {
+        let material_data = MaterialData {
+            base_color: Vector4::new(1., 1., 1., alpha),
+            ..Default::default()
+        };
+        render_buffers.update_material(&material_id, &material_data);
+    };
+
+    set_alpha(0.5);
+    render_buffers.change_mesh(&id, &mut mesh);
+    assert!(mesh.has_flags(MeshFlags::Transparent));
+    assert!(!mesh.has_flags(MeshFlags::Opaque));
+
+    //The mesh is opaque again with its material
+    set_alpha(1.);
+    render_buffers.change_mesh(&id, &mut mesh);
+    assert!(mesh.has_flags(MeshFlags::Opaque));
+    assert!(!mesh.has_flags(MeshFlags::Transparent));
+
+    drop(material);
+    unregister_resource_types(&shared_data, &message_hub);
+}
+
+#[allow(dead_code)]
+fn test_material_flags() {
+    use crate::{register_resource_types, unregister_resource_types};
+    use inox_messenger::MessageHub;
+    use inox_resources::{DataTypeResource, SharedDataRc};
+    use inox_uid::generate_random_resource_uid;
+
+    let shared_data = SharedDataRc::default();
+    let message_hub = Arc::new(MessageHub::default());
+    register_resource_types(&shared_data, &message_hub);
+    let render_buffers = RenderBuffers::default();
+    let material_id = generate_random_resource_uid();
+    let material = shared_data.add_resource(
+        &message_hub,
+        material_id,
+        Material::new(material_id, &shared_data, &message_hub),
+    );
+    render_buffers.add_material(&material_id, &mut material.get_mut());
+    //Flags of the material the shaders read
+    let flags = |material_data: &MaterialData| {
+        render_buffers.update_material(&material_id, material_data);
+        let materials = render_buffers.materials.read().unwrap();
+        materials.get(&material_id).unwrap().flags
+    };
+
+    assert_eq!(
+        flags(&MaterialData::default()),
+        u32::from(MaterialFlags::None)
+    );
+    let mut material_data = MaterialData {
+        unlit: true,
+        ..Default::default()
+    };
+    assert_eq!(flags(&material_data), u32::from(MaterialFlags::Unlit));
+    material_data.double_sided = true;
+    assert_eq!(
+        flags(&material_data),
+        u32::from(MaterialFlags::Unlit | MaterialFlags::DoubleSided)
+    );
+    //Turning it off lights the material again
+    material_data.unlit = false;
+    assert_eq!(flags(&material_data), u32::from(MaterialFlags::DoubleSided));
+
+    drop(material);
+    unregister_resource_types(&shared_data, &message_hub);
+}
+
+#[test]
+fn test() {
+    test_layer_mask();
+    test_procedural_shapes();
+    test_instances();
+    test_transparency();
+    test_material_flags();
+}
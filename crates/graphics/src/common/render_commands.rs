@@ -66,14 +66,14 @@ impl RenderCommands {
     ) -> &mut Self {
         let mut commands = Vec::new();
         match draw_command_type {
+            //The copies of a meshlet in all the instances are contiguous,
+            //so one command draws them all
             DrawCommandType::PerMeshlet => {
-                for meshlet_index in
-                    mesh.meshlets_offset..mesh.meshlets_offset + mesh.meshlets_count
-                {
+                for meshlet_index in mesh.meshlet_indices() {
                     let meshlet = &meshlets[meshlet_index as usize];
                     let command = DrawIndexedCommand {
                         vertex_count: meshlet.indices_count as _,
-                        instance_count: 1,
+                        instance_count: mesh.instances_count,
                         base_index: (mesh.indices_offset + meshlet.indices_offset) as _,
                         vertex_offset: mesh.vertex_offset as _,
                         base_instance: meshlet_index as _,
@@ -82,9 +82,7 @@ impl RenderCommands {
                 }
             }
             DrawCommandType::PerTriangle => {
-                for meshlet_index in
-                    mesh.meshlets_offset..mesh.meshlets_offset + mesh.meshlets_count
-                {
+                for meshlet_index in mesh.meshlet_indices() {
                     let meshlet = &meshlets[meshlet_index as usize];
 
                     let total_indices =
@@ -99,7 +97,7 @@ impl RenderCommands {
                     while i < total_indices {
                         let command = DrawIndexedCommand {
                             vertex_count: 3,
-                            instance_count: 1,
+                            instance_count: mesh.instances_count,
                             base_index: i as _,
                             vertex_offset: mesh.vertex_offset as _,
                             base_instance: (triangle_index << 24 | meshlet_index) as _,
@@ -6,33 +6,120 @@ use std::{
 use inox_math::{Degrees, Matrix4, Vector2};
 use inox_platform::Handle;
 use inox_resources::Resource;
+use inox_serialize::{Deserialize, Serialize};
 
 use crate::{
-    platform::{platform_limits, required_gpu_features},
+    max_anisotropy, max_texture_atlas_count,
+    platform::{
+        device_features, is_indirect_mode_enabled, platform_limits, set_created_device_features,
+    },
     BindingDataBuffer, BindingDataBufferRc, BufferId, ConstantData, ConstantDataRw,
-    DrawCommandType, GpuBuffer, MeshFlags, RenderBuffers, Renderer, RendererRw, Texture,
-    TextureHandler, TextureHandlerRc, CONSTANT_DATA_FLAGS_SUPPORT_SRGB, DEFAULT_HEIGHT,
-    DEFAULT_WIDTH,
+    DrawCommandType, Environment, EnvironmentSource, FrameCapture, GpuBuffer, MeshFlags,
+    RenderBuffers, Renderer, RendererRw, Texture, TextureAtlasError, TextureHandler,
+    TextureHandlerRc, View, ViewSubmission, CONSTANT_DATA_FLAGS_SUPPORT_SRGB,
+    CONSTANT_DATA_FLAGS_USE_IBL, DEFAULT_HEIGHT, DEFAULT_WIDTH,
 };
 
 const USE_VULKAN: bool = false;
 
+#[derive(Default, Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+#[serde(crate = "inox_serialize")]
+pub enum PresentMode {
+    AutoVsync,
+    #[default]
+    AutoNoVsync,
+    Mailbox,
+    Fifo,
+}
+
+impl From<PresentMode> for wgpu::PresentMode {
+    fn from(val: PresentMode) -> Self {
+        match val {
+            PresentMode::AutoVsync => wgpu::PresentMode::AutoVsync,
+            PresentMode::AutoNoVsync => wgpu::PresentMode::AutoNoVsync,
+            PresentMode::Mailbox => wgpu::PresentMode::Mailbox,
+            PresentMode::Fifo => wgpu::PresentMode::Fifo,
+        }
+    }
+}
+
+impl PresentMode {
+    pub fn is_vsync(&self) -> bool {
+        matches!(self, PresentMode::AutoVsync | PresentMode::Fifo)
+    }
+    //Auto modes are resolved by wgpu with the best supported mode,
+    //the others fall back to Fifo that every surface supports.
+    //Returns the mode to use and a warning when the requested one has been replaced
+    pub fn select(self, supported: &[wgpu::PresentMode]) -> (PresentMode, Option<String>) {
+        match self {
+            PresentMode::AutoVsync | PresentMode::AutoNoVsync => (self, None),
+            _ if supported.contains(&self.into()) => (self, None),
+            _ => (
+                PresentMode::Fifo,
+                Some(format!(
+                    "Present mode {self:?} is not supported by the surface, using Fifo"
+                )),
+            ),
+        }
+    }
+}
+
+//Sizes requested during a frame are coalesced so that only the last one configures the surface,
+//a zero size (minimized window) is never configured and rendering is skipped until a valid one
+#[derive(Default, Debug, PartialEq, Eq, Clone, Copy)]
+pub struct SurfaceResize {
+    requested: Option<(u32, u32)>,
+    is_minimized: bool,
+}
+
+impl SurfaceResize {
+    pub fn request(&mut self, width: u32, height: u32) {
+        self.requested = Some((width, height));
+    }
+    pub fn is_minimized(&self) -> bool {
+        self.is_minimized
+    }
+    //Returns true when the surface has been configured with the requested size
+    pub fn apply<F>(&mut self, configure: F) -> bool
+    where
+        F: FnOnce(u32, u32),
+    {
+        match self.requested.take() {
+            Some((width, height)) if width == 0 || height == 0 => {
+                self.is_minimized = true;
+                false
+            }
+            Some((width, height)) => {
+                self.is_minimized = false;
+                configure(width, height);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
 pub struct CommandBuffer {
     pub encoder: wgpu::CommandEncoder,
 }
 
 pub struct RenderCoreContext {
     pub instance: wgpu::Instance,
-    pub surface: wgpu::Surface,
+    //None when rendering headless, in an offscreen texture of the same size and format
+    pub surface: Option<wgpu::Surface>,
     pub adapter: wgpu::Adapter,
     pub device: wgpu::Device,
     pub queue: wgpu::Queue,
     pub config: RwLock<wgpu::SurfaceConfiguration>,
+    pub frame_capture: RwLock<FrameCapture>,
 }
 
 pub type RenderCoreContextRc = Arc<RenderCoreContext>;
 
 impl RenderCoreContext {
+    pub fn is_indirect_mode_enabled(&self) -> bool {
+        is_indirect_mode_enabled(self.device.features())
+    }
     pub fn new_command_buffer(&self) -> CommandBuffer {
         inox_profiler::scoped_profile!("render_context::new_command_buffer");
         CommandBuffer {
@@ -49,14 +136,30 @@ impl RenderCoreContext {
         self.queue.submit(std::iter::once(command_buffer));
     }
     pub fn set_surface_size(&self, width: u32, height: u32) {
-        self.config.write().unwrap().width = width;
-        self.config.write().unwrap().height = height;
+        debug_assert!(width > 0 && height > 0);
+        let mut config = self.config.write().unwrap();
+        config.width = width;
+        config.height = height;
         inox_log::debug_log!("Surface size: {}x{}", width, height);
     }
+    //The surface needs to be configured again to use the new mode
+    pub fn set_present_mode(&self, present_mode: PresentMode) -> PresentMode {
+        let Some(surface) = &self.surface else {
+            return present_mode;
+        };
+        let capabilities = surface.get_capabilities(&self.adapter);
+        let (present_mode, warning) = present_mode.select(&capabilities.present_modes);
+        if let Some(warning) = warning {
+            inox_log::debug_log!("{}", warning);
+        }
+        self.config.write().unwrap().present_mode = present_mode.into();
+        present_mode
+    }
     pub fn configure(&self) {
         inox_profiler::scoped_profile!("render_context::configure");
-        self.surface
-            .configure(&self.device, &self.config.read().unwrap());
+        if let Some(surface) = &self.surface {
+            surface.configure(&self.device, &self.config.read().unwrap());
+        }
     }
 }
 
@@ -66,6 +169,8 @@ pub struct RenderContext {
     pub binding_data_buffer: BindingDataBufferRc,
     pub render_buffers: RenderBuffers,
     pub constant_data: ConstantDataRw,
+    views: RwLock<Vec<(ViewSubmission, View)>>,
+    current_view: RwLock<Option<ViewSubmission>>,
 }
 
 pub type RenderContextRw = Arc<RwLock<RenderContext>>;
@@ -114,8 +219,8 @@ impl RenderContext {
                 .request_device(
                     &wgpu::DeviceDescriptor {
                         label: None,
-                        features: required_gpu_features(),
-                        limits: platform_limits(),
+                        features: device_features(&adapter),
+                        limits: platform_limits(&adapter),
                     },
                     // Some(&std::path::Path::new("trace")), // Trace path
                     None,
@@ -144,8 +249,8 @@ impl RenderContext {
                     .request_device(
                         &wgpu::DeviceDescriptor {
                             label: None,
-                            features: required_gpu_features(),
-                            limits: platform_limits(),
+                            features: device_features(&vulkan_adapter),
+                            limits: platform_limits(&vulkan_adapter),
                         },
                         // Some(&std::path::Path::new("trace")), // Trace path
                         None,
@@ -163,10 +268,103 @@ impl RenderContext {
             }
         };
 
+        Self::set_render_context(
+            renderer,
+            instance,
+            Some(surface),
+            adapter,
+            device,
+            queue,
+            on_create_func,
+        );
+    }
+
+    //Without a window the frames are drawn in an offscreen texture.
+    //Returns false when there is no adapter able to render
+    pub async fn create_headless_render_context<F>(renderer: RendererRw, on_create_func: F) -> bool
+    where
+        F: FnOnce(&mut Renderer),
+    {
+        inox_profiler::scoped_profile!("render_context::create_headless_render_context");
+
+        let dx12_shader_compiler = wgpu::util::dx12_shader_compiler_from_env().unwrap_or_default();
+        let backends = if USE_VULKAN {
+            wgpu::Backends::VULKAN
+        } else {
+            wgpu::Backends::all()
+        };
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends,
+            dx12_shader_compiler,
+        });
+        let Some(adapter) =
+            wgpu::util::initialize_adapter_from_env_or_default(&instance, backends, None).await
+        else {
+            inox_log::debug_log!("No suitable GPU adapters found on the system!");
+            return false;
+        };
+        let Ok((device, queue)) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: None,
+                    features: device_features(&adapter),
+                    limits: platform_limits(&adapter),
+                },
+                None,
+            )
+            .await
+        else {
+            inox_log::debug_log!("Failed to create device");
+            return false;
+        };
+        Self::set_render_context(
+            renderer,
+            instance,
+            None,
+            adapter,
+            device,
+            queue,
+            on_create_func,
+        );
+        true
+    }
+
+    fn set_render_context<F>(
+        renderer: RendererRw,
+        instance: wgpu::Instance,
+        surface: Option<wgpu::Surface>,
+        adapter: wgpu::Adapter,
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        on_create_func: F,
+    ) where
+        F: FnOnce(&mut Renderer),
+    {
         inox_log::debug_log!("Using {:?} adapter", adapter.get_info().backend);
+        set_created_device_features(device.features());
+        if !is_indirect_mode_enabled(device.features()) {
+            inox_log::debug_log!("Indirect draw not supported - using direct draws");
+        }
 
-        let capabilities = surface.get_capabilities(&adapter);
         let format = wgpu::TextureFormat::Rgba8Unorm;
+        let (present_mode, alpha_mode) = match &surface {
+            Some(surface) => {
+                let capabilities = surface.get_capabilities(&adapter);
+                let (present_mode, warning) = renderer
+                    .read()
+                    .unwrap()
+                    .present_mode()
+                    .select(&capabilities.present_modes);
+                if let Some(warning) = warning {
+                    inox_log::debug_log!("{}", warning);
+                }
+                (present_mode, *capabilities.alpha_modes.first().unwrap())
+            }
+            None => (
+                renderer.read().unwrap().present_mode(),
+                wgpu::CompositeAlphaMode::Opaque,
+            ),
+        };
 
         inox_log::debug_log!("Format {:?}", format);
 
@@ -176,15 +374,20 @@ impl RenderContext {
             view_formats: vec![format],
             width: DEFAULT_WIDTH,
             height: DEFAULT_HEIGHT,
-            present_mode: wgpu::PresentMode::AutoNoVsync,
-            alpha_mode: *capabilities.alpha_modes.first().unwrap(),
+            present_mode: present_mode.into(),
+            alpha_mode,
         };
 
         //debug_log!("Surface format: {:?}", config.format);
-        surface.configure(&device, &config);
+        if let Some(surface) = &surface {
+            surface.configure(&device, &config);
+        }
 
         inox_profiler::create_gpu_profiler!(&device, &queue, false);
 
+        let render_buffers = RenderBuffers::default();
+        render_buffers.set_limits(&device.limits());
+
         let render_core_context = RenderCoreContext {
             instance,
             surface,
@@ -192,17 +395,29 @@ impl RenderContext {
             device,
             queue,
             config: RwLock::new(config),
+            frame_capture: RwLock::new(FrameCapture::default()),
         };
 
         renderer
             .write()
             .unwrap()
             .set_render_context(Arc::new(RwLock::new(RenderContext {
-                texture_handler: Arc::new(TextureHandler::create(&render_core_context.device)),
+                texture_handler: Arc::new(TextureHandler::create(
+                    &render_core_context.device,
+                    max_texture_atlas_count(&render_core_context.device.limits()),
+                    max_anisotropy(
+                        render_core_context
+                            .adapter
+                            .get_downlevel_capabilities()
+                            .flags,
+                    ),
+                )),
                 core: Arc::new(render_core_context),
                 constant_data: Arc::new(RwLock::new(ConstantData::default())),
                 binding_data_buffer: Arc::new(BindingDataBuffer::default()),
-                render_buffers: RenderBuffers::default(),
+                render_buffers,
+                views: RwLock::new(Vec::new()),
+                current_view: RwLock::new(None),
             })));
 
         let mut renderer = renderer.write().unwrap();
@@ -232,6 +447,77 @@ impl RenderContext {
                 .unwrap()
                 .remove_flag(CONSTANT_DATA_FLAGS_SUPPORT_SRGB);
         }
+        if self.texture_handler.has_ibl() {
+            self.constant_data
+                .write()
+                .unwrap()
+                .add_flag(CONSTANT_DATA_FLAGS_USE_IBL);
+        } else {
+            self.constant_data
+                .write()
+                .unwrap()
+                .remove_flag(CONSTANT_DATA_FLAGS_USE_IBL);
+        }
+    }
+
+    //Views are rendered in the order of their submissions, each one in its own scissor rect
+    pub fn update_views(&self, views: &[View]) {
+        inox_profiler::scoped_profile!("render_context::update_views");
+        let (width, height) = {
+            let config = self.core.config.read().unwrap();
+            (config.width, config.height)
+        };
+        let viewports = views
+            .iter()
+            .map(|v| (v.view_index(), *v.viewport()))
+            .collect::<Vec<_>>();
+        *self.views.write().unwrap() = ViewSubmission::plan(&viewports, width, height)
+            .into_iter()
+            .filter_map(|s| {
+                views
+                    .iter()
+                    .find(|v| v.view_index() == s.view_index)
+                    .map(|v| (s, v.clone()))
+            })
+            .collect();
+    }
+    pub fn view_submissions(&self) -> Vec<ViewSubmission> {
+        self.views.read().unwrap().iter().map(|(s, _)| *s).collect()
+    }
+    //Fills the constant data with the matrices of the view, projected inside its viewport,
+    //and keeps only the commands of the meshes on its layers.
+    //With None the passes are drawn on the whole surface
+    pub fn set_current_view(&self, view_submission: Option<&ViewSubmission>) {
+        *self.current_view.write().unwrap() = view_submission.copied();
+        let Some(view_submission) = view_submission else {
+            return;
+        };
+        let (width, height) = {
+            let config = self.core.config.read().unwrap();
+            (config.width, config.height)
+        };
+        if let Some((_, view)) = self
+            .views
+            .read()
+            .unwrap()
+            .iter()
+            .find(|(s, _)| s == view_submission)
+        {
+            //Views with a different layer mask need their own commands
+            if self.render_buffers.set_layer_mask(view.layer_mask()) {
+                self.render_buffers
+                    .bind_commands(&self.binding_data_buffer, &self.core, false);
+            }
+            self.update_constant_data(
+                view.view(),
+                view.viewport().clip_transform() * view.proj(),
+                Vector2::new(width as _, height as _),
+                view.fov_in_degrees(),
+            );
+        }
+    }
+    pub fn current_view(&self) -> Option<ViewSubmission> {
+        *self.current_view.read().unwrap()
     }
 
     pub fn has_commands(
@@ -260,11 +546,48 @@ impl RenderContext {
         self.binding_data_buffer.buffers.write().unwrap()
     }
 
+    pub fn add_environment(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        environment: &Resource<Environment>,
+    ) -> usize {
+        let environment_id = environment.id();
+        let environment = environment.get();
+        let images = environment.images();
+        let cubemap_index = match &environment.data().source {
+            EnvironmentSource::Faces(_) => {
+                let faces = images.iter().map(|i| i.data.clone()).collect::<Vec<_>>();
+                self.texture_handler.add_cubemap(
+                    &self.core.device,
+                    encoder,
+                    environment_id,
+                    images[0].width,
+                    images[0].format,
+                    &faces,
+                )
+            }
+            EnvironmentSource::Equirectangular(_) => {
+                self.texture_handler.add_cubemap_from_equirectangular(
+                    &self.core.device,
+                    encoder,
+                    environment_id,
+                    environment.data().face_size,
+                    (images[0].width, images[0].height),
+                    images[0].format,
+                    &images[0].data,
+                )
+            }
+        };
+        self.texture_handler
+            .add_ibl(&self.core.device, encoder, environment_id);
+        cubemap_index
+    }
+
     pub fn add_image(
         &mut self,
         encoder: &mut wgpu::CommandEncoder,
         texture: &Resource<Texture>,
-    ) -> usize {
+    ) -> Result<usize, TextureAtlasError> {
         let texture_id = texture.id();
         let width = texture.get().width();
         let height = texture.get().height();
@@ -277,7 +600,7 @@ impl RenderContext {
                 (width, height),
                 format,
                 image_data,
-            );
+            )?;
             info.texture_index as _
         } else {
             let usage = texture.get().usage();
@@ -290,6 +613,63 @@ impl RenderContext {
             );
             index as _
         };
-        index
+        Ok(index)
     }
 }
+
+#[allow(dead_code)]
+fn test_present_mode() {
+    let supported = [wgpu::PresentMode::Fifo, wgpu::PresentMode::Immediate];
+
+    assert_eq!(
+        PresentMode::AutoVsync.select(&supported),
+        (PresentMode::AutoVsync, None)
+    );
+    assert_eq!(
+        PresentMode::AutoNoVsync.select(&supported),
+        (PresentMode::AutoNoVsync, None)
+    );
+    assert_eq!(
+        PresentMode::Fifo.select(&supported),
+        (PresentMode::Fifo, None)
+    );
+
+    let (present_mode, warning) = PresentMode::Mailbox.select(&supported);
+    assert_eq!(present_mode, PresentMode::Fifo);
+    assert_eq!(
+        warning.unwrap(),
+        "Present mode Mailbox is not supported by the surface, using Fifo"
+    );
+    assert_eq!(
+        PresentMode::Mailbox.select(&[wgpu::PresentMode::Mailbox]),
+        (PresentMode::Mailbox, None)
+    );
+}
+
+#[allow(dead_code)]
+fn test_surface_resize() {
+    let mut configured = Vec::new();
+
+    let mut surface_resize = SurfaceResize::default();
+    surface_resize.request(1280, 0);
+    assert!(!surface_resize.apply(|w, h| configured.push((w, h))));
+    assert!(surface_resize.is_minimized());
+    assert!(configured.is_empty());
+
+    surface_resize.request(800, 600);
+    surface_resize.request(1024, 768);
+    surface_resize.request(1920, 1080);
+    assert!(surface_resize.apply(|w, h| configured.push((w, h))));
+    assert!(!surface_resize.is_minimized());
+    assert_eq!(configured, vec![(1920, 1080)]);
+
+    //Nothing new has been requested in the next frame
+    assert!(!surface_resize.apply(|w, h| configured.push((w, h))));
+    assert_eq!(configured.len(), 1);
+}
+
+#[test]
+fn test() {
+    test_present_mode();
+    test_surface_resize();
+}
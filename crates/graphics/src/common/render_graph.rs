@@ -0,0 +1,317 @@
+use inox_uid::INVALID_RESOURCE_UID;
+
+use crate::TextureId;
+
+//How a pass uses a texture, a transition is needed every time it changes between passes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureAccess {
+    Sampled,
+    Storage,
+    ColorTarget,
+    DepthTarget,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenderGraphNode {
+    pub name: String,
+    pub reads: Vec<(TextureId, TextureAccess)>,
+    pub writes: Vec<(TextureId, TextureAccess)>,
+}
+
+impl RenderGraphNode {
+    pub fn new(
+        name: &str,
+        reads: Vec<(TextureId, TextureAccess)>,
+        writes: Vec<(TextureId, TextureAccess)>,
+    ) -> Self {
+        //Textures not set yet are not a dependency
+        Self {
+            name: name.to_string(),
+            reads: reads
+                .into_iter()
+                .filter(|(id, _)| *id != INVALID_RESOURCE_UID)
+                .collect(),
+            writes: writes
+                .into_iter()
+                .filter(|(id, _)| *id != INVALID_RESOURCE_UID)
+                .collect(),
+        }
+    }
+    fn reads(&self, texture_id: &TextureId) -> bool {
+        self.reads.iter().any(|(id, _)| id == texture_id)
+    }
+    fn writes(&self, texture_id: &TextureId) -> bool {
+        self.writes.iter().any(|(id, _)| id == texture_id)
+    }
+    fn access(&self, texture_id: &TextureId) -> Option<TextureAccess> {
+        self.writes
+            .iter()
+            .chain(self.reads.iter())
+            .find(|(id, _)| id == texture_id)
+            .map(|(_, access)| *access)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextureTransition {
+    pub texture_id: TextureId,
+    pub pass_index: usize,
+    pub from: TextureAccess,
+    pub to: TextureAccess,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RenderGraphError {
+    UnwrittenTexture { pass: String, texture_id: TextureId },
+    Cycle(Vec<String>),
+    MisplacedPass(String),
+}
+
+impl std::fmt::Display for RenderGraphError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RenderGraphError::UnwrittenTexture { pass, texture_id } => {
+                write!(
+                    f,
+                    "Pass {pass} reads texture {texture_id} but no pass writes it"
+                )
+            }
+            RenderGraphError::Cycle(passes) => {
+                write!(f, "Render passes dependency cycle: {}", passes.join(" -> "))
+            }
+            RenderGraphError::MisplacedPass(pass) => {
+                write!(f, "Pass {pass} is placed before the passes it depends on")
+            }
+        }
+    }
+}
+
+//Orders the passes from the textures they declare to read and write.
+//wgpu tracks the texture states by itself, the transitions between passes
+//are recorded to validate the frame and to know where they happen
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct RenderGraph {
+    order: Vec<usize>,
+    transitions: Vec<TextureTransition>,
+}
+
+impl RenderGraph {
+    pub fn build(nodes: &[RenderGraphNode]) -> Result<Self, RenderGraphError> {
+        let mut dependencies = Vec::with_capacity(nodes.len());
+        for (i, node) in nodes.iter().enumerate() {
+            let mut node_dependencies = Vec::new();
+            for (texture_id, _) in node.reads.iter() {
+                //Passes reading and writing the same texture keep their declaration order
+                let writers = nodes
+                    .iter()
+                    .enumerate()
+                    .filter(|(j, n)| {
+                        *j != i && n.writes(texture_id) && (*j < i || !n.reads(texture_id))
+                    })
+                    .map(|(j, _)| j)
+                    .collect::<Vec<_>>();
+                if writers.is_empty() {
+                    return Err(RenderGraphError::UnwrittenTexture {
+                        pass: node.name.clone(),
+                        texture_id: *texture_id,
+                    });
+                }
+                node_dependencies.extend(writers);
+            }
+            dependencies.push(node_dependencies);
+        }
+
+        let mut order = Vec::with_capacity(nodes.len());
+        let mut is_added = vec![false; nodes.len()];
+        while order.len() < nodes.len() {
+            let next = (0..nodes.len())
+                .find(|&i| !is_added[i] && dependencies[i].iter().all(|&d| is_added[d]));
+            match next {
+                Some(i) => {
+                    is_added[i] = true;
+                    order.push(i);
+                }
+                None => {
+                    let cycle = (0..nodes.len())
+                        .filter(|&i| !is_added[i])
+                        .map(|i| nodes[i].name.clone())
+                        .collect();
+                    return Err(RenderGraphError::Cycle(cycle));
+                }
+            }
+        }
+
+        let mut transitions = Vec::new();
+        let mut last_access: Vec<(TextureId, TextureAccess)> = Vec::new();
+        order.iter().enumerate().for_each(|(pass_index, &i)| {
+            let node = &nodes[i];
+            node.writes
+                .iter()
+                .chain(node.reads.iter())
+                .for_each(|(texture_id, _)| {
+                    let access = node.access(texture_id).unwrap();
+                    match last_access.iter_mut().find(|(id, _)| id == texture_id) {
+                        Some((_, last)) => {
+                            if *last != access {
+                                transitions.push(TextureTransition {
+                                    texture_id: *texture_id,
+                                    pass_index,
+                                    from: *last,
+                                    to: access,
+                                });
+                                *last = access;
+                            }
+                        }
+                        None => last_access.push((*texture_id, access)),
+                    }
+                });
+        });
+
+        Ok(Self { order, transitions })
+    }
+    //Keeps the nodes in the given order, failing if a node comes before its dependencies
+    pub fn build_in_order(nodes: &[RenderGraphNode]) -> Result<Self, RenderGraphError> {
+        let render_graph = Self::build(nodes)?;
+        if let Some(i) = render_graph
+            .order
+            .iter()
+            .enumerate()
+            .position(|(i, &node)| i != node)
+        {
+            return Err(RenderGraphError::MisplacedPass(nodes[i].name.clone()));
+        }
+        Ok(render_graph)
+    }
+    //Indices of the nodes in execution order
+    pub fn order(&self) -> &[usize] {
+        &self.order
+    }
+    pub fn transitions(&self) -> &[TextureTransition] {
+        &self.transitions
+    }
+}
+
+#[allow(dead_code)]
+fn test_render_graph() {
+    use inox_uid::generate_random_resource_uid;
+
+    let gbuffer_texture = generate_random_resource_uid();
+    let depth_texture = generate_random_resource_uid();
+    let unwritten_texture = generate_random_resource_uid();
+
+    let gbuffer = RenderGraphNode::new(
+        "GBufferPass",
+        Vec::new(),
+        vec![
+            (gbuffer_texture, TextureAccess::ColorTarget),
+            (depth_texture, TextureAccess::DepthTarget),
+        ],
+    );
+    let pbr = RenderGraphNode::new(
+        "PBRPass",
+        vec![
+            (gbuffer_texture, TextureAccess::Sampled),
+            (depth_texture, TextureAccess::Sampled),
+        ],
+        Vec::new(),
+    );
+    let wireframe = RenderGraphNode::new(
+        "WireframePass",
+        vec![(depth_texture, TextureAccess::DepthTarget)],
+        vec![(depth_texture, TextureAccess::DepthTarget)],
+    );
+    let transparent = RenderGraphNode::new(
+        "TransparentPass",
+        vec![(depth_texture, TextureAccess::DepthTarget)],
+        vec![(depth_texture, TextureAccess::DepthTarget)],
+    );
+
+    let graph = RenderGraph::build(&[
+        pbr.clone(),
+        transparent.clone(),
+        wireframe.clone(),
+        gbuffer.clone(),
+    ])
+    .unwrap();
+    assert_eq!(graph.order(), &[3, 0, 1, 2]);
+    assert_eq!(
+        graph.transitions(),
+        &[
+            TextureTransition {
+                texture_id: gbuffer_texture,
+                pass_index: 1,
+                from: TextureAccess::ColorTarget,
+                to: TextureAccess::Sampled,
+            },
+            TextureTransition {
+                texture_id: depth_texture,
+                pass_index: 1,
+                from: TextureAccess::DepthTarget,
+                to: TextureAccess::Sampled,
+            },
+            TextureTransition {
+                texture_id: depth_texture,
+                pass_index: 2,
+                from: TextureAccess::Sampled,
+                to: TextureAccess::DepthTarget,
+            },
+        ]
+    );
+
+    let reader = RenderGraphNode::new(
+        "BlitPass",
+        vec![(unwritten_texture, TextureAccess::Sampled)],
+        Vec::new(),
+    );
+    let error = RenderGraph::build(&[gbuffer.clone(), reader]).unwrap_err();
+    assert_eq!(
+        error,
+        RenderGraphError::UnwrittenTexture {
+            pass: "BlitPass".to_string(),
+            texture_id: unwritten_texture,
+        }
+    );
+    assert_eq!(
+        error.to_string(),
+        format!("Pass BlitPass reads texture {unwritten_texture} but no pass writes it")
+    );
+
+    //Without a pass writing the depth first nothing initializes it
+    let error = RenderGraph::build(&[wireframe.clone(), transparent.clone()]).unwrap_err();
+    assert!(
+        matches!(error, RenderGraphError::UnwrittenTexture { pass, .. } if pass == "WireframePass")
+    );
+
+    let graph = RenderGraph::build(&[RenderGraphNode::new(
+        "BlitPass",
+        vec![(TextureId::nil(), TextureAccess::Sampled)],
+        Vec::new(),
+    )])
+    .unwrap();
+    assert_eq!(graph.order(), &[0]);
+
+    //Passes reading and writing the same texture can be swapped, readers can't move before writers
+    let graph =
+        RenderGraph::build_in_order(&[gbuffer.clone(), transparent.clone(), wireframe.clone()])
+            .unwrap();
+    assert_eq!(graph.order(), &[0, 1, 2]);
+    let graph =
+        RenderGraph::build_in_order(&[gbuffer.clone(), wireframe.clone(), transparent.clone()])
+            .unwrap();
+    assert_eq!(graph.order(), &[0, 1, 2]);
+    assert_eq!(
+        RenderGraph::build_in_order(&[pbr.clone(), gbuffer.clone()]).unwrap_err(),
+        RenderGraphError::MisplacedPass("PBRPass".to_string())
+    );
+    assert_eq!(
+        RenderGraph::build_in_order(&[gbuffer, pbr])
+            .unwrap()
+            .order(),
+        &[0, 1]
+    );
+}
+
+#[test]
+fn test() {
+    test_render_graph();
+}
@@ -0,0 +1,145 @@
+pub const MIN_RENDER_SCALE: f32 = 0.25;
+pub const MAX_RENDER_SCALE: f32 = 1.;
+//Change of the scale each time the frame time is out of its target
+const RENDER_SCALE_STEP: f32 = 0.05;
+//Frames to wait after a change, the timings are averaged and arrive some frames later
+const FRAMES_TO_SETTLE: u32 = 30;
+//The scale grows again only when the frame time is this fraction under the target
+const FRAME_TIME_TOLERANCE: f32 = 0.15;
+
+//Fraction of the full resolution the scaled render targets are rendered at.
+//With a target frame time the scale is nudged to hold it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderScale {
+    scale: f32,
+    target_frame_time: Option<f32>,
+    frames_to_settle: u32,
+    is_changed: bool,
+}
+
+impl Default for RenderScale {
+    fn default() -> Self {
+        Self {
+            scale: MAX_RENDER_SCALE,
+            target_frame_time: None,
+            frames_to_settle: 0,
+            is_changed: false,
+        }
+    }
+}
+
+impl RenderScale {
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+    pub fn set_scale(&mut self, scale: f32) -> &mut Self {
+        let scale = scale.clamp(MIN_RENDER_SCALE, MAX_RENDER_SCALE);
+        if self.scale != scale {
+            self.scale = scale;
+            self.is_changed = true;
+        }
+        self
+    }
+    pub fn target_frame_time(&self) -> Option<f32> {
+        self.target_frame_time
+    }
+    //Milliseconds to hold, none keeps the scale as it has been set
+    pub fn set_target_frame_time(&mut self, target_frame_time: Option<f32>) -> &mut Self {
+        self.target_frame_time = target_frame_time.filter(|ms| *ms > 0.);
+        self.frames_to_settle = 0;
+        self
+    }
+    //Returns true when the frame time moved the scale
+    pub fn update(&mut self, frame_time: f32) -> bool {
+        let Some(target_frame_time) = self.target_frame_time else {
+            return false;
+        };
+        if self.frames_to_settle > 0 {
+            self.frames_to_settle -= 1;
+            return false;
+        }
+        let scale = if frame_time > target_frame_time {
+            self.scale - RENDER_SCALE_STEP
+        } else if frame_time < target_frame_time * (1. - FRAME_TIME_TOLERANCE) {
+            self.scale + RENDER_SCALE_STEP
+        } else {
+            self.scale
+        };
+        let scale = scale.clamp(MIN_RENDER_SCALE, MAX_RENDER_SCALE);
+        if self.scale == scale {
+            return false;
+        }
+        self.scale = scale;
+        self.is_changed = true;
+        self.frames_to_settle = FRAMES_TO_SETTLE;
+        true
+    }
+    pub fn scaled_size(&self, width: u32, height: u32) -> (u32, u32) {
+        let scaled = |size: u32| ((size as f32 * self.scale).round() as u32).max(1);
+        (scaled(width), scaled(height))
+    }
+    //Returns true only once after each change of the scale
+    pub fn take_changed(&mut self) -> bool {
+        std::mem::take(&mut self.is_changed)
+    }
+}
+
+#[allow(dead_code)]
+fn test_render_scale_size() {
+    let mut render_scale = RenderScale::default();
+    assert_eq!(render_scale.scaled_size(1920, 1080), (1920, 1080));
+    assert!(!render_scale.take_changed());
+
+    render_scale.set_scale(0.5);
+    assert_eq!(render_scale.scaled_size(1920, 1080), (960, 540));
+    assert_eq!(render_scale.scaled_size(3, 1), (2, 1));
+    assert!(render_scale.take_changed());
+    assert!(!render_scale.take_changed());
+
+    render_scale.set_scale(0.5);
+    assert!(!render_scale.take_changed());
+    render_scale.set_scale(0.);
+    assert_eq!(render_scale.scale(), MIN_RENDER_SCALE);
+    render_scale.set_scale(2.);
+    assert_eq!(render_scale.scale(), MAX_RENDER_SCALE);
+}
+
+#[allow(dead_code)]
+fn test_render_scale_frame_time() {
+    let mut render_scale = RenderScale::default();
+    assert!(!render_scale.update(100.));
+    assert_eq!(render_scale.scale(), MAX_RENDER_SCALE);
+
+    render_scale.set_target_frame_time(Some(16.));
+    assert!(render_scale.update(20.));
+    assert_eq!(render_scale.scale(), MAX_RENDER_SCALE - RENDER_SCALE_STEP);
+    assert!(render_scale.take_changed());
+
+    //Timings of the frames before the change are not trusted
+    (0..FRAMES_TO_SETTLE).for_each(|_| assert!(!render_scale.update(20.)));
+    assert!(render_scale.update(20.));
+    assert_eq!(
+        render_scale.scale(),
+        MAX_RENDER_SCALE - 2. * RENDER_SCALE_STEP
+    );
+
+    //Inside the tolerance the scale is kept
+    render_scale.set_target_frame_time(Some(16.));
+    assert!(!render_scale.update(15.));
+    assert!(render_scale.update(10.));
+    assert_eq!(render_scale.scale(), MAX_RENDER_SCALE - RENDER_SCALE_STEP);
+
+    render_scale.set_scale(MIN_RENDER_SCALE);
+    render_scale.set_target_frame_time(Some(16.));
+    assert!(!render_scale.update(100.));
+    assert_eq!(render_scale.scale(), MIN_RENDER_SCALE);
+
+    render_scale.set_target_frame_time(None);
+    assert!(!render_scale.update(1.));
+}
+
+#[test]
+fn test() {
+    test_render_scale_size();
+    test_render_scale_frame_time();
+}
@@ -1,15 +1,21 @@
 use crate::{
-    CommandBuffer, ComputePipeline, Material, Pass, RenderContext, RenderContextRw, RenderPass,
-    RenderPipeline, Texture, TextureId, TextureUsage, TextureView,
+    CommandBuffer, ComputePipeline, Environment, EnvironmentId, GpuTimestamps, Material, Pass,
+    PresentMode, RenderContext, RenderContextRw, RenderGraph, RenderGraphError, RenderGraphNode,
+    RenderPass, RenderPipeline, RenderScale, SurfaceResize, Texture, TextureId, TextureTransition,
+    TextureUsage, TextureView,
 };
-use inox_core::ContextRc;
+use inox_core::{ContextRc, JobHandlerRw};
 
 use inox_messenger::MessageHubRc;
 
 use inox_platform::Handle;
-use inox_resources::{ResourceTrait, SharedData, SharedDataRc};
+use inox_resources::{Resource, ResourceTrait, SharedData, SharedDataRc};
+use inox_serialize::SerializeFile;
 
-use std::sync::{Arc, RwLock, RwLockReadGuard};
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock, RwLockReadGuard},
+};
 
 pub const DEFAULT_WIDTH: u32 = 1920;
 pub const DEFAULT_HEIGHT: u32 = 1080;
@@ -30,15 +36,24 @@ pub enum RendererState {
 
 pub struct Renderer {
     render_context: Option<RenderContextRw>,
+    job_handler: JobHandlerRw,
     shared_data: SharedDataRc,
     message_hub: MessageHubRc,
     state: RendererState,
     passes: Vec<(Box<dyn Pass>, bool)>,
+    texture_transitions: Vec<TextureTransition>,
+    present_mode: PresentMode,
+    surface_resize: SurfaceResize,
+    render_scale: RenderScale,
+    //Full size of the render targets that follow the render scale
+    scaled_render_targets: Vec<(TextureId, (u32, u32))>,
     command_buffer: Option<CommandBuffer>,
     surface_texture: Option<wgpu::SurfaceTexture>,
     surface_view: Option<TextureView>,
     need_recreate: bool,
     need_commands_rebind: bool,
+    capture_path: Option<PathBuf>,
+    timestamps: GpuTimestamps,
 }
 pub type RendererRw = Arc<RwLock<Renderer>>;
 
@@ -56,20 +71,7 @@ impl Renderer {
     where
         F: FnOnce(&mut Renderer) + 'static,
     {
-        crate::register_resource_types(context.shared_data(), context.message_hub());
-
-        let renderer = Arc::new(RwLock::new(Renderer {
-            state: RendererState::Init,
-            render_context: None,
-            shared_data: context.shared_data().clone(),
-            message_hub: context.message_hub().clone(),
-            passes: Vec::new(),
-            command_buffer: None,
-            surface_texture: None,
-            surface_view: None,
-            need_recreate: false,
-            need_commands_rebind: true,
-        }));
+        let renderer = Self::create(context);
 
         #[cfg(target_arch = "wasm32")]
         wasm_bindgen_futures::spawn_local(RenderContext::create_render_context(
@@ -87,7 +89,51 @@ impl Renderer {
 
         renderer
     }
+    //Renderer without a window, None when no adapter is able to render
+    #[cfg(all(not(target_arch = "wasm32")))]
+    pub fn new_headless<F>(context: &ContextRc, on_create_func: F) -> Option<RendererRw>
+    where
+        F: FnOnce(&mut Renderer) + 'static,
+    {
+        let renderer = Self::create(context);
+        if !futures::executor::block_on(RenderContext::create_headless_render_context(
+            renderer.clone(),
+            on_create_func,
+        )) {
+            return None;
+        }
+        Some(renderer)
+    }
+    fn create(context: &ContextRc) -> RendererRw {
+        crate::register_resource_types(context.shared_data(), context.message_hub());
+
+        Arc::new(RwLock::new(Renderer {
+            state: RendererState::Init,
+            render_context: None,
+            job_handler: context.job_handler().clone(),
+            shared_data: context.shared_data().clone(),
+            message_hub: context.message_hub().clone(),
+            passes: Vec::new(),
+            texture_transitions: Vec::new(),
+            present_mode: PresentMode::default(),
+            surface_resize: SurfaceResize::default(),
+            render_scale: RenderScale::default(),
+            scaled_render_targets: Vec::new(),
+            command_buffer: None,
+            surface_texture: None,
+            surface_view: None,
+            need_recreate: false,
+            need_commands_rebind: true,
+            capture_path: None,
+            timestamps: GpuTimestamps::default(),
+        }))
+    }
     pub fn set_render_context(&mut self, context: RenderContextRw) {
+        context
+            .read()
+            .unwrap()
+            .render_buffers
+            .set_job_handler(&self.job_handler);
         self.render_context = Some(context);
     }
     pub fn render_context(&self) -> RwLockReadGuard<RenderContext> {
@@ -96,24 +142,28 @@ impl Renderer {
     pub fn num_passes(&self) -> usize {
         self.passes.len()
     }
-    pub fn is_pass_enabled(&self, index: usize) -> bool {
-        if let Some(v) = self.passes.get(index) {
-            return v.1;
-        }
-        false
+    pub fn is_pass_enabled(&self, name: &str) -> bool {
+        self.passes
+            .iter()
+            .any(|(pass, is_enabled)| *is_enabled && pass.name() == name)
     }
-    pub fn set_pass_enabled(&mut self, index: usize, is_enabled: bool) {
-        if let Some(v) = self.passes.get_mut(index) {
+    //Disabled passes keep their gpu resources, they're only skipped by the frame
+    pub fn set_pass_enabled(&mut self, name: &str, is_enabled: bool) -> &mut Self {
+        if let Some(v) = self.passes.iter_mut().find(|(pass, _)| pass.name() == name) {
             if v.1 != is_enabled {
                 v.1 = is_enabled;
-                self.need_recreate = true;
                 self.need_commands_rebind = true;
             }
         }
+        self
     }
     pub fn pass_at(&self, index: usize) -> Option<&dyn Pass> {
         self.passes.get(index).map(|v| v.0.as_ref())
     }
+    //Names of the passes in the order they're executed
+    pub fn pass_names(&self) -> Vec<&str> {
+        self.passes.iter().map(|(pass, _)| pass.name()).collect()
+    }
     pub fn pass<T>(&self) -> Option<&T>
     where
         T: Pass,
@@ -127,11 +177,92 @@ impl Renderer {
         }
         None
     }
+    pub fn pass_mut<T>(&mut self) -> Option<&mut T>
+    where
+        T: Pass,
+    {
+        if let Some(p) = self
+            .passes
+            .iter_mut()
+            .find(|(pass, _)| pass.name() == T::static_name())
+        {
+            return p.0.downcast_mut::<T>();
+        }
+        None
+    }
 
     pub fn add_pass(&mut self, pass: impl Pass, is_enabled: bool) -> &mut Self {
         self.passes.push((Box::new(pass), is_enabled));
         self
     }
+    //Reorders the added passes from the textures they read and write.
+    //On error the passes are left in the order they have been added
+    pub fn build_render_graph(&mut self) -> Result<&mut Self, RenderGraphError> {
+        let declared_order = (0..self.passes.len()).collect::<Vec<_>>();
+        let render_graph = RenderGraph::build(&self.render_graph_nodes(&declared_order))?;
+        let order = render_graph.order().to_vec();
+        self.apply_render_graph(&order, &render_graph);
+        Ok(self)
+    }
+    //Passes not named keep their relative order after the named ones.
+    //A pass can't be moved before the passes writing the textures it reads
+    pub fn reorder_passes(&mut self, names: &[&str]) -> Result<&mut Self, RenderGraphError> {
+        let mut order = Vec::with_capacity(self.passes.len());
+        names.iter().for_each(|name| {
+            if let Some(i) = self
+                .passes
+                .iter()
+                .position(|(pass, _)| pass.name() == *name)
+            {
+                if !order.contains(&i) {
+                    order.push(i);
+                }
+            }
+        });
+        (0..self.passes.len()).for_each(|i| {
+            if !order.contains(&i) {
+                order.push(i);
+            }
+        });
+        let render_graph = RenderGraph::build_in_order(&self.render_graph_nodes(&order))?;
+        self.apply_render_graph(&order, &render_graph);
+        Ok(self)
+    }
+    fn render_graph_nodes(&self, order: &[usize]) -> Vec<RenderGraphNode> {
+        order
+            .iter()
+            .map(|&i| {
+                let pass = &self.passes[i].0;
+                RenderGraphNode::new(pass.name(), pass.read_textures(), pass.written_textures())
+            })
+            .collect()
+    }
+    fn apply_render_graph(&mut self, order: &[usize], render_graph: &RenderGraph) {
+        let mut passes = std::mem::take(&mut self.passes)
+            .into_iter()
+            .map(Some)
+            .collect::<Vec<_>>();
+        self.passes = order.iter().map(|&i| passes[i].take().unwrap()).collect();
+        self.texture_transitions = render_graph.transitions().to_vec();
+        self.need_commands_rebind = true;
+    }
+    //Rolling average of the gpu milliseconds of the enabled passes, in execution order.
+    //Empty when the device can't write timestamps
+    pub fn pass_timings(&self) -> Vec<(String, f32)> {
+        let timings = self.timestamps.timings();
+        self.passes
+            .iter()
+            .filter(|(_, is_enabled)| *is_enabled)
+            .filter_map(|(pass, _)| {
+                timings
+                    .get(pass.name())
+                    .map(|ms| (pass.name().to_string(), ms))
+            })
+            .collect()
+    }
+    pub fn texture_transitions(&self) -> &[TextureTransition] {
+        &self.texture_transitions
+    }
 
     pub fn check_initialization(&mut self) {
         if self.render_context.is_none() {
@@ -149,6 +280,35 @@ impl Renderer {
         self
     }
 
+    pub fn present_mode(&self) -> PresentMode {
+        self.present_mode
+    }
+    //Applied when the surface is recreated, it's validated against the surface capabilities
+    pub fn set_present_mode(&mut self, present_mode: PresentMode) -> &mut Self {
+        if self.present_mode != present_mode {
+            self.present_mode = present_mode;
+            self.need_recreate = true;
+        }
+        self
+    }
+
+    pub fn anisotropy(&self) -> u16 {
+        self.render_context().texture_handler.anisotropy()
+    }
+    pub fn set_anisotropy(&mut self, anisotropy: u16) -> &mut Self {
+        let render_context = self.render_context();
+        render_context
+            .texture_handler
+            .set_anisotropy(&render_context.core.device, anisotropy);
+        self
+    }
+
+    //Writes in a json file the passes of the next rendered frame with what they bound and drew
+    pub fn capture_next_frame(&mut self, path: &Path) -> &mut Self {
+        self.capture_path = Some(path.to_path_buf());
+        self
+    }
+
     pub fn need_redraw(&self) -> bool {
         self.state != RendererState::Submitted
     }
@@ -158,6 +318,10 @@ impl Renderer {
 
         self.need_recreate = false;
 
+        self.present_mode = self
+            .render_context()
+            .core
+            .set_present_mode(self.present_mode);
         self.render_context().core.configure();
 
         SharedData::for_each_resource_mut(
@@ -180,9 +344,96 @@ impl Renderer {
         );
     }
 
+    //The size is applied once per frame with apply_surface_size()
     pub fn set_surface_size(&mut self, width: u32, height: u32) {
-        self.render_context().core.set_surface_size(width, height);
-        self.recreate();
+        self.surface_resize.request(width, height);
+    }
+    pub fn apply_surface_size(&mut self) -> bool {
+        let mut surface_resize = self.surface_resize;
+        let is_resized = surface_resize.apply(|width, height| {
+            self.render_context().core.set_surface_size(width, height);
+            self.recreate();
+        });
+        self.surface_resize = surface_resize;
+        is_resized
+    }
+    pub fn is_minimized(&self) -> bool {
+        self.surface_resize.is_minimized()
+    }
+
+    pub fn render_scale(&self) -> f32 {
+        self.render_scale.scale()
+    }
+    //Fraction of their full size the scaled render targets are rendered at,
+    //the surface and the passes drawing on it keep the native resolution.
+    //The scale is applied once per frame with apply_render_scale()
+    pub fn set_render_scale(&mut self, scale: f32) -> &mut Self {
+        self.render_scale.set_scale(scale);
+        self
+    }
+    pub fn target_frame_time(&self) -> Option<f32> {
+        self.render_scale.target_frame_time()
+    }
+    //Nudges the render scale to hold the gpu milliseconds of the enabled passes,
+    //it's left as it is when the device can't write timestamps
+    pub fn set_target_frame_time(&mut self, target_frame_time: Option<f32>) -> &mut Self {
+        self.render_scale.set_target_frame_time(target_frame_time);
+        self
+    }
+    //False when the passes in use all render at the native resolution of the surface
+    pub fn has_scaled_render_targets(&self) -> bool {
+        !self.scaled_render_targets.is_empty()
+    }
+    //The current size of the texture is its full size
+    pub fn add_scaled_render_target(&mut self, texture: &Resource<Texture>) -> &mut Self {
+        let id = *texture.id();
+        if !self.scaled_render_targets.iter().any(|(t, _)| *t == id) {
+            let (width, height) = texture.get().dimensions();
+            let (scaled_width, scaled_height) = self.render_scale.scaled_size(width, height);
+            texture
+                .get_mut()
+                .set_texture_size(scaled_width, scaled_height);
+            {
+                let render_context = self.render_context();
+                render_context.texture_handler.resize_render_target(
+                    &render_context.core.device,
+                    &id,
+                    (scaled_width, scaled_height),
+                );
+            }
+            self.scaled_render_targets.push((id, (width, height)));
+        }
+        self
+    }
+    pub fn apply_render_scale(&mut self) -> bool {
+        //Without targets to scale the shaders keep reading the native resolution
+        if !self.has_scaled_render_targets() {
+            return false;
+        }
+        let frame_time = self.pass_timings().iter().map(|(_, ms)| ms).sum::<f32>();
+        if frame_time > 0. {
+            self.render_scale.update(frame_time);
+        }
+        if !self.render_scale.take_changed() {
+            return false;
+        }
+        //The shaders read the size of the resized textures, the scale isn't uploaded
+        let render_context = self.render_context();
+        self.scaled_render_targets
+            .iter()
+            .for_each(|(id, (width, height))| {
+                let size = self.render_scale.scaled_size(*width, *height);
+                if let Some(texture) = self.shared_data.get_resource::<Texture>(id) {
+                    texture.get_mut().set_texture_size(size.0, size.1);
+                }
+                //Textures not yet on the gpu are created with the new size
+                render_context.texture_handler.resize_render_target(
+                    &render_context.core.device,
+                    id,
+                    size,
+                );
+            });
+        true
     }
 
     pub fn on_texture_changed(
@@ -202,17 +453,28 @@ impl Renderer {
                     .usage()
                     .contains(TextureUsage::RenderAttachment)
                 {
-                    let uniform_index = render_context.add_image(encoder, &texture);
-                    texture.get_mut().set_texture_index(uniform_index);
+                    match render_context.add_image(encoder, &texture) {
+                        Ok(uniform_index) => {
+                            texture.get_mut().set_texture_index(uniform_index);
+                        }
+                        Err(e) => inox_log::debug_log!("{}", e),
+                    }
                 } else if render_context
                     .texture_handler
                     .texture_info(texture_id)
                     .is_none()
                 {
-                    render_context.add_image(encoder, &texture);
-                    if let Some(texture_info) =
+                    if let Err(e) = render_context.add_image(encoder, &texture) {
+                        inox_log::debug_log!("{}", e);
+                    }
+                    if let Some(mut texture_info) =
                         render_context.texture_handler.texture_info(texture_id)
                     {
+                        let sampler = *texture.get().sampler();
+                        let sampler_index = render_context
+                            .texture_handler
+                            .sampler_index(&render_context.core.device, &sampler);
+                        texture_info.set_sampler(sampler_index, &sampler);
                         let uniform_index = render_context
                             .render_buffers
                             .add_texture(texture_id, &texture_info);
@@ -229,6 +491,45 @@ impl Renderer {
                             });
                     }
                 }
+            } else if texture.get().is_initialized() {
+                //Only the regions changed since the image has been added are sent again
+                let regions = texture.get_mut().take_updated_regions();
+                regions.iter().for_each(|region| {
+                    render_context.texture_handler.update_image_region(
+                        &render_context.core.device,
+                        encoder,
+                        texture_id,
+                        region,
+                    );
+                });
+                //Sampler of a texture already in an atlas could have been changed
+                if let Some(mut texture_info) =
+                    render_context.texture_handler.texture_info(texture_id)
+                {
+                    let sampler = *texture.get().sampler();
+                    let sampler_index = render_context
+                        .texture_handler
+                        .sampler_index(&render_context.core.device, &sampler);
+                    texture_info.set_sampler(sampler_index, &sampler);
+                    render_context
+                        .render_buffers
+                        .add_texture(texture_id, &texture_info);
+                }
+            }
+        }
+    }
+
+    pub fn on_environment_changed(
+        &mut self,
+        environment_id: &EnvironmentId,
+        encoder: &mut wgpu::CommandEncoder,
+    ) {
+        inox_profiler::scoped_profile!("renderer::on_environment_changed");
+        let mut render_context = self.render_context.as_ref().unwrap().write().unwrap();
+        if let Some(environment) = self.shared_data.get_resource::<Environment>(environment_id) {
+            if !environment.get().is_initialized() && environment.get().is_loaded() {
+                let cubemap_index = render_context.add_environment(encoder, &environment);
+                environment.get_mut().set_cubemap_index(cubemap_index);
             }
         }
     }
@@ -237,10 +538,21 @@ impl Renderer {
         if self.need_recreate {
             return false;
         }
+        if self.render_context().core.surface.is_none() {
+            let screen_view = self.create_offscreen_view();
+            self.surface_view = Some(TextureView::new(screen_view));
+            return true;
+        }
         let surface_texture = {
             inox_profiler::scoped_profile!("wgpu::get_current_texture");
 
-            self.render_context().core.surface.get_current_texture()
+            let render_context = self.render_context();
+            render_context
+                .core
+                .surface
+                .as_ref()
+                .unwrap()
+                .get_current_texture()
         };
         if let Ok(screen_texture) = surface_texture {
             let screen_view = screen_texture
@@ -255,6 +567,30 @@ impl Renderer {
         }
     }
 
+    //Headless frames are drawn in a texture with the size and the format of the surface
+    fn create_offscreen_view(&self) -> wgpu::TextureView {
+        let render_context = self.render_context();
+        let config = render_context.core.config.read().unwrap();
+        let texture = render_context
+            .core
+            .device
+            .create_texture(&wgpu::TextureDescriptor {
+                label: Some("Offscreen surface"),
+                size: wgpu::Extent3d {
+                    width: config.width,
+                    height: config.height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: config.format,
+                usage: config.usage | wgpu::TextureUsages::COPY_SRC,
+                view_formats: &config.view_formats,
+            });
+        texture.create_view(&wgpu::TextureViewDescriptor::default())
+    }
+
     pub fn prepare(&mut self) {
         inox_profiler::scoped_profile!("renderer::prepare");
 
@@ -270,28 +606,80 @@ impl Renderer {
         );
         self.need_commands_rebind = false;
     }
-    pub fn update_passes(&mut self, command_buffer: CommandBuffer) {
+    pub fn update_passes(&mut self, mut command_buffer: CommandBuffer) {
         inox_profiler::scoped_profile!("renderer::update_passes");
 
         let render_context = self.render_context.as_ref().unwrap().read().unwrap();
         let render_context: &RenderContext = &render_context;
-        self.passes.iter_mut().for_each(|(pass, is_enabled)| {
-            if *is_enabled && pass.is_active(render_context) {
-                pass.init(render_context);
-            }
-        });
-        self.command_buffer = Some(command_buffer);
-        if let Some(surface_view) = &self.surface_view {
+        let Some(surface_view) = &self.surface_view else {
             self.passes.iter_mut().for_each(|(pass, is_enabled)| {
                 if *is_enabled && pass.is_active(render_context) {
-                    pass.update(
-                        render_context,
-                        surface_view,
-                        self.command_buffer.as_mut().unwrap(),
-                    );
+                    pass.init(render_context);
                 }
             });
+            self.command_buffer = Some(command_buffer);
+            return;
+        };
+        self.timestamps.init(&render_context.core);
+        self.timestamps.begin_frame(&render_context.core.device);
+        if let Some(path) = self.capture_path.take() {
+            render_context
+                .core
+                .frame_capture
+                .write()
+                .unwrap()
+                .begin_frame(path.as_path());
         }
+        let view_submissions = render_context.view_submissions();
+        for (i, view_submission) in view_submissions.iter().enumerate() {
+            //Buffers written by the passes init are uploaded before the next submit,
+            //so every view needs its own one to keep its constant and culling data
+            if i > 0 {
+                render_context.core.submit(command_buffer);
+                command_buffer = render_context.core.new_command_buffer();
+            }
+            render_context.set_current_view(Some(view_submission));
+            Self::update_enabled_passes(
+                &mut self.passes,
+                &mut self.timestamps,
+                render_context,
+                surface_view,
+                &mut command_buffer,
+                true,
+            );
+        }
+        render_context.set_current_view(None);
+        Self::update_enabled_passes(
+            &mut self.passes,
+            &mut self.timestamps,
+            render_context,
+            surface_view,
+            &mut command_buffer,
+            false,
+        );
+        self.command_buffer = Some(command_buffer);
+    }
+
+    fn update_enabled_passes(
+        passes: &mut [(Box<dyn Pass>, bool)],
+        timestamps: &mut GpuTimestamps,
+        render_context: &RenderContext,
+        surface_view: &TextureView,
+        command_buffer: &mut CommandBuffer,
+        is_per_view: bool,
+    ) {
+        passes.iter_mut().for_each(|(pass, is_enabled)| {
+            if *is_enabled && pass.is_per_view() == is_per_view && pass.is_active(render_context) {
+                pass.init(render_context);
+            }
+        });
+        passes.iter_mut().for_each(|(pass, is_enabled)| {
+            if *is_enabled && pass.is_per_view() == is_per_view && pass.is_active(render_context) {
+                let query = timestamps.begin_pass(pass.name(), &mut command_buffer.encoder);
+                pass.update(render_context, surface_view, command_buffer);
+                timestamps.end_pass(query, &mut command_buffer.encoder);
+            }
+        });
     }
 
     pub fn submit_command_buffer(&mut self) {
@@ -300,10 +688,22 @@ impl Renderer {
             let render_context = self.render_context.as_ref().unwrap().read().unwrap();
 
             render_context.binding_data_buffer.reset_buffers_changed();
+            self.timestamps.resolve(&mut command_buffer.encoder);
             {
                 inox_profiler::gpu_profiler_pre_submit!(&mut command_buffer.encoder);
                 render_context.core.submit(command_buffer);
             }
+            self.timestamps.end_frame();
+            let frame_capture = render_context
+                .core
+                .frame_capture
+                .write()
+                .unwrap()
+                .end_frame();
+            if let Some((path, data)) = frame_capture {
+                data.save_to_file(path.as_path(), self.shared_data.serializable_registry());
+                inox_log::debug_log!("Frame captured in {:?}", path);
+            }
         }
     }
 
@@ -10,59 +10,45 @@ pub fn create_cube(size: Vector3, color: Vector4) -> MeshData {
 
 pub fn create_cube_from_min_max(min: Vector3, max: Vector3, color: Vector4) -> MeshData {
     let mut mesh_data = MeshData::default();
-    mesh_data.add_vertex_pos_color_normal_uv(
-        Vector3::new(min.x, min.y, min.z),
-        color,
-        Vector3::new(-1., -1., -1.),
-        Vector2::new(0., 0.),
-    );
-    mesh_data.add_vertex_pos_color_normal_uv(
-        Vector3::new(max.x, min.y, min.z),
-        color,
-        Vector3::new(1., -1., -1.),
-        Vector2::new(1., 0.),
-    );
-    mesh_data.add_vertex_pos_color_normal_uv(
-        Vector3::new(max.x, max.y, min.z),
-        color,
-        Vector3::new(1., 1., -1.),
-        Vector2::new(1., 1.),
-    );
-    mesh_data.add_vertex_pos_color_normal_uv(
-        Vector3::new(min.x, max.y, min.z),
-        color,
-        Vector3::new(-1., 1., -1.),
-        Vector2::new(0., 1.),
-    );
-    mesh_data.add_vertex_pos_color_normal_uv(
-        Vector3::new(min.x, min.y, max.z),
-        color,
-        Vector3::new(-1., -1., 1.),
-        Vector2::new(0., 0.),
-    );
-    mesh_data.add_vertex_pos_color_normal_uv(
-        Vector3::new(max.x, min.y, max.z),
-        color,
-        Vector3::new(1., -1., 1.),
-        Vector2::new(1., 0.),
-    );
-    mesh_data.add_vertex_pos_color_normal_uv(
-        Vector3::new(max.x, max.y, max.z),
-        color,
-        Vector3::new(1., 1., 1.),
-        Vector2::new(1., 1.),
-    );
-    mesh_data.add_vertex_pos_color_normal_uv(
-        Vector3::new(min.x, max.y, max.z),
-        color,
-        Vector3::new(-1., 1., 1.),
-        Vector2::new(0., 1.),
-    );
-    mesh_data.indices = [
-        0, 1, 3, 3, 1, 2, 1, 5, 2, 2, 5, 6, 5, 4, 6, 6, 4, 7, 4, 0, 7, 7, 0, 3, 3, 2, 7, 7, 2, 6,
-        4, 5, 0, 0, 5, 1,
-    ]
-    .to_vec();
+    let center = (min + max) * 0.5;
+    let half_size = (max - min) * 0.5;
+    //Normal and uv axes of each face, with u x v = normal to have it counter clockwise.
+    //Faces don't share vertices to have flat normals and the whole uv range
+    let faces = [
+        [Vector3::unit_x(), -Vector3::unit_z(), Vector3::unit_y()],
+        [-Vector3::unit_x(), Vector3::unit_z(), Vector3::unit_y()],
+        [Vector3::unit_y(), Vector3::unit_x(), -Vector3::unit_z()],
+        [-Vector3::unit_y(), Vector3::unit_x(), Vector3::unit_z()],
+        [Vector3::unit_z(), Vector3::unit_x(), Vector3::unit_y()],
+        [-Vector3::unit_z(), -Vector3::unit_x(), Vector3::unit_y()],
+    ];
+    faces.into_iter().for_each(|[n, u, v]| {
+        let first = mesh_data.vertex_count() as u32;
+        [(-1., -1.), (1., -1.), (1., 1.), (-1., 1.)]
+            .into_iter()
+            .for_each(|(su, sv)| {
+                let d = n + u * su + v * sv;
+                mesh_data.add_vertex_pos_color_normal_uv(
+                    [
+                        center.x + d.x * half_size.x,
+                        center.y + d.y * half_size.y,
+                        center.z + d.z * half_size.z,
+                    ]
+                    .into(),
+                    color,
+                    n,
+                    [(su + 1.) * 0.5, (sv + 1.) * 0.5].into(),
+                );
+            });
+        mesh_data.indices.extend_from_slice(&[
+            first,
+            first + 1,
+            first + 2,
+            first,
+            first + 2,
+            first + 3,
+        ]);
+    });
     let meshlet = MeshletData {
         indices_count: mesh_data.index_count() as _,
         aabb_min: mesh_data.aabb_min(),
@@ -73,6 +59,8 @@ pub fn create_cube_from_min_max(min: Vector3, max: Vector3, color: Vector4) -> M
     mesh_data
 }
 
+//Along the z axis, centered in the origin.
+//A radius of zero closes that side in a tip instead of a cap
 pub fn create_cylinder(
     base_radius: f32,
     top_radius: f32,
@@ -90,7 +78,7 @@ pub fn create_cylinder(
     let ny0 = 0.;
     let nz0 = angle_z.sin();
 
-    //start with sides
+    //start with sides, with the first and last slice vertices duplicated to wrap the uvs
     for i in 0..num_stack + 1 {
         let z = -(height * 0.5) + i as f32 / num_stack as f32 * height;
         let radius = base_radius + i as f32 / num_stack as f32 * (top_radius - base_radius);
@@ -113,112 +101,96 @@ pub fn create_cylinder(
         }
     }
 
-    //then base
-    let base_vertex_index = mesh_data.vertex_count() as _;
-    mesh_data.add_vertex_pos_color_normal_uv(
-        [0., 0., -height * 0.5].into(),
-        color,
-        [0., 0., -1.].into(),
-        [0.5, 0.5].into(),
-    );
+    for i in 0..num_stack {
+        let mut k1 = i * (num_slices + 1); // beginning of current stack
+        let mut k2 = k1 + num_slices + 1; // beginning of next stack
 
-    for i in 0..num_slices + 1 {
-        let angle: f32 = i as f32 * angle_step;
+        for _ in 0..num_slices {
+            //skip the triangles collapsed in a tip
+            if i != 0 || base_radius > 0. {
+                mesh_data.indices.push(k1);
+                mesh_data.indices.push(k1 + 1);
+                mesh_data.indices.push(k2);
+            }
+            if i != (num_stack - 1) || top_radius > 0. {
+                mesh_data.indices.push(k2);
+                mesh_data.indices.push(k1 + 1);
+                mesh_data.indices.push(k2 + 1);
+            }
+            k1 += 1;
+            k2 += 1;
+        }
+    }
 
-        mesh_data.add_vertex_pos_color_normal_uv(
-            [
-                base_radius * angle.cos(),
-                base_radius * angle.sin(),
-                -height * 0.5,
-            ]
-            .into(),
+    //then base and top
+    if base_radius > 0. {
+        add_cylinder_cap(
+            &mut mesh_data,
+            base_radius,
+            -height * 0.5,
+            num_slices,
             color,
-            [0., 0., -1.].into(),
-            [-angle.cos() * 0.5 + 0.5, -angle.sin() * 0.5 + 0.5].into(), // flip horizontal
         );
     }
+    if top_radius > 0. {
+        add_cylinder_cap(&mut mesh_data, top_radius, height * 0.5, num_slices, color);
+    }
 
-    //then top
-    let top_vertex_index = mesh_data.vertex_count() as _;
+    let meshlet = MeshletData {
+        indices_count: mesh_data.index_count() as _,
+        aabb_min: mesh_data.aabb_min(),
+        aabb_max: mesh_data.aabb_max(),
+        ..Default::default()
+    };
+    mesh_data.meshlets.push(meshlet);
+    mesh_data
+}
 
+//Facing down when below the origin and up otherwise
+fn add_cylinder_cap(
+    mesh_data: &mut MeshData,
+    radius: f32,
+    z: f32,
+    num_slices: u32,
+    color: Vector4,
+) {
+    let angle_step = 2. * PI / num_slices as f32;
+    let nz = if z < 0. { -1. } else { 1. };
+
+    let center_index = mesh_data.vertex_count() as u32;
     mesh_data.add_vertex_pos_color_normal_uv(
-        [0., 0., height * 0.5].into(),
+        [0., 0., z].into(),
         color,
-        [0., 0., 1.].into(),
+        [0., 0., nz].into(),
         [0.5, 0.5].into(),
     );
-
     for i in 0..num_slices + 1 {
         let angle: f32 = i as f32 * angle_step;
 
         mesh_data.add_vertex_pos_color_normal_uv(
-            [
-                top_radius * angle.cos(),
-                top_radius * angle.sin(),
-                height * 0.5,
-            ]
-            .into(),
+            [radius * angle.cos(), radius * angle.sin(), z].into(),
             color,
-            [0., 0., 1.].into(),
-            [angle.cos() * 0.5 + 0.5, -angle.sin() * 0.5 + 0.5].into(),
+            [0., 0., nz].into(),
+            // flip horizontal at the base
+            [nz * angle.cos() * 0.5 + 0.5, -angle.sin() * 0.5 + 0.5].into(),
         );
     }
-
-    //fill indices for sides
-    for i in 0..num_stack + 1 {
-        let mut k1 = i * (num_slices); // bebinning of current stack
-        let mut k2 = k1 + num_slices; // beginning of next stack
-
-        for _ in 0..num_slices + 1 {
-            mesh_data.indices.push(k1);
-            mesh_data.indices.push(k1 + 1);
-            mesh_data.indices.push(k2);
-
-            mesh_data.indices.push(k2);
-            mesh_data.indices.push(k1 + 1);
-            mesh_data.indices.push(k2 + 1);
-
-            k1 += 1;
-            k2 += 1;
-        }
-    }
-
-    // fill indices for base
-    let mut k = base_vertex_index + 1;
-    for i in 0..num_slices + 1 {
-        mesh_data.indices.push(base_vertex_index);
-        // last triangle
-        if i >= (num_slices - 1) {
-            mesh_data.indices.push(base_vertex_index + 1);
-        } else {
+    for i in 0..num_slices {
+        let k = center_index + 1 + i;
+        mesh_data.indices.push(center_index);
+        if nz > 0. {
+            mesh_data.indices.push(k);
             mesh_data.indices.push(k + 1);
-        }
-        mesh_data.indices.push(k);
-        k += 1;
-    }
-
-    // fill indices for top
-    let mut k = top_vertex_index + 1;
-    for i in 0..num_slices + 1 {
-        mesh_data.indices.push(top_vertex_index);
-        mesh_data.indices.push(k);
-        // last triangle
-        if i >= (num_slices - 1) {
-            mesh_data.indices.push(top_vertex_index + 1);
         } else {
             mesh_data.indices.push(k + 1);
+            mesh_data.indices.push(k);
         }
-        k += 1;
     }
+}
 
-    let meshlet = MeshletData {
-        indices_count: mesh_data.index_count() as _,
-        aabb_min: mesh_data.aabb_min(),
-        aabb_max: mesh_data.aabb_max(),
-        ..Default::default()
-    };
-    mesh_data.meshlets.push(meshlet);
-    mesh_data
+//Along the z axis, centered in the origin with the tip on top
+pub fn create_cone(radius: f32, num_slices: u32, height: f32, color: Vector4) -> MeshData {
+    create_cylinder(radius, 0., num_slices, height, 1, color)
 }
 
 pub fn create_sphere(
@@ -232,23 +204,28 @@ pub fn create_sphere(
 
     let slice_step = 2. * PI / num_slices as f32;
     let stack_step = PI / num_stack as f32;
-    let inv = 1. / radius;
 
     for i in 0..num_stack + 1 {
         let stack_angle = PI / 2. - i as f32 * stack_step; // from pi/2 to -pi/2
-        let xy = radius * stack_angle.cos();
-        let z = radius * stack_angle.sin();
+        let xy = stack_angle.cos();
+        let z = stack_angle.sin();
 
         for j in 0..num_slices + 1 {
             let slice_angle = j as f32 * slice_step; // from 0 to 2pi
-            let mut pos = position;
-            pos += [xy * slice_angle.cos(), xy * slice_angle.sin(), z].into();
+            let normal: Vector3 = [xy * slice_angle.cos(), xy * slice_angle.sin(), z].into();
+            //the last slice duplicates the first one with u = 1 to close the uv seam,
+            //while the poles use the middle of the slice of their triangle
+            let u = if i == 0 || i == num_stack {
+                (j as f32 + 0.5) / num_slices as f32
+            } else {
+                j as f32 / num_slices as f32
+            };
 
             mesh_data.add_vertex_pos_color_normal_uv(
-                pos,
+                position + normal * radius,
                 color,
-                pos * inv,
-                [j as f32 / num_slices as f32, i as f32 / num_stack as f32].into(),
+                normal,
+                [u, i as f32 / num_stack as f32].into(),
             );
         }
     }
@@ -258,14 +235,15 @@ pub fn create_sphere(
         let mut k2 = k1 + num_slices + 1; // beginning of next stack
 
         for _ in 0..num_slices {
-            // 2 triangles per sector excluding 1st and last stacks
+            // 2 triangles per sector excluding 1st and last stacks,
+            // that use the pole vertex of their slice
             if i != 0 {
                 mesh_data.indices.push(k1);
                 mesh_data.indices.push(k2);
                 mesh_data.indices.push(k1 + 1);
             }
             if i != (num_stack - 1) {
-                mesh_data.indices.push(k1 + 1);
+                mesh_data.indices.push(if i == 0 { k1 } else { k1 + 1 });
                 mesh_data.indices.push(k2);
                 mesh_data.indices.push(k2 + 1);
             }
@@ -283,6 +261,52 @@ pub fn create_sphere(
     mesh_data
 }
 
+//On the xz plane facing up, from -size to size, split in subdivisions + 1 quads per side
+pub fn create_plane(size: Vector2, subdivisions: u32, color: Vector4) -> MeshData {
+    let mut mesh_data = MeshData::default();
+
+    let num_quads = subdivisions + 1;
+    for i in 0..num_quads + 1 {
+        let v = i as f32 / num_quads as f32;
+        for j in 0..num_quads + 1 {
+            let u = j as f32 / num_quads as f32;
+            mesh_data.add_vertex_pos_color_normal_uv(
+                [-size.x + 2. * size.x * u, 0., -size.y + 2. * size.y * v].into(),
+                color,
+                Vector3::unit_y(),
+                [u, v].into(),
+            );
+        }
+    }
+
+    for i in 0..num_quads {
+        let mut k1 = i * (num_quads + 1); // beginning of current row
+        let mut k2 = k1 + num_quads + 1; // beginning of next row
+
+        for _ in 0..num_quads {
+            mesh_data.indices.push(k1);
+            mesh_data.indices.push(k2);
+            mesh_data.indices.push(k1 + 1);
+
+            mesh_data.indices.push(k1 + 1);
+            mesh_data.indices.push(k2);
+            mesh_data.indices.push(k2 + 1);
+
+            k1 += 1;
+            k2 += 1;
+        }
+    }
+
+    let meshlet = MeshletData {
+        indices_count: mesh_data.index_count() as _,
+        aabb_min: mesh_data.aabb_min(),
+        aabb_max: mesh_data.aabb_max(),
+        ..Default::default()
+    };
+    mesh_data.meshlets.push(meshlet);
+    mesh_data
+}
+
 pub fn create_arrow(position: Vector3, direction: Vector3, color: Vector4) -> MeshData {
     let mut shape_mesh_data = MeshData::default();
 
@@ -493,3 +517,145 @@ pub fn create_torus(
     mesh_data.aabb_max = position + matrix.rotate_point(mesh_data.aabb_max);
     mesh_data
 }
+
+#[allow(dead_code)]
+fn assert_outward_faces(mesh_data: &MeshData, inner_point: Vector3) {
+    use inox_math::VecBase;
+
+    mesh_data.indices.chunks(3).for_each(|triangle| {
+        let vertices = triangle.iter().map(|i| mesh_data.vertex(*i as _));
+        let p = vertices
+            .clone()
+            .map(|v| mesh_data.position(v.position_and_color_offset as _))
+            .collect::<Vec<_>>();
+        let face_normal = (p[1] - p[0]).cross(p[2] - p[0]);
+        let center = (p[0] + p[1] + p[2]) / 3.;
+        assert!(
+            face_normal.dot_product(center - inner_point) > 0.,
+            "Triangle {triangle:?} is clockwise"
+        );
+        vertices.for_each(|v| {
+            let normal = mesh_data.normal(v.normal_offset as _);
+            assert!(
+                (normal.length() - 1.).abs() < 0.01,
+                "{normal:?} is not unit"
+            );
+            assert!(normal.dot_product(face_normal) > 0.);
+        });
+    });
+}
+
+#[allow(dead_code)]
+fn test_shapes_winding() {
+    let color = Vector4::new(1., 1., 1., 1.);
+    let center = Vector3::new(0., 0., 0.);
+    assert_outward_faces(&create_cube(Vector3::new(1., 2., 3.), color), center);
+    assert_outward_faces(&create_sphere(center, 2., 16, 8, color), center);
+    assert_outward_faces(&create_cylinder(1., 1., 16, 2., 3, color), center);
+    assert_outward_faces(&create_cylinder(1., 0.5, 16, 2., 1, color), center);
+    assert_outward_faces(&create_cone(1., 16, 2., color), center);
+    assert_outward_faces(
+        &create_plane(Vector2::new(2., 1.), 3, color),
+        Vector3::new(0., -1., 0.),
+    );
+
+    let cube = create_cube(Vector3::new(1., 1., 1.), color);
+    assert_eq!(cube.vertex_count(), 24);
+    assert_eq!(cube.index_count(), 36);
+
+    //A cone has no top cap and no triangles collapsed in its tip
+    let cone = create_cone(1., 16, 2., color);
+    assert_eq!(cone.index_count(), (16 + 16) * 3);
+
+    let plane = create_plane(Vector2::new(1., 1.), 1, color);
+    assert_eq!(plane.vertex_count(), 9);
+    assert_eq!(plane.index_count(), 8 * 3);
+    assert_eq!(plane.aabb_min(), Vector3::new(-1., 0., -1.));
+    assert_eq!(plane.aabb_max(), Vector3::new(1., 0., 1.));
+}
+
+#[allow(dead_code)]
+fn test_sphere() {
+    use inox_math::VecBase;
+
+    let (num_slices, num_stack) = (16, 8);
+    let position = Vector3::new(10., 0., -5.);
+    let sphere = create_sphere(position, 2., num_slices, num_stack, [1.; 4].into());
+    assert_eq!(
+        sphere.vertex_count(),
+        ((num_slices + 1) * (num_stack + 1)) as usize
+    );
+    assert_eq!(sphere.meshlets.len(), 1);
+    assert_eq!(
+        sphere.meshlets[0].indices_count as usize,
+        sphere.index_count()
+    );
+
+    let vertex = |i: u32, j: u32| sphere.vertex((i * (num_slices + 1) + j) as _);
+    let position_of = |i, j| sphere.position(vertex(i, j).position_and_color_offset as _);
+    let uv_of = |i, j| sphere.uv(vertex(i, j).uv_offset[0] as _);
+    for i in 0..num_stack + 1 {
+        let v = i as f32 / num_stack as f32;
+        for j in 0..num_slices + 1 {
+            //Normals point away from the center even when it's not in the origin
+            let normal = sphere.normal(vertex(i, j).normal_offset as _);
+            let direction = (position_of(i, j) - position).normalized();
+            assert!(normal.dot_product(direction) > 0.99);
+            assert_eq!(uv_of(i, j).y, v);
+        }
+        if i == 0 || i == num_stack {
+            //Poles are in the middle of the slices to not stretch the texture
+            (0..num_slices).for_each(|j| {
+                let u = uv_of(i, j).x;
+                assert!(u > j as f32 / num_slices as f32);
+                assert!(u < (j + 1) as f32 / num_slices as f32);
+            });
+        } else {
+            //The seam duplicates the first vertices of each stack with u = 1
+            assert!(position_of(i, 0).squared_distance(position_of(i, num_slices)) < 1e-4);
+            assert_eq!(uv_of(i, 0).x, 0.);
+            assert_eq!(uv_of(i, num_slices).x, 1.);
+        }
+    }
+    //Each pole vertex is used only by the triangle of its slice
+    (0..num_slices).for_each(|j| {
+        let count = sphere.indices.iter().filter(|i| **i == j).count();
+        assert_eq!(count, 1);
+    });
+}
+
+#[allow(dead_code)]
+fn test_bounding_sphere() {
+    let color = Vector4::new(1., 1., 1., 1.);
+    let position = Vector3::new(3., -2., 1.);
+    [
+        create_cube_from_min_max(Vector3::new(-1., 0., 2.), Vector3::new(4., 1., 3.), color),
+        create_sphere(position, 2., 16, 8, color),
+        create_cone(1., 16, 4., color),
+        create_plane(Vector2::new(10., 1.), 4, color),
+    ]
+    .iter_mut()
+    .for_each(|mesh_data| {
+        let (center, radius) = mesh_data.bounding_sphere();
+        (0..mesh_data.positions.len()).for_each(|i| {
+            let p = mesh_data.position(i);
+            assert!((p - center).length() <= radius + 1e-4, "{p:?} is outside");
+        });
+        //Once computed it's stored
+        mesh_data.compute_bounding_sphere();
+        assert_eq!(mesh_data.bounding_sphere(), (center, radius));
+        assert_eq!(mesh_data.bounding_sphere[3], radius);
+    });
+
+    //Tight around a sphere
+    let (center, radius) = create_sphere(position, 2., 16, 8, color).bounding_sphere();
+    assert!((center - position).length() < 0.05);
+    assert!(radius < 2.2);
+}
+
+#[test]
+fn test() {
+    test_shapes_winding();
+    test_sphere();
+    test_bounding_sphere();
+}
@@ -1,5 +1,5 @@
 use inox_math::Vector4;
-use inox_uid::{generate_random_uid, Uid, INVALID_UID};
+use inox_uid::{generate_random_resource_uid, INVALID_RESOURCE_UID};
 
 use crate::TextureId;
 
@@ -17,7 +17,7 @@ pub struct Area {
 impl Default for Area {
     fn default() -> Self {
         Self {
-            id: INVALID_UID,
+            id: INVALID_RESOURCE_UID,
             x: 0,
             y: 0,
             width: 0,
@@ -198,14 +198,14 @@ impl AreaList {
 
 #[derive(Clone)]
 pub struct AreaAllocator {
-    id: Uid,
+    id: TextureId,
     free: AreaList,
     occupied: AreaList,
 }
 
 impl AreaAllocator {
     pub fn new(width: u32, height: u32) -> Self {
-        let id = generate_random_uid();
+        let id = generate_random_resource_uid();
         Self {
             free: AreaList::new(&[Area::new(&id, 0, 0, width as _, height as _)]),
             occupied: AreaList::default(),
@@ -0,0 +1,164 @@
+use wgpu::util::DeviceExt;
+
+use super::gpu_texture::{GpuTexture, CUBEMAP_FACES_COUNT};
+
+const EQUIRECTANGULAR_TO_CUBE_SHADER: &str = r#"
+struct FaceData {
+    index: u32,
+    _padding_0: u32,
+    _padding_1: u32,
+    _padding_2: u32,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+@group(0) @binding(0) var equirectangular: texture_2d<f32>;
+@group(0) @binding(1) var<uniform> face: FaceData;
+
+@vertex
+fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((in_vertex_index << 1u) & 2u), f32(in_vertex_index & 2u));
+    var output: VertexOutput;
+    output.clip_position = vec4<f32>(uv.x * 2. - 1., 1. - uv.y * 2., 0., 1.);
+    output.uv = uv;
+    return output;
+}
+
+fn face_direction(face_index: u32, uv: vec2<f32>) -> vec3<f32> {
+    let s = uv.x * 2. - 1.;
+    let t = uv.y * 2. - 1.;
+    switch face_index {
+        case 0u: { return vec3<f32>(1., -t, -s); }
+        case 1u: { return vec3<f32>(-1., -t, s); }
+        case 2u: { return vec3<f32>(s, 1., t); }
+        case 3u: { return vec3<f32>(s, -1., -t); }
+        case 4u: { return vec3<f32>(s, -t, 1.); }
+        default: { return vec3<f32>(-s, -t, -1.); }
+    }
+}
+
+@fragment
+fn fs_main(v_in: VertexOutput) -> @location(0) vec4<f32> {
+    let PI = 3.14159265359;
+    let direction = normalize(face_direction(face.index, v_in.uv));
+    let phi = atan2(direction.z, direction.x);
+    let theta = asin(clamp(direction.y, -1., 1.));
+    let equirectangular_uv = vec2<f32>(phi / (2. * PI) + 0.5, 0.5 - theta / PI);
+    let size = vec2<f32>(textureDimensions(equirectangular));
+    let texel = min(vec2<i32>(equirectangular_uv * size), vec2<i32>(size) - vec2<i32>(1));
+    return textureLoad(equirectangular, texel, 0);
+}
+"#;
+
+//Renders every face of the cubemap sampling the equirectangular texture along the face directions.
+//The cubemap must have been created with RENDER_ATTACHMENT usage.
+pub fn convert_equirectangular_to_cube(
+    device: &wgpu::Device,
+    encoder: &mut wgpu::CommandEncoder,
+    equirectangular: &GpuTexture,
+    cube: &GpuTexture,
+) {
+    debug_assert!(cube.is_cube(), "Destination texture is not a cubemap");
+    inox_profiler::scoped_profile!("cubemap::convert_equirectangular_to_cube");
+
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("EquirectangularToCube"),
+        source: wgpu::ShaderSource::Wgsl(EQUIRECTANGULAR_TO_CUBE_SHADER.into()),
+    });
+    let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("EquirectangularToCube BindGroupLayout"),
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+        ],
+    });
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("EquirectangularToCube PipelineLayout"),
+        bind_group_layouts: &[&bind_group_layout],
+        push_constant_ranges: &[],
+    });
+    let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("EquirectangularToCube Pipeline"),
+        layout: Some(&pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: "vs_main",
+            buffers: &[],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: "fs_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format: (*cube.format()).into(),
+                blend: None,
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    });
+
+    for face_index in 0..CUBEMAP_FACES_COUNT {
+        let face_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("EquirectangularToCube FaceData"),
+            contents: &[face_index, 0, 0, 0]
+                .iter()
+                .flat_map(|v| v.to_le_bytes())
+                .collect::<Vec<u8>>(),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("EquirectangularToCube BindGroup"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(equirectangular.view().as_wgpu()),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: face_buffer.as_entire_binding(),
+                },
+            ],
+        });
+        let face_view = cube.layer_view(face_index);
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("EquirectangularToCube RenderPass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: face_view.as_wgpu(),
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        render_pass.set_pipeline(&pipeline);
+        render_pass.set_bind_group(0, &bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
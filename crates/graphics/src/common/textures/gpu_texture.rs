@@ -19,13 +19,29 @@ impl TextureView {
     }
 }
 
+pub const CUBEMAP_FACES_COUNT: u32 = 6;
+
+pub fn texture_view_dimension(layers_count: u32, is_cube: bool) -> wgpu::TextureViewDimension {
+    if is_cube && layers_count > CUBEMAP_FACES_COUNT {
+        wgpu::TextureViewDimension::CubeArray
+    } else if is_cube {
+        wgpu::TextureViewDimension::Cube
+    } else if layers_count > 1 {
+        wgpu::TextureViewDimension::D2Array
+    } else {
+        wgpu::TextureViewDimension::D2
+    }
+}
+
 pub struct GpuTexture {
     id: TextureId,
     texture: wgpu::Texture,
     view: TextureView,
+    view_dimension: wgpu::TextureViewDimension,
     width: u32,
     height: u32,
     layers_count: u32,
+    mip_levels_count: u32,
     format: TextureFormat,
 }
 
@@ -39,6 +55,54 @@ impl GpuTexture {
         format: TextureFormat,
         usage: wgpu::TextureUsages,
     ) -> Self {
+        Self::create_with_view_dimension(
+            device,
+            id,
+            (width, height),
+            (layers_count, 1),
+            format,
+            usage,
+            texture_view_dimension(layers_count, false),
+        )
+    }
+    pub fn create_cube(
+        device: &wgpu::Device,
+        id: TextureId,
+        face_size: u32,
+        format: TextureFormat,
+        usage: wgpu::TextureUsages,
+    ) -> Self {
+        Self::create_cube_with_mips(device, id, face_size, 1, format, usage)
+    }
+    pub fn create_cube_with_mips(
+        device: &wgpu::Device,
+        id: TextureId,
+        face_size: u32,
+        mip_levels_count: u32,
+        format: TextureFormat,
+        usage: wgpu::TextureUsages,
+    ) -> Self {
+        Self::create_with_view_dimension(
+            device,
+            id,
+            (face_size, face_size),
+            (CUBEMAP_FACES_COUNT, mip_levels_count),
+            format,
+            usage,
+            texture_view_dimension(CUBEMAP_FACES_COUNT, true),
+        )
+    }
+    fn create_with_view_dimension(
+        device: &wgpu::Device,
+        id: TextureId,
+        dimensions: (u32, u32),
+        layers_and_mips: (u32, u32),
+        format: TextureFormat,
+        usage: wgpu::TextureUsages,
+        view_dimension: wgpu::TextureViewDimension,
+    ) -> Self {
+        let (layers_count, mip_levels_count) = layers_and_mips;
+        let (width, height) = dimensions;
         let size = wgpu::Extent3d {
             width,
             height,
@@ -47,7 +111,7 @@ impl GpuTexture {
         let texture = device.create_texture(&wgpu::TextureDescriptor {
             label: Some(format!("Texture[{id}]").as_str()),
             size,
-            mip_level_count: 1,
+            mip_level_count: mip_levels_count,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
             format: format.into(),
@@ -57,14 +121,10 @@ impl GpuTexture {
         let view = texture.create_view(&wgpu::TextureViewDescriptor {
             label: Some(format!("TextureView[{id}]").as_str()),
             format: Some(format.into()),
-            dimension: if layers_count > 1 {
-                Some(wgpu::TextureViewDimension::D2Array)
-            } else {
-                Some(wgpu::TextureViewDimension::D2)
-            },
+            dimension: Some(view_dimension),
             aspect: wgpu::TextureAspect::default(),
             base_mip_level: 0,
-            mip_level_count: Some(1),
+            mip_level_count: Some(mip_levels_count),
             base_array_layer: 0,
             array_layer_count: Some(layers_count),
         });
@@ -72,12 +132,65 @@ impl GpuTexture {
             id,
             texture,
             view: TextureView::new(view),
+            view_dimension,
             width,
             height,
             layers_count,
+            mip_levels_count,
             format,
         }
     }
+    //A new texture with the same id and format, the content is not kept
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        let resized = Self::create_with_view_dimension(
+            device,
+            self.id,
+            (width, height),
+            (self.layers_count, self.mip_levels_count),
+            self.format,
+            self.texture.usage(),
+            self.view_dimension,
+        );
+        std::mem::replace(self, resized).release();
+    }
+    //View of a single layer (e.g. a cubemap face) to be used as render attachment
+    pub fn layer_view(&self, layer_index: u32) -> TextureView {
+        TextureView::new(self.texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some(format!("TextureView[{}][{layer_index}]", self.id).as_str()),
+            format: Some(self.format.into()),
+            dimension: Some(wgpu::TextureViewDimension::D2),
+            aspect: wgpu::TextureAspect::default(),
+            base_mip_level: 0,
+            mip_level_count: Some(1),
+            base_array_layer: layer_index,
+            array_layer_count: Some(1),
+        }))
+    }
+    //View of all the layers of a single mip level (e.g. to be written as storage texture)
+    pub fn mip_view(&self, mip_level: u32) -> TextureView {
+        TextureView::new(self.texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some(format!("TextureView[{}][mip {mip_level}]", self.id).as_str()),
+            format: Some(self.format.into()),
+            dimension: Some(texture_view_dimension(self.layers_count, false)),
+            aspect: wgpu::TextureAspect::default(),
+            base_mip_level: mip_level,
+            mip_level_count: Some(1),
+            base_array_layer: 0,
+            array_layer_count: Some(self.layers_count),
+        }))
+    }
+    pub fn view_dimension(&self) -> wgpu::TextureViewDimension {
+        self.view_dimension
+    }
+    pub fn is_cube(&self) -> bool {
+        matches!(
+            self.view_dimension,
+            wgpu::TextureViewDimension::Cube | wgpu::TextureViewDimension::CubeArray
+        )
+    }
+    pub fn texture(&self) -> &wgpu::Texture {
+        &self.texture
+    }
     pub fn view(&self) -> &TextureView {
         &self.view
     }
@@ -97,6 +210,9 @@ impl GpuTexture {
     pub fn layers_count(&self) -> u32 {
         self.layers_count
     }
+    pub fn mip_levels_count(&self) -> u32 {
+        self.mip_levels_count
+    }
     pub fn send_to_gpu(
         &self,
         device: &wgpu::Device,
@@ -109,24 +225,28 @@ impl GpuTexture {
         //   BufferCopyView.layout.bytes_per_row % wgpu::COPY_BYTES_PER_ROW_ALIGNMENT == 0
         // So we calculate padded_width by rounding width up to the next
         // multiple of wgpu::COPY_BYTES_PER_ROW_ALIGNMENT.
+        // Compressed formats are copied by blocks: a row is a row of blocks and
+        // block_size is the size in bytes of a single block (or pixel if uncompressed).
         let format: wgpu::TextureFormat = self.format.into();
-        let pixel_size = format
+        let block_size = format
             .block_size(Some(wgpu::TextureAspect::All))
             .unwrap_or_default();
+        let (block_width, block_height) = format.block_dimensions();
+        let blocks_per_row = (area.width + block_width - 1) / block_width;
+        let rows_count = (area.height + block_height - 1) / block_height;
+        let row_size = (block_size * blocks_per_row) as usize;
         let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
-        let padding = (align - (pixel_size * area.width) % align) % align;
-        let padded_width = (pixel_size * area.width + padding) as usize;
-        let padded_data_size = (pixel_size * area.width * area.height) as usize;
+        let padding = (align - (block_size * blocks_per_row) % align) % align;
+        let padded_width = row_size + padding as usize;
+        let padded_data_size = padded_width * rows_count as usize;
 
         let mut padded_data = vec![0; padded_data_size];
 
-        for row in 0..area.height as usize {
+        for row in 0..rows_count as usize {
             let offset = row * padded_width;
 
-            padded_data[offset..offset + (pixel_size * area.width) as usize].copy_from_slice(
-                &data[row * (pixel_size * area.width) as usize
-                    ..(row + 1) * (pixel_size * area.width) as usize],
-            )
+            padded_data[offset..offset + row_size]
+                .copy_from_slice(&data[row * row_size..(row + 1) * row_size])
         }
         let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("image staging buffer"),
@@ -145,8 +265,8 @@ impl GpuTexture {
                 buffer: &buffer,
                 layout: wgpu::ImageDataLayout {
                     offset: 0,
-                    bytes_per_row: NonZeroU32::new(pixel_size * area.width + padding),
-                    rows_per_image: NonZeroU32::new(area.height),
+                    bytes_per_row: NonZeroU32::new(padded_width as u32),
+                    rows_per_image: NonZeroU32::new(rows_count),
                 },
             },
             wgpu::ImageCopyTexture {
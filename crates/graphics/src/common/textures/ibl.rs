@@ -0,0 +1,487 @@
+use inox_math::{InnerSpace, Vector3};
+use wgpu::util::DeviceExt;
+
+use super::gpu_texture::{GpuTexture, CUBEMAP_FACES_COUNT};
+
+pub const BRDF_LUT_SIZE: u32 = 256;
+pub const IBL_PREFILTERED_FACE_SIZE: u32 = 128;
+pub const IBL_PREFILTERED_MIP_LEVELS: u32 = 5;
+pub const IBL_SAMPLE_COUNT: u32 = 512;
+const IBL_WORKGROUP_SIZE: u32 = 8;
+
+const IBL_COMMON_SHADER: &str = r#"
+const PI: f32 = 3.14159265359;
+
+fn hammersley(i: u32, count: u32) -> vec2<f32> {
+    return vec2<f32>(f32(i) / f32(count), f32(reverseBits(i)) * 2.3283064365386963e-10);
+}
+
+//Half vector around n distributed following GGX with alpha = roughness^2
+fn importance_sample_ggx(xi: vec2<f32>, n: vec3<f32>, roughness: f32) -> vec3<f32> {
+    let a = roughness * roughness;
+    let phi = 2. * PI * xi.x;
+    let cos_theta = sqrt((1. - xi.y) / (1. + (a * a - 1.) * xi.y));
+    let sin_theta = sqrt(1. - cos_theta * cos_theta);
+    let h = vec3<f32>(cos(phi) * sin_theta, sin(phi) * sin_theta, cos_theta);
+    var up = vec3<f32>(0., 0., 1.);
+    if abs(n.z) >= 0.999 {
+        up = vec3<f32>(1., 0., 0.);
+    }
+    let tangent = normalize(cross(up, n));
+    let bitangent = cross(n, tangent);
+    return normalize(tangent * h.x + bitangent * h.y + n * h.z);
+}
+"#;
+
+const BRDF_LUT_SHADER: &str = r#"
+@group(0) @binding(0) var brdf_lut: texture_storage_2d<rgba16float, write>;
+
+fn geometry_schlick_ggx(n_dot_v: f32, roughness: f32) -> f32 {
+    //k remapping used for image based lighting
+    let k = (roughness * roughness) / 2.;
+    return n_dot_v / (n_dot_v * (1. - k) + k);
+}
+
+@compute
+@workgroup_size(8, 8, 1)
+fn main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+    let size = textureDimensions(brdf_lut);
+    if global_invocation_id.x >= size.x || global_invocation_id.y >= size.y {
+        return;
+    }
+    let n_dot_v = max((f32(global_invocation_id.x) + 0.5) / f32(size.x), 0.0001);
+    let roughness = (f32(global_invocation_id.y) + 0.5) / f32(size.y);
+    let v = vec3<f32>(sqrt(1. - n_dot_v * n_dot_v), 0., n_dot_v);
+    let n = vec3<f32>(0., 0., 1.);
+    var a = 0.;
+    var b = 0.;
+    for (var i = 0u; i < SAMPLE_COUNTu; i++) {
+        let h = importance_sample_ggx(hammersley(i, SAMPLE_COUNTu), n, roughness);
+        let l = normalize(2. * dot(v, h) * h - v);
+        let n_dot_l = max(l.z, 0.);
+        let n_dot_h = max(h.z, 0.);
+        let v_dot_h = max(dot(v, h), 0.);
+        if n_dot_l > 0. {
+            let g = geometry_schlick_ggx(n_dot_v, roughness) * geometry_schlick_ggx(n_dot_l, roughness);
+            let g_vis = (g * v_dot_h) / (n_dot_h * n_dot_v);
+            let fc = pow(1. - v_dot_h, 5.);
+            a += (1. - fc) * g_vis;
+            b += fc * g_vis;
+        }
+    }
+    let scale_and_bias = vec2<f32>(a, b) / f32(SAMPLE_COUNTu);
+    textureStore(brdf_lut, vec2<i32>(global_invocation_id.xy), vec4<f32>(scale_and_bias, 0., 1.));
+}
+"#;
+
+const PREFILTER_SHADER: &str = r#"
+struct PrefilterData {
+    roughness: f32,
+    source_face_size: f32,
+    _padding_0: u32,
+    _padding_1: u32,
+};
+
+@group(0) @binding(0) var environment_sampler: sampler;
+@group(0) @binding(1) var environment: texture_cube<f32>;
+@group(0) @binding(2) var<uniform> prefilter: PrefilterData;
+@group(0) @binding(3) var prefiltered: texture_storage_2d_array<rgba16float, write>;
+
+fn face_direction(face_index: u32, uv: vec2<f32>) -> vec3<f32> {
+    let s = uv.x * 2. - 1.;
+    let t = uv.y * 2. - 1.;
+    switch face_index {
+        case 0u: { return vec3<f32>(1., -t, -s); }
+        case 1u: { return vec3<f32>(-1., -t, s); }
+        case 2u: { return vec3<f32>(s, 1., t); }
+        case 3u: { return vec3<f32>(s, -1., -t); }
+        case 4u: { return vec3<f32>(s, -t, 1.); }
+        default: { return vec3<f32>(-s, -t, -1.); }
+    }
+}
+
+@compute
+@workgroup_size(8, 8, 1)
+fn main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+    let size = textureDimensions(prefiltered);
+    if global_invocation_id.x >= size.x || global_invocation_id.y >= size.y {
+        return;
+    }
+    let uv = (vec2<f32>(global_invocation_id.xy) + vec2<f32>(0.5)) / vec2<f32>(size);
+    //Split-sum approximation assumes n = v = r
+    let n = normalize(face_direction(global_invocation_id.z, uv));
+    var color = vec3<f32>(0.);
+    var total_weight = 0.;
+    for (var i = 0u; i < SAMPLE_COUNTu; i++) {
+        let h = importance_sample_ggx(hammersley(i, SAMPLE_COUNTu), n, prefilter.roughness);
+        let l = normalize(2. * dot(n, h) * h - n);
+        let n_dot_l = dot(n, l);
+        if n_dot_l > 0. {
+            //Sample a lower mip of the source where the pdf is low to avoid fireflies
+            let n_dot_h = max(dot(n, h), 0.);
+            let a = prefilter.roughness * prefilter.roughness;
+            let d = (n_dot_h * n_dot_h) * (a * a - 1.) + 1.;
+            let pdf = (a * a) / (PI * d * d) / 4. + 0.0001;
+            let texel_solid_angle = 4. * PI / (6. * prefilter.source_face_size * prefilter.source_face_size);
+            let sample_solid_angle = 1. / (f32(SAMPLE_COUNTu) * pdf + 0.0001);
+            var mip_level = 0.;
+            if prefilter.roughness > 0. {
+                mip_level = 0.5 * log2(sample_solid_angle / texel_solid_angle);
+            }
+            color += textureSampleLevel(environment, environment_sampler, l, mip_level).rgb * n_dot_l;
+            total_weight += n_dot_l;
+        }
+    }
+    color = color / max(total_weight, 0.0001);
+    textureStore(prefiltered, vec2<i32>(global_invocation_id.xy), i32(global_invocation_id.z), vec4<f32>(color, 1.));
+}
+"#;
+
+fn create_shader(device: &wgpu::Device, label: &str, source: &str) -> wgpu::ShaderModule {
+    let source = format!("{IBL_COMMON_SHADER}{source}")
+        .replace("SAMPLE_COUNT", IBL_SAMPLE_COUNT.to_string().as_str());
+    device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some(label),
+        source: wgpu::ShaderSource::Wgsl(source.into()),
+    })
+}
+
+fn storage_texture_entry(
+    binding: u32,
+    view_dimension: wgpu::TextureViewDimension,
+) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::StorageTexture {
+            access: wgpu::StorageTextureAccess::WriteOnly,
+            format: wgpu::TextureFormat::Rgba16Float,
+            view_dimension,
+        },
+        count: None,
+    }
+}
+
+fn dispatch_size(size: u32) -> u32 {
+    (size + IBL_WORKGROUP_SIZE - 1) / IBL_WORKGROUP_SIZE
+}
+
+//Texel (x, y) of the lut stores the split-sum scale and bias for n_dot_v along x and roughness along y
+pub fn brdf_lut_texel_coords(x: u32, y: u32, size: u32) -> (f32, f32) {
+    let n_dot_v = ((x as f32 + 0.5) / size as f32).max(0.0001);
+    let roughness = (y as f32 + 0.5) / size as f32;
+    (n_dot_v, roughness)
+}
+
+//Cpu reference of the BRDF_LUT_SHADER integration
+pub fn integrate_brdf(n_dot_v: f32, roughness: f32, sample_count: u32) -> (f32, f32) {
+    let geometry_schlick_ggx = |n_dot_v: f32| {
+        let k = (roughness * roughness) / 2.;
+        n_dot_v / (n_dot_v * (1. - k) + k)
+    };
+    let v = Vector3::new((1. - n_dot_v * n_dot_v).sqrt(), 0., n_dot_v);
+    let a = roughness * roughness;
+    let mut scale = 0.;
+    let mut bias = 0.;
+    for i in 0..sample_count {
+        let xi = (
+            i as f32 / sample_count as f32,
+            i.reverse_bits() as f32 * 2.328_306_4e-10,
+        );
+        let phi = 2. * std::f32::consts::PI * xi.0;
+        let cos_theta = ((1. - xi.1) / (1. + (a * a - 1.) * xi.1)).sqrt();
+        let sin_theta = (1. - cos_theta * cos_theta).sqrt();
+        //Normal is the z axis so the half vector is already in tangent space
+        let h = Vector3::new(phi.cos() * sin_theta, phi.sin() * sin_theta, cos_theta);
+        let l = (h * 2. * v.dot(h) - v).normalize();
+        let n_dot_l = l.z.max(0.);
+        let n_dot_h = h.z.max(0.);
+        let v_dot_h = v.dot(h).max(0.);
+        if n_dot_l > 0. {
+            let g = geometry_schlick_ggx(n_dot_v) * geometry_schlick_ggx(n_dot_l);
+            let g_vis = (g * v_dot_h) / (n_dot_h * n_dot_v);
+            let fc = (1. - v_dot_h).powi(5);
+            scale += (1. - fc) * g_vis;
+            bias += fc * g_vis;
+        }
+    }
+    (scale / sample_count as f32, bias / sample_count as f32)
+}
+
+//Fills the rgba16float storage texture with the split-sum BRDF integration
+pub fn generate_brdf_lut(
+    device: &wgpu::Device,
+    encoder: &mut wgpu::CommandEncoder,
+    brdf_lut: &GpuTexture,
+) {
+    inox_profiler::scoped_profile!("ibl::generate_brdf_lut");
+
+    let shader = create_shader(device, "BrdfLut", BRDF_LUT_SHADER);
+    let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("BrdfLut BindGroupLayout"),
+        entries: &[storage_texture_entry(0, wgpu::TextureViewDimension::D2)],
+    });
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("BrdfLut PipelineLayout"),
+        bind_group_layouts: &[&bind_group_layout],
+        push_constant_ranges: &[],
+    });
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: Some("BrdfLut Pipeline"),
+        layout: Some(&pipeline_layout),
+        module: &shader,
+        entry_point: "main",
+    });
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("BrdfLut BindGroup"),
+        layout: &bind_group_layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: wgpu::BindingResource::TextureView(brdf_lut.view().as_wgpu()),
+        }],
+    });
+    let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+        label: Some("BrdfLut ComputePass"),
+    });
+    compute_pass.set_pipeline(&pipeline);
+    compute_pass.set_bind_group(0, &bind_group, &[]);
+    compute_pass.dispatch_workgroups(
+        dispatch_size(brdf_lut.width()),
+        dispatch_size(brdf_lut.height()),
+        1,
+    );
+}
+
+//Every mip level of the prefiltered cubemap stores the environment convolved with
+//the GGX lobe of roughness mip / (mip_levels - 1)
+pub fn prefilter_environment(
+    device: &wgpu::Device,
+    encoder: &mut wgpu::CommandEncoder,
+    sampler: &wgpu::Sampler,
+    environment: &GpuTexture,
+    prefiltered: &GpuTexture,
+) {
+    debug_assert!(
+        environment.is_cube(),
+        "Environment texture is not a cubemap"
+    );
+    debug_assert!(
+        prefiltered.is_cube(),
+        "Prefiltered texture is not a cubemap"
+    );
+    inox_profiler::scoped_profile!("ibl::prefilter_environment");
+
+    let shader = create_shader(device, "PrefilterEnvironment", PREFILTER_SHADER);
+    let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("PrefilterEnvironment BindGroupLayout"),
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::Cube,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            storage_texture_entry(3, wgpu::TextureViewDimension::D2Array),
+        ],
+    });
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("PrefilterEnvironment PipelineLayout"),
+        bind_group_layouts: &[&bind_group_layout],
+        push_constant_ranges: &[],
+    });
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: Some("PrefilterEnvironment Pipeline"),
+        layout: Some(&pipeline_layout),
+        module: &shader,
+        entry_point: "main",
+    });
+
+    let mip_levels_count = prefiltered.mip_levels_count();
+    for mip_level in 0..mip_levels_count {
+        let roughness = if mip_levels_count > 1 {
+            mip_level as f32 / (mip_levels_count - 1) as f32
+        } else {
+            0.
+        };
+        let prefilter_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("PrefilterEnvironment PrefilterData"),
+            contents: &[roughness, environment.width() as f32, 0., 0.]
+                .iter()
+                .flat_map(|v| v.to_le_bytes())
+                .collect::<Vec<u8>>(),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let mip_view = prefiltered.mip_view(mip_level);
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("PrefilterEnvironment BindGroup"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(environment.view().as_wgpu()),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: prefilter_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(mip_view.as_wgpu()),
+                },
+            ],
+        });
+        let mip_size = (prefiltered.width() >> mip_level).max(1);
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("PrefilterEnvironment ComputePass"),
+        });
+        compute_pass.set_pipeline(&pipeline);
+        compute_pass.set_bind_group(0, &bind_group, &[]);
+        compute_pass.dispatch_workgroups(
+            dispatch_size(mip_size),
+            dispatch_size(mip_size),
+            CUBEMAP_FACES_COUNT,
+        );
+    }
+}
+
+//Runs generate_brdf_lut on a device without a surface and reads the lut back,
+//none when the system has no adapter able to run it
+#[allow(dead_code)]
+#[cfg(not(target_arch = "wasm32"))]
+fn gpu_brdf_lut() -> Option<Vec<(f32, f32)>> {
+    use inox_math::decode_half;
+    use std::num::NonZeroU32;
+
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+    let adapter = futures::executor::block_on(
+        instance.request_adapter(&wgpu::RequestAdapterOptions::default()),
+    )?;
+    let (device, queue) = futures::executor::block_on(adapter.request_device(
+        &wgpu::DeviceDescriptor {
+            label: None,
+            features: wgpu::Features::empty(),
+            limits: adapter.limits(),
+        },
+        None,
+    ))
+    .ok()?;
+
+    let brdf_lut = GpuTexture::create(
+        &device,
+        inox_uid::generate_random_resource_uid(),
+        BRDF_LUT_SIZE,
+        BRDF_LUT_SIZE,
+        1,
+        crate::TextureFormat::Rgba16Float,
+        wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::COPY_SRC,
+    );
+    //Four half floats for each texel
+    let bytes_per_row = BRDF_LUT_SIZE * 8;
+    let read_back = device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size: (bytes_per_row * BRDF_LUT_SIZE) as u64,
+        usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+    generate_brdf_lut(&device, &mut encoder, &brdf_lut);
+    encoder.copy_texture_to_buffer(
+        brdf_lut.texture().as_image_copy(),
+        wgpu::ImageCopyBuffer {
+            buffer: &read_back,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: NonZeroU32::new(bytes_per_row),
+                rows_per_image: None,
+            },
+        },
+        wgpu::Extent3d {
+            width: BRDF_LUT_SIZE,
+            height: BRDF_LUT_SIZE,
+            depth_or_array_layers: 1,
+        },
+    );
+    queue.submit(Some(encoder.finish()));
+
+    let slice = read_back.slice(..);
+    slice.map_async(wgpu::MapMode::Read, |_| {});
+    device.poll(wgpu::Maintain::Wait);
+    let texels = slice.get_mapped_range();
+    Some(
+        texels
+            .chunks(8)
+            .map(|t| {
+                let channel = |i: usize| decode_half(u16::from_le_bytes([t[i], t[i + 1]]));
+                (channel(0), channel(2))
+            })
+            .collect(),
+    )
+}
+
+#[allow(dead_code)]
+fn test_brdf_lut() {
+    //At normal incidence on a perfectly smooth surface the whole energy is in the scale term
+    let (n_dot_v, roughness) = brdf_lut_texel_coords(BRDF_LUT_SIZE - 1, 0, BRDF_LUT_SIZE);
+    let (scale, bias) = integrate_brdf(n_dot_v, roughness, IBL_SAMPLE_COUNT);
+    assert!((scale - 1.).abs() < 1e-2, "scale {scale} should be 1");
+    assert!(bias.abs() < 1e-2, "bias {bias} should be 0");
+
+    //Grazing angles on rough surfaces lose energy
+    let (n_dot_v, roughness) = brdf_lut_texel_coords(0, BRDF_LUT_SIZE - 1, BRDF_LUT_SIZE);
+    let (scale, bias) = integrate_brdf(n_dot_v, roughness, IBL_SAMPLE_COUNT);
+    assert!(scale + bias < 1.);
+}
+
+//The lut written by the gpu pass matches the cpu reference
+#[allow(dead_code)]
+#[cfg(not(target_arch = "wasm32"))]
+fn test_gpu_brdf_lut() {
+    let Some(brdf_lut) = gpu_brdf_lut() else {
+        //No adapter able to run the pass on this system
+        return;
+    };
+    assert_eq!(brdf_lut.len(), (BRDF_LUT_SIZE * BRDF_LUT_SIZE) as usize);
+    let last = BRDF_LUT_SIZE - 1;
+    [(last, 0), (0, last), (last / 2, last / 2), (last / 4, last)]
+        .into_iter()
+        .for_each(|(x, y)| {
+            let (n_dot_v, roughness) = brdf_lut_texel_coords(x, y, BRDF_LUT_SIZE);
+            let expected = integrate_brdf(n_dot_v, roughness, IBL_SAMPLE_COUNT);
+            let texel = brdf_lut[(y * BRDF_LUT_SIZE + x) as usize];
+            assert!(
+                (texel.0 - expected.0).abs() < 1e-2 && (texel.1 - expected.1).abs() < 1e-2,
+                "texel ({x}, {y}) is {texel:?} instead of {expected:?}"
+            );
+        });
+}
+
+#[test]
+fn test() {
+    test_brdf_lut();
+    #[cfg(not(target_arch = "wasm32"))]
+    test_gpu_brdf_lut();
+}
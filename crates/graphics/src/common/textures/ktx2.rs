@@ -0,0 +1,301 @@
+use crate::{AstcBlock, AstcChannel, TextureData, TextureFormat, TextureUsage};
+
+pub const KTX2_EXTENSION: &str = "ktx2";
+
+const KTX2_IDENTIFIER: [u8; 12] = [
+    0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A,
+];
+const KTX2_HEADER_SIZE: usize = 80;
+const KTX2_LEVEL_INDEX_SIZE: usize = 24;
+
+const SUPERCOMPRESSION_NONE: u32 = 0;
+const SUPERCOMPRESSION_BASIS_LZ: u32 = 1;
+
+const VK_FORMAT_UNDEFINED: u32 = 0;
+const VK_FORMAT_R8G8B8A8_UNORM: u32 = 37;
+const VK_FORMAT_R8G8B8A8_SRGB: u32 = 43;
+const VK_FORMAT_BC1_RGBA_UNORM_BLOCK: u32 = 133;
+const VK_FORMAT_BC1_RGBA_SRGB_BLOCK: u32 = 134;
+const VK_FORMAT_BC3_UNORM_BLOCK: u32 = 137;
+const VK_FORMAT_BC3_SRGB_BLOCK: u32 = 138;
+const VK_FORMAT_BC7_UNORM_BLOCK: u32 = 145;
+const VK_FORMAT_BC7_SRGB_BLOCK: u32 = 146;
+const VK_FORMAT_ETC2_R8G8B8_UNORM_BLOCK: u32 = 147;
+const VK_FORMAT_ETC2_R8G8B8_SRGB_BLOCK: u32 = 148;
+const VK_FORMAT_ETC2_R8G8B8A1_UNORM_BLOCK: u32 = 149;
+const VK_FORMAT_ETC2_R8G8B8A1_SRGB_BLOCK: u32 = 150;
+const VK_FORMAT_ETC2_R8G8B8A8_UNORM_BLOCK: u32 = 151;
+const VK_FORMAT_ETC2_R8G8B8A8_SRGB_BLOCK: u32 = 152;
+const VK_FORMAT_ASTC_4X4_UNORM_BLOCK: u32 = 157;
+const VK_FORMAT_ASTC_4X4_SRGB_BLOCK: u32 = 158;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ktx2Header {
+    pub vk_format: u32,
+    pub width: u32,
+    pub height: u32,
+    pub level_count: u32,
+    pub supercompression_scheme: u32,
+    pub level0_offset: usize,
+    pub level0_length: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ktx2Error {
+    InvalidHeader,
+    BasisTranscodingUnsupported { target: Option<TextureFormat> },
+    SupercompressionUnsupported(u32),
+    FormatUnsupported(u32),
+    FormatNotSupportedByDevice(TextureFormat),
+}
+
+impl std::fmt::Display for Ktx2Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Ktx2Error::InvalidHeader => write!(f, "KTX2 header or level index is invalid"),
+            Ktx2Error::BasisTranscodingUnsupported { target } => {
+                write!(
+                    f,
+                    "KTX2 Basis payload needs transcoding to {target:?} \
+                    but Basis Universal transcoding is not supported"
+                )
+            }
+            Ktx2Error::SupercompressionUnsupported(scheme) => {
+                write!(f, "KTX2 supercompression scheme {scheme} is not supported")
+            }
+            Ktx2Error::FormatUnsupported(vk_format) => {
+                write!(f, "KTX2 vkFormat {vk_format} is not supported")
+            }
+            Ktx2Error::FormatNotSupportedByDevice(format) => {
+                write!(
+                    f,
+                    "KTX2 texture format {format:?} is not supported by device"
+                )
+            }
+        }
+    }
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
+
+pub fn is_ktx2(bytes: &[u8]) -> bool {
+    bytes.len() >= KTX2_HEADER_SIZE && bytes[0..KTX2_IDENTIFIER.len()] == KTX2_IDENTIFIER
+}
+
+pub fn parse_ktx2_header(bytes: &[u8]) -> Option<Ktx2Header> {
+    if !is_ktx2(bytes) || bytes.len() < KTX2_HEADER_SIZE + KTX2_LEVEL_INDEX_SIZE {
+        return None;
+    }
+    let header = Ktx2Header {
+        vk_format: read_u32(bytes, 12),
+        width: read_u32(bytes, 20),
+        height: read_u32(bytes, 24).max(1),
+        level_count: read_u32(bytes, 40).max(1),
+        supercompression_scheme: read_u32(bytes, 44),
+        //Level index starts right after the header and level 0 is always the first entry
+        level0_offset: read_u64(bytes, KTX2_HEADER_SIZE) as usize,
+        level0_length: read_u64(bytes, KTX2_HEADER_SIZE + 8) as usize,
+    };
+    match header.level0_offset.checked_add(header.level0_length) {
+        Some(end) if end <= bytes.len() => Some(header),
+        _ => None,
+    }
+}
+
+pub fn texture_format_from_vk_format(vk_format: u32) -> Option<TextureFormat> {
+    let astc_4x4 = |channel| TextureFormat::Astc {
+        block: AstcBlock::B4x4,
+        channel,
+    };
+    match vk_format {
+        VK_FORMAT_R8G8B8A8_UNORM => Some(TextureFormat::Rgba8Unorm),
+        VK_FORMAT_R8G8B8A8_SRGB => Some(TextureFormat::Rgba8UnormSrgb),
+        VK_FORMAT_BC1_RGBA_UNORM_BLOCK => Some(TextureFormat::Bc1RgbaUnorm),
+        VK_FORMAT_BC1_RGBA_SRGB_BLOCK => Some(TextureFormat::Bc1RgbaUnormSrgb),
+        VK_FORMAT_BC3_UNORM_BLOCK => Some(TextureFormat::Bc3RgbaUnorm),
+        VK_FORMAT_BC3_SRGB_BLOCK => Some(TextureFormat::Bc3RgbaUnormSrgb),
+        VK_FORMAT_BC7_UNORM_BLOCK => Some(TextureFormat::Bc7RgbaUnorm),
+        VK_FORMAT_BC7_SRGB_BLOCK => Some(TextureFormat::Bc7RgbaUnormSrgb),
+        VK_FORMAT_ETC2_R8G8B8_UNORM_BLOCK => Some(TextureFormat::Etc2Rgb8Unorm),
+        VK_FORMAT_ETC2_R8G8B8_SRGB_BLOCK => Some(TextureFormat::Etc2Rgb8UnormSrgb),
+        VK_FORMAT_ETC2_R8G8B8A1_UNORM_BLOCK => Some(TextureFormat::Etc2Rgb8A1Unorm),
+        VK_FORMAT_ETC2_R8G8B8A1_SRGB_BLOCK => Some(TextureFormat::Etc2Rgb8A1UnormSrgb),
+        VK_FORMAT_ETC2_R8G8B8A8_UNORM_BLOCK => Some(TextureFormat::Etc2Rgba8Unorm),
+        VK_FORMAT_ETC2_R8G8B8A8_SRGB_BLOCK => Some(TextureFormat::Etc2Rgba8UnormSrgb),
+        VK_FORMAT_ASTC_4X4_UNORM_BLOCK => Some(astc_4x4(AstcChannel::Unorm)),
+        VK_FORMAT_ASTC_4X4_SRGB_BLOCK => Some(astc_4x4(AstcChannel::UnormSrgb)),
+        _ => None,
+    }
+}
+
+//Block format that a Basis Universal payload should be transcoded to on this device
+pub fn preferred_compressed_format(features: wgpu::Features) -> Option<TextureFormat> {
+    if features.contains(wgpu::Features::TEXTURE_COMPRESSION_BC) {
+        Some(TextureFormat::Bc7RgbaUnorm)
+    } else if features.contains(wgpu::Features::TEXTURE_COMPRESSION_ASTC_LDR) {
+        Some(TextureFormat::Astc {
+            block: AstcBlock::B4x4,
+            channel: AstcChannel::Unorm,
+        })
+    } else if features.contains(wgpu::Features::TEXTURE_COMPRESSION_ETC2) {
+        Some(TextureFormat::Etc2Rgba8Unorm)
+    } else {
+        None
+    }
+}
+
+pub fn is_format_supported(format: TextureFormat, features: wgpu::Features) -> bool {
+    let format: wgpu::TextureFormat = format.into();
+    features.contains(format.required_features())
+}
+
+//Returns the level 0 payload as it is stored in the file, ready to be uploaded without re-encoding.
+//An error means that the caller has to fall back to an uncompressed RGBA8 source.
+//Basis Universal payloads are not transcoded and always return an error.
+pub fn load_ktx2(bytes: &[u8], features: wgpu::Features) -> Result<TextureData, Ktx2Error> {
+    let header = parse_ktx2_header(bytes).ok_or(Ktx2Error::InvalidHeader)?;
+    if header.vk_format == VK_FORMAT_UNDEFINED
+        || header.supercompression_scheme == SUPERCOMPRESSION_BASIS_LZ
+    {
+        return Err(Ktx2Error::BasisTranscodingUnsupported {
+            target: preferred_compressed_format(features),
+        });
+    }
+    if header.supercompression_scheme != SUPERCOMPRESSION_NONE {
+        return Err(Ktx2Error::SupercompressionUnsupported(
+            header.supercompression_scheme,
+        ));
+    }
+    let format = texture_format_from_vk_format(header.vk_format)
+        .ok_or(Ktx2Error::FormatUnsupported(header.vk_format))?;
+    if !is_format_supported(format, features) {
+        return Err(Ktx2Error::FormatNotSupportedByDevice(format));
+    }
+    let data = bytes[header.level0_offset..header.level0_offset + header.level0_length].to_vec();
+    Ok(TextureData {
+        width: header.width,
+        height: header.height,
+        format,
+        data: Some(data),
+        usage: TextureUsage::TextureBinding | TextureUsage::CopyDst,
+    })
+}
+
+#[allow(dead_code)]
+fn create_test_ktx2(
+    vk_format: u32,
+    supercompression_scheme: u32,
+    width: u32,
+    height: u32,
+    payload: &[u8],
+) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&KTX2_IDENTIFIER);
+    //vkFormat, typeSize, width, height, depth, layers, faces, levels, supercompression
+    let header = [
+        vk_format,
+        1,
+        width,
+        height,
+        0,
+        0,
+        1,
+        1,
+        supercompression_scheme,
+    ];
+    for value in header {
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+    //Data format, key/value and supercompression global data are left empty
+    bytes.extend_from_slice(&[0u8; 32]);
+    let level0_offset = (KTX2_HEADER_SIZE + KTX2_LEVEL_INDEX_SIZE) as u64;
+    for value in [level0_offset, payload.len() as u64, payload.len() as u64] {
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+    bytes.extend_from_slice(payload);
+    bytes
+}
+
+#[allow(dead_code)]
+fn test_ktx2() {
+    let block = [0xAAu8; 16];
+    let bytes = create_test_ktx2(
+        VK_FORMAT_BC7_UNORM_BLOCK,
+        SUPERCOMPRESSION_NONE,
+        4,
+        4,
+        &block,
+    );
+    let header = parse_ktx2_header(&bytes).unwrap();
+    assert_eq!(header.width, 4);
+    assert_eq!(header.height, 4);
+    assert_eq!(header.level0_length, block.len());
+
+    let data = load_ktx2(&bytes, wgpu::Features::TEXTURE_COMPRESSION_BC).unwrap();
+    assert_eq!(data.format, TextureFormat::Bc7RgbaUnorm);
+    assert_eq!(data.data.as_deref(), Some(block.as_slice()));
+
+    //Without device support the caller has to fall back to RGBA8
+    assert_eq!(
+        load_ktx2(&bytes, wgpu::Features::empty()).err(),
+        Some(Ktx2Error::FormatNotSupportedByDevice(
+            TextureFormat::Bc7RgbaUnorm
+        ))
+    );
+    //Adapters without BC can still create the device
+    assert!(
+        !crate::platform::required_gpu_features().contains(wgpu::Features::TEXTURE_COMPRESSION_BC)
+    );
+    assert_eq!(
+        preferred_compressed_format(wgpu::Features::TEXTURE_COMPRESSION_BC),
+        Some(TextureFormat::Bc7RgbaUnorm)
+    );
+    assert_eq!(preferred_compressed_format(wgpu::Features::empty()), None);
+
+    let pixels = [0xFFu8; 4];
+    let bytes = create_test_ktx2(
+        VK_FORMAT_R8G8B8A8_UNORM,
+        SUPERCOMPRESSION_NONE,
+        1,
+        1,
+        &pixels,
+    );
+    let data = load_ktx2(&bytes, wgpu::Features::empty()).unwrap();
+    assert_eq!(data.format, TextureFormat::Rgba8Unorm);
+
+    assert!(parse_ktx2_header(&bytes[..KTX2_HEADER_SIZE]).is_none());
+
+    //A level 0 range that overflows is invalid instead of panicking
+    let mut overflowing = bytes.clone();
+    let length_offset = KTX2_HEADER_SIZE + 8;
+    overflowing[length_offset..length_offset + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+    assert!(parse_ktx2_header(&overflowing).is_none());
+    assert_eq!(
+        load_ktx2(&overflowing, wgpu::Features::empty()).err(),
+        Some(Ktx2Error::InvalidHeader)
+    );
+
+    //Basis Universal payloads are not transcoded
+    let basis = create_test_ktx2(VK_FORMAT_UNDEFINED, SUPERCOMPRESSION_BASIS_LZ, 4, 4, &block);
+    assert_eq!(
+        load_ktx2(&basis, wgpu::Features::TEXTURE_COMPRESSION_BC).err(),
+        Some(Ktx2Error::BasisTranscodingUnsupported {
+            target: Some(TextureFormat::Bc7RgbaUnorm)
+        })
+    );
+    let uastc = create_test_ktx2(VK_FORMAT_UNDEFINED, SUPERCOMPRESSION_NONE, 4, 4, &block);
+    assert!(matches!(
+        load_ktx2(&uastc, wgpu::Features::empty()),
+        Err(Ktx2Error::BasisTranscodingUnsupported { target: None })
+    ));
+}
+
+#[test]
+fn test() {
+    test_ktx2();
+}
@@ -1,10 +1,18 @@
+pub use cubemap::*;
 pub use gpu_texture::*;
+pub use ibl::*;
+pub use ktx2::*;
+pub use sampler_cache::*;
 pub use texture_atlas::*;
 pub use texture_handler::*;
 
 pub const TEXTURE_CHANNEL_COUNT: u32 = 4;
 
 mod area;
+pub mod cubemap;
 pub mod gpu_texture;
+pub mod ibl;
+pub mod ktx2;
+pub mod sampler_cache;
 pub mod texture_atlas;
 pub mod texture_handler;
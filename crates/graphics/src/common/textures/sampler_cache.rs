@@ -0,0 +1,131 @@
+use inox_log::debug_log;
+
+use crate::SamplerData;
+
+pub const MAX_SAMPLER_COUNT: u32 = 8u32;
+
+//Samplers of the textures, one for each different SamplerData requested.
+//The first one is the default and it's used for new SamplerData once the cache is full
+pub struct SamplerCache<T> {
+    samplers: Vec<(SamplerData, T)>,
+}
+
+impl<T> SamplerCache<T> {
+    pub fn new(create_sampler: impl FnOnce(&SamplerData) -> T) -> Self {
+        let sampler_data = SamplerData::default();
+        let sampler = create_sampler(&sampler_data);
+        Self {
+            samplers: vec![(sampler_data, sampler)],
+        }
+    }
+
+    pub fn index(&self, sampler_data: &SamplerData) -> Option<u32> {
+        self.samplers
+            .iter()
+            .position(|(data, _)| data == sampler_data)
+            .map(|index| index as _)
+    }
+
+    pub fn get_or_create(
+        &mut self,
+        sampler_data: &SamplerData,
+        create_sampler: impl FnOnce(&SamplerData) -> T,
+    ) -> u32 {
+        if let Some(index) = self.index(sampler_data) {
+            return index;
+        }
+        if self.samplers.len() >= MAX_SAMPLER_COUNT as usize {
+            debug_log!(
+                "Unable to add a sampler for {:?}, using the default one",
+                sampler_data
+            );
+            return 0;
+        }
+        self.samplers
+            .push((*sampler_data, create_sampler(sampler_data)));
+        debug_log!("Adding new sampler {:?}", sampler_data);
+        (self.samplers.len() - 1) as _
+    }
+
+    pub fn count(&self) -> usize {
+        self.samplers.len()
+    }
+
+    //Samplers are created again from their data keeping their indices
+    pub fn recreate(&mut self, create_sampler: impl Fn(&SamplerData) -> T) {
+        self.samplers
+            .iter_mut()
+            .for_each(|(data, sampler)| *sampler = create_sampler(data));
+    }
+
+    pub fn default_sampler(&self) -> &T {
+        &self.samplers[0].1
+    }
+
+    //Samplers bound in the sampler array, the default one fills the count of the layout
+    pub fn bindings(&self) -> Vec<&T> {
+        let mut samplers = self.samplers.iter().map(|(_, s)| s).collect::<Vec<_>>();
+        samplers.resize(MAX_SAMPLER_COUNT as _, self.default_sampler());
+        samplers
+    }
+}
+
+#[allow(dead_code)]
+fn test_sampler_cache() {
+    use crate::{AddressMode, FilterMode};
+
+    let mut cache = SamplerCache::new(|data| *data);
+    let clamp = SamplerData::default();
+    let repeat = SamplerData::repeat();
+    assert_eq!(clamp.address_mode_u, AddressMode::ClampToEdge);
+
+    let clamp_index = cache.get_or_create(&clamp, |data| *data);
+    let repeat_index = cache.get_or_create(&repeat, |data| *data);
+    assert_eq!(clamp_index, 0);
+    assert_ne!(repeat_index, clamp_index);
+    assert_eq!(cache.count(), 2);
+
+    //Textures asking for the same SamplerData share the cached sampler
+    assert_eq!(
+        cache.get_or_create(&repeat, |_| unreachable!()),
+        repeat_index
+    );
+    let bindings = cache.bindings();
+    assert_eq!(bindings.len(), MAX_SAMPLER_COUNT as usize);
+    assert_eq!(*bindings[repeat_index as usize], repeat);
+    assert_eq!(*bindings[MAX_SAMPLER_COUNT as usize - 1], clamp);
+
+    //Once full the default sampler is used
+    for anisotropy_clamp in 2..2 + MAX_SAMPLER_COUNT as u16 {
+        cache.get_or_create(
+            &SamplerData {
+                mag_filter: FilterMode::Nearest,
+                anisotropy_clamp,
+                ..repeat
+            },
+            |data| *data,
+        );
+    }
+    assert_eq!(cache.count(), MAX_SAMPLER_COUNT as usize);
+    let mirror = SamplerData {
+        address_mode_u: AddressMode::MirrorRepeat,
+        ..clamp
+    };
+    assert_eq!(cache.get_or_create(&mirror, |data| *data), 0);
+    assert_eq!(cache.index(&mirror), None);
+
+    cache.recreate(|data| SamplerData {
+        anisotropy_clamp: data.anisotropy(4),
+        ..*data
+    });
+    assert_eq!(cache.count(), MAX_SAMPLER_COUNT as usize);
+    assert_eq!(cache.index(&repeat), Some(repeat_index));
+    let bindings = cache.bindings();
+    assert_eq!(bindings[repeat_index as usize].anisotropy_clamp, 1);
+    assert_eq!(bindings[MAX_SAMPLER_COUNT as usize - 1].anisotropy_clamp, 4);
+}
+
+#[test]
+fn test() {
+    test_sampler_cache();
+}
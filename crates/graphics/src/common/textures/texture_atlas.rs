@@ -1,7 +1,10 @@
 use inox_log::debug_log;
-use inox_uid::generate_random_uid;
+use inox_uid::generate_random_resource_uid;
 
-use crate::{TextureFormat, TextureId, TextureInfo, TextureView};
+use crate::{
+    platform::required_gpu_features, TextureFormat, TextureId, TextureInfo, TextureRegion,
+    TextureView,
+};
 
 use super::{
     area::{Area, AreaAllocator, DEFAULT_AREA_SIZE},
@@ -9,22 +12,240 @@ use super::{
 };
 
 pub const DEFAULT_LAYER_COUNT: u32 = 8u32;
+//Atlases bound when the shaders can't index an array of textures, each one has its own binding
 pub const MAX_TEXTURE_ATLAS_COUNT: u32 = 8u32;
+//Sampled textures of a shader stage left to the other textures bound by the passes
+pub const RESERVED_SAMPLED_TEXTURE_COUNT: u32 = 8u32;
 
-pub struct TextureAtlas {
-    texture: GpuTexture,
+//Atlases that can be bound at once in the texture array of the passes
+pub fn max_texture_atlas_count(limits: &wgpu::Limits) -> u32 {
+    if required_gpu_features().contains(wgpu::Features::TEXTURE_BINDING_ARRAY) {
+        limits
+            .max_sampled_textures_per_shader_stage
+            .saturating_sub(RESERVED_SAMPLED_TEXTURE_COUNT)
+            .max(1)
+    } else {
+        MAX_TEXTURE_ATLAS_COUNT
+    }
+}
+
+//Textures bound in the texture array of the passes.
+//Every atlas is bound, the first one is repeated to fill the count of the layout
+pub fn texture_atlas_bindings(
+    atlas_ids: impl Iterator<Item = TextureId>,
+    count: u32,
+) -> Vec<TextureId> {
+    let mut textures = atlas_ids.take(count as _).collect::<Vec<_>>();
+    let first = textures.first().copied().unwrap_or_default();
+    textures.resize(count as _, first);
+    textures
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureAtlasError {
+    TooLarge {
+        id: TextureId,
+        width: u32,
+        height: u32,
+    },
+    Exhausted {
+        id: TextureId,
+        format: TextureFormat,
+        max_count: u32,
+    },
+}
+
+impl std::fmt::Display for TextureAtlasError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TextureAtlasError::TooLarge { id, width, height } => {
+                write!(
+                    f,
+                    "Unable to allocate texture {id} of {width}x{height}: \
+                    atlas layers are {DEFAULT_AREA_SIZE}x{DEFAULT_AREA_SIZE}"
+                )
+            }
+            TextureAtlasError::Exhausted {
+                id,
+                format,
+                max_count,
+            } => {
+                write!(
+                    f,
+                    "Unable to allocate texture {id}: all the {max_count} texture atlases \
+                    supported by the device are in use and none with format {format:?} has room"
+                )
+            }
+        }
+    }
+}
+
+//Areas used in every layer of an atlas, without its gpu texture
+#[derive(Clone)]
+pub struct AtlasAllocator {
+    id: TextureId,
+    format: TextureFormat,
     allocators: Vec<AreaAllocator>,
 }
 
-impl TextureAtlas {
-    pub fn create_default(device: &wgpu::Device, format: TextureFormat) -> Self {
+impl AsMut<AtlasAllocator> for AtlasAllocator {
+    fn as_mut(&mut self) -> &mut AtlasAllocator {
+        self
+    }
+}
+
+impl AtlasAllocator {
+    pub fn new(id: &TextureId, format: TextureFormat) -> Self {
         let mut allocators: Vec<AreaAllocator> = Vec::new();
         for _i in 0..DEFAULT_LAYER_COUNT {
             allocators.push(AreaAllocator::new(DEFAULT_AREA_SIZE, DEFAULT_AREA_SIZE));
         }
+        Self {
+            id: *id,
+            format,
+            allocators,
+        }
+    }
+
+    pub fn id(&self) -> &TextureId {
+        &self.id
+    }
+    pub fn format(&self) -> &TextureFormat {
+        &self.format
+    }
+
+    pub fn get_area(&self, texture_id: &TextureId) -> Option<&Area> {
+        for allocator in &self.allocators {
+            if let Some(area) = allocator.get_area(texture_id) {
+                return Some(area);
+            }
+        }
+        None
+    }
+
+    //Returns the layer and the area where the image has been placed
+    pub fn allocate(&mut self, id: &TextureId, dimensions: (u32, u32)) -> Option<(u32, Area)> {
+        //Areas of compressed formats have to start and end on block boundaries
+        let format: wgpu::TextureFormat = self.format.into();
+        let (block_width, block_height) = format.block_dimensions();
+        let width = ((dimensions.0 + block_width - 1) / block_width) * block_width;
+        let height = ((dimensions.1 + block_height - 1) / block_height) * block_height;
+        for (layer_index, area_allocator) in self.allocators.iter_mut().enumerate() {
+            if let Some(area) = area_allocator.allocate(id, width, height) {
+                return Some((layer_index as _, *area));
+            }
+        }
+        None
+    }
+
+    //Returns the layer and the area of the image
+    pub fn find(&self, texture_id: &TextureId) -> Option<(u32, &Area)> {
+        self.allocators
+            .iter()
+            .enumerate()
+            .find_map(|(layer_index, a)| {
+                a.get_area(texture_id).map(|area| (layer_index as _, area))
+            })
+    }
+
+    pub fn texture_info(&self, texture_index: u32, texture_id: &TextureId) -> Option<TextureInfo> {
+        for (layer_index, area_allocator) in self.allocators.iter().enumerate() {
+            if let Some(area) = area_allocator.get_area(texture_id) {
+                return Some(Self::info(texture_index, layer_index as _, area));
+            }
+        }
+        None
+    }
+
+    pub fn remove(&mut self, texture_id: &TextureId) -> bool {
+        for (layer_index, allocator) in self.allocators.iter_mut().enumerate() {
+            if allocator.remove_texture(texture_id) {
+                //todo remove the real texture from device memory
+                //atlas.texture.remove_from_layer(device, layer_index, &area);
+                debug_log!(
+                    "Removing from texture atlas with format {:?} at layer {:}",
+                    self.format,
+                    layer_index
+                )
+            }
+        }
+        self.is_empty()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.allocators.iter().all(|a| a.is_empty())
+    }
+
+    fn info(texture_index: u32, layer_index: u32, area: &Area) -> TextureInfo {
+        TextureInfo::new(
+            texture_index,
+            layer_index,
+            area.into(),
+            (DEFAULT_AREA_SIZE, DEFAULT_AREA_SIZE),
+        )
+    }
+}
+
+//Allocates the image in the first atlas with the same format and room for it.
+//A new atlas is added with create_atlas only while there are less than max_count of them.
+//Returns the index of the atlas with the layer and the area of the image
+pub fn allocate_in_atlases<T: AsMut<AtlasAllocator>>(
+    atlases: &mut Vec<T>,
+    max_count: u32,
+    id: &TextureId,
+    format: TextureFormat,
+    dimensions: (u32, u32),
+    create_atlas: impl FnOnce(TextureFormat) -> T,
+) -> Result<(usize, u32, Area), TextureAtlasError> {
+    let too_large = TextureAtlasError::TooLarge {
+        id: *id,
+        width: dimensions.0,
+        height: dimensions.1,
+    };
+    if dimensions.0 > DEFAULT_AREA_SIZE || dimensions.1 > DEFAULT_AREA_SIZE {
+        return Err(too_large);
+    }
+    for (atlas_index, atlas) in atlases.iter_mut().enumerate() {
+        let allocator = atlas.as_mut();
+        if allocator.format() == &format {
+            if let Some((layer_index, area)) = allocator.allocate(id, dimensions) {
+                return Ok((atlas_index, layer_index, area));
+            }
+        }
+    }
+    if atlases.len() >= max_count as usize {
+        return Err(TextureAtlasError::Exhausted {
+            id: *id,
+            format,
+            max_count,
+        });
+    }
+    atlases.push(create_atlas(format));
+    let atlas_index = atlases.len() - 1;
+    atlases[atlas_index]
+        .as_mut()
+        .allocate(id, dimensions)
+        .map(|(layer_index, area)| (atlas_index, layer_index, area))
+        .ok_or(too_large)
+}
+
+pub struct TextureAtlas {
+    texture: GpuTexture,
+    allocator: AtlasAllocator,
+}
+
+impl AsMut<AtlasAllocator> for TextureAtlas {
+    fn as_mut(&mut self) -> &mut AtlasAllocator {
+        &mut self.allocator
+    }
+}
+
+impl TextureAtlas {
+    pub fn create_default(device: &wgpu::Device, format: TextureFormat) -> Self {
+        let id = generate_random_resource_uid();
         let texture = GpuTexture::create(
             device,
-            generate_random_uid(),
+            id,
             DEFAULT_AREA_SIZE,
             DEFAULT_AREA_SIZE,
             DEFAULT_LAYER_COUNT,
@@ -33,7 +254,7 @@ impl TextureAtlas {
         );
         Self {
             texture,
-            allocators,
+            allocator: AtlasAllocator::new(&id, format),
         }
     }
 
@@ -58,70 +279,151 @@ impl TextureAtlas {
     }
 
     pub fn get_area(&self, texture_id: &TextureId) -> Option<&Area> {
-        for allocator in &self.allocators {
-            if let Some(area) = allocator.get_area(texture_id) {
-                return Some(area);
-            }
-        }
-        None
+        self.allocator.get_area(texture_id)
     }
 
-    pub fn allocate(
-        &mut self,
+    pub fn send_to_gpu(
+        &self,
         device: &wgpu::Device,
         encoder: &mut wgpu::CommandEncoder,
-        id: &TextureId,
         texture_index: u32,
-        dimensions: (u32, u32),
+        layer_index: u32,
+        area: &Area,
         image_data: &[u8],
-    ) -> Option<TextureInfo> {
-        for (layer_index, area_allocator) in self.allocators.iter_mut().enumerate() {
-            if let Some(area) = area_allocator.allocate(id, dimensions.0, dimensions.1) {
-                self.texture
-                    .send_to_gpu(device, encoder, layer_index as _, area, image_data);
-                return Some(TextureInfo {
-                    texture_index: texture_index as _,
-                    layer_index: layer_index as _,
-                    area: area.into(),
-                    total_width: self.texture.width() as _,
-                    total_height: self.texture.height() as _,
-                });
-            }
+    ) -> TextureInfo {
+        self.texture
+            .send_to_gpu(device, encoder, layer_index, area, image_data);
+        AtlasAllocator::info(texture_index, layer_index, area)
+    }
+
+    //Sends only a region of an image already in the atlas.
+    //Returns false when the image is not in this atlas
+    pub fn send_region_to_gpu(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        texture_id: &TextureId,
+        region: &TextureRegion,
+    ) -> bool {
+        if let Some((layer_index, area)) = self.allocator.find(texture_id) {
+            let region_area = Area::new(
+                texture_id,
+                area.x + region.x,
+                area.y + region.y,
+                region.width,
+                region.height,
+            );
+            self.texture
+                .send_to_gpu(device, encoder, layer_index, &region_area, &region.data);
+            return true;
         }
-        None
+        false
     }
 
     pub fn texture_info(&self, texture_index: u32, texture_id: &TextureId) -> Option<TextureInfo> {
-        for (layer_index, area_allocator) in self.allocators.iter().enumerate() {
-            if let Some(area) = area_allocator.get_area(texture_id) {
-                return Some(TextureInfo {
-                    texture_index: texture_index as _,
-                    layer_index: layer_index as _,
-                    area: area.into(),
-                    total_width: self.texture.width() as _,
-                    total_height: self.texture.height() as _,
-                });
-            }
-        }
-        None
+        self.allocator.texture_info(texture_index, texture_id)
     }
 
     pub fn remove(&mut self, texture_id: &TextureId) -> bool {
-        for (layer_index, allocator) in self.allocators.iter_mut().enumerate() {
-            if allocator.remove_texture(texture_id) {
-                //todo remove the real texture from device memory
-                //atlas.texture.remove_from_layer(device, layer_index, &area);
-                debug_log!(
-                    "Removing from texture atlas with format {:?} at layer {:}",
-                    self.texture.format(),
-                    layer_index
-                )
-            }
-        }
-        self.is_empty()
+        self.allocator.remove(texture_id)
     }
 
     pub fn is_empty(&self) -> bool {
-        self.allocators.iter().all(|a| a.is_empty())
+        self.allocator.is_empty()
     }
 }
+
+#[allow(dead_code)]
+fn test_texture_atlas_overflow() {
+    let format = TextureFormat::Rgba8Unorm;
+    let max_count = 3;
+    let create_atlas = |format| AtlasAllocator::new(&generate_random_resource_uid(), format);
+    let full_layer = (DEFAULT_AREA_SIZE, DEFAULT_AREA_SIZE);
+    let mut atlases: Vec<AtlasAllocator> = Vec::new();
+
+    //Every layer is filled by a single image, the last one overflows in a second atlas
+    for i in 0..DEFAULT_LAYER_COUNT + 1 {
+        let (atlas_index, layer_index, _) = allocate_in_atlases(
+            &mut atlases,
+            max_count,
+            &generate_random_resource_uid(),
+            format,
+            full_layer,
+            create_atlas,
+        )
+        .unwrap();
+        assert_eq!(atlas_index, (i / DEFAULT_LAYER_COUNT) as usize);
+        assert_eq!(layer_index, i % DEFAULT_LAYER_COUNT);
+    }
+    assert_eq!(atlases.len(), 2);
+
+    let bindings = texture_atlas_bindings(atlases.iter().map(|a| *a.id()), max_count);
+    assert_eq!(
+        bindings,
+        vec![*atlases[0].id(), *atlases[1].id(), *atlases[0].id()]
+    );
+
+    //Images of a different format never share an atlas
+    let (atlas_index, _, _) = allocate_in_atlases(
+        &mut atlases,
+        max_count,
+        &generate_random_resource_uid(),
+        TextureFormat::Rgba16Float,
+        (64, 64),
+        create_atlas,
+    )
+    .unwrap();
+    assert_eq!(atlas_index, 2);
+
+    //Once the device limit is reached an error is returned instead of a new atlas
+    for _ in 0..DEFAULT_LAYER_COUNT - 1 {
+        allocate_in_atlases(
+            &mut atlases,
+            max_count,
+            &generate_random_resource_uid(),
+            format,
+            full_layer,
+            create_atlas,
+        )
+        .unwrap();
+    }
+    let id = generate_random_resource_uid();
+    assert_eq!(
+        allocate_in_atlases(
+            &mut atlases,
+            max_count,
+            &id,
+            format,
+            full_layer,
+            create_atlas
+        )
+        .err(),
+        Some(TextureAtlasError::Exhausted {
+            id,
+            format,
+            max_count,
+        })
+    );
+    assert_eq!(
+        allocate_in_atlases(
+            &mut atlases,
+            max_count,
+            &id,
+            format,
+            (DEFAULT_AREA_SIZE + 1, 1),
+            create_atlas,
+        )
+        .err(),
+        Some(TextureAtlasError::TooLarge {
+            id,
+            width: DEFAULT_AREA_SIZE + 1,
+            height: 1,
+        })
+    );
+    assert_eq!(atlases.len(), 3);
+}
+
+#[test]
+fn test() {
+    test_texture_atlas_overflow();
+}
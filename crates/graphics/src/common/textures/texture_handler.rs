@@ -1,23 +1,62 @@
-use std::sync::{Arc, RwLock, RwLockReadGuard};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, AtomicU16, AtomicU64, Ordering},
+        Arc, RwLock, RwLockReadGuard,
+    },
+};
 
 use inox_log::debug_log;
+use inox_uid::{generate_random_resource_uid, INVALID_RESOURCE_UID};
 
-use crate::{TextureFormat, TextureId, TextureInfo, TextureUsage};
+use crate::{SamplerData, TextureFormat, TextureId, TextureInfo, TextureRegion, TextureUsage};
 
-use super::{gpu_texture::GpuTexture, texture_atlas::TextureAtlas};
+use super::{
+    area::Area,
+    cubemap::convert_equirectangular_to_cube,
+    gpu_texture::{GpuTexture, CUBEMAP_FACES_COUNT},
+    ibl::{
+        generate_brdf_lut, prefilter_environment, BRDF_LUT_SIZE, IBL_PREFILTERED_FACE_SIZE,
+        IBL_PREFILTERED_MIP_LEVELS,
+    },
+    sampler_cache::SamplerCache,
+    texture_atlas::{allocate_in_atlases, texture_atlas_bindings, TextureAtlas, TextureAtlasError},
+};
+
+const IBL_TEXTURE_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
 
 pub struct TextureHandler {
     texture_atlas: RwLock<Vec<TextureAtlas>>,
+    max_texture_atlas_count: u32,
     render_targets: RwLock<Vec<GpuTexture>>,
+    //Changed when a render target is recreated with the same id or the samplers are recreated
+    //with the same indices, they must be bound again
+    render_targets_generation: AtomicU64,
     default_sampler: wgpu::Sampler,
+    //Samplers of the textures in the atlases
+    samplers: RwLock<SamplerCache<wgpu::Sampler>>,
+    max_anisotropy: u16,
+    //Anisotropy level set in the config, up to the device support
+    anisotropy: AtomicU16,
     unfiltered_sampler: wgpu::Sampler,
     depth_sampler: wgpu::Sampler,
+    environment_sampler: wgpu::Sampler,
+    brdf_lut_id: TextureId,
+    is_brdf_lut_generated: AtomicBool,
+    default_environment_id: TextureId,
+    //Prefiltered cubemap id for every environment cubemap id
+    prefiltered_environments: RwLock<HashMap<TextureId, TextureId>>,
+    active_environment_id: RwLock<TextureId>,
 }
 
 pub type TextureHandlerRc = Arc<TextureHandler>;
 
 impl TextureHandler {
-    pub fn create(device: &wgpu::Device) -> Self {
+    pub fn create(
+        device: &wgpu::Device,
+        max_texture_atlas_count: u32,
+        max_anisotropy: u16,
+    ) -> Self {
         let default_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
             address_mode_u: wgpu::AddressMode::Repeat,
             address_mode_v: wgpu::AddressMode::Repeat,
@@ -46,12 +85,51 @@ impl TextureHandler {
             compare: Some(wgpu::CompareFunction::LessEqual),
             ..Default::default()
         });
+        let environment_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        //Textures are zero initialized so the default environment is black
+        let default_environment = GpuTexture::create_cube(
+            device,
+            generate_random_resource_uid(),
+            1,
+            IBL_TEXTURE_FORMAT,
+            wgpu::TextureUsages::TEXTURE_BINDING,
+        );
+        let brdf_lut = GpuTexture::create(
+            device,
+            generate_random_resource_uid(),
+            BRDF_LUT_SIZE,
+            BRDF_LUT_SIZE,
+            1,
+            IBL_TEXTURE_FORMAT,
+            wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::STORAGE_BINDING,
+        );
+        let samplers =
+            SamplerCache::new(|data| device.create_sampler(&data.descriptor(max_anisotropy)));
         Self {
             texture_atlas: RwLock::new(Vec::new()),
-            render_targets: RwLock::new(Vec::new()),
+            max_texture_atlas_count,
             default_sampler,
+            samplers: RwLock::new(samplers),
+            max_anisotropy,
+            anisotropy: AtomicU16::new(max_anisotropy),
             unfiltered_sampler,
             depth_sampler,
+            environment_sampler,
+            brdf_lut_id: *brdf_lut.id(),
+            is_brdf_lut_generated: AtomicBool::new(false),
+            default_environment_id: *default_environment.id(),
+            prefiltered_environments: RwLock::new(HashMap::new()),
+            active_environment_id: RwLock::new(INVALID_RESOURCE_UID),
+            render_targets: RwLock::new(vec![default_environment, brdf_lut]),
+            render_targets_generation: AtomicU64::new(0),
         }
     }
     pub fn default_sampler(&self) -> &wgpu::Sampler {
@@ -63,6 +141,43 @@ impl TextureHandler {
     pub fn depth_sampler(&self) -> &wgpu::Sampler {
         &self.depth_sampler
     }
+    pub fn environment_sampler(&self) -> &wgpu::Sampler {
+        &self.environment_sampler
+    }
+    pub fn max_anisotropy(&self) -> u16 {
+        self.max_anisotropy
+    }
+    pub fn anisotropy(&self) -> u16 {
+        self.anisotropy.load(Ordering::SeqCst)
+    }
+    //Samplers asking for anisotropic filtering are limited to the level,
+    //returns the one applied once clamped to the device support
+    pub fn set_anisotropy(&self, device: &wgpu::Device, anisotropy: u16) -> u16 {
+        let anisotropy = anisotropy.clamp(1, self.max_anisotropy.max(1));
+        if self.anisotropy.swap(anisotropy, Ordering::SeqCst) != anisotropy {
+            self.samplers
+                .write()
+                .unwrap()
+                .recreate(|data| device.create_sampler(&data.descriptor(anisotropy)));
+            self.render_targets_generation
+                .fetch_add(1, Ordering::SeqCst);
+        }
+        anisotropy
+    }
+    pub fn samplers(&self) -> RwLockReadGuard<SamplerCache<wgpu::Sampler>> {
+        self.samplers.read().unwrap()
+    }
+    pub fn sampler_index(&self, device: &wgpu::Device, sampler_data: &SamplerData) -> u32 {
+        if let Some(index) = self.samplers.read().unwrap().index(sampler_data) {
+            return index;
+        }
+        self.samplers
+            .write()
+            .unwrap()
+            .get_or_create(sampler_data, |data| {
+                device.create_sampler(&data.descriptor(self.anisotropy()))
+            })
+    }
 
     pub fn textures_atlas(&self) -> RwLockReadGuard<Vec<TextureAtlas>> {
         self.texture_atlas.read().unwrap()
@@ -70,12 +185,38 @@ impl TextureHandler {
     pub fn render_targets(&self) -> RwLockReadGuard<Vec<GpuTexture>> {
         self.render_targets.read().unwrap()
     }
+    pub fn render_targets_generation(&self) -> u64 {
+        self.render_targets_generation.load(Ordering::SeqCst)
+    }
 
     pub fn texture_atlas_id(&self, index: usize) -> TextureId {
         *self.texture_atlas.read().unwrap()[index].texture_id()
     }
+    pub fn max_texture_atlas_count(&self) -> u32 {
+        self.max_texture_atlas_count
+    }
+    pub fn texture_atlas_bindings(&self) -> Vec<TextureId> {
+        texture_atlas_bindings(
+            self.texture_atlas
+                .read()
+                .unwrap()
+                .iter()
+                .map(|atlas| *atlas.texture_id()),
+            self.max_texture_atlas_count,
+        )
+    }
 
     pub fn remove(&self, id: &TextureId) {
+        let prefiltered_id = self.prefiltered_environments.write().unwrap().remove(id);
+        if let Some(prefiltered_id) = prefiltered_id {
+            self.remove(&prefiltered_id);
+        }
+        {
+            let mut active_environment_id = self.active_environment_id.write().unwrap();
+            if *active_environment_id == *id {
+                *active_environment_id = INVALID_RESOURCE_UID;
+            }
+        }
         self.texture_atlas.write().unwrap().retain_mut(|atlas| {
             if atlas.remove(id) {
                 atlas.destroy();
@@ -122,38 +263,222 @@ impl TextureHandler {
         self.render_targets.write().unwrap().push(texture);
         self.render_targets.read().unwrap().len() - 1
     }
+    //Render targets read by the passes are bound again with the new size
+    pub fn resize_render_target(
+        &self,
+        device: &wgpu::Device,
+        id: &TextureId,
+        dimensions: (u32, u32),
+    ) -> bool {
+        let mut render_targets = self.render_targets.write().unwrap();
+        let Some(texture) = render_targets.iter_mut().find(|t| t.id() == id) else {
+            return false;
+        };
+        if texture.width() == dimensions.0 && texture.height() == dimensions.1 {
+            return false;
+        }
+        texture.resize(device, dimensions.0, dimensions.1);
+        inox_log::debug_log!(
+            "Resizing render target to {:?}x{:?}",
+            dimensions.0,
+            dimensions.1
+        );
+        self.render_targets_generation
+            .fetch_add(1, Ordering::SeqCst);
+        true
+    }
 
-    pub fn add_image_to_texture_atlas(
+    pub fn add_cubemap(
         &self,
         device: &wgpu::Device,
         encoder: &mut wgpu::CommandEncoder,
         id: &TextureId,
+        face_size: u32,
+        format: TextureFormat,
+        faces: &[Vec<u8>],
+    ) -> usize {
+        debug_assert!(
+            faces.is_empty() || faces.len() == CUBEMAP_FACES_COUNT as usize,
+            "A cubemap needs {} faces but {} were provided",
+            CUBEMAP_FACES_COUNT,
+            faces.len()
+        );
+        let mut usage = wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST;
+        if faces.is_empty() {
+            //Empty cubemaps are filled by rendering into their faces
+            usage |= wgpu::TextureUsages::RENDER_ATTACHMENT;
+        }
+        let texture = GpuTexture::create_cube(device, *id, face_size, format, usage);
+        let area = Area::new(id, 0, 0, face_size, face_size);
+        faces.iter().enumerate().for_each(|(face_index, face)| {
+            texture.send_to_gpu(device, encoder, face_index as _, &area, face);
+        });
+        inox_log::debug_log!(
+            "Adding new cubemap {:?}x{:?} with format {:?}",
+            face_size,
+            face_size,
+            format
+        );
+        self.render_targets.write().unwrap().push(texture);
+        self.render_targets.read().unwrap().len() - 1
+    }
+
+    //The HDR cubemap is generated on gpu from the equirectangular image
+    pub fn add_cubemap_from_equirectangular(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        id: &TextureId,
+        face_size: u32,
         dimensions: (u32, u32),
         format: TextureFormat,
         image_data: &[u8],
-    ) -> TextureInfo {
-        for (texture_index, texture_atlas) in
-            self.texture_atlas.write().unwrap().iter_mut().enumerate()
+    ) -> usize {
+        let equirectangular = GpuTexture::create(
+            device,
+            generate_random_resource_uid(),
+            dimensions.0,
+            dimensions.1,
+            1,
+            format,
+            wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        );
+        let area = Area::new(equirectangular.id(), 0, 0, dimensions.0, dimensions.1);
+        equirectangular.send_to_gpu(device, encoder, 0, &area, image_data);
+        let index = self.add_cubemap(
+            device,
+            encoder,
+            id,
+            face_size,
+            TextureFormat::Rgba16Float,
+            &[],
+        );
+        convert_equirectangular_to_cube(
+            device,
+            encoder,
+            &equirectangular,
+            &self.render_targets.read().unwrap()[index],
+        );
+        index
+    }
+
+    //Prefilters the environment cubemap for image based lighting and makes it the active one.
+    //Results are cached by environment id so the work is done only once per environment.
+    pub fn add_ibl(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        environment_id: &TextureId,
+    ) -> TextureId {
+        if !self.is_brdf_lut_generated.swap(true, Ordering::SeqCst) {
+            let render_targets = self.render_targets.read().unwrap();
+            if let Some(brdf_lut) = render_targets.iter().find(|t| t.id() == &self.brdf_lut_id) {
+                generate_brdf_lut(device, encoder, brdf_lut);
+            }
+        }
+        *self.active_environment_id.write().unwrap() = *environment_id;
+        if let Some(prefiltered_id) = self
+            .prefiltered_environments
+            .read()
+            .unwrap()
+            .get(environment_id)
         {
-            if texture_atlas.texture_format() == &format {
-                if let Some(texture_data) = texture_atlas.allocate(
+            return *prefiltered_id;
+        }
+
+        let prefiltered = GpuTexture::create_cube_with_mips(
+            device,
+            generate_random_resource_uid(),
+            IBL_PREFILTERED_FACE_SIZE,
+            IBL_PREFILTERED_MIP_LEVELS,
+            IBL_TEXTURE_FORMAT,
+            wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::STORAGE_BINDING,
+        );
+        let prefiltered_id = *prefiltered.id();
+        {
+            let render_targets = self.render_targets.read().unwrap();
+            if let Some(environment) = render_targets.iter().find(|t| t.id() == environment_id) {
+                prefilter_environment(
                     device,
                     encoder,
-                    id,
-                    texture_index as _,
-                    dimensions,
-                    image_data,
-                ) {
-                    return texture_data;
-                }
+                    &self.environment_sampler,
+                    environment,
+                    &prefiltered,
+                );
             }
         }
-        self.texture_atlas
+        inox_log::debug_log!(
+            "Adding new prefiltered environment with {:?} mip levels",
+            IBL_PREFILTERED_MIP_LEVELS
+        );
+        self.render_targets.write().unwrap().push(prefiltered);
+        self.prefiltered_environments
             .write()
             .unwrap()
-            .push(TextureAtlas::create_default(device, format));
-        inox_log::debug_log!("Adding new texture atlas with format {:?}", format);
-        self.add_image_to_texture_atlas(device, encoder, id, dimensions, format, image_data)
+            .insert(*environment_id, prefiltered_id);
+        prefiltered_id
+    }
+
+    pub fn has_ibl(&self) -> bool {
+        *self.active_environment_id.read().unwrap() != INVALID_RESOURCE_UID
+    }
+
+    //Prefiltered environment and brdf lut to sample, defaulting to a black environment
+    pub fn ibl_textures(&self) -> (TextureId, TextureId) {
+        let active_environment_id = self.active_environment_id.read().unwrap();
+        let prefiltered_id = self
+            .prefiltered_environments
+            .read()
+            .unwrap()
+            .get(&*active_environment_id)
+            .copied()
+            .unwrap_or(self.default_environment_id);
+        (prefiltered_id, self.brdf_lut_id)
+    }
+
+    pub fn add_image_to_texture_atlas(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        id: &TextureId,
+        dimensions: (u32, u32),
+        format: TextureFormat,
+        image_data: &[u8],
+    ) -> Result<TextureInfo, TextureAtlasError> {
+        let mut texture_atlas = self.texture_atlas.write().unwrap();
+        let (texture_index, layer_index, area) = allocate_in_atlases(
+            &mut *texture_atlas,
+            self.max_texture_atlas_count,
+            id,
+            format,
+            dimensions,
+            |format| {
+                inox_log::debug_log!("Adding new texture atlas with format {:?}", format);
+                TextureAtlas::create_default(device, format)
+            },
+        )?;
+        Ok(texture_atlas[texture_index].send_to_gpu(
+            device,
+            encoder,
+            texture_index as _,
+            layer_index,
+            &area,
+            image_data,
+        ))
+    }
+
+    pub fn update_image_region(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        id: &TextureId,
+        region: &TextureRegion,
+    ) -> bool {
+        self.texture_atlas
+            .read()
+            .unwrap()
+            .iter()
+            .any(|texture_atlas| texture_atlas.send_region_to_gpu(device, encoder, id, region))
     }
 
     pub fn texture_info(&self, id: &TextureId) -> Option<TextureInfo> {
@@ -1,7 +1,14 @@
-use std::ops::Range;
+use std::{
+    ops::Range,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
 
-use inox_bhv::BHVTree;
-use inox_math::Vector4;
+use inox_bhv::{BHVBuildQuality, BHVTree, AABB};
+use inox_core::{JobHandlerRw, JobHandlerTrait, JobPriority, INDEPENDENT_JOB_ID};
+use inox_math::{Vector3, Vector4};
 
 use crate::{DrawBHVNode, INVALID_INDEX};
 
@@ -20,6 +27,29 @@ pub fn compute_id_from_color(color: Vector4) -> u32 {
     (color.x as u32) | (color.y as u32) << 8 | (color.z as u32) << 16 | (color.w as u32) << 24
 }
 
+//Packed as two's complement snorm, the format read by unpack4x8snorm in the shaders.
+//The cutoff is rounded up with the error of the quantized axis, as done by meshoptimizer,
+//so that the packing never culls a meshlet the exact cone would keep
+pub fn pack_cone_axis_cutoff(axis: Vector3, cutoff: f32) -> [i8; 4] {
+    let axis = [axis.x, axis.y, axis.z];
+    let packed_axis = axis.map(|v| (v.clamp(-1., 1.) * 127.).round() as i8);
+    let error: f32 = (0..3)
+        .map(|i| (packed_axis[i] as f32 / 127. - axis[i]).abs())
+        .sum();
+    let packed_cutoff = ((127. * (cutoff + error) + 1.) as i32).clamp(-127, 127);
+    [
+        packed_axis[0],
+        packed_axis[1],
+        packed_axis[2],
+        packed_cutoff as i8,
+    ]
+}
+
+#[inline]
+pub fn unpack_cone_axis_cutoff(packed: [i8; 4]) -> [f32; 4] {
+    packed.map(|v| (v as f32 / 127.).max(-1.))
+}
+
 pub fn create_linearized_bhv(bhv: &BHVTree) -> Vec<DrawBHVNode> {
     inox_profiler::scoped_profile!("create_linearized_bhv");
 
@@ -61,6 +91,52 @@ pub fn create_linearized_bhv(bhv: &BHVTree) -> Vec<DrawBHVNode> {
     linearized_bhv
 }
 
+//Lists with more aabbs than this build their bhv in parallel jobs
+pub const PARALLEL_BHV_MIN_AABBS: usize = 2048;
+//Fixed, and not based on the number of workers, so that the tree doesn't change between machines
+const BHV_AABBS_PER_JOB: usize = 512;
+
+//The tree is identical to the one built serially by BHVTree::build
+pub fn create_bhv_in_jobs(
+    list: &[AABB],
+    quality: BHVBuildQuality,
+    job_handler: &JobHandlerRw,
+) -> BHVTree {
+    inox_profiler::scoped_profile!("create_bhv_in_jobs");
+
+    BHVTree::build_in_tasks(list, quality, BHV_AABBS_PER_JOB, |groups| {
+        let results = Arc::new(Mutex::new(
+            (0..groups.len())
+                .map(|_| None)
+                .collect::<Vec<Option<BHVTree>>>(),
+        ));
+        let wait_count = Arc::new(AtomicUsize::new(groups.len()));
+        groups.into_iter().enumerate().for_each(|(i, group)| {
+            let results = results.clone();
+            let wait_count = wait_count.clone();
+            job_handler.add_job(
+                &INDEPENDENT_JOB_ID,
+                format!("create_bhv_in_jobs[{i}]").as_str(),
+                JobPriority::High,
+                move || {
+                    let tree = BHVTree::build(&group, quality);
+                    results.lock().unwrap()[i] = Some(tree);
+                    wait_count.fetch_sub(1, Ordering::SeqCst);
+                },
+            );
+        });
+        //Executing jobs while waiting avoids a stall when called from a worker or without workers
+        while wait_count.load(Ordering::SeqCst) > 0 {
+            match job_handler.get_job_with_priority(JobPriority::High) {
+                Some(job) => job.execute(),
+                None => std::thread::yield_now(),
+            }
+        }
+        let subtrees = results.lock().unwrap().drain(..).flatten().collect();
+        subtrees
+    })
+}
+
 pub fn print_bhv(bhv: &[DrawBHVNode], bhv_range: &Range<usize>) {
     println!("BHV {} - {}", bhv_range.start, bhv_range.end + 1);
     bhv.iter().enumerate().for_each(|(i, n)| {
@@ -69,3 +145,65 @@ pub fn print_bhv(bhv: &[DrawBHVNode], bhv_range: &Range<usize>) {
         println!("      Ref [{}]", n.reference);
     });
 }
+
+#[allow(dead_code)]
+fn test_bhv_in_jobs() {
+    use inox_math::Vector3;
+    use std::sync::atomic::AtomicBool;
+
+    let list = (0..3 * PARALLEL_BHV_MIN_AABBS)
+        .map(|i| {
+            //Scattered boxes of different sizes
+            let x = ((i * 7919) % 101) as f32;
+            let y = ((i * 104729) % 53) as f32;
+            let z = (i % 17) as f32 * 3.;
+            let min = Vector3::new(x, y, z);
+            let size = 0.5 + (i % 5) as f32;
+            AABB::create(min, min + Vector3::new(size, size * 0.5, size), i as _)
+        })
+        .collect::<Vec<_>>();
+
+    let job_handler = JobHandlerRw::default();
+    job_handler.write().unwrap().set_num_workers(2);
+    let can_continue = Arc::new(AtomicBool::new(true));
+    job_handler.start(&can_continue);
+
+    [BHVBuildQuality::Fast, BHVBuildQuality::SurfaceAreaHeuristic]
+        .iter()
+        .for_each(|&quality| {
+            let serial = create_linearized_bhv(&BHVTree::build(&list, quality));
+            let parallel = create_linearized_bhv(&create_bhv_in_jobs(&list, quality, &job_handler));
+            assert_eq!(serial.len(), 2 * list.len() - 1);
+            assert!(serial == parallel, "Mismatch with {:?} build", quality);
+        });
+
+    can_continue.store(false, Ordering::SeqCst);
+    job_handler.stop();
+}
+
+#[allow(dead_code)]
+fn test_cone_packing() {
+    use inox_math::InnerSpace;
+
+    //Negative components keep their sign and magnitude
+    let unpacked = unpack_cone_axis_cutoff(pack_cone_axis_cutoff(Vector3::new(0., 0., -1.), 0.5));
+    assert_eq!(unpacked[2], -1.);
+    assert!(unpacked[3] >= 0.5 && unpacked[3] < 0.52);
+
+    let axis = Vector3::new(-0.3, 0.4, -0.8).normalize();
+    let unpacked = unpack_cone_axis_cutoff(pack_cone_axis_cutoff(axis, -0.2));
+    [axis.x, axis.y, axis.z]
+        .iter()
+        .enumerate()
+        .for_each(|(i, v)| assert!((unpacked[i] - v).abs() <= 0.5 / 127.));
+    assert!(unpacked[3] >= -0.2);
+
+    //A degenerate cone never culls
+    assert_eq!(pack_cone_axis_cutoff(axis, 1.)[3], 127);
+}
+
+#[test]
+fn test() {
+    test_bhv_in_jobs();
+    test_cone_packing();
+}
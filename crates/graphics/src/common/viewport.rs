@@ -0,0 +1,217 @@
+use inox_math::Matrix4;
+
+//Normalized rect of the framebuffer where a View is rendered, the origin is the top left corner
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Default for Viewport {
+    fn default() -> Self {
+        Self::full()
+    }
+}
+
+impl Viewport {
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+    pub fn full() -> Self {
+        Self::new(0., 0., 1., 1.)
+    }
+    pub fn aspect_ratio(&self, screen_width: u32, screen_height: u32) -> f32 {
+        (self.width * screen_width as f32) / (self.height * screen_height as f32).max(1.)
+    }
+    //Pixels covered by the viewport, clamped to the framebuffer.
+    //Returns None when nothing would be visible
+    pub fn scissor(&self, screen_width: u32, screen_height: u32) -> Option<ScissorRect> {
+        let to_pixels = |v: f32, size: u32| (v.clamp(0., 1.) * size as f32).round() as u32;
+        let left = to_pixels(self.x, screen_width);
+        let top = to_pixels(self.y, screen_height);
+        let right = to_pixels(self.x + self.width, screen_width);
+        let bottom = to_pixels(self.y + self.height, screen_height);
+        if right <= left || bottom <= top {
+            return None;
+        }
+        Some(ScissorRect {
+            x: left,
+            y: top,
+            width: right - left,
+            height: bottom - top,
+        })
+    }
+    //Maps the clip space of a projection into the viewport rect, so that the whole frame
+    //can still be drawn with the full framebuffer as wgpu viewport and screen space passes
+    //reconstruct positions with the same matrices
+    #[rustfmt::skip]
+    pub fn clip_transform(&self) -> Matrix4 {
+        let offset_x = 2. * self.x + self.width - 1.;
+        let offset_y = 1. - 2. * self.y - self.height;
+        Matrix4::new(
+            self.width, 0., 0., 0.,
+            0., self.height, 0., 0.,
+            0., 0., 1., 0.,
+            offset_x, offset_y, 0., 1.,
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScissorRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl ScissorRect {
+    //Render targets smaller than the surface only get the part of the rect inside them
+    pub fn clamp(&self, width: u32, height: u32) -> ScissorRect {
+        let x = self.x.min(width);
+        let y = self.y.min(height);
+        ScissorRect {
+            x,
+            y,
+            width: self.width.min(width - x),
+            height: self.height.min(height - y),
+        }
+    }
+    pub fn contains(&self, other: &ScissorRect) -> bool {
+        other.x >= self.x
+            && other.y >= self.y
+            && other.x + other.width <= self.x + self.width
+            && other.y + other.height <= self.y + self.height
+    }
+}
+
+//A render of all the passes for a View inside its scissor rect.
+//The first submission clears the surface, the next ones are drawn on top of it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ViewSubmission {
+    pub view_index: u32,
+    pub scissor: ScissorRect,
+    pub load_surface: bool,
+}
+
+impl ViewSubmission {
+    //Views are drawn by view index, so overlapping views are drawn over the ones with a lower
+    //index. Views without visible pixels or completely hidden by a following one are skipped
+    pub fn plan(
+        views: &[(u32, Viewport)],
+        screen_width: u32,
+        screen_height: u32,
+    ) -> Vec<ViewSubmission> {
+        let mut views = views
+            .iter()
+            .filter_map(|(view_index, viewport)| {
+                viewport
+                    .scissor(screen_width, screen_height)
+                    .map(|scissor| (*view_index, scissor))
+            })
+            .collect::<Vec<_>>();
+        views.sort_by_key(|(view_index, _)| *view_index);
+
+        let mut submissions: Vec<ViewSubmission> = Vec::with_capacity(views.len());
+        for (i, (view_index, scissor)) in views.iter().enumerate() {
+            if views[i + 1..].iter().any(|(_, s)| s.contains(scissor)) {
+                continue;
+            }
+            submissions.push(ViewSubmission {
+                view_index: *view_index,
+                scissor: *scissor,
+                load_surface: !submissions.is_empty(),
+            });
+        }
+        submissions
+    }
+}
+
+#[allow(dead_code)]
+fn test_viewport() {
+    use inox_math::Vector4;
+
+    let left = Viewport::new(0., 0., 0.5, 1.);
+    let right = Viewport::new(0.5, 0., 0.5, 1.);
+
+    let submissions = ViewSubmission::plan(&[(1, right), (0, left)], 1920, 1080);
+    assert_eq!(
+        submissions,
+        vec![
+            ViewSubmission {
+                view_index: 0,
+                scissor: ScissorRect {
+                    x: 0,
+                    y: 0,
+                    width: 960,
+                    height: 1080,
+                },
+                load_surface: false,
+            },
+            ViewSubmission {
+                view_index: 1,
+                scissor: ScissorRect {
+                    x: 960,
+                    y: 0,
+                    width: 960,
+                    height: 1080,
+                },
+                load_surface: true,
+            },
+        ]
+    );
+    assert_eq!(left.aspect_ratio(1920, 1080), 960. / 1080.);
+
+    //A picture in picture view is drawn over the full one, a hidden or empty view is skipped
+    let picture_in_picture = Viewport::new(0.75, 0.75, 0.25, 0.25);
+    let submissions = ViewSubmission::plan(
+        &[
+            (0, left),
+            (1, Viewport::full()),
+            (2, picture_in_picture),
+            (3, Viewport::new(1.5, 0., 0.5, 1.)),
+        ],
+        1920,
+        1080,
+    );
+    assert_eq!(
+        submissions
+            .iter()
+            .map(|s| (s.view_index, s.load_surface))
+            .collect::<Vec<_>>(),
+        vec![(1, false), (2, true)]
+    );
+    assert_eq!(
+        submissions[1].scissor,
+        ScissorRect {
+            x: 1440,
+            y: 810,
+            width: 480,
+            height: 270,
+        }
+    );
+
+    //Clip space corners of the projection end in the corners of the rect
+    let m = right.clip_transform();
+    assert_eq!(
+        m * Vector4::new(-1., 1., 0.5, 1.),
+        Vector4::new(0., 1., 0.5, 1.)
+    );
+    assert_eq!(
+        m * Vector4::new(2., -2., 0.5, 2.),
+        Vector4::new(2., -2., 0.5, 2.)
+    );
+    assert_eq!(Viewport::full().clip_transform(), Matrix4::from_scale(1.));
+}
+
+#[test]
+fn test() {
+    test_viewport();
+}
@@ -2,10 +2,11 @@ use std::num::NonZeroU32;
 
 use inox_bitmask::bitmask;
 use inox_serialize::{Deserialize, Serialize};
+use inox_uid::INVALID_RESOURCE_UID;
 
 use crate::{
     platform::required_gpu_features, AsBinding, BindingDataBufferRc, BufferId, RenderContext,
-    RenderCoreContextRc, ShaderStage, TextureHandlerRc, TextureId, MAX_TEXTURE_ATLAS_COUNT,
+    RenderCoreContextRc, ShaderStage, TextureHandlerRc, TextureId, MAX_SAMPLER_COUNT,
 };
 
 const DEBUG_BINDINGS: bool = false;
@@ -79,8 +80,10 @@ impl Default for BindingInfo {
 enum BindingType {
     Buffer(usize, BufferId),
     DefaultSampler(usize),
+    EnvironmentSampler(usize),
+    SamplerArray(usize, usize),
     Texture(usize, TextureId),
-    TextureArray(usize, Box<[TextureId; MAX_TEXTURE_ATLAS_COUNT as usize]>),
+    TextureArray(usize, Vec<TextureId>),
 }
 
 pub struct BindingData {
@@ -96,6 +99,7 @@ pub struct BindingData {
     pass_name: String,
     is_layout_changed: bool,
     is_data_changed: bool,
+    render_targets_generation: u64,
 }
 
 impl BindingData {
@@ -112,6 +116,7 @@ impl BindingData {
             index_buffer: None,
             is_layout_changed: false,
             is_data_changed: false,
+            render_targets_generation: render_context.texture_handler.render_targets_generation(),
             pass_name: pass_name.to_string(),
         }
     }
@@ -187,6 +192,36 @@ impl BindingData {
     pub fn index_buffer(&self) -> &Option<BufferId> {
         &self.index_buffer
     }
+    //Buffers bound to the pass, vertex and index buffers included
+    pub fn bound_buffers(&self) -> Vec<BufferId> {
+        let mut buffers = self
+            .binding_types
+            .iter()
+            .flatten()
+            .filter_map(|binding_type| match binding_type {
+                BindingType::Buffer(_, id) => Some(*id),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        buffers.extend(self.vertex_buffers.iter());
+        buffers.extend(self.index_buffer.iter());
+        buffers
+    }
+    pub fn bound_textures(&self) -> Vec<TextureId> {
+        self.binding_types
+            .iter()
+            .flatten()
+            .flat_map(|binding_type| match binding_type {
+                BindingType::Texture(_, id) => vec![*id],
+                BindingType::TextureArray(_, ids) => ids
+                    .iter()
+                    .filter(|id| **id != INVALID_RESOURCE_UID)
+                    .copied()
+                    .collect(),
+                _ => Vec::new(),
+            })
+            .collect()
+    }
 
     pub fn add_uniform_buffer<T>(
         &mut self,
@@ -350,24 +385,71 @@ impl BindingData {
         self
     }
 
-    pub fn add_material_textures(&mut self, info: BindingInfo) -> &mut Self {
-        inox_profiler::scoped_profile!("binding_data::add_material_textures");
+    pub fn add_environment_sampler(&mut self, info: BindingInfo) -> &mut Self {
+        inox_profiler::scoped_profile!("binding_data::add_environment_sampler");
 
         self.create_group_and_binding_index(info.group_index);
 
-        let mut textures = [TextureId::default(); MAX_TEXTURE_ATLAS_COUNT as usize];
-        {
-            let texture_atlas = self.texture_handler.textures_atlas();
-            let num_textures = texture_atlas.len();
+        if self.bind_group_layout_entries[info.group_index].is_empty() {
+            self.bind_group_layout_entries[info.group_index].push(wgpu::BindGroupLayoutEntry {
+                binding: info.binding_index as _,
+                visibility: info.stage.into(),
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            });
+            self.is_layout_changed = true;
+        }
+        if self.binding_types[info.group_index].is_empty() {
+            self.binding_types[info.group_index]
+                .push(BindingType::EnvironmentSampler(info.binding_index));
+            self.is_data_changed = true;
+        }
+        self
+    }
+    //Samplers of the textures in the atlases, the shaders pick the one of each texture
+    pub fn add_material_samplers(&mut self, info: BindingInfo) -> &mut Self {
+        inox_profiler::scoped_profile!("binding_data::add_material_samplers");
 
-            for i in 0..MAX_TEXTURE_ATLAS_COUNT as usize {
-                if i < num_textures {
-                    textures[i] = *texture_atlas[i].texture_id();
-                } else {
-                    textures[i] = *texture_atlas[0].texture_id();
-                }
+        self.create_group_and_binding_index(info.group_index);
+
+        if self.bind_group_layout_entries[info.group_index].len() <= info.binding_index {
+            let count = if required_gpu_features().contains(wgpu::Features::TEXTURE_BINDING_ARRAY) {
+                NonZeroU32::new(MAX_SAMPLER_COUNT)
+            } else {
+                None
+            };
+            self.bind_group_layout_entries[info.group_index].push(wgpu::BindGroupLayoutEntry {
+                binding: info.binding_index as _,
+                visibility: info.stage.into(),
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count,
+            });
+            self.is_layout_changed = true;
+        }
+
+        let sampler_count = self.texture_handler.samplers().count();
+        if self.binding_types[info.group_index].len() <= info.binding_index {
+            self.binding_types[info.group_index]
+                .push(BindingType::SamplerArray(info.binding_index, sampler_count));
+            self.is_data_changed = true;
+        } else if let BindingType::SamplerArray(_, old_sampler_count) =
+            &self.binding_types[info.group_index][info.binding_index]
+        {
+            if *old_sampler_count != sampler_count {
+                self.binding_types[info.group_index][info.binding_index] =
+                    BindingType::SamplerArray(info.binding_index, sampler_count);
+                self.is_data_changed = true;
             }
         }
+        self
+    }
+    pub fn add_material_textures(&mut self, info: BindingInfo) -> &mut Self {
+        inox_profiler::scoped_profile!("binding_data::add_material_textures");
+
+        self.create_group_and_binding_index(info.group_index);
+
+        let max_texture_atlas_count = self.texture_handler.max_texture_atlas_count();
+        let textures = self.texture_handler.texture_atlas_bindings();
 
         let textures_bind_group_layout_index = info.binding_index;
         let mut bind_group_layout_count = textures_bind_group_layout_index;
@@ -383,14 +465,14 @@ impl BindingData {
                         view_dimension: wgpu::TextureViewDimension::D2Array,
                         multisampled: false,
                     },
-                    count: NonZeroU32::new(MAX_TEXTURE_ATLAS_COUNT),
+                    count: NonZeroU32::new(max_texture_atlas_count),
                 });
                 self.is_layout_changed = true;
             }
         } else if self.bind_group_layout_entries[info.group_index].len()
-            < (textures_bind_group_layout_index + MAX_TEXTURE_ATLAS_COUNT as usize)
+            < (textures_bind_group_layout_index + max_texture_atlas_count as usize)
         {
-            (0..MAX_TEXTURE_ATLAS_COUNT).for_each(|_| {
+            (0..max_texture_atlas_count).for_each(|_| {
                 self.bind_group_layout_entries[info.group_index].push(wgpu::BindGroupLayoutEntry {
                     binding: bind_group_layout_count as _,
                     visibility: info.stage.into(),
@@ -418,19 +500,15 @@ impl BindingData {
         if self.binding_types[info.group_index].len() <= textures_bind_group_layout_index {
             self.binding_types[info.group_index].push(BindingType::TextureArray(
                 textures_bind_group_layout_index,
-                Box::new(textures),
+                textures,
             ));
             self.is_data_changed = true;
         } else if let BindingType::TextureArray(_, old_textures) =
             &self.binding_types[info.group_index][textures_bind_group_layout_index]
         {
-            if old_textures
-                .iter()
-                .enumerate()
-                .any(|(index, id)| textures[index] != *id)
-            {
+            if *old_textures != textures {
                 self.binding_types[info.group_index][textures_bind_group_layout_index] =
-                    BindingType::TextureArray(textures_bind_group_layout_index, Box::new(textures));
+                    BindingType::TextureArray(textures_bind_group_layout_index, textures);
                 self.is_data_changed = true;
             }
         }
@@ -471,11 +549,7 @@ impl BindingData {
                             sample_type: format
                                 .sample_type(Some(wgpu::TextureAspect::All))
                                 .unwrap_or_default(),
-                            view_dimension: if texture.layers_count() > 1 {
-                                wgpu::TextureViewDimension::D2Array
-                            } else {
-                                wgpu::TextureViewDimension::D2
-                            },
+                            view_dimension: texture.view_dimension(),
                             multisampled: false,
                         }
                     },
@@ -562,10 +636,19 @@ impl BindingData {
                     });
                 });
         }
+        //Resized render targets have new views with the same ids
+        let render_targets_generation = self.texture_handler.render_targets_generation();
+        if self.render_targets_generation != render_targets_generation {
+            self.render_targets_generation = render_targets_generation;
+            self.is_data_changed = true;
+        }
 
         if self.is_data_changed {
             let render_targets = self.texture_handler.render_targets();
             let texture_atlas = self.texture_handler.textures_atlas();
+            let max_texture_atlas_count = self.texture_handler.max_texture_atlas_count();
+            let sampler_cache = self.texture_handler.samplers();
+            let samplers = sampler_cache.bindings();
             self.bind_group.clear();
             self.binding_types
                 .iter()
@@ -645,6 +728,42 @@ impl BindingData {
                                     ),
                                 });
                             }
+                            BindingType::EnvironmentSampler(binding_index) => {
+                                if DEBUG_BINDINGS {
+                                    inox_log::debug_log!(
+                                        "Binding Environment sampler [{}][{}]",
+                                        group_index,
+                                        binding_index
+                                    );
+                                }
+                                bind_group.push(wgpu::BindGroupEntry {
+                                    binding: *binding_index as _,
+                                    resource: wgpu::BindingResource::Sampler(
+                                        self.texture_handler.environment_sampler(),
+                                    ),
+                                });
+                            }
+                            BindingType::SamplerArray(binding_index, sampler_count) => {
+                                if DEBUG_BINDINGS {
+                                    inox_log::debug_log!(
+                                        "Binding Samplers[{}][{}] - NumSamplers {:?}",
+                                        group_index,
+                                        binding_index,
+                                        sampler_count
+                                    );
+                                }
+                                let resource = if required_gpu_features()
+                                    .contains(wgpu::Features::TEXTURE_BINDING_ARRAY)
+                                {
+                                    wgpu::BindingResource::SamplerArray(samplers.as_slice())
+                                } else {
+                                    wgpu::BindingResource::Sampler(samplers[0])
+                                };
+                                bind_group.push(wgpu::BindGroupEntry {
+                                    binding: *binding_index as _,
+                                    resource,
+                                });
+                            }
                             BindingType::Texture(binding_index, id) => {
                                 if DEBUG_BINDINGS {
                                     inox_log::debug_log!(
@@ -683,7 +802,7 @@ impl BindingData {
                                         }),
                                     });
                                 } else {
-                                    (0..MAX_TEXTURE_ATLAS_COUNT).for_each(|i| {
+                                    (0..max_texture_atlas_count).for_each(|i| {
                                         bind_group.push(wgpu::BindGroupEntry {
                                             binding: *binding_index as u32 + i,
                                             resource: wgpu::BindingResource::TextureView(
@@ -3,7 +3,7 @@ use std::{
     sync::{Arc, RwLock},
 };
 
-use inox_math::{matrix4_to_array, Mat4Ops, Matrix4, Radians, Vector2, Degrees};
+use inox_math::{matrix4_to_array, Degrees, Mat4Ops, Matrix4, Radians, Vector2};
 
 use crate::{AsBinding, GpuBuffer, RenderCoreContext};
 
@@ -12,6 +12,7 @@ pub const CONSTANT_DATA_FLAGS_SUPPORT_SRGB: u32 = 1;
 pub const CONSTANT_DATA_FLAGS_DISPLAY_MESHLETS: u32 = 1 << 1;
 pub const CONSTANT_DATA_FLAGS_DISPLAY_MESHLETS_SPHERE: u32 = 1 << 2;
 pub const CONSTANT_DATA_FLAGS_DISPLAY_MESHLETS_BOUNDING_BOX: u32 = 1 << 3;
+pub const CONSTANT_DATA_FLAGS_USE_IBL: u32 = 1 << 4;
 
 #[repr(C, align(16))]
 #[derive(Default, Debug, Clone, Copy)]
@@ -103,4 +104,7 @@ impl ConstantData {
     pub fn view(&self) -> [[f32; 4]; 4] {
         self.data.view
     }
+    pub fn proj(&self) -> [[f32; 4]; 4] {
+        self.data.proj
+    }
 }
@@ -1,10 +1,10 @@
 use inox_bitmask::bitmask;
-use inox_math::{Mat4Ops, Matrix4};
+use inox_math::{Mat4Ops, Matrix4, Vector3};
 use inox_serialize::{Deserialize, Serialize};
 
 use crate::{
-    MaterialAlphaMode, TextureType, VertexBufferLayoutBuilder, VertexFormat, INVALID_INDEX,
-    MAX_TEXTURE_COORDS_SETS,
+    MaterialAlphaMode, MaterialFlags, MeshFlags, TextureType, VertexBufferLayoutBuilder,
+    VertexFormat, INVALID_INDEX, MAX_TEXTURE_COORDS_SETS,
 };
 
 // Pipeline has a list of meshes to process
@@ -52,6 +52,13 @@ pub struct DrawMesh {
     pub scale: [f32; 3],
     pub meshlets_count: u32,
     pub orientation: [f32; 4],
+    pub bounding_sphere: [f32; 4], //local center and radius
+    pub flags: u32,
+    //Meshlets of the instances are interleaved: meshlet k of this instance is at
+    //meshlets_offset + k * instances_count
+    pub instances_count: u32,
+    pub instance_index: u32,
+    pub _padding: u32,
 }
 
 impl Default for DrawMesh {
@@ -66,6 +73,11 @@ impl Default for DrawMesh {
             scale: [1.; 3],
             meshlets_count: 0,
             orientation: [0., 0., 0., 1.],
+            bounding_sphere: [0.; 4],
+            flags: MeshFlags::None.into(),
+            instances_count: 1,
+            instance_index: 0,
+            _padding: 0,
         }
     }
 }
@@ -78,6 +90,19 @@ impl DrawMesh {
             self.scale.into(),
         )
     }
+    //Scaled by the largest scale to keep containing the mesh when not uniform
+    pub fn world_bounding_sphere(&self) -> (Vector3, f32) {
+        let [x, y, z, radius] = self.bounding_sphere;
+        let center = self.transform().rotate_point(Vector3::new(x, y, z));
+        let max_scale = self
+            .scale
+            .iter()
+            .fold(0f32, |max_scale, s| max_scale.max(s.abs()));
+        (center, radius * max_scale)
+    }
+    pub fn meshlet_indices(&self) -> impl Iterator<Item = u32> + '_ {
+        (0..self.meshlets_count).map(|i| self.meshlets_offset + i * self.instances_count)
+    }
 }
 
 #[repr(C, align(4))]
@@ -135,6 +160,16 @@ pub struct DrawMaterial {
     pub occlusion_strength: f32,
     pub diffuse_color: [f32; 4],
     pub specular_color: [f32; 4],
+    pub emissive_strength: f32,
+    pub transmission_factor: f32,
+    pub thickness_factor: f32,
+    pub ior: f32,
+    pub attenuation_color: [f32; 3],
+    pub attenuation_distance: f32,
+    pub clearcoat_factor: f32,
+    pub clearcoat_roughness_factor: f32,
+    pub flags: u32,
+    _padding: u32,
 }
 
 impl Default for DrawMaterial {
@@ -151,6 +186,16 @@ impl Default for DrawMaterial {
             occlusion_strength: 0.0,
             diffuse_color: [1.; 4],
             specular_color: [1.; 4],
+            emissive_strength: 1.,
+            transmission_factor: 0.,
+            thickness_factor: 0.,
+            ior: 1.5,
+            attenuation_color: [1.; 3],
+            attenuation_distance: f32::MAX,
+            clearcoat_factor: 0.,
+            clearcoat_roughness_factor: 0.,
+            flags: MaterialFlags::None.into(),
+            _padding: 0,
         }
     }
 }
@@ -201,3 +246,90 @@ pub struct DrawRay {
     pub direction: [f32; 3],
     pub t_max: f32,
 }
+
+//Size of a struct of common.inc with the alignment rules of wgsl, only for the types used by the
+//structs shared with the gpu
+#[allow(dead_code)]
+fn wgsl_struct_size(name: &str) -> usize {
+    fn align_and_size(ty: &str) -> (usize, usize) {
+        match ty {
+            "f32" | "u32" | "i32" => (4, 4),
+            "vec2<f32>" | "vec2<u32>" | "vec2<i32>" => (8, 8),
+            "vec3<f32>" | "vec3<u32>" | "vec3<i32>" => (16, 12),
+            "vec4<f32>" | "vec4<u32>" | "vec4<i32>" => (16, 16),
+            _ => {
+                let (ty, count) = ty
+                    .strip_prefix("array<")
+                    .and_then(|a| a.strip_suffix('>'))
+                    .and_then(|a| a.rsplit_once(','))
+                    .unwrap_or_else(|| panic!("Unsupported wgsl type {ty}"));
+                let (align, size) = align_and_size(ty.trim());
+                let stride = size.div_ceil(align) * align;
+                (align, stride * count.trim().parse::<usize>().unwrap())
+            }
+        }
+    }
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../../data_raw/shaders/wgsl/common.inc");
+    let source = std::fs::read_to_string(path).unwrap();
+    let start = source.find(&format!("struct {name} {{")).unwrap();
+    let mut offset: usize = 0;
+    let mut struct_align: usize = 1;
+    for line in source[start..].lines().skip(1) {
+        let line = line.split("//").next().unwrap().trim();
+        if line.starts_with('}') {
+            break;
+        }
+        if let Some((_, ty)) = line.trim_end_matches(',').split_once(':') {
+            let (align, size) = align_and_size(ty.trim());
+            offset = offset.div_ceil(align) * align + size;
+            struct_align = struct_align.max(align);
+        }
+    }
+    offset.div_ceil(struct_align) * struct_align
+}
+
+#[allow(dead_code)]
+fn test_draw_material_size() {
+    assert_eq!(
+        std::mem::size_of::<DrawMaterial>(),
+        wgsl_struct_size("Material")
+    );
+}
+
+//Value of a u32 const of common.inc
+#[allow(dead_code)]
+fn wgsl_const_u32(name: &str) -> u32 {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../../data_raw/shaders/wgsl/common.inc");
+    let source = std::fs::read_to_string(path).unwrap();
+    let line = source
+        .lines()
+        .find(|line| line.starts_with(&format!("const {name}:")))
+        .unwrap_or_else(|| panic!("Missing wgsl const {name}"));
+    let value = line.split('=').nth(1).unwrap().split(';').next().unwrap();
+    value.trim().trim_end_matches('u').parse().unwrap()
+}
+
+#[allow(dead_code)]
+fn test_material_flags() {
+    //The shaders test the same bits the material flags are uploaded with
+    assert_eq!(
+        wgsl_const_u32("MATERIAL_FLAGS_NONE"),
+        u32::from(MaterialFlags::None)
+    );
+    assert_eq!(
+        wgsl_const_u32("MATERIAL_FLAGS_DOUBLE_SIDED"),
+        u32::from(MaterialFlags::DoubleSided)
+    );
+    assert_eq!(
+        wgsl_const_u32("MATERIAL_FLAGS_UNLIT"),
+        u32::from(MaterialFlags::Unlit)
+    );
+}
+
+#[test]
+fn test() {
+    test_draw_material_size();
+    test_material_flags();
+}
@@ -0,0 +1,155 @@
+use std::path::{Path, PathBuf};
+
+use inox_serialize::{Deserialize, Serialize, SerializeFile};
+
+use crate::{texture_view_dimension, CUBEMAP_FACES_COUNT};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(crate = "inox_serialize")]
+pub enum EnvironmentSource {
+    //Faces are ordered as +X, -X, +Y, -Y, +Z, -Z
+    Faces([PathBuf; CUBEMAP_FACES_COUNT as _]),
+    //Converted into a cubemap with faces of EnvironmentData::face_size
+    Equirectangular(PathBuf),
+}
+
+impl Default for EnvironmentSource {
+    fn default() -> Self {
+        Self::Faces(Default::default())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(crate = "inox_serialize")]
+pub struct EnvironmentData {
+    pub source: EnvironmentSource,
+    pub face_size: u32,
+    pub intensity: f32,
+}
+
+impl SerializeFile for EnvironmentData {
+    fn extension() -> &'static str {
+        "environment"
+    }
+}
+
+impl Default for EnvironmentData {
+    fn default() -> Self {
+        Self {
+            source: EnvironmentSource::default(),
+            face_size: 512,
+            intensity: 1.,
+        }
+    }
+}
+
+impl EnvironmentData {
+    pub fn image_paths(&self) -> Vec<&Path> {
+        match &self.source {
+            EnvironmentSource::Faces(faces) => faces.iter().map(|p| p.as_path()).collect(),
+            EnvironmentSource::Equirectangular(path) => vec![path.as_path()],
+        }
+    }
+    pub fn layers_count(&self) -> u32 {
+        CUBEMAP_FACES_COUNT
+    }
+    pub fn view_dimension(&self) -> wgpu::TextureViewDimension {
+        texture_view_dimension(self.layers_count(), true)
+    }
+}
+
+#[allow(dead_code)]
+fn test_environment_data() {
+    let faces = ["px", "nx", "py", "ny", "pz", "nz"].map(|f| PathBuf::from(format!("{f}.png")));
+    let data = EnvironmentData {
+        source: EnvironmentSource::Faces(faces),
+        ..Default::default()
+    };
+    assert_eq!(data.image_paths().len(), CUBEMAP_FACES_COUNT as usize);
+    assert_eq!(data.layers_count(), 6);
+    assert_eq!(data.view_dimension(), wgpu::TextureViewDimension::Cube);
+    //The same layers without the cube flag are sampled as a plain array
+    assert_eq!(
+        texture_view_dimension(data.layers_count(), false),
+        wgpu::TextureViewDimension::D2Array
+    );
+    assert_eq!(
+        texture_view_dimension(2 * CUBEMAP_FACES_COUNT, true),
+        wgpu::TextureViewDimension::CubeArray
+    );
+
+    let data = EnvironmentData {
+        source: EnvironmentSource::Equirectangular(PathBuf::from("sky.hdr")),
+        ..Default::default()
+    };
+    assert_eq!(data.image_paths(), vec![Path::new("sky.hdr")]);
+    assert_eq!(data.layers_count(), 6);
+}
+
+//The faces of a loaded environment are uploaded in a single cube texture
+#[allow(dead_code)]
+#[cfg(not(target_arch = "wasm32"))]
+fn test_environment_cubemap_view() {
+    use crate::{Environment, Renderer};
+    use inox_core::{Context, ContextRc};
+    use inox_resources::DataTypeResource;
+    use inox_uid::generate_random_resource_uid;
+
+    let context = ContextRc::new(Context::default());
+    let Some(renderer) = Renderer::new_headless(&context, |_| {}) else {
+        //No adapter able to render on this system
+        return;
+    };
+    let folder = std::env::temp_dir().join("inox_environment_data");
+    std::fs::create_dir_all(folder.as_path()).unwrap();
+    let faces = ["px", "nx", "py", "ny", "pz", "nz"].map(|f| folder.join(format!("{f}.png")));
+    faces.iter().enumerate().for_each(|(i, path)| {
+        image::RgbaImage::from_pixel(4, 4, image::Rgba([i as u8 * 40, 0, 0, 255]))
+            .save(path)
+            .unwrap();
+    });
+    let data = EnvironmentData {
+        source: EnvironmentSource::Faces(faces),
+        face_size: 4,
+        ..Default::default()
+    };
+    let id = generate_random_resource_uid();
+    let environment = Environment::new_resource(
+        context.shared_data(),
+        context.message_hub(),
+        id,
+        &data,
+        None,
+    );
+    assert!(environment.get().is_loaded());
+
+    let mut encoder = renderer
+        .read()
+        .unwrap()
+        .render_context()
+        .core
+        .device
+        .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+    renderer
+        .write()
+        .unwrap()
+        .on_environment_changed(&id, &mut encoder);
+    let renderer = renderer.read().unwrap();
+    let render_context = renderer.render_context();
+    render_context.core.queue.submit(Some(encoder.finish()));
+    let cubemap_index = environment.get().cubemap_index();
+    assert!(cubemap_index >= 0);
+    let render_targets = render_context.texture_handler.render_targets();
+    let cubemap = &render_targets[cubemap_index as usize];
+    assert_eq!(cubemap.id(), &id);
+    assert_eq!(cubemap.view_dimension(), wgpu::TextureViewDimension::Cube);
+    assert_eq!(cubemap.layers_count(), data.layers_count());
+    assert_eq!(cubemap.width(), 4);
+}
+
+#[test]
+fn test() {
+    test_environment_data();
+    #[cfg(not(target_arch = "wasm32"))]
+    test_environment_cubemap_view();
+}
@@ -9,11 +9,15 @@ pub enum LightType {
     Directional = 1,
     Point = 2,
     Spot = 3,
+    Area = 4,
 }
 
+pub const DEFAULT_SHADOW_BIAS: f32 = 0.005;
+pub const DEFAULT_SHADOW_NORMAL_BIAS: f32 = 0.01;
+
 #[repr(C, align(16))]
-#[derive(Default, Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
-#[serde(crate = "inox_serialize")]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+#[serde(crate = "inox_serialize", default)]
 pub struct LightData {
     pub position: [f32; 3],
     pub light_type: u32,
@@ -22,6 +26,32 @@ pub struct LightData {
     pub range: f32,
     pub inner_cone_angle: f32,
     pub outer_cone_angle: f32,
+    pub direction: [f32; 3],
+    pub cast_shadows: u32,
+    //Depth offset and offset along the normal applied when sampling the shadow map
+    pub shadow_bias: f32,
+    pub shadow_normal_bias: f32,
+    //Width and height of the rectangle emitting the light of an area light
+    pub area_size: [f32; 2],
+}
+
+impl Default for LightData {
+    fn default() -> Self {
+        Self {
+            position: [0.; 3],
+            light_type: LightType::None as _,
+            color: [0.; 4],
+            intensity: 0.,
+            range: 0.,
+            inner_cone_angle: 0.,
+            outer_cone_angle: 0.,
+            direction: [0., 0., -1.],
+            cast_shadows: 0,
+            shadow_bias: DEFAULT_SHADOW_BIAS,
+            shadow_normal_bias: DEFAULT_SHADOW_NORMAL_BIAS,
+            area_size: [1., 1.],
+        }
+    }
 }
 
 impl SerializeFile for LightData {
@@ -44,6 +74,11 @@ impl LightData {
         print_field_size!(s, range, f32, 1);
         print_field_size!(s, inner_cone_angle, f32, 1);
         print_field_size!(s, outer_cone_angle, f32, 1);
+        print_field_size!(s, direction, [f32; 3], 1);
+        print_field_size!(s, cast_shadows, u32, 1);
+        print_field_size!(s, shadow_bias, f32, 1);
+        print_field_size!(s, shadow_normal_bias, f32, 1);
+        print_field_size!(s, area_size, [f32; 2], 1);
 
         println!(
             "Alignment result: {} -> {}",
@@ -56,3 +91,41 @@ impl LightData {
         );
     }
 }
+
+#[allow(dead_code)]
+fn test_light_data() {
+    use inox_serialize::inox_serializable::SerializableRegistryRc;
+
+    //Same layout of the LightData of the shaders
+    assert_eq!(std::mem::size_of::<LightData>(), 80);
+
+    //Lights written before the shadow parameters get the default ones
+    let registry = SerializableRegistryRc::default();
+    let light: LightData = inox_serialize::deserialize(
+        "{\"position\":[1.0,2.0,3.0],\"light_type\":3,\"color\":[1.0,1.0,1.0,1.0],\
+        \"intensity\":2.0,\"range\":10.0,\"inner_cone_angle\":0.1,\"outer_cone_angle\":0.5}",
+        &registry,
+    )
+    .unwrap();
+    assert_eq!(light.light_type, LightType::Spot as u32);
+    assert_eq!(light.range, 10.);
+    assert_eq!(light.cast_shadows, 0);
+    assert_eq!(light.shadow_bias, DEFAULT_SHADOW_BIAS);
+    assert_eq!(light.shadow_normal_bias, DEFAULT_SHADOW_NORMAL_BIAS);
+    assert_eq!(light.direction, [0., 0., -1.]);
+
+    let light = LightData {
+        light_type: LightType::Area as _,
+        cast_shadows: 1,
+        area_size: [2., 0.5],
+        ..Default::default()
+    };
+    let s = inox_serialize::serialize(&light, &registry);
+    let deserialized: LightData = inox_serialize::deserialize(&s, &registry).unwrap();
+    assert_eq!(deserialized, light);
+}
+
+#[test]
+fn test() {
+    test_light_data();
+}
@@ -1,9 +1,10 @@
 use std::path::PathBuf;
 
+use inox_bitmask::bitmask;
 use inox_math::{Vector3, Vector4};
-use inox_serialize::{Deserialize, Serialize, SerializeFile};
+use inox_serialize::{de::Error, Deserialize, Deserializer, Serialize, SerializeFile};
 
-use crate::TextureType;
+use crate::{SamplerData, TextureType};
 
 #[repr(C)]
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
@@ -24,20 +25,97 @@ impl From<MaterialAlphaMode> for u32 {
     }
 }
 
+#[bitmask]
+#[repr(u32)]
+pub enum MaterialFlags {
+    None = 0,
+    DoubleSided = 1,
+    Unlit = 2,
+}
+
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 #[serde(crate = "inox_serialize")]
 pub struct MaterialData {
+    #[serde(deserialize_with = "deserialize_per_texture")]
     pub textures: [PathBuf; TextureType::Count as _],
+    #[serde(deserialize_with = "deserialize_per_texture")]
     pub texcoords_set: [usize; TextureType::Count as _],
+    //Sampler of each texture, shared by every material using the same texture
+    #[serde(default, deserialize_with = "deserialize_per_texture")]
+    pub samplers: [SamplerData; TextureType::Count as _],
     pub roughness_factor: f32,
     pub metallic_factor: f32,
     pub alpha_cutoff: f32,
     pub alpha_mode: MaterialAlphaMode,
     pub base_color: Vector4,
     pub emissive_color: Vector3,
+    //Multiplier of the emissive color, to have emitters brighter than 1
+    #[serde(default = "default_emissive_strength")]
+    pub emissive_strength: f32,
     pub occlusion_strength: f32,
     pub diffuse_color: Vector4,
     pub specular_color: Vector4,
+    //Fraction of the light passing through the surface, as glass does
+    #[serde(default)]
+    pub transmission_factor: f32,
+    //Thickness of the volume below the surface, zero for thin walled materials
+    #[serde(default)]
+    pub thickness_factor: f32,
+    //Color that white light turns into after travelling the attenuation distance in the volume
+    #[serde(default = "default_attenuation_color")]
+    pub attenuation_color: Vector3,
+    #[serde(default = "default_attenuation_distance")]
+    pub attenuation_distance: f32,
+    #[serde(default = "default_ior")]
+    pub ior: f32,
+    //Strength of the transparent coating layer over the surface, as on car paint
+    #[serde(default)]
+    pub clearcoat_factor: f32,
+    #[serde(default)]
+    pub clearcoat_roughness_factor: f32,
+    //Back faces are not culled and are lit as front faces, as leaves and thin sheets need
+    #[serde(default)]
+    pub double_sided: bool,
+    //Not lit by the lights, shown with its base and vertex colors as baked lighting needs
+    #[serde(default)]
+    pub unlit: bool,
+}
+
+//Materials written when there were less texture types get the missing ones empty
+fn deserialize_per_texture<'de, D, T>(
+    deserializer: D,
+) -> Result<[T; TextureType::Count as _], D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + Default,
+{
+    let values = Vec::<T>::deserialize(deserializer)?;
+    if values.len() > TextureType::Count as usize {
+        return Err(D::Error::invalid_length(
+            values.len(),
+            &"at most one value for each texture type",
+        ));
+    }
+    let mut result: [T; TextureType::Count as _] = Default::default();
+    values
+        .into_iter()
+        .enumerate()
+        .for_each(|(i, v)| result[i] = v);
+    Ok(result)
+}
+
+fn default_emissive_strength() -> f32 {
+    1.
+}
+fn default_attenuation_color() -> Vector3 {
+    Vector3::new(1., 1., 1.)
+}
+//Infinite in glTF, that can't be serialized
+fn default_attenuation_distance() -> f32 {
+    f32::MAX
+}
+fn default_ior() -> f32 {
+    1.5
 }
 
 impl SerializeFile for MaterialData {
@@ -51,15 +129,88 @@ impl Default for MaterialData {
         Self {
             textures: Default::default(),
             texcoords_set: Default::default(),
+            samplers: Default::default(),
             roughness_factor: 1.,
             metallic_factor: 1.,
             alpha_cutoff: 1.,
             alpha_mode: MaterialAlphaMode::Opaque,
             base_color: Vector4::new(1., 1., 1., 1.),
             emissive_color: Vector3::new(1., 1., 1.),
+            emissive_strength: default_emissive_strength(),
             occlusion_strength: 0.,
             diffuse_color: Vector4::new(1., 1., 1., 1.),
             specular_color: Vector4::new(0., 0., 0., 1.),
+            transmission_factor: 0.,
+            thickness_factor: 0.,
+            attenuation_color: default_attenuation_color(),
+            attenuation_distance: default_attenuation_distance(),
+            ior: default_ior(),
+            clearcoat_factor: 0.,
+            clearcoat_roughness_factor: 0.,
+            double_sided: false,
+            unlit: false,
         }
     }
 }
+
+#[allow(dead_code)]
+fn test_material_data() {
+    use inox_serialize::inox_serializable::SerializableRegistryRc;
+
+    //Same layout of the Material of the shaders
+    assert_eq!(std::mem::size_of::<crate::DrawMaterial>(), 224);
+
+    let registry = SerializableRegistryRc::default();
+    let material_data = MaterialData {
+        transmission_factor: 1.,
+        thickness_factor: 0.2,
+        attenuation_color: Vector3::new(0.5, 1., 0.5),
+        attenuation_distance: 3.,
+        ior: 1.33,
+        clearcoat_factor: 1.,
+        clearcoat_roughness_factor: 0.1,
+        double_sided: true,
+        unlit: true,
+        ..Default::default()
+    };
+    let s = inox_serialize::serialize(&material_data, &registry);
+    let deserialized: MaterialData = inox_serialize::deserialize(&s, &registry).unwrap();
+    assert_eq!(deserialized, material_data);
+
+    //Materials written before the transmission parameters are not transmissive
+    let end = s.find(",\"transmission_factor\"").unwrap();
+    let old_material = format!("{}}}", &s[..end]);
+    let deserialized: MaterialData = inox_serialize::deserialize(&old_material, &registry).unwrap();
+    assert_eq!(deserialized.transmission_factor, 0.);
+    assert_eq!(deserialized.attenuation_distance, f32::MAX);
+    assert_eq!(deserialized.ior, 1.5);
+    assert_eq!(deserialized.clearcoat_factor, 0.);
+    assert!(!deserialized.double_sided);
+    assert!(!deserialized.unlit);
+
+    //Materials written with 8 texture types have no clearcoat textures
+    let count = TextureType::Count as usize;
+    let old_material = s
+        .replace(
+            &format!("[{}]", vec!["\"\""; count].join(",")),
+            &format!("[{}]", ["\"\""; 8].join(",")),
+        )
+        .replace(
+            &format!("[{}]", vec!["0"; count].join(",")),
+            &format!("[{}]", ["0"; 8].join(",")),
+        );
+    assert_ne!(old_material, s);
+    let deserialized: MaterialData = inox_serialize::deserialize(&old_material, &registry).unwrap();
+    assert_eq!(deserialized, material_data);
+
+    let new_material = s.replace(
+        &format!("[{}]", vec!["0"; count].join(",")),
+        &format!("[{}]", vec!["0"; count + 1].join(",")),
+    );
+    assert!(inox_serialize::deserialize::<MaterialData>(&new_material, &registry).is_err());
+}
+
+#[test]
+fn test() {
+    test_material_data();
+}
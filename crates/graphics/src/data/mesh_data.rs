@@ -1,6 +1,9 @@
 use std::path::PathBuf;
 
-use inox_math::{decode_unorm, quantize_half, quantize_unorm, VecBase, Vector2, Vector3, Vector4};
+use inox_math::{
+    compute_bounding_sphere, decode_half, normalize_vec3_in_0_1, pack_3_f32_to_unorm_10,
+    quantize_half, quantize_unorm, unpack_unorm_10_to_3_f32, VecBase, Vector2, Vector3, Vector4,
+};
 
 use inox_serialize::{Deserialize, Serialize, SerializeFile};
 
@@ -37,6 +40,9 @@ impl Default for MeshletData {
 pub struct MeshData {
     pub aabb_min: Vector3,
     pub aabb_max: Vector3,
+    //Center and radius, zero until computed from the positions
+    #[serde(default)]
+    pub bounding_sphere: [f32; 4],
     pub positions: Vec<u32>, // u32 (10 x, 10 y, 10 z, 2 null)
     pub colors: Vec<u32>,    //rgba
     pub normals: Vec<u32>,   // u32 (10 x, 10 y, 10 z, 2 null)
@@ -60,6 +66,7 @@ impl Default for MeshData {
                 y: -f32::INFINITY,
                 z: -f32::INFINITY,
             },
+            bounding_sphere: [0.; 4],
             positions: Vec::new(),
             colors: Vec::new(),
             normals: Vec::new(),
@@ -102,14 +109,19 @@ impl MeshData {
 
     pub fn position(&self, i: usize) -> Vector3 {
         let size = self.aabb_max - self.aabb_min;
-        let p = self.positions[i];
-        let px = decode_unorm((p >> 20) & 0x000003FF, 10);
-        let py = decode_unorm((p >> 10) & 0x000003FF, 10);
-        let pz = decode_unorm(p & 0x000003FF, 10);
-        Vector3 {
-            x: self.aabb_min.x + size.x * px,
-            y: self.aabb_min.y + size.y * py,
-            z: self.aabb_min.z + size.z * pz,
+        self.aabb_min + size.mul(unpack_unorm_10_to_3_f32(self.positions[i]))
+    }
+
+    //Normals are unit vectors, mapped from [-1, 1] to [0, 1] to be packed as unorm
+    pub fn normal(&self, i: usize) -> Vector3 {
+        unpack_unorm_10_to_3_f32(self.normals[i]) * 2. - Vector3::default_one()
+    }
+
+    pub fn uv(&self, i: usize) -> Vector2 {
+        let uv = self.uvs[i];
+        Vector2 {
+            x: decode_half((uv & 0xFFFF) as u16),
+            y: decode_half((uv >> 16) as u16),
         }
     }
 
@@ -120,46 +132,23 @@ impl MeshData {
         let new_size = new_max - new_min;
         if new_max != self.aabb_max || new_min != self.aabb_min || new_size != old_size {
             self.positions.iter_mut().for_each(|p| {
-                let px = decode_unorm((*p >> 20) & 0x000003FF, 10);
-                let py = decode_unorm((*p >> 10) & 0x000003FF, 10);
-                let pz = decode_unorm(*p & 0x000003FF, 10);
-                let pos = Vector3 {
-                    x: self.aabb_min.x + old_size.x * px,
-                    y: self.aabb_min.y + old_size.y * py,
-                    z: self.aabb_min.z + old_size.z * pz,
-                };
-
-                let mut v = pos - new_min;
-                v.x /= new_size.x;
-                v.y /= new_size.y;
-                v.z /= new_size.z;
-                let vx = quantize_unorm(v.x, 10);
-                let vy = quantize_unorm(v.y, 10);
-                let vz = quantize_unorm(v.z, 10);
-                let new_p = vx << 20 | vy << 10 | vz;
-                *p = new_p;
+                let pos = self.aabb_min + old_size.mul(unpack_unorm_10_to_3_f32(*p));
+                *p = pack_3_f32_to_unorm_10(normalize_vec3_in_0_1(pos, new_min, new_max));
             });
         }
 
-        let mut v = p - new_min;
-        v.x /= new_size.x;
-        v.y /= new_size.y;
-        v.z /= new_size.z;
-        let vx = quantize_unorm(v.x, 10);
-        let vy = quantize_unorm(v.y, 10);
-        let vz = quantize_unorm(v.z, 10);
-        let new_p = vx << 20 | vy << 10 | vz;
-        self.positions.push(new_p);
+        self.positions
+            .push(pack_3_f32_to_unorm_10(normalize_vec3_in_0_1(
+                p, new_min, new_max,
+            )));
 
         self.aabb_max = new_max;
         self.aabb_min = new_min;
     }
 
     fn insert_normal(&mut self, n: Vector3) {
-        let nx = quantize_unorm(n.x, 10);
-        let ny = quantize_unorm(n.y, 10);
-        let nz = quantize_unorm(n.z, 10);
-        self.normals.push(nx << 20 | ny << 10 | nz);
+        self.normals
+            .push(pack_3_f32_to_unorm_10((n + Vector3::default_one()) * 0.5));
     }
 
     fn insert_color(&mut self, c: Vector4) {
@@ -267,14 +256,7 @@ impl MeshData {
         self.positions
             .reserve(self.positions.len() + mesh_data.positions.len());
         mesh_data.positions.iter().for_each(|p| {
-            let px = decode_unorm((p >> 20) & 0x000003FF, 10);
-            let py = decode_unorm((p >> 10) & 0x000003FF, 10);
-            let pz = decode_unorm(p & 0x000003FF, 10);
-            let pos = Vector3 {
-                x: mesh_data.aabb_min.x + size.x * px,
-                y: mesh_data.aabb_min.y + size.y * py,
-                z: mesh_data.aabb_min.z + size.z * pz,
-            };
+            let pos = mesh_data.aabb_min + size.mul(unpack_unorm_10_to_3_f32(*p));
             self.insert_position(pos);
         });
         self.colors.append(&mut mesh_data.colors);
@@ -305,6 +287,24 @@ impl MeshData {
         self.aabb_max
     }
 
+    //Computed from the positions when not stored, like for meshes created at runtime
+    pub fn bounding_sphere(&self) -> (Vector3, f32) {
+        let [x, y, z, radius] = self.bounding_sphere;
+        if radius > 0. || self.positions.is_empty() {
+            return (Vector3::new(x, y, z), radius);
+        }
+        let positions = (0..self.positions.len())
+            .map(|i| self.position(i))
+            .collect::<Vec<_>>();
+        compute_bounding_sphere(&positions)
+    }
+    pub fn compute_bounding_sphere(&mut self) -> &mut Self {
+        self.bounding_sphere = [0.; 4];
+        let (center, radius) = self.bounding_sphere();
+        self.bounding_sphere = [center.x, center.y, center.z, radius];
+        self
+    }
+
     pub fn compute_center(&self) -> Vector3 {
         let min = self.aabb_min();
         let max = self.aabb_max();
@@ -316,6 +316,14 @@ impl MeshData {
         .into()
     }
 
+    //Vertices without colors are white
+    pub fn has_vertex_colors(&self) -> bool {
+        self.colors.iter().any(|c| *c != u32::MAX)
+    }
+    pub fn has_vertex_alpha(&self) -> bool {
+        self.colors.iter().any(|c| c & 0xFF != 0xFF)
+    }
+
     pub fn set_vertex_color(&mut self, color: Vector4) -> &mut Self {
         let r = quantize_unorm(color.x, 8);
         let g = quantize_unorm(color.y, 8);
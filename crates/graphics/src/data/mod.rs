@@ -5,12 +5,15 @@ pub use compute_pass_data::*;
 pub use compute_pipeline_data::*;
 pub use constant_data::*;
 pub use draw_data::*;
+pub use environment_data::*;
 pub use instance_data::*;
 pub use light_data::*;
 pub use material_data::*;
 pub use mesh_data::*;
+pub use pass_graph_data::*;
 pub use render_pass_data::*;
 pub use render_pipeline_data::*;
+pub use sampler_data::*;
 pub use shader_data::*;
 pub use texture_data::*;
 pub use vertex_data::*;
@@ -20,12 +23,15 @@ pub mod compute_pass_data;
 pub mod compute_pipeline_data;
 pub mod constant_data;
 pub mod draw_data;
+pub mod environment_data;
 pub mod instance_data;
 pub mod light_data;
 pub mod material_data;
 pub mod mesh_data;
+pub mod pass_graph_data;
 pub mod render_pass_data;
 pub mod render_pipeline_data;
+pub mod sampler_data;
 pub mod shader_data;
 pub mod texture_data;
 pub mod vertex_data;
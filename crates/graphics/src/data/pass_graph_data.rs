@@ -0,0 +1,279 @@
+use inox_resources::ConfigBase;
+use inox_serialize::{Deserialize, Serialize, SerializeFile};
+
+use crate::MeshFlags;
+
+//A pass of the graph: it runs after every pass producing one of its inputs.
+//An input that is an output too is modified in place, after the passes declared before it
+//writing it or, when there are none, after the passes producing it without reading it.
+//When mesh_flags is set it replaces the flags of the meshes drawn by the pass
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(crate = "inox_serialize")]
+pub struct PassNodeData {
+    pub name: String,
+    #[serde(default)]
+    pub inputs: Vec<String>,
+    #[serde(default)]
+    pub outputs: Vec<String>,
+    #[serde(default)]
+    pub mesh_flags: Option<MeshFlags>,
+    #[serde(default = "is_enabled_by_default")]
+    pub is_enabled: bool,
+}
+
+fn is_enabled_by_default() -> bool {
+    true
+}
+
+impl PassNodeData {
+    pub fn new(name: &str, inputs: &[&str], outputs: &[&str]) -> Self {
+        Self {
+            name: name.to_string(),
+            inputs: inputs.iter().map(|s| s.to_string()).collect(),
+            outputs: outputs.iter().map(|s| s.to_string()).collect(),
+            mesh_flags: None,
+            is_enabled: true,
+        }
+    }
+    pub fn with_mesh_flags(mut self, mesh_flags: MeshFlags) -> Self {
+        self.mesh_flags = Some(mesh_flags);
+        self
+    }
+    pub fn enabled(mut self, is_enabled: bool) -> Self {
+        self.is_enabled = is_enabled;
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PassGraphError {
+    DuplicatedPass(String),
+    MissingDependency { pass: String, input: String },
+    Cycle(Vec<String>),
+}
+
+impl std::fmt::Display for PassGraphError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PassGraphError::DuplicatedPass(name) => write!(f, "Pass {name} is declared twice"),
+            PassGraphError::MissingDependency { pass, input } => {
+                write!(f, "Pass {pass} reads {input} but no pass writes it")
+            }
+            PassGraphError::Cycle(passes) => {
+                write!(f, "Passes dependency cycle: {}", passes.join(" -> "))
+            }
+        }
+    }
+}
+
+#[derive(Default, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(crate = "inox_serialize")]
+pub struct PassGraphData {
+    pub passes: Vec<PassNodeData>,
+}
+
+impl SerializeFile for PassGraphData {
+    fn extension() -> &'static str {
+        "pass_graph"
+    }
+}
+
+impl ConfigBase for PassGraphData {
+    fn get_filename(&self) -> &'static str {
+        "render.pass_graph"
+    }
+}
+
+impl PassGraphData {
+    //Returns the passes sorted so that every pass comes after the ones writing its inputs,
+    //keeping the declaration order between independent passes
+    pub fn ordered_passes(&self) -> Result<Vec<&PassNodeData>, PassGraphError> {
+        for (i, pass) in self.passes.iter().enumerate() {
+            if self.passes[..i].iter().any(|p| p.name == pass.name) {
+                return Err(PassGraphError::DuplicatedPass(pass.name.clone()));
+            }
+        }
+        let mut dependencies = Vec::with_capacity(self.passes.len());
+        for (i, pass) in self.passes.iter().enumerate() {
+            let mut pass_dependencies = Vec::new();
+            for input in pass.inputs.iter() {
+                let is_modified = pass.outputs.contains(input);
+                let producers = self
+                    .passes
+                    .iter()
+                    .enumerate()
+                    .filter(|(j, p)| *j != i && p.outputs.contains(input))
+                    .map(|(j, _)| j)
+                    .collect::<Vec<_>>();
+                let mut writers = producers.clone();
+                if is_modified {
+                    writers.retain(|&j| j < i);
+                    if writers.is_empty() {
+                        writers = producers
+                            .into_iter()
+                            .filter(|&j| !self.passes[j].inputs.contains(input))
+                            .collect();
+                    }
+                }
+                if writers.is_empty() {
+                    return Err(PassGraphError::MissingDependency {
+                        pass: pass.name.clone(),
+                        input: input.clone(),
+                    });
+                }
+                pass_dependencies.extend(writers);
+            }
+            dependencies.push(pass_dependencies);
+        }
+
+        let mut ordered = Vec::with_capacity(self.passes.len());
+        let mut is_added = vec![false; self.passes.len()];
+        while ordered.len() < self.passes.len() {
+            let next = (0..self.passes.len())
+                .find(|&i| !is_added[i] && dependencies[i].iter().all(|&d| is_added[d]));
+            match next {
+                Some(i) => {
+                    is_added[i] = true;
+                    ordered.push(&self.passes[i]);
+                }
+                None => {
+                    return Err(PassGraphError::Cycle(
+                        self.find_cycle(&dependencies, &is_added),
+                    ))
+                }
+            }
+        }
+        Ok(ordered)
+    }
+
+    fn find_cycle(&self, dependencies: &[Vec<usize>], is_added: &[bool]) -> Vec<String> {
+        //Every pass left has at least a dependency not added yet, so walking them loops
+        let mut path = Vec::new();
+        let mut current = is_added.iter().position(|&added| !added).unwrap();
+        while !path.contains(&current) {
+            path.push(current);
+            current = *dependencies[current]
+                .iter()
+                .find(|&&d| !is_added[d])
+                .unwrap();
+        }
+        let start = path.iter().position(|&i| i == current).unwrap();
+        let mut cycle = path[start..]
+            .iter()
+            .map(|&i| self.passes[i].name.clone())
+            .collect::<Vec<_>>();
+        cycle.push(self.passes[current].name.clone());
+        cycle
+    }
+}
+
+#[allow(dead_code)]
+fn test_pass_graph() {
+    let names = |passes: Vec<&PassNodeData>| {
+        passes
+            .iter()
+            .map(|p| p.name.as_str())
+            .collect::<Vec<_>>()
+            .join(",")
+    };
+    let gbuffer = PassNodeData::new("GBufferPass", &[], &["gbuffer", "depth"])
+        .with_mesh_flags(MeshFlags::Visible | MeshFlags::Opaque);
+    let pbr = PassNodeData::new("PBRPass", &["gbuffer"], &["screen"]);
+    let wireframe = PassNodeData::new("WireframePass", &["depth"], &["screen"]);
+    let ui = PassNodeData::new("UIPass", &["screen"], &["screen"]);
+
+    let graph = PassGraphData {
+        passes: vec![gbuffer.clone(), wireframe.clone(), pbr.clone(), ui.clone()],
+    };
+    assert_eq!(
+        names(graph.ordered_passes().unwrap()),
+        "GBufferPass,WireframePass,PBRPass,UIPass"
+    );
+
+    //Swapping independent passes changes their order, dependencies are always respected
+    let graph = PassGraphData {
+        passes: vec![pbr.clone(), ui.clone(), wireframe.clone(), gbuffer.clone()],
+    };
+    assert_eq!(
+        names(graph.ordered_passes().unwrap()),
+        "GBufferPass,PBRPass,UIPass,WireframePass"
+    );
+
+    //Passes modifying the same output in place run in declaration order
+    let overlay = PassNodeData::new("OverlayPass", &["screen"], &["screen"]);
+    let graph = PassGraphData {
+        passes: vec![
+            gbuffer.clone(),
+            pbr.clone(),
+            ui.clone(),
+            overlay.clone(),
+            wireframe.clone(),
+        ],
+    };
+    assert_eq!(
+        names(graph.ordered_passes().unwrap()),
+        "GBufferPass,PBRPass,UIPass,OverlayPass,WireframePass"
+    );
+    //An in place pass declared before every writer runs after the passes producing its input
+    let graph = PassGraphData {
+        passes: vec![overlay, ui.clone(), gbuffer.clone(), pbr.clone()],
+    };
+    assert_eq!(
+        names(graph.ordered_passes().unwrap()),
+        "GBufferPass,PBRPass,OverlayPass,UIPass"
+    );
+    let graph = PassGraphData {
+        passes: vec![gbuffer.clone(), ui.clone()],
+    };
+    assert_eq!(
+        graph.ordered_passes().unwrap_err(),
+        PassGraphError::MissingDependency {
+            pass: "UIPass".to_string(),
+            input: "screen".to_string()
+        }
+    );
+
+    let graph = PassGraphData {
+        passes: vec![pbr.clone(), wireframe.clone()],
+    };
+    assert_eq!(
+        graph.ordered_passes().unwrap_err(),
+        PassGraphError::MissingDependency {
+            pass: "PBRPass".to_string(),
+            input: "gbuffer".to_string()
+        }
+    );
+
+    let graph = PassGraphData {
+        passes: vec![
+            PassNodeData::new("A", &["b"], &["a"]),
+            PassNodeData::new("B", &["a"], &["b"]),
+        ],
+    };
+    assert_eq!(
+        graph.ordered_passes().unwrap_err(),
+        PassGraphError::Cycle(vec!["A".to_string(), "B".to_string(), "A".to_string()])
+    );
+
+    let graph = PassGraphData {
+        passes: vec![pbr.clone(), pbr],
+    };
+    assert_eq!(
+        graph.ordered_passes().unwrap_err(),
+        PassGraphError::DuplicatedPass("PBRPass".to_string())
+    );
+
+    let data = r#"{"passes":[{"name":"GBufferPass","outputs":["depth"],"mesh_flags":{"bits":3}}]}"#;
+    let registry = inox_serialize::inox_serializable::SerializableRegistryRc::default();
+    let graph: PassGraphData = inox_serialize::deserialize(data, &registry).unwrap();
+    assert!(graph.passes[0].is_enabled);
+    assert_eq!(
+        graph.passes[0].mesh_flags,
+        Some(MeshFlags::Visible | MeshFlags::Opaque)
+    );
+}
+
+#[test]
+fn test() {
+    test_pass_graph();
+}
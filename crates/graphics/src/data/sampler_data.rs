@@ -0,0 +1,140 @@
+use inox_serialize::{Deserialize, Serialize};
+
+//Values are the ones the shaders use to wrap the coordinates inside the atlas area of a texture
+#[repr(C)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone, Copy)]
+#[serde(crate = "inox_serialize")]
+pub enum AddressMode {
+    ClampToEdge = 0,
+    Repeat = 1,
+    MirrorRepeat = 2,
+}
+
+impl From<AddressMode> for wgpu::AddressMode {
+    fn from(val: AddressMode) -> Self {
+        match val {
+            AddressMode::ClampToEdge => wgpu::AddressMode::ClampToEdge,
+            AddressMode::Repeat => wgpu::AddressMode::Repeat,
+            AddressMode::MirrorRepeat => wgpu::AddressMode::MirrorRepeat,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone, Copy)]
+#[serde(crate = "inox_serialize")]
+pub enum FilterMode {
+    Nearest,
+    Linear,
+}
+
+impl From<FilterMode> for wgpu::FilterMode {
+    fn from(val: FilterMode) -> Self {
+        match val {
+            FilterMode::Nearest => wgpu::FilterMode::Nearest,
+            FilterMode::Linear => wgpu::FilterMode::Linear,
+        }
+    }
+}
+
+//Highest anisotropy level accepted by the samplers
+pub const MAX_ANISOTROPY: u16 = 16;
+
+//Anisotropy level supported by the device, 1 when it can't do anisotropic filtering
+pub fn max_anisotropy(downlevel_flags: wgpu::DownlevelFlags) -> u16 {
+    if downlevel_flags.contains(wgpu::DownlevelFlags::ANISOTROPIC_FILTERING) {
+        MAX_ANISOTROPY
+    } else {
+        1
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone, Copy)]
+#[serde(crate = "inox_serialize")]
+pub struct SamplerData {
+    pub address_mode_u: AddressMode,
+    pub address_mode_v: AddressMode,
+    pub mag_filter: FilterMode,
+    pub min_filter: FilterMode,
+    pub mipmap_filter: FilterMode,
+    pub anisotropy_clamp: u16,
+}
+
+impl Default for SamplerData {
+    fn default() -> Self {
+        Self {
+            address_mode_u: AddressMode::ClampToEdge,
+            address_mode_v: AddressMode::ClampToEdge,
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Nearest,
+            mipmap_filter: FilterMode::Nearest,
+            anisotropy_clamp: 1,
+        }
+    }
+}
+
+impl SamplerData {
+    pub fn repeat() -> Self {
+        Self {
+            address_mode_u: AddressMode::Repeat,
+            address_mode_v: AddressMode::Repeat,
+            ..Default::default()
+        }
+    }
+    pub fn anisotropy(&self, max_anisotropy: u16) -> u16 {
+        self.anisotropy_clamp.clamp(1, max_anisotropy.max(1))
+    }
+    //Anisotropic filtering needs every filter to be linear.
+    //Samplers asking for it fall back to trilinear filtering when the device can't do it
+    pub fn descriptor(&self, max_anisotropy: u16) -> wgpu::SamplerDescriptor<'static> {
+        let is_filtered = self.anisotropy_clamp > 1;
+        let filter = |filter: FilterMode| {
+            if is_filtered {
+                wgpu::FilterMode::Linear
+            } else {
+                filter.into()
+            }
+        };
+        wgpu::SamplerDescriptor {
+            address_mode_u: self.address_mode_u.into(),
+            address_mode_v: self.address_mode_v.into(),
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: filter(self.mag_filter),
+            min_filter: filter(self.min_filter),
+            mipmap_filter: filter(self.mipmap_filter),
+            anisotropy_clamp: self.anisotropy(max_anisotropy),
+            ..Default::default()
+        }
+    }
+}
+
+#[allow(dead_code)]
+fn test_anisotropy() {
+    let sampler = SamplerData {
+        anisotropy_clamp: 16,
+        ..Default::default()
+    };
+    let descriptor = sampler.descriptor(4);
+    assert_eq!(descriptor.anisotropy_clamp, 4);
+    assert_eq!(descriptor.min_filter, wgpu::FilterMode::Linear);
+    assert_eq!(descriptor.mipmap_filter, wgpu::FilterMode::Linear);
+
+    //Devices without anisotropic filtering get a trilinear sampler
+    let descriptor = sampler.descriptor(max_anisotropy(wgpu::DownlevelFlags::empty()));
+    assert_eq!(descriptor.anisotropy_clamp, 1);
+    assert_eq!(descriptor.mag_filter, wgpu::FilterMode::Linear);
+    assert_eq!(descriptor.min_filter, wgpu::FilterMode::Linear);
+    assert_eq!(descriptor.mipmap_filter, wgpu::FilterMode::Linear);
+
+    let descriptor = SamplerData::default().descriptor(MAX_ANISOTROPY);
+    assert_eq!(descriptor.anisotropy_clamp, 1);
+    assert_eq!(descriptor.min_filter, wgpu::FilterMode::Nearest);
+    assert_eq!(
+        max_anisotropy(wgpu::DownlevelFlags::ANISOTROPIC_FILTERING),
+        MAX_ANISOTROPY
+    );
+}
+
+#[test]
+fn test() {
+    test_anisotropy();
+}
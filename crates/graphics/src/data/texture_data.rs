@@ -1,4 +1,4 @@
-use crate::print_field_size;
+use crate::{print_field_size, SamplerData};
 
 use inox_bitmask::bitmask;
 use inox_serialize::{Deserialize, Serialize};
@@ -37,8 +37,12 @@ pub enum TextureType {
     Occlusion = 4,
     SpecularGlossiness = 5,
     Diffuse = 6,
-    _EmptyForPadding = 7,
-    Count = 8,
+    Clearcoat = 7,
+    ClearcoatRoughness = 8,
+    ClearcoatNormal = 9,
+    Transmission = 10,
+    _EmptyForPadding1 = 11,
+    Count = 12,
 }
 
 impl From<TextureType> for usize {
@@ -56,8 +60,12 @@ impl From<usize> for TextureType {
             4 => TextureType::Occlusion,
             5 => TextureType::SpecularGlossiness,
             6 => TextureType::Diffuse,
-            7 => TextureType::_EmptyForPadding,
-            8 => TextureType::Count,
+            7 => TextureType::Clearcoat,
+            8 => TextureType::ClearcoatRoughness,
+            9 => TextureType::ClearcoatNormal,
+            10 => TextureType::Transmission,
+            11 => TextureType::_EmptyForPadding1,
+            12 => TextureType::Count,
             _ => panic!("Invalid TextureType value: {value}"),
         }
     }
@@ -71,6 +79,10 @@ pub struct TextureInfo {
     pub total_width: f32,
     pub total_height: f32,
     pub area: [f32; 4],
+    pub sampler_index: u32,
+    pub address_mode_u: u32,
+    pub address_mode_v: u32,
+    _padding: u32,
 }
 
 impl Default for TextureInfo {
@@ -81,11 +93,30 @@ impl Default for TextureInfo {
             total_width: 0.,
             total_height: 0.,
             area: [0., 0., 1., 1.],
+            sampler_index: 0,
+            address_mode_u: 0,
+            address_mode_v: 0,
+            _padding: 0,
         }
     }
 }
 
 impl TextureInfo {
+    pub fn new(
+        texture_index: u32,
+        layer_index: u32,
+        area: [f32; 4],
+        total_dimensions: (u32, u32),
+    ) -> Self {
+        Self {
+            texture_index,
+            layer_index,
+            total_width: total_dimensions.0 as _,
+            total_height: total_dimensions.1 as _,
+            area,
+            ..Default::default()
+        }
+    }
     pub fn get_texture_index(&self) -> u32 {
         self.texture_index
     }
@@ -104,6 +135,13 @@ impl TextureInfo {
     pub fn height(&self) -> u32 {
         self.area[3] as _
     }
+    //Textures in an atlas can't be wrapped by the sampler, the shaders do it with the address modes
+    pub fn set_sampler(&mut self, sampler_index: u32, sampler: &SamplerData) -> &mut Self {
+        self.sampler_index = sampler_index;
+        self.address_mode_u = sampler.address_mode_u as _;
+        self.address_mode_v = sampler.address_mode_v as _;
+        self
+    }
 }
 
 impl TextureInfo {
@@ -118,6 +156,10 @@ impl TextureInfo {
         print_field_size!(s, total_width, u32, 1);
         print_field_size!(s, total_height, u32, 1);
         print_field_size!(s, area, [f32; 4], 1);
+        print_field_size!(s, sampler_index, u32, 1);
+        print_field_size!(s, address_mode_u, u32, 1);
+        print_field_size!(s, address_mode_v, u32, 1);
+        print_field_size!(s, _padding, u32, 1);
 
         println!(
             "Alignment result: {} -> {}",
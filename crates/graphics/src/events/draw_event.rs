@@ -18,6 +18,39 @@ impl DrawEvent {
     fn compare_and_discard(&self, _other: &Self) -> bool {
         false
     }
+    //Identical events sent in the same frame share the same key and are drawn only once
+    pub fn coalesce_key(&self) -> Vec<u32> {
+        let zero = Vector4::new(0., 0., 0., 0.);
+        let (kind, is_wireframe, values) = match *self {
+            DrawEvent::Line(start, end, color) => {
+                (0, false, [start.extend(0.), end.extend(0.), color])
+            }
+            DrawEvent::BoundingBox(min, max, color) => {
+                (1, false, [min.extend(0.), max.extend(0.), color])
+            }
+            DrawEvent::Quad(min, max, z, color, is_wireframe) => (
+                2,
+                is_wireframe,
+                [min.extend(z).extend(0.), max.extend(0.).extend(0.), color],
+            ),
+            DrawEvent::Arrow(start, direction, color, is_wireframe) => (
+                3,
+                is_wireframe,
+                [start.extend(0.), direction.extend(0.), color],
+            ),
+            DrawEvent::Sphere(position, radius, color, is_wireframe) => {
+                (4, is_wireframe, [position.extend(radius), zero, color])
+            }
+            DrawEvent::Circle(position, radius, color, is_wireframe) => {
+                (5, is_wireframe, [position.extend(radius), zero, color])
+            }
+        };
+        let mut key = vec![kind, is_wireframe as u32];
+        values.iter().for_each(|v| {
+            key.extend([v.x, v.y, v.z, v.w].iter().map(|f| f.to_bits()));
+        });
+        key
+    }
     fn message_from_command_parser(command_parser: CommandParser) -> Option<Self>
     where
         Self: Sized,
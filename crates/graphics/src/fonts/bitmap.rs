@@ -0,0 +1,460 @@
+use inox_math::Vector2;
+use ttf_parser::Face;
+
+use super::{geometry::Geometry, raster::signed_distance_field};
+
+//Rgba coverage of a glyph, rows go from the top to the bottom
+pub struct GlyphBitmap {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
+//Alpha of a glyph surrounded by padding empty pixels, rows go from the bottom to the top
+struct GlyphCoverage {
+    width: usize,
+    height: usize,
+    alpha: Vec<f32>,
+}
+
+//None for glyphs without an outline, like spaces
+fn glyph_coverage(
+    face: &Face,
+    character: char,
+    size: u32,
+    padding: usize,
+) -> Option<GlyphCoverage> {
+    let glyph_id = face.glyph_index(character)?;
+    let bb = face.glyph_bounding_box(glyph_id)?;
+    let scale = size as f32 / face.units_per_em() as f32;
+    let width = ((bb.x_max as f32 - bb.x_min as f32) * scale).ceil() as usize + 1 + 2 * padding;
+    let height = ((bb.y_max as f32 - bb.y_min as f32) * scale).ceil() as usize + 1 + 2 * padding;
+    let padding = padding as f32 / scale;
+
+    //Lines touching the last column write in the next pixel too
+    let mut coverage = vec![0.; width * height + 4];
+    let mut geometry = Geometry::new(
+        Vector2::new(padding - bb.x_min as f32, padding - bb.y_min as f32),
+        Vector2::new(scale, scale),
+        width,
+        height,
+        &mut coverage,
+    );
+    face.outline_glyph(glyph_id, &mut geometry)?;
+
+    let mut accumulated_alpha = 0.;
+    let alpha = coverage[..width * height]
+        .iter()
+        .map(|c| {
+            accumulated_alpha += c;
+            accumulated_alpha.abs().min(1.)
+        })
+        .collect();
+    Some(GlyphCoverage {
+        width,
+        height,
+        alpha,
+    })
+}
+
+impl GlyphBitmap {
+    pub fn rasterize(face: &Face, character: char, size: u32) -> Option<Self> {
+        let coverage = glyph_coverage(face, character, size, 0)?;
+        Some(Self::from_values(
+            coverage.width,
+            coverage.height,
+            &coverage.alpha,
+        ))
+    }
+
+    //The distance from the edge is stored instead of the coverage: 0.5 on the edge, 0 and 1 when
+    //spread pixels away from it. Sampled with a linear filter the edges stay crisp at any scale
+    pub fn rasterize_sdf(face: &Face, character: char, size: u32, spread: u32) -> Option<Self> {
+        let spread = spread.max(1) as usize;
+        let coverage = glyph_coverage(face, character, size, spread)?;
+        let values =
+            signed_distance_field(&coverage.alpha, coverage.width, coverage.height, spread)
+                .iter()
+                .map(|d| 0.5 + d / (2 * spread) as f32)
+                .collect::<Vec<_>>();
+        Some(Self::from_values(coverage.width, coverage.height, &values))
+    }
+
+    fn from_values(width: usize, height: usize, values: &[f32]) -> Self {
+        let mut data = vec![0; width * height * 4];
+        values.iter().enumerate().for_each(|(index, v)| {
+            let value = (v.clamp(0., 1.) * 255.).round() as u8;
+            let (x, y) = (index % width, height - 1 - index / width);
+            let pixel = (y * width + x) * 4;
+            data[pixel..pixel + 4].copy_from_slice(&[value; 4]);
+        });
+        Self {
+            width: width as _,
+            height: height as _,
+            data,
+        }
+    }
+}
+
+#[allow(dead_code)]
+fn test_glyph_bitmap() {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../../data_raw/fonts/Espera-Regular.ttf");
+    let font_data = crate::FontData::new(path.as_path());
+    let face = font_data.face().unwrap();
+    let bitmap = GlyphBitmap::rasterize(&face, 'A', 32).unwrap();
+    assert!(bitmap.width > 1 && bitmap.height > 1 && bitmap.height <= 33);
+    assert_eq!(
+        bitmap.data.len(),
+        (bitmap.width * bitmap.height * 4) as usize
+    );
+    assert!(bitmap.data.iter().any(|a| *a == 255));
+    assert!(GlyphBitmap::rasterize(&face, ' ', 32).is_none());
+}
+
+#[allow(dead_code)]
+fn test_sdf() {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../../data_raw/fonts/Espera-Regular.ttf");
+    let font_data = crate::FontData::new(path.as_path());
+    let face = font_data.face().unwrap();
+    const SIZE: u32 = 48;
+    const SPREAD: usize = 4;
+
+    let bitmap = GlyphBitmap::rasterize_sdf(&face, 'A', SIZE, SPREAD as _).unwrap();
+    //Padding pixels are far outside the glyph
+    assert_eq!(bitmap.data[0], 0);
+    assert!(bitmap.data.iter().any(|v| *v > 128));
+
+    let coverage = glyph_coverage(&face, 'A', SIZE, SPREAD).unwrap();
+    let field = signed_distance_field(&coverage.alpha, coverage.width, coverage.height, SPREAD);
+    let value = |x: isize, y: isize, values: &[f32]| {
+        let x = x.clamp(0, coverage.width as isize - 1) as usize;
+        let y = y.clamp(0, coverage.height as isize - 1) as usize;
+        values[y * coverage.width + x]
+    };
+    //Same as a linear sampler, with x and y in pixels of the distance field
+    let sample = |x: f32, y: f32| {
+        let (x, y) = (x - 0.5, y - 0.5);
+        let (x0, y0) = (x.floor() as isize, y.floor() as isize);
+        let (tx, ty) = (x - x.floor(), y - y.floor());
+        let bottom = value(x0, y0, &field) * (1. - tx) + value(x0 + 1, y0, &field) * tx;
+        let top = value(x0, y0 + 1, &field) * (1. - tx) + value(x0 + 1, y0 + 1, &field) * tx;
+        bottom * (1. - ty) + top * ty
+    };
+    //Pixels drawn scaling the glyph rasterized at SIZE, compared with the glyph rasterized at size
+    let errors = |size: u32| {
+        let reference = glyph_coverage(&face, 'A', size, 0).unwrap();
+        let ratio = SIZE as f32 / size as f32;
+        let mut sdf_errors = 0;
+        let mut nearest_errors = 0;
+        let mut is_sdf_outside_glyph = false;
+        for y in 0..reference.height {
+            for x in 0..reference.width {
+                let alpha = reference.alpha[y * reference.width + x];
+                let sx = (x as f32 + 0.5) * ratio + SPREAD as f32;
+                let sy = (y as f32 + 0.5) * ratio + SPREAD as f32;
+                let is_sdf_inside = sample(sx, sy) >= 0.;
+                let is_nearest_inside = value(sx as _, sy as _, &coverage.alpha) >= 0.5;
+                sdf_errors += (is_sdf_inside != (alpha >= 0.5)) as u32;
+                nearest_errors += (is_nearest_inside != (alpha >= 0.5)) as u32;
+                is_sdf_outside_glyph |= is_sdf_inside && alpha <= 0.;
+            }
+        }
+        (sdf_errors, nearest_errors, is_sdf_outside_glyph)
+    };
+
+    //Magnified edges follow the outline instead of the pixels of the distance field
+    let (sdf_errors, nearest_errors, _) = errors(200);
+    assert!(sdf_errors < nearest_errors);
+    //Minified glyphs never draw where the glyph isn't
+    let (_, _, is_sdf_outside_glyph) = errors(8);
+    assert!(!is_sdf_outside_glyph);
+}
+
+//Draws the distance field of the glyph over a width x height target with sdf_text.wgsl
+//on a device without a surface, uv_rect being the area of the field shown.
+//Returns the alpha of the target with rows from the bottom to the top like the coverage,
+//none when the system has no adapter able to run it
+#[allow(dead_code)]
+#[cfg(not(target_arch = "wasm32"))]
+fn gpu_draw_sdf(
+    bitmap: &GlyphBitmap,
+    uv_rect: [f32; 4],
+    (width, height): (u32, u32),
+    outline_width: f32,
+    softness: f32,
+) -> Option<Vec<f32>> {
+    use inox_resources::to_slice;
+    use std::num::NonZeroU32;
+    use wgpu::util::DeviceExt;
+
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+    let adapter = futures::executor::block_on(
+        instance.request_adapter(&wgpu::RequestAdapterOptions::default()),
+    )?;
+    let (device, queue) = futures::executor::block_on(adapter.request_device(
+        &wgpu::DeviceDescriptor {
+            label: None,
+            features: wgpu::Features::empty(),
+            limits: adapter.limits(),
+        },
+        None,
+    ))
+    .ok()?;
+
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../../data_raw/shaders/wgsl/sdf_text.wgsl");
+    let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("sdf_text.wgsl"),
+        source: wgpu::ShaderSource::Wgsl(crate::wgsl_with_imports(&path).into()),
+    });
+    let format = wgpu::TextureFormat::Rgba8Unorm;
+    let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("SdfText Pipeline"),
+        layout: None,
+        vertex: wgpu::VertexState {
+            module: &module,
+            entry_point: "vs_main",
+            buffers: &[wgpu::VertexBufferLayout {
+                array_stride: 8 * std::mem::size_of::<f32>() as u64,
+                step_mode: wgpu::VertexStepMode::Instance,
+                attributes: &wgpu::vertex_attr_array![0 => Float32x4, 1 => Float32x4],
+            }],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &module,
+            entry_point: "fs_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: None,
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleStrip,
+            ..Default::default()
+        },
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    });
+
+    let glyph_texture = device.create_texture_with_data(
+        &queue,
+        &wgpu::TextureDescriptor {
+            label: None,
+            size: wgpu::Extent3d {
+                width: bitmap.width,
+                height: bitmap.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[format],
+        },
+        &bitmap.data,
+    );
+    let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+        mag_filter: wgpu::FilterMode::Linear,
+        min_filter: wgpu::FilterMode::Linear,
+        ..Default::default()
+    });
+    let text_data: [f32; 12] = [
+        1.,
+        1.,
+        1.,
+        1.,
+        0.,
+        0.,
+        0.,
+        1.,
+        outline_width,
+        softness,
+        0.,
+        0.,
+    ];
+    let uniform = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: None,
+        contents: to_slice(&text_data),
+        usage: wgpu::BufferUsages::UNIFORM,
+    });
+    let glyph: [f32; 8] = [
+        -1., -1., 1., 1., uv_rect[0], uv_rect[1], uv_rect[2], uv_rect[3],
+    ];
+    let glyphs = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: None,
+        contents: to_slice(&glyph),
+        usage: wgpu::BufferUsages::VERTEX,
+    });
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &pipeline.get_bind_group_layout(0),
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(
+                    &glyph_texture.create_view(&wgpu::TextureViewDescriptor::default()),
+                ),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::Sampler(&sampler),
+            },
+        ],
+    });
+
+    let target = device.create_texture(&wgpu::TextureDescriptor {
+        label: None,
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[format],
+    });
+    let target_view = target.create_view(&wgpu::TextureViewDescriptor::default());
+    //Rows are copied padded to the copy alignment
+    let alignment = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    let bytes_per_row = (width * 4 + alignment - 1) / alignment * alignment;
+    let read_back = device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size: (bytes_per_row * height) as u64,
+        usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+    {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("SdfText RenderPass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &target_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        render_pass.set_pipeline(&pipeline);
+        render_pass.set_bind_group(0, &bind_group, &[]);
+        render_pass.set_vertex_buffer(0, glyphs.slice(..));
+        render_pass.draw(0..4, 0..1);
+    }
+    encoder.copy_texture_to_buffer(
+        target.as_image_copy(),
+        wgpu::ImageCopyBuffer {
+            buffer: &read_back,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: NonZeroU32::new(bytes_per_row),
+                rows_per_image: None,
+            },
+        },
+        wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+    );
+    queue.submit(Some(encoder.finish()));
+
+    let slice = read_back.slice(..);
+    slice.map_async(wgpu::MapMode::Read, |_| {});
+    device.poll(wgpu::Maintain::Wait);
+    let pixels = slice.get_mapped_range();
+    let alpha = (0..height)
+        .rev()
+        .flat_map(|y| {
+            let row = &pixels[(y * bytes_per_row) as usize..];
+            (0..width as usize).map(move |x| row[x * 4 + 3] as f32 / 255.)
+        })
+        .collect();
+    Some(alpha)
+}
+
+//The 48px distance field drawn by the text shader at 8px and 200px
+#[allow(dead_code)]
+#[cfg(not(target_arch = "wasm32"))]
+fn test_gpu_sdf() {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../../data_raw/fonts/Espera-Regular.ttf");
+    let font_data = crate::FontData::new(path.as_path());
+    let face = font_data.face().unwrap();
+    const SIZE: u32 = 48;
+    const SPREAD: usize = 4;
+
+    let bitmap = GlyphBitmap::rasterize_sdf(&face, 'A', SIZE, SPREAD as _).unwrap();
+    let coverage = glyph_coverage(&face, 'A', SIZE, SPREAD).unwrap();
+    //Alpha drawn at size with the glyph rasterized at size as reference,
+    //the quad shows the glyph without the padding of the distance field
+    let draw = |size: u32, outline_width: f32| {
+        let reference = glyph_coverage(&face, 'A', size, 0).unwrap();
+        let ratio = SIZE as f32 / size as f32;
+        let u = |x: usize| (x as f32 * ratio + SPREAD as f32) / bitmap.width as f32;
+        let v = |y: usize| 1. - (y as f32 * ratio + SPREAD as f32) / bitmap.height as f32;
+        let uv_rect = [u(0), v(0), u(reference.width), v(reference.height)];
+        let dimensions = (reference.width as u32, reference.height as u32);
+        gpu_draw_sdf(&bitmap, uv_rect, dimensions, outline_width, 0.)
+            .map(|alpha| (reference, ratio, alpha))
+    };
+
+    let Some((reference, ratio, alpha)) = draw(200, 0.) else {
+        //No adapter able to draw on this system
+        return;
+    };
+    //Magnified edges follow the outline instead of the pixels of the distance field,
+    //and they are smoothed instead of stepping
+    let mut sdf_errors = 0;
+    let mut nearest_errors = 0;
+    for y in 0..reference.height {
+        for x in 0..reference.width {
+            let is_inside = reference.alpha[y * reference.width + x] >= 0.5;
+            let sx = (((x as f32 + 0.5) * ratio) as usize + SPREAD).min(coverage.width - 1);
+            let sy = (((y as f32 + 0.5) * ratio) as usize + SPREAD).min(coverage.height - 1);
+            let is_nearest_inside = coverage.alpha[sy * coverage.width + sx] >= 0.5;
+            sdf_errors += ((alpha[y * reference.width + x] >= 0.5) != is_inside) as u32;
+            nearest_errors += (is_nearest_inside != is_inside) as u32;
+        }
+    }
+    assert!(
+        sdf_errors < nearest_errors,
+        "{sdf_errors} pixels drawn wrong instead of less than {nearest_errors}"
+    );
+    assert!(alpha.iter().any(|a| *a > 0.1 && *a < 0.9));
+    //The outline grows the glyph
+    let (_, _, outlined_alpha) = draw(200, 0.1).unwrap();
+    let count_opaque = |alpha: &[f32]| alpha.iter().filter(|a| **a >= 0.5).count();
+    assert!(count_opaque(&outlined_alpha) > count_opaque(&alpha));
+
+    //Minified glyphs are drawn and never opaque where the glyph isn't
+    let (reference, _, alpha) = draw(8, 0.).unwrap();
+    assert!(alpha.iter().any(|a| *a > 0.));
+    alpha
+        .iter()
+        .zip(reference.alpha.iter())
+        .for_each(|(a, reference)| {
+            assert!(*a < 0.5 || *reference > 0.);
+        });
+}
+
+#[test]
+fn test() {
+    test_glyph_bitmap();
+    test_sdf();
+    #[cfg(not(target_arch = "wasm32"))]
+    test_gpu_sdf();
+}
@@ -2,7 +2,7 @@ use image::*;
 use inox_math::{Vector2, Vector4};
 use inox_platform::DEFAULT_DPI;
 use inox_serialize::{Deserialize, Serialize};
-use std::path::Path;
+use std::{path::Path, sync::Arc};
 use ttf_parser::*;
 
 use crate::{create_quad_with_texture, Glyph, MeshData, Metrics};
@@ -17,6 +17,9 @@ pub const FONT_PT_TO_PIXEL: f32 = DEFAULT_DPI / (72. * 2048.);
 pub struct FontData {
     metrics: Metrics,
     glyphs: Vec<Glyph>,
+    //Content of the ttf file, kept to lay out and rasterize glyphs on demand
+    #[serde(skip)]
+    face_data: Arc<Vec<u8>>,
 }
 
 #[derive(Clone)]
@@ -52,6 +55,10 @@ impl FontData {
         self.create_mesh_from_text(&data)
     }
 
+    pub fn face(&self) -> Option<Face> {
+        Face::parse(self.face_data.as_slice(), 0).ok()
+    }
+
     #[inline]
     pub fn get_metrics(&self) -> &Metrics {
         &self.metrics
@@ -119,6 +126,7 @@ impl FontData {
         Self {
             metrics: max_glyph_metrics,
             glyphs,
+            face_data: Arc::new(font_data),
         }
     }
 
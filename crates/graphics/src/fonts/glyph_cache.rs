@@ -0,0 +1,197 @@
+use std::collections::HashMap;
+
+use inox_uid::generate_random_resource_uid;
+
+use crate::{
+    FontData, GlyphBitmap, TextureAtlasError, TextureFormat, TextureHandler, TextureId, TextureInfo,
+};
+
+pub const DEFAULT_GLYPH_CACHE_CAPACITY: usize = 512;
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+pub struct GlyphKey {
+    pub character: char,
+    //Pixels per em
+    pub size: u32,
+}
+
+struct CachedGlyph {
+    texture_id: TextureId,
+    texture_info: Option<TextureInfo>,
+    last_used: u64,
+}
+
+//Glyphs rasterized when they're first requested and packed in the texture atlases.
+//When full the least recently used glyph is removed from its atlas to make room for the new one
+pub struct GlyphCache {
+    capacity: usize,
+    sdf_spread: Option<u32>,
+    uses: u64,
+    glyphs: HashMap<GlyphKey, CachedGlyph>,
+    to_upload: Vec<(TextureId, GlyphBitmap)>,
+    to_remove: Vec<TextureId>,
+}
+
+impl Default for GlyphCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_GLYPH_CACHE_CAPACITY)
+    }
+}
+
+impl GlyphCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            sdf_spread: None,
+            uses: 0,
+            glyphs: HashMap::new(),
+            to_upload: Vec::new(),
+            to_remove: Vec::new(),
+        }
+    }
+
+    //Glyphs are rasterized as distance fields with this spread in pixels.
+    //Glyphs already cached are evicted
+    pub fn set_sdf_spread(&mut self, sdf_spread: Option<u32>) -> &mut Self {
+        if self.sdf_spread != sdf_spread {
+            self.sdf_spread = sdf_spread;
+            self.clear();
+        }
+        self
+    }
+    pub fn sdf_spread(&self) -> Option<u32> {
+        self.sdf_spread
+    }
+
+    pub fn clear(&mut self) {
+        self.to_upload.clear();
+        self.to_remove.extend(
+            self.glyphs
+                .drain()
+                .filter(|(_, g)| g.texture_info.is_some())
+                .map(|(_, g)| g.texture_id),
+        );
+    }
+
+    pub fn len(&self) -> usize {
+        self.glyphs.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.glyphs.is_empty()
+    }
+    pub fn contains(&self, key: &GlyphKey) -> bool {
+        self.glyphs.contains_key(key)
+    }
+    //None until the glyph is sent to the gpu with update
+    pub fn texture_info(&self, key: &GlyphKey) -> Option<TextureInfo> {
+        self.glyphs.get(key).and_then(|g| g.texture_info)
+    }
+
+    pub fn request(&mut self, font_data: &FontData, key: GlyphKey) -> TextureId {
+        self.uses += 1;
+        if let Some(glyph) = self.glyphs.get_mut(&key) {
+            glyph.last_used = self.uses;
+            return glyph.texture_id;
+        }
+        while self.glyphs.len() >= self.capacity {
+            self.evict();
+        }
+        let texture_id = generate_random_resource_uid();
+        let sdf_spread = self.sdf_spread;
+        if let Some(bitmap) = font_data.face().and_then(|face| match sdf_spread {
+            Some(spread) => GlyphBitmap::rasterize_sdf(&face, key.character, key.size, spread),
+            None => GlyphBitmap::rasterize(&face, key.character, key.size),
+        }) {
+            self.to_upload.push((texture_id, bitmap));
+        }
+        self.glyphs.insert(
+            key,
+            CachedGlyph {
+                texture_id,
+                texture_info: None,
+                last_used: self.uses,
+            },
+        );
+        texture_id
+    }
+
+    fn evict(&mut self) {
+        if let Some(key) = self
+            .glyphs
+            .iter()
+            .min_by_key(|(_, g)| g.last_used)
+            .map(|(k, _)| *k)
+        {
+            if let Some(glyph) = self.glyphs.remove(&key) {
+                self.to_upload.retain(|(id, _)| *id != glyph.texture_id);
+                if glyph.texture_info.is_some() {
+                    self.to_remove.push(glyph.texture_id);
+                }
+            }
+        }
+    }
+
+    //Evicted glyphs leave the atlases before the new ones are added
+    pub fn update(
+        &mut self,
+        texture_handler: &TextureHandler,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+    ) -> Result<(), TextureAtlasError> {
+        self.to_remove
+            .drain(..)
+            .for_each(|id| texture_handler.remove(&id));
+        while let Some((id, bitmap)) = self.to_upload.pop() {
+            let texture_info = texture_handler.add_image_to_texture_atlas(
+                device,
+                encoder,
+                &id,
+                (bitmap.width, bitmap.height),
+                TextureFormat::Rgba8Unorm,
+                &bitmap.data,
+            )?;
+            if let Some(glyph) = self.glyphs.values_mut().find(|g| g.texture_id == id) {
+                glyph.texture_info = Some(texture_info);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[allow(dead_code)]
+fn test_glyph_cache() {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../../data_raw/fonts/Espera-Regular.ttf");
+    let font_data = FontData::new(path.as_path());
+    let key = |character: char| GlyphKey {
+        character,
+        size: 32,
+    };
+
+    let mut cache = GlyphCache::new(2);
+    let a = cache.request(&font_data, key('A'));
+    cache.request(&font_data, key('B'));
+    assert_eq!(cache.request(&font_data, key('A')), a);
+
+    //B is the least recently used one
+    cache.request(&font_data, key('C'));
+    assert_eq!(cache.len(), 2);
+    assert!(cache.contains(&key('A')) && cache.contains(&key('C')));
+    assert!(!cache.contains(&key('B')));
+    assert_eq!(cache.to_upload.len(), 2);
+
+    //Sizes are cached apart
+    let large_a = GlyphKey {
+        character: 'A',
+        size: 64,
+    };
+    assert_ne!(cache.request(&font_data, large_a), a);
+    assert!(cache.contains(&large_a) && cache.contains(&key('C')));
+    assert!(!cache.contains(&key('A')));
+    assert!(cache.texture_info(&large_a).is_none());
+}
+
+#[test]
+fn test() {
+    test_glyph_cache();
+}
@@ -0,0 +1,95 @@
+use inox_math::{VecBase, Vector2};
+use ttf_parser::{Face, GlyphId};
+
+use crate::FontData;
+
+//Glyph placed on the baseline, y goes up and everything is in the units of the text size
+#[derive(Debug, PartialEq, Clone)]
+pub struct GlyphQuad {
+    pub character: char,
+    pub min: Vector2,
+    pub max: Vector2,
+    //Distance from this glyph to the next one, kerning with it included
+    pub advance: f32,
+}
+
+//Only the legacy kern table is read, kerning in the GPOS table needs text shaping
+pub fn kerning(face: &Face, left: GlyphId, right: GlyphId) -> i16 {
+    face.tables()
+        .kern
+        .and_then(|kern| {
+            kern.subtables
+                .into_iter()
+                .filter(|s| s.horizontal && !s.variable)
+                .find_map(|s| s.glyphs_kerning(left, right))
+        })
+        .unwrap_or_default()
+}
+
+impl FontData {
+    pub fn layout(&self, text: &str, size: f32) -> Vec<GlyphQuad> {
+        let mut quads: Vec<GlyphQuad> = Vec::new();
+        let Some(face) = self.face() else {
+            return quads;
+        };
+        let scale = size / face.units_per_em() as f32;
+        let line_height = (face.ascender() - face.descender() + face.line_gap()) as f32 * scale;
+
+        let mut position = Vector2::default_zero();
+        let mut previous = None;
+        for character in text.chars() {
+            if character == '\n' {
+                position.x = 0.;
+                position.y -= line_height;
+                previous = None;
+                continue;
+            }
+            let glyph_id = face.glyph_index(character).unwrap_or_default();
+            if let (Some(previous), Some(quad)) = (previous, quads.last_mut()) {
+                let kerning = kerning(&face, previous, glyph_id) as f32 * scale;
+                quad.advance += kerning;
+                position.x += kerning;
+            }
+            let (min, max) = match face.glyph_bounding_box(glyph_id) {
+                Some(bb) => (
+                    position + Vector2::new(bb.x_min as f32, bb.y_min as f32) * scale,
+                    position + Vector2::new(bb.x_max as f32, bb.y_max as f32) * scale,
+                ),
+                None => (position, position),
+            };
+            let advance = face.glyph_hor_advance(glyph_id).unwrap_or_default() as f32 * scale;
+            quads.push(GlyphQuad {
+                character,
+                min,
+                max,
+                advance,
+            });
+            position.x += advance;
+            previous = Some(glyph_id);
+        }
+        quads
+    }
+}
+
+#[allow(dead_code)]
+fn test_layout() {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../../data_raw/fonts/Espera-Regular.ttf");
+    let font_data = FontData::new(path.as_path());
+
+    let single = font_data.layout("A", 1.);
+    let pair = font_data.layout("AV", 1.);
+    assert_eq!(pair.len(), 2);
+    assert!(pair[0].advance < single[0].advance);
+
+    //Kerning pairs don't span lines
+    let lines = font_data.layout("A\nV", 1.);
+    assert_eq!(lines[0].advance, single[0].advance);
+    assert!(lines[1].min.y < pair[1].min.y);
+    assert_eq!(font_data.layout("AV", 2.)[0].advance, pair[0].advance * 2.);
+}
+
+#[test]
+fn test() {
+    test_layout();
+}
@@ -1,7 +1,13 @@
+pub use crate::fonts::bitmap::*;
 pub use crate::fonts::font::*;
 pub use crate::fonts::glyph::*;
+pub use crate::fonts::glyph_cache::*;
+pub use crate::fonts::layout::*;
 
+pub mod bitmap;
 pub mod font;
 mod geometry;
 pub mod glyph;
+pub mod glyph_cache;
+pub mod layout;
 mod raster;
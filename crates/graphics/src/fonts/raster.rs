@@ -76,3 +76,46 @@ impl<'a> Raster<'a> {
         }
     }
 }
+
+//Signed distance in pixels of every pixel from the edge of the alpha coverage, positive inside.
+//Pixels crossed by the edge use their coverage to keep the sub pixel position of the edge,
+//the others the distance from the nearest pixel on the other side, up to spread pixels away
+pub fn signed_distance_field(
+    alpha: &[f32],
+    width: usize,
+    height: usize,
+    spread: usize,
+) -> Vec<f32> {
+    let is_inside = |x: isize, y: isize| {
+        x >= 0
+            && y >= 0
+            && (x as usize) < width
+            && (y as usize) < height
+            && alpha[y as usize * width + x as usize] >= 0.5
+    };
+    let max_distance = spread as f32;
+    let spread = spread as isize;
+    let mut field = vec![0.; width * height];
+    for y in 0..height {
+        for x in 0..width {
+            let a = alpha[y * width + x];
+            if a > 0. && a < 1. {
+                field[y * width + x] = a - 0.5;
+                continue;
+            }
+            let (x, y) = (x as isize, y as isize);
+            let inside = is_inside(x, y);
+            let mut min_squared_distance = (max_distance + 0.5) * (max_distance + 0.5);
+            for dy in -spread..=spread {
+                for dx in -spread..=spread {
+                    if is_inside(x + dx, y + dy) != inside {
+                        min_squared_distance = min_squared_distance.min((dx * dx + dy * dy) as f32);
+                    }
+                }
+            }
+            let distance = (min_squared_distance.sqrt() - 0.5).min(max_distance);
+            field[y as usize * width + x as usize] = if inside { distance } else { -distance };
+        }
+    }
+    field
+}
@@ -1,3 +1,5 @@
+use std::sync::RwLock;
+
 use inox_platform::{PlatformType, PLATFORM_TYPE_PC};
 #[cfg(target_arch = "wasm32")]
 pub use wasm::*;
@@ -20,6 +22,24 @@ pub fn shader_preprocessor_defs<const PLATFORM_TYPE: PlatformType>() -> Vec<Stri
 pub fn has_primitive_index_support() -> bool {
     required_gpu_features().contains(wgpu::Features::SHADER_PRIMITIVE_INDEX)
 }
-pub fn is_indirect_mode_enabled() -> bool {
-    required_gpu_features().contains(wgpu::Features::MULTI_DRAW_INDIRECT)
-}
\ No newline at end of file
+pub fn device_features(adapter: &wgpu::Adapter) -> wgpu::Features {
+    required_gpu_features() | (optional_gpu_features() & adapter.features())
+}
+
+//Features of the created device, for the resources loaded without access to it
+static CREATED_DEVICE_FEATURES: RwLock<wgpu::Features> = RwLock::new(wgpu::Features::empty());
+
+pub fn set_created_device_features(features: wgpu::Features) {
+    *CREATED_DEVICE_FEATURES.write().unwrap() = features;
+}
+//Only the required features until the device has been created
+pub fn created_device_features() -> wgpu::Features {
+    required_gpu_features() | *CREATED_DEVICE_FEATURES.read().unwrap()
+}
+pub fn is_indirect_mode_enabled(features: wgpu::Features) -> bool {
+    features.contains(
+        wgpu::Features::INDIRECT_FIRST_INSTANCE
+            | wgpu::Features::MULTI_DRAW_INDIRECT
+            | wgpu::Features::MULTI_DRAW_INDIRECT_COUNT,
+    )
+}
@@ -3,9 +3,6 @@
 pub fn required_gpu_features() -> wgpu::Features {
     wgpu::Features::default()
         | wgpu::Features::POLYGON_MODE_LINE
-        | wgpu::Features::INDIRECT_FIRST_INSTANCE
-        | wgpu::Features::MULTI_DRAW_INDIRECT
-        | wgpu::Features::MULTI_DRAW_INDIRECT_COUNT
         | wgpu::Features::TEXTURE_BINDING_ARRAY
         | wgpu::Features::BUFFER_BINDING_ARRAY
         | wgpu::Features::SAMPLED_TEXTURE_AND_STORAGE_BUFFER_ARRAY_NON_UNIFORM_INDEXING
@@ -18,6 +15,27 @@ pub fn required_gpu_features() -> wgpu::Features {
         | wgpu::Features::CLEAR_TEXTURE
 }
 
-pub fn platform_limits() -> wgpu::Limits {
-    wgpu::Limits::default()
+//Requested only if the adapter has them, otherwise the renderer falls back to direct draws
+//and the compressed textures to their uncompressed version
+pub fn optional_gpu_features() -> wgpu::Features {
+    wgpu::Features::INDIRECT_FIRST_INSTANCE
+        | wgpu::Features::MULTI_DRAW_INDIRECT
+        | wgpu::Features::MULTI_DRAW_INDIRECT_COUNT
+        | wgpu::Features::TEXTURE_COMPRESSION_BC
+        | wgpu::Features::TEXTURE_COMPRESSION_ASTC_LDR
+        | wgpu::Features::TEXTURE_COMPRESSION_ETC2
+}
+
+//Every pass binds the whole array of texture atlases padding it with duplicates,
+//so the huge limits of bindless capable adapters are not requested
+const MAX_SAMPLED_TEXTURES_PER_SHADER_STAGE: u32 = 128;
+
+pub fn platform_limits(adapter: &wgpu::Adapter) -> wgpu::Limits {
+    wgpu::Limits {
+        max_sampled_textures_per_shader_stage: adapter
+            .limits()
+            .max_sampled_textures_per_shader_stage
+            .min(MAX_SAMPLED_TEXTURES_PER_SHADER_STAGE),
+        ..Default::default()
+    }
 }
@@ -4,6 +4,10 @@ pub fn required_gpu_features() -> wgpu::Features {
     wgpu::Features::default() | wgpu::Features::POLYGON_MODE_LINE | wgpu::Features::CLEAR_TEXTURE
 }
 
-pub fn platform_limits() -> wgpu::Limits {
+pub fn optional_gpu_features() -> wgpu::Features {
+    wgpu::Features::empty()
+}
+
+pub fn platform_limits(_adapter: &wgpu::Adapter) -> wgpu::Limits {
     wgpu::Limits::default()
 }
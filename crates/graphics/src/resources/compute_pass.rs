@@ -15,6 +15,7 @@ pub struct ComputePass {
     message_hub: MessageHubRc,
     name: String,
     pipelines: Vec<Resource<ComputePipeline>>,
+    workgroup_size: Option<[u32; 3]>,
     is_initialized: bool,
 }
 
@@ -37,6 +38,7 @@ impl DataTypeResource for ComputePass {
             message_hub: message_hub.clone(),
             name: String::new(),
             pipelines: Vec::new(),
+            workgroup_size: None,
             is_initialized: false,
         }
     }
@@ -55,6 +57,7 @@ impl DataTypeResource for ComputePass {
             message_hub: message_hub.clone(),
             name: data.name.clone(),
             pipelines: Vec::new(),
+            workgroup_size: None,
             is_initialized: false,
         };
         pass.set_pipelines(&data.pipelines);
@@ -82,10 +85,20 @@ impl ComputePass {
         self
     }
 
+    //Overrides the workgroup size of the shaders of all the pipelines of the pass
+    pub fn set_workgroup_size(&mut self, size: [u32; 3]) -> &mut Self {
+        self.workgroup_size = Some(size);
+        self
+    }
+
     pub fn init(&mut self, render_context: &RenderContext, binding_data: &mut BindingData) {
         let mut is_initialized = false;
         binding_data.set_bind_group_layout();
         self.pipelines.iter().for_each(|pipeline| {
+            //Pipelines are replaced once loaded, so it's set again every time
+            if let Some(size) = self.workgroup_size {
+                pipeline.get_mut().set_workgroup_size(size);
+            }
             is_initialized |= pipeline.get_mut().init(render_context, binding_data);
         });
         self.is_initialized = is_initialized;
@@ -126,6 +139,21 @@ impl ComputePass {
                 compute_pass.set_bind_group(index as _, bind_group, &[]);
             });
 
+        {
+            let mut frame_capture = render_context.core.frame_capture.write().unwrap();
+            if frame_capture.is_capturing() {
+                frame_capture.begin_pass(
+                    &self.name,
+                    self.pipelines
+                        .iter()
+                        .map(|p| p.get().path().to_path_buf())
+                        .collect(),
+                    binding_data.bound_buffers(),
+                    binding_data.bound_textures(),
+                );
+            }
+        }
+
         compute_pass
     }
 
@@ -159,6 +187,12 @@ impl ComputePass {
                     "compute_pass::dispatch_workgroups",
                 );
                 compute_pass.dispatch_workgroups(x, y, z);
+                render_context
+                    .core
+                    .frame_capture
+                    .write()
+                    .unwrap()
+                    .add_dispatches(1);
             }
         }
     }
@@ -17,6 +17,7 @@ pub struct ComputePipeline {
     shared_data: SharedDataRc,
     message_hub: MessageHubRc,
     shader: Handle<Shader>,
+    workgroup_size: Option<[u32; 3]>,
     compute_pipeline: Option<wgpu::ComputePipeline>,
 }
 
@@ -27,6 +28,7 @@ impl Clone for ComputePipeline {
             shared_data: self.shared_data.clone(),
             message_hub: self.message_hub.clone(),
             shader: self.shader.clone(),
+            workgroup_size: self.workgroup_size,
             compute_pipeline: None,
         }
     }
@@ -72,6 +74,7 @@ impl DataTypeResource for ComputePipeline {
             shared_data: shared_data.clone(),
             message_hub: message_hub.clone(),
             shader: None,
+            workgroup_size: None,
             compute_pipeline: None,
         }
     }
@@ -104,12 +107,20 @@ impl ComputePipeline {
     ) -> Resource<Shader> {
         Shader::request_load(shared_data, message_hub, data.shader.as_path(), None)
     }
+    pub fn set_workgroup_size(&mut self, size: [u32; 3]) -> &mut Self {
+        self.workgroup_size = Some(size);
+        self
+    }
     pub fn init(&mut self, context: &RenderContext, binding_data: &BindingData) -> bool {
         inox_profiler::scoped_profile!("compute_pipeline::init");
         if self.shader.is_none() {
             return false;
         }
         if let Some(shader) = self.shader.as_ref() {
+            //The shader is reloaded from file without it, so it's set again every time
+            if let Some(size) = self.workgroup_size {
+                shader.get_mut().set_workgroup_size(size);
+            }
             if !shader.get().is_initialized() {
                 if !shader.get_mut().init(context) {
                     return false;
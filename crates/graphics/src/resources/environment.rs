@@ -0,0 +1,214 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
+
+use image::ImageFormat;
+use inox_filesystem::{convert_from_local_path, File};
+use inox_messenger::MessageHubRc;
+use inox_resources::{
+    Data, DataTypeResource, ResourceEvent, ResourceId, ResourceTrait, SerializableResource,
+    SharedDataRc,
+};
+use inox_serialize::{inox_serializable::SerializableRegistryRc, read_from_file, SerializeFile};
+
+use crate::{EnvironmentData, EnvironmentSource, TextureFormat, INVALID_INDEX};
+
+pub type EnvironmentId = ResourceId;
+
+#[derive(Clone)]
+pub struct EnvironmentImage {
+    pub width: u32,
+    pub height: u32,
+    pub format: TextureFormat,
+    pub data: Vec<u8>,
+}
+
+#[derive(Clone)]
+pub struct Environment {
+    id: EnvironmentId,
+    filepath: PathBuf,
+    message_hub: MessageHubRc,
+    data: EnvironmentData,
+    images: Arc<RwLock<Vec<Option<EnvironmentImage>>>>,
+    cubemap_index: i32,
+}
+
+impl ResourceTrait for Environment {
+    fn is_initialized(&self) -> bool {
+        self.cubemap_index != INVALID_INDEX
+    }
+    fn invalidate(&mut self) -> &mut Self {
+        self.cubemap_index = INVALID_INDEX;
+        self
+    }
+}
+
+impl SerializableResource for Environment {
+    fn path(&self) -> &Path {
+        self.filepath.as_path()
+    }
+    fn set_path(&mut self, path: &Path) -> &mut Self {
+        self.filepath = path.to_path_buf();
+        self
+    }
+    fn extension() -> &'static str {
+        EnvironmentData::extension()
+    }
+    fn deserialize_data(
+        path: &Path,
+        registry: &SerializableRegistryRc,
+        f: Box<dyn FnMut(Self::DataType) + 'static>,
+    ) {
+        read_from_file::<Self::DataType>(path, registry, f);
+    }
+}
+
+impl DataTypeResource for Environment {
+    type DataType = EnvironmentData;
+
+    fn new(id: ResourceId, _shared_data: &SharedDataRc, message_hub: &MessageHubRc) -> Self {
+        Self {
+            id,
+            filepath: PathBuf::new(),
+            message_hub: message_hub.clone(),
+            data: EnvironmentData::default(),
+            images: Arc::new(RwLock::new(Vec::new())),
+            cubemap_index: INVALID_INDEX,
+        }
+    }
+
+    fn create_from_data(
+        shared_data: &SharedDataRc,
+        message_hub: &MessageHubRc,
+        id: ResourceId,
+        data: &Self::DataType,
+    ) -> Self
+    where
+        Self: Sized,
+    {
+        let mut environment = Self::new(id, shared_data, message_hub);
+        environment.data = data.clone();
+        environment.load_images();
+        environment
+    }
+}
+
+//Faces of a cubemap are squares all of the same size, returns the size of a face
+pub fn check_cubemap_faces(sizes: &[(u32, u32)]) -> Result<u32, String> {
+    let Some(&(width, _)) = sizes.first() else {
+        return Err("no faces".to_string());
+    };
+    if let Some((width, height)) = sizes.iter().find(|(w, h)| w != h) {
+        return Err(format!("face of {width}x{height} is not square"));
+    }
+    if let Some((size, _)) = sizes.iter().find(|(w, _)| *w != width) {
+        return Err(format!("faces of {width} and {size} have different sizes"));
+    }
+    Ok(width)
+}
+
+impl Environment {
+    fn load_images(&mut self) {
+        let paths = self
+            .data
+            .image_paths()
+            .iter()
+            .map(|p| convert_from_local_path(Data::platform_data_folder().as_path(), p))
+            .collect::<Vec<_>>();
+        let is_hdr = matches!(self.data.source, EnvironmentSource::Equirectangular(_));
+        *self.images.write().unwrap() = vec![None; paths.len()];
+        paths.into_iter().enumerate().for_each(|(index, path)| {
+            let images = self.images.clone();
+            let message_hub = self.message_hub.clone();
+            let id = self.id;
+            let mut file = File::new(path.as_path());
+            file.load(move |bytes| {
+                let image_format = ImageFormat::from_path(path.as_path()).unwrap();
+                let image_data =
+                    image::load_from_memory_with_format(bytes.as_slice(), image_format).unwrap();
+                let image = if is_hdr {
+                    EnvironmentImage {
+                        width: image_data.width(),
+                        height: image_data.height(),
+                        format: TextureFormat::Rgba32Float,
+                        data: image_data
+                            .into_rgba32f()
+                            .iter()
+                            .flat_map(|v| v.to_le_bytes())
+                            .collect(),
+                    }
+                } else {
+                    EnvironmentImage {
+                        width: image_data.width(),
+                        height: image_data.height(),
+                        format: TextureFormat::Rgba8Unorm,
+                        data: image_data.into_rgba8().to_vec(),
+                    }
+                };
+                let mut images = images.write().unwrap();
+                images[index] = Some(image);
+                if images.iter().all(|i| i.is_some()) {
+                    if !is_hdr {
+                        let sizes = images
+                            .iter()
+                            .flatten()
+                            .map(|i| (i.width, i.height))
+                            .collect::<Vec<_>>();
+                        if let Err(e) = check_cubemap_faces(&sizes) {
+                            inox_log::debug_log!("Unable to create cubemap of {:?} - {}", id, e);
+                            //Never loaded, so it's never uploaded to the gpu
+                            images.clear();
+                            return;
+                        }
+                    }
+                    message_hub.send_event(ResourceEvent::<Environment>::Changed(id));
+                }
+            });
+        });
+    }
+    pub fn id(&self) -> &EnvironmentId {
+        &self.id
+    }
+    pub fn data(&self) -> &EnvironmentData {
+        &self.data
+    }
+    pub fn is_loaded(&self) -> bool {
+        let images = self.images.read().unwrap();
+        !images.is_empty() && images.iter().all(|i| i.is_some())
+    }
+    pub fn images(&self) -> Vec<EnvironmentImage> {
+        self.images
+            .read()
+            .unwrap()
+            .iter()
+            .flatten()
+            .cloned()
+            .collect()
+    }
+    pub fn cubemap_index(&self) -> i32 {
+        self.cubemap_index
+    }
+    pub fn set_cubemap_index(&mut self, cubemap_index: usize) -> &mut Self {
+        self.cubemap_index = cubemap_index as _;
+        self
+    }
+}
+
+#[allow(dead_code)]
+fn test_check_cubemap_faces() {
+    assert_eq!(check_cubemap_faces(&[(64, 64); 6]), Ok(64));
+    assert!(check_cubemap_faces(&[]).is_err());
+
+    let mut sizes = [(64, 64); 6];
+    sizes[3] = (64, 32);
+    assert!(check_cubemap_faces(&sizes).is_err());
+    sizes[3] = (32, 32);
+    assert!(check_cubemap_faces(&sizes).is_err());
+    assert!(check_cubemap_faces(&[(64, 32); 6]).is_err());
+}
+
+#[test]
+fn test() {
+    test_check_cubemap_faces();
+}
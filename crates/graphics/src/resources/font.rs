@@ -1,5 +1,6 @@
 use crate::{
-    FontData, Texture, TextureData, TextureFormat, TextureUsage, DEFAULT_FONT_TEXTURE_SIZE,
+    FontData, GlyphQuad, Texture, TextureData, TextureFormat, TextureUsage,
+    DEFAULT_FONT_TEXTURE_SIZE,
 };
 
 use inox_math::Vector4;
@@ -9,7 +10,7 @@ use inox_resources::{
     SharedDataRc,
 };
 use inox_serialize::inox_serializable::SerializableRegistryRc;
-use inox_uid::{generate_random_uid, INVALID_UID};
+use inox_uid::{generate_random_resource_uid, INVALID_RESOURCE_UID};
 use std::path::{Path, PathBuf};
 
 pub type FontId = ResourceId;
@@ -62,7 +63,7 @@ impl DataTypeResource for Font {
         let texture = Texture::new_resource(
             shared_data,
             message_hub,
-            generate_random_uid(),
+            generate_random_resource_uid(),
             &texture_data,
             None,
         );
@@ -98,7 +99,7 @@ impl Font {
         if let Some(font) = SharedData::match_resource(shared_data, |f: &Font| f.path().exists()) {
             return *font.id();
         }
-        INVALID_UID
+        INVALID_RESOURCE_UID
     }
 
     pub fn font_data(&self) -> &FontData {
@@ -107,6 +108,9 @@ impl Font {
     pub fn texture(&self) -> &Handle<Texture> {
         &self.texture
     }
+    pub fn layout(&self, text: &str, size: f32) -> Vec<GlyphQuad> {
+        self.font_data.layout(text, size)
+    }
     pub fn glyph_texture_coord(&self, c: char) -> Vector4 {
         let texture_coord = self.font_data.get_glyph(c as _).texture_coord;
         texture_coord
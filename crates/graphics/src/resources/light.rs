@@ -99,6 +99,16 @@ impl Light {
         self
     }
 
+    //Direction the light is pointing to, used by directional, spot and area lights
+    #[inline]
+    pub fn set_direction(&mut self, direction: Vector3) -> &mut Self {
+        let d = direction.into();
+        if self.data.direction != d {
+            self.data.direction = d;
+            self.mark_as_dirty();
+        }
+        self
+    }
     #[inline]
     pub fn data(&self) -> &LightData {
         &self.data
@@ -2,10 +2,11 @@ use std::path::{Path, PathBuf};
 
 use crate::{MaterialData, Texture, TextureId, TextureType, INVALID_INDEX};
 
+use inox_filesystem::convert_in_local_path;
 use inox_messenger::MessageHubRc;
 use inox_resources::{
-    DataTypeResource, Handle, Resource, ResourceEvent, ResourceId, ResourceTrait,
-    SerializableResource, SharedDataRc,
+    Data, DataTypeResource, Handle, OnCreateData, Resource, ResourceEvent, ResourceId,
+    ResourceTrait, SerializableResource, SharedDataRc,
 };
 use inox_serialize::{inox_serializable::SerializableRegistryRc, read_from_file, SerializeFile};
 
@@ -19,6 +20,7 @@ pub struct Material {
     shared_data: SharedDataRc,
     textures: [Handle<Texture>; TextureType::Count as _],
     material_index: i32,
+    data: MaterialData,
 }
 
 impl ResourceTrait for Material {
@@ -64,6 +66,7 @@ impl DataTypeResource for Material {
             material_index: INVALID_INDEX,
             path: PathBuf::new(),
             textures: Default::default(),
+            data: MaterialData::default(),
         }
     }
 
@@ -79,7 +82,17 @@ impl DataTypeResource for Material {
         let mut textures: [Handle<Texture>; TextureType::Count as _] = Default::default();
         for (i, t) in material_data.textures.iter().enumerate() {
             if !t.as_os_str().is_empty() {
-                let texture = Texture::request_load(shared_data, message_hub, t.as_path(), None);
+                let sampler = material_data.samplers[i];
+                let texture = Texture::request_load(
+                    shared_data,
+                    message_hub,
+                    t.as_path(),
+                    OnCreateData::create(move |texture: &mut Texture| {
+                        texture.set_sampler(sampler);
+                    }),
+                );
+                //Textures already loaded don't call the OnCreateData
+                texture.get_mut().set_sampler(sampler);
                 textures[i] = Some(texture);
             }
         }
@@ -91,6 +104,7 @@ impl DataTypeResource for Material {
             textures,
             material_index: INVALID_INDEX,
             path: PathBuf::new(),
+            data: material_data.clone(),
         }
     }
 }
@@ -112,6 +126,42 @@ impl Material {
         false
     }
 
+    #[inline]
+    pub fn data(&self) -> &MaterialData {
+        &self.data
+    }
+    #[inline]
+    pub fn data_mut(&mut self) -> &mut MaterialData {
+        &mut self.data
+    }
+    //Writes the current data back to the file the material was loaded from,
+    //returns false for materials that were created at runtime
+    pub fn save(&self) -> bool {
+        if self.path.as_os_str().is_empty() {
+            return false;
+        }
+        let mut data = self.data.clone();
+        //Textures could have been changed after the load
+        self.textures.iter().enumerate().for_each(|(i, t)| {
+            if let Some(texture) = t {
+                let path = texture.get().path().to_path_buf();
+                if !path.as_os_str().is_empty() {
+                    data.textures[i] = convert_in_local_path(
+                        path.as_path(),
+                        Data::platform_data_folder().as_path(),
+                    );
+                }
+            } else {
+                data.textures[i] = PathBuf::new();
+            }
+        });
+        data.save_to_file(
+            self.path.as_path(),
+            self.shared_data.serializable_registry(),
+        );
+        true
+    }
+
     pub fn textures(&self) -> &[Handle<Texture>; TextureType::Count as _] {
         &self.textures
     }
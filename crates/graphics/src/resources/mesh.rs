@@ -16,25 +16,47 @@ use inox_serialize::{
 
 pub type MeshId = ResourceId;
 
+//Meshes and views on every layer
+pub const LAYER_MASK_ALL: u32 = u32::MAX;
+
 #[bitmask]
 #[repr(u32)]
 pub enum MeshFlags {
     None = 0,
     Visible = 1,
     Opaque = 1 << 1,
-    Tranparent = 1 << 2,
+    Transparent = 1 << 2,
     Wireframe = 1 << 3,
     Custom = 1 << 4,
+    //Base color multiplied by the vertex colors, whose alpha makes the mesh transparent
+    UseVertexColor = 1 << 5,
+}
+
+#[allow(non_upper_case_globals)]
+impl MeshFlags {
+    #[deprecated(note = "Use MeshFlags::Transparent")]
+    pub const Tranparent: MeshFlags = MeshFlags::Transparent;
 }
 
 #[test]
 fn test_serialize() {
-    let flags = MeshFlags::Visible | MeshFlags::Tranparent;
+    let flags = MeshFlags::Visible | MeshFlags::Transparent;
     let registry = SerializableRegistryRc::default();
     let s = inox_serialize::serialize(&flags, &registry);
     println!("{}", s);
 }
 
+#[test]
+#[allow(deprecated)]
+fn test_transparent_rename() {
+    //Flags are serialized as bits so data written with the old name still maps to the new one
+    let registry = SerializableRegistryRc::default();
+    let flags: MeshFlags = inox_serialize::deserialize("{\"bits\":5}", &registry).unwrap();
+    assert_eq!(flags, MeshFlags::Visible | MeshFlags::Transparent);
+    assert_eq!(MeshFlags::Tranparent, MeshFlags::Transparent);
+    assert_eq!(MeshFlags::Transparent.bits(), 1 << 2);
+}
+
 #[derive(Clone)]
 pub struct Mesh {
     id: MeshId,
@@ -42,10 +64,17 @@ pub struct Mesh {
     shared_data: SharedDataRc,
     path: PathBuf,
     matrix: Matrix4,
+    //Transforms of the other copies of the mesh, drawn with the same geometry and material
+    instances: Vec<Matrix4>,
     material: Handle<Material>,
     flags: MeshFlags,
+    //False when an ancestor of the mesh in the scene hierarchy is hidden
+    is_parent_visible: bool,
+    //Drawn only in the views with at least one of these layers in their mask
+    layer_mask: u32,
     min: Vector3,
     max: Vector3,
+    has_vertex_alpha: bool,
 }
 
 impl ResourceTrait for Mesh {
@@ -91,10 +120,14 @@ impl DataTypeResource for Mesh {
             message_hub: message_hub.clone(),
             path: PathBuf::new(),
             matrix: Matrix4::default_identity(),
+            instances: Vec::new(),
             material: None,
             flags: MeshFlags::Visible | MeshFlags::Opaque,
+            is_parent_visible: true,
+            layer_mask: LAYER_MASK_ALL,
             min: Vector3::default_zero(),
             max: Vector3::default_zero(),
+            has_vertex_alpha: false,
         }
     }
 
@@ -118,6 +151,10 @@ impl DataTypeResource for Mesh {
         mesh.material = material;
         mesh.min = data.aabb_min;
         mesh.max = data.aabb_max;
+        mesh.has_vertex_alpha = data.has_vertex_alpha();
+        if data.has_vertex_colors() {
+            mesh.flags |= MeshFlags::UseVertexColor;
+        }
         mesh
     }
 }
@@ -138,6 +175,25 @@ impl Mesh {
         }
         self
     }
+    pub fn set_instances(&mut self, instances: Vec<Matrix4>) -> &mut Self {
+        if self.instances != instances {
+            self.instances = instances;
+            self.mark_as_dirty();
+        }
+        self
+    }
+    pub fn add_instance(&mut self, transform: Matrix4) -> &mut Self {
+        self.instances.push(transform);
+        self.mark_as_dirty();
+        self
+    }
+    pub fn instances(&self) -> &[Matrix4] {
+        &self.instances
+    }
+    //The mesh itself is the first instance
+    pub fn instance_count(&self) -> usize {
+        1 + self.instances.len()
+    }
     pub fn set_material(&mut self, material: Resource<Material>) -> &mut Self {
         if self.material.is_none() || self.material.as_ref().unwrap().id() != material.id() {
             self.material = Some(material);
@@ -155,6 +211,12 @@ impl Mesh {
         &self.material
     }
     pub fn set_mesh_data(&mut self, mesh_data: MeshData) -> &mut Self {
+        self.min = mesh_data.aabb_min;
+        self.max = mesh_data.aabb_max;
+        self.has_vertex_alpha = mesh_data.has_vertex_alpha();
+        if mesh_data.has_vertex_colors() {
+            self.flags |= MeshFlags::UseVertexColor;
+        }
         self.message_hub
             .send_event(DataTypeResourceEvent::<Self>::Loaded(self.id, mesh_data));
         self.mark_as_dirty();
@@ -192,7 +254,109 @@ impl Mesh {
         }
         self
     }
+    pub fn set_parent_visible(&mut self, is_parent_visible: bool) -> &mut Self {
+        if self.is_parent_visible != is_parent_visible {
+            self.is_parent_visible = is_parent_visible;
+            self.mark_as_dirty();
+        }
+        self
+    }
+    pub fn is_parent_visible(&self) -> bool {
+        self.is_parent_visible
+    }
+    //True when some vertex colors are not fully opaque
+    pub fn has_vertex_alpha(&self) -> bool {
+        self.has_vertex_alpha
+    }
+    //Flags selecting the passes that draw the mesh: without Visible when one of its ancestors
+    //is hidden and without the ones changing only how the mesh is shaded
+    pub fn effective_flags(&self) -> MeshFlags {
+        let flags = self.flags & !MeshFlags::UseVertexColor;
+        if self.is_parent_visible {
+            flags
+        } else {
+            flags & !MeshFlags::Visible
+        }
+    }
+    pub fn layer_mask(&self) -> u32 {
+        self.layer_mask
+    }
+    pub fn set_layer_mask(&mut self, layer_mask: u32) -> &mut Self {
+        if self.layer_mask != layer_mask {
+            self.layer_mask = layer_mask;
+            self.mark_as_dirty();
+        }
+        self
+    }
     pub fn matrix(&self) -> Matrix4 {
         self.matrix
     }
 }
+
+#[test]
+fn test_handoff_keeps_mesh() {
+    use crate::{register_resource_types, unregister_resource_types};
+    use inox_messenger::MessageHub;
+    use inox_uid::generate_random_resource_uid;
+    use std::sync::Arc;
+
+    let shared_data = SharedDataRc::default();
+    let message_hub = Arc::new(MessageHub::default());
+    register_resource_types(&shared_data, &message_hub);
+
+    let id = generate_random_resource_uid();
+    let mesh =
+        shared_data.add_resource(&message_hub, id, Mesh::new(id, &shared_data, &message_hub));
+    mesh.get_mut().set_layer_mask(1 << 3);
+
+    //The plugin registering the mesh type is unloaded and loaded again
+    shared_data.begin_handoff();
+    unregister_resource_types(&shared_data, &message_hub);
+    shared_data.flush_resources(&message_hub);
+    register_resource_types(&shared_data, &message_hub);
+    shared_data.end_handoff();
+    shared_data.flush_resources(&message_hub);
+
+    let restored = shared_data.get_resource::<Mesh>(&id).unwrap();
+    assert_eq!(restored.get().layer_mask(), 1 << 3);
+    restored.get_mut().set_layer_mask(LAYER_MASK_ALL);
+    assert_eq!(mesh.get().layer_mask(), LAYER_MASK_ALL);
+
+    //Without a handoff the meshes are dropped with their type
+    unregister_resource_types(&shared_data, &message_hub);
+    register_resource_types(&shared_data, &message_hub);
+    assert!(shared_data.get_resource::<Mesh>(&id).is_none());
+
+    unregister_resource_types(&shared_data, &message_hub);
+}
+
+#[test]
+fn test_vertex_colors() {
+    use inox_math::Vector4;
+    use inox_messenger::MessageHub;
+    use inox_uid::generate_random_resource_uid;
+    use std::sync::Arc;
+
+    let shared_data = SharedDataRc::default();
+    let message_hub = Arc::new(MessageHub::default());
+
+    let mut mesh_data = MeshData {
+        colors: vec![u32::MAX; 3],
+        ..Default::default()
+    };
+    let id = generate_random_resource_uid();
+    let mesh = Mesh::create_from_data(&shared_data, &message_hub, id, &mesh_data);
+    //White vertices don't need the vertex colors
+    assert!(!mesh.has_flags(MeshFlags::UseVertexColor));
+    assert!(!mesh.has_vertex_alpha());
+
+    mesh_data.set_vertex_color(Vector4::new(1., 0., 0., 0.5));
+    let mesh = Mesh::create_from_data(&shared_data, &message_hub, id, &mesh_data);
+    assert!(mesh.has_flags(MeshFlags::UseVertexColor));
+    assert!(mesh.has_vertex_alpha());
+    //Passes select the meshes by flags, so the shading flags are not part of them
+    assert_eq!(
+        mesh.effective_flags(),
+        MeshFlags::Visible | MeshFlags::Opaque
+    );
+}
@@ -5,6 +5,7 @@ use inox_resources::SharedDataRc;
 
 pub use self::compute_pass::*;
 pub use self::compute_pipeline::*;
+pub use self::environment::*;
 pub use self::font::*;
 pub use self::light::*;
 pub use self::material::*;
@@ -17,6 +18,7 @@ pub use self::view::*;
 
 pub mod compute_pass;
 pub mod compute_pipeline;
+pub mod environment;
 pub mod font;
 pub mod light;
 pub mod material;
@@ -39,9 +41,11 @@ pub fn register_resource_types(shared_data: &SharedDataRc, message_hub: &Message
     shared_data.register_type_serializable::<Texture>(message_hub);
     shared_data.register_type::<View>(message_hub);
     shared_data.register_type_serializable::<Light>(message_hub);
+    shared_data.register_type_serializable::<Environment>(message_hub);
 }
 
 pub fn unregister_resource_types(shared_data: &SharedDataRc, message_hub: &MessageHubRc) {
+    shared_data.unregister_type_serializable::<Environment>(message_hub);
     shared_data.unregister_type_serializable::<Light>(message_hub);
     shared_data.unregister_type::<View>(message_hub);
     shared_data.unregister_type_serializable::<Texture>(message_hub);
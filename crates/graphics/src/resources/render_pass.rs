@@ -1,5 +1,6 @@
 use std::{collections::HashMap, ops::Range, path::Path};
 
+use inox_math::Vector4;
 use inox_messenger::MessageHubRc;
 use inox_resources::{
     DataTypeResource, Handle, Resource, ResourceId, ResourceTrait, SerializableResource,
@@ -7,20 +8,29 @@ use inox_resources::{
 };
 
 use crate::{
-    gpu_texture::GpuTexture, platform::is_indirect_mode_enabled, AsBinding, BindingData, BufferId,
-    CommandBuffer, DrawCommandType, GpuBuffer, LoadOperation, RenderContext, RenderCoreContextRc,
-    RenderMode, RenderPassData, RenderPipeline, RenderTarget, StoreOperation, Texture, TextureId,
-    TextureUsage, TextureView, VertexBufferLayoutBuilder,
+    gpu_texture::GpuTexture, AsBinding, BindingData, BufferId, CommandBuffer, DrawCommandType,
+    GpuBuffer, LoadOperation, MeshFlags, RenderContext, RenderCoreContextRc, RenderMode,
+    RenderPassData, RenderPipeline, RenderTarget, StoreOperation, Texture, TextureAccess,
+    TextureId, TextureUsage, TextureView, VertexBufferLayoutBuilder, ViewSubmission,
 };
 
 pub type RenderPassId = ResourceId;
 
+pub const DEFAULT_CLEAR_COLOR: Vector4 = Vector4 {
+    x: 0.,
+    y: 0.,
+    z: 0.,
+    w: 1.,
+};
+pub const DEFAULT_CLEAR_DEPTH: f32 = 1.;
+
 pub struct RenderPassBeginData<'a> {
     pub render_core_context: &'a RenderCoreContextRc,
     pub render_targets: &'a [GpuTexture],
     pub buffers: &'a HashMap<BufferId, GpuBuffer>,
     pub surface_view: &'a TextureView,
     pub command_buffer: &'a mut CommandBuffer,
+    pub view_submission: Option<ViewSubmission>,
 }
 
 #[derive(Clone)]
@@ -32,10 +42,13 @@ pub struct RenderPass {
     store_color: StoreOperation,
     load_depth: LoadOperation,
     store_depth: StoreOperation,
+    clear_color: Vector4,
+    clear_depth: f32,
     render_mode: RenderMode,
     pipeline: Handle<RenderPipeline>,
     render_textures: Vec<Resource<Texture>>,
     depth_texture: Handle<Texture>,
+    mesh_flags: Option<MeshFlags>,
 }
 
 impl ResourceTrait for RenderPass {
@@ -59,10 +72,13 @@ impl DataTypeResource for RenderPass {
             store_color: StoreOperation::DontCare,
             load_depth: LoadOperation::DontCare,
             store_depth: StoreOperation::DontCare,
+            clear_color: DEFAULT_CLEAR_COLOR,
+            clear_depth: DEFAULT_CLEAR_DEPTH,
             render_mode: RenderMode::Indirect,
             pipeline: None,
             render_textures: Vec::new(),
             depth_texture: None,
+            mesh_flags: None,
         }
     }
 
@@ -83,10 +99,13 @@ impl DataTypeResource for RenderPass {
             store_color: data.store_color,
             load_depth: data.load_depth,
             store_depth: data.store_depth,
+            clear_color: DEFAULT_CLEAR_COLOR,
+            clear_depth: DEFAULT_CLEAR_DEPTH,
             render_mode: data.render_mode,
             pipeline: None,
             render_textures: Vec::new(),
             depth_texture: None,
+            mesh_flags: None,
         };
         pass.add_render_target(data.render_target)
             .add_depth_target(data.depth_target)
@@ -102,6 +121,15 @@ impl RenderPass {
     pub fn pipeline(&self) -> &Resource<RenderPipeline> {
         self.pipeline.as_ref().unwrap()
     }
+    //Flags of the meshes to draw, by default the ones of the pipeline
+    pub fn mesh_flags(&self) -> MeshFlags {
+        self.mesh_flags
+            .unwrap_or_else(|| self.pipeline().get().data().mesh_flags)
+    }
+    pub fn set_mesh_flags(&mut self, mesh_flags: MeshFlags) -> &mut Self {
+        self.mesh_flags = Some(mesh_flags);
+        self
+    }
     pub fn set_pipeline(&mut self, path: &Path) -> &mut Self {
         if !path.as_os_str().is_empty() {
             let pipeline =
@@ -134,6 +162,17 @@ impl RenderPass {
 
         self
     }
+    //Replaces the texture of a render target with a new one, i.e. to resize it
+    pub fn set_render_target(&mut self, index: usize, render_target: RenderTarget) -> &mut Self {
+        let count = self.render_textures.len();
+        if index < count {
+            self.add_render_target(render_target);
+            if self.render_textures.len() > count {
+                self.render_textures.swap_remove(index);
+            }
+        }
+        self
+    }
     pub fn add_render_target_from_texture(&mut self, texture: &Resource<Texture>) -> &mut Self {
         self.render_textures.push(texture.clone());
         self
@@ -179,6 +218,32 @@ impl RenderPass {
     pub fn depth_texture_id(&self) -> Option<&TextureId> {
         self.depth_texture.as_ref().map(|t| t.id())
     }
+    //Attachments loaded at the beginning of the pass keep what previous passes wrote
+    pub fn read_textures(&self) -> Vec<(TextureId, TextureAccess)> {
+        let mut textures = Vec::new();
+        if self.load_color == LoadOperation::Load {
+            self.render_textures
+                .iter()
+                .for_each(|t| textures.push((*t.id(), TextureAccess::ColorTarget)));
+        }
+        if self.load_depth == LoadOperation::Load {
+            if let Some(depth_texture) = &self.depth_texture {
+                textures.push((*depth_texture.id(), TextureAccess::DepthTarget));
+            }
+        }
+        textures
+    }
+    pub fn written_textures(&self) -> Vec<(TextureId, TextureAccess)> {
+        let mut textures = self
+            .render_textures
+            .iter()
+            .map(|t| (*t.id(), TextureAccess::ColorTarget))
+            .collect::<Vec<_>>();
+        if let Some(depth_texture) = &self.depth_texture {
+            textures.push((*depth_texture.id(), TextureAccess::DepthTarget));
+        }
+        textures
+    }
     pub fn set_load_color_operation(&mut self, load_color: LoadOperation) -> &mut Self {
         self.load_color = load_color;
         self
@@ -195,6 +260,21 @@ impl RenderPass {
         self.store_depth = store_depth;
         self
     }
+    pub fn clear_color(&self) -> Vector4 {
+        self.clear_color
+    }
+    //Used only when the color load operation is not Load, read every time the pass begins
+    pub fn set_clear_color(&mut self, clear_color: Vector4) -> &mut Self {
+        self.clear_color = clear_color;
+        self
+    }
+    pub fn clear_depth(&self) -> f32 {
+        self.clear_depth
+    }
+    pub fn set_clear_depth(&mut self, clear_depth: f32) -> &mut Self {
+        self.clear_depth = clear_depth;
+        self
+    }
 
     pub fn init(
         &mut self,
@@ -237,7 +317,12 @@ impl RenderPass {
         wgpu::Operations {
             load: match &self.load_color {
                 LoadOperation::Load => wgpu::LoadOp::Load,
-                _ => wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                _ => wgpu::LoadOp::Clear(wgpu::Color {
+                    r: self.clear_color.x as _,
+                    g: self.clear_color.y as _,
+                    b: self.clear_color.z as _,
+                    a: self.clear_color.w as _,
+                }),
             },
             store: matches!(&self.store_color, StoreOperation::Store),
         }
@@ -247,7 +332,7 @@ impl RenderPass {
         wgpu::Operations {
             load: match &self.load_depth {
                 LoadOperation::Load => wgpu::LoadOp::Load,
-                _ => wgpu::LoadOp::Clear(1.),
+                _ => wgpu::LoadOp::Clear(self.clear_depth),
             },
             store: matches!(&self.store_depth, StoreOperation::Store),
         }
@@ -270,8 +355,23 @@ impl RenderPass {
         let mut depth_target_view = None;
 
         let render_textures = self.render_textures_id();
+        let mut color_operations = self.color_operations();
+        let mut target_size = {
+            let config = render_pass_begin_data
+                .render_core_context
+                .config
+                .read()
+                .unwrap();
+            (config.width, config.height)
+        };
         if render_textures.is_empty() {
             render_targets_views.push(render_pass_begin_data.surface_view.as_wgpu());
+            //Views after the first one must not clear what the previous ones drew
+            if let Some(view_submission) = &render_pass_begin_data.view_submission {
+                if view_submission.load_surface {
+                    color_operations.load = wgpu::LoadOp::Load;
+                }
+            }
         } else {
             render_textures.iter().for_each(|&id| {
                 if let Some(texture) = render_pass_begin_data
@@ -280,6 +380,8 @@ impl RenderPass {
                     .find(|t| t.id() == id)
                 {
                     render_targets_views.push(texture.view().as_wgpu());
+                    target_size.0 = target_size.0.min(texture.width());
+                    target_size.1 = target_size.1.min(texture.height());
                 }
             });
         }
@@ -293,7 +395,6 @@ impl RenderPass {
             }
         }
 
-        let color_operations = self.color_operations();
         let depth_write_enabled = pipeline.data().depth_write_enabled;
 
         let label = format!("RenderPass {}", self.name);
@@ -333,6 +434,10 @@ impl RenderPass {
                     }),
                 })
         };
+        if let Some(view_submission) = &render_pass_begin_data.view_submission {
+            let scissor = view_submission.scissor.clamp(target_size.0, target_size.1);
+            render_pass.set_scissor_rect(scissor.x, scissor.y, scissor.width, scissor.height);
+        }
         {
             binding_data.set_bind_groups();
 
@@ -358,6 +463,21 @@ impl RenderPass {
             );
             render_pass.set_pipeline(pipeline.render_pipeline());
         }
+        {
+            let mut frame_capture = render_pass_begin_data
+                .render_core_context
+                .frame_capture
+                .write()
+                .unwrap();
+            if frame_capture.is_capturing() {
+                frame_capture.begin_pass(
+                    &self.name,
+                    vec![pipeline.path().to_path_buf()],
+                    binding_data.bound_buffers(),
+                    binding_data.bound_textures(),
+                );
+            }
+        }
 
         let num_vertex_buffers = binding_data.vertex_buffers_count();
         for i in 0..num_vertex_buffers {
@@ -394,9 +514,10 @@ impl RenderPass {
     pub fn draw_meshlets(&self, render_context: &RenderContext, mut render_pass: wgpu::RenderPass) {
         inox_profiler::scoped_profile!("render_pass::draw_meshlets");
 
-        let mesh_flags = self.pipeline().get().data().mesh_flags;
+        let mesh_flags = self.mesh_flags();
         let meshlets = render_context.render_buffers.meshlets.read().unwrap();
         let meshlets = meshlets.data();
+        let mut draw_count = 0;
         render_context
             .render_buffers
             .meshes
@@ -412,7 +533,7 @@ impl RenderPass {
                 {
                     if flags == &mesh_flags {
                         inox_profiler::scoped_profile!("render_pass::draw_mesh");
-                        for i in mesh.meshlets_offset..mesh.meshlets_offset + mesh.meshlets_count {
+                        for i in mesh.meshlet_indices() {
                             inox_profiler::scoped_profile!("render_pass::draw_indexed");
                             inox_profiler::gpu_scoped_profile!(
                                 &mut render_pass,
@@ -429,10 +550,56 @@ impl RenderPass {
                                 mesh.vertex_offset as _,
                                 i as _..(i + 1),
                             );
+                            draw_count += 1;
                         }
                     }
                 }
             });
+        render_context
+            .core
+            .frame_capture
+            .write()
+            .unwrap()
+            .add_draws(draw_count);
+    }
+
+    //Issues one draw for each of the commands the indirect draw would have read from the gpu
+    pub fn draw_commands(
+        &self,
+        render_context: &RenderContext,
+        draw_commands_type: DrawCommandType,
+        mut render_pass: wgpu::RenderPass,
+    ) {
+        inox_profiler::scoped_profile!("render_pass::draw_commands");
+
+        let mesh_flags = self.mesh_flags();
+        let commands = render_context.render_buffers.commands.read().unwrap();
+        let Some(commands) = commands
+            .get(&mesh_flags)
+            .and_then(|c| c.map.get(&draw_commands_type))
+        else {
+            return;
+        };
+        let mut draw_count = 0;
+        commands.commands.for_each_data(|_, _, command| {
+            inox_profiler::gpu_scoped_profile!(
+                &mut render_pass,
+                &render_context.core.device,
+                "render_pass::draw_indexed",
+            );
+            render_pass.draw_indexed(
+                command.base_index..command.base_index + command.vertex_count,
+                command.vertex_offset,
+                command.base_instance..command.base_instance + command.instance_count,
+            );
+            draw_count += 1;
+        });
+        render_context
+            .core
+            .frame_capture
+            .write()
+            .unwrap()
+            .add_draws(draw_count);
     }
 
     pub fn indirect_indexed_draw<'a>(
@@ -444,8 +611,12 @@ impl RenderPass {
     ) {
         inox_profiler::scoped_profile!("render_pass::indirect_draw");
 
-        if is_indirect_mode_enabled() && self.render_mode == RenderMode::Indirect {
-            let mesh_flags = self.pipeline().get().data().mesh_flags;
+        if self.render_mode == RenderMode::Indirect {
+            if !render_context.core.is_indirect_mode_enabled() {
+                self.draw_commands(render_context, draw_commands_type, render_pass);
+                return;
+            }
+            let mesh_flags = self.mesh_flags();
             if let Some(commands) = render_context
                 .render_buffers
                 .commands
@@ -471,6 +642,12 @@ impl RenderPass {
                                     0,
                                     commands.commands.item_count() as _,
                                 );
+                                render_context
+                                    .core
+                                    .frame_capture
+                                    .write()
+                                    .unwrap()
+                                    .add_draws(commands.commands.item_count() as _);
                                 return;
                             }
                         }
@@ -497,14 +674,21 @@ impl RenderPass {
             "render_pass::draw",
         );
         render_pass.draw(vertices, instances);
+        render_context
+            .core
+            .frame_capture
+            .write()
+            .unwrap()
+            .add_draws(1);
     }
 
     pub fn draw_meshes(&self, render_context: &RenderContext, mut render_pass: wgpu::RenderPass) {
         inox_profiler::scoped_profile!("render_pass::draw_meshes");
 
-        let mesh_flags = self.pipeline().get().data().mesh_flags;
+        let mesh_flags = self.mesh_flags();
         let meshlets = render_context.render_buffers.meshlets.read().unwrap();
         let meshlets = meshlets.data();
+        let mut draw_count = 0;
         render_context
             .render_buffers
             .meshes
@@ -521,7 +705,7 @@ impl RenderPass {
                     if flags == &mesh_flags {
                         let start = mesh.indices_offset;
                         let mut end = start;
-                        for i in mesh.meshlets_offset..mesh.meshlets_offset + mesh.meshlets_count {
+                        for i in mesh.meshlet_indices() {
                             let meshlet = &meshlets[i as usize];
                             end += meshlet.indices_count;
                         }
@@ -535,8 +719,56 @@ impl RenderPass {
                             mesh.vertex_offset as _,
                             index as _..(index as u32 + 1),
                         );
+                        draw_count += 1;
                     }
                 }
             });
+        render_context
+            .core
+            .frame_capture
+            .write()
+            .unwrap()
+            .add_draws(draw_count);
     }
 }
+
+#[allow(dead_code)]
+fn test_clear_values() {
+    use inox_messenger::MessageHub;
+    use inox_uid::generate_random_resource_uid;
+    use std::sync::Arc;
+
+    let shared_data = SharedDataRc::default();
+    let message_hub = Arc::new(MessageHub::default());
+    let mut pass = RenderPass::new(generate_random_resource_uid(), &shared_data, &message_hub);
+    pass.set_load_color_operation(LoadOperation::Clear)
+        .set_load_depth_operation(LoadOperation::Clear);
+    assert_eq!(
+        pass.color_operations().load,
+        wgpu::LoadOp::Clear(wgpu::Color::BLACK)
+    );
+    assert_eq!(pass.depth_operations().load, wgpu::LoadOp::Clear(1.));
+
+    pass.set_clear_color(Vector4::new(0.5, 0.25, 1., 1.))
+        .set_clear_depth(0.);
+    assert_eq!(
+        pass.color_operations().load,
+        wgpu::LoadOp::Clear(wgpu::Color {
+            r: 0.5,
+            g: 0.25,
+            b: 1.,
+            a: 1.,
+        })
+    );
+    assert_eq!(pass.depth_operations().load, wgpu::LoadOp::Clear(0.));
+
+    //Clear values are kept while loading
+    pass.set_load_color_operation(LoadOperation::Load);
+    assert_eq!(pass.color_operations().load, wgpu::LoadOp::Load);
+    assert_eq!(pass.clear_color(), Vector4::new(0.5, 0.25, 1., 1.));
+}
+
+#[test]
+fn test() {
+    test_clear_values();
+}
@@ -9,7 +9,7 @@ use inox_resources::{
 use inox_serialize::{inox_serializable::SerializableRegistryRc, read_from_file, SerializeFile};
 
 use crate::{
-    BindingData, RenderContext, RenderPipelineData, Shader, TextureFormat,
+    BindingData, CompareFunction, RenderContext, RenderPipelineData, Shader, TextureFormat,
     VertexBufferLayoutBuilder, FRAGMENT_SHADER_ENTRY_POINT, SHADER_ENTRY_POINT,
     VERTEX_SHADER_ENTRY_POINT,
 };
@@ -118,6 +118,12 @@ impl RenderPipeline {
     pub fn data(&self) -> &RenderPipelineData {
         &self.data
     }
+    //The pipeline will be recreated with the new depth compare function on next init
+    pub fn set_depth_compare(&mut self, depth_compare: CompareFunction) -> &mut Self {
+        self.data.depth_compare = depth_compare;
+        self.invalidate();
+        self
+    }
     pub fn render_pipeline(&self) -> &wgpu::RenderPipeline {
         self.render_pipeline.as_ref().unwrap()
     }
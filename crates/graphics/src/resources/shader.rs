@@ -29,6 +29,55 @@ pub const SHADER_ENTRY_POINT: &str = "main";
 pub const VERTEX_SHADER_ENTRY_POINT: &str = "vs_main";
 pub const FRAGMENT_SHADER_ENTRY_POINT: &str = "fs_main";
 
+//Replaces the workgroup size declared by the entry points of a wgsl shader
+pub fn wgsl_with_workgroup_size(code: &str, size: [u32; 3]) -> String {
+    const ATTRIBUTE: &str = "@workgroup_size(";
+    let mut result = String::with_capacity(code.len());
+    let mut rest = code;
+    while let Some(start) = rest.find(ATTRIBUTE) {
+        let (before, after) = rest.split_at(start + ATTRIBUTE.len());
+        result.push_str(before);
+        result.push_str(&format!("{}, {}, {}", size[0], size[1], size[2]));
+        rest = &after[after.find(')').unwrap_or(after.len())..];
+    }
+    result.push_str(rest);
+    result
+}
+
+//Reads a wgsl shader from the disk with its imports replaced by the included files
+pub fn wgsl_with_imports(path: &Path) -> String {
+    wgsl_code_with_imports(&std::fs::read_to_string(path).unwrap(), path)
+}
+
+//Replaces the imports of a wgsl code with the files next to the given path.
+//No feature is defined, so only the #else branches of the #ifdef blocks are kept
+pub fn wgsl_code_with_imports(code: &str, path: &Path) -> String {
+    let mut should_skip = false;
+    code.lines()
+        .filter_map(|line| {
+            let line_trimmed = line.trim();
+            if line_trimmed.starts_with("#ifdef") {
+                should_skip = true;
+                None
+            } else if line_trimmed.starts_with("#else") {
+                should_skip = !should_skip;
+                None
+            } else if line_trimmed.starts_with("#endif") {
+                should_skip = false;
+                None
+            } else if should_skip {
+                None
+            } else if let Some(import) = line_trimmed.strip_prefix("#import") {
+                Some(wgsl_with_imports(
+                    &path.with_file_name(import.trim().trim_matches('"')),
+                ))
+            } else {
+                Some(format!("{line}\n"))
+            }
+        })
+        .collect()
+}
+
 pub fn is_shader(path: &Path) -> bool {
     path.extension().unwrap() == SHADER_EXTENSION_SPV
         || path.extension().unwrap() == SHADER_EXTENSION_WGSL
@@ -72,6 +121,7 @@ pub fn read_spirv_from_bytes<Data: ::std::io::Read + ::std::io::Seek>(
 pub struct Shader {
     path: PathBuf,
     data: ShaderData,
+    workgroup_size: Option<[u32; 3]>,
     module: Option<ShaderModule>,
 }
 
@@ -80,6 +130,7 @@ impl Clone for Shader {
         Self {
             path: self.path.clone(),
             data: self.data.clone(),
+            workgroup_size: self.workgroup_size,
             module: None,
         }
     }
@@ -124,6 +175,7 @@ impl DataTypeResource for Shader {
         Self {
             path: PathBuf::new(),
             data: ShaderData::default(),
+            workgroup_size: None,
             module: None,
         }
     }
@@ -144,6 +196,14 @@ impl DataTypeResource for Shader {
 }
 
 impl Shader {
+    //Only wgsl code can be patched, spirv keeps the workgroup size it was compiled with
+    pub fn set_workgroup_size(&mut self, size: [u32; 3]) -> &mut Self {
+        if self.workgroup_size != Some(size) {
+            self.workgroup_size = Some(size);
+            self.module = None;
+        }
+        self
+    }
     pub fn init(&mut self, context: &RenderContext) -> bool {
         if self.module.is_none() {
             inox_profiler::scoped_profile!("shader::init({:?})", self.path);
@@ -168,13 +228,17 @@ impl Shader {
                         });
                 self.module = Some(module);
             } else if !self.data.wgsl_code.is_empty() {
+                let code = match self.workgroup_size {
+                    Some(size) => wgsl_with_workgroup_size(&self.data.wgsl_code, size),
+                    None => self.data.wgsl_code.clone(),
+                };
                 let module =
                     context
                         .core
                         .device
                         .create_shader_module(wgpu::ShaderModuleDescriptor {
                             label: Some(shader_name.as_str()),
-                            source: wgpu::ShaderSource::Wgsl(self.data.wgsl_code.clone().into()),
+                            source: wgpu::ShaderSource::Wgsl(code.into()),
                         });
                 self.module = Some(module);
             }
@@ -2,6 +2,7 @@ use std::path::{Path, PathBuf};
 
 use image::ImageFormat;
 use inox_filesystem::{convert_from_local_path, File};
+use inox_log::debug_log;
 
 use inox_messenger::MessageHubRc;
 use inox_resources::{
@@ -9,12 +10,25 @@ use inox_resources::{
     SerializableResource, SharedData, SharedDataRc,
 };
 use inox_serialize::inox_serializable::SerializableRegistryRc;
-use inox_uid::generate_random_uid;
+use inox_uid::generate_random_resource_uid;
 
-use crate::{TextureData, TextureFormat, TextureUsage, INVALID_INDEX};
+use crate::{
+    is_ktx2, load_ktx2, platform::created_device_features, SamplerData, TextureData, TextureFormat,
+    TextureUsage, INVALID_INDEX, KTX2_EXTENSION,
+};
 
 pub type TextureId = ResourceId;
 
+//Part of the image changed after it has been sent to gpu
+#[derive(Clone)]
+pub struct TextureRegion {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
 #[derive(Clone)]
 pub struct Texture {
     id: TextureId,
@@ -27,7 +41,9 @@ pub struct Texture {
     height: u32,
     format: TextureFormat,
     usage: TextureUsage,
+    sampler: SamplerData,
     update_from_gpu: bool,
+    updated_regions: Vec<TextureRegion>,
 }
 
 impl ResourceTrait for Texture {
@@ -55,7 +71,9 @@ impl DataTypeResource for Texture {
             height: 0,
             format: TextureFormat::Rgba8Unorm,
             usage: TextureUsage::TextureBinding | TextureUsage::CopyDst,
+            sampler: SamplerData::default(),
             update_from_gpu: false,
+            updated_regions: Vec::new(),
         }
     }
 
@@ -95,12 +113,36 @@ impl SerializableResource for Texture {
 
     fn deserialize_data(
         path: &Path,
-        _registry: &SerializableRegistryRc,
-        mut f: Box<dyn FnMut(Self::DataType) + 'static>,
+        registry: &SerializableRegistryRc,
+        f: Box<dyn FnMut(Self::DataType) + 'static>,
     ) {
         let mut file = File::new(path);
         let filepath = path.to_path_buf();
+        let registry = registry.clone();
+        let mut on_loaded = Some(f);
         file.load(move |bytes| {
+            let Some(mut f) = on_loaded.take() else {
+                return;
+            };
+            if is_ktx2(bytes.as_slice()) {
+                match load_ktx2(bytes.as_slice(), created_device_features()) {
+                    Ok(texture_data) => f(texture_data),
+                    Err(e) => {
+                        if let Some(fallback_path) = Self::find_uncompressed_fallback(&filepath) {
+                            debug_log!(
+                                "{} - Falling back to uncompressed {:?} for {:?}",
+                                e,
+                                fallback_path,
+                                filepath
+                            );
+                            Self::deserialize_data(fallback_path.as_path(), &registry, f);
+                        } else {
+                            debug_log!("{} - Unable to load {:?} on this device", e, filepath);
+                        }
+                    }
+                }
+                return;
+            }
             let image_format = ImageFormat::from_path(filepath.as_path()).unwrap();
             let image_data =
                 image::load_from_memory_with_format(bytes.as_slice(), image_format).unwrap();
@@ -134,13 +176,23 @@ impl SerializableResource for Texture {
                 || ext == IMAGE_DDS_EXTENSION
                 || ext == IMAGE_TIFF_EXTENSION
                 || ext == IMAGE_GIF_EXTENSION
-                || ext == IMAGE_ICO_EXTENSION;
+                || ext == IMAGE_ICO_EXTENSION
+                || ext == KTX2_EXTENSION;
         }
         false
     }
 }
 
 impl Texture {
+    //A KTX2 texture can ship with an uncompressed image with the same name
+    //to be used when the device can't sample its compressed format
+    fn find_uncompressed_fallback(path: &Path) -> Option<PathBuf> {
+        const FALLBACK_EXTENSIONS: [&str; 3] = ["png", "jpg", "jpeg"];
+        FALLBACK_EXTENSIONS
+            .iter()
+            .map(|ext| path.with_extension(ext))
+            .find(|p| File::new(p).exists())
+    }
     fn mark_as_dirty(&self) -> &Self {
         self.message_hub
             .send_event(ResourceEvent::<Self>::Changed(self.id));
@@ -183,6 +235,62 @@ impl Texture {
         self.height = height;
         self
     }
+    //Copies the region in the image data, only the regions changed
+    //after the image has been sent to gpu are sent again
+    pub fn update_region(
+        &mut self,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        region_data: &[u8],
+    ) -> &mut Self {
+        if width == 0 || height == 0 || x + width > self.width || y + height > self.height {
+            debug_log!(
+                "Region {}x{} at ({}, {}) is outside of texture {:?}",
+                width,
+                height,
+                x,
+                y,
+                self.id
+            );
+            return self;
+        }
+        let row_size = region_data.len() / height as usize;
+        let pixel_size = row_size / width as usize;
+        if let Some(data) = &mut self.data {
+            let image_row_size = self.width as usize * pixel_size;
+            for row in 0..height as usize {
+                let offset = (y as usize + row) * image_row_size + x as usize * pixel_size;
+                data[offset..offset + row_size]
+                    .copy_from_slice(&region_data[row * row_size..(row + 1) * row_size]);
+            }
+        }
+        if self.is_initialized() {
+            self.updated_regions.push(TextureRegion {
+                x,
+                y,
+                width,
+                height,
+                data: region_data.to_vec(),
+            });
+        }
+        self.mark_as_dirty();
+        self
+    }
+    pub fn take_updated_regions(&mut self) -> Vec<TextureRegion> {
+        std::mem::take(&mut self.updated_regions)
+    }
+    pub fn sampler(&self) -> &SamplerData {
+        &self.sampler
+    }
+    pub fn set_sampler(&mut self, sampler: SamplerData) -> &mut Self {
+        if self.sampler != sampler {
+            self.sampler = sampler;
+            self.mark_as_dirty();
+        }
+        self
+    }
 
     pub fn create_from_format(
         shared_data: &SharedDataRc,
@@ -192,7 +300,7 @@ impl Texture {
         format: TextureFormat,
         usage: TextureUsage,
     ) -> Resource<Texture> {
-        let texture_id = generate_random_uid();
+        let texture_id = generate_random_resource_uid();
         let texture = Texture::create_from_data(
             shared_data,
             message_hub,
@@ -319,3 +427,54 @@ impl Texture {
         }
     }
 }
+
+#[allow(dead_code)]
+fn test_update_region() {
+    use inox_messenger::MessageHub;
+    use std::sync::Arc;
+
+    let shared_data = SharedDataRc::default();
+    let message_hub = Arc::new(MessageHub::default());
+    let mut texture = Texture::create_from_data(
+        &shared_data,
+        &message_hub,
+        generate_random_resource_uid(),
+        &TextureData {
+            width: 4,
+            height: 4,
+            format: TextureFormat::Rgba8Unorm,
+            data: Some(vec![0u8; 4 * 4 * 4]),
+            usage: TextureUsage::TextureBinding | TextureUsage::CopyDst,
+        },
+    );
+    let pixel = |texture: &Texture, x: usize, y: usize| {
+        let offset = (y * 4 + x) * 4;
+        texture.image_data().as_ref().unwrap()[offset]
+    };
+
+    //Before being sent to gpu the whole image will be sent anyway
+    texture.update_region(1, 2, 2, 2, &[255u8; 2 * 2 * 4]);
+    assert_eq!(pixel(&texture, 0, 2), 0);
+    assert_eq!(pixel(&texture, 1, 2), 255);
+    assert_eq!(pixel(&texture, 2, 3), 255);
+    assert_eq!(pixel(&texture, 3, 3), 0);
+    assert_eq!(pixel(&texture, 1, 1), 0);
+    assert!(texture.take_updated_regions().is_empty());
+
+    texture.set_texture_index(0);
+    texture.update_region(0, 0, 1, 1, &[128u8; 4]);
+    //Out of the image
+    texture.update_region(3, 3, 2, 1, &[64u8; 2 * 4]);
+    let regions = texture.take_updated_regions();
+    assert_eq!(regions.len(), 1);
+    assert_eq!((regions[0].x, regions[0].y), (0, 0));
+    assert_eq!((regions[0].width, regions[0].height), (1, 1));
+    assert_eq!(pixel(&texture, 0, 0), 128);
+    assert_eq!(pixel(&texture, 3, 3), 0);
+    assert!(texture.take_updated_regions().is_empty());
+}
+
+#[test]
+fn test() {
+    test_update_region();
+}
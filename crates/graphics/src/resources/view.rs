@@ -4,7 +4,9 @@ use inox_resources::{
     DataTypeResource, Handle, ResourceId, ResourceTrait, SharedData, SharedDataRc,
 };
 
-use crate::{DEFAULT_FAR, DEFAULT_FOV, DEFAULT_HEIGHT, DEFAULT_NEAR, DEFAULT_WIDTH};
+use crate::{
+    Viewport, DEFAULT_FAR, DEFAULT_FOV, DEFAULT_HEIGHT, DEFAULT_NEAR, DEFAULT_WIDTH, LAYER_MASK_ALL,
+};
 
 pub type ViewId = ResourceId;
 
@@ -14,6 +16,8 @@ pub struct View {
     view: Matrix4,
     proj: Matrix4,
     fov_in_degrees: Degrees,
+    viewport: Viewport,
+    layer_mask: u32,
 }
 
 impl ResourceTrait for View {
@@ -34,6 +38,8 @@ impl DataTypeResource for View {
             view: Matrix4::default_identity(),
             proj: Matrix4::default_identity(),
             fov_in_degrees: Degrees::new(DEFAULT_FOV),
+            viewport: Viewport::full(),
+            layer_mask: LAYER_MASK_ALL,
         }
     }
 
@@ -57,6 +63,8 @@ impl DataTypeResource for View {
                 DEFAULT_FAR,
             ),
             fov_in_degrees,
+            viewport: Viewport::full(),
+            layer_mask: LAYER_MASK_ALL,
         }
     }
 }
@@ -77,6 +85,12 @@ impl View {
     pub fn fov_in_degrees(&self) -> Degrees {
         self.fov_in_degrees
     }
+    pub fn viewport(&self) -> &Viewport {
+        &self.viewport
+    }
+    pub fn layer_mask(&self) -> u32 {
+        self.layer_mask
+    }
     pub fn find_from_view_index(shared_data: &SharedDataRc, view_index: u32) -> Handle<View> {
         SharedData::match_resource(shared_data, |v: &View| v.view_index == view_index)
     }
@@ -93,4 +107,14 @@ impl View {
         self.proj = mat;
         self
     }
+    //Views with different viewports are rendered one after the other in their rect
+    pub fn set_viewport(&mut self, viewport: Viewport) -> &mut Self {
+        self.viewport = viewport;
+        self
+    }
+    //Only the meshes with at least one of these layers are drawn in the view
+    pub fn set_layer_mask(&mut self, layer_mask: u32) -> &mut Self {
+        self.layer_mask = layer_mask;
+        self
+    }
 }
@@ -3,11 +3,22 @@ use std::path::PathBuf;
 use inox_resources::ConfigBase;
 use inox_serialize::{Deserialize, Serialize, SerializeFile};
 
+use crate::{PresentMode, MAX_ANISOTROPY};
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 #[serde(crate = "inox_serialize")]
 pub struct Config {
     pub default_pipeline: PathBuf,
     pub wireframe_pipeline: PathBuf,
+    #[serde(default)]
+    pub present_mode: PresentMode,
+    //Limited to the device support, 1 disables anisotropic filtering
+    #[serde(default = "default_anisotropy")]
+    pub anisotropy: u16,
+}
+
+fn default_anisotropy() -> u16 {
+    MAX_ANISOTROPY
 }
 
 impl Default for Config {
@@ -15,6 +26,8 @@ impl Default for Config {
         Self {
             default_pipeline: PathBuf::new(),
             wireframe_pipeline: PathBuf::new(),
+            present_mode: PresentMode::default(),
+            anisotropy: default_anisotropy(),
         }
     }
 }
@@ -13,7 +13,7 @@ use inox_resources::{
     ConfigBase, ConfigEvent, DataTypeResource, Handle, Resource, SerializableResource, SharedDataRc,
 };
 use inox_serialize::read_from_file;
-use inox_uid::{generate_random_uid, generate_uid_from_string};
+use inox_uid::{generate_random_resource_uid, generate_uid_from_string};
 
 use super::config::Config;
 
@@ -67,7 +67,7 @@ impl DebugDrawerSystem {
         let mesh_instance = Mesh::new_resource(
             context.shared_data(),
             context.message_hub(),
-            generate_random_uid(),
+            generate_random_resource_uid(),
             &mesh_data,
             None,
         );
@@ -79,7 +79,7 @@ impl DebugDrawerSystem {
         let wireframe_mesh_instance = Mesh::new_resource(
             context.shared_data(),
             context.message_hub(),
-            generate_random_uid(),
+            generate_random_resource_uid(),
             &mesh_data,
             None,
         );
@@ -90,7 +90,7 @@ impl DebugDrawerSystem {
         //println!("DebugDrawerWireframeMesh {:?}", wireframe_mesh_instance.id());
 
         let listener = Listener::new(context.message_hub());
-        listener.register::<DrawEvent>();
+        listener.register_coalesced(|e: &DrawEvent| e.coalesce_key());
 
         Self {
             config: Config::default(),
@@ -124,7 +124,7 @@ impl DebugDrawerSystem {
 
         self.listener
             .process_messages(|e: &ConfigEvent<Config>| match e {
-                ConfigEvent::Loaded(filename, config) => {
+                ConfigEvent::Loaded(filename, config) | ConfigEvent::Reloaded(filename, config) => {
                     inox_profiler::scoped_profile!("Processing ConfigEvent");
                     if filename == self.config.get_filename() {
                         self.config = config.clone();
@@ -145,7 +145,7 @@ impl DebugDrawerSystem {
                         let material = Material::new_resource(
                             &self.shared_data,
                             &self.message_hub,
-                            generate_random_uid(),
+                            generate_random_resource_uid(),
                             &material_data,
                             None,
                         );
@@ -153,7 +153,7 @@ impl DebugDrawerSystem {
                         let wireframe_material = Material::new_resource(
                             &self.shared_data,
                             &self.message_hub,
-                            generate_random_uid(),
+                            generate_random_resource_uid(),
                             &material_data,
                             None,
                         );
@@ -1,6 +1,5 @@
 use inox_core::{implement_unique_system_uid, ContextRc, System};
 
-use inox_math::Vector2;
 use inox_messenger::{Listener, MessageHubRc};
 use inox_platform::WindowEvent;
 use inox_resources::{
@@ -8,11 +7,11 @@ use inox_resources::{
     ResourceEvent, SerializableResourceEvent, SharedData, SharedDataRc,
 };
 use inox_serialize::read_from_file;
-use inox_uid::generate_random_uid;
+use inox_uid::generate_random_resource_uid;
 
 use crate::{
-    is_shader, CommandBuffer, ComputePipeline, Light, Material, Mesh, RenderPipeline, RendererRw,
-    RendererState, Texture, View, DEFAULT_HEIGHT, DEFAULT_WIDTH,
+    is_shader, CommandBuffer, ComputePipeline, Environment, Light, Material, Mesh, RenderPipeline,
+    RendererRw, RendererState, Texture, View, DEFAULT_HEIGHT, DEFAULT_WIDTH,
 };
 
 use super::config::Config;
@@ -24,7 +23,8 @@ pub struct UpdateSystem {
     shared_data: SharedDataRc,
     message_hub: MessageHubRc,
     listener: Listener,
-    view: Resource<View>,
+    //Default view, always rendered even without other View resources
+    _view: Resource<View>,
     scale_factor: f32,
     width: u32,
     height: u32,
@@ -36,10 +36,10 @@ impl UpdateSystem {
         let listener = Listener::new(context.message_hub());
 
         Self {
-            view: View::new_resource(
+            _view: View::new_resource(
                 context.shared_data(),
                 context.message_hub(),
-                generate_random_uid(),
+                generate_random_resource_uid(),
                 &0,
                 None,
             ),
@@ -55,21 +55,37 @@ impl UpdateSystem {
         }
     }
 
+    //Processed every frame, even when nothing is rendered, to resume after a minimize
+    fn handle_window_events(&mut self) {
+        self.listener.process_messages(|e: &WindowEvent| match e {
+            WindowEvent::SizeChanged(width, height) => {
+                self.width = *width;
+                self.height = *height;
+                self.resolution_changed = true;
+            }
+            WindowEvent::ScaleFactorChanged(v) => {
+                self.scale_factor = *v;
+                self.resolution_changed = true;
+            }
+            _ => {}
+        });
+    }
+
     fn handle_events(&mut self, command_buffer: &mut CommandBuffer) {
         inox_profiler::scoped_profile!("update_system::handle_events");
         //REMINDER: message processing order is important - RenderPass must be processed before Texture
         self.listener
-            .process_messages(|e: &WindowEvent| match e {
-                WindowEvent::SizeChanged(width, height) => {
-                    self.width = *width;
-                    self.height = *height;
-                    self.resolution_changed = true;
-                }
-                WindowEvent::ScaleFactorChanged(v) => {
-                    self.scale_factor = *v;
-                    self.resolution_changed = true;
+            .process_messages(|e: &ConfigEvent<Config>| match e {
+                ConfigEvent::Loaded(filename, config) | ConfigEvent::Reloaded(filename, config) => {
+                    if filename == self.config.get_filename() {
+                        self.config = config.clone();
+                        self.renderer
+                            .write()
+                            .unwrap()
+                            .set_present_mode(self.config.present_mode)
+                            .set_anisotropy(self.config.anisotropy);
+                    }
                 }
-                _ => {}
             })
             .process_messages(|e: &ReloadEvent| {
                 let ReloadEvent::Reload(path) = e;
@@ -106,7 +122,32 @@ impl UpdateSystem {
                     let render_context = renderer.render_context();
                     render_context.render_buffers.remove_texture(id);
                 }
+                ResourceEvent::Failed(..) => {}
             })
+            .process_messages(|e: &ResourceEvent<Environment>| match e {
+                ResourceEvent::Created(environment) => {
+                    self.renderer
+                        .write()
+                        .unwrap()
+                        .on_environment_changed(environment.id(), &mut command_buffer.encoder);
+                }
+                ResourceEvent::Changed(id) => {
+                    self.renderer
+                        .write()
+                        .unwrap()
+                        .on_environment_changed(id, &mut command_buffer.encoder);
+                }
+                ResourceEvent::Destroyed(id) => {
+                    let renderer = self.renderer.read().unwrap();
+                    let render_context = renderer.render_context();
+                    render_context.texture_handler.remove(id);
+                }
+                ResourceEvent::Failed(..) => {}
+            });
+        //Events of the same resource are handled in the order they have been sent,
+        //so that a resource loaded again after being destroyed is kept in the render buffers
+        self.listener
+            .process_in_order()
             .process_messages(|e: &DataTypeResourceEvent<Light>| {
                 let DataTypeResourceEvent::Loaded(id, light_data) = e;
                 let renderer = self.renderer.read().unwrap();
@@ -136,6 +177,7 @@ impl UpdateSystem {
                     let render_context = renderer.render_context();
                     render_context.render_buffers.remove_light(id);
                 }
+                ResourceEvent::Failed(..) => {}
             })
             .process_messages(|e: &ResourceEvent<Material>| match e {
                 ResourceEvent::Created(m) => {
@@ -152,6 +194,9 @@ impl UpdateSystem {
                         render_context
                             .render_buffers
                             .add_material(m.id(), &mut m.get_mut());
+                        render_context
+                            .render_buffers
+                            .update_material(m.id(), m.get().data());
                     }
                 }
                 ResourceEvent::Destroyed(id) => {
@@ -159,6 +204,7 @@ impl UpdateSystem {
                     let render_context = renderer.render_context();
                     render_context.render_buffers.remove_material(id);
                 }
+                ResourceEvent::Failed(..) => {}
             })
             .process_messages(|e: &DataTypeResourceEvent<Material>| {
                 let DataTypeResourceEvent::Loaded(id, material_data) = e;
@@ -172,7 +218,11 @@ impl UpdateSystem {
                 let DataTypeResourceEvent::Loaded(id, mesh_data) = e;
                 let renderer = self.renderer.read().unwrap();
                 let render_context = renderer.render_context();
-                render_context.render_buffers.add_mesh(id, mesh_data);
+                if let Err(e) = render_context.render_buffers.add_mesh(id, mesh_data) {
+                    inox_log::debug_log!("Unable to add mesh {:?} - {e}", id);
+                    self.message_hub
+                        .send_event(ResourceEvent::<Mesh>::Failed(*id, e.to_string()));
+                }
             })
             .process_messages(|e: &ResourceEvent<Mesh>| match e {
                 ResourceEvent::Changed(id) => {
@@ -190,7 +240,8 @@ impl UpdateSystem {
                     render_context.render_buffers.remove_mesh(id, true);
                 }
                 _ => {}
-            });
+            })
+            .deliver();
     }
 }
 
@@ -229,6 +280,7 @@ impl System for UpdateSystem {
             .register::<SerializableResourceEvent<Texture>>()
             .register::<ResourceEvent<Material>>()
             .register::<ResourceEvent<Texture>>()
+            .register::<ResourceEvent<Environment>>()
             .register::<ResourceEvent<Light>>()
             .register::<ResourceEvent<Mesh>>();
     }
@@ -242,12 +294,22 @@ impl System for UpdateSystem {
             return true;
         }
 
+        self.handle_window_events();
+
         {
             let mut renderer = self.renderer.write().unwrap();
-            if self.resolution_changed || !renderer.obtain_surface_texture() {
-                renderer.set_surface_size(self.width as f32 as _, self.height as f32 as _);
-
+            if self.resolution_changed {
+                renderer.set_surface_size(self.width, self.height);
                 self.resolution_changed = false;
+            }
+            //All the sizes received in a frame are applied at once, nothing is drawn while minimized
+            if renderer.apply_surface_size() || renderer.is_minimized() {
+                return true;
+            }
+            //Scaled render targets are resized before the passes bind them
+            renderer.apply_render_scale();
+            if !renderer.obtain_surface_texture() {
+                renderer.set_surface_size(self.width, self.height);
                 return true;
             }
         }
@@ -268,15 +330,10 @@ impl System for UpdateSystem {
         {
             let mut renderer = self.renderer.write().unwrap();
             {
-                let screen_size = Vector2::new(self.width as _, self.height as _);
-
-                let render_context = renderer.render_context();
-                render_context.update_constant_data(
-                    self.view.get().view(),
-                    self.view.get().proj(),
-                    screen_size,
-                    self.view.get().fov_in_degrees(),
-                );
+                let mut views = Vec::new();
+                self.shared_data
+                    .for_each_resource(|_, v: &View| views.push(v.clone()));
+                renderer.render_context().update_views(&views);
             }
 
             renderer.prepare();
@@ -303,6 +360,7 @@ impl System for UpdateSystem {
             .unregister::<ConfigEvent<Config>>()
             .unregister::<ResourceEvent<Light>>()
             .unregister::<ResourceEvent<Texture>>()
+            .unregister::<ResourceEvent<Environment>>()
             .unregister::<ResourceEvent<Material>>()
             .unregister::<ResourceEvent<Mesh>>();
     }
@@ -86,6 +86,25 @@ impl Default for Frustum {
     }
 }
 
+impl Frustum {
+    pub fn center(&self) -> Vector3 {
+        (self.ntr + self.ntl + self.nbr + self.nbl + self.ftr + self.ftl + self.fbr + self.fbl) / 8.
+    }
+    //Visible when not completely on the outer side of any face.
+    //Faces are oriented towards the frustum center, whatever the handedness of the view
+    pub fn is_sphere_visible(&self, center: Vector3, radius: f32) -> bool {
+        let frustum_center = self.center();
+        self.faces.iter().all(|face| {
+            let side = if face.normal.dot_product(frustum_center) >= face.distance {
+                1.
+            } else {
+                -1.
+            };
+            side * (face.normal.dot_product(center) - face.distance) >= -radius
+        })
+    }
+}
+
 pub fn normalize_plane(plane: Vector4) -> Vector4 {
     plane / plane.xyz().length()
 }
@@ -185,7 +204,92 @@ pub fn compute_frustum(
     frustum.faces[Faces::Right as usize].normal =
         (up).cross((aux - position).normalized()).normalized();
     frustum.faces[Faces::Right as usize].distance =
-        frustum.faces[Faces::Left as usize].normal.dot_product(aux);
+        frustum.faces[Faces::Right as usize].normal.dot_product(aux);
 
     frustum
 }
+
+//Frustum of what a view and a projection with the clip space z from -1 to 1 show.
+//The planes are the ones of the clip space moved to world space
+pub fn compute_frustum_from_view_proj(view: &Matrix4, proj: &Matrix4) -> Frustum {
+    let view_proj = *proj * *view;
+    let row = |i: usize| {
+        Vector4::new(
+            view_proj[0][i],
+            view_proj[1][i],
+            view_proj[2][i],
+            view_proj[3][i],
+        )
+    };
+    let plane = |p: Vector4| {
+        let p = normalize_plane(p);
+        Plane {
+            normal: p.xyz(),
+            distance: -p.w,
+        }
+    };
+    let corner = |x: f32, y: f32, z: f32| unproject(Vector3::new(x, y, z), *view, *proj);
+
+    let mut frustum = Frustum::default();
+    frustum.faces[Faces::Near as usize] = plane(row(3) + row(2));
+    frustum.faces[Faces::Far as usize] = plane(row(3) - row(2));
+    frustum.faces[Faces::Top as usize] = plane(row(3) - row(1));
+    frustum.faces[Faces::Bottom as usize] = plane(row(3) + row(1));
+    frustum.faces[Faces::Left as usize] = plane(row(3) + row(0));
+    frustum.faces[Faces::Right as usize] = plane(row(3) - row(0));
+
+    frustum.ntl = corner(-1., 1., -1.);
+    frustum.ntr = corner(1., 1., -1.);
+    frustum.nbl = corner(-1., -1., -1.);
+    frustum.nbr = corner(1., -1., -1.);
+    frustum.ftl = corner(-1., 1., 1.);
+    frustum.ftr = corner(1., 1., 1.);
+    frustum.fbl = corner(-1., -1., 1.);
+    frustum.fbr = corner(1., -1., 1.);
+    frustum
+}
+
+#[test]
+fn frustum_sphere_test() {
+    use crate::{MatBase, NewAngle};
+
+    let view = Matrix4::default_identity();
+    let frustum = compute_frustum(&view, 0.1, 100., Degrees::new(45.), 1.);
+    let facing = view.forward().normalized();
+    let right = view.right().normalized();
+
+    assert!(frustum.is_sphere_visible(frustum.center(), 0.));
+    assert!(frustum.is_sphere_visible(facing * 50., 1.));
+    //Behind the camera and past the far plane
+    assert!(!frustum.is_sphere_visible(-facing * 10., 1.));
+    assert!(!frustum.is_sphere_visible(facing * 110., 1.));
+    //Large enough to reach the far plane
+    assert!(frustum.is_sphere_visible(facing * 110., 20.));
+    //On both sides
+    assert!(!frustum.is_sphere_visible(facing * 10. + right * 50., 1.));
+    assert!(!frustum.is_sphere_visible(facing * 10. - right * 50., 1.));
+    assert!(frustum.is_sphere_visible(facing * 10. - right * 50., 50.));
+}
+
+#[test]
+fn frustum_from_view_proj_test() {
+    use crate::{perspective, NewAngle};
+
+    //The camera at z 10 looks towards the origin, down its -z
+    let camera = Matrix4::from_translation(Vector3::new(0., 0., 10.));
+    let view = camera.inverse();
+    let proj = perspective(Degrees::new(45.), 1., 0.1, 100.);
+    let frustum = compute_frustum_from_view_proj(&view, &proj);
+
+    assert!((frustum.center() - Vector3::new(0., 0., 10. - 50.05)).length() < 1e-2);
+    assert!(frustum.is_sphere_visible(Vector3::new(0., 0., 0.), 1.));
+    //Behind the camera and past the far plane
+    assert!(!frustum.is_sphere_visible(Vector3::new(0., 0., 20.), 1.));
+    assert!(!frustum.is_sphere_visible(Vector3::new(0., 0., -100.), 1.));
+    assert!(frustum.is_sphere_visible(Vector3::new(0., 0., -100.), 20.));
+    //On both sides and above
+    assert!(!frustum.is_sphere_visible(Vector3::new(50., 0., 0.), 1.));
+    assert!(!frustum.is_sphere_visible(Vector3::new(-50., 0., 0.), 1.));
+    assert!(!frustum.is_sphere_visible(Vector3::new(0., 50., 0.), 1.));
+    assert!(frustum.is_sphere_visible(Vector3::new(-50., 0., 0.), 50.));
+}
@@ -8,6 +8,7 @@ pub use crate::parser::*;
 pub use crate::quaternion::*;
 pub use crate::random::*;
 pub use crate::ray::*;
+pub use crate::sphere::*;
 pub use crate::triangle::*;
 pub use crate::vector::*;
 
@@ -19,5 +20,6 @@ pub mod parser;
 pub mod quaternion;
 pub mod random;
 pub mod ray;
+pub mod sphere;
 pub mod triangle;
 pub mod vector;
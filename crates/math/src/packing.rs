@@ -1,4 +1,4 @@
-use crate::Vector4;
+use crate::{Vector3, Vector4};
 
 // https://docs.microsoft.com/en-us/windows/win32/direct3d10/d3d10-graphics-programming-guide-resources-data-conversion
 
@@ -8,10 +8,24 @@ pub fn normalize_f32_in_neg1_pos1(v: f32, min: f32, max: f32) -> f32 {
     2. * (v - min) / (max - min) - 1.
 }
 
-//output: [0..1] float in range [min..max]
+//output: [0..1] float in range [min..max], 0.5 when the range is empty
 #[inline]
 pub fn normalize_f32_in_0_1(v: f32, min: f32, max: f32) -> f32 {
-    (v - min) / (max - min)
+    let size = max - min;
+    if size.abs() < f32::EPSILON {
+        return 0.5;
+    }
+    (v - min) / size
+}
+
+//output: [0..1] floats in the box [min..max], per axis, so flat boxes don't produce NaN
+#[inline]
+pub fn normalize_vec3_in_0_1(v: Vector3, min: Vector3, max: Vector3) -> Vector3 {
+    Vector3::new(
+        normalize_f32_in_0_1(v.x, min.x, max.x),
+        normalize_f32_in_0_1(v.y, min.y, max.y),
+        normalize_f32_in_0_1(v.z, min.z, max.z),
+    )
 }
 
 //input: [-1..1] float; output: [-127..127] integer
@@ -40,25 +54,44 @@ pub fn pack_4_f32_to_unorm(value: Vector4) -> u32 {
 
 #[inline]
 pub fn unpack_unorm_to_4_f32(value: u32) -> Vector4 {
-    let r = decode_unorm((value >> 24) & 255, 8);
-    let g = decode_unorm((value >> 16) & 255, 8);
-    let b = decode_unorm((value >> 8) & 255, 8);
-    let a = decode_unorm(value & 255, 8);
+    let r = dequantize_unorm((value >> 24) & 255, 8);
+    let g = dequantize_unorm((value >> 16) & 255, 8);
+    let b = dequantize_unorm((value >> 8) & 255, 8);
+    let a = dequantize_unorm(value & 255, 8);
     Vector4::new(r, g, b, a)
 }
 
 #[inline]
 pub fn unpack_snorm_to_4_f32(value: u32) -> Vector4 {
-    let r = decode_snorm((value >> 24) & 255, 8);
-    let g = decode_snorm((value >> 16) & 255, 8);
-    let b = decode_snorm((value >> 8) & 255, 8);
-    let a = decode_snorm(value & 255, 8);
+    let r = dequantize_snorm((value >> 24) & 255, 8);
+    let g = dequantize_snorm((value >> 16) & 255, 8);
+    let b = dequantize_snorm((value >> 8) & 255, 8);
+    let a = dequantize_snorm(value & 255, 8);
     Vector4::new(r, g, b, a)
 }
 
+//input: [0..1] float; output: 10 bits per component in the lower 30 bits
+#[inline]
+pub fn pack_3_f32_to_unorm_10(value: Vector3) -> u32 {
+    let x = quantize_unorm(value.x, 10);
+    let y = quantize_unorm(value.y, 10);
+    let z = quantize_unorm(value.z, 10);
+    x << 20 | y << 10 | z
+}
+
+#[inline]
+pub fn unpack_unorm_10_to_3_f32(value: u32) -> Vector3 {
+    let x = dequantize_unorm((value >> 20) & 0x3FF, 10);
+    let y = dequantize_unorm((value >> 10) & 0x3FF, 10);
+    let z = dequantize_unorm(value & 0x3FF, 10);
+    Vector3::new(x, y, z)
+}
+
 // Quantize a f32 in [0..1] range into an N-bit fixed point unorm value
 // Assumes reconstruction function (q / (2^N-1)), which is the case for fixed-function normalized fixed point conversion
 // Maximum reconstruction error: 1/2^(N+1)
+// Values out of range, NaN included, are clamped so that 1 is the largest value, 2^N-1, and
+// never overflows in the bits of the next packed value
 #[inline]
 pub fn quantize_unorm(mut v: f32, n: u32) -> u32 {
     let scale = ((1 << n) - 1) as f32;
@@ -67,31 +100,32 @@ pub fn quantize_unorm(mut v: f32, n: u32) -> u32 {
     (0.5 + (v * scale)) as _
 }
 #[inline]
+pub fn dequantize_unorm(i: u32, n: u32) -> f32 {
+    let c = (1 << n) - 1;
+    (i & c) as f32 / c as f32
+}
+#[inline]
+#[deprecated(note = "Use dequantize_unorm, the values in between are no longer biased")]
 pub fn decode_unorm(i: u32, n: u32) -> f32 {
-    let scale = ((1 << n) - 1) as f32;
-    if i == 0 {
-        0.
-    } else if i == scale as _ {
-        1.
-    } else {
-        (i as f32 - 0.5) / scale
-    }
+    dequantize_unorm(i, n)
 }
 
 // Quantize a f32 in [-1..1] range into an N-bit fixed point snorm value
 // Assumes reconstruction function (q / (2^(N-1)-1)), which is the case for fixed-function normalized fixed point conversion (except early OpenGL versions)
 // Maximum reconstruction error: 1/2^N
+// Values out of range are clamped, to not wrap around in the magnitude bits
 #[inline]
 pub fn quantize_snorm(v: f32, n: u32) -> u32 {
     let c = (1 << (n - 1)) - 1;
     let scale = c as f32;
+    let v = v.clamp(-1., 1.);
     if v < 0. {
         return ((-v * scale) as u32 & c) | (1 << (n - 1));
     }
     (v * scale) as u32 & c
 }
 #[inline]
-pub fn decode_snorm(i: u32, n: u32) -> f32 {
+pub fn dequantize_snorm(i: u32, n: u32) -> f32 {
     let s = i >> (n - 1);
     let c = (1 << (n - 1)) - 1;
     let scale = c as f32;
@@ -101,6 +135,11 @@ pub fn decode_snorm(i: u32, n: u32) -> f32 {
     }
     (i & c) as f32 / scale
 }
+#[inline]
+#[deprecated(note = "Use dequantize_snorm")]
+pub fn decode_snorm(i: u32, n: u32) -> f32 {
+    dequantize_snorm(i, n)
+}
 
 // Quantize a f32 into half-precision floating point value (16 bit)
 // Generates +-inf for overflow, preserves NaN, flushes denormals to zero, rounds to nearest
@@ -226,10 +265,80 @@ fn encode_decode_test() {
     debug_assert!(a == ca, "{} != {}", a, ca);
     debug_assert!(b == cb, "{} != {}", b, cb);
     debug_assert!(c == cc, "{} != {}", c, cc);
-    let cv1 = decode_unorm(ca, 10);
-    let cv2 = decode_unorm(cb, 10);
-    let cv3 = decode_unorm(cc, 10);
+    let cv1 = dequantize_unorm(ca, 10);
+    let cv2 = dequantize_unorm(cb, 10);
+    let cv3 = dequantize_unorm(cc, 10);
+    let step = 1. / 1023.;
     debug_assert!(v1 == cv1, "{} != {}", v1, cv1);
-    debug_assert!(v2 == cv2, "{} != {}", v2, cv2);
+    debug_assert!((v2 - cv2).abs() <= step * 0.5, "{} != {}", v2, cv2);
     debug_assert!(v3 == cv3, "{} != {}", v3, cv3);
 }
+
+#[test]
+fn pack_bit_fields_test() {
+    //Max values, and past them, don't bleed into the next fields nor past 30 bits
+    assert_eq!(
+        pack_3_f32_to_unorm_10(Vector3::new(1., 1., 1.)),
+        0x3FFF_FFFF
+    );
+    assert_eq!(
+        pack_3_f32_to_unorm_10(Vector3::new(2., 1e9, f32::INFINITY)),
+        0x3FFF_FFFF
+    );
+    assert_eq!(pack_3_f32_to_unorm_10(Vector3::new(0., 0., 1.)), 0x3FF);
+    assert_eq!(
+        pack_3_f32_to_unorm_10(Vector3::new(0., 1., 0.)),
+        0x3FF << 10
+    );
+    assert_eq!(
+        pack_3_f32_to_unorm_10(Vector3::new(1., 0., 0.)),
+        0x3FF << 20
+    );
+    assert_eq!(pack_3_f32_to_unorm_10(Vector3::new(-1., f32::NAN, 0.)), 0);
+    assert_eq!(pack_4_f32_to_unorm(Vector4::new(0., 0., 0., 2.)), 0xFF);
+    //Magnitudes past 1 don't wrap around
+    assert_eq!(quantize_snorm(1.5, 8), quantize_snorm(1., 8));
+    assert_eq!(quantize_snorm(-1.5, 8), quantize_snorm(-1., 8));
+    assert_eq!(dequantize_snorm(quantize_snorm(-3., 8), 8), -1.);
+}
+
+#[test]
+fn round_trip_test() {
+    for n in [8, 10] {
+        let step = 1. / ((1 << n) - 1) as f32;
+        for i in 0..=1000 {
+            let v = i as f32 / 1000.;
+            let unorm = dequantize_unorm(quantize_unorm(v, n), n);
+            assert!((v - unorm).abs() <= step, "{v} != {unorm}");
+            let snorm_step = 1. / ((1 << (n - 1)) - 1) as f32;
+            let v = v * 2. - 1.;
+            let snorm = dequantize_snorm(quantize_snorm(v, n), n);
+            assert!((v - snorm).abs() <= snorm_step, "{v} != {snorm}");
+        }
+    }
+    let v = Vector3::new(0.25, 0.7, 0.999);
+    let packed = unpack_unorm_10_to_3_f32(pack_3_f32_to_unorm_10(v));
+    assert!((packed.x - v.x).abs() <= 0.5 / 1023.);
+    assert!((packed.y - v.y).abs() <= 0.5 / 1023.);
+    assert!((packed.z - v.z).abs() <= 0.5 / 1023.);
+}
+
+#[test]
+fn normalize_flat_box_test() {
+    let min = Vector3::new(-1., 2., 5.);
+    let max = Vector3::new(1., 2., 5.);
+    let v = normalize_vec3_in_0_1(Vector3::new(0., 2., 5.), min, max);
+    assert_eq!(v, Vector3::new(0.5, 0.5, 0.5));
+    let v = normalize_vec3_in_0_1(Vector3::new(1., 2., 5.), min, max);
+    assert_eq!(v, Vector3::new(1., 0.5, 0.5));
+    assert_eq!(pack_3_f32_to_unorm_10(v), 1023 << 20 | 512 << 10 | 512);
+}
+
+#[test]
+#[allow(deprecated)]
+fn deprecated_decode_test() {
+    (0..256).for_each(|i| {
+        assert_eq!(decode_unorm(i, 8), dequantize_unorm(i, 8));
+        assert_eq!(decode_snorm(i, 8), dequantize_snorm(i, 8));
+    });
+}
@@ -0,0 +1,58 @@
+use crate::{VecBase, VecBaseFloat, Vector3};
+
+//Ritter's bounding sphere: not the minimal one but usually within a few percents of it,
+//in linear time.
+//Returns center and radius, with a zero radius when there are no points
+pub fn compute_bounding_sphere(points: &[Vector3]) -> (Vector3, f32) {
+    let Some(first) = points.first() else {
+        return (Vector3::default_zero(), 0.);
+    };
+    let farthest_from = |p: Vector3| {
+        points.iter().fold(p, |farthest, v| {
+            if v.squared_distance(p) > farthest.squared_distance(p) {
+                *v
+            } else {
+                farthest
+            }
+        })
+    };
+    let x = farthest_from(*first);
+    let y = farthest_from(x);
+    let mut center = (x + y) * 0.5;
+    let mut radius = (y - x).length() * 0.5;
+    points.iter().for_each(|p| {
+        let distance = (*p - center).length();
+        if distance > radius {
+            let new_radius = (radius + distance) * 0.5;
+            center += (*p - center) * ((new_radius - radius) / distance);
+            radius = new_radius;
+        }
+    });
+    (center, radius)
+}
+
+#[test]
+fn bounding_sphere_test() {
+    use crate::get_random_f32;
+
+    assert_eq!(compute_bounding_sphere(&[]), (Vector3::default_zero(), 0.));
+    let single = Vector3::new(1., 2., 3.);
+    assert_eq!(compute_bounding_sphere(&[single]), (single, 0.));
+
+    let points = (0..1000)
+        .map(|_| {
+            Vector3::new(
+                get_random_f32(-10., 10.),
+                get_random_f32(-5., 5.),
+                get_random_f32(-1., 20.),
+            )
+        })
+        .collect::<Vec<_>>();
+    let (center, radius) = compute_bounding_sphere(&points);
+    points.iter().for_each(|p| {
+        assert!(
+            (*p - center).length() <= radius + 1e-4,
+            "{p:?} is outside of the sphere"
+        );
+    });
+}
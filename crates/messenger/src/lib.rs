@@ -1,7 +1,9 @@
 pub use macros::*;
 pub use message::*;
 pub use message_hub::*;
+pub use request::*;
 
 pub mod macros;
 pub mod message;
 pub mod message_hub;
+pub mod request;
@@ -1,17 +1,32 @@
 use std::{
     any::{Any, TypeId},
-    collections::HashMap,
-    sync::{Arc, RwLock},
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, RwLock,
+    },
 };
 
 use inox_uid::{generate_random_uid, Uid};
 
-use crate::Message;
+use crate::{
+    request::{RequestQueue, RequestQueueTrait},
+    Message, ResponseFuture, DEFAULT_REQUEST_TIMEOUT_FRAMES,
+};
 
 pub type MessageHubRc = Arc<MessageHub>;
 
 pub type ListenerId = Uid;
 type MessageId = Uid;
+pub type MessageSequence = u64;
+
+//Shared by every message type so that messages of different types can be sorted by send order
+static MESSAGE_SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+fn next_sequence() -> MessageSequence {
+    MESSAGE_SEQUENCE.fetch_add(1, Ordering::Relaxed)
+}
 
 pub struct Listener {
     id: ListenerId,
@@ -35,6 +50,27 @@ impl Listener {
         self.message_hub.register_listener::<T>(&self.id);
         self
     }
+    //Only the messages accepted by the filter are delivered to this listener
+    pub fn register_filtered<T, F>(&self, filter: F) -> &Self
+    where
+        T: Message + 'static,
+        F: Fn(&T) -> bool + 'static,
+    {
+        self.message_hub
+            .register_filtered_listener::<T, F>(&self.id, filter);
+        self
+    }
+    //Registers the listener and coalesces the messages of type T for every listener
+    pub fn register_coalesced<T, K, F>(&self, key_fn: F) -> &Self
+    where
+        T: Message + 'static,
+        K: Hash,
+        F: Fn(&T) -> K + 'static,
+    {
+        self.message_hub.register_coalesced::<T, K, F>(key_fn);
+        self.message_hub.register_listener::<T>(&self.id);
+        self
+    }
     pub fn unregister<T>(&self) -> &Self
     where
         T: Message + 'static,
@@ -51,17 +87,93 @@ impl Listener {
         self.message_hub.process_messages(&self.id, f);
         self
     }
+    //Only the most recent message is handled, the older ones are dropped.
+    //Meant for types where each message replaces the previous state, like a size
+    #[inline]
+    pub fn process_latest<T, F>(&self, f: F) -> &Self
+    where
+        F: FnOnce(&T),
+        T: Message + 'static,
+    {
+        self.message_hub.process_latest(&self.id, f);
+        self
+    }
+    //Messages of the types added to the returned OrderedMessages are delivered
+    //in the order they have been sent, instead of one type after the other
+    #[inline]
+    pub fn process_in_order(&self) -> OrderedMessages<'_> {
+        OrderedMessages {
+            listener: self,
+            queues: Vec::new(),
+        }
+    }
+}
+
+struct OrderedQueue<'a> {
+    messages: Vec<(MessageSequence, MessageId)>,
+    next: usize,
+    handler: Box<dyn FnMut(&MessageHub, &MessageId) + 'a>,
 }
 
-struct ListenerData {
+pub struct OrderedMessages<'a> {
+    listener: &'a Listener,
+    queues: Vec<OrderedQueue<'a>>,
+}
+
+impl<'a> OrderedMessages<'a> {
+    pub fn process_messages<T, F>(mut self, mut f: F) -> Self
+    where
+        F: FnMut(&T) + 'a,
+        T: Message + 'static,
+    {
+        let message_hub = &self.listener.message_hub;
+        let messages = message_hub.take_messages::<T>(&self.listener.id);
+        if !messages.is_empty() {
+            self.queues.push(OrderedQueue {
+                messages,
+                next: 0,
+                handler: Box::new(move |message_hub: &MessageHub, msg_id: &MessageId| {
+                    message_hub.process_message::<T, _>(msg_id, &mut f)
+                }),
+            });
+        }
+        self
+    }
+    pub fn deliver(mut self) {
+        let message_hub: &MessageHub = &self.listener.message_hub;
+        while let Some(queue) = self
+            .queues
+            .iter_mut()
+            .filter(|q| q.next < q.messages.len())
+            .min_by_key(|q| q.messages[q.next].0)
+        {
+            let msg_id = queue.messages[queue.next].1;
+            queue.next += 1;
+            (queue.handler)(message_hub, &msg_id);
+        }
+    }
+}
+
+pub type MessageFilterFn<T> = dyn Fn(&T) -> bool;
+pub type MessageKeyFn<T> = dyn Fn(&T) -> u64;
+
+struct ListenerData<T> {
     id: ListenerId,
-    messages: RwLock<Vec<MessageId>>,
+    messages: RwLock<Vec<(MessageSequence, MessageId)>>,
+    filter: Option<Box<MessageFilterFn<T>>>,
 }
-impl ListenerData {
-    fn new(id: &ListenerId) -> Self {
+impl<T> ListenerData<T> {
+    fn new(id: &ListenerId, filter: Option<Box<MessageFilterFn<T>>>) -> Self {
         Self {
             id: *id,
             messages: RwLock::new(Vec::new()),
+            filter,
+        }
+    }
+    fn accepts(&self, msg: &T) -> bool {
+        match &self.filter {
+            Some(filter) => filter(msg),
+            None => true,
         }
     }
 }
@@ -90,9 +202,10 @@ where
     T: Message,
 {
     msg_from_str: Option<Box<MessageFromStrFn<T>>>,
-    new_messages: RwLock<Vec<T>>,
+    coalesce_key: Option<Box<MessageKeyFn<T>>>,
+    new_messages: RwLock<Vec<(MessageSequence, T)>>,
     messages: RwLock<HashMap<MessageId, T>>,
-    listeners: RwLock<Vec<ListenerData>>,
+    listeners: RwLock<Vec<ListenerData<T>>>,
 }
 
 impl<T> MessageType<T>
@@ -105,11 +218,36 @@ where
     {
         Self {
             msg_from_str: Some(Box::new(f)),
+            coalesce_key: None,
             new_messages: RwLock::new(Vec::new()),
             messages: RwLock::new(HashMap::new()),
             listeners: RwLock::new(Vec::new()),
         }
     }
+    fn add_filtered_listener(&self, listener_id: &ListenerId, filter: Box<MessageFilterFn<T>>) {
+        let mut listeners = self.listeners.write().unwrap();
+        listeners.retain(|l| l.id != *listener_id);
+        listeners.push(ListenerData::new(listener_id, Some(filter)));
+    }
+    fn set_coalesce_key(&mut self, key_fn: Box<MessageKeyFn<T>>) {
+        self.coalesce_key = Some(key_fn);
+    }
+    //Keeps only the latest of the new messages sharing the same key
+    fn coalesce(&self, new_messages: &mut Vec<(MessageSequence, T)>) {
+        if let Some(key_fn) = &self.coalesce_key {
+            let mut latest = HashMap::new();
+            new_messages.iter().enumerate().for_each(|(i, (_, msg))| {
+                latest.insert(key_fn(msg), i);
+            });
+            let mut is_latest = vec![false; new_messages.len()];
+            latest.values().for_each(|&i| is_latest[i] = true);
+            let mut i = 0;
+            new_messages.retain(|_| {
+                i += 1;
+                is_latest[i - 1]
+            });
+        }
+    }
 }
 
 impl<T> MsgType for MessageType<T>
@@ -121,7 +259,7 @@ where
         self.listeners
             .write()
             .unwrap()
-            .push(ListenerData::new(listener_id));
+            .push(ListenerData::new(listener_id, None));
     }
     fn remove_listener(&self, listener_id: &ListenerId) {
         self.listeners
@@ -136,14 +274,18 @@ where
         //inox_log::debug_log!("Flushing messages for {}", type_name::<T>());
         //inox_log::debug_log!("From {}", self.messages.read().unwrap().len());
         self.messages.write().unwrap().retain(|msg_id, _| {
-            self.listeners
-                .read()
-                .unwrap()
-                .iter()
-                .any(|l| l.messages.read().unwrap().contains(msg_id))
+            self.listeners.read().unwrap().iter().any(|l| {
+                l.messages
+                    .read()
+                    .unwrap()
+                    .iter()
+                    .any(|(_, id)| id == msg_id)
+            })
         });
         //inox_log::debug_log!("to {}", self.messages.read().unwrap().len());
-        for msg in self.new_messages.write().unwrap().drain(..) {
+        let mut new_messages = std::mem::take(&mut *self.new_messages.write().unwrap());
+        self.coalesce(&mut new_messages);
+        for (sequence, msg) in new_messages {
             self.messages.write().unwrap().retain(|msg_id, other| {
                 let discard = msg.compare_and_discard(other);
                 if discard {
@@ -151,7 +293,7 @@ where
                         .read()
                         .unwrap()
                         .iter()
-                        .for_each(|l| l.messages.write().unwrap().retain(|id| id != msg_id));
+                        .for_each(|l| l.messages.write().unwrap().retain(|(_, id)| id != msg_id));
                 }
                 !discard
             });
@@ -160,7 +302,8 @@ where
                 .read()
                 .unwrap()
                 .iter()
-                .for_each(|l| l.messages.write().unwrap().push(msg_id));
+                .filter(|l| l.accepts(&msg))
+                .for_each(|l| l.messages.write().unwrap().push((sequence, msg_id)));
             self.messages.write().unwrap().insert(msg_id, msg);
         }
     }
@@ -188,8 +331,11 @@ where
         self.new_messages
             .write()
             .unwrap()
-            .retain(|other| !msg.compare_and_discard(other));
-        self.new_messages.write().unwrap().push(msg);
+            .retain(|(_, other)| !msg.compare_and_discard(other));
+        self.new_messages
+            .write()
+            .unwrap()
+            .push((next_sequence(), msg));
     }
     pub fn process_messages<F>(&self, listener_id: &ListenerId, mut f: F)
     where
@@ -211,13 +357,52 @@ where
                 messages.append(listener_messages.as_mut());
                 listener_messages.clear();
             }
-            messages.iter().for_each(|msg_id| {
+            messages.iter().for_each(|(_, msg_id)| {
                 if let Some(msg) = self.messages.read().unwrap().get(msg_id) {
                     f(msg);
                 }
             });
         }
     }
+    pub fn process_latest<F>(&self, listener_id: &ListenerId, f: F)
+    where
+        F: FnOnce(&T),
+    {
+        if let Some(listener) = self
+            .listeners
+            .read()
+            .unwrap()
+            .iter()
+            .find(|l| l.id == *listener_id)
+        {
+            let listener_messages = std::mem::take(&mut *listener.messages.write().unwrap());
+            let messages = self.messages.read().unwrap();
+            if let Some(msg) = listener_messages
+                .iter()
+                .rev()
+                .find_map(|(_, msg_id)| messages.get(msg_id))
+            {
+                f(msg);
+            }
+        }
+    }
+    fn take_messages(&self, listener_id: &ListenerId) -> Vec<(MessageSequence, MessageId)> {
+        self.listeners
+            .read()
+            .unwrap()
+            .iter()
+            .find(|l| l.id == *listener_id)
+            .map(|l| std::mem::take(&mut *l.messages.write().unwrap()))
+            .unwrap_or_default()
+    }
+    fn process_message<F>(&self, msg_id: &MessageId, f: F)
+    where
+        F: FnOnce(&T),
+    {
+        if let Some(msg) = self.messages.read().unwrap().get(msg_id) {
+            f(msg);
+        }
+    }
 }
 unsafe impl<T> Send for MessageType<T> where T: Message {}
 unsafe impl<T> Sync for MessageType<T> where T: Message {}
@@ -225,6 +410,7 @@ unsafe impl<T> Sync for MessageType<T> where T: Message {}
 #[derive(Default)]
 pub struct MessageHub {
     registered_types: RwLock<HashMap<TypeId, Box<dyn MsgType>>>,
+    responders: RwLock<HashMap<TypeId, Box<dyn RequestQueueTrait>>>,
 }
 
 impl Drop for MessageHub {
@@ -288,6 +474,40 @@ impl MessageHub {
         self
     }
     #[inline]
+    pub fn register_filtered_listener<T, F>(&self, listener_id: &ListenerId, filter: F) -> &Self
+    where
+        T: Message + 'static,
+        F: Fn(&T) -> bool + 'static,
+    {
+        self.register_type::<T>();
+        let typeid = TypeId::of::<T>();
+        if let Some(entry) = self.registered_types.write().unwrap().get_mut(&typeid) {
+            let msg_type = entry.as_any_mut().downcast_mut::<MessageType<T>>().unwrap();
+            msg_type.add_filtered_listener(listener_id, Box::new(filter));
+        }
+        self
+    }
+    //Within a frame only the latest message for each key is delivered to the listeners
+    #[inline]
+    pub fn register_coalesced<T, K, F>(&self, key_fn: F) -> &Self
+    where
+        T: Message + 'static,
+        K: Hash,
+        F: Fn(&T) -> K + 'static,
+    {
+        self.register_type::<T>();
+        let typeid = TypeId::of::<T>();
+        if let Some(entry) = self.registered_types.write().unwrap().get_mut(&typeid) {
+            let msg_type = entry.as_any_mut().downcast_mut::<MessageType<T>>().unwrap();
+            msg_type.set_coalesce_key(Box::new(move |msg| {
+                let mut hasher = DefaultHasher::new();
+                key_fn(msg).hash(&mut hasher);
+                hasher.finish()
+            }));
+        }
+        self
+    }
+    #[inline]
     pub fn unregister_listener<T>(&self, listener_id: &ListenerId) -> &Self
     where
         T: Message + 'static,
@@ -312,6 +532,44 @@ impl MessageHub {
         }
     }
 
+    pub fn process_latest<T, F>(&self, listener_id: &ListenerId, f: F)
+    where
+        F: FnOnce(&T),
+        T: Message + 'static,
+    {
+        let typeid = TypeId::of::<T>();
+        if let Some(entry) = self.registered_types.read().unwrap().get(&typeid) {
+            let msg_type = entry.as_any().downcast_ref::<MessageType<T>>().unwrap();
+            msg_type.process_latest(listener_id, f);
+        }
+    }
+
+    fn take_messages<T>(&self, listener_id: &ListenerId) -> Vec<(MessageSequence, MessageId)>
+    where
+        T: Message + 'static,
+    {
+        let typeid = TypeId::of::<T>();
+        if let Some(entry) = self.registered_types.read().unwrap().get(&typeid) {
+            let msg_type = entry.as_any().downcast_ref::<MessageType<T>>().unwrap();
+            return msg_type.take_messages(listener_id);
+        }
+        Vec::new()
+    }
+
+    fn process_message<T, F>(&self, msg_id: &MessageId, f: F)
+    where
+        F: FnOnce(&T),
+        T: Message + 'static,
+    {
+        let typeid = TypeId::of::<T>();
+        if let Some(entry) = self.registered_types.read().unwrap().get(&typeid) {
+            let msg_type = entry.as_any().downcast_ref::<MessageType<T>>().unwrap();
+            msg_type.process_message(msg_id, f);
+        }
+    }
+
+    //Every message type keeps the send order of its messages,
+    //a listener gets them sorted across types through process_in_order
     pub fn flush(&self) {
         self.registered_types
             .read()
@@ -320,6 +578,13 @@ impl MessageHub {
             .for_each(|(_, msg_type)| {
                 msg_type.flush();
             });
+        self.responders
+            .read()
+            .unwrap()
+            .iter()
+            .for_each(|(_, requests)| {
+                requests.update();
+            });
     }
 
     pub fn send_from_string(&self, s: String) {
@@ -342,4 +607,252 @@ impl MessageHub {
             msg_type.send_event(msg);
         }
     }
+
+    #[inline]
+    pub fn register_responder<Req, Resp>(&self) -> &Self
+    where
+        Req: Send + Sync + 'static,
+        Resp: Send + 'static,
+    {
+        let typeid = TypeId::of::<(Req, Resp)>();
+        self.responders
+            .write()
+            .unwrap()
+            .entry(typeid)
+            .or_insert_with(|| Box::<RequestQueue<Req, Resp>>::default())
+            .add_responder();
+        self
+    }
+    #[inline]
+    pub fn unregister_responder<Req, Resp>(&self) -> &Self
+    where
+        Req: Send + Sync + 'static,
+        Resp: Send + 'static,
+    {
+        let typeid = TypeId::of::<(Req, Resp)>();
+        let mut responders = self.responders.write().unwrap();
+        let is_last = responders
+            .get(&typeid)
+            .map_or(false, |requests| requests.remove_responder());
+        if is_last {
+            if let Some(requests) = responders.remove(&typeid) {
+                requests.cancel();
+            }
+        }
+        self
+    }
+
+    pub fn request<Req, Resp>(&self, request: Req) -> ResponseFuture<Resp>
+    where
+        Req: Send + Sync + 'static,
+        Resp: Send + 'static,
+    {
+        self.request_with_timeout(request, DEFAULT_REQUEST_TIMEOUT_FRAMES)
+    }
+
+    pub fn request_with_timeout<Req, Resp>(
+        &self,
+        request: Req,
+        timeout_frames: u32,
+    ) -> ResponseFuture<Resp>
+    where
+        Req: Send + Sync + 'static,
+        Resp: Send + 'static,
+    {
+        let typeid = TypeId::of::<(Req, Resp)>();
+        if let Some(entry) = self.responders.read().unwrap().get(&typeid) {
+            let requests = entry
+                .as_any()
+                .downcast_ref::<RequestQueue<Req, Resp>>()
+                .unwrap();
+            return requests.push(request, timeout_frames);
+        }
+        ResponseFuture::no_responder()
+    }
+
+    pub fn process_requests<Req, Resp, F>(&self, f: F)
+    where
+        Req: Send + Sync + 'static,
+        Resp: Send + 'static,
+        F: FnMut(&Req) -> Resp,
+    {
+        let typeid = TypeId::of::<(Req, Resp)>();
+        if let Some(entry) = self.responders.read().unwrap().get(&typeid) {
+            let requests = entry
+                .as_any()
+                .downcast_ref::<RequestQueue<Req, Resp>>()
+                .unwrap();
+            requests.process(f);
+        }
+    }
+}
+
+#[allow(dead_code)]
+fn test_coalescing_and_filtering() {
+    use inox_commands::CommandParser;
+
+    #[derive(Clone, Copy, PartialEq, Debug)]
+    enum TestEvent {
+        Moved(u32, u32),
+        Resized(u32, u32),
+    }
+    impl Message for TestEvent {
+        fn from_command_parser(_command_parser: CommandParser) -> Option<Self> {
+            None
+        }
+        fn compare_and_discard(&self, _other: &Self) -> bool {
+            false
+        }
+    }
+
+    let message_hub = Arc::new(MessageHub::default());
+    let listener = Listener::new(&message_hub);
+    let resize_listener = Listener::new(&message_hub);
+    listener.register_coalesced(|e: &TestEvent| std::mem::discriminant(e));
+    resize_listener.register_filtered(|e: &TestEvent| matches!(e, TestEvent::Resized(..)));
+
+    message_hub.send_event(TestEvent::Moved(0, 0));
+    message_hub.send_event(TestEvent::Resized(10, 10));
+    message_hub.send_event(TestEvent::Moved(1, 1));
+    message_hub.send_event(TestEvent::Resized(20, 20));
+    message_hub.send_event(TestEvent::Moved(2, 2));
+    message_hub.flush();
+
+    let mut events = Vec::new();
+    listener.process_messages(|e: &TestEvent| events.push(*e));
+    assert_eq!(
+        events,
+        vec![TestEvent::Resized(20, 20), TestEvent::Moved(2, 2)]
+    );
+    let mut events = Vec::new();
+    resize_listener.process_messages(|e: &TestEvent| events.push(*e));
+    assert_eq!(events, vec![TestEvent::Resized(20, 20)]);
+
+    listener.unregister::<TestEvent>();
+    resize_listener.unregister::<TestEvent>();
+}
+
+#[allow(dead_code)]
+fn test_process_latest() {
+    use inox_commands::CommandParser;
+
+    #[derive(Clone, Copy, PartialEq, Debug)]
+    struct ResizeEvent(u32, u32);
+    impl Message for ResizeEvent {
+        fn from_command_parser(_command_parser: CommandParser) -> Option<Self> {
+            None
+        }
+        fn compare_and_discard(&self, _other: &Self) -> bool {
+            false
+        }
+    }
+
+    let message_hub = Arc::new(MessageHub::default());
+    let listener = Listener::new(&message_hub);
+    listener.register::<ResizeEvent>();
+
+    message_hub.send_event(ResizeEvent(10, 10));
+    message_hub.send_event(ResizeEvent(20, 20));
+    message_hub.send_event(ResizeEvent(30, 15));
+    message_hub.flush();
+
+    let mut events = Vec::new();
+    listener.process_latest(|e: &ResizeEvent| events.push(*e));
+    assert_eq!(events, vec![ResizeEvent(30, 15)]);
+
+    //The older messages have been dropped with the handled one
+    let mut events = Vec::new();
+    listener.process_messages(|e: &ResizeEvent| events.push(*e));
+    listener.process_latest(|e: &ResizeEvent| events.push(*e));
+    assert!(events.is_empty());
+
+    listener.unregister::<ResizeEvent>();
+}
+
+#[allow(dead_code)]
+fn test_process_in_order() {
+    use inox_commands::CommandParser;
+    use std::cell::RefCell;
+
+    #[derive(Clone, Copy, PartialEq, Debug)]
+    enum ResourceTestEvent {
+        Changed(u32),
+        Destroyed(u32),
+    }
+    #[derive(Clone, Copy, PartialEq, Debug)]
+    struct LoadedTestEvent(u32);
+    impl Message for ResourceTestEvent {
+        fn from_command_parser(_command_parser: CommandParser) -> Option<Self> {
+            None
+        }
+        fn compare_and_discard(&self, _other: &Self) -> bool {
+            false
+        }
+    }
+    impl Message for LoadedTestEvent {
+        fn from_command_parser(_command_parser: CommandParser) -> Option<Self> {
+            None
+        }
+        fn compare_and_discard(&self, _other: &Self) -> bool {
+            false
+        }
+    }
+
+    let message_hub = Arc::new(MessageHub::default());
+    let listener = Listener::new(&message_hub);
+    listener
+        .register::<ResourceTestEvent>()
+        .register::<LoadedTestEvent>();
+
+    message_hub.send_event(LoadedTestEvent(1));
+    message_hub.send_event(ResourceTestEvent::Changed(1));
+    message_hub.send_event(ResourceTestEvent::Destroyed(1));
+    message_hub.send_event(LoadedTestEvent(1));
+    message_hub.send_event(ResourceTestEvent::Changed(1));
+    message_hub.flush();
+
+    let events = RefCell::new(Vec::new());
+    listener
+        .process_in_order()
+        .process_messages(|e: &ResourceTestEvent| events.borrow_mut().push(format!("{e:?}")))
+        .process_messages(|e: &LoadedTestEvent| events.borrow_mut().push(format!("{e:?}")))
+        .deliver();
+    assert_eq!(
+        events.into_inner(),
+        vec![
+            "LoadedTestEvent(1)",
+            "Changed(1)",
+            "Destroyed(1)",
+            "LoadedTestEvent(1)",
+            "Changed(1)",
+        ]
+    );
+
+    //Messages not processed in a frame stay before the ones sent later
+    message_hub.send_event(ResourceTestEvent::Destroyed(2));
+    message_hub.flush();
+    message_hub.send_event(LoadedTestEvent(2));
+    message_hub.send_event(ResourceTestEvent::Changed(2));
+    message_hub.flush();
+    let events = RefCell::new(Vec::new());
+    listener
+        .process_in_order()
+        .process_messages(|e: &LoadedTestEvent| events.borrow_mut().push(format!("{e:?}")))
+        .process_messages(|e: &ResourceTestEvent| events.borrow_mut().push(format!("{e:?}")))
+        .deliver();
+    assert_eq!(
+        events.into_inner(),
+        vec!["Destroyed(2)", "LoadedTestEvent(2)", "Changed(2)"]
+    );
+
+    listener
+        .unregister::<ResourceTestEvent>()
+        .unregister::<LoadedTestEvent>();
+}
+
+#[test]
+fn test() {
+    test_coalescing_and_filtering();
+    test_process_latest();
+    test_process_in_order();
 }
@@ -0,0 +1,267 @@
+use std::{
+    any::Any,
+    future::Future,
+    marker::PhantomData,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll, Waker},
+};
+
+use crate::MessageHubRc;
+
+//Requests not answered within this number of MessageHub::flush() are completed with TimedOut
+pub const DEFAULT_REQUEST_TIMEOUT_FRAMES: u32 = 300;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestError {
+    NoResponder,
+    TimedOut,
+}
+
+pub type RequestResult<Resp> = Result<Resp, RequestError>;
+
+struct ResponseState<Resp> {
+    result: Option<RequestResult<Resp>>,
+    waker: Option<Waker>,
+}
+
+type ResponseStateRc<Resp> = Arc<Mutex<ResponseState<Resp>>>;
+
+fn complete<Resp>(state: &ResponseStateRc<Resp>, result: RequestResult<Resp>) {
+    let mut state = state.lock().unwrap();
+    state.result = Some(result);
+    if let Some(waker) = state.waker.take() {
+        waker.wake();
+    }
+}
+
+pub struct ResponseFuture<Resp> {
+    state: ResponseStateRc<Resp>,
+}
+
+impl<Resp> ResponseFuture<Resp> {
+    fn new() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(ResponseState {
+                result: None,
+                waker: None,
+            })),
+        }
+    }
+    pub(crate) fn no_responder() -> Self {
+        let future = Self::new();
+        complete(&future.state, Err(RequestError::NoResponder));
+        future
+    }
+    //Non blocking check for systems that poll once per frame instead of awaiting
+    pub fn try_take(&self) -> Option<RequestResult<Resp>> {
+        self.state.lock().unwrap().result.take()
+    }
+}
+
+impl<Resp> Future for ResponseFuture<Resp> {
+    type Output = RequestResult<Resp>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.state.lock().unwrap();
+        match state.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+struct PendingRequest<Req, Resp> {
+    request: Req,
+    state: ResponseStateRc<Resp>,
+    remaining_frames: u32,
+}
+
+pub(crate) trait RequestQueueTrait: Send + Sync + Any {
+    fn add_responder(&self);
+    //Returns true when the last responder has been removed
+    fn remove_responder(&self) -> bool;
+    fn update(&self);
+    fn cancel(&self);
+    fn as_any(&self) -> &dyn Any;
+}
+
+pub(crate) struct RequestQueue<Req, Resp> {
+    pending: Mutex<Vec<PendingRequest<Req, Resp>>>,
+    responders_count: AtomicU32,
+}
+
+impl<Req, Resp> Default for RequestQueue<Req, Resp> {
+    fn default() -> Self {
+        Self {
+            pending: Mutex::new(Vec::new()),
+            responders_count: AtomicU32::new(0),
+        }
+    }
+}
+
+impl<Req, Resp> RequestQueue<Req, Resp>
+where
+    Req: Send + Sync + 'static,
+    Resp: Send + 'static,
+{
+    pub(crate) fn push(&self, request: Req, timeout_frames: u32) -> ResponseFuture<Resp> {
+        let future = ResponseFuture::new();
+        self.pending.lock().unwrap().push(PendingRequest {
+            request,
+            state: future.state.clone(),
+            remaining_frames: timeout_frames,
+        });
+        future
+    }
+    pub(crate) fn process<F>(&self, mut f: F)
+    where
+        F: FnMut(&Req) -> Resp,
+    {
+        //Taken out of the lock so that the responder can send other requests
+        let pending = std::mem::take(&mut *self.pending.lock().unwrap());
+        pending.into_iter().for_each(|p| {
+            let response = f(&p.request);
+            complete(&p.state, Ok(response));
+        });
+    }
+}
+
+impl<Req, Resp> RequestQueueTrait for RequestQueue<Req, Resp>
+where
+    Req: Send + Sync + 'static,
+    Resp: Send + 'static,
+{
+    fn add_responder(&self) {
+        self.responders_count.fetch_add(1, Ordering::SeqCst);
+    }
+    fn remove_responder(&self) -> bool {
+        self.responders_count.fetch_sub(1, Ordering::SeqCst) == 1
+    }
+    fn update(&self) {
+        self.pending.lock().unwrap().retain_mut(|p| {
+            //Nobody is waiting for the response anymore
+            if Arc::strong_count(&p.state) == 1 {
+                return false;
+            }
+            p.remaining_frames = p.remaining_frames.saturating_sub(1);
+            if p.remaining_frames == 0 {
+                complete(&p.state, Err(RequestError::TimedOut));
+                return false;
+            }
+            true
+        });
+    }
+    fn cancel(&self) {
+        self.pending.lock().unwrap().drain(..).for_each(|p| {
+            complete(&p.state, Err(RequestError::NoResponder));
+        });
+    }
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+//Answers the requests of type Req sent with MessageHub::request()
+//while it or one of its clones is alive there is a responder registered for them
+pub struct Responder<Req, Resp>
+where
+    Req: Send + Sync + 'static,
+    Resp: Send + 'static,
+{
+    message_hub: MessageHubRc,
+    _marker: PhantomData<fn(Req) -> Resp>,
+}
+
+impl<Req, Resp> Responder<Req, Resp>
+where
+    Req: Send + Sync + 'static,
+    Resp: Send + 'static,
+{
+    pub fn new(message_hub: &MessageHubRc) -> Self {
+        message_hub.register_responder::<Req, Resp>();
+        Self {
+            message_hub: message_hub.clone(),
+            _marker: PhantomData,
+        }
+    }
+    #[inline]
+    pub fn process_requests<F>(&self, f: F) -> &Self
+    where
+        F: FnMut(&Req) -> Resp,
+    {
+        self.message_hub.process_requests::<Req, Resp, F>(f);
+        self
+    }
+}
+
+impl<Req, Resp> Clone for Responder<Req, Resp>
+where
+    Req: Send + Sync + 'static,
+    Resp: Send + 'static,
+{
+    fn clone(&self) -> Self {
+        Self::new(&self.message_hub)
+    }
+}
+
+impl<Req, Resp> Drop for Responder<Req, Resp>
+where
+    Req: Send + Sync + 'static,
+    Resp: Send + 'static,
+{
+    fn drop(&mut self) {
+        self.message_hub.unregister_responder::<Req, Resp>();
+    }
+}
+
+#[allow(dead_code)]
+fn test_request() {
+    use crate::MessageHub;
+
+    struct ResolutionRequest;
+    let message_hub = Arc::new(MessageHub::default());
+
+    let unanswered = message_hub.request::<ResolutionRequest, (u32, u32)>(ResolutionRequest);
+    assert_eq!(unanswered.try_take(), Some(Err(RequestError::NoResponder)));
+
+    let responder = Responder::<ResolutionRequest, (u32, u32)>::new(&message_hub);
+    let response = message_hub.request::<ResolutionRequest, (u32, u32)>(ResolutionRequest);
+    assert_eq!(response.try_take(), None);
+    responder.process_requests(|_| (1920, 1080));
+    assert_eq!(response.try_take(), Some(Ok((1920, 1080))));
+
+    let response =
+        message_hub.request_with_timeout::<ResolutionRequest, (u32, u32)>(ResolutionRequest, 2);
+    message_hub.flush();
+    assert_eq!(response.try_take(), None);
+    message_hub.flush();
+    assert_eq!(response.try_take(), Some(Err(RequestError::TimedOut)));
+
+    //The requests are still answered while a clone of the responder is alive
+    let other_responder = responder.clone();
+    drop(responder);
+    let response = message_hub.request::<ResolutionRequest, (u32, u32)>(ResolutionRequest);
+    other_responder.process_requests(|_| (800, 600));
+    assert_eq!(response.try_take(), Some(Ok((800, 600))));
+    let another_responder = Responder::<ResolutionRequest, (u32, u32)>::new(&message_hub);
+    drop(other_responder);
+    let response = message_hub.request::<ResolutionRequest, (u32, u32)>(ResolutionRequest);
+    another_responder.process_requests(|_| (640, 480));
+    assert_eq!(response.try_take(), Some(Ok((640, 480))));
+
+    let response = message_hub.request::<ResolutionRequest, (u32, u32)>(ResolutionRequest);
+    drop(another_responder);
+    assert_eq!(response.try_take(), Some(Err(RequestError::NoResponder)));
+}
+
+#[test]
+fn test() {
+    test_request();
+}
@@ -6,6 +6,7 @@ pub mod macros;
 pub mod node;
 pub mod node_registry;
 pub mod node_tree;
+pub mod node_tree_builder;
 pub mod pin;
 
 pub use link::*;
@@ -16,6 +17,7 @@ pub use macros::*;
 pub use node::*;
 pub use node_registry::*;
 pub use node_tree::*;
+pub use node_tree_builder::*;
 pub use pin::*;
 
 use inox_resources::SharedDataRc;
@@ -2,7 +2,7 @@ use inox_serialize::{Deserialize, Serialize};
 
 use crate::{LogicNodeRegistry, Node};
 
-#[derive(Default, Serialize, Deserialize, Clone)]
+#[derive(Default, Serialize, Deserialize, PartialEq, Eq, Clone)]
 #[serde(crate = "inox_serialize")]
 pub struct NodeLink {
     from_node: String,
@@ -54,6 +54,9 @@ impl From<NodeTree> for LogicData {
 }
 
 impl LogicData {
+    pub fn tree(&self) -> &NodeTree {
+        &self.tree
+    }
     pub fn context(&self) -> &LogicContext {
         &self.context
     }
@@ -63,7 +66,37 @@ impl LogicData {
     pub fn is_initialized(&self) -> bool {
         !self.execution_state.is_empty()
     }
+    //Same nodes, with the same pins, connected by the same links
+    pub fn has_same_topology(&self, other: &LogicData) -> bool {
+        let nodes = self.tree.nodes();
+        let other_nodes = other.tree.nodes();
+        nodes.len() == other_nodes.len()
+            && nodes.iter().zip(other_nodes.iter()).all(|(n, o)| {
+                n.name() == o.name()
+                    && n.as_any().type_id() == o.as_any().type_id()
+                    && n.node().has_same_pins(o.node())
+                    && o.node().has_same_pins(n.node())
+            })
+            && self.tree.links() == other.tree.links()
+    }
+    //Swaps the node tree keeping the context.
+    //Execution state is kept only when the topology is unchanged, otherwise it starts over
+    pub fn reload(&mut self, data: LogicData) -> bool {
+        let is_same_topology = self.has_same_topology(&data);
+        let active_nodes = std::mem::take(&mut self.active_nodes);
+        let execution_state = std::mem::take(&mut self.execution_state);
+        self.tree = data.tree;
+        self.init();
+        if is_same_topology && !execution_state.is_empty() {
+            self.active_nodes = active_nodes;
+            self.execution_state = execution_state;
+        }
+        is_same_topology
+    }
     pub fn init(&mut self) {
+        self.active_nodes.clear();
+        self.nodes_info.clear();
+        self.execution_state.clear();
         let nodes = self.tree.nodes();
         nodes.iter().enumerate().for_each(|(node_index, n)| {
             if !n.node().has_input::<LogicExecution>() && n.node().has_output::<LogicExecution>() {
@@ -244,3 +277,43 @@ impl LogicData {
         new_nodes_to_execute
     }
 }
+
+#[allow(dead_code)]
+fn test_reload() {
+    use crate::{RustExampleNode, ScriptInitNode};
+
+    let mut tree = NodeTree::default();
+    tree.add_default_node::<ScriptInitNode>("ScriptInitNode");
+    tree.add_default_node::<RustExampleNode>("NodeA");
+    tree.add_link("ScriptInitNode", "NodeA", "Execute", "in_execute");
+
+    let mut logic_data = LogicData::from(tree.clone());
+    logic_data.init();
+    assert_eq!(logic_data.active_nodes.len(), 1);
+    logic_data.execute(&Duration::from_millis(30));
+    //The init node runs only once
+    assert!(logic_data.active_nodes.is_empty());
+    let execution_state = logic_data.execution_state.clone();
+
+    //Same topology keeps the execution state, the init node is not run again
+    assert!(logic_data.reload(LogicData::from(tree.clone())));
+    assert!(logic_data.active_nodes.is_empty());
+    assert!(logic_data.execution_state == execution_state);
+    assert_eq!(logic_data.nodes_info.len(), 2);
+
+    //A new node resets the execution state
+    tree.add_default_node::<RustExampleNode>("NodeB");
+    tree.add_link("NodeA", "NodeB", "out_execute", "in_execute");
+    assert!(!logic_data.reload(LogicData::from(tree)));
+    assert_eq!(logic_data.active_nodes.len(), 1);
+    assert_eq!(logic_data.nodes_info.len(), 3);
+    assert!(logic_data
+        .execution_state
+        .iter()
+        .all(|s| *s == NodeState::Active));
+}
+
+#[test]
+fn test() {
+    test_reload();
+}
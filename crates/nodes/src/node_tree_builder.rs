@@ -0,0 +1,274 @@
+use inox_serialize::Serialize;
+
+use crate::{LogicData, NodeTrait, NodeTree, Pin, PinId};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NodeTreeError {
+    DuplicatedNode(String),
+    MissingNode(String),
+    MissingPin {
+        node: String,
+        pin: String,
+    },
+    PinTypeMismatch {
+        from_node: String,
+        from_pin: String,
+        from_type: String,
+        to_node: String,
+        to_pin: String,
+        to_type: String,
+    },
+    Cycle(String),
+}
+
+impl std::fmt::Display for NodeTreeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NodeTreeError::DuplicatedNode(node) => {
+                write!(f, "A node named {node} is already in the tree")
+            }
+            NodeTreeError::MissingNode(node) => write!(f, "Unable to find node {node}"),
+            NodeTreeError::MissingPin { node, pin } => {
+                write!(f, "Unable to find pin {pin} of node {node}")
+            }
+            NodeTreeError::PinTypeMismatch {
+                from_node,
+                from_pin,
+                from_type,
+                to_node,
+                to_pin,
+                to_type,
+            } => {
+                write!(
+                    f,
+                    "Unable to connect pin {from_pin} of node {from_node} with type {from_type} \
+                    to pin {to_pin} of node {to_node} with type {to_type}"
+                )
+            }
+            NodeTreeError::Cycle(node) => {
+                write!(
+                    f,
+                    "Node {node} is linked back to itself through its outputs"
+                )
+            }
+        }
+    }
+}
+
+//Builds a node tree from code, checking every link while it's added.
+//Custom nodes need to be registered in the LogicNodeRegistry only to be serialized
+#[derive(Default)]
+pub struct NodeTreeBuilder {
+    tree: NodeTree,
+}
+
+impl NodeTreeBuilder {
+    pub fn add_node<N>(&mut self, name: &str) -> Result<&mut Self, NodeTreeError>
+    where
+        N: NodeTrait + Default + Serialize + 'static,
+    {
+        if self.tree.find_node(name).is_some() {
+            return Err(NodeTreeError::DuplicatedNode(name.to_string()));
+        }
+        self.tree.add_default_node::<N>(name);
+        Ok(self)
+    }
+    pub fn add(
+        &mut self,
+        node: Box<dyn NodeTrait + Send + Sync>,
+    ) -> Result<&mut Self, NodeTreeError> {
+        if self.tree.find_node(node.name()).is_some() {
+            return Err(NodeTreeError::DuplicatedNode(node.name().to_string()));
+        }
+        self.tree.add_node(node);
+        Ok(self)
+    }
+    pub fn set_input<V>(
+        &mut self,
+        node: &str,
+        pin: &str,
+        value: V,
+    ) -> Result<&mut Self, NodeTreeError>
+    where
+        V: Pin,
+    {
+        let n = self
+            .tree
+            .find_node_mut(node)
+            .ok_or_else(|| NodeTreeError::MissingNode(node.to_string()))?;
+        let input =
+            n.node_mut()
+                .get_input_mut::<V>(pin)
+                .ok_or_else(|| NodeTreeError::MissingPin {
+                    node: node.to_string(),
+                    pin: pin.to_string(),
+                })?;
+        *input = value;
+        Ok(self)
+    }
+    //Links an output pin to an input pin with the same type
+    pub fn connect(
+        &mut self,
+        from_node: &str,
+        from_pin: &str,
+        to_node: &str,
+        to_pin: &str,
+    ) -> Result<&mut Self, NodeTreeError> {
+        let find_pin = |node: &str, pin: &str, is_input: bool| {
+            let n = self
+                .tree
+                .find_node(node)
+                .ok_or_else(|| NodeTreeError::MissingNode(node.to_string()))?;
+            let pins = if is_input {
+                n.node().inputs()
+            } else {
+                n.node().outputs()
+            };
+            pins.get(&PinId::new(pin))
+                .map(|p| (p.get_type_id(), p.get_type_name()))
+                .ok_or_else(|| NodeTreeError::MissingPin {
+                    node: node.to_string(),
+                    pin: pin.to_string(),
+                })
+        };
+        let (from_type_id, from_type) = find_pin(from_node, from_pin, false)?;
+        let (to_type_id, to_type) = find_pin(to_node, to_pin, true)?;
+        if from_type_id != to_type_id {
+            return Err(NodeTreeError::PinTypeMismatch {
+                from_node: from_node.to_string(),
+                from_pin: from_pin.to_string(),
+                from_type: from_type.to_string(),
+                to_node: to_node.to_string(),
+                to_pin: to_pin.to_string(),
+                to_type: to_type.to_string(),
+            });
+        }
+        self.tree.add_link(from_node, to_node, from_pin, to_pin);
+        Ok(self)
+    }
+    //A node can't depend on itself: inputs are resolved executing the linked nodes first
+    pub fn validate(&self) -> Result<(), NodeTreeError> {
+        #[derive(Clone, Copy)]
+        enum Visit {
+            None,
+            InProgress,
+            Done,
+        }
+        fn visit(node: usize, links: &[Vec<usize>], visits: &mut [Visit]) -> Result<(), usize> {
+            match visits[node] {
+                Visit::Done => return Ok(()),
+                Visit::InProgress => return Err(node),
+                Visit::None => {}
+            }
+            visits[node] = Visit::InProgress;
+            for &next in &links[node] {
+                visit(next, links, visits)?;
+            }
+            visits[node] = Visit::Done;
+            Ok(())
+        }
+
+        let nodes = self.tree.nodes();
+        let mut links = vec![Vec::new(); nodes.len()];
+        self.tree.links().iter().for_each(|l| {
+            if let (Some(from), Some(to)) = (
+                self.tree.find_node_index(l.from_node()),
+                self.tree.find_node_index(l.to_node()),
+            ) {
+                links[from].push(to);
+            }
+        });
+        let mut visits = vec![Visit::None; nodes.len()];
+        (0..nodes.len())
+            .try_for_each(|node| visit(node, &links, &mut visits))
+            .map_err(|n| NodeTreeError::Cycle(nodes[n].name().to_string()))
+    }
+    pub fn build(self) -> Result<NodeTree, NodeTreeError> {
+        self.validate()?;
+        Ok(self.tree)
+    }
+    //Logic ready to be executed
+    pub fn build_logic(self) -> Result<LogicData, NodeTreeError> {
+        let mut logic_data = LogicData::from(self.build()?);
+        logic_data.init();
+        Ok(logic_data)
+    }
+}
+
+#[allow(dead_code)]
+fn test_node_tree_builder() {
+    use crate::{RustExampleNode, ScriptInitNode};
+
+    let mut builder = NodeTreeBuilder::default();
+    builder
+        .add_node::<ScriptInitNode>("Init")
+        .unwrap()
+        .add_node::<RustExampleNode>("NodeA")
+        .unwrap()
+        .add_node::<RustExampleNode>("NodeB")
+        .unwrap()
+        .set_input("NodeA", "in_int", 19_i32)
+        .unwrap()
+        .connect("Init", "Execute", "NodeA", "in_execute")
+        .unwrap()
+        .connect("NodeA", "out_execute", "NodeB", "in_execute")
+        .unwrap()
+        .connect("NodeA", "out_int", "NodeB", "in_int")
+        .unwrap();
+
+    assert_eq!(
+        builder.add_node::<RustExampleNode>("NodeA").err(),
+        Some(NodeTreeError::DuplicatedNode("NodeA".to_string()))
+    );
+    assert_eq!(
+        builder
+            .connect("NodeA", "out_int", "NodeB", "in_float")
+            .err(),
+        Some(NodeTreeError::PinTypeMismatch {
+            from_node: "NodeA".to_string(),
+            from_pin: "out_int".to_string(),
+            from_type: "i32".to_string(),
+            to_node: "NodeB".to_string(),
+            to_pin: "in_float".to_string(),
+            to_type: "f32".to_string(),
+        })
+    );
+    //Outputs can't be linked as inputs
+    assert_eq!(
+        builder
+            .connect("NodeA", "out_int", "NodeB", "out_int")
+            .err(),
+        Some(NodeTreeError::MissingPin {
+            node: "NodeB".to_string(),
+            pin: "out_int".to_string(),
+        })
+    );
+    assert_eq!(
+        builder.set_input("NodeC", "in_int", 1_i32).err(),
+        Some(NodeTreeError::MissingNode("NodeC".to_string()))
+    );
+    assert!(builder.validate().is_ok());
+
+    let mut logic_data = builder.build_logic().unwrap();
+    logic_data.execute(&std::time::Duration::from_millis(30));
+    let node_b = logic_data.tree().find_node("NodeB").unwrap();
+    assert_eq!(node_b.node().get_output::<i32>("out_int"), Some(&19));
+
+    let mut builder = NodeTreeBuilder::default();
+    builder
+        .add_node::<RustExampleNode>("NodeA")
+        .unwrap()
+        .add_node::<RustExampleNode>("NodeB")
+        .unwrap()
+        .connect("NodeA", "out_int", "NodeB", "in_int")
+        .unwrap()
+        .connect("NodeB", "out_float", "NodeA", "in_float")
+        .unwrap();
+    assert!(matches!(builder.validate(), Err(NodeTreeError::Cycle(_))));
+    assert!(builder.build().is_err());
+}
+
+#[test]
+fn test() {
+    test_node_tree_builder();
+}
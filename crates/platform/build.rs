@@ -63,6 +63,9 @@ fn main() {
         link_library("gdi32");
         link_library("dwmapi");
         link_library("uxtheme");
+        link_library("shell32");
+        link_library("ole32");
+        link_library("imm32");
     } else if is_android_platform || is_web_platform {
     } else {
         panic!("Platform {target_os} not yet supported - Check build.rs to setup this platform to build from source");
@@ -0,0 +1,36 @@
+use inox_commands::CommandParser;
+use inox_messenger::implement_message;
+
+use crate::Window;
+
+#[derive(Debug, PartialOrd, PartialEq, Eq, Clone)]
+pub enum ClipboardEvent {
+    //Text pasted from the system clipboard, sent where it can't be read on request (web)
+    Paste(String),
+}
+implement_message!(
+    ClipboardEvent,
+    clipboard_event_from_command_parser,
+    compare_and_discard
+);
+
+impl ClipboardEvent {
+    fn compare_and_discard(&self, _other: &Self) -> bool {
+        false
+    }
+    fn clipboard_event_from_command_parser(command_parser: CommandParser) -> Option<Self> {
+        if command_parser.has("paste") {
+            let values = command_parser.get_values_of::<String>("paste");
+            return Some(ClipboardEvent::Paste(values[0].clone()));
+        }
+        None
+    }
+}
+
+pub fn clipboard_text() -> Option<String> {
+    Window::clipboard_text()
+}
+
+pub fn set_clipboard_text(text: &str) -> bool {
+    Window::set_clipboard_text(text)
+}
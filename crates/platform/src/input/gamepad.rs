@@ -1,3 +1,13 @@
+use std::{collections::HashMap, hash::Hash};
+
+use inox_commands::CommandParser;
+use inox_messenger::implement_message;
+
+use crate::InputState;
+
+//Stick values below the deadzone are reported as 0
+pub const DEFAULT_GAMEPAD_DEADZONE: f32 = 0.15;
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum GamepadButton {
     South,
@@ -32,3 +42,122 @@ pub enum GamepadAxis {
     DPadX,
     DPadY,
 }
+
+//Index is stable while the gamepad is connected and it's given back
+//to the same gamepad when it's reconnected, if nobody took its place
+#[derive(Debug, PartialOrd, PartialEq, Clone, Copy)]
+pub enum GamepadEvent {
+    Connected(usize),
+    Disconnected(usize),
+    AxisChanged(usize, GamepadAxis, f32),
+    ButtonChanged(usize, GamepadButton, InputState),
+}
+implement_message!(
+    GamepadEvent,
+    gamepad_event_from_command_parser,
+    compare_and_discard
+);
+
+impl GamepadEvent {
+    fn compare_and_discard(&self, _other: &Self) -> bool {
+        false
+    }
+    fn gamepad_event_from_command_parser(command_parser: CommandParser) -> Option<Self> {
+        if command_parser.has("gamepad_connected") {
+            let values = command_parser.get_values_of("gamepad_connected");
+            return Some(GamepadEvent::Connected(values[0]));
+        } else if command_parser.has("gamepad_disconnected") {
+            let values = command_parser.get_values_of("gamepad_disconnected");
+            return Some(GamepadEvent::Disconnected(values[0]));
+        }
+        None
+    }
+}
+
+//Rescales the values outside the deadzone so that they still go from 0 to 1
+pub fn apply_deadzone(value: f32, deadzone: f32) -> f32 {
+    if value.abs() <= deadzone {
+        return 0.;
+    }
+    value.signum() * ((value.abs() - deadzone) / (1. - deadzone)).min(1.)
+}
+
+pub struct GamepadIndices<Id> {
+    indices: HashMap<Id, usize>,
+    connected: Vec<Id>,
+}
+
+impl<Id> Default for GamepadIndices<Id> {
+    fn default() -> Self {
+        Self {
+            indices: HashMap::new(),
+            connected: Vec::new(),
+        }
+    }
+}
+
+impl<Id> GamepadIndices<Id>
+where
+    Id: Copy + Eq + Hash,
+{
+    pub fn connect(&mut self, id: Id) -> usize {
+        if !self.connected.contains(&id) {
+            self.connected.push(id);
+        }
+        if let Some(&index) = self.indices.get(&id) {
+            if !self.is_used_by_other(id, index) {
+                return index;
+            }
+        }
+        let index = (0..)
+            .find(|&index| !self.is_used_by_other(id, index))
+            .unwrap();
+        //A disconnected gamepad loses its index when another one takes it
+        self.indices.retain(|_, i| *i != index);
+        self.indices.insert(id, index);
+        index
+    }
+    pub fn disconnect(&mut self, id: Id) -> Option<usize> {
+        self.connected.retain(|c| *c != id);
+        self.indices.get(&id).copied()
+    }
+    pub fn index(&self, id: Id) -> Option<usize> {
+        if self.connected.contains(&id) {
+            return self.indices.get(&id).copied();
+        }
+        None
+    }
+    fn is_used_by_other(&self, id: Id, index: usize) -> bool {
+        self.connected
+            .iter()
+            .any(|c| *c != id && self.indices.get(c) == Some(&index))
+    }
+}
+
+#[allow(dead_code)]
+fn test_gamepad() {
+    assert_eq!(apply_deadzone(0.1, DEFAULT_GAMEPAD_DEADZONE), 0.);
+    assert_eq!(apply_deadzone(-0.15, DEFAULT_GAMEPAD_DEADZONE), 0.);
+    assert_eq!(apply_deadzone(1., DEFAULT_GAMEPAD_DEADZONE), 1.);
+    assert_eq!(apply_deadzone(-1., DEFAULT_GAMEPAD_DEADZONE), -1.);
+    assert!((apply_deadzone(0.575, DEFAULT_GAMEPAD_DEADZONE) - 0.5).abs() < 1e-5);
+
+    let mut indices = GamepadIndices::default();
+    assert_eq!(indices.connect("a"), 0);
+    assert_eq!(indices.connect("b"), 1);
+    assert_eq!(indices.disconnect("a"), Some(0));
+    assert_eq!(indices.index("a"), None);
+    assert_eq!(indices.index("b"), Some(1));
+    //Reconnecting gives back the same index
+    assert_eq!(indices.connect("a"), 0);
+    assert_eq!(indices.disconnect("a"), Some(0));
+    //Until another gamepad takes it
+    assert_eq!(indices.connect("c"), 0);
+    assert_eq!(indices.connect("a"), 2);
+    assert_eq!(indices.disconnect("d"), None);
+}
+
+#[test]
+fn test() {
+    test_gamepad();
+}
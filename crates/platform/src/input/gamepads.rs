@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+
+use gilrs::{Axis, Button, EventType, GamepadId, Gilrs};
+use inox_messenger::MessageHubRc;
+
+use crate::{
+    apply_deadzone, GamepadAxis, GamepadButton, GamepadEvent, GamepadIndices, InputState,
+    DEFAULT_GAMEPAD_DEADZONE,
+};
+
+//Polls the connected controllers and sends their input as GamepadEvent
+pub struct Gamepads {
+    gilrs: Option<Gilrs>,
+    message_hub: MessageHubRc,
+    indices: GamepadIndices<GamepadId>,
+    axes: HashMap<(usize, GamepadAxis), f32>,
+    deadzone: f32,
+}
+
+impl Gamepads {
+    pub fn new(message_hub: &MessageHubRc) -> Self {
+        message_hub.register_type::<GamepadEvent>();
+        let gilrs = match Gilrs::new() {
+            Ok(gilrs) => Some(gilrs),
+            Err(e) => {
+                eprintln!("Unable to initialize gamepads support: {e}");
+                None
+            }
+        };
+        let mut gamepads = Self {
+            gilrs,
+            message_hub: message_hub.clone(),
+            indices: GamepadIndices::default(),
+            axes: HashMap::new(),
+            deadzone: DEFAULT_GAMEPAD_DEADZONE,
+        };
+        let connected = gamepads
+            .gilrs
+            .as_ref()
+            .map(|gilrs| gilrs.gamepads().map(|(id, _)| id).collect::<Vec<_>>())
+            .unwrap_or_default();
+        connected.into_iter().for_each(|id| {
+            let index = gamepads.indices.connect(id);
+            gamepads
+                .message_hub
+                .send_event(GamepadEvent::Connected(index));
+        });
+        gamepads
+    }
+    pub fn deadzone(&self) -> f32 {
+        self.deadzone
+    }
+    pub fn set_deadzone(&mut self, deadzone: f32) -> &mut Self {
+        self.deadzone = deadzone.clamp(0., 0.99);
+        self
+    }
+    pub fn update(&mut self) {
+        let mut events = Vec::new();
+        if let Some(gilrs) = &mut self.gilrs {
+            while let Some(event) = gilrs.next_event() {
+                events.push((event.id, event.event));
+            }
+        }
+        events
+            .into_iter()
+            .for_each(|(id, event)| self.handle_event(id, event));
+    }
+    fn handle_event(&mut self, id: GamepadId, event: EventType) {
+        match event {
+            EventType::Connected => {
+                let index = self.indices.connect(id);
+                self.message_hub.send_event(GamepadEvent::Connected(index));
+            }
+            EventType::Disconnected => {
+                if let Some(index) = self.indices.disconnect(id) {
+                    self.axes.retain(|(i, _), _| *i != index);
+                    self.message_hub
+                        .send_event(GamepadEvent::Disconnected(index));
+                }
+            }
+            EventType::ButtonPressed(button, _) => {
+                self.send_button(id, button, InputState::JustPressed);
+            }
+            EventType::ButtonReleased(button, _) => {
+                self.send_button(id, button, InputState::JustReleased);
+            }
+            EventType::AxisChanged(axis, value, _) => {
+                let (Some(index), Some(axis)) = (self.indices.index(id), convert_axis(axis)) else {
+                    return;
+                };
+                let value = apply_deadzone(value, self.deadzone);
+                //Values inside the deadzone are sent only once
+                let last_value = self.axes.insert((index, axis), value).unwrap_or(0.);
+                if last_value != value {
+                    self.message_hub
+                        .send_event(GamepadEvent::AxisChanged(index, axis, value));
+                }
+            }
+            _ => {}
+        }
+    }
+    fn send_button(&self, id: GamepadId, button: Button, state: InputState) {
+        if let (Some(index), Some(button)) = (self.indices.index(id), convert_button(button)) {
+            self.message_hub
+                .send_event(GamepadEvent::ButtonChanged(index, button, state));
+        }
+    }
+}
+
+fn convert_axis(axis: Axis) -> Option<GamepadAxis> {
+    match axis {
+        Axis::LeftStickX => Some(GamepadAxis::LeftStickX),
+        Axis::LeftStickY => Some(GamepadAxis::LeftStickY),
+        Axis::LeftZ => Some(GamepadAxis::LeftZ),
+        Axis::RightStickX => Some(GamepadAxis::RightStickX),
+        Axis::RightStickY => Some(GamepadAxis::RightStickY),
+        Axis::RightZ => Some(GamepadAxis::RightZ),
+        Axis::DPadX => Some(GamepadAxis::DPadX),
+        Axis::DPadY => Some(GamepadAxis::DPadY),
+        Axis::Unknown => None,
+    }
+}
+
+fn convert_button(button: Button) -> Option<GamepadButton> {
+    match button {
+        Button::South => Some(GamepadButton::South),
+        Button::East => Some(GamepadButton::East),
+        Button::North => Some(GamepadButton::North),
+        Button::West => Some(GamepadButton::West),
+        Button::C => Some(GamepadButton::C),
+        Button::Z => Some(GamepadButton::Z),
+        Button::LeftTrigger => Some(GamepadButton::LeftTrigger),
+        Button::LeftTrigger2 => Some(GamepadButton::LeftTrigger2),
+        Button::RightTrigger => Some(GamepadButton::RightTrigger),
+        Button::RightTrigger2 => Some(GamepadButton::RightTrigger2),
+        Button::Select => Some(GamepadButton::Select),
+        Button::Start => Some(GamepadButton::Start),
+        Button::Mode => Some(GamepadButton::Mode),
+        Button::LeftThumb => Some(GamepadButton::LeftThumb),
+        Button::RightThumb => Some(GamepadButton::RightThumb),
+        Button::DPadUp => Some(GamepadButton::DPadUp),
+        Button::DPadDown => Some(GamepadButton::DPadDown),
+        Button::DPadLeft => Some(GamepadButton::DPadLeft),
+        Button::DPadRight => Some(GamepadButton::DPadRight),
+        Button::Unknown => None,
+    }
+}
@@ -1,9 +1,13 @@
 pub use self::gamepad::*;
+pub use self::gamepads::*;
 pub use self::keyboard::*;
 pub use self::mouse::*;
 pub use self::state::*;
+pub use self::touch::*;
 
 pub mod gamepad;
+pub mod gamepads;
 pub mod keyboard;
 pub mod mouse;
 pub mod state;
+pub mod touch;
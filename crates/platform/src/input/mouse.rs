@@ -18,6 +18,11 @@ pub enum MouseState {
     DoubleClick,
     Down,
     Up,
+    //Sent only while the cursor is grabbed: x and y are the unbounded movement in pixels
+    //and normalized_x and normalized_y the same movement relative to the window size
+    RelativeMove,
+    //Sent when the wheel is scrolled: y is the number of notches, positive away from the user
+    Wheel,
 }
 
 #[derive(Debug, PartialOrd, PartialEq, Clone, Copy)]
@@ -36,6 +41,26 @@ implement_message!(
 );
 
 impl MouseEvent {
+    pub fn relative_move(delta_x: f64, delta_y: f64, width: f32, height: f32) -> Self {
+        Self {
+            x: delta_x,
+            y: delta_y,
+            normalized_x: delta_x as f32 / width,
+            normalized_y: delta_y as f32 / height,
+            button: MouseButton::None,
+            state: MouseState::RelativeMove,
+        }
+    }
+    pub fn wheel(notches: f64) -> Self {
+        Self {
+            x: 0.,
+            y: notches,
+            normalized_x: 0.,
+            normalized_y: notches as _,
+            button: MouseButton::Middle,
+            state: MouseState::Wheel,
+        }
+    }
     fn compare_and_discard(&self, _other: &Self) -> bool {
         false
     }
@@ -50,6 +75,12 @@ impl MouseEvent {
                 button: MouseButton::None,
                 state: MouseState::Move,
             });
+        } else if command_parser.has("mouse_relative_move") {
+            let values = command_parser.get_values_of("mouse_relative_move");
+            return Some(MouseEvent::relative_move(values[0], values[1], 1., 1.));
+        } else if command_parser.has("mouse_wheel") {
+            let values = command_parser.get_values_of("mouse_wheel");
+            return Some(MouseEvent::wheel(values[0]));
         } else if command_parser.has("mouse_left_down") {
             let values = command_parser.get_values_of("mouse_left_down");
             return Some(MouseEvent {
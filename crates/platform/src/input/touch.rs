@@ -0,0 +1,73 @@
+use inox_commands::CommandParser;
+use inox_messenger::implement_message;
+
+#[derive(Debug, Hash, Ord, PartialOrd, PartialEq, Eq, Clone, Copy)]
+pub enum TouchState {
+    Begin,
+    Move,
+    End,
+    //The touch is interrupted by the system and it has to be discarded as if it ended
+    Cancel,
+}
+
+//Every finger on the screen has its own id until it's lifted.
+//x and y are in pixels while normalized_x and normalized_y are relative to the window size
+#[derive(Debug, PartialOrd, PartialEq, Clone, Copy)]
+pub struct TouchEvent {
+    pub id: u64,
+    pub x: f64,
+    pub y: f64,
+    pub normalized_x: f32,
+    pub normalized_y: f32,
+    pub state: TouchState,
+}
+implement_message!(
+    TouchEvent,
+    touch_event_from_command_parser,
+    compare_and_discard
+);
+
+impl TouchEvent {
+    fn compare_and_discard(&self, _other: &Self) -> bool {
+        false
+    }
+    fn touch_event_from_command_parser(command_parser: CommandParser) -> Option<Self> {
+        let commands = [
+            ("touch_begin", TouchState::Begin),
+            ("touch_move", TouchState::Move),
+            ("touch_end", TouchState::End),
+            ("touch_cancel", TouchState::Cancel),
+        ];
+        for (command, state) in commands {
+            if command_parser.has(command) {
+                let values = command_parser.get_values_of::<f64>(command);
+                return Some(TouchEvent {
+                    id: values[0] as _,
+                    x: values[1],
+                    y: values[2],
+                    normalized_x: values[1] as _,
+                    normalized_y: values[2] as _,
+                    state,
+                });
+            }
+        }
+        None
+    }
+    pub fn is_released(&self) -> bool {
+        self.state == TouchState::End || self.state == TouchState::Cancel
+    }
+}
+
+impl Default for TouchEvent {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            id: 0,
+            x: 0.,
+            y: 0.,
+            normalized_x: 0.,
+            normalized_y: 0.,
+            state: TouchState::Move,
+        }
+    }
+}
@@ -2,16 +2,20 @@
 #![allow(clippy::too_many_arguments)]
 
 // Common
+pub use self::clipboard::*;
 pub use self::handle::*;
 pub use self::input::*;
 pub use self::thread::*;
+pub use self::url::*;
 pub use self::watcher::*;
 pub use self::window::*;
 
 //Modules
+pub mod clipboard;
 mod ctypes;
 pub mod handle;
 pub mod thread;
+pub mod url;
 pub mod watcher;
 pub mod window;
 
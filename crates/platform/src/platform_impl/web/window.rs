@@ -1,13 +1,21 @@
 use inox_messenger::MessageHubRc;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use wasm_bindgen::closure::Closure;
+use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
 
 use super::handle::*;
 use crate::handle::*;
 use crate::window::*;
-use crate::{InputState, Key, MouseButton, MouseState};
+use crate::{ClipboardEvent, InputState, Key, MouseButton, MouseState, TouchState};
+
+#[wasm_bindgen]
+extern "C" {
+    //Throws when the page isn't in a secure context and navigator.clipboard is missing
+    #[wasm_bindgen(catch, js_namespace = ["navigator", "clipboard"], js_name = writeText)]
+    fn write_clipboard_text(text: &str) -> Result<JsValue, JsValue>;
+}
 
 impl Window {
     pub fn create_handle(
@@ -44,10 +52,38 @@ impl Window {
         Self::add_mouse_event_listener(events_dispatcher, &canvas, "mousemove", MouseState::Move);
         Self::add_mouse_event_listener(events_dispatcher, &canvas, "mousedown", MouseState::Down);
         Self::add_mouse_event_listener(events_dispatcher, &canvas, "mouseup", MouseState::Up);
+        Self::add_wheel_event_listener(events_dispatcher, &canvas);
+        Self::add_touch_event_listener(events_dispatcher, &canvas, "touchstart", TouchState::Begin);
+        Self::add_touch_event_listener(events_dispatcher, &canvas, "touchmove", TouchState::Move);
+        Self::add_touch_event_listener(events_dispatcher, &canvas, "touchend", TouchState::End);
+        Self::add_touch_event_listener(
+            events_dispatcher,
+            &canvas,
+            "touchcancel",
+            TouchState::Cancel,
+        );
 
         Self::add_key_event_listener(events_dispatcher, &canvas, "keyup", InputState::Released);
         Self::add_key_event_listener(events_dispatcher, &canvas, "keydown", InputState::Pressed);
 
+        Self::add_resize_event_listener(events_dispatcher, &window, *scale_factor);
+        Self::add_focus_event_listener(
+            events_dispatcher,
+            &window,
+            "focus",
+            WindowEvent::FocusGained,
+        );
+        Self::add_focus_event_listener(events_dispatcher, &window, "blur", WindowEvent::FocusLost);
+
+        Self::add_drag_event_listener(events_dispatcher, &canvas, "dragover", |path| {
+            WindowEvent::FileHovered(path)
+        });
+        Self::add_drag_event_listener(events_dispatcher, &canvas, "drop", |path| {
+            WindowEvent::FileDropped(path)
+        });
+        Self::add_drag_leave_event_listener(events_dispatcher, &canvas);
+        Self::add_paste_event_listener(events_dispatcher, &window);
+
         Handle {
             handle_impl: HandleImpl { id: 0 },
         }
@@ -86,6 +122,75 @@ impl Window {
                 button,
                 state,
             });
+            //With the pointer locked the offset doesn't change anymore, only the movement does
+            if state == MouseState::Move && document.pointer_lock_element().is_some() {
+                events_dispatcher.send_event(crate::MouseEvent::relative_move(
+                    event.movement_x() as f64 * (width / rect.width() as f32) as f64,
+                    event.movement_y() as f64 * (height / rect.height() as f32) as f64,
+                    width,
+                    height,
+                ));
+            }
+        }) as Box<dyn FnMut(_)>);
+        canvas
+            .add_event_listener_with_callback(event_name, closure.as_ref().unchecked_ref())
+            .ok();
+        closure.forget();
+    }
+
+    //Browsers use different units for the delta, only its direction is kept as a notch
+    fn add_wheel_event_listener(
+        events_dispatcher: &MessageHubRc,
+        canvas: &web_sys::HtmlCanvasElement,
+    ) {
+        let events_dispatcher = events_dispatcher.clone();
+        let closure = Closure::wrap(Box::new(move |event: web_sys::WheelEvent| {
+            if event.delta_y() != 0. {
+                events_dispatcher.send_event(crate::MouseEvent::wheel(-event.delta_y().signum()));
+            }
+        }) as Box<dyn FnMut(_)>);
+        canvas
+            .add_event_listener_with_callback("wheel", closure.as_ref().unchecked_ref())
+            .ok();
+        closure.forget();
+    }
+
+    //Only the touches that changed are sent, each one with its own id
+    fn add_touch_event_listener(
+        events_dispatcher: &MessageHubRc,
+        canvas: &web_sys::HtmlCanvasElement,
+        event_name: &str,
+        state: TouchState,
+    ) {
+        let events_dispatcher = events_dispatcher.clone();
+        let closure = Closure::wrap(Box::new(move |event: web_sys::TouchEvent| {
+            //Without it the browser scrolls and zooms the page and sends mouse events too
+            event.prevent_default();
+            let window = web_sys::window().unwrap();
+            let document = window.document().unwrap();
+            let canvas = document.get_element_by_id("canvas").unwrap();
+            let canvas: web_sys::HtmlCanvasElement =
+                canvas.dyn_into::<web_sys::HtmlCanvasElement>().unwrap();
+            let rect = canvas.get_bounding_client_rect();
+            let width = canvas.width() as f32;
+            let height = canvas.height() as f32;
+            let touches = event.changed_touches();
+            for i in 0..touches.length() {
+                if let Some(touch) = touches.get(i) {
+                    let x = (touch.client_x() as f32 - rect.left() as f32)
+                        * (width / rect.width() as f32);
+                    let y = (touch.client_y() as f32 - rect.top() as f32)
+                        * (height / rect.height() as f32);
+                    events_dispatcher.send_event(crate::TouchEvent {
+                        id: touch.identifier() as _,
+                        x: x as _,
+                        y: y as _,
+                        normalized_x: x / width,
+                        normalized_y: y / height,
+                        state,
+                    });
+                }
+            }
         }) as Box<dyn FnMut(_)>);
         canvas
             .add_event_listener_with_callback(event_name, closure.as_ref().unchecked_ref())
@@ -112,13 +217,175 @@ impl Window {
         closure.forget();
     }
 
+    //Moving the browser to a monitor with a different pixel ratio sends a resize too
+    fn add_resize_event_listener(
+        events_dispatcher: &MessageHubRc,
+        window: &web_sys::Window,
+        scale_factor: f32,
+    ) {
+        let events_dispatcher = events_dispatcher.clone();
+        let mut scale_factor = scale_factor;
+        let closure = Closure::wrap(Box::new(move |_event: web_sys::Event| {
+            let window = web_sys::window().unwrap();
+            let device_pixel_ratio = window.device_pixel_ratio().max(1.) as f32;
+            if device_pixel_ratio != scale_factor {
+                scale_factor = device_pixel_ratio;
+                events_dispatcher.send_event(WindowEvent::ScaleFactorChanged(scale_factor));
+            }
+            let width = window.inner_width().unwrap().as_f64().unwrap() as u32;
+            let height = window.inner_height().unwrap().as_f64().unwrap() as u32;
+            if let Some(canvas) = window.document().unwrap().get_element_by_id("canvas") {
+                if let Ok(canvas) = canvas.dyn_into::<web_sys::HtmlCanvasElement>() {
+                    canvas.set_width(width);
+                    canvas.set_height(height);
+                }
+            }
+            events_dispatcher.send_event(WindowEvent::SizeChanged(width, height));
+        }) as Box<dyn FnMut(_)>);
+        window
+            .add_event_listener_with_callback("resize", closure.as_ref().unchecked_ref())
+            .ok();
+        closure.forget();
+    }
+
+    //Switching tab or clicking outside the page blurs the whole window, not only the canvas
+    fn add_focus_event_listener(
+        events_dispatcher: &MessageHubRc,
+        window: &web_sys::Window,
+        event_name: &str,
+        event: WindowEvent,
+    ) {
+        let events_dispatcher = events_dispatcher.clone();
+        let closure = Closure::wrap(Box::new(move |_event: web_sys::FocusEvent| {
+            events_dispatcher.send_event(event.clone());
+        }) as Box<dyn FnMut(_)>);
+        window
+            .add_event_listener_with_callback(event_name, closure.as_ref().unchecked_ref())
+            .ok();
+        closure.forget();
+    }
+
+    //Browsers don't expose where the files are, only their names are sent
+    fn add_drag_event_listener(
+        events_dispatcher: &MessageHubRc,
+        canvas: &web_sys::HtmlCanvasElement,
+        event_name: &str,
+        create_event: fn(PathBuf) -> WindowEvent,
+    ) {
+        let events_dispatcher = events_dispatcher.clone();
+        let closure = Closure::wrap(Box::new(move |event: web_sys::DragEvent| {
+            //Without it the browser opens the dropped file itself
+            event.prevent_default();
+            let Some(data_transfer) = event.data_transfer() else {
+                return;
+            };
+            let files = data_transfer.files();
+            let count = files.as_ref().map_or(0, |f| f.length());
+            if let Some(files) = files.filter(|_| count > 0) {
+                for i in 0..count {
+                    if let Some(file) = files.get(i) {
+                        events_dispatcher.send_event(create_event(PathBuf::from(file.name())));
+                    }
+                }
+            } else {
+                //While dragging the names aren't known yet, files are sent without one
+                let items = data_transfer.items();
+                let is_file = (0..items.length())
+                    .any(|i| items.get(i).map_or(false, |item| item.kind() == "file"));
+                if is_file {
+                    events_dispatcher.send_event(create_event(PathBuf::new()));
+                }
+            }
+        }) as Box<dyn FnMut(_)>);
+        canvas
+            .add_event_listener_with_callback(event_name, closure.as_ref().unchecked_ref())
+            .ok();
+        closure.forget();
+    }
+
+    fn add_drag_leave_event_listener(
+        events_dispatcher: &MessageHubRc,
+        canvas: &web_sys::HtmlCanvasElement,
+    ) {
+        let events_dispatcher = events_dispatcher.clone();
+        let closure = Closure::wrap(Box::new(move |_event: web_sys::DragEvent| {
+            events_dispatcher.send_event(WindowEvent::FileHoverCancelled);
+        }) as Box<dyn FnMut(_)>);
+        canvas
+            .add_event_listener_with_callback("dragleave", closure.as_ref().unchecked_ref())
+            .ok();
+        closure.forget();
+    }
+
+    //The clipboard can be read only asynchronously or in a paste event,
+    //so the pasted text is sent with a ClipboardEvent instead
+    fn add_paste_event_listener(events_dispatcher: &MessageHubRc, window: &web_sys::Window) {
+        let events_dispatcher = events_dispatcher.clone();
+        let closure = Closure::wrap(Box::new(move |event: web_sys::ClipboardEvent| {
+            let text = event
+                .clipboard_data()
+                .and_then(|data| data.get_data("text").ok())
+                .unwrap_or_default();
+            if !text.is_empty() {
+                event.prevent_default();
+                events_dispatcher.send_event(ClipboardEvent::Paste(text));
+            }
+        }) as Box<dyn FnMut(_)>);
+        window
+            .add_event_listener_with_callback("paste", closure.as_ref().unchecked_ref())
+            .ok();
+        closure.forget();
+    }
+
     pub fn change_title(_handle: &Handle, _title: &str) {}
     pub fn change_visibility(_handle: &Handle, _is_visible: bool) {}
 
+    //Browsers can block the new tab, the page is left as it is then
+    pub fn launch_url(url: &str) -> bool {
+        web_sys::window()
+            .and_then(|w| w.open_with_url_and_target(url, "_blank").ok())
+            .flatten()
+            .is_some()
+    }
+
+    pub fn clipboard_text() -> Option<String> {
+        None
+    }
+
+    //The text is written asynchronously, only the request is checked
+    pub fn set_clipboard_text(text: &str) -> bool {
+        write_clipboard_text(text).is_ok()
+    }
+
     pub fn change_position(_handle: &Handle, _x: u32, _y: u32) {}
 
     pub fn change_size(_handle: &Handle, _width: u32, _height: u32) {}
 
+    //The browser shows the cursor again where it was locked once the pointer lock is released
+    pub fn change_cursor_grab(_handle: &Handle, is_grabbed: bool) {
+        let document = web_sys::window().unwrap().document().unwrap();
+        if is_grabbed {
+            if let Some(canvas) = document.get_element_by_id("canvas") {
+                canvas.request_pointer_lock();
+            }
+        } else if document.pointer_lock_element().is_some() {
+            document.exit_pointer_lock();
+        }
+    }
+
+    pub fn change_cursor_visibility(_handle: &Handle, is_visible: bool) {
+        let document = web_sys::window().unwrap().document().unwrap();
+        if let Some(canvas) = document.get_element_by_id("canvas") {
+            if let Ok(canvas) = canvas.dyn_into::<web_sys::HtmlElement>() {
+                let cursor = if is_visible { "auto" } else { "none" };
+                canvas.style().set_property("cursor", cursor).ok();
+            }
+        }
+    }
+
+    //Composition events are sent only to editable elements and the canvas isn't one
+    pub fn change_ime_allowed(_handle: &Handle, _is_allowed: bool) {}
+
     #[inline]
     pub fn internal_update(_handle: &Handle) -> bool {
         true
@@ -0,0 +1,244 @@
+#![allow(bad_style)]
+
+use std::{
+    ffi::OsString,
+    os::windows::ffi::OsStringExt,
+    path::PathBuf,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use inox_messenger::MessageHubRc;
+
+use super::errors::*;
+use super::externs::*;
+use super::types::*;
+use crate::window::*;
+
+const IID_IUNKNOWN: GUID = GUID {
+    Data1: 0x00000000,
+    Data2: 0x0000,
+    Data3: 0x0000,
+    Data4: [0xC0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x46],
+};
+const IID_IDROPTARGET: GUID = GUID {
+    Data1: 0x00000122,
+    Data2: 0x0000,
+    Data3: 0x0000,
+    Data4: [0xC0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x46],
+};
+
+//Only the methods before GetData are needed to call it
+#[repr(C)]
+struct IDataObjectVtbl {
+    QueryInterface: LPVOID,
+    AddRef: LPVOID,
+    Release: LPVOID,
+    GetData:
+        unsafe extern "system" fn(*mut IDataObject, *const FORMATETC, *mut STGMEDIUM) -> HRESULT,
+}
+
+#[repr(C)]
+struct IDataObject {
+    vtbl: *const IDataObjectVtbl,
+}
+
+#[repr(C)]
+struct IDropTargetVtbl {
+    QueryInterface: unsafe extern "system" fn(*mut DropTarget, REFIID, *mut LPVOID) -> HRESULT,
+    AddRef: unsafe extern "system" fn(*mut DropTarget) -> ULONG,
+    Release: unsafe extern "system" fn(*mut DropTarget) -> ULONG,
+    DragEnter: unsafe extern "system" fn(
+        *mut DropTarget,
+        *mut IDataObject,
+        DWORD,
+        POINTL,
+        *mut DWORD,
+    ) -> HRESULT,
+    DragOver: unsafe extern "system" fn(*mut DropTarget, DWORD, POINTL, *mut DWORD) -> HRESULT,
+    DragLeave: unsafe extern "system" fn(*mut DropTarget) -> HRESULT,
+    Drop: unsafe extern "system" fn(
+        *mut DropTarget,
+        *mut IDataObject,
+        DWORD,
+        POINTL,
+        *mut DWORD,
+    ) -> HRESULT,
+}
+
+static DROP_TARGET_VTBL: IDropTargetVtbl = IDropTargetVtbl {
+    QueryInterface: DropTarget::query_interface,
+    AddRef: DropTarget::add_ref,
+    Release: DropTarget::release,
+    DragEnter: DropTarget::drag_enter,
+    DragOver: DropTarget::drag_over,
+    DragLeave: DropTarget::drag_leave,
+    Drop: DropTarget::drop_files,
+};
+
+//IDropTarget of the window: unlike WS_EX_ACCEPTFILES it's notified while the files are dragged over
+#[repr(C)]
+pub struct DropTarget {
+    vtbl: *const IDropTargetVtbl,
+    ref_count: AtomicU32,
+    events_dispatcher: MessageHubRc,
+    has_files: bool,
+}
+
+impl DropTarget {
+    //Returns false when the window can't be registered, then only WM_DROPFILES notifies the drop
+    pub fn register(hwnd: HWND, events_dispatcher: &MessageHubRc) -> bool {
+        unsafe {
+            OleInitialize(std::ptr::null_mut());
+            let drop_target = Box::into_raw(Box::new(Self {
+                vtbl: &DROP_TARGET_VTBL,
+                ref_count: AtomicU32::new(1),
+                events_dispatcher: events_dispatcher.clone(),
+                has_files: false,
+            }));
+            //The window keeps its own reference until it's revoked
+            let result = RegisterDragDrop(hwnd, drop_target as _);
+            Self::release(drop_target);
+            SUCCEEDED(result)
+        }
+    }
+    pub fn revoke(hwnd: HWND) {
+        unsafe {
+            RevokeDragDrop(hwnd);
+        }
+    }
+
+    fn drop_effect(&self) -> DWORD {
+        if self.has_files {
+            DROPEFFECT_COPY
+        } else {
+            DROPEFFECT_NONE
+        }
+    }
+
+    unsafe extern "system" fn query_interface(
+        this: *mut Self,
+        riid: REFIID,
+        ppv: *mut LPVOID,
+    ) -> HRESULT {
+        if *riid == IID_IUNKNOWN || *riid == IID_IDROPTARGET {
+            Self::add_ref(this);
+            *ppv = this as _;
+            S_OK
+        } else {
+            *ppv = std::ptr::null_mut();
+            E_NOINTERFACE
+        }
+    }
+    unsafe extern "system" fn add_ref(this: *mut Self) -> ULONG {
+        (*this).ref_count.fetch_add(1, Ordering::SeqCst) + 1
+    }
+    unsafe extern "system" fn release(this: *mut Self) -> ULONG {
+        let ref_count = (*this).ref_count.fetch_sub(1, Ordering::SeqCst) - 1;
+        if ref_count == 0 {
+            drop(Box::from_raw(this));
+        }
+        ref_count
+    }
+    unsafe extern "system" fn drag_enter(
+        this: *mut Self,
+        data_object: *mut IDataObject,
+        _key_state: DWORD,
+        _pt: POINTL,
+        effect: *mut DWORD,
+    ) -> HRESULT {
+        let files = data_object_files(data_object);
+        (*this).has_files = !files.is_empty();
+        files.into_iter().for_each(|path| {
+            (*this)
+                .events_dispatcher
+                .send_event(WindowEvent::FileHovered(path));
+        });
+        *effect = (*this).drop_effect();
+        S_OK
+    }
+    unsafe extern "system" fn drag_over(
+        this: *mut Self,
+        _key_state: DWORD,
+        _pt: POINTL,
+        effect: *mut DWORD,
+    ) -> HRESULT {
+        *effect = (*this).drop_effect();
+        S_OK
+    }
+    unsafe extern "system" fn drag_leave(this: *mut Self) -> HRESULT {
+        if (*this).has_files {
+            (*this).has_files = false;
+            (*this)
+                .events_dispatcher
+                .send_event(WindowEvent::FileHoverCancelled);
+        }
+        S_OK
+    }
+    unsafe extern "system" fn drop_files(
+        this: *mut Self,
+        data_object: *mut IDataObject,
+        _key_state: DWORD,
+        _pt: POINTL,
+        effect: *mut DWORD,
+    ) -> HRESULT {
+        data_object_files(data_object).into_iter().for_each(|path| {
+            (*this)
+                .events_dispatcher
+                .send_event(WindowEvent::FileDropped(path));
+        });
+        *effect = (*this).drop_effect();
+        (*this).has_files = false;
+        S_OK
+    }
+}
+
+pub fn dropped_files(hdrop: HDROP) -> Vec<PathBuf> {
+    unsafe {
+        let count = DragQueryFileW(hdrop, 0xFFFFFFFF, std::ptr::null_mut(), 0);
+        (0..count)
+            .map(|i| {
+                let length = DragQueryFileW(hdrop, i, std::ptr::null_mut(), 0) as usize;
+                let mut buffer: Vec<u16> = vec![0; length + 1];
+                DragQueryFileW(hdrop, i, buffer.as_mut_ptr(), buffer.len() as _);
+                buffer.truncate(length);
+                PathBuf::from(OsString::from_wide(&buffer))
+            })
+            .collect()
+    }
+}
+
+//Files dragged from the explorer come as an HDROP, anything else has none
+unsafe fn data_object_files(data_object: *mut IDataObject) -> Vec<PathBuf> {
+    if data_object.is_null() {
+        return Vec::new();
+    }
+    let format = FORMATETC {
+        cfFormat: CF_HDROP,
+        ptd: std::ptr::null_mut(),
+        dwAspect: DVASPECT_CONTENT,
+        lindex: -1,
+        tymed: TYMED_HGLOBAL,
+    };
+    let mut medium = STGMEDIUM {
+        tymed: 0,
+        hGlobal: std::ptr::null_mut(),
+        pUnkForRelease: std::ptr::null_mut(),
+    };
+    if FAILED(((*(*data_object).vtbl).GetData)(
+        data_object,
+        &format,
+        &mut medium,
+    )) {
+        return Vec::new();
+    }
+    let hdrop = GlobalLock(medium.hGlobal) as HDROP;
+    let files = if hdrop.is_null() {
+        Vec::new()
+    } else {
+        let files = dropped_files(hdrop);
+        GlobalUnlock(medium.hGlobal);
+        files
+    };
+    ReleaseStgMedium(&mut medium);
+    files
+}
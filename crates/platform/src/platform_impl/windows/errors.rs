@@ -5,6 +5,8 @@ use super::types::*;
 pub const ERROR_SUCCESS: DWORD = 0;
 pub const NO_ERROR: DWORD = 0;
 pub const SEC_E_OK: HRESULT = 0;
+pub const S_OK: HRESULT = 0;
+pub const E_NOINTERFACE: HRESULT = 0x80004002;
 pub const ERROR_INVALID_FUNCTION: DWORD = 1;
 pub const ERROR_FILE_NOT_FOUND: DWORD = 2;
 pub const ERROR_PATH_NOT_FOUND: DWORD = 3;
@@ -570,6 +570,54 @@ extern "system" {
     pub fn GetForegroundWindow() -> HWND;
     pub fn GetClientRect(aWnd: HWND, lpRect: &mut RECT);
     pub fn ScreenToClient(hWnd: HWND, lpPoint: &mut POINT);
+    pub fn ClientToScreen(hWnd: HWND, lpPoint: &mut POINT) -> BOOL;
+    pub fn SetCursorPos(X: c_int, Y: c_int) -> BOOL;
+    pub fn ClipCursor(lpRect: *const RECT) -> BOOL;
+    pub fn ShowCursor(bShow: BOOL) -> c_int;
+    pub fn RegisterRawInputDevices(
+        pRawInputDevices: PCRAWINPUTDEVICE,
+        uiNumDevices: UINT,
+        cbSize: UINT,
+    ) -> BOOL;
+    pub fn GetRawInputData(
+        hRawInput: HRAWINPUT,
+        uiCommand: UINT,
+        pData: LPVOID,
+        pcbSize: *mut UINT,
+        cbSizeHeader: UINT,
+    ) -> UINT;
+    pub fn DragQueryFileW(hDrop: HDROP, iFile: UINT, lpszFile: LPWSTR, cch: UINT) -> UINT;
+    pub fn DragFinish(hDrop: HDROP);
+    pub fn OleInitialize(pvReserved: LPVOID) -> HRESULT;
+    pub fn RegisterDragDrop(hwnd: HWND, pDropTarget: LPVOID) -> HRESULT;
+    pub fn RevokeDragDrop(hwnd: HWND) -> HRESULT;
+    pub fn ReleaseStgMedium(pmedium: *mut STGMEDIUM);
+    pub fn ShellExecuteW(
+        hwnd: HWND,
+        lpOperation: LPCWSTR,
+        lpFile: LPCWSTR,
+        lpParameters: LPCWSTR,
+        lpDirectory: LPCWSTR,
+        nShowCmd: c_int,
+    ) -> HINSTANCE;
+    pub fn OpenClipboard(hWndNewOwner: HWND) -> BOOL;
+    pub fn CloseClipboard() -> BOOL;
+    pub fn EmptyClipboard() -> BOOL;
+    pub fn GetClipboardData(uFormat: UINT) -> HANDLE;
+    pub fn SetClipboardData(uFormat: UINT, hMem: HANDLE) -> HANDLE;
+    pub fn GlobalAlloc(uFlags: UINT, dwBytes: SIZE_T) -> HGLOBAL;
+    pub fn GlobalFree(hMem: HGLOBAL) -> HGLOBAL;
+    pub fn GlobalLock(hMem: HGLOBAL) -> LPVOID;
+    pub fn GlobalUnlock(hMem: HGLOBAL) -> BOOL;
+    pub fn ImmGetContext(hWnd: HWND) -> HIMC;
+    pub fn ImmReleaseContext(hWnd: HWND, hIMC: HIMC) -> BOOL;
+    pub fn ImmGetCompositionStringW(
+        hIMC: HIMC,
+        dwIndex: DWORD,
+        lpBuf: LPVOID,
+        dwBufLen: DWORD,
+    ) -> LONG;
+    pub fn ImmAssociateContextEx(hWnd: HWND, hIMC: HIMC, dwFlags: DWORD) -> BOOL;
     pub fn SetProcessDPIAware() -> BOOL;
     pub fn SetProcessDpiAwareness(value: PROCESS_DPI_AWARENESS) -> HRESULT;
     pub fn GetProcessDpiAwareness(hProcess: HANDLE, value: *mut PROCESS_DPI_AWARENESS) -> HRESULT;
@@ -1,5 +1,6 @@
 #![cfg(target_os = "windows")]
 
+mod drop_target;
 mod errors;
 mod macros;
 mod types;
@@ -30,6 +30,8 @@ declare_handle! {HUMPD, HUMPD__}
 declare_handle! {HMONITOR, HMONITOR__}
 declare_handle! {HKL, HKL__}
 declare_handle! {HRGN, HRGN__}
+declare_handle! {HRAWINPUT, HRAWINPUT__}
+declare_handle! {HDROP, HDROP__}
 
 #[inline]
 pub fn MAKEWORD(a: BYTE, b: BYTE) -> WORD {
@@ -103,6 +105,9 @@ pub type HGDIOBJ = *mut c_void;
 pub type INT_PTR = isize;
 pub type PINT_PTR = *mut isize;
 pub type HTHEME = HANDLE;
+pub type HGLOBAL = HANDLE;
+pub type HIMC = HANDLE;
+pub type SIZE_T = usize;
 pub type PVOID64 = u64; // This is a 64-bit pointer, even when in 32-bit
 pub type VOID = c_void;
 pub type PBYTE = *mut BYTE;
@@ -867,6 +872,14 @@ pub const TRUE: BOOL = 1;
 pub const INVALID_HANDLE_VALUE: HANDLE = -1isize as HANDLE;
 pub const INFINITE: DWORD = 0xFFFFFFFF;
 
+pub const CF_UNICODETEXT: UINT = 13;
+pub const CF_HDROP: CLIPFORMAT = 15;
+pub const GMEM_MOVEABLE: UINT = 0x0002;
+
+pub const GCS_COMPSTR: DWORD = 0x0008;
+pub const GCS_RESULTSTR: DWORD = 0x0800;
+pub const IACE_DEFAULT: DWORD = 0x0010;
+
 pub const CS_VREDRAW: UINT = 0x0001;
 pub const CS_HREDRAW: UINT = 0x0002;
 pub const CS_OWNDC: UINT = 0x0020;
@@ -1465,6 +1478,47 @@ pub struct POINT {
     pub y: LONG,
 }
 pub type PPOINT = *mut POINT;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct POINTL {
+    pub x: LONG,
+    pub y: LONG,
+}
+
+pub type CLIPFORMAT = WORD;
+pub type REFIID = *const GUID;
+
+pub const DVASPECT_CONTENT: DWORD = 1;
+pub const TYMED_HGLOBAL: DWORD = 1;
+pub const DROPEFFECT_NONE: DWORD = 0;
+pub const DROPEFFECT_COPY: DWORD = 1;
+
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct GUID {
+    pub Data1: c_ulong,
+    pub Data2: c_ushort,
+    pub Data3: c_ushort,
+    pub Data4: [c_uchar; 8],
+}
+
+#[repr(C)]
+pub struct FORMATETC {
+    pub cfFormat: CLIPFORMAT,
+    pub ptd: LPVOID,
+    pub dwAspect: DWORD,
+    pub lindex: LONG,
+    pub tymed: DWORD,
+}
+
+//hGlobal is the only member of the union used
+#[repr(C)]
+pub struct STGMEDIUM {
+    pub tymed: DWORD,
+    pub hGlobal: HGLOBAL,
+    pub pUnkForRelease: LPVOID,
+}
 pub type NPPOINT = *mut POINT;
 pub type LPPOINT = *mut POINT;
 
@@ -1559,3 +1613,54 @@ pub enum MONITOR_DPI_TYPE {
     MDT_RAW_DPI = 2,
 }
 pub const MDT_DEFAULT: MONITOR_DPI_TYPE = MONITOR_DPI_TYPE::MDT_EFFECTIVE_DPI;
+
+pub const RID_INPUT: UINT = 0x10000003;
+pub const RID_HEADER: UINT = 0x10000005;
+pub const RIM_TYPEMOUSE: DWORD = 0;
+pub const RIM_TYPEKEYBOARD: DWORD = 1;
+pub const RIM_TYPEHID: DWORD = 2;
+pub const RIDEV_REMOVE: DWORD = 0x00000001;
+pub const MOUSE_MOVE_RELATIVE: USHORT = 0x00;
+pub const MOUSE_MOVE_ABSOLUTE: USHORT = 0x01;
+pub const HID_USAGE_PAGE_GENERIC: USHORT = 0x01;
+pub const HID_USAGE_GENERIC_MOUSE: USHORT = 0x02;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct RAWINPUTDEVICE {
+    pub usUsagePage: USHORT,
+    pub usUsage: USHORT,
+    pub dwFlags: DWORD,
+    pub hwndTarget: HWND,
+}
+pub type PRAWINPUTDEVICE = *mut RAWINPUTDEVICE;
+pub type PCRAWINPUTDEVICE = *const RAWINPUTDEVICE;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct RAWINPUTHEADER {
+    pub dwType: DWORD,
+    pub dwSize: DWORD,
+    pub hDevice: HANDLE,
+    pub wParam: WPARAM,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct RAWMOUSE {
+    pub usFlags: USHORT,
+    pub ulButtons: ULONG,
+    pub ulRawButtons: ULONG,
+    pub lLastX: LONG,
+    pub lLastY: LONG,
+    pub ulExtraInformation: ULONG,
+}
+
+//Only mouse devices are registered, so the data is never a keyboard or hid one
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct RAWINPUT {
+    pub header: RAWINPUTHEADER,
+    pub mouse: RAWMOUSE,
+}
+pub type PRAWINPUT = *mut RAWINPUT;
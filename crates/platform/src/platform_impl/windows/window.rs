@@ -1,7 +1,8 @@
-use std::path::Path;
+use std::{ffi::OsStr, os::windows::ffi::OsStrExt, path::Path};
 
 use inox_messenger::MessageHubRc;
 
+use super::drop_target::*;
 use super::externs::*;
 use super::handle::*;
 use super::types::*;
@@ -11,6 +12,10 @@ use crate::input::*;
 use crate::window::*;
 
 static mut EVENTS_DISPATCHER: Option<MessageHubRc> = None;
+//Cursor position in screen coordinates before it has been grabbed
+static mut CURSOR_GRAB_POS: Option<POINT> = None;
+//Window owning the clipboard content set by the application
+static mut CLIPBOARD_OWNER: HWND = std::ptr::null_mut();
 
 impl Window {
     pub fn create_handle(
@@ -98,6 +103,12 @@ impl Window {
             *width = rc.right as _;
             *height = rc.bottom as _;
 
+            DropTarget::register(win_handle, events_dispatcher);
+            CLIPBOARD_OWNER = win_handle;
+
+            //The input method is enabled only while it's requested, like when editing a text
+            ImmAssociateContextEx(win_handle, std::ptr::null_mut(), 0);
+
             events_dispatcher.send_event(WindowEvent::ScaleFactorChanged(*scale_factor));
 
             Handle {
@@ -128,6 +139,87 @@ impl Window {
         }
     }
 
+    //ShellExecute returns a value greater than 32 when it succeeds
+    pub fn launch_url(url: &str) -> bool {
+        let operation: Vec<u16> = OsStr::new("open").encode_wide().chain(Some(0)).collect();
+        let url: Vec<u16> = OsStr::new(url).encode_wide().chain(Some(0)).collect();
+        unsafe {
+            let result = ShellExecuteW(
+                std::ptr::null_mut(),
+                operation.as_ptr(),
+                url.as_ptr(),
+                std::ptr::null(),
+                std::ptr::null(),
+                SW_SHOWNORMAL,
+            );
+            result as isize > 32
+        }
+    }
+
+    pub fn clipboard_text() -> Option<String> {
+        unsafe {
+            if OpenClipboard(std::ptr::null_mut()) == FALSE {
+                return None;
+            }
+            let data = GetClipboardData(CF_UNICODETEXT);
+            let chars = if data.is_null() {
+                std::ptr::null()
+            } else {
+                GlobalLock(data) as *const u16
+            };
+            let text = if chars.is_null() {
+                None
+            } else {
+                let length = (0..).take_while(|&i| *chars.add(i) != 0).count();
+                let text = String::from_utf16_lossy(std::slice::from_raw_parts(chars, length));
+                GlobalUnlock(data);
+                Some(text)
+            };
+            CloseClipboard();
+            text
+        }
+    }
+
+    //Once it's set the memory is owned by the system, it's freed only on failure.
+    //The clipboard has to be opened by the window: without an owner EmptyClipboard
+    //leaves it unowned and SetClipboardData fails
+    pub fn set_clipboard_text(text: &str) -> bool {
+        let text: Vec<u16> = OsStr::new(text).encode_wide().chain(Some(0)).collect();
+        unsafe {
+            let owner = CLIPBOARD_OWNER;
+            if owner.is_null() || OpenClipboard(owner) == FALSE {
+                eprintln!("Unable to open the clipboard - error {}", GetLastError());
+                return false;
+            }
+            if EmptyClipboard() == FALSE {
+                eprintln!("Unable to empty the clipboard - error {}", GetLastError());
+                CloseClipboard();
+                return false;
+            }
+            let mut is_set = false;
+            let data = GlobalAlloc(GMEM_MOVEABLE, text.len() * std::mem::size_of::<u16>());
+            if !data.is_null() {
+                let chars = GlobalLock(data) as *mut u16;
+                if !chars.is_null() {
+                    std::ptr::copy_nonoverlapping(text.as_ptr(), chars, text.len());
+                    GlobalUnlock(data);
+                    is_set = !SetClipboardData(CF_UNICODETEXT, data).is_null();
+                    if !is_set {
+                        eprintln!(
+                            "Unable to set the clipboard text - error {}",
+                            GetLastError()
+                        );
+                    }
+                }
+                if !is_set {
+                    GlobalFree(data);
+                }
+            }
+            CloseClipboard();
+            is_set
+        }
+    }
+
     pub fn change_position(handle: &Handle, x: u32, y: u32) {
         unsafe {
             let mut rect: RECT = RECT {
@@ -179,6 +271,96 @@ impl Window {
         }
     }
 
+    pub fn change_cursor_grab(handle: &Handle, is_grabbed: bool) {
+        unsafe {
+            if is_grabbed == CURSOR_GRAB_POS.is_some() {
+                return;
+            }
+            //Raw input gives the mouse movement even when the cursor is stopped by the clip rect
+            let device = RAWINPUTDEVICE {
+                usUsagePage: HID_USAGE_PAGE_GENERIC,
+                usUsage: HID_USAGE_GENERIC_MOUSE,
+                dwFlags: if is_grabbed { 0 } else { RIDEV_REMOVE },
+                hwndTarget: if is_grabbed {
+                    handle.handle_impl.hwnd
+                } else {
+                    ::std::ptr::null_mut()
+                },
+            };
+            RegisterRawInputDevices(&device, 1, ::std::mem::size_of::<RAWINPUTDEVICE>() as _);
+            if is_grabbed {
+                let mut cursor_pos = POINT { x: 0, y: 0 };
+                GetCursorPos(&mut cursor_pos);
+                CURSOR_GRAB_POS = Some(cursor_pos);
+
+                let mut rect: RECT = RECT {
+                    left: 0,
+                    top: 0,
+                    right: 0,
+                    bottom: 0,
+                };
+                GetClientRect(handle.handle_impl.hwnd, &mut rect);
+                let mut top_left = POINT {
+                    x: rect.left,
+                    y: rect.top,
+                };
+                let mut bottom_right = POINT {
+                    x: rect.right,
+                    y: rect.bottom,
+                };
+                ClientToScreen(handle.handle_impl.hwnd, &mut top_left);
+                ClientToScreen(handle.handle_impl.hwnd, &mut bottom_right);
+                let rect = RECT {
+                    left: top_left.x,
+                    top: top_left.y,
+                    right: bottom_right.x,
+                    bottom: bottom_right.y,
+                };
+                ClipCursor(&rect);
+            } else {
+                ClipCursor(::std::ptr::null());
+                if let Some(cursor_pos) = CURSOR_GRAB_POS.take() {
+                    SetCursorPos(cursor_pos.x, cursor_pos.y);
+                }
+            }
+        }
+    }
+
+    pub fn change_cursor_visibility(_handle: &Handle, is_visible: bool) {
+        unsafe {
+            //ShowCursor increments or decrements a display counter, the cursor is shown when >= 0
+            if is_visible {
+                while ShowCursor(TRUE) < 0 {}
+            } else {
+                while ShowCursor(FALSE) >= 0 {}
+            }
+        }
+    }
+
+    //Without a context associated to the window the keys are never sent to the input method
+    pub fn change_ime_allowed(handle: &Handle, is_allowed: bool) {
+        unsafe {
+            ImmAssociateContextEx(
+                handle.handle_impl.hwnd,
+                std::ptr::null_mut(),
+                if is_allowed { IACE_DEFAULT } else { 0 },
+            );
+        }
+    }
+
+    fn ime_composition_string(himc: HIMC, index: DWORD) -> Option<String> {
+        unsafe {
+            //The size is in bytes and the string isn't null terminated
+            let size = ImmGetCompositionStringW(himc, index, std::ptr::null_mut(), 0);
+            if size < 0 {
+                return None;
+            }
+            let mut buffer: Vec<u16> = vec![0; size as usize / std::mem::size_of::<u16>()];
+            ImmGetCompositionStringW(himc, index, buffer.as_mut_ptr() as _, size as _);
+            Some(String::from_utf16_lossy(&buffer))
+        }
+    }
+
     #[inline]
     pub fn internal_update(handle: &Handle) -> bool {
         unsafe {
@@ -250,12 +432,48 @@ impl Window {
                             },
                         });
                     }
-                } else if message.message == WM_CHAR {
-                    let char = message.wParam as INT;
+                } else if message.message == WM_MOUSEWHEEL {
+                    let delta = GET_WHEEL_DELTA_WPARAM(message.wParam) as f64;
                     if let Some(events_dispatcher) = &mut EVENTS_DISPATCHER {
-                        events_dispatcher.send_event(KeyTextEvent {
-                            char: char as u8 as _,
-                        });
+                        events_dispatcher.send_event(MouseEvent::wheel(delta / WHEEL_DELTA as f64));
+                    }
+                } else if message.message == WM_INPUT {
+                    let mut raw_input: RAWINPUT = ::std::mem::MaybeUninit::zeroed().assume_init();
+                    let mut size = ::std::mem::size_of::<RAWINPUT>() as UINT;
+                    let read = GetRawInputData(
+                        message.lParam as HRAWINPUT,
+                        RID_INPUT,
+                        &mut raw_input as *mut RAWINPUT as LPVOID,
+                        &mut size,
+                        ::std::mem::size_of::<RAWINPUTHEADER>() as _,
+                    );
+                    if read != UINT::MAX
+                        && raw_input.header.dwType == RIM_TYPEMOUSE
+                        && raw_input.mouse.usFlags & MOUSE_MOVE_ABSOLUTE == 0
+                        && (raw_input.mouse.lLastX != 0 || raw_input.mouse.lLastY != 0)
+                    {
+                        let mut rc: RECT = RECT {
+                            left: 0,
+                            top: 0,
+                            right: 0,
+                            bottom: 0,
+                        };
+                        GetClientRect(handle.handle_impl.hwnd, &mut rc);
+                        if let Some(events_dispatcher) = &mut EVENTS_DISPATCHER {
+                            events_dispatcher.send_event(MouseEvent::relative_move(
+                                raw_input.mouse.lLastX as _,
+                                raw_input.mouse.lLastY as _,
+                                (rc.right - rc.left) as _,
+                                (rc.bottom - rc.top) as _,
+                            ));
+                        }
+                    }
+                } else if message.message == WM_CHAR {
+                    //Characters out of the basic plane come as two surrogates and are skipped
+                    if let Some(char) = char::from_u32(message.wParam as _) {
+                        if let Some(events_dispatcher) = &mut EVENTS_DISPATCHER {
+                            events_dispatcher.send_event(KeyTextEvent { char });
+                        }
                     }
                     can_continue = true;
                 } else if message.message == WM_KEYDOWN
@@ -322,9 +540,10 @@ impl Window {
     ) -> LRESULT {
         match msg {
             WM_DPICHANGED => {
-                let mut rect: RECT = *(lparam as *const c_void as *const RECT);
+                //The suggested rect is already the window one scaled for the new dpi,
+                //resizing to it sends the new physical size of the client area with WM_SIZE
+                let rect: RECT = *(lparam as *const c_void as *const RECT);
                 let dpi_x = LOWORD(wparam as _);
-                AdjustWindowRectEx(&mut rect, WS_OVERLAPPEDWINDOW, FALSE, 0);
                 SetWindowPos(
                     hwnd,
                     0 as _, // No relative window
@@ -353,21 +572,62 @@ impl Window {
                     events_dispatcher.send_event(WindowEvent::PosChanged(x as _, y as _));
                 }
             }
+            WM_DROPFILES => {
+                //Sent only without the drop target, WS_EX_ACCEPTFILES doesn't notify the hovering
+                let hdrop = wparam as HDROP;
+                if let Some(events_dispatcher) = &mut EVENTS_DISPATCHER {
+                    dropped_files(hdrop).into_iter().for_each(|path| {
+                        events_dispatcher.send_event(WindowEvent::FileDropped(path));
+                    });
+                }
+                DragFinish(hdrop);
+                return 0;
+            }
             WM_DESTROY | WM_CLOSE | WM_QUIT | WM_NCDESTROY => {
+                DropTarget::revoke(hwnd);
                 if let Some(events_dispatcher) = &mut EVENTS_DISPATCHER {
                     events_dispatcher.send_event(WindowEvent::Close);
                 }
                 PostQuitMessage(0);
                 return 0;
             }
+            //The composition is shown by the focused widget instead of the system window
+            WM_IME_STARTCOMPOSITION => {
+                return 0;
+            }
+            //Handling it here keeps the result from being sent again with WM_CHAR
+            WM_IME_COMPOSITION => {
+                let himc = ImmGetContext(hwnd);
+                if !himc.is_null() {
+                    if let Some(events_dispatcher) = &mut EVENTS_DISPATCHER {
+                        if lparam as DWORD & GCS_RESULTSTR != 0 {
+                            if let Some(text) = Self::ime_composition_string(himc, GCS_RESULTSTR) {
+                                events_dispatcher.send_event(WindowEvent::ImeCommit(text));
+                            }
+                        }
+                        if lparam as DWORD & GCS_COMPSTR != 0 {
+                            if let Some(text) = Self::ime_composition_string(himc, GCS_COMPSTR) {
+                                events_dispatcher.send_event(WindowEvent::ImePreedit(text));
+                            }
+                        }
+                    }
+                    ImmReleaseContext(hwnd, himc);
+                }
+                return 0;
+            }
+            WM_IME_ENDCOMPOSITION => {
+                if let Some(events_dispatcher) = &mut EVENTS_DISPATCHER {
+                    events_dispatcher.send_event(WindowEvent::ImeCommit(String::new()));
+                }
+            }
             WM_SETFOCUS => {
                 if let Some(events_dispatcher) = &mut EVENTS_DISPATCHER {
-                    events_dispatcher.send_event(WindowEvent::Show);
+                    events_dispatcher.send_event(WindowEvent::FocusGained);
                 }
             }
             WM_KILLFOCUS => {
                 if let Some(events_dispatcher) = &mut EVENTS_DISPATCHER {
-                    events_dispatcher.send_event(WindowEvent::Hide);
+                    events_dispatcher.send_event(WindowEvent::FocusLost);
                 }
             }
             _ => {}
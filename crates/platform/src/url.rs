@@ -0,0 +1,42 @@
+use crate::Window;
+
+const WEB_SCHEMES: [&str; 2] = ["http://", "https://"];
+
+//Only web pages can be opened: any other scheme could launch the program registered for it
+pub fn is_web_url(url: &str) -> bool {
+    let url = url.trim();
+    WEB_SCHEMES.iter().any(|scheme| {
+        url.len() > scheme.len()
+            && url
+                .get(..scheme.len())
+                .map_or(false, |s| s.eq_ignore_ascii_case(scheme))
+    })
+}
+
+//Opens the url with the default browser, returns false when it's refused or it can't be opened
+pub fn open_url(url: &str) -> bool {
+    if !is_web_url(url) {
+        return false;
+    }
+    Window::launch_url(url.trim())
+}
+
+#[allow(dead_code)]
+fn test_web_url() {
+    assert!(is_web_url("https://github.com/gents83/INOX"));
+    assert!(is_web_url("http://localhost:8080"));
+    assert!(is_web_url(" HTTPS://github.com "));
+
+    assert!(!is_web_url("file:///C:/Windows/System32/calc.exe"));
+    assert!(!is_web_url("C:/Windows/System32/calc.exe"));
+    assert!(!is_web_url("javascript:alert(1)"));
+    assert!(!is_web_url("ftp://github.com"));
+    assert!(!is_web_url("https://"));
+    assert!(!is_web_url("httpsè://github.com"));
+    assert!(!is_web_url(""));
+}
+
+#[test]
+fn test() {
+    test_web_url();
+}
@@ -1,6 +1,6 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-use crate::{handle::*, KeyEvent, KeyTextEvent, MouseEvent};
+use crate::{handle::*, ClipboardEvent, Gamepads, KeyEvent, KeyTextEvent, MouseEvent};
 use inox_commands::CommandParser;
 use inox_messenger::{implement_message, Listener, MessageHubRc};
 
@@ -11,6 +11,8 @@ pub enum WindowEvent {
     Show,
     Hide,
     Close,
+    FocusGained,
+    FocusLost,
     ScaleFactorChanged(f32),
     SizeChanged(u32, u32),
     PosChanged(u32, u32),
@@ -18,6 +20,16 @@ pub enum WindowEvent {
     RequestChangeTitle(String),
     RequestChangePos(u32, u32),
     RequestChangeSize(u32, u32),
+    SetCursorGrab(bool),
+    SetCursorVisible(bool),
+    FileHovered(PathBuf),
+    FileHoverCancelled,
+    FileDropped(PathBuf),
+    //Text being composed by the input method, empty when the composition is cancelled
+    ImePreedit(String),
+    //Text confirmed by the input method, empty when the composition ended without it
+    ImeCommit(String),
+    RequestImeAllowed(bool),
 }
 implement_message!(
     WindowEvent,
@@ -36,6 +48,10 @@ impl WindowEvent {
             return Some(WindowEvent::Hide);
         } else if command_parser.has("window_close") {
             return Some(WindowEvent::Close);
+        } else if command_parser.has("window_focus_gained") {
+            return Some(WindowEvent::FocusGained);
+        } else if command_parser.has("window_focus_lost") {
+            return Some(WindowEvent::FocusLost);
         } else if command_parser.has("dpi_changed") {
             let values = command_parser.get_values_of("scale_factor");
             return Some(WindowEvent::ScaleFactorChanged(values[0]));
@@ -57,6 +73,29 @@ impl WindowEvent {
         } else if command_parser.has("window_position") {
             let values = command_parser.get_values_of("window_position");
             return Some(WindowEvent::RequestChangePos(values[0], values[1]));
+        } else if command_parser.has("cursor_grab") {
+            let values = command_parser.get_values_of("cursor_grab");
+            return Some(WindowEvent::SetCursorGrab(values[0]));
+        } else if command_parser.has("cursor_visible") {
+            let values = command_parser.get_values_of("cursor_visible");
+            return Some(WindowEvent::SetCursorVisible(values[0]));
+        } else if command_parser.has("file_hovered") {
+            let values = command_parser.get_values_of::<String>("file_hovered");
+            return Some(WindowEvent::FileHovered(PathBuf::from(values[0].as_str())));
+        } else if command_parser.has("file_hover_cancelled") {
+            return Some(WindowEvent::FileHoverCancelled);
+        } else if command_parser.has("file_dropped") {
+            let values = command_parser.get_values_of::<String>("file_dropped");
+            return Some(WindowEvent::FileDropped(PathBuf::from(values[0].as_str())));
+        } else if command_parser.has("ime_preedit") {
+            let values = command_parser.get_values_of::<String>("ime_preedit");
+            return Some(WindowEvent::ImePreedit(values[0].clone()));
+        } else if command_parser.has("ime_commit") {
+            let values = command_parser.get_values_of::<String>("ime_commit");
+            return Some(WindowEvent::ImeCommit(values[0].clone()));
+        } else if command_parser.has("ime_allowed") {
+            let values = command_parser.get_values_of("ime_allowed");
+            return Some(WindowEvent::RequestImeAllowed(values[0]));
         }
         None
     }
@@ -70,6 +109,7 @@ pub struct Window {
     height: u32,
     scale_factor: f32,
     listener: Listener,
+    gamepads: Gamepads,
     can_continue: bool,
 }
 
@@ -90,11 +130,14 @@ impl Window {
             .register_type::<WindowEvent>()
             .register_type::<KeyEvent>()
             .register_type::<KeyTextEvent>()
-            .register_type::<MouseEvent>();
+            .register_type::<MouseEvent>()
+            .register_type::<ClipboardEvent>();
 
         let listener = Listener::new(message_hub);
         listener.register::<WindowEvent>();
 
+        let gamepads = Gamepads::new(message_hub);
+
         let mut scale_factor = 1.0;
         let handle = Window::create_handle(
             title,
@@ -114,6 +157,7 @@ impl Window {
             height,
             scale_factor,
             listener,
+            gamepads,
             can_continue: true,
         }
     }
@@ -147,9 +191,15 @@ impl Window {
         &self.handle
     }
 
+    #[inline]
+    pub fn gamepads_mut(&mut self) -> &mut Gamepads {
+        &mut self.gamepads
+    }
+
     #[inline]
     pub fn update(&mut self) -> bool {
         Window::internal_update(&self.handle);
+        self.gamepads.update();
         self.manage_window_events();
         self.can_continue
     }
@@ -191,6 +241,15 @@ impl Window {
             WindowEvent::RequestChangeSize(new_width, new_height) => {
                 Window::change_size(&self.handle, *new_width, *new_height);
             }
+            WindowEvent::SetCursorGrab(is_grabbed) => {
+                Window::change_cursor_grab(&self.handle, *is_grabbed);
+            }
+            WindowEvent::SetCursorVisible(is_visible) => {
+                Window::change_cursor_visibility(&self.handle, *is_visible);
+            }
+            WindowEvent::RequestImeAllowed(is_allowed) => {
+                Window::change_ime_allowed(&self.handle, *is_allowed);
+            }
             _ => {}
         });
 
@@ -1,8 +1,14 @@
 use std::{
+    collections::HashSet,
     fs::{self, create_dir_all, File},
     io::{Seek, SeekFrom},
     mem::size_of,
     path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
 };
 
 use crate::{need_to_binarize, to_local_path, ExtensionHandler};
@@ -14,17 +20,17 @@ use gltf::{
     khr_lights_punctual::{Kind, Light},
     material::AlphaMode,
     mesh::Mode,
-    Accessor, Camera, Gltf, Node, Primitive, Semantic, Texture,
+    Accessor, Camera, Document, Gltf, Node, Primitive, Semantic, Texture,
 };
 
 use inox_graphics::{
     DrawVertex, LightData, LightType, MaterialAlphaMode, MaterialData, MeshData, MeshletData,
-    TextureType, MAX_TEXTURE_COORDS_SETS,
+    TextureType, KTX2_EXTENSION, MAX_TEXTURE_COORDS_SETS,
 };
 use inox_log::debug_log;
 use inox_math::{
-    pack_4_f32_to_unorm, quantize_half, quantize_unorm, Mat4Ops, Matrix4, NewAngle, Parser,
-    Radians, VecBase, Vector2, Vector3, Vector4, Vector4h,
+    normalize_vec3_in_0_1, pack_3_f32_to_unorm_10, pack_4_f32_to_unorm, quantize_half, Mat4Ops,
+    Matrix4, NewAngle, Parser, Radians, VecBase, Vector2, Vector3, Vector4, Vector4h,
 };
 
 use inox_nodes::LogicData;
@@ -35,8 +41,10 @@ use inox_serialize::{
 };
 
 const GLTF_EXTENSION: &str = "gltf";
+//Nodes named with one of these prefixes followed by '_' get it as tag, like "collision_wall"
+const NODE_NAME_TAGS: [&str; 3] = ["collision", "spawn", "nav"];
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Clone)]
 #[serde(crate = "inox_serialize")]
 struct ExtraData {
     name: String,
@@ -47,7 +55,18 @@ struct ExtraData {
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 #[serde(crate = "inox_serialize")]
 struct ExtraProperties {
+    #[serde(default)]
     logic: ExtraData,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    cast_shadows: Option<bool>,
+    #[serde(default)]
+    shadow_bias: Option<f32>,
+    #[serde(default)]
+    shadow_normal_bias: Option<f32>,
+    #[serde(default)]
+    area_size: Option<[f32; 2]>,
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
@@ -56,6 +75,25 @@ struct Extras {
     inox_properties: ExtraProperties,
 }
 
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq, Clone)]
+#[serde(crate = "inox_serialize", rename_all = "camelCase")]
+struct TextureReference {
+    index: usize,
+    #[serde(default)]
+    tex_coord: usize,
+}
+
+//KHR_materials_clearcoat, that the gltf crate doesn't parse
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Clone)]
+#[serde(crate = "inox_serialize", rename_all = "camelCase", default)]
+struct Clearcoat {
+    clearcoat_factor: f32,
+    clearcoat_texture: Option<TextureReference>,
+    clearcoat_roughness_factor: f32,
+    clearcoat_roughness_texture: Option<TextureReference>,
+    clearcoat_normal_texture: Option<TextureReference>,
+}
+
 #[derive(Clone)]
 struct GltfVertex {
     position: Vector3,
@@ -88,8 +126,10 @@ pub struct GltfCompiler {
     data_raw_folder: PathBuf,
     data_folder: PathBuf,
     optimize_meshes: bool,
-    node_index: usize,
-    material_index: usize,
+    process_in_parallel: bool,
+    node_index: AtomicUsize,
+    material_index: AtomicUsize,
+    files_in_progress: Mutex<HashSet<PathBuf>>,
 }
 
 impl GltfCompiler {
@@ -104,12 +144,19 @@ impl GltfCompiler {
             data_raw_folder: data_raw_folder.to_path_buf(),
             data_folder: data_folder.to_path_buf(),
             optimize_meshes,
-            node_index: 0,
-            material_index: 0,
+            process_in_parallel: true,
+            node_index: AtomicUsize::new(0),
+            material_index: AtomicUsize::new(0),
+            files_in_progress: Mutex::new(HashSet::new()),
         }
     }
 
-    fn num_from_type(&mut self, accessor: &Accessor) -> usize {
+    pub fn set_process_in_parallel(&mut self, process_in_parallel: bool) -> &mut Self {
+        self.process_in_parallel = process_in_parallel;
+        self
+    }
+
+    fn num_from_type(&self, accessor: &Accessor) -> usize {
         match accessor.dimensions() {
             Dimensions::Vec2 => 2,
             Dimensions::Vec3 => 3,
@@ -120,7 +167,7 @@ impl GltfCompiler {
             _ => 1,
         }
     }
-    fn bytes_from_dimension(&mut self, accessor: &Accessor) -> usize {
+    fn bytes_from_dimension(&self, accessor: &Accessor) -> usize {
         match accessor.data_type() {
             DataType::F32 | DataType::U32 => 4,
             DataType::U16 | DataType::I16 => 2,
@@ -128,7 +175,7 @@ impl GltfCompiler {
         }
     }
 
-    fn read_accessor_from_path<T>(&mut self, path: &Path, accessor: &Accessor) -> Option<Vec<T>>
+    fn read_accessor_from_path<T>(&self, path: &Path, accessor: &Accessor) -> Option<Vec<T>>
     where
         T: Parser,
     {
@@ -155,7 +202,7 @@ impl GltfCompiler {
         None
     }
 
-    fn read_from_file<T>(&mut self, file: &mut File, view: &View, accessor: &Accessor) -> Vec<T>
+    fn read_from_file<T>(&self, file: &mut File, view: &View, accessor: &Accessor) -> Vec<T>
     where
         T: Parser,
     {
@@ -180,7 +227,7 @@ impl GltfCompiler {
         result
     }
 
-    fn extract_indices(&mut self, path: &Path, primitive: &Primitive) -> Vec<u32> {
+    fn extract_indices(&self, path: &Path, primitive: &Primitive) -> Vec<u32> {
         debug_assert!(primitive.mode() == Mode::Triangles);
         let mut indices = Vec::new();
         if let Some(accessor) = primitive.indices() {
@@ -202,7 +249,7 @@ impl GltfCompiler {
         indices
     }
 
-    fn extract_vertices(&mut self, path: &Path, primitive: &Primitive) -> Vec<GltfVertex> {
+    fn extract_vertices(&self, path: &Path, primitive: &Primitive) -> Vec<GltfVertex> {
         let mut vertices = Vec::new();
         for (_attribute_index, (semantic, accessor)) in primitive.attributes().enumerate() {
             //debug_log!("Attribute[{}]: {:?}", _attribute_index, semantic);
@@ -418,7 +465,7 @@ impl GltfCompiler {
     }
 
     fn process_mesh_data(
-        &mut self,
+        &self,
         path: &Path,
         mesh_name: &str,
         primitive: &Primitive,
@@ -439,28 +486,19 @@ impl GltfCompiler {
             mesh_data.aabb_max = mesh_data.aabb_max.max(v.position);
             mesh_data.aabb_min = mesh_data.aabb_min.min(v.position);
         });
-        let size = mesh_data.aabb_max - mesh_data.aabb_min;
-
         vertices.iter().enumerate().for_each(|(i, vertex)| {
-            let mut v = vertex.position - mesh_data.aabb_min;
-            v.x /= size.x;
-            v.y /= size.y;
-            v.z /= size.z;
-            let vx = quantize_unorm(v.x, 10);
-            let vy = quantize_unorm(v.y, 10);
-            let vz = quantize_unorm(v.z, 10);
-            let position = vx << 20 | vy << 10 | vz;
+            let position = pack_3_f32_to_unorm_10(normalize_vec3_in_0_1(
+                vertex.position,
+                mesh_data.aabb_min,
+                mesh_data.aabb_max,
+            ));
             mesh_data.positions.push(position);
             mesh_data.vertices[i].position_and_color_offset = (mesh_data.positions.len() - 1) as _;
 
             let color = pack_4_f32_to_unorm(vertex.color);
             mesh_data.colors.push(color);
 
-            let n = vertex.normal;
-            let nx = quantize_unorm(n.x, 10);
-            let ny = quantize_unorm(n.y, 10);
-            let nz = quantize_unorm(n.z, 10);
-            let normal = nx << 20 | ny << 10 | nz;
+            let normal = pack_3_f32_to_unorm_10((vertex.normal + Vector3::default_one()) * 0.5);
             mesh_data.normals.push(normal);
             mesh_data.vertices[i].normal_offset = (mesh_data.normals.len() - 1) as _;
 
@@ -475,6 +513,7 @@ impl GltfCompiler {
         });
 
         mesh_data.indices = indices;
+        mesh_data.compute_bounding_sphere();
 
         mesh_data.meshlets = self.compute_meshlets(vertices.as_slice(), &mut mesh_data.indices);
 
@@ -488,7 +527,7 @@ impl GltfCompiler {
             self.shared_data.serializable_registry(),
         )
     }
-    fn process_texture(&mut self, path: &Path, texture: Texture) -> PathBuf {
+    fn process_texture(&self, path: &Path, texture: Texture) -> PathBuf {
         if let ImageSource::Uri {
             uri,
             mime_type: _, /* fields */
@@ -496,7 +535,13 @@ impl GltfCompiler {
         {
             if let Some(parent_folder) = path.parent() {
                 let parent_path = parent_folder.to_str().unwrap().to_string();
-                let filepath = PathBuf::from(parent_path).join(uri);
+                let mut filepath = PathBuf::from(parent_path).join(uri);
+                //A compressed KTX2 version of the image is preferred when present:
+                //the original image is kept as fallback for devices that can't sample it
+                let ktx2_filepath = filepath.with_extension(KTX2_EXTENSION);
+                if ktx2_filepath.exists() {
+                    filepath = ktx2_filepath;
+                }
                 let path = to_local_path(
                     filepath.as_path(),
                     self.data_raw_folder.as_path(),
@@ -507,7 +552,13 @@ impl GltfCompiler {
         }
         PathBuf::new()
     }
-    fn process_material_data(&mut self, path: &Path, primitive: &Primitive) -> PathBuf {
+    fn process_material_data(
+        &self,
+        path: &Path,
+        document: &Document,
+        primitive: &Primitive,
+        material_index: usize,
+    ) -> PathBuf {
         let mut material_data = MaterialData::default();
 
         let material = primitive.material().pbr_metallic_roughness();
@@ -552,12 +603,61 @@ impl GltfCompiler {
             AlphaMode::Blend => MaterialAlphaMode::Blend,
         };
         material_data.alpha_cutoff = primitive.material().alpha_cutoff().unwrap_or(1.);
+        material_data.double_sided = material.double_sided();
+        material_data.unlit = material.unlit();
         material_data.emissive_color = [
             primitive.material().emissive_factor()[0],
             primitive.material().emissive_factor()[1],
             primitive.material().emissive_factor()[2],
         ]
         .into();
+        material_data.emissive_strength = emissive_strength(material.emissive_strength());
+        if let Some(transmission) = material.transmission() {
+            material_data.transmission_factor = transmission.transmission_factor();
+            if let Some(texture) = transmission.transmission_texture() {
+                material_data.textures[TextureType::Transmission as usize] =
+                    self.process_texture(path, texture.texture());
+                material_data.texcoords_set[TextureType::Transmission as usize] =
+                    texture.tex_coord() as _;
+            }
+        }
+        if let Some(volume) = material.volume() {
+            material_data.thickness_factor = volume.thickness_factor();
+            material_data.attenuation_color = volume.attenuation_color().into();
+            material_data.attenuation_distance = volume.attenuation_distance().min(f32::MAX);
+        }
+        if let Some(ior) = material.ior() {
+            material_data.ior = ior;
+        }
+        if let Some(clearcoat) = material
+            .extensions()
+            .and_then(|e| e.get("KHR_materials_clearcoat"))
+            .and_then(|e| parse_clearcoat(e.to_string().as_str(), &self.shared_data))
+        {
+            material_data.clearcoat_factor = clearcoat.clearcoat_factor;
+            material_data.clearcoat_roughness_factor = clearcoat.clearcoat_roughness_factor;
+            [
+                (TextureType::Clearcoat, &clearcoat.clearcoat_texture),
+                (
+                    TextureType::ClearcoatRoughness,
+                    &clearcoat.clearcoat_roughness_texture,
+                ),
+                (
+                    TextureType::ClearcoatNormal,
+                    &clearcoat.clearcoat_normal_texture,
+                ),
+            ]
+            .into_iter()
+            .for_each(|(texture_type, reference)| {
+                if let Some(reference) = reference {
+                    if let Some(texture) = document.textures().nth(reference.index) {
+                        let index = texture_type as usize;
+                        material_data.textures[index] = self.process_texture(path, texture);
+                        material_data.texcoords_set[index] = reference.tex_coord;
+                    }
+                }
+            });
+        }
         if let Some(material) = material.pbr_specular_glossiness() {
             if let Some(texture) = material.specular_glossiness_texture() {
                 material_data.textures[TextureType::SpecularGlossiness as usize] =
@@ -581,7 +681,7 @@ impl GltfCompiler {
             .into();
         }
 
-        let name = format!("Material_{}", self.material_index);
+        let name = format!("Material_{material_index}");
         self.create_file(
             path,
             &material_data,
@@ -591,40 +691,110 @@ impl GltfCompiler {
         )
     }
 
+    fn process_primitive(
+        &self,
+        path: &Path,
+        document: &Document,
+        mesh_name: &str,
+        primitive: &Primitive,
+        material_index: usize,
+    ) -> PathBuf {
+        let material_path = self.process_material_data(path, document, primitive, material_index);
+        let material_path = to_local_path(
+            material_path.as_path(),
+            self.data_raw_folder.as_path(),
+            self.data_folder.as_path(),
+        );
+        let mesh_path = self.process_mesh_data(path, mesh_name, primitive, material_path.as_path());
+        to_local_path(
+            mesh_path.as_path(),
+            self.data_raw_folder.as_path(),
+            self.data_folder.as_path(),
+        )
+    }
+
     fn process_node(
-        &mut self,
+        &self,
         path: &Path,
+        document: &Document,
         node: &Node,
         node_name: &str,
     ) -> Option<(NodeType, PathBuf)> {
-        let (node_type, node_path) = self.process_object(path, node, node_name);
-        self.node_index += 1;
+        let (node_type, node_path) = self.process_object(path, document, node, node_name);
+        self.node_index.fetch_add(1, Ordering::SeqCst);
         Some((node_type, node_path))
     }
 
-    fn process_object(&mut self, path: &Path, node: &Node, node_name: &str) -> (NodeType, PathBuf) {
+    fn process_object(
+        &self,
+        path: &Path,
+        document: &Document,
+        node: &Node,
+        node_name: &str,
+    ) -> (NodeType, PathBuf) {
         let mut object_data = ObjectData::default();
         let object_transform: Matrix4 = Matrix4::from(node.transform().matrix());
         object_data.transform = object_transform;
+        if let Some(name) = node.name() {
+            let name = name.to_lowercase();
+            object_data.tags.extend(
+                NODE_NAME_TAGS
+                    .iter()
+                    .filter(|tag| name.starts_with(&format!("{tag}_")))
+                    .map(|tag| tag.to_string()),
+            );
+        }
 
         if let Some(mesh) = node.mesh() {
-            for (primitive_index, primitive) in mesh.primitives().enumerate() {
-                let name = format!("{node_name}_Primitive_{primitive_index}");
-                let material_path = self.process_material_data(path, &primitive);
-                let material_path = to_local_path(
-                    material_path.as_path(),
-                    self.data_raw_folder.as_path(),
-                    self.data_folder.as_path(),
-                );
-                let mesh_path =
-                    self.process_mesh_data(path, &name, &primitive, material_path.as_path());
-                let mesh_path = to_local_path(
-                    mesh_path.as_path(),
-                    self.data_raw_folder.as_path(),
-                    self.data_folder.as_path(),
-                );
-                object_data.components.push(mesh_path);
-            }
+            //Names and material indices are assigned serially so that they don't depend on threads scheduling
+            let primitives = mesh
+                .primitives()
+                .enumerate()
+                .map(|(primitive_index, primitive)| {
+                    let name = format!("{node_name}_Primitive_{primitive_index}");
+                    let material_index = self.material_index.fetch_add(1, Ordering::SeqCst);
+                    (name, material_index, primitive)
+                })
+                .collect::<Vec<_>>();
+            let mesh_paths = if self.process_in_parallel && primitives.len() > 1 {
+                let num_threads = thread::available_parallelism()
+                    .map(|n| n.get())
+                    .unwrap_or(1);
+                let chunk_size = (primitives.len() + num_threads - 1) / num_threads;
+                thread::scope(|scope| {
+                    let handles = primitives
+                        .chunks(chunk_size)
+                        .map(|chunk| {
+                            scope.spawn(move || {
+                                chunk
+                                    .iter()
+                                    .map(|(name, material_index, primitive)| {
+                                        self.process_primitive(
+                                            path,
+                                            document,
+                                            name,
+                                            primitive,
+                                            *material_index,
+                                        )
+                                    })
+                                    .collect::<Vec<_>>()
+                            })
+                        })
+                        .collect::<Vec<_>>();
+                    handles
+                        .into_iter()
+                        .flat_map(|h| h.join().unwrap())
+                        .collect::<Vec<_>>()
+                })
+            } else {
+                primitives
+                    .iter()
+                    .map(|(name, material_index, primitive)| {
+                        self.process_primitive(path, document, name, primitive, *material_index)
+                    })
+                    .collect::<Vec<_>>()
+            };
+            object_data.components.extend(mesh_paths);
         }
         if let Some(camera) = node.camera() {
             let position = object_data.transform.translation();
@@ -652,6 +822,11 @@ impl GltfCompiler {
                 extras.to_string().as_str(),
                 self.shared_data.serializable_registry(),
             ) {
+                extras.inox_properties.tags.iter().for_each(|tag| {
+                    if !object_data.tags.contains(tag) {
+                        object_data.tags.push(tag.clone());
+                    }
+                });
                 if !extras.inox_properties.logic.name.is_empty() {
                     let mut path = path
                         .parent()
@@ -678,7 +853,11 @@ impl GltfCompiler {
         }
 
         for (child_index, child) in node.children().enumerate() {
-            let name = format!("Node_{}_Child_{}", self.node_index, child_index);
+            let name = format!(
+                "Node_{}_Child_{}",
+                self.node_index.load(Ordering::SeqCst),
+                child_index
+            );
             if let Some(camera) = child.camera() {
                 object_data.transform =
                     object_data.transform * Matrix4::from(child.transform().matrix());
@@ -694,7 +873,7 @@ impl GltfCompiler {
                     self.data_folder.as_path(),
                 ));
             } else if let Some((node_type, node_path)) =
-                self.process_node(path, &child, child.name().unwrap_or(&name))
+                self.process_node(path, document, &child, child.name().unwrap_or(&name))
             {
                 if node_type == NodeType::Object {
                     let node_path = to_local_path(
@@ -719,7 +898,7 @@ impl GltfCompiler {
         )
     }
 
-    fn process_light(&mut self, path: &Path, light: &Light) -> (NodeType, PathBuf) {
+    fn process_light(&self, path: &Path, light: &Light) -> (NodeType, PathBuf) {
         let mut light_data = LightData {
             color: [light.color()[0], light.color()[1], light.color()[2], 1.],
             intensity: light.intensity().max(1.),
@@ -742,8 +921,35 @@ impl GltfCompiler {
                 light_data.outer_cone_angle = outer_cone_angle;
             }
         }
+        //Shadows and area size are custom properties of the light.
+        //An area size turns it into an area light
+        if let Some(extras) = light.extras() {
+            if let Ok(extras) = deserialize::<Extras>(
+                extras.to_string().as_str(),
+                self.shared_data.serializable_registry(),
+            ) {
+                let properties = extras.inox_properties;
+                if let Some(cast_shadows) = properties.cast_shadows {
+                    light_data.cast_shadows = cast_shadows as _;
+                }
+                if let Some(shadow_bias) = properties.shadow_bias {
+                    light_data.shadow_bias = shadow_bias;
+                }
+                if let Some(shadow_normal_bias) = properties.shadow_normal_bias {
+                    light_data.shadow_normal_bias = shadow_normal_bias;
+                }
+                if let Some(area_size) = properties.area_size {
+                    light_data.light_type = LightType::Area as _;
+                    light_data.area_size = area_size;
+                }
+            }
+        }
 
-        let name = format!("Node_{}_Light_{}", self.node_index, light.index());
+        let name = format!(
+            "Node_{}_Light_{}",
+            self.node_index.load(Ordering::SeqCst),
+            light.index()
+        );
         (
             NodeType::Light,
             self.create_file(
@@ -756,7 +962,7 @@ impl GltfCompiler {
         )
     }
 
-    fn process_camera(&mut self, path: &Path, camera: &Camera) -> (NodeType, PathBuf) {
+    fn process_camera(&self, path: &Path, camera: &Camera) -> (NodeType, PathBuf) {
         let mut camera_data = CameraData::default();
         match camera.projection() {
             Projection::Perspective(p) => {
@@ -770,7 +976,11 @@ impl GltfCompiler {
                 camera_data.far = o.zfar();
             }
         }
-        let name = format!("Node_{}_Camera_{}", self.node_index, camera.index());
+        let name = format!(
+            "Node_{}_Camera_{}",
+            self.node_index.load(Ordering::SeqCst),
+            camera.index()
+        );
 
         (
             NodeType::Camera,
@@ -784,7 +994,7 @@ impl GltfCompiler {
         )
     }
 
-    pub fn process_path(&mut self, path: &Path) {
+    pub fn process_path(&self, path: &Path) {
         if let Ok(gltf) = Gltf::open(path) {
             for scene in gltf.scenes() {
                 let mut scene_data = SceneData::default();
@@ -798,13 +1008,17 @@ impl GltfCompiler {
 
                 let new_path = self.compute_path_name::<SceneData>(path, scene_name, "");
                 if need_to_binarize(path, new_path.as_path()) {
-                    self.material_index = 0;
-                    self.node_index = 0;
+                    self.material_index.store(0, Ordering::SeqCst);
+                    self.node_index.store(0, Ordering::SeqCst);
+                    self.files_in_progress.lock().unwrap().clear();
                     for node in scene.nodes() {
-                        let name = format!("Node_{}", self.node_index);
-                        if let Some((node_type, node_path)) =
-                            self.process_node(path, &node, node.name().unwrap_or(&name))
-                        {
+                        let name = format!("Node_{}", self.node_index.load(Ordering::SeqCst));
+                        if let Some((node_type, node_path)) = self.process_node(
+                            path,
+                            &gltf.document,
+                            &node,
+                            node.name().unwrap_or(&name),
+                        ) {
                             let node_path = to_local_path(
                                 node_path.as_path(),
                                 self.data_raw_folder.as_path(),
@@ -878,7 +1092,14 @@ impl GltfCompiler {
             let result = create_dir_all(new_path.parent().unwrap());
             debug_assert!(result.is_ok());
         }
-        if need_to_binarize(path, new_path.as_path()) {
+        //Primitives sharing a material are processed concurrently: only the first one writes it
+        if need_to_binarize(path, new_path.as_path())
+            && self
+                .files_in_progress
+                .lock()
+                .unwrap()
+                .insert(new_path.clone())
+        {
             debug_log!("Serializing {:?}", new_path);
             data.save_to_file(new_path.as_path(), serializable_registry);
         }
@@ -896,3 +1117,444 @@ impl ExtensionHandler for GltfCompiler {
         }
     }
 }
+
+//All the scenes of a gltf are compiled in a scene named after the folder of the gltf
+pub fn gltf_scene_path(path: &Path, data_raw_folder: &Path, data_folder: &Path) -> Option<PathBuf> {
+    let folder = path.parent()?;
+    let scene_name = folder.file_stem()?.to_str()?;
+    let folder = folder
+        .to_str()?
+        .replace(data_raw_folder.to_str()?, data_folder.to_str()?);
+    Some(PathBuf::from(folder).join(format!("{}.{}", scene_name, SceneData::extension())))
+}
+
+#[allow(dead_code)]
+fn collect_compiled_files(root: &Path, folder: &Path, files: &mut Vec<PathBuf>) {
+    if let Ok(dir) = fs::read_dir(folder) {
+        dir.filter_map(Result::ok).for_each(|entry| {
+            let path = entry.path();
+            if path.is_dir() {
+                collect_compiled_files(root, path.as_path(), files);
+            } else if let Ok(relative_path) = path.strip_prefix(root) {
+                files.push(relative_path.to_path_buf());
+            }
+        });
+    }
+}
+
+//KHR_materials_emissive_strength multiplier, that can't be negative
+fn emissive_strength(strength: Option<f32>) -> f32 {
+    match strength {
+        None => 1.,
+        Some(strength) if strength >= 0. => strength,
+        Some(strength) => {
+            eprintln!("Invalid emissive strength {strength} - using 0");
+            0.
+        }
+    }
+}
+
+fn parse_clearcoat(extension: &str, shared_data: &SharedDataRc) -> Option<Clearcoat> {
+    match deserialize::<Clearcoat>(extension, shared_data.serializable_registry()) {
+        Ok(clearcoat) => Some(clearcoat),
+        Err(e) => {
+            eprintln!("Invalid KHR_materials_clearcoat {extension} - {e}");
+            None
+        }
+    }
+}
+
+//Compiles a mesh made of several triangle primitives, two of them sharing a material,
+//and returns the compiled files with their content
+#[allow(dead_code)]
+fn compile_test_model(process_in_parallel: bool) -> Vec<(PathBuf, Vec<u8>)> {
+    const NUM_PRIMITIVES: usize = 4;
+    let root = std::env::temp_dir().join(format!("inox_gltf_compiler_{process_in_parallel}"));
+    fs::remove_dir_all(root.as_path()).ok();
+    let model_folder = root.join("data_raw").join("model");
+    let data_folder = root.join("data");
+    create_dir_all(model_folder.as_path()).unwrap();
+    create_dir_all(data_folder.as_path()).unwrap();
+
+    //Every primitive is a triangle moved along x, with its positions followed by its indices
+    let mut bin = Vec::new();
+    let mut buffer_views = Vec::new();
+    let mut accessors = Vec::new();
+    let mut primitives = Vec::new();
+    (0..NUM_PRIMITIVES).for_each(|i| {
+        let x = i as f32;
+        let positions = [x, 0., 0., x + 1., 0., 0., x, 1., (i % 2) as f32];
+        let offset = bin.len();
+        positions
+            .iter()
+            .for_each(|v| bin.extend_from_slice(&v.to_le_bytes()));
+        buffer_views.push(format!(
+            r#"{{ "buffer": 0, "byteOffset": {offset}, "byteLength": {} }}"#,
+            bin.len() - offset
+        ));
+        accessors.push(format!(
+            r#"{{ "bufferView": {}, "componentType": 5126, "count": 3, "type": "VEC3",
+                "min": [{x}, 0, 0], "max": [{}, 1, {}] }}"#,
+            buffer_views.len() - 1,
+            x + 1.,
+            i % 2
+        ));
+        let offset = bin.len();
+        [0u16, 1, 2, 0]
+            .iter()
+            .for_each(|i| bin.extend_from_slice(&i.to_le_bytes()));
+        buffer_views.push(format!(
+            r#"{{ "buffer": 0, "byteOffset": {offset}, "byteLength": 6 }}"#
+        ));
+        accessors.push(format!(
+            r#"{{ "bufferView": {}, "componentType": 5123, "count": 3, "type": "SCALAR" }}"#,
+            buffer_views.len() - 1
+        ));
+        primitives.push(format!(
+            r#"{{ "attributes": {{ "POSITION": {} }}, "indices": {}, "material": {} }}"#,
+            accessors.len() - 2,
+            accessors.len() - 1,
+            i.min(NUM_PRIMITIVES - 2)
+        ));
+    });
+    fs::write(model_folder.join("Model.bin"), &bin).unwrap();
+    let materials = (0..NUM_PRIMITIVES - 1)
+        .map(|i| format!(r#"{{ "name": "Material_{i}", "emissiveFactor": [0.{i}, 0, 0] }}"#))
+        .collect::<Vec<_>>();
+    fs::write(
+        model_folder.join("Model.gltf"),
+        format!(
+            r#"{{
+            "asset": {{ "version": "2.0" }},
+            "scene": 0,
+            "scenes": [{{ "nodes": [0] }}],
+            "nodes": [{{ "mesh": 0, "name": "Model" }}],
+            "meshes": [{{ "primitives": [{}] }}],
+            "materials": [{}],
+            "buffers": [{{ "uri": "Model.bin", "byteLength": {} }}],
+            "bufferViews": [{}],
+            "accessors": [{}]
+        }}"#,
+            primitives.join(", "),
+            materials.join(", "),
+            bin.len(),
+            buffer_views.join(", "),
+            accessors.join(", "),
+        ),
+    )
+    .unwrap();
+    let data_raw_folder = root.join("data_raw").canonicalize().unwrap();
+    let data_folder = data_folder.canonicalize().unwrap();
+
+    let mut compiler = GltfCompiler::new(
+        SharedDataRc::default(),
+        data_raw_folder.as_path(),
+        data_folder.as_path(),
+        false,
+    );
+    compiler.set_process_in_parallel(process_in_parallel);
+    compiler.process_path(data_raw_folder.join("model").join("Model.gltf").as_path());
+
+    let mut files = Vec::new();
+    collect_compiled_files(data_folder.as_path(), data_folder.as_path(), &mut files);
+    files.sort();
+    files
+        .into_iter()
+        .map(|f| {
+            let content = fs::read(data_folder.join(f.as_path())).unwrap();
+            (f, content)
+        })
+        .collect()
+}
+
+#[test]
+fn test_parallel_process_path() {
+    let serial_files = compile_test_model(false);
+    let parallel_files = compile_test_model(true);
+    let count = |extension: &str| {
+        serial_files
+            .iter()
+            .filter(|(f, _)| f.extension().map_or(false, |e| e == extension))
+            .count()
+    };
+    //One mesh for each primitive and one material less, as two primitives share it
+    assert_eq!(count(MeshData::extension()), 4);
+    assert_eq!(count(MaterialData::extension()), 3);
+    assert_eq!(
+        serial_files.iter().map(|(f, _)| f).collect::<Vec<_>>(),
+        parallel_files.iter().map(|(f, _)| f).collect::<Vec<_>>()
+    );
+    serial_files
+        .iter()
+        .zip(parallel_files.iter())
+        .for_each(|((path, serial), (_, parallel))| {
+            assert!(
+                serial == parallel,
+                "{path:?} differs when processed in parallel"
+            );
+        });
+}
+
+#[test]
+fn test_gltf_scene_path() {
+    let data_raw_folder = PathBuf::from("root").join("data_raw");
+    let data_folder = PathBuf::from("root").join("data");
+    let path = data_raw_folder
+        .join("external")
+        .join("craftman")
+        .join("Craftman.gltf");
+    assert_eq!(
+        gltf_scene_path(
+            path.as_path(),
+            data_raw_folder.as_path(),
+            data_folder.as_path()
+        ),
+        Some(
+            data_folder
+                .join("external")
+                .join("craftman")
+                .join(format!("craftman.{}", SceneData::extension()))
+        )
+    );
+}
+
+#[test]
+fn test_emissive_strength() {
+    assert_eq!(emissive_strength(None), 1.);
+    assert_eq!(emissive_strength(Some(0.)), 0.);
+    assert_eq!(emissive_strength(Some(25.)), 25.);
+    assert_eq!(emissive_strength(Some(-2.)), 0.);
+    assert_eq!(emissive_strength(Some(f32::NAN)), 0.);
+}
+
+#[test]
+fn test_clearcoat() {
+    let shared_data = SharedDataRc::default();
+    assert_eq!(
+        parse_clearcoat("{}", &shared_data),
+        Some(Clearcoat::default())
+    );
+    let clearcoat = parse_clearcoat(
+        r#"{
+            "clearcoatFactor": 1.0,
+            "clearcoatRoughnessFactor": 0.2,
+            "clearcoatNormalTexture": { "index": 3, "texCoord": 1, "scale": 0.5 }
+        }"#,
+        &shared_data,
+    )
+    .unwrap();
+    assert_eq!(clearcoat.clearcoat_factor, 1.);
+    assert_eq!(clearcoat.clearcoat_roughness_factor, 0.2);
+    assert_eq!(clearcoat.clearcoat_texture, None);
+    assert_eq!(
+        clearcoat.clearcoat_normal_texture,
+        Some(TextureReference {
+            index: 3,
+            tex_coord: 1
+        })
+    );
+}
+
+//Compiles a mesh with the given positions, indices and gltf material
+//and returns the first compiled data of type T
+#[allow(dead_code)]
+fn compile_test_mesh<T>(test_name: &str, positions: &[f32], indices: &[u16], material: &str) -> T
+where
+    T: SerializeFile + for<'de> Deserialize<'de>,
+{
+    let root = std::env::temp_dir().join(format!("inox_gltf_compiler_{test_name}"));
+    fs::remove_dir_all(root.as_path()).ok();
+    let model_folder = root.join("data_raw").join("mesh");
+    let data_folder = root.join("data");
+    create_dir_all(model_folder.as_path()).unwrap();
+    create_dir_all(data_folder.as_path()).unwrap();
+
+    //Positions followed by the indices padded to 4 bytes
+    let mut bin = Vec::new();
+    positions
+        .iter()
+        .for_each(|v| bin.extend_from_slice(&v.to_le_bytes()));
+    let positions_length = bin.len();
+    indices
+        .iter()
+        .for_each(|i| bin.extend_from_slice(&i.to_le_bytes()));
+    let indices_length = bin.len() - positions_length;
+    bin.resize((bin.len() + 3) & !3, 0);
+    fs::write(model_folder.join("Mesh.bin"), &bin).unwrap();
+    let bounds = |f: fn(f32, f32) -> f32| {
+        (0..3)
+            .map(|c| {
+                positions
+                    .iter()
+                    .skip(c)
+                    .step_by(3)
+                    .copied()
+                    .reduce(f)
+                    .unwrap()
+            })
+            .map(|v| v.to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    fs::write(
+        model_folder.join("Mesh.gltf"),
+        format!(
+            r#"{{
+            "asset": {{ "version": "2.0" }},
+            "scene": 0,
+            "scenes": [{{ "nodes": [0] }}],
+            "nodes": [{{ "mesh": 0, "name": "Mesh" }}],
+            "meshes": [{{
+                "primitives": [{{ "attributes": {{ "POSITION": 0 }}, "indices": 1, "material": 0 }}]
+            }}],
+            "materials": [{material}],
+            "textures": [{{ "source": 0 }}],
+            "images": [{{ "uri": "Leaf.png" }}],
+            "buffers": [{{ "uri": "Mesh.bin", "byteLength": {} }}],
+            "bufferViews": [
+                {{ "buffer": 0, "byteOffset": 0, "byteLength": {positions_length} }},
+                {{ "buffer": 0, "byteOffset": {positions_length}, "byteLength": {indices_length} }}
+            ],
+            "accessors": [
+                {{
+                    "bufferView": 0, "componentType": 5126, "count": {}, "type": "VEC3",
+                    "min": [{}], "max": [{}]
+                }},
+                {{ "bufferView": 1, "componentType": 5123, "count": {}, "type": "SCALAR" }}
+            ]
+        }}"#,
+            bin.len(),
+            positions.len() / 3,
+            bounds(f32::min),
+            bounds(f32::max),
+            indices.len(),
+        ),
+    )
+    .unwrap();
+    let data_raw_folder = root.join("data_raw").canonicalize().unwrap();
+    let data_folder = data_folder.canonicalize().unwrap();
+
+    let compiler = GltfCompiler::new(
+        SharedDataRc::default(),
+        data_raw_folder.as_path(),
+        data_folder.as_path(),
+        false,
+    );
+    compiler.process_path(data_raw_folder.join("mesh").join("Mesh.gltf").as_path());
+
+    let mut files = Vec::new();
+    collect_compiled_files(data_folder.as_path(), data_folder.as_path(), &mut files);
+    let path = files
+        .iter()
+        .find(|f| f.extension().map_or(false, |e| e == T::extension()))
+        .unwrap();
+    deserialize(
+        fs::read_to_string(data_folder.join(path)).unwrap().as_str(),
+        compiler.shared_data.serializable_registry(),
+    )
+    .unwrap()
+}
+
+//Compiles a single tilted leaf triangle with the given gltf material and returns its material data
+#[allow(dead_code)]
+fn compile_triangle_material(test_name: &str, material: &str) -> MaterialData {
+    compile_test_mesh(
+        test_name,
+        &[0., 0., 0., 1., 0., 1., 0., 1., 0.],
+        &[0, 1, 2],
+        material,
+    )
+}
+
+#[test]
+fn test_double_sided_material() {
+    let material_data =
+        compile_triangle_material("double_sided", r#"{ "name": "Leaf", "doubleSided": true }"#);
+    assert!(material_data.double_sided);
+}
+
+#[test]
+fn test_unlit_material() {
+    let material_data = compile_triangle_material(
+        "unlit",
+        r#"{ "name": "Baked", "extensions": { "KHR_materials_unlit": {} } }"#,
+    );
+    assert!(material_data.unlit);
+    let material_data = compile_triangle_material("lit", r#"{ "name": "Leaf" }"#);
+    assert!(!material_data.unlit);
+}
+
+#[test]
+fn test_emissive_strength_material() {
+    let material_data = compile_triangle_material(
+        "emissive_strength",
+        r#"{
+            "name": "Lamp",
+            "emissiveFactor": [1.0, 0.5, 0.25],
+            "extensions": { "KHR_materials_emissive_strength": { "emissiveStrength": 5.0 } }
+        }"#,
+    );
+    assert_eq!(material_data.emissive_strength, 5.);
+    //The shader multiplies the emissive color by the strength
+    let emissive = material_data.emissive_color * material_data.emissive_strength;
+    assert_eq!(emissive, Vector3::new(5., 2.5, 1.25));
+
+    let material_data = compile_triangle_material(
+        "emissive_default_strength",
+        r#"{ "name": "Lamp", "emissiveFactor": [1.0, 0.5, 0.25] }"#,
+    );
+    assert_eq!(material_data.emissive_strength, 1.);
+}
+
+#[test]
+fn test_clearcoat_material() {
+    let material_data = compile_triangle_material(
+        "clearcoat",
+        r#"{
+            "name": "Paint",
+            "extensions": {
+                "KHR_materials_clearcoat": {
+                    "clearcoatFactor": 0.8,
+                    "clearcoatRoughnessFactor": 0.3,
+                    "clearcoatTexture": { "index": 0, "texCoord": 1 }
+                }
+            }
+        }"#,
+    );
+    assert_eq!(material_data.clearcoat_factor, 0.8);
+    assert_eq!(material_data.clearcoat_roughness_factor, 0.3);
+    let clearcoat = TextureType::Clearcoat as usize;
+    assert!(material_data.textures[clearcoat].ends_with("Leaf.png"));
+    assert_eq!(material_data.texcoords_set[clearcoat], 1);
+    assert!(
+        material_data.textures[TextureType::ClearcoatNormal as usize]
+            .as_os_str()
+            .is_empty()
+    );
+
+    //Without the extension the material is a standard PBR one
+    let material_data = compile_triangle_material("no_clearcoat", r#"{ "name": "Paint" }"#);
+    assert_eq!(material_data.clearcoat_factor, 0.);
+    assert_eq!(material_data.transmission_factor, 0.);
+    assert!(material_data.textures[clearcoat].as_os_str().is_empty());
+}
+
+#[test]
+fn test_planar_mesh_positions() {
+    //A quad with no extent along z
+    let mesh_data: MeshData = compile_test_mesh(
+        "planar",
+        &[0., 0., 0., 2., 0., 0., 2., 1., 0., 0., 1., 0.],
+        &[0, 1, 2, 0, 2, 3],
+        r#"{ "name": "Quad" }"#,
+    );
+    assert_eq!(mesh_data.positions.len(), 4);
+    mesh_data.positions.iter().enumerate().for_each(|(i, p)| {
+        assert!(*p <= 0x3FFF_FFFF, "{p:#x} is past 30 bits");
+        let position = mesh_data.position(i);
+        assert!(position.x.is_finite() && position.y.is_finite() && position.z.is_finite());
+        assert!(position.x >= 0. && position.x <= 2.);
+        assert!(position.y >= 0. && position.y <= 1.);
+        assert_eq!(position.z, 0.);
+    });
+}
@@ -3,22 +3,22 @@ use std::{
     path::{Path, PathBuf},
     sync::{
         atomic::{AtomicBool, Ordering},
-        Arc,
+        Arc, Mutex,
     },
     thread::{self, JoinHandle},
 };
 
 use inox_core::{ContextRc, System, SystemId, SystemUID};
-use inox_messenger::MessageHubRc;
+use inox_messenger::{Listener, MessageHubRc};
 
 use inox_platform::PlatformType;
-use inox_resources::{ConfigBase, SharedDataRc};
+use inox_resources::{BinarizeEvent, ConfigBase, SharedDataRc};
 use inox_serialize::read_from_file;
 use inox_uid::generate_uid_from_string;
 
 use crate::{
-    config::Config, CopyCompiler, DataWatcher, FontCompiler, GltfCompiler, ImageCompiler,
-    ShaderCompiler,
+    config::Config, gltf_scene_path, send_reloaded_event, CopyCompiler, DataWatcher, FontCompiler,
+    GltfCompiler, ImageCompiler, ShaderCompiler,
 };
 
 struct Info {
@@ -32,6 +32,9 @@ pub struct Binarizer<const PLATFORM_TYPE: PlatformType> {
     data_folder: PathBuf,
     shared_data: SharedDataRc,
     message_hub: MessageHubRc,
+    listener: Listener,
+    //Files to binarize with the folder where their references are resolved
+    external_files: Arc<Mutex<Vec<(PathBuf, PathBuf)>>>,
     thread_handle: Option<JoinHandle<bool>>,
     is_running: Arc<AtomicBool>,
     is_ready: Arc<AtomicBool>,
@@ -58,10 +61,13 @@ impl<const PLATFORM_TYPE: PlatformType> Binarizer<PLATFORM_TYPE> {
             data_raw_folder.exists() && data_raw_folder.is_dir() && data_raw_folder.is_absolute()
         );
         debug_assert!(data_folder.exists() && data_folder.is_dir() && data_folder.is_absolute());
+        app_context.message_hub().register_type::<BinarizeEvent>();
         Self {
             config: Config::default(),
             shared_data: app_context.shared_data().clone(),
             message_hub: app_context.message_hub().clone(),
+            listener: Listener::new(app_context.message_hub()),
+            external_files: Arc::new(Mutex::new(Vec::new())),
             data_raw_folder,
             data_folder,
             thread_handle: None,
@@ -126,6 +132,7 @@ impl<const PLATFORM_TYPE: PlatformType> Binarizer<PLATFORM_TYPE> {
         let message_hub = self.message_hub.clone();
         let data_raw_folder = self.data_raw_folder.clone();
         let data_folder = self.data_folder.clone();
+        let external_files = self.external_files.clone();
 
         let t = builder
             .spawn(move || -> bool {
@@ -146,6 +153,25 @@ impl<const PLATFORM_TYPE: PlatformType> Binarizer<PLATFORM_TYPE> {
                 loop {
                     binarizer.update();
 
+                    let files = std::mem::take(&mut *external_files.lock().unwrap());
+                    for (path, folder) in files {
+                        inox_log::debug_log!("Binarizing {:?} from {:?}", path, folder);
+                        //A compiled scene is loaded as soon as it's ready
+                        if let Some(scene_path) = binarizer
+                            .binarize_external(path.as_path(), folder.as_path())
+                            .and_then(|raw_path| {
+                                gltf_scene_path(
+                                    raw_path.as_path(),
+                                    data_raw_folder.as_path(),
+                                    data_folder.as_path(),
+                                )
+                            })
+                            .filter(|scene_path| scene_path.exists())
+                        {
+                            send_reloaded_event(&message_hub, scene_path.as_path());
+                        }
+                    }
+
                     if info.should_end_on_completion.load(Ordering::SeqCst) {
                         can_continue.store(false, Ordering::SeqCst);
                     }
@@ -211,10 +237,18 @@ impl<const PLATFORM_TYPE: PlatformType> System for Binarizer<PLATFORM_TYPE> {
     }
 
     fn init(&mut self) {
+        self.listener.register::<BinarizeEvent>();
         self.start();
     }
 
     fn run(&mut self) -> bool {
+        self.listener.process_messages(|event: &BinarizeEvent| {
+            let BinarizeEvent::Binarize(path, folder) = event;
+            self.external_files
+                .lock()
+                .unwrap()
+                .push((path.clone(), folder.clone()));
+        });
         let result = self.is_running();
         if !result {
             self.stop();
@@ -223,6 +257,7 @@ impl<const PLATFORM_TYPE: PlatformType> System for Binarizer<PLATFORM_TYPE> {
         result
     }
     fn uninit(&mut self) {
+        self.listener.unregister::<BinarizeEvent>();
         self.stop();
     }
 }
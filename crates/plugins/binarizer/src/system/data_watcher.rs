@@ -1,8 +1,15 @@
 use std::path::{Path, PathBuf};
 
+use gltf::{buffer::Source, image::Source as ImageSource, Gltf};
 use inox_filesystem::convert_from_local_path;
+use inox_graphics::KTX2_EXTENSION;
 use inox_platform::{FileEvent, FileWatcher};
 
+use crate::need_to_binarize;
+
+//Folder of the data raw folder where the files coming from outside are copied
+const EXTERNAL_FOLDER: &str = "external";
+
 pub trait ExtensionHandler {
     fn on_changed(&mut self, path: &Path);
 }
@@ -44,6 +51,31 @@ impl DataWatcher {
         self.binarize_folder(path.as_path());
     }
 
+    //Files outside of the data raw folder are copied in it together with the files they reference,
+    //keeping their path relative to folder so that the references are still valid when binarized.
+    //Returns the path of the raw file that has been binarized
+    pub fn binarize_external(&mut self, path: &Path, folder: &Path) -> Option<PathBuf> {
+        let path = path.canonicalize().ok()?;
+        let folder = folder.canonicalize().ok()?;
+        let relative_path = path.strip_prefix(folder.as_path()).ok()?.to_path_buf();
+        let files = referenced_files(path.as_path());
+        let (raw_folder, raw_files) = if folder.starts_with(self.data_raw_folder.as_path()) {
+            (folder, files)
+        } else {
+            let raw_folder = self
+                .data_raw_folder
+                .join(EXTERNAL_FOLDER)
+                .join(folder.file_name()?);
+            let raw_files = copy_files(&files, folder.as_path(), raw_folder.as_path());
+            (raw_folder, raw_files)
+        };
+        raw_files.iter().for_each(|raw_file| {
+            self.binarize_file(raw_file.as_path());
+        });
+        let raw_path = raw_folder.join(relative_path);
+        raw_path.exists().then_some(raw_path)
+    }
+
     fn binarize_file(&mut self, path: &Path) {
         let absolute_path = convert_from_local_path(self.data_raw_folder.as_path(), path);
         for handler in self.handlers.iter_mut() {
@@ -67,8 +99,106 @@ impl DataWatcher {
     }
 }
 
+//Returns the file itself followed by the buffers and images a gltf or glb file references
+fn referenced_files(path: &Path) -> Vec<PathBuf> {
+    let mut files = vec![path.to_path_buf()];
+    let (Some(parent_folder), Ok(gltf)) = (path.parent(), Gltf::open(path)) else {
+        return files;
+    };
+    let is_external = |uri: &str| !uri.starts_with("data:");
+    let mut add_file = |path: PathBuf| {
+        if !files.contains(&path) {
+            files.push(path);
+        }
+    };
+    gltf.buffers().for_each(|buffer| {
+        if let Source::Uri(uri) = buffer.source() {
+            if is_external(uri) {
+                add_file(parent_folder.join(uri));
+            }
+        }
+    });
+    gltf.images().for_each(|image| {
+        if let ImageSource::Uri { uri, .. } = image.source() {
+            if is_external(uri) {
+                let image_path = parent_folder.join(uri);
+                //The compressed version is used in place of the image when present
+                let ktx2_path = image_path.with_extension(KTX2_EXTENSION);
+                if ktx2_path.exists() {
+                    add_file(ktx2_path);
+                }
+                add_file(image_path);
+            }
+        }
+    });
+    files
+}
+
+//Copies the files keeping their path relative to from and returns the copied paths
+fn copy_files(files: &[PathBuf], from: &Path, to: &Path) -> Vec<PathBuf> {
+    files
+        .iter()
+        .filter_map(|path| {
+            let path = path.canonicalize().ok()?;
+            let Ok(relative_path) = path.strip_prefix(from) else {
+                eprintln!("Unable to copy {path:?} that is outside of {from:?}");
+                return None;
+            };
+            let new_path = to.join(relative_path);
+            std::fs::create_dir_all(new_path.parent()?).ok()?;
+            if need_to_binarize(path.as_path(), new_path.as_path()) {
+                std::fs::copy(path.as_path(), new_path.as_path()).ok()?;
+            }
+            Some(new_path)
+        })
+        .collect()
+}
+
 impl Drop for DataWatcher {
     fn drop(&mut self) {
         self.filewatcher.stop();
     }
 }
+
+#[test]
+fn test_copy_referenced_files() {
+    let root = std::env::temp_dir().join("inox_data_watcher_external");
+    let _ = std::fs::remove_dir_all(root.as_path());
+    let from = root.join("Model");
+    let to = root.join("data_raw").join(EXTERNAL_FOLDER).join("Model");
+    std::fs::create_dir_all(from.join("textures")).unwrap();
+    let gltf = r#"{
+        "asset": {"version": "2.0"},
+        "buffers": [
+            {"uri": "Model.bin", "byteLength": 4},
+            {"uri": "data:application/octet-stream;base64,AAAAAA==", "byteLength": 4}
+        ],
+        "images": [{"uri": "textures/Color.png"}]
+    }"#;
+    std::fs::write(from.join("Model.gltf"), gltf).unwrap();
+    std::fs::write(from.join("Model.bin"), [0u8; 4]).unwrap();
+    std::fs::write(from.join("textures").join("Color.png"), [0u8; 4]).unwrap();
+    std::fs::write(from.join("Unrelated.gltf"), gltf).unwrap();
+    std::fs::write(from.join("textures").join("Unrelated.png"), [0u8; 4]).unwrap();
+
+    let from = from.canonicalize().unwrap();
+    let files = referenced_files(from.join("Model.gltf").as_path());
+    let copied = copy_files(&files, from.as_path(), to.as_path());
+    assert_eq!(
+        copied,
+        vec![
+            to.join("Model.gltf"),
+            to.join("Model.bin"),
+            to.join("textures").join("Color.png")
+        ]
+    );
+    assert!(copied.iter().all(|path| path.exists()));
+    assert!(!to.join("Unrelated.gltf").exists());
+    assert!(!to.join("textures").join("Unrelated.png").exists());
+
+    //Files that aren't gltf are copied alone
+    let files = referenced_files(from.join("Model.bin").as_path());
+    assert_eq!(files, vec![from.join("Model.bin")]);
+
+    let _ = std::fs::remove_dir_all(root.as_path());
+}
@@ -53,7 +53,7 @@ impl Connector {
     fn handle_events(&mut self) {
         self.listener
             .process_messages(|e: &ConfigEvent<Config>| match e {
-                ConfigEvent::Loaded(filename, config) => {
+                ConfigEvent::Loaded(filename, config) | ConfigEvent::Reloaded(filename, config) => {
                     if filename == self.config.get_filename() {
                         self.config = config.clone();
                         self.host_address_and_port = self.config.host_address.clone()
@@ -1,14 +1,39 @@
 use std::path::PathBuf;
 
-use inox_resources::ConfigBase;
+use inox_graphics::{RenderPass, DEFAULT_CLEAR_COLOR, GBUFFER_PASS_NAME, WIREFRAME_PASS_NAME};
+use inox_math::Vector4;
+use inox_resources::{ConfigBase, SharedDataRc};
+use inox_scene::{CameraController, DEFAULT_CAMERA_ROTATION_SPEED, DEFAULT_CAMERA_SPEED};
 use inox_serialize::{Deserialize, Serialize, SerializeFile};
+use inox_ui::{DockTree, UI_PASS_NAME};
 
-#[derive(Default, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
-#[serde(crate = "inox_serialize")]
+use crate::widgets::default_dock_layout;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(crate = "inox_serialize", default)]
 pub struct Config {
     pub opaque_pass_pipeline: PathBuf,
     pub wireframe_pass_pipeline: PathBuf,
     pub ui_pass_pipeline: PathBuf,
+    pub camera_speed: f32,
+    pub camera_rotation_speed: f32,
+    pub background_color: Vector4,
+    //Saved when the panels are moved or resized, to restore them on next launch
+    pub dock_layout: DockTree,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            opaque_pass_pipeline: PathBuf::new(),
+            wireframe_pass_pipeline: PathBuf::new(),
+            ui_pass_pipeline: PathBuf::new(),
+            camera_speed: DEFAULT_CAMERA_SPEED,
+            camera_rotation_speed: DEFAULT_CAMERA_ROTATION_SPEED,
+            background_color: DEFAULT_CLEAR_COLOR,
+            dock_layout: default_dock_layout(),
+        }
+    }
 }
 
 impl SerializeFile for Config {
@@ -21,3 +46,91 @@ impl ConfigBase for Config {
         "viewer.cfg"
     }
 }
+
+impl Config {
+    pub fn apply(&self, shared_data: &SharedDataRc) {
+        if let Some(ui_pass) = shared_data.match_resource(|r: &RenderPass| r.name() == UI_PASS_NAME)
+        {
+            ui_pass.get_mut().set_pipeline(&self.ui_pass_pipeline);
+        }
+        if let Some(default_pass) =
+            shared_data.match_resource(|r: &RenderPass| r.name() == GBUFFER_PASS_NAME)
+        {
+            default_pass
+                .get_mut()
+                .set_pipeline(&self.opaque_pass_pipeline);
+        }
+        if let Some(wireframe_pass) =
+            shared_data.match_resource(|r: &RenderPass| r.name() == WIREFRAME_PASS_NAME)
+        {
+            wireframe_pass
+                .get_mut()
+                .set_pipeline(&self.wireframe_pass_pipeline);
+        }
+        //Passes drawing on the screen clear it with the background color
+        shared_data.for_each_resource_mut(|_, r: &mut RenderPass| {
+            if r.render_textures_id().is_empty() {
+                r.set_clear_color(self.background_color);
+            }
+        });
+    }
+    //Speeds are clamped by the controller
+    pub fn apply_to_camera_controller(&self, camera_controller: &mut CameraController) {
+        camera_controller
+            .set_speed(self.camera_speed)
+            .set_rotation_speed(self.camera_rotation_speed);
+    }
+}
+
+#[allow(dead_code)]
+fn test_camera_speed() {
+    use inox_math::VecBaseFloat;
+    use inox_messenger::MessageHub;
+    use inox_platform::{InputState, Key, KeyEvent};
+    use inox_resources::DataTypeResource;
+    use inox_scene::{CameraControllerMode, Object, ObjectId, MAX_CAMERA_SPEED};
+    use inox_uid::generate_random_resource_uid;
+    use std::sync::Arc;
+
+    let shared_data = SharedDataRc::default();
+    let message_hub = Arc::new(MessageHub::default());
+    shared_data.register_type_serializable::<Object>(&message_hub);
+
+    //Distance moved in a frame of 0.1 seconds holding W
+    let translation = |camera_speed: f32| {
+        let id: ObjectId = generate_random_resource_uid();
+        let object = shared_data.add_resource(
+            &message_hub,
+            id,
+            Object::new(id, &shared_data, &message_hub),
+        );
+        let mut controller = CameraController::new(CameraControllerMode::FreeFly);
+        controller.set_parent(&object);
+        let config = Config {
+            camera_speed,
+            ..Default::default()
+        };
+        config.apply_to_camera_controller(&mut controller);
+        controller.on_key_event(
+            &KeyEvent {
+                code: Key::W,
+                state: InputState::Pressed,
+            },
+            0.1,
+        );
+        let position = object.get().position();
+        position.length()
+    };
+    let is_near = |a: f32, b: f32| (a - b).abs() < 1e-3;
+
+    assert!(is_near(translation(10.), 1.));
+    assert!(is_near(translation(20.), 2.));
+    assert!(is_near(translation(1e9), MAX_CAMERA_SPEED * 0.1));
+
+    shared_data.unregister_type_serializable::<Object>(&message_hub);
+}
+
+#[test]
+fn test() {
+    test_camera_speed();
+}
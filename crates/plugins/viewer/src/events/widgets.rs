@@ -1,9 +1,22 @@
+use std::path::PathBuf;
+
 use inox_commands::CommandParser;
 use inox_messenger::implement_message;
-use inox_uid::Uid;
+use inox_scene::ObjectId;
+use inox_uid::resource_uid_from_string;
 
 pub enum WidgetEvent {
-    Selected(Uid),
+    Selected(ObjectId),
+    ResetLayout,
+    Undo,
+    Redo,
+    AddObject,
+    RemoveObject(ObjectId),
+    //Scene or raw file added to the scene, compiled first when it's a raw one
+    Import(PathBuf),
+    //Asks where to save the scene, then it's saved at the path
+    SaveSceneAs,
+    SaveScene(PathBuf),
 }
 
 implement_message!(
@@ -14,19 +27,49 @@ implement_message!(
 
 impl WidgetEvent {
     fn compare_and_discard(&self, other: &Self) -> bool {
-        match self {
-            Self::Selected(id) => match other {
-                Self::Selected(other_id) => id == other_id,
-            },
+        match (self, other) {
+            (Self::Selected(id), Self::Selected(other_id)) => id == other_id,
+            (Self::ResetLayout, Self::ResetLayout) => true,
+            (Self::RemoveObject(id), Self::RemoveObject(other_id)) => id == other_id,
+            (Self::Import(path), Self::Import(other_path)) => path == other_path,
+            (Self::SaveSceneAs, Self::SaveSceneAs) => true,
+            (Self::SaveScene(path), Self::SaveScene(other_path)) => path == other_path,
+            _ => false,
         }
     }
     fn message_from_command_parser(command_parser: CommandParser) -> Option<Self> {
         if command_parser.has("select_object") {
             let values = command_parser.get_values_of::<String>("select_object");
-            if let Ok(id) = Uid::parse_str(values[0].as_str()) {
+            if let Some(id) = resource_uid_from_string(values[0].as_str()) {
                 return Some(Self::Selected(id));
             }
         }
+        if command_parser.has("reset_layout") {
+            return Some(Self::ResetLayout);
+        }
+        if command_parser.has("undo") {
+            return Some(Self::Undo);
+        }
+        if command_parser.has("redo") {
+            return Some(Self::Redo);
+        }
+        if command_parser.has("add_object") {
+            return Some(Self::AddObject);
+        }
+        if command_parser.has("remove_object") {
+            let values = command_parser.get_values_of::<String>("remove_object");
+            if let Some(id) = resource_uid_from_string(values[0].as_str()) {
+                return Some(Self::RemoveObject(id));
+            }
+        }
+        if command_parser.has("import_file") {
+            let values = command_parser.get_values_of::<String>("import_file");
+            return Some(Self::Import(PathBuf::from(values[0].as_str())));
+        }
+        if command_parser.has("save_scene") {
+            let values = command_parser.get_values_of::<String>("save_scene");
+            return Some(Self::SaveScene(PathBuf::from(values[0].as_str())));
+        }
         None
     }
 }
@@ -0,0 +1,536 @@
+use inox_graphics::DrawEvent;
+use inox_math::{
+    InnerSpace, Mat4Ops, MatBase, Matrix4, NewAngle, Radians, VecBase, VecBaseFloat, Vector2,
+    Vector3, Vector4,
+};
+use inox_messenger::MessageHubRc;
+
+//Size of the gizmo as a fraction of the height of the view, whatever its distance from the camera
+const GIZMO_SCREEN_SIZE: f32 = 0.15;
+//Distance from a handle within which it's picked, as a fraction of the gizmo size
+const GIZMO_PICK_TOLERANCE: f32 = 0.08;
+//Segments of the rings drawn around each axis to rotate
+const GIZMO_RING_SEGMENTS: u32 = 32;
+//Smallest scale reached by a drag, to not collapse or mirror the object
+const GIZMO_MIN_SCALE: f32 = 0.001;
+const GIZMO_AXIS_COLORS: [[f32; 4]; 3] = [[1., 0., 0., 1.], [0., 1., 0., 1.], [0., 0., 1., 1.]];
+const GIZMO_ACTIVE_COLOR: [f32; 4] = [1., 1., 0., 1.];
+
+pub const DEFAULT_TRANSLATION_SNAP: f32 = 1.;
+pub const DEFAULT_ROTATION_SNAP: f32 = 15.;
+pub const DEFAULT_SCALE_SNAP: f32 = 0.1;
+
+#[derive(Default, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum GizmoMode {
+    #[default]
+    Translate,
+    Rotate,
+    Scale,
+}
+
+#[derive(Default, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum GizmoSpace {
+    #[default]
+    World,
+    Local,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct GizmoSettings {
+    pub mode: GizmoMode,
+    pub space: GizmoSpace,
+    pub is_snapping: bool,
+    pub translation_snap: f32,
+    pub rotation_snap: f32, //in degrees
+    pub scale_snap: f32,
+}
+
+impl Default for GizmoSettings {
+    fn default() -> Self {
+        Self {
+            mode: GizmoMode::default(),
+            space: GizmoSpace::default(),
+            is_snapping: false,
+            translation_snap: DEFAULT_TRANSLATION_SNAP,
+            rotation_snap: DEFAULT_ROTATION_SNAP,
+            scale_snap: DEFAULT_SCALE_SNAP,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Ray {
+    pub origin: Vector3,
+    pub direction: Vector3,
+}
+
+impl Ray {
+    //From the two points returned by Camera::convert_in_3d
+    pub fn from_points(start: Vector3, end: Vector3) -> Self {
+        Self {
+            origin: start,
+            direction: (end - start).normalized(),
+        }
+    }
+    pub fn at(&self, distance: f32) -> Vector3 {
+        self.origin + self.direction * distance
+    }
+}
+
+//Position normalized in the view, with y going down, in the [-1, 1] range of convert_in_3d
+pub fn view_to_ndc(normalized_x: f32, normalized_y: f32) -> Vector2 {
+    Vector2::new(normalized_x * 2. - 1., 1. - normalized_y * 2.)
+}
+
+//World size having the same size on screen at any distance from the camera
+pub fn gizmo_size(position: Vector3, camera_position: Vector3, fov: Radians) -> f32 {
+    let distance = (position - camera_position).length();
+    2. * distance * (fov.0 * 0.5).tan() * GIZMO_SCREEN_SIZE
+}
+
+//Distances along the ray and along the axis of their closest points, None when parallel.
+//Both directions have to be normalized
+pub fn closest_points_on_axis(ray: &Ray, origin: Vector3, axis: Vector3) -> Option<(f32, f32)> {
+    let offset = ray.origin - origin;
+    let b = ray.direction.dot(axis);
+    let d = ray.direction.dot(offset);
+    let e = axis.dot(offset);
+    let denominator = 1. - b * b;
+    if denominator < f32::EPSILON {
+        return None;
+    }
+    Some(((b * e - d) / denominator, (e - b * d) / denominator))
+}
+
+//Distance along the ray and point where it hits the plane, None when parallel or behind
+pub fn ray_plane_intersection(
+    ray: &Ray,
+    point: Vector3,
+    normal: Vector3,
+) -> Option<(f32, Vector3)> {
+    let denominator = ray.direction.dot(normal);
+    if denominator.abs() < f32::EPSILON {
+        return None;
+    }
+    let distance = (point - ray.origin).dot(normal) / denominator;
+    if distance < 0. {
+        return None;
+    }
+    Some((distance, ray.at(distance)))
+}
+
+//Signed angle in radians from reference to direction, counterclockwise around the axis
+pub fn angle_around_axis(reference: Vector3, direction: Vector3, axis: Vector3) -> f32 {
+    reference
+        .cross(direction)
+        .dot(axis)
+        .atan2(reference.dot(direction))
+}
+
+pub fn snap(value: f32, step: f32) -> f32 {
+    if step > 0. {
+        (value / step).round() * step
+    } else {
+        value
+    }
+}
+
+//Scale handles always follow the object, it can't be scaled along other axes
+pub fn gizmo_axes(transform: &Matrix4, mode: GizmoMode, space: GizmoSpace) -> [Vector3; 3] {
+    if space == GizmoSpace::Local || mode == GizmoMode::Scale {
+        [transform.right(), transform.up(), transform.forward()]
+    } else {
+        [Vector3::unit_x(), Vector3::unit_y(), Vector3::unit_z()]
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct GizmoDrag {
+    axis: usize,
+    //Taken when the drag started, the handle moves from there
+    transform: Matrix4,
+    direction: Vector3,
+    start_distance: f32,
+    start_direction: Vector3,
+}
+
+//Translates, rotates or scales an object dragging the handles of one of its axes
+#[derive(Default)]
+pub struct Gizmo {
+    settings: GizmoSettings,
+    hovered_axis: Option<usize>,
+    drag: Option<GizmoDrag>,
+}
+
+impl Gizmo {
+    pub fn settings(&self) -> &GizmoSettings {
+        &self.settings
+    }
+    //Handles change with mode and space, stopping the current drag
+    pub fn set_settings(&mut self, settings: GizmoSettings) -> &mut Self {
+        if settings.mode != self.settings.mode || settings.space != self.settings.space {
+            self.hovered_axis = None;
+            self.drag = None;
+        }
+        self.settings = settings;
+        self
+    }
+    pub fn is_dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+
+    //Axis of the handle under the ray, the closest to the camera when more than one is
+    pub fn pick(&self, transform: &Matrix4, size: f32, ray: &Ray) -> Option<usize> {
+        let center = transform.translation();
+        let axes = gizmo_axes(transform, self.settings.mode, self.settings.space);
+        let tolerance = size * GIZMO_PICK_TOLERANCE;
+        let mut picked: Option<(usize, f32)> = None;
+        for (i, axis) in axes.iter().enumerate() {
+            let hit = match self.settings.mode {
+                //The center is shared by all the axes and doesn't pick any of them
+                GizmoMode::Translate | GizmoMode::Scale => {
+                    closest_points_on_axis(ray, center, *axis)
+                        .filter(|(ray_distance, axis_distance)| {
+                            *ray_distance >= 0.
+                                && *axis_distance >= tolerance
+                                && *axis_distance <= size + tolerance
+                                && (ray.at(*ray_distance) - (center + *axis * *axis_distance))
+                                    .length()
+                                    <= tolerance
+                        })
+                        .map(|(ray_distance, _)| ray_distance)
+                }
+                GizmoMode::Rotate => ray_plane_intersection(ray, center, *axis)
+                    .filter(|(_, point)| ((*point - center).length() - size).abs() <= tolerance)
+                    .map(|(ray_distance, _)| ray_distance),
+            };
+            if let Some(distance) = hit {
+                if !picked.is_some_and(|(_, d)| d <= distance) {
+                    picked = Some((i, distance));
+                }
+            }
+        }
+        picked.map(|(i, _)| i)
+    }
+
+    //The handle under the ray is highlighted until a drag starts
+    pub fn hover(&mut self, transform: &Matrix4, size: f32, ray: &Ray) -> &mut Self {
+        if self.drag.is_none() {
+            self.hovered_axis = self.pick(transform, size, ray);
+        }
+        self
+    }
+
+    //Starts dragging the handle under the ray, returning false when there's none
+    pub fn begin_drag(&mut self, transform: &Matrix4, size: f32, ray: &Ray) -> bool {
+        let Some(axis) = self.pick(transform, size, ray) else {
+            return false;
+        };
+        let center = transform.translation();
+        let direction = gizmo_axes(transform, self.settings.mode, self.settings.space)[axis];
+        let mut drag = GizmoDrag {
+            axis,
+            transform: *transform,
+            direction,
+            start_distance: 0.,
+            start_direction: Vector3::default_zero(),
+        };
+        if self.settings.mode == GizmoMode::Rotate {
+            let Some((_, point)) = ray_plane_intersection(ray, center, direction) else {
+                return false;
+            };
+            drag.start_direction = point - center;
+        } else {
+            let Some((_, distance)) = closest_points_on_axis(ray, center, direction) else {
+                return false;
+            };
+            drag.start_distance = distance;
+        }
+        self.hovered_axis = Some(axis);
+        self.drag = Some(drag);
+        true
+    }
+    //Transform the object had when the ended drag started, None when not dragging
+    pub fn end_drag(&mut self) -> Option<Matrix4> {
+        self.drag.take().map(|d| d.transform)
+    }
+
+    //Transform of the dragged object following the ray, None while the ray can't move it
+    pub fn drag(&self, ray: &Ray) -> Option<Matrix4> {
+        let drag = self.drag.as_ref()?;
+        let settings = &self.settings;
+        let center = drag.transform.translation();
+        match settings.mode {
+            GizmoMode::Translate => {
+                let (_, distance) = closest_points_on_axis(ray, center, drag.direction)?;
+                let mut offset = distance - drag.start_distance;
+                if settings.is_snapping && settings.space == GizmoSpace::World {
+                    //The position along a world axis lands on the grid
+                    let coordinate = center.dot(drag.direction);
+                    offset = snap(coordinate + offset, settings.translation_snap) - coordinate;
+                } else if settings.is_snapping {
+                    offset = snap(offset, settings.translation_snap);
+                }
+                let mut transform = drag.transform;
+                transform.set_translation(center + drag.direction * offset);
+                Some(transform)
+            }
+            GizmoMode::Rotate => {
+                let (_, point) = ray_plane_intersection(ray, center, drag.direction)?;
+                let mut angle =
+                    angle_around_axis(drag.start_direction, point - center, drag.direction);
+                if settings.is_snapping {
+                    angle = snap(angle, settings.rotation_snap.to_radians());
+                }
+                let mut rotation = drag.transform;
+                rotation.set_translation(Vector3::default_zero());
+                let mut transform =
+                    Matrix4::from_axis_angle(drag.direction, Radians::new(angle)) * rotation;
+                transform.set_translation(center);
+                Some(transform)
+            }
+            GizmoMode::Scale => {
+                let (_, distance) = closest_points_on_axis(ray, center, drag.direction)?;
+                let start_scale = drag.transform.scale()[drag.axis];
+                if start_scale == 0. {
+                    return None;
+                }
+                let mut scale = start_scale * distance / drag.start_distance;
+                if settings.is_snapping {
+                    scale = snap(scale, settings.scale_snap);
+                }
+                if scale * start_scale.signum() < GIZMO_MIN_SCALE {
+                    scale = GIZMO_MIN_SCALE.copysign(start_scale);
+                }
+                let mut factor = Vector3::default_one();
+                factor[drag.axis] = scale / start_scale;
+                Some(drag.transform * Matrix4::from_nonuniform_scale(factor.x, factor.y, factor.z))
+            }
+        }
+    }
+
+    pub fn draw(&self, message_hub: &MessageHubRc, transform: &Matrix4, size: f32) {
+        let center = transform.translation();
+        let axes = gizmo_axes(transform, self.settings.mode, self.settings.space);
+        let active_axis = self.drag.map(|d| d.axis).or(self.hovered_axis);
+        let handle_size = size * GIZMO_PICK_TOLERANCE;
+        for (i, axis) in axes.iter().enumerate() {
+            let color: Vector4 = if active_axis == Some(i) {
+                GIZMO_ACTIVE_COLOR.into()
+            } else {
+                GIZMO_AXIS_COLORS[i].into()
+            };
+            let u = axes[(i + 1) % 3];
+            let v = axes[(i + 2) % 3];
+            let end = center + *axis * size;
+            match self.settings.mode {
+                //Arrows of the debug drawer have a fixed thickness, heads are drawn with lines
+                GizmoMode::Translate => {
+                    message_hub.send_event(DrawEvent::Line(center, end, color));
+                    let base = end - *axis * handle_size * 2.;
+                    for side in [u, -u, v, -v] {
+                        message_hub.send_event(DrawEvent::Line(
+                            end,
+                            base + side * handle_size,
+                            color,
+                        ));
+                    }
+                }
+                GizmoMode::Rotate => {
+                    let mut previous = center + u * size;
+                    for s in 1..=GIZMO_RING_SEGMENTS {
+                        let angle = s as f32 * std::f32::consts::TAU / GIZMO_RING_SEGMENTS as f32;
+                        let point = center + (u * angle.cos() + v * angle.sin()) * size;
+                        message_hub.send_event(DrawEvent::Line(previous, point, color));
+                        previous = point;
+                    }
+                }
+                GizmoMode::Scale => {
+                    message_hub.send_event(DrawEvent::Line(center, end, color));
+                    let half_size = Vector3::default_value(handle_size * 0.5);
+                    message_hub.send_event(DrawEvent::BoundingBox(
+                        end - half_size,
+                        end + half_size,
+                        color,
+                    ));
+                }
+            }
+        }
+    }
+}
+
+#[allow(dead_code)]
+fn assert_near(a: Vector3, b: Vector3) {
+    assert!((a - b).length() < 1e-4, "{a:?} != {b:?}");
+}
+
+#[allow(dead_code)]
+fn test_gizmo_size() {
+    let fov = Radians::new(std::f32::consts::FRAC_PI_2);
+    let camera_position = Vector3::new(0., 0., -10.);
+    let size = gizmo_size(Vector3::default_zero(), camera_position, fov);
+    assert!((size - 2. * 10. * GIZMO_SCREEN_SIZE).abs() < 1e-4);
+    //Twice as far it has to be twice as big to look the same
+    let far_size = gizmo_size(Vector3::new(0., 0., 10.), camera_position, fov);
+    assert!((far_size - size * 2.).abs() < 1e-4);
+
+    assert_eq!(view_to_ndc(0.5, 0.5), Vector2::new(0., 0.));
+    assert_eq!(view_to_ndc(0., 0.), Vector2::new(-1., 1.));
+    assert_eq!(view_to_ndc(1., 1.), Vector2::new(1., -1.));
+}
+
+#[allow(dead_code)]
+fn test_gizmo_math() {
+    let ray = Ray::from_points(Vector3::new(2., 5., 0.), Vector3::new(2., 0., 0.));
+    let (ray_distance, axis_distance) =
+        closest_points_on_axis(&ray, Vector3::default_zero(), Vector3::unit_x()).unwrap();
+    assert!((ray_distance - 5.).abs() < 1e-4);
+    assert!((axis_distance - 2.).abs() < 1e-4);
+    assert!(closest_points_on_axis(&ray, Vector3::default_zero(), Vector3::unit_y()).is_none());
+
+    let (distance, point) =
+        ray_plane_intersection(&ray, Vector3::new(0., 1., 0.), Vector3::unit_y()).unwrap();
+    assert!((distance - 4.).abs() < 1e-4);
+    assert_near(point, Vector3::new(2., 1., 0.));
+    //The plane behind the ray is never hit
+    assert!(ray_plane_intersection(&ray, Vector3::new(0., 6., 0.), Vector3::unit_y()).is_none());
+
+    let angle = angle_around_axis(Vector3::unit_x(), Vector3::unit_y(), Vector3::unit_z());
+    assert!((angle - std::f32::consts::FRAC_PI_2).abs() < 1e-4);
+    let angle = angle_around_axis(Vector3::unit_x(), -Vector3::unit_y(), Vector3::unit_z());
+    assert!((angle + std::f32::consts::FRAC_PI_2).abs() < 1e-4);
+
+    assert_eq!(snap(2.7, 1.), 3.);
+    assert_eq!(snap(-0.26, 0.5), -0.5);
+    assert_eq!(snap(2.7, 0.), 2.7);
+}
+
+#[allow(dead_code)]
+fn test_gizmo_drag() {
+    //Rays going toward positive z, as from a camera looking at the origin
+    let ray_at =
+        |x: f32, y: f32| Ray::from_points(Vector3::new(x, y, -10.), Vector3::new(x, y, 0.));
+    let identity = Matrix4::default_identity();
+    let mut gizmo = Gizmo::default();
+
+    //Nothing is picked away from the handles or on their shared center
+    assert_eq!(gizmo.pick(&identity, 1., &ray_at(0.5, 0.5)), None);
+    assert_eq!(gizmo.pick(&identity, 1., &ray_at(0., 0.)), None);
+    assert_eq!(gizmo.pick(&identity, 1., &ray_at(0., 0.7)), Some(1));
+
+    assert!(gizmo.begin_drag(&identity, 1., &ray_at(0.5, 0.)));
+    assert!(gizmo.is_dragging());
+    let transform = gizmo.drag(&ray_at(3.2, 1.)).unwrap();
+    assert_near(transform.translation(), Vector3::new(2.7, 0., 0.));
+    gizmo.set_settings(GizmoSettings {
+        is_snapping: true,
+        ..*gizmo.settings()
+    });
+    let transform = gizmo.drag(&ray_at(3.2, 1.)).unwrap();
+    assert_near(transform.translation(), Vector3::new(3., 0., 0.));
+    assert_eq!(gizmo.end_drag(), Some(identity));
+    assert!(gizmo.drag(&ray_at(3.2, 1.)).is_none());
+    assert_eq!(gizmo.end_drag(), None);
+
+    //The ring around z is the only one facing the rays
+    gizmo.set_settings(GizmoSettings {
+        mode: GizmoMode::Rotate,
+        is_snapping: false,
+        ..*gizmo.settings()
+    });
+    assert!(gizmo.begin_drag(&identity, 1., &ray_at(1., 0.)));
+    let transform = gizmo.drag(&ray_at(0., 1.)).unwrap();
+    assert_near(transform.right(), Vector3::unit_y());
+    assert_near(transform.translation(), Vector3::default_zero());
+    gizmo.set_settings(GizmoSettings {
+        is_snapping: true,
+        ..*gizmo.settings()
+    });
+    let angle = 50_f32.to_radians();
+    let transform = gizmo.drag(&ray_at(angle.cos(), angle.sin())).unwrap();
+    let snapped = 45_f32.to_radians();
+    assert_near(
+        transform.right(),
+        Vector3::new(snapped.cos(), snapped.sin(), 0.),
+    );
+    //Changing mode stops the drag
+    gizmo.set_settings(GizmoSettings {
+        mode: GizmoMode::Scale,
+        is_snapping: false,
+        ..*gizmo.settings()
+    });
+    assert!(!gizmo.is_dragging());
+
+    let scaled = Matrix4::from_nonuniform_scale(2., 1., 1.);
+    assert!(gizmo.begin_drag(&scaled, 1., &ray_at(1., 0.)));
+    let transform = gizmo.drag(&ray_at(1.5, 0.)).unwrap();
+    assert_near(transform.scale(), Vector3::new(3., 1., 1.));
+    //Dragging past the center doesn't mirror the object
+    let transform = gizmo.drag(&ray_at(-1., 0.)).unwrap();
+    assert_near(transform.scale(), Vector3::new(GIZMO_MIN_SCALE, 1., 1.));
+}
+
+#[allow(dead_code)]
+fn test_gizmo_drag_child() {
+    use inox_messenger::MessageHub;
+    use inox_resources::{DataTypeResource, SharedDataRc};
+    use inox_scene::{Object, Scene};
+    use inox_uid::generate_random_resource_uid;
+    use std::sync::Arc;
+
+    let shared_data = SharedDataRc::default();
+    let message_hub = Arc::new(MessageHub::default());
+    inox_scene::register_resource_types(&shared_data, &message_hub);
+    let scene_id = generate_random_resource_uid();
+    let scene = shared_data.add_resource(
+        &message_hub,
+        scene_id,
+        Scene::new(scene_id, &shared_data, &message_hub),
+    );
+    let add_object = || {
+        let id = generate_random_resource_uid();
+        shared_data.add_resource(
+            &message_hub,
+            id,
+            Object::new(id, &shared_data, &message_hub),
+        )
+    };
+    let parent = add_object();
+    let child = add_object();
+    scene.get_mut().add_object(parent.clone());
+    scene.get_mut().add_child_object(&parent, child.clone());
+
+    //Axes of the parent space are turned and scaled compared to the world ones
+    parent.get_mut().set_transform(
+        Matrix4::from_translation(Vector3::new(10., 0., 0.))
+            * Matrix4::from_angle_y(inox_math::Degrees::new(90.))
+            * Matrix4::from_scale(2.),
+    );
+    child.get_mut().set_position(Vector3::new(1., 0., 0.));
+    let world = child.get().world_transform();
+    assert_near(world.translation(), Vector3::new(10., 0., -2.));
+
+    let ray_at =
+        |x: f32, y: f32| Ray::from_points(Vector3::new(x, y, -10.), Vector3::new(x, y, 0.));
+    let mut gizmo = Gizmo::default();
+    assert!(gizmo.begin_drag(&world, 1., &ray_at(10.5, 0.)));
+    let transform = gizmo.drag(&ray_at(13.2, 1.)).unwrap();
+    child.get_mut().set_world_transform(transform);
+
+    //The object follows the handle along the world x axis,
+    //moving along the parent z axis by half the distance
+    assert_near(
+        child.get().world_transform().translation(),
+        Vector3::new(12.7, 0., -2.),
+    );
+    assert_near(child.get().position(), Vector3::new(1., 0., 1.35));
+    assert_eq!(gizmo.end_drag(), Some(world));
+}
+
+#[test]
+fn test() {
+    test_gizmo_size();
+    test_gizmo_math();
+    test_gizmo_drag();
+    test_gizmo_drag_child();
+}
@@ -0,0 +1,370 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, RwLock},
+};
+
+use inox_graphics::{Material, MaterialData, MaterialId};
+use inox_math::Matrix4;
+use inox_messenger::MessageHubRc;
+use inox_resources::{Handle, Resource, SharedDataRc};
+use inox_scene::{Object, ObjectId, Scene};
+use inox_uid::resource_uid_to_string;
+
+pub const DEFAULT_HISTORY_CAPACITY: usize = 100;
+
+pub type CommandsHistoryRw = Arc<RwLock<CommandsHistory>>;
+
+//Edit of the scene that can be undone and done again
+pub trait Command: Send + Sync {
+    fn name(&self) -> String;
+    fn execute(&mut self, shared_data: &SharedDataRc, message_hub: &MessageHubRc);
+    fn undo(&mut self, shared_data: &SharedDataRc, message_hub: &MessageHubRc);
+}
+
+//Commands done and undone, the oldest ones are forgotten beyond the capacity
+pub struct CommandsHistory {
+    done: VecDeque<Box<dyn Command>>,
+    undone: Vec<Box<dyn Command>>,
+    capacity: usize,
+}
+
+impl Default for CommandsHistory {
+    fn default() -> Self {
+        Self::new(DEFAULT_HISTORY_CAPACITY)
+    }
+}
+
+impl CommandsHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            done: VecDeque::new(),
+            undone: Vec::new(),
+            capacity,
+        }
+    }
+
+    pub fn execute(
+        &mut self,
+        mut command: Box<dyn Command>,
+        shared_data: &SharedDataRc,
+        message_hub: &MessageHubRc,
+    ) -> &mut Self {
+        command.execute(shared_data, message_hub);
+        self.push(command)
+    }
+    //Adds a command already applied, as an edit done while dragging
+    pub fn push(&mut self, command: Box<dyn Command>) -> &mut Self {
+        self.undone.clear();
+        if self.capacity > 0 {
+            if self.done.len() >= self.capacity {
+                self.done.pop_front();
+            }
+            self.done.push_back(command);
+        }
+        self
+    }
+    pub fn undo(&mut self, shared_data: &SharedDataRc, message_hub: &MessageHubRc) -> bool {
+        if let Some(mut command) = self.done.pop_back() {
+            command.undo(shared_data, message_hub);
+            self.undone.push(command);
+            return true;
+        }
+        false
+    }
+    pub fn redo(&mut self, shared_data: &SharedDataRc, message_hub: &MessageHubRc) -> bool {
+        if let Some(mut command) = self.undone.pop() {
+            command.execute(shared_data, message_hub);
+            self.done.push_back(command);
+            return true;
+        }
+        false
+    }
+    pub fn can_undo(&self) -> bool {
+        !self.done.is_empty()
+    }
+    pub fn can_redo(&self) -> bool {
+        !self.undone.is_empty()
+    }
+    //From the oldest command, the last one is the next to undo
+    pub fn done_names(&self) -> Vec<String> {
+        self.done.iter().map(|c| c.name()).collect()
+    }
+    //From the next command to redo
+    pub fn undone_names(&self) -> Vec<String> {
+        self.undone.iter().rev().map(|c| c.name()).collect()
+    }
+    pub fn clear(&mut self) {
+        self.done.clear();
+        self.undone.clear();
+    }
+}
+
+pub struct TransformCommand {
+    object_id: ObjectId,
+    from: Matrix4,
+    to: Matrix4,
+}
+
+impl TransformCommand {
+    pub fn new(object_id: &ObjectId, from: Matrix4, to: Matrix4) -> Self {
+        Self {
+            object_id: *object_id,
+            from,
+            to,
+        }
+    }
+    fn set_transform(&self, shared_data: &SharedDataRc, transform: Matrix4) {
+        if let Some(object) = shared_data.get_resource::<Object>(&self.object_id) {
+            object.get_mut().set_transform(transform);
+        }
+    }
+}
+
+impl Command for TransformCommand {
+    fn name(&self) -> String {
+        format!("Transform {}", resource_uid_to_string(&self.object_id))
+    }
+    fn execute(&mut self, shared_data: &SharedDataRc, _message_hub: &MessageHubRc) {
+        self.set_transform(shared_data, self.to);
+    }
+    fn undo(&mut self, shared_data: &SharedDataRc, _message_hub: &MessageHubRc) {
+        self.set_transform(shared_data, self.from);
+    }
+}
+
+pub struct MaterialCommand {
+    material_id: MaterialId,
+    from: MaterialData,
+    to: MaterialData,
+}
+
+impl MaterialCommand {
+    pub fn new(material_id: &MaterialId, from: MaterialData, to: MaterialData) -> Self {
+        Self {
+            material_id: *material_id,
+            from,
+            to,
+        }
+    }
+    fn set_data(&self, shared_data: &SharedDataRc, data: &MaterialData) {
+        if let Some(material) = shared_data.get_resource::<Material>(&self.material_id) {
+            *material.get_mut().data_mut() = data.clone();
+            material.get().mark_as_dirty();
+        }
+    }
+}
+
+impl Command for MaterialCommand {
+    fn name(&self) -> String {
+        format!("Material {}", resource_uid_to_string(&self.material_id))
+    }
+    fn execute(&mut self, shared_data: &SharedDataRc, _message_hub: &MessageHubRc) {
+        self.set_data(shared_data, &self.to);
+    }
+    fn undo(&mut self, shared_data: &SharedDataRc, _message_hub: &MessageHubRc) {
+        self.set_data(shared_data, &self.from);
+    }
+}
+
+//Object added to the scene or removed from it, with the parent it's a child of, if any.
+//Removed objects are kept alive and hidden to be added back
+pub struct SceneObjectCommand {
+    scene: Resource<Scene>,
+    object: Resource<Object>,
+    parent: Handle<Object>,
+    is_adding: bool,
+    is_visible: bool,
+}
+
+impl SceneObjectCommand {
+    pub fn add(scene: &Resource<Scene>, object: &Resource<Object>, parent: Handle<Object>) -> Self {
+        Self {
+            scene: scene.clone(),
+            object: object.clone(),
+            parent,
+            is_adding: true,
+            is_visible: true,
+        }
+    }
+    pub fn remove(scene: &Resource<Scene>, object: &Resource<Object>) -> Self {
+        let parent = scene.get().parent_of(object.id());
+        Self {
+            scene: scene.clone(),
+            object: object.clone(),
+            parent,
+            is_adding: false,
+            is_visible: true,
+        }
+    }
+    fn insert(&mut self) {
+        if let Some(parent) = &self.parent {
+            self.scene
+                .get_mut()
+                .add_child_object(parent, self.object.clone());
+        } else {
+            self.scene.get_mut().add_object(self.object.clone());
+        }
+        self.object.get_mut().set_visible(self.is_visible);
+    }
+    fn remove_from_scene(&mut self) {
+        self.scene.get_mut().remove_object(self.object.id());
+        let mut object = self.object.get_mut();
+        self.is_visible = object.is_visible();
+        object.set_visible(false);
+    }
+}
+
+impl Command for SceneObjectCommand {
+    fn name(&self) -> String {
+        let action = if self.is_adding { "Add" } else { "Remove" };
+        format!("{} {}", action, resource_uid_to_string(self.object.id()))
+    }
+    fn execute(&mut self, _shared_data: &SharedDataRc, _message_hub: &MessageHubRc) {
+        if self.is_adding {
+            self.insert();
+        } else {
+            self.remove_from_scene();
+        }
+    }
+    fn undo(&mut self, _shared_data: &SharedDataRc, _message_hub: &MessageHubRc) {
+        if self.is_adding {
+            self.remove_from_scene();
+        } else {
+            self.insert();
+        }
+    }
+}
+
+#[allow(dead_code)]
+struct PushCommand {
+    values: Arc<RwLock<Vec<u32>>>,
+    value: u32,
+}
+
+impl Command for PushCommand {
+    fn name(&self) -> String {
+        format!("Push {}", self.value)
+    }
+    fn execute(&mut self, _shared_data: &SharedDataRc, _message_hub: &MessageHubRc) {
+        self.values.write().unwrap().push(self.value);
+    }
+    fn undo(&mut self, _shared_data: &SharedDataRc, _message_hub: &MessageHubRc) {
+        self.values.write().unwrap().pop();
+    }
+}
+
+#[allow(dead_code)]
+fn test_history() {
+    use inox_messenger::MessageHub;
+
+    let shared_data = SharedDataRc::default();
+    let message_hub = Arc::new(MessageHub::default());
+    let values = Arc::new(RwLock::new(Vec::new()));
+    let push = |value: u32| {
+        Box::new(PushCommand {
+            values: values.clone(),
+            value,
+        })
+    };
+
+    let mut history = CommandsHistory::new(3);
+    assert!(!history.can_undo());
+    assert!(!history.undo(&shared_data, &message_hub));
+    for value in 1..=4 {
+        history.execute(push(value), &shared_data, &message_hub);
+    }
+    assert_eq!(*values.read().unwrap(), vec![1, 2, 3, 4]);
+    //Only the last commands within the capacity can be undone
+    assert_eq!(history.done_names(), vec!["Push 2", "Push 3", "Push 4"]);
+    assert!(history.undo(&shared_data, &message_hub));
+    assert!(history.undo(&shared_data, &message_hub));
+    assert_eq!(*values.read().unwrap(), vec![1, 2]);
+    assert_eq!(history.undone_names(), vec!["Push 3", "Push 4"]);
+
+    assert!(history.redo(&shared_data, &message_hub));
+    assert_eq!(*values.read().unwrap(), vec![1, 2, 3]);
+    assert!(history.can_redo());
+    //A new command forgets the undone ones
+    history.execute(push(5), &shared_data, &message_hub);
+    assert!(!history.can_redo());
+    assert!(!history.redo(&shared_data, &message_hub));
+    assert_eq!(history.done_names(), vec!["Push 2", "Push 3", "Push 5"]);
+
+    assert!(history.undo(&shared_data, &message_hub));
+    history.clear();
+    assert!(!history.can_undo());
+    assert!(!history.can_redo());
+}
+
+#[allow(dead_code)]
+fn test_object_commands() {
+    use inox_math::Vector3;
+    use inox_messenger::MessageHub;
+    use inox_uid::generate_random_resource_uid;
+
+    let shared_data = SharedDataRc::default();
+    let message_hub = Arc::new(MessageHub::default());
+    inox_scene::register_resource_types(&shared_data, &message_hub);
+
+    let scene_id = generate_random_resource_uid();
+    let scene = shared_data.add_resource(
+        &message_hub,
+        scene_id,
+        Scene::new(scene_id, &shared_data, &message_hub),
+    );
+    let add_object = || {
+        let id = generate_random_resource_uid();
+        shared_data.add_resource(
+            &message_hub,
+            id,
+            Object::new(id, &shared_data, &message_hub),
+        )
+    };
+    let parent = add_object();
+    let child = add_object();
+    scene.get_mut().add_object(parent.clone());
+    scene.get_mut().add_child_object(&parent, child.clone());
+    assert!(scene.get().contains_object(child.id()));
+
+    let mut history = CommandsHistory::default();
+    let from = child.get().transform();
+    child.get_mut().set_position(Vector3::new(1., 2., 3.));
+    let to = child.get().transform();
+    history.push(Box::new(TransformCommand::new(child.id(), from, to)));
+    history.undo(&shared_data, &message_hub);
+    assert_eq!(child.get().transform(), from);
+    history.redo(&shared_data, &message_hub);
+    assert_eq!(child.get().transform(), to);
+
+    //A removed child goes back to its parent, hidden while removed
+    history.execute(
+        Box::new(SceneObjectCommand::remove(&scene, &child)),
+        &shared_data,
+        &message_hub,
+    );
+    assert!(!scene.get().contains_object(child.id()));
+    assert!(!parent.get().is_child(child.id()));
+    assert!(!child.get().is_visible());
+    history.undo(&shared_data, &message_hub);
+    assert!(parent.get().is_child(child.id()));
+    assert!(child.get().is_visible());
+
+    let object = add_object();
+    history.execute(
+        Box::new(SceneObjectCommand::add(&scene, &object, None)),
+        &shared_data,
+        &message_hub,
+    );
+    assert!(scene.get().objects().iter().any(|o| o.id() == object.id()));
+    history.undo(&shared_data, &message_hub);
+    assert!(!scene.get().contains_object(object.id()));
+    history.redo(&shared_data, &message_hub);
+    assert!(scene.get().contains_object(object.id()));
+    assert!(object.get().is_visible());
+}
+
+#[test]
+fn test() {
+    test_history();
+    test_object_commands();
+}
@@ -4,6 +4,8 @@ pub use viewer::*;
 
 mod config;
 mod events;
+mod gizmo;
+mod history;
 mod systems;
 pub mod viewer;
 mod widgets;
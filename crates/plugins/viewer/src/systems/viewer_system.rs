@@ -1,36 +1,122 @@
 use inox_commands::CommandParser;
 use inox_core::{implement_unique_system_uid, ContextRc, System};
 use inox_graphics::{
-    create_quad, Light, Material, MaterialData, Mesh, MeshData, MeshFlags, RendererRw, Texture,
-    View,
+    create_quad, Light, Material, MaterialData, Mesh, MeshData, MeshFlags, RenderPass,
+    RenderTarget, RendererRw, Texture, View, BLIT_PASS_NAME,
 };
 use inox_log::debug_log;
-use inox_math::{Mat4Ops, Matrix4, VecBase, Vector2, Vector3};
+use inox_math::{Mat4Ops, Vector3};
 use inox_messenger::Listener;
-use inox_platform::{InputState, Key, KeyEvent, MouseEvent, MouseState, WindowEvent};
-use inox_resources::{DataTypeResource, Resource, SerializableResource, SerializableResourceEvent};
-use inox_scene::{Camera, Object, Scene};
+use inox_platform::{
+    GamepadEvent, InputState, Key, KeyEvent, MouseButton, MouseEvent, MouseState, TouchEvent,
+    TouchState, WindowEvent,
+};
+use inox_resources::{
+    BinarizeEvent, ConfigBase, ConfigEvent, DataTypeResource, LoadGroup, LoadGroupEvent,
+    LoadGroupId, Resource, SerializableResource, SerializableResourceEvent,
+};
+use inox_scene::{Camera, CameraController, CameraControllerMode, Object, ObjectId, Scene};
+use inox_serialize::{read_from_file, SerializeFile};
 use inox_ui::UIWidget;
-use inox_uid::generate_random_uid;
-use std::path::PathBuf;
+use inox_uid::{
+    generate_random_resource_uid, generate_resource_uid_from_string, INVALID_RESOURCE_UID,
+};
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
 
-use crate::widgets::{Info, InfoParams, View3D};
+use crate::{
+    config::Config,
+    events::WidgetEvent,
+    gizmo::{gizmo_size, view_to_ndc, Gizmo, Ray},
+    history::{CommandsHistory, CommandsHistoryRw, SceneObjectCommand, TransformCommand},
+    widgets::{
+        compiled_scene_path, ContentBrowser, DockSpace, DropTarget, ImportProgress, Info,
+        InfoParams, SaveSceneDialog, View3D,
+    },
+};
 
 pub struct ViewerSystem {
     context: ContextRc,
     listener: Listener,
     scene: Resource<Scene>,
-    last_mouse_pos: Vector2,
+    scene_load_group: Option<LoadGroup>,
+    config: Config,
+    config_path: PathBuf,
+    is_cursor_grabbed: bool,
+    camera_controller: Option<Resource<CameraController>>,
     is_on_view3d: bool,
     view_3d: Option<View3D>,
+    dock_space: DockSpace,
     info: Option<Info>,
+    drop_target: DropTarget,
+    content_browser: ContentBrowser,
+    imports: Vec<Import>,
+    save_scene_dialog: SaveSceneDialog,
     last_frame: u64,
-    camera_index: u32,
+    //None until a camera is chosen with the cycle key
+    camera_index: Option<u32>,
+    gizmo: Gizmo,
+    selected_object_id: ObjectId,
+    history: CommandsHistoryRw,
+    is_control_pressed: bool,
+    //A text field of the ui has the focus, its keys aren't viewer shortcuts
+    is_ui_using_keyboard: bool,
 }
 
 const FORCE_USE_DEFAULT_CAMERA: bool = false;
-const CAMERA_SPEED: f32 = 200.;
-const CAMERA_ROTATION_SPEED: f32 = 200.;
+const CYCLE_CAMERA_KEY: Key = Key::N;
+const GLTF_EXTENSION: &str = "gltf";
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum HistoryShortcut {
+    Undo,
+    Redo,
+}
+
+//What the keys of a frame ask the viewer to do
+#[derive(Default, Debug, PartialEq, Eq)]
+struct KeyActions {
+    is_toggling_info: bool,
+    is_framing_scene: bool,
+    is_cycling_camera: bool,
+    is_removing_selected: bool,
+    history_shortcuts: Vec<HistoryShortcut>,
+}
+
+//Scene added to the current one, loaded once the binarizer has compiled it
+struct Import {
+    name: String,
+    scene_path: PathBuf,
+    load: Option<(LoadGroup, Resource<Scene>)>,
+}
+
+impl Import {
+    //Scene sent by the binarizer once the raw file has been compiled
+    fn is_compiled_as(&self, path: &Path) -> bool {
+        self.load.is_none() && path.ends_with(self.scene_path.as_path())
+    }
+    //Load requested by the import itself, that mustn't replace the current scene
+    fn is_loading(&self, path: &Path) -> bool {
+        self.load.as_ref().is_some_and(|(_, scene)| {
+            *scene.id() == generate_resource_uid_from_string(path.to_str().unwrap_or_default())
+        })
+    }
+    fn is_in_load_group(&self, load_group_id: &LoadGroupId) -> bool {
+        self.load
+            .as_ref()
+            .is_some_and(|(load_group, _)| load_group.id() == load_group_id)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum DroppedFile {
+    Scene(String),
+    //Raw file with the folder where its buffers and textures are looked for
+    Raw(PathBuf, PathBuf),
+    Unsupported,
+}
 
 impl Drop for ViewerSystem {
     fn drop(&mut self) {
@@ -44,25 +130,62 @@ impl Drop for ViewerSystem {
 implement_unique_system_uid!(ViewerSystem);
 
 impl System for ViewerSystem {
-    fn read_config(&mut self, _plugin_name: &str) {}
+    fn read_config(&mut self, plugin_name: &str) {
+        self.listener.register::<ConfigEvent<Config>>();
+        let message_hub = self.context.message_hub().clone();
+        let filename = self.config.get_filename().to_string();
+        self.config_path = self.config.get_filepath(plugin_name);
+        read_from_file(
+            self.config_path.as_path(),
+            self.context.shared_data().serializable_registry(),
+            Box::new(move |data: Config| {
+                message_hub.send_event(ConfigEvent::Loaded(filename.clone(), data));
+            }),
+        );
+    }
     fn should_run_when_not_focused(&self) -> bool {
         false
     }
+    fn should_run_when_paused(&self) -> bool {
+        true
+    }
+    fn on_focus_changed(&mut self, is_focused: bool) {
+        if !is_focused {
+            if let Some(controller) = &self.camera_controller {
+                controller.get_mut().release_input();
+            }
+            self.is_on_view3d = false;
+            self.update_cursor_grab();
+        }
+    }
 
     fn init(&mut self) {
-        self.check_command_line_arguments();
+        if self.scene.get().objects().is_empty() {
+            self.check_command_line_arguments();
+        }
 
         self.listener
             .register::<KeyEvent>()
             .register::<MouseEvent>()
+            .register::<TouchEvent>()
+            .register::<GamepadEvent>()
             .register::<WindowEvent>()
+            .register::<LoadGroupEvent>()
+            .register::<ConfigEvent<Config>>()
+            .register::<WidgetEvent>()
             .register::<SerializableResourceEvent<Scene>>();
     }
 
     fn run(&mut self) -> bool {
         inox_profiler::scoped_profile!("viewer_system::run");
 
-        self.update_events().update_view_from_camera();
+        self.update_camera_speed()
+            .update_events()
+            .update_view_from_camera()
+            .update_dock_layout()
+            .update_view_3d_size()
+            .update_gizmo()
+            .update_imports();
 
         if let Some(info) = &mut self.info {
             info.update();
@@ -79,7 +202,12 @@ impl System for ViewerSystem {
         self.listener
             .unregister::<KeyEvent>()
             .unregister::<MouseEvent>()
+            .unregister::<TouchEvent>()
+            .unregister::<GamepadEvent>()
             .unregister::<WindowEvent>()
+            .unregister::<LoadGroupEvent>()
+            .unregister::<ConfigEvent<Config>>()
+            .unregister::<WidgetEvent>()
             .unregister::<SerializableResourceEvent<Scene>>();
     }
 }
@@ -92,251 +220,401 @@ impl ViewerSystem {
 
         inox_scene::register_resource_types(shared_data, message_hub);
 
-        let scene_id = generate_random_uid();
-        let scene = shared_data.add_resource::<Scene>(
-            message_hub,
-            scene_id,
-            Scene::new(scene_id, shared_data, message_hub),
-        );
+        //After a plugin reload the scene restored by the resources handoff is kept
+        let scene = shared_data
+            .match_resource(|_: &Scene| true)
+            .unwrap_or_else(|| {
+                let scene_id = generate_random_resource_uid();
+                shared_data.add_resource::<Scene>(
+                    message_hub,
+                    scene_id,
+                    Scene::new(scene_id, shared_data, message_hub),
+                )
+            });
 
+        //Created before the panels to place them in the layout of the same frame
+        let dock_space = DockSpace::new(shared_data, message_hub);
+        let history = Arc::new(RwLock::new(CommandsHistory::default()));
         let view_3d = if use_3dview {
-            Some(View3D::new(shared_data, message_hub))
+            Some(View3D::new(shared_data, message_hub, dock_space.dock()))
         } else {
             None
         };
+        let content_browser = ContentBrowser::new(shared_data, message_hub, dock_space.dock());
         let info = Some(Info::new(
             context,
             InfoParams {
                 is_active: true,
                 scene_id: *scene.id(),
                 renderer: renderer.clone(),
+                dock: dock_space.dock().clone(),
+                history: history.clone(),
             },
         ));
         Self {
             last_frame: u64::MAX,
             is_on_view3d: false,
             view_3d,
+            dock_space,
             info,
+            drop_target: DropTarget::new(shared_data, message_hub),
+            content_browser,
+            imports: Vec::new(),
+            save_scene_dialog: SaveSceneDialog::new(shared_data, message_hub),
             context: context.clone(),
             listener,
             scene,
-            camera_index: 0,
-            last_mouse_pos: Vector2::default_zero(),
+            scene_load_group: None,
+            config: Config::default(),
+            config_path: PathBuf::new(),
+            camera_index: None,
+            is_cursor_grabbed: false,
+            camera_controller: None,
+            gizmo: Gizmo::default(),
+            selected_object_id: INVALID_RESOURCE_UID,
+            history,
+            is_control_pressed: false,
+            is_ui_using_keyboard: false,
         }
     }
 
     fn check_command_line_arguments(&mut self) -> &mut Self {
         let command_parser = CommandParser::from_command_line();
         if command_parser.has("load_file") {
-            let values = command_parser.get_values_of::<String>("load_file");
-            self.load_scene(values[0].as_str());
-        } else {
-            self.create_default_scene();
+            match command_parser.get_value_of::<String>("load_file") {
+                Ok(filename) => {
+                    self.load_scene(filename.as_str());
+                    return self;
+                }
+                Err(e) => eprintln!("{e}"),
+            }
         }
+        self.create_default_scene();
 
         self
     }
 
     fn create_default_scene(&mut self) {
-        let default_object = {
-            let object_id = generate_random_uid();
-            let object = self.context.shared_data().add_resource(
-                self.context.message_hub(),
-                object_id,
-                Object::new(
-                    object_id,
-                    self.context.shared_data(),
-                    self.context.message_hub(),
-                ),
-            );
-            let mesh_id = generate_random_uid();
-
-            let mesh = self.context.shared_data().add_resource(
-                self.context.message_hub(),
-                mesh_id,
-                Mesh::new(
-                    mesh_id,
-                    self.context.shared_data(),
-                    self.context.message_hub(),
-                ),
-            );
-            let material = Material::new_resource(
-                self.context.shared_data(),
-                self.context.message_hub(),
-                generate_random_uid(),
-                &MaterialData::default(),
-                None,
-            );
-            let texture = Texture::request_load(
-                self.context.shared_data(),
-                self.context.message_hub(),
-                PathBuf::from("textures\\Test.png").as_path(),
-                None,
-            );
-            material
-                .get_mut()
-                .set_texture(inox_graphics::TextureType::BaseColor, &texture);
-            mesh.get_mut()
-                .set_material(material)
-                .set_flags(MeshFlags::Visible | MeshFlags::Opaque);
-
-            let mut mesh_data = MeshData::default();
-            let quad = create_quad([-10., -10., 10., 10.].into(), 0.);
-            mesh_data.append_mesh_data(quad, false);
-            mesh_data.set_vertex_color([0.0, 0.0, 1.0, 1.0].into());
-
-            //println!("Quad Mesh {:?}", mesh.id());
-
-            mesh.get_mut().set_mesh_data(mesh_data);
-            object.get_mut().add_component(mesh);
-            object.get_mut().set_position([-20., 0., 0.].into());
-            object
-        };
-        let flat_object = {
-            let object_id = generate_random_uid();
-            let object = self.context.shared_data().add_resource(
-                self.context.message_hub(),
-                object_id,
-                Object::new(
-                    object_id,
-                    self.context.shared_data(),
-                    self.context.message_hub(),
-                ),
-            );
-            let mesh_id = generate_random_uid();
-
-            let flat_mesh = self.context.shared_data().add_resource(
-                self.context.message_hub(),
-                mesh_id,
-                Mesh::new(
-                    mesh_id,
-                    self.context.shared_data(),
-                    self.context.message_hub(),
-                ),
-            );
-            let flat_material = Material::new_resource(
-                self.context.shared_data(),
-                self.context.message_hub(),
-                generate_random_uid(),
-                &MaterialData::default(),
-                None,
-            );
-            flat_mesh
-                .get_mut()
-                .set_material(flat_material)
-                .set_flags(MeshFlags::Visible | MeshFlags::Opaque);
-
-            let mut mesh_data = MeshData::default();
-            let quad = create_quad([-10., -10., 10., 10.].into(), 0.);
-            mesh_data.append_mesh_data(quad, false);
-            mesh_data.set_vertex_color([1.0, 1.0, 0.0, 1.0].into());
-
-            //println!("Flat Mesh {:?}", mesh.id());
-
-            flat_mesh.get_mut().set_mesh_data(mesh_data);
-            object.get_mut().add_component(flat_mesh);
-            object.get_mut().set_position([20., 0., 0.].into());
-            object
-        };
-        self.scene.get_mut().add_object(default_object);
-        self.scene.get_mut().add_object(flat_object);
-
-        let camera_id = generate_random_uid();
-        let camera_object = self.context.shared_data().add_resource::<Object>(
-            self.context.message_hub(),
-            camera_id,
-            Object::new(
-                camera_id,
-                self.context.shared_data(),
-                self.context.message_hub(),
-            ),
-        );
-        camera_object
-            .get_mut()
-            .set_position(Vector3::new(0.0, 0.0, -50.0));
-        camera_object.get_mut().look_at(Vector3::new(0.0, 0.0, 0.0));
-        let camera = camera_object.get_mut().add_default_component::<Camera>(
-            self.context.shared_data(),
-            self.context.message_hub(),
-        );
-        camera
-            .get_mut()
-            .set_parent(&camera_object)
-            .set_active(false);
-        self.scene.get_mut().add_object(camera_object);
-
-        let light_id = generate_random_uid();
-        let light_object = self.context.shared_data().add_resource::<Object>(
-            self.context.message_hub(),
-            light_id,
-            Object::new(
-                light_id,
-                self.context.shared_data(),
-                self.context.message_hub(),
-            ),
-        );
-        let light = light_object
-            .get_mut()
-            .add_default_component::<Light>(self.context.shared_data(), self.context.message_hub());
-        light.get_mut().set_active(true);
-        self.scene.get_mut().add_object(light_object);
+        create_default_scene(&self.context, &self.scene);
     }
 
     fn load_scene(&mut self, filename: &str) {
         if filename.ends_with(Scene::extension()) {
             self.scene.get_mut().clear();
-            self.scene = Scene::request_load(
+            if let Some(load_group) = self.scene_load_group.take() {
+                load_group.cancel(self.context.shared_data(), self.context.message_hub());
+            }
+            let load_group = self
+                .context
+                .shared_data()
+                .begin_load_group(self.context.message_hub());
+            self.scene = load_group.request_load(
                 self.context.shared_data(),
                 self.context.message_hub(),
                 PathBuf::from(filename).as_path(),
                 None,
             );
+            self.scene_load_group = Some(load_group);
+            //Commands of the previous scene can't be undone anymore
+            self.gizmo.end_drag();
+            self.history.write().unwrap().clear();
             if let Some(info) = &mut self.info {
                 info.set_scene_id(self.scene.id());
             }
         }
     }
 
+    //The scene keeps the path it's saved at, to start from it when saved again
+    fn save_scene(&mut self, path: &Path) {
+        let registry = self.context.shared_data().serializable_registry();
+        self.scene.get().save(path, registry);
+        self.scene.get_mut().set_filepath(path);
+        self.content_browser.refresh();
+        debug_log!("Scene saved as {:?}", path);
+    }
+
+    fn load_dropped_file(&mut self, path: &Path) {
+        match dropped_file(path) {
+            DroppedFile::Scene(filename) => self.load_scene(filename.as_str()),
+            DroppedFile::Raw(path, folder) => {
+                self.context
+                    .message_hub()
+                    .send_event(BinarizeEvent::Binarize(path, folder));
+            }
+            DroppedFile::Unsupported => {
+                debug_log!("Unable to load dropped file {:?}", path);
+            }
+        }
+    }
+
+    //Raw files are compiled by the binarizer only when stale, it sends their scene to load anyway
+    fn import_file(&mut self, path: &Path) {
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        match dropped_file(path) {
+            DroppedFile::Scene(filename) => {
+                let scene_path = PathBuf::from(filename);
+                self.imports.push(Import {
+                    name,
+                    scene_path: scene_path.clone(),
+                    load: None,
+                });
+                self.load_import(scene_path.as_path());
+            }
+            DroppedFile::Raw(path, folder) => {
+                let raw_folder = self.content_browser.raw_folder();
+                let Some(scene_path) = path
+                    .strip_prefix(raw_folder.as_path())
+                    .ok()
+                    .and_then(compiled_scene_path)
+                else {
+                    debug_log!("Unable to import {:?} from outside {:?}", path, raw_folder);
+                    return;
+                };
+                self.imports.push(Import {
+                    name,
+                    scene_path,
+                    load: None,
+                });
+                self.context
+                    .message_hub()
+                    .send_event(BinarizeEvent::Binarize(path, folder));
+            }
+            DroppedFile::Unsupported => {
+                debug_log!("Unable to import {:?}", path);
+            }
+        }
+    }
+
+    //The imported scene is loaded in a group of its own, not to cancel the current scene
+    fn load_import(&mut self, path: &Path) {
+        let shared_data = self.context.shared_data();
+        let message_hub = self.context.message_hub();
+        let Some(import) = self.imports.iter_mut().find(|i| i.is_compiled_as(path)) else {
+            return;
+        };
+        let load_group = shared_data.begin_load_group(message_hub);
+        let scene = load_group.request_load::<Scene>(shared_data, message_hub, path, None);
+        import.load = Some((load_group, scene));
+        //The compiled scene is shown next to its raw file
+        self.content_browser.refresh();
+    }
+
+    //Objects of the imported scene are moved in the current one, each as a step of the history
+    fn add_imported_scene(&mut self, load_group_id: &LoadGroupId) {
+        let Some(index) = self
+            .imports
+            .iter()
+            .position(|i| i.is_in_load_group(load_group_id))
+        else {
+            return;
+        };
+        let Some((_, scene)) = self.imports.remove(index).load else {
+            return;
+        };
+        if scene.id() == self.scene.id() {
+            debug_log!("Scene {:?} is already loaded", scene.get().path());
+            return;
+        }
+        let objects = scene.get().objects().clone();
+        scene.get_mut().clear();
+        let shared_data = self.context.shared_data();
+        let message_hub = self.context.message_hub();
+        let mut history = self.history.write().unwrap();
+        objects
+            .iter()
+            .filter(|o| !self.scene.get().contains_object(o.id()))
+            .for_each(|object| {
+                history.execute(
+                    Box::new(SceneObjectCommand::add(&self.scene, object, None)),
+                    shared_data,
+                    message_hub,
+                );
+            });
+    }
+
+    //The first import still in progress is shown in the content browser
+    fn update_imports(&mut self) -> &mut Self {
+        let shared_data = self.context.shared_data();
+        let progress = self.imports.first().map(|import| ImportProgress {
+            name: import.name.clone(),
+            loaded: import
+                .load
+                .as_ref()
+                .map(|(load_group, _)| load_group.progress(shared_data).unwrap_or_default()),
+        });
+        self.content_browser.set_import_progress(progress);
+        self
+    }
+
+    //Hovering is sent continuously by some platforms, only changes are shown
+    fn update_hovered_file(&mut self, hovered_file: Option<PathBuf>) {
+        if hovered_file != self.drop_target.hovered_file() {
+            let is_supported = hovered_file
+                .as_ref()
+                .map_or(false, |p| dropped_file(p) != DroppedFile::Unsupported);
+            if let Some(path) = &hovered_file {
+                debug_log!("Drop {:?} to load it", path);
+            }
+            self.drop_target
+                .set_hovered_file(hovered_file.as_deref(), is_supported);
+        }
+    }
+
     fn update_events(&mut self) -> &mut Self {
         inox_profiler::scoped_profile!("update_events");
 
         self.handle_keyboard_event();
         self.handle_mouse_event();
+        self.handle_touch_event();
+        self.handle_gamepad_event();
+        let mut scene_to_load = None;
+        let mut window_size = None;
+        let mut dropped_files = Vec::new();
+        let mut hovered_file = None;
+        let mut is_layout_reset = false;
+        let mut selected_object_id = None;
+        let mut history_shortcuts = Vec::new();
+        let mut is_adding_object = false;
+        let mut removed_objects = Vec::new();
+        let mut imported_files = Vec::new();
+        let mut scenes_to_import = Vec::new();
+        let mut completed_imports = Vec::new();
+        let mut is_saving_scene_as = false;
+        let mut scene_to_save = None;
         self.listener
-            .process_messages(|event: &WindowEvent| {
-                if let WindowEvent::SizeChanged(width, height) = event {
-                    self.context
-                        .shared_data()
-                        .for_each_resource_mut(|_, c: &mut Camera| {
-                            c.set_projection(
-                                c.fov_in_degrees(),
-                                *width as _,
-                                *height as _,
-                                c.near_plane(),
-                                c.far_plane(),
+            .process_messages(|event: &WindowEvent| match event {
+                WindowEvent::SizeChanged(width, height) => {
+                    window_size = Some((*width, *height));
+                }
+                WindowEvent::FileHovered(path) => {
+                    hovered_file = Some(Some(path.clone()));
+                }
+                WindowEvent::FileHoverCancelled => {
+                    hovered_file = Some(None);
+                }
+                WindowEvent::FileDropped(path) => {
+                    hovered_file = Some(None);
+                    dropped_files.push(path.clone());
+                }
+                //Sent by the ui when a text field gains or loses the focus
+                WindowEvent::RequestImeAllowed(is_allowed) => {
+                    self.is_ui_using_keyboard = *is_allowed;
+                }
+                _ => {}
+            })
+            .process_messages(|event: &LoadGroupEvent| match event {
+                LoadGroupEvent::Progress(id, loaded, total) => {
+                    if self.scene_load_group.map(|g| g.id() == id).unwrap_or(false) {
+                        debug_log!("Loading scene: {}/{}", loaded, total);
+                    }
+                }
+                LoadGroupEvent::Completed(id) => {
+                    if self.scene_load_group.map(|g| g.id() == id).unwrap_or(false) {
+                        debug_log!("Scene loaded");
+                        self.scene_load_group = None;
+                    }
+                    if self.imports.iter().any(|i| i.is_in_load_group(id)) {
+                        completed_imports.push(*id);
+                    }
+                }
+                LoadGroupEvent::Cancelled(id) => {
+                    self.imports.retain(|i| !i.is_in_load_group(id));
+                }
+            })
+            .process_messages(|event: &ConfigEvent<Config>| match event {
+                ConfigEvent::Loaded(filename, config) | ConfigEvent::Reloaded(filename, config) => {
+                    if filename == self.config.get_filename() {
+                        if let ConfigEvent::Reloaded(..) = event {
+                            debug_log!("Reloading {}", filename);
+                            config.apply(self.context.shared_data());
+                        }
+                        self.config = config.clone();
+                        self.dock_space.set_layout(&config.dock_layout);
+                        if let Some(info) = &self.info {
+                            info.set_camera_speed(
+                                config.camera_speed,
+                                config.camera_rotation_speed,
                             );
-                        });
+                        }
+                    }
                 }
             })
+            .process_messages(|event: &WidgetEvent| match event {
+                WidgetEvent::Selected(object_id) => selected_object_id = Some(*object_id),
+                WidgetEvent::ResetLayout => is_layout_reset = true,
+                WidgetEvent::Undo => history_shortcuts.push(HistoryShortcut::Undo),
+                WidgetEvent::Redo => history_shortcuts.push(HistoryShortcut::Redo),
+                WidgetEvent::AddObject => is_adding_object = true,
+                WidgetEvent::RemoveObject(object_id) => removed_objects.push(*object_id),
+                WidgetEvent::Import(path) => imported_files.push(path.clone()),
+                WidgetEvent::SaveSceneAs => is_saving_scene_as = true,
+                WidgetEvent::SaveScene(path) => scene_to_save = Some(path.clone()),
+            })
             .process_messages(|event: &SerializableResourceEvent<Scene>| {
                 let SerializableResourceEvent::<Scene>::Load(path, _option) = event;
+                //Imported scenes are added to the current one instead of replacing it
+                if self.imports.iter().any(|i| i.is_loading(path)) {
+                    return;
+                }
+                if self.imports.iter().any(|i| i.is_compiled_as(path)) {
+                    scenes_to_import.push(path.clone());
+                    return;
+                }
                 debug_log!("Loading scene: {:?}", path);
                 if let Some(scene_path) = path.to_str() {
-                    if scene_path.ends_with(Scene::extension()) {
-                        self.scene.get_mut().clear();
-                        self.scene = Scene::request_load(
-                            self.context.shared_data(),
-                            self.context.message_hub(),
-                            PathBuf::from(scene_path).as_path(),
-                            None,
-                        );
-
-                        if let Some(info) = &mut self.info {
-                            info.set_scene_id(self.scene.id());
-                        }
-                    }
+                    scene_to_load = Some(scene_path.to_string());
                 }
             });
+        //Only the last size of the frame is applied and a minimized window keeps the projection.
+        //The 3D view follows the size of its panel instead
+        if let Some((width, height)) = window_size.filter(|(w, h)| *w > 0 && *h > 0) {
+            if self.view_3d.is_none() {
+                self.set_cameras_size(width, height);
+            }
+        }
+        if is_layout_reset {
+            self.dock_space.reset_layout();
+        }
+        if let Some(object_id) = selected_object_id {
+            self.end_gizmo_drag();
+            self.selected_object_id = object_id;
+        }
+        history_shortcuts
+            .iter()
+            .for_each(|shortcut| self.apply_history_shortcut(*shortcut));
+        if is_adding_object {
+            self.add_object();
+        }
+        removed_objects
+            .iter()
+            .for_each(|object_id| self.remove_object(object_id));
+        if is_saving_scene_as {
+            self.save_scene_dialog.open(self.scene.get().path());
+        }
+        if let Some(path) = scene_to_save {
+            self.save_scene(path.as_path());
+        }
+        if let Some(scene_path) = scene_to_load {
+            self.load_scene(scene_path.as_str());
+        }
+        imported_files
+            .iter()
+            .for_each(|path| self.import_file(path.as_path()));
+        scenes_to_import
+            .iter()
+            .for_each(|path| self.load_import(path.as_path()));
+        completed_imports
+            .iter()
+            .for_each(|load_group_id| self.add_imported_scene(load_group_id));
+        if let Some(hovered_file) = hovered_file {
+            self.update_hovered_file(hovered_file);
+        }
+        dropped_files.iter().for_each(|path| {
+            self.load_dropped_file(path.as_path());
+        });
         self
     }
 
@@ -348,18 +626,13 @@ impl ViewerSystem {
             .shared_data()
             .match_resource(|view: &View| view.view_index() == 0)
         {
-            if FORCE_USE_DEFAULT_CAMERA || self.context.shared_data().num_resources::<Camera>() <= 1
-            {
-                self.camera_index = 0;
-            } else {
-                self.camera_index = 1;
-            }
+            let camera_index = self.active_camera_index();
             let mut index = 0;
             self.context
                 .shared_data()
                 .for_each_resource_mut(|_, c: &mut Camera| {
                     c.set_active(false);
-                    if self.camera_index == index {
+                    if camera_index == index {
                         c.set_active(true);
 
                         let view_matrix = c.view_matrix();
@@ -375,56 +648,236 @@ impl ViewerSystem {
         self
     }
 
-    fn handle_keyboard_event(&mut self) {
-        self.listener.process_messages(|event: &KeyEvent| {
-            if event.code == Key::F1 && event.state == InputState::Released {
-                if let Some(info) = &mut self.info {
-                    if info.is_active() {
-                        info.set_active(false);
-                    } else {
-                        info.set_active(true);
-                    }
-                }
+    //Layout changes made by the user are saved in the config, restored by read_config
+    fn set_cameras_size(&self, width: u32, height: u32) {
+        self.context
+            .shared_data()
+            .for_each_resource_mut(|_, c: &mut Camera| {
+                c.set_projection(
+                    c.fov_in_degrees(),
+                    width as _,
+                    height as _,
+                    c.near_plane(),
+                    c.far_plane(),
+                );
+            });
+    }
+
+    //The scene is rendered in a texture as big as the panel of the 3D view
+    fn update_view_3d_size(&mut self) -> &mut Self {
+        let Some(viewport) = self.view_3d.as_mut().and_then(|v| v.take_resized()) else {
+            return self;
+        };
+        let (width, height) = viewport.size();
+        if let Some(render_pass) = self
+            .context
+            .shared_data()
+            .match_resource(|r: &RenderPass| r.name() == BLIT_PASS_NAME)
+        {
+            let format = render_pass
+                .get()
+                .render_textures()
+                .first()
+                .map(|t| t.get().format());
+            if let Some(format) = format {
+                render_pass.get_mut().set_render_target(
+                    0,
+                    RenderTarget::Texture {
+                        width,
+                        height,
+                        format,
+                        read_back: false,
+                    },
+                );
             }
+        }
+        self.set_cameras_size(width, height);
+        self
+    }
+
+    fn update_dock_layout(&mut self) -> &mut Self {
+        if let Some(dock_layout) = self.dock_space.changed_layout() {
+            self.config.dock_layout = dock_layout;
+            self.config.save_to_file(
+                self.config_path.as_path(),
+                self.context.shared_data().serializable_registry(),
+            );
+        }
+        self
+    }
+
+    fn active_camera_index(&self) -> u32 {
+        let camera_count = self.context.shared_data().num_resources::<Camera>();
+        active_camera_index(self.camera_index, camera_count)
+    }
+
+    fn cycle_camera(&mut self) {
+        let camera_count = self.context.shared_data().num_resources::<Camera>();
+        self.camera_index = cycled_camera_index(self.camera_index, camera_count);
+    }
+
+    //Speeds edited in the debug window are applied to the controller of the active camera
+    fn update_camera_speed(&mut self) -> &mut Self {
+        if let Some((speed, rotation_speed)) = self.info.as_ref().and_then(|i| i.camera_speed()) {
+            self.config.camera_speed = speed;
+            self.config.camera_rotation_speed = rotation_speed;
+        }
+        if let Some(controller) = self.camera_controller() {
+            self.config
+                .apply_to_camera_controller(&mut controller.get_mut());
+        }
+        self
+    }
 
-            let mut movement = Vector3::default_zero();
-            if event.code == Key::W {
-                movement.z += CAMERA_SPEED;
-            } else if event.code == Key::S {
-                movement.z -= CAMERA_SPEED;
-            } else if event.code == Key::A {
-                movement.x -= CAMERA_SPEED;
-            } else if event.code == Key::D {
-                movement.x += CAMERA_SPEED;
-            } else if event.code == Key::Q {
-                movement.y += CAMERA_SPEED;
-            } else if event.code == Key::E {
-                movement.y -= CAMERA_SPEED;
+    //The active camera is moved by a controller added to its object the first time it's used
+    fn camera_controller(&mut self) -> Option<Resource<CameraController>> {
+        let shared_data = self.context.shared_data();
+        let camera = shared_data.match_resource(|c: &Camera| c.is_active())?;
+        let object = camera.get().parent().clone()?;
+        if let Some(controller) = &self.camera_controller {
+            if controller.get().parent().as_ref().map(|o| o.id()) == Some(object.id()) {
+                return Some(controller.clone());
             }
-            movement *= self.context.global_timer().dt().as_secs_f32();
-            if movement != Vector3::default_zero() {
-                self.context
-                    .shared_data()
-                    .for_each_resource_mut(|_, c: &mut Camera| {
-                        if c.is_active() {
-                            let matrix = c.transform();
-                            let translation = matrix.right() * movement.x
-                                + matrix.up() * movement.y
-                                + matrix.forward() * movement.z;
-                            c.translate(translation);
-                        }
-                    });
+        }
+        let controller = object
+            .get()
+            .components_of_type::<CameraController>()
+            .first()
+            .cloned();
+        let controller = controller.unwrap_or_else(|| {
+            let controller = shared_data.add_resource(
+                self.context.message_hub(),
+                generate_random_resource_uid(),
+                CameraController::new(CameraControllerMode::FreeFly),
+            );
+            object.get_mut().add_component(controller.clone());
+            controller
+        });
+        controller.get_mut().set_parent(&object);
+        self.camera_controller = Some(controller.clone());
+        Some(controller)
+    }
+
+    fn handle_keyboard_event(&mut self) {
+        let camera_controller = self.camera_controller();
+        let dt = self.context.global_timer().dt().as_secs_f32();
+        let mut actions = KeyActions::default();
+        let mut is_control_pressed = self.is_control_pressed;
+        let is_ui_using_keyboard = self.is_ui_using_keyboard;
+        self.listener.process_messages(|event: &KeyEvent| {
+            actions.add(event, &mut is_control_pressed, is_ui_using_keyboard);
+            if let Some(controller) = &camera_controller {
+                controller.get_mut().on_key_event(event, dt);
             }
         });
+        self.is_control_pressed = is_control_pressed;
+        if actions.is_toggling_info {
+            if let Some(info) = &mut self.info {
+                info.set_active(!info.is_active());
+            }
+        }
+        if actions.is_framing_scene {
+            self.frame_scene();
+        }
+        if actions.is_cycling_camera {
+            self.cycle_camera();
+        }
+        actions
+            .history_shortcuts
+            .iter()
+            .for_each(|shortcut| self.apply_history_shortcut(*shortcut));
+        if actions.is_removing_selected {
+            let object_id = self.selected_object_id;
+            self.remove_object(&object_id);
+        }
+    }
+
+    fn apply_history_shortcut(&mut self, shortcut: HistoryShortcut) {
+        self.end_gizmo_drag();
+        let shared_data = self.context.shared_data();
+        let message_hub = self.context.message_hub();
+        let mut history = self.history.write().unwrap();
+        match shortcut {
+            HistoryShortcut::Undo => history.undo(shared_data, message_hub),
+            HistoryShortcut::Redo => history.redo(shared_data, message_hub),
+        };
+    }
+
+    //An empty object is added at the origin, selected to be placed with the gizmo
+    fn add_object(&mut self) {
+        let shared_data = self.context.shared_data();
+        let message_hub = self.context.message_hub();
+        let object_id = generate_random_resource_uid();
+        let object = shared_data.add_resource(
+            message_hub,
+            object_id,
+            Object::new(object_id, shared_data, message_hub),
+        );
+        self.history.write().unwrap().execute(
+            Box::new(SceneObjectCommand::add(&self.scene, &object, None)),
+            shared_data,
+            message_hub,
+        );
+        message_hub.send_event(WidgetEvent::Selected(object_id));
+    }
+
+    fn remove_object(&mut self, object_id: &ObjectId) {
+        if !self.scene.get().contains_object(object_id) {
+            return;
+        }
+        if *object_id == self.selected_object_id {
+            self.end_gizmo_drag();
+            self.context
+                .message_hub()
+                .send_event(WidgetEvent::Selected(INVALID_RESOURCE_UID));
+        }
+        let shared_data = self.context.shared_data();
+        let message_hub = self.context.message_hub();
+        let Some(object) = shared_data.get_resource::<Object>(object_id) else {
+            return;
+        };
+        self.history.write().unwrap().execute(
+            Box::new(SceneObjectCommand::remove(&self.scene, &object)),
+            shared_data,
+            message_hub,
+        );
+    }
+
+    //Moves the active camera to see the whole scene
+    fn frame_scene(&mut self) {
+        let Some((min, max)) = self.scene.get().world_aabb() else {
+            return;
+        };
+        let shared_data = self.context.shared_data();
+        if let Some(camera) = shared_data.match_resource(|c: &Camera| c.is_active()) {
+            camera.get_mut().frame_aabb(min, max);
+        }
+        //The controller takes again its yaw and pitch from the framed camera
+        if let Some(controller) = self.camera_controller() {
+            let object = controller.get().parent().clone();
+            if let Some(object) = object {
+                controller.get_mut().set_parent(&object);
+            }
+        }
     }
 
     fn handle_mouse_event(&mut self) {
+        let camera_controller = self.camera_controller();
+        let dt = self.context.global_timer().dt().as_secs_f32();
+        let mut gizmo_events = Vec::new();
         self.listener.process_messages(|event: &MouseEvent| {
-            if let Some(view_3d) = &self.view_3d {
-                self.is_on_view3d = view_3d.is_interacting();
-            } else if let MouseState::Down = event.state {
+            let is_rotate_button = CameraController::is_rotate_button(event.button);
+            //In the 3D view the rotation starts only on its panel, with positions mapped in it
+            let viewport = self.view_3d.as_ref().and_then(|v| v.viewport());
+            if self.view_3d.is_some() {
+                if event.state == MouseState::Down && is_rotate_button {
+                    self.is_on_view3d = viewport.is_some_and(|v| v.contains(event.x, event.y));
+                } else if event.state == MouseState::Up && is_rotate_button {
+                    self.is_on_view3d = false;
+                }
+            } else if event.state == MouseState::Down && is_rotate_button {
                 self.is_on_view3d = true;
-            } else if let MouseState::Up = event.state {
+            } else if event.state == MouseState::Up && is_rotate_button {
                 self.is_on_view3d = false;
             } else {
                 self.context
@@ -435,28 +888,503 @@ impl ViewerSystem {
                         }
                     });
             }
-            if self.is_on_view3d {
-                let mut rotation_angle = Vector3::default_zero();
-
-                rotation_angle.x = self.last_mouse_pos.y - event.normalized_y;
-                rotation_angle.y = self.last_mouse_pos.x - event.normalized_x;
-                rotation_angle *=
-                    CAMERA_ROTATION_SPEED * self.context.global_timer().dt().as_secs_f32();
-                if rotation_angle != Vector3::default_zero() {
+            //The left button drags the gizmo, starting only on the view
+            if event.button == MouseButton::Left || event.state == MouseState::Move {
+                let is_on_view = if self.view_3d.is_some() {
+                    viewport.is_some_and(|v| v.contains(event.x, event.y))
+                } else {
+                    let mut is_on_ui = false;
                     self.context
                         .shared_data()
-                        .for_each_resource_mut(|_, c: &mut Camera| {
-                            if c.is_active() {
-                                let d = c.transform().direction();
-                                rotation_angle.x *= d.dot_product([0., 0., -1.].into()).signum();
-                                let m = Matrix4::from_euler_angles(rotation_angle);
-                                let v = m.rotate_vector(d);
-                                c.look_toward(v);
-                            }
+                        .for_each_resource(|_, w: &UIWidget| {
+                            is_on_ui |= w.is_interacting();
                         });
+                    !is_on_ui
+                };
+                let mapped_event = viewport.map_or(*event, |v| v.map_mouse_event(event));
+                gizmo_events.push((mapped_event, is_on_view));
+            }
+            let event = &viewport.map_or(*event, |v| v.map_mouse_event(event));
+            //Only movements are guarded by the ui, buttons and wheel always reach the controller
+            if self.is_on_view3d || event.state != MouseState::RelativeMove {
+                if let Some(controller) = &camera_controller {
+                    controller.get_mut().on_mouse_event(event, dt);
+                }
+            }
+        });
+        self.handle_gizmo_events(&gizmo_events);
+        self.update_cursor_grab();
+    }
+
+    //Ray from the active camera through a position normalized in the view
+    fn camera_ray(&self, event: &MouseEvent) -> Option<Ray> {
+        let shared_data = self.context.shared_data();
+        let camera = shared_data.match_resource(|c: &Camera| c.is_active())?;
+        //Mouse positions are relative to the window, the render scale doesn't move them
+        let (start, end) = camera
+            .get()
+            .convert_in_3d(view_to_ndc(event.normalized_x, event.normalized_y));
+        Some(Ray::from_points(start, end))
+    }
+
+    //Selected object with the size keeping its gizmo as big on screen at any distance
+    fn gizmo_target(&self) -> Option<(Resource<Object>, f32)> {
+        if self.selected_object_id == INVALID_RESOURCE_UID
+            || !self.scene.get().contains_object(&self.selected_object_id)
+        {
+            return None;
+        }
+        let shared_data = self.context.shared_data();
+        let object = shared_data.get_resource::<Object>(&self.selected_object_id)?;
+        let camera = shared_data.match_resource(|c: &Camera| c.is_active())?;
+        let size = gizmo_size(
+            object.get().world_transform().translation(),
+            camera.get().transform().translation(),
+            camera.get().fov_in_radians(),
+        );
+        Some((object, size))
+    }
+
+    //A handle picked with the left button moves the selected object until released.
+    //The gizmo works in world space, the dragged transform goes back in the parent space
+    fn handle_gizmo_events(&mut self, events: &[(MouseEvent, bool)]) {
+        let Some((object, size)) = self.gizmo_target() else {
+            self.gizmo.end_drag();
+            return;
+        };
+        for (event, is_on_view) in events {
+            if event.state == MouseState::Up {
+                self.end_gizmo_drag();
+                continue;
+            }
+            let Some(ray) = self.camera_ray(event) else {
+                continue;
+            };
+            let transform = object.get().world_transform();
+            match event.state {
+                MouseState::Down if *is_on_view => {
+                    self.gizmo.begin_drag(&transform, size, &ray);
+                }
+                MouseState::Move if self.gizmo.is_dragging() => {
+                    if let Some(transform) = self.gizmo.drag(&ray) {
+                        object.get_mut().set_world_transform(transform);
+                    }
+                }
+                MouseState::Move if *is_on_view => {
+                    self.gizmo.hover(&transform, size, &ray);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    //The whole drag is a single step of the history, pushed when it ends
+    fn end_gizmo_drag(&mut self) {
+        let Some(from) = self.gizmo.end_drag() else {
+            return;
+        };
+        let shared_data = self.context.shared_data();
+        if let Some(object) = shared_data.get_resource::<Object>(&self.selected_object_id) {
+            //The gizmo gives the world transform the drag started from,
+            //the history restores the one relative to the parent, unchanged by the drag
+            if from != object.get().world_transform() {
+                let from = object.get().parent_transform().inverse() * from;
+                let to = object.get().transform();
+                self.history
+                    .write()
+                    .unwrap()
+                    .push(Box::new(TransformCommand::new(object.id(), from, to)));
+            }
+        }
+    }
+
+    //Settings are edited in the debug window, the gizmo is drawn on the selected object
+    fn update_gizmo(&mut self) -> &mut Self {
+        if let Some(settings) = self.info.as_ref().and_then(|i| i.gizmo_settings()) {
+            self.gizmo.set_settings(settings);
+        }
+        if let Some((object, size)) = self.gizmo_target() {
+            let transform = object.get().world_transform();
+            self.gizmo
+                .draw(self.context.message_hub(), &transform, size);
+        }
+        self
+    }
+
+    //Touches on the ui don't move the camera, ended ones always reach it to be forgotten
+    fn handle_touch_event(&mut self) {
+        let camera_controller = self.camera_controller();
+        let dt = self.context.global_timer().dt().as_secs_f32();
+        self.listener.process_messages(|event: &TouchEvent| {
+            let is_on_view3d = if let Some(view_3d) = &self.view_3d {
+                view_3d.is_interacting()
+            } else {
+                let mut is_on_ui = false;
+                self.context
+                    .shared_data()
+                    .for_each_resource(|_, w: &UIWidget| {
+                        is_on_ui |= w.is_interacting();
+                    });
+                !is_on_ui
+            };
+            if is_on_view3d || matches!(event.state, TouchState::End | TouchState::Cancel) {
+                if let Some(controller) = &camera_controller {
+                    controller.get_mut().on_touch_event(event, dt);
                 }
             }
-            self.last_mouse_pos = Vector2::new(event.normalized_x as _, event.normalized_y as _);
         });
     }
+
+    //The cursor is grabbed while rotating the camera and it's restored where it was after
+    fn update_cursor_grab(&mut self) {
+        if self.is_on_view3d != self.is_cursor_grabbed {
+            self.is_cursor_grabbed = self.is_on_view3d;
+            let message_hub = self.context.message_hub();
+            message_hub.send_event(WindowEvent::SetCursorGrab(self.is_cursor_grabbed));
+            message_hub.send_event(WindowEvent::SetCursorVisible(!self.is_cursor_grabbed));
+        }
+    }
+
+    fn handle_gamepad_event(&mut self) {
+        let camera_controller = self.camera_controller();
+        self.listener.process_messages(|event: &GamepadEvent| {
+            if let Some(controller) = &camera_controller {
+                controller.get_mut().on_gamepad_event(event);
+            }
+        });
+        if let Some(controller) = &camera_controller {
+            let dt = self.context.global_timer().dt().as_secs_f32();
+            controller.get_mut().update(dt);
+        }
+    }
+}
+
+//Raw files are binarized first and their scene is loaded once it has been compiled
+fn dropped_file(path: &Path) -> DroppedFile {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default();
+    if extension == Scene::extension() {
+        if let Some(filename) = path.to_str() {
+            return DroppedFile::Scene(filename.to_string());
+        }
+    } else if extension == GLTF_EXTENSION {
+        let folder = path.parent().map(|p| p.to_path_buf()).unwrap_or_default();
+        return DroppedFile::Raw(path.to_path_buf(), folder);
+    }
+    DroppedFile::Unsupported
+}
+
+impl KeyActions {
+    //Control is tracked across the events, it's held down while the other keys come.
+    //While the ui edits a text the editing keys are left to it
+    fn add(&mut self, event: &KeyEvent, is_control_pressed: &mut bool, is_ui_using_keyboard: bool) {
+        if event.code == Key::Control {
+            *is_control_pressed = is_key_down(event);
+        }
+        if let Some(shortcut) = history_shortcut(event, *is_control_pressed) {
+            if !is_ui_using_keyboard {
+                self.history_shortcuts.push(shortcut);
+            }
+        }
+        //Keys pressed with Control are shortcuts of their own, like Ctrl+C to copy
+        if event.state != InputState::Released || *is_control_pressed {
+            return;
+        }
+        match event.code {
+            Key::F1 => self.is_toggling_info = true,
+            Key::F => self.is_framing_scene = true,
+            CYCLE_CAMERA_KEY => self.is_cycling_camera = true,
+            Key::Delete if !is_ui_using_keyboard => self.is_removing_selected = true,
+            _ => {}
+        }
+    }
+}
+
+fn is_key_down(event: &KeyEvent) -> bool {
+    event.state == InputState::Pressed || event.state == InputState::JustPressed
+}
+
+//Ctrl+Z undoes and Ctrl+Y redoes, repeating while the key is held
+fn history_shortcut(event: &KeyEvent, is_control_pressed: bool) -> Option<HistoryShortcut> {
+    if !is_control_pressed || !is_key_down(event) {
+        return None;
+    }
+    match event.code {
+        Key::Z => Some(HistoryShortcut::Undo),
+        Key::Y => Some(HistoryShortcut::Redo),
+        _ => None,
+    }
+}
+
+//Without a chosen camera the first imported one is used, if any
+fn active_camera_index(camera_index: Option<u32>, camera_count: usize) -> u32 {
+    match camera_index {
+        Some(camera_index) => camera_index % camera_count.max(1) as u32,
+        None if FORCE_USE_DEFAULT_CAMERA || camera_count <= 1 => 0,
+        None => 1,
+    }
+}
+
+//A single camera is never cycled
+fn cycled_camera_index(camera_index: Option<u32>, camera_count: usize) -> Option<u32> {
+    if camera_count <= 1 {
+        return camera_index;
+    }
+    let camera_index = active_camera_index(camera_index, camera_count);
+    Some((camera_index + 1) % camera_count as u32)
+}
+
+//Two quads with a camera and a light, used when no scene is given on the command line
+pub(crate) fn create_default_scene(context: &ContextRc, scene: &Resource<Scene>) {
+    let default_object = {
+        let object_id = generate_random_resource_uid();
+        let object = context.shared_data().add_resource(
+            context.message_hub(),
+            object_id,
+            Object::new(object_id, context.shared_data(), context.message_hub()),
+        );
+        let mesh_id = generate_random_resource_uid();
+
+        let mesh = context.shared_data().add_resource(
+            context.message_hub(),
+            mesh_id,
+            Mesh::new(mesh_id, context.shared_data(), context.message_hub()),
+        );
+        let material = Material::new_resource(
+            context.shared_data(),
+            context.message_hub(),
+            generate_random_resource_uid(),
+            &MaterialData::default(),
+            None,
+        );
+        let texture = Texture::request_load(
+            context.shared_data(),
+            context.message_hub(),
+            PathBuf::from("textures\\Test.png").as_path(),
+            None,
+        );
+        material
+            .get_mut()
+            .set_texture(inox_graphics::TextureType::BaseColor, &texture);
+        mesh.get_mut()
+            .set_material(material)
+            .set_flags(MeshFlags::Visible | MeshFlags::Opaque);
+
+        let mut mesh_data = MeshData::default();
+        let quad = create_quad([-10., -10., 10., 10.].into(), 0.);
+        mesh_data.append_mesh_data(quad, false);
+        mesh_data.set_vertex_color([0.0, 0.0, 1.0, 1.0].into());
+
+        //println!("Quad Mesh {:?}", mesh.id());
+
+        mesh.get_mut().set_mesh_data(mesh_data);
+        object.get_mut().add_component(mesh);
+        object.get_mut().set_position([-20., 0., 0.].into());
+        object
+    };
+    let flat_object = {
+        let object_id = generate_random_resource_uid();
+        let object = context.shared_data().add_resource(
+            context.message_hub(),
+            object_id,
+            Object::new(object_id, context.shared_data(), context.message_hub()),
+        );
+        let mesh_id = generate_random_resource_uid();
+
+        let flat_mesh = context.shared_data().add_resource(
+            context.message_hub(),
+            mesh_id,
+            Mesh::new(mesh_id, context.shared_data(), context.message_hub()),
+        );
+        let flat_material = Material::new_resource(
+            context.shared_data(),
+            context.message_hub(),
+            generate_random_resource_uid(),
+            &MaterialData::default(),
+            None,
+        );
+        flat_mesh
+            .get_mut()
+            .set_material(flat_material)
+            .set_flags(MeshFlags::Visible | MeshFlags::Opaque);
+
+        let mut mesh_data = MeshData::default();
+        let quad = create_quad([-10., -10., 10., 10.].into(), 0.);
+        mesh_data.append_mesh_data(quad, false);
+        mesh_data.set_vertex_color([1.0, 1.0, 0.0, 1.0].into());
+
+        //println!("Flat Mesh {:?}", mesh.id());
+
+        flat_mesh.get_mut().set_mesh_data(mesh_data);
+        object.get_mut().add_component(flat_mesh);
+        object.get_mut().set_position([20., 0., 0.].into());
+        object
+    };
+    scene.get_mut().add_object(default_object);
+    scene.get_mut().add_object(flat_object);
+
+    let camera_id = generate_random_resource_uid();
+    let camera_object = context.shared_data().add_resource::<Object>(
+        context.message_hub(),
+        camera_id,
+        Object::new(camera_id, context.shared_data(), context.message_hub()),
+    );
+    camera_object
+        .get_mut()
+        .set_position(Vector3::new(0.0, 0.0, -50.0));
+    camera_object.get_mut().look_at(Vector3::new(0.0, 0.0, 0.0));
+    let camera = camera_object
+        .get_mut()
+        .add_default_component::<Camera>(context.shared_data(), context.message_hub());
+    camera
+        .get_mut()
+        .set_parent(&camera_object)
+        .set_active(false);
+    scene.get_mut().add_object(camera_object);
+
+    let light_id = generate_random_resource_uid();
+    let light_object = context.shared_data().add_resource::<Object>(
+        context.message_hub(),
+        light_id,
+        Object::new(light_id, context.shared_data(), context.message_hub()),
+    );
+    let light = light_object
+        .get_mut()
+        .add_default_component::<Light>(context.shared_data(), context.message_hub());
+    light.get_mut().set_active(true);
+    scene.get_mut().add_object(light_object);
+}
+
+#[allow(dead_code)]
+fn test_cycle_camera() {
+    let key = |code: Key, state: InputState| KeyEvent { code, state };
+    //The events go through the same actions and camera indices handle_keyboard_event uses
+    let press = |camera_index: Option<u32>, camera_count: usize, events: &[KeyEvent]| {
+        let mut actions = KeyActions::default();
+        let mut is_control_pressed = false;
+        events
+            .iter()
+            .for_each(|event| actions.add(event, &mut is_control_pressed, false));
+        if actions.is_cycling_camera {
+            cycled_camera_index(camera_index, camera_count)
+        } else {
+            camera_index
+        }
+    };
+    let released = key(CYCLE_CAMERA_KEY, InputState::Released);
+    let pressed = key(CYCLE_CAMERA_KEY, InputState::Pressed);
+
+    //The first cycle moves on from the imported camera in use
+    assert_eq!(press(None, 3, &[released]), Some(2));
+    assert_eq!(press(Some(0), 3, &[released]), Some(1));
+    assert_eq!(press(Some(1), 3, &[released]), Some(2));
+    //The last camera wraps around to the first one
+    assert_eq!(press(Some(2), 3, &[released]), Some(0));
+    assert_eq!(press(Some(1), 3, &[pressed]), Some(1));
+    assert_eq!(
+        press(Some(1), 3, &[key(Key::F, InputState::Released)]),
+        Some(1)
+    );
+    //With Control held the key belongs to the other shortcuts
+    let control = key(Key::Control, InputState::Pressed);
+    assert_eq!(press(Some(1), 3, &[control, released]), Some(1));
+    let control_released = key(Key::Control, InputState::Released);
+    assert_eq!(
+        press(Some(1), 3, &[control, control_released, released]),
+        Some(2)
+    );
+    //A single camera is never cycled
+    assert_eq!(press(None, 1, &[released]), None);
+    assert_eq!(press(Some(0), 1, &[released]), Some(0));
+}
+
+#[allow(dead_code)]
+fn test_dropped_file() {
+    let folder = PathBuf::from("models").join("sponza");
+    let scene = folder.join(format!("sponza.{}", Scene::extension()));
+    let filename = scene.to_str().unwrap().to_string();
+    //The scene path is the one load_scene accepts
+    assert!(filename.ends_with(Scene::extension()));
+    assert_eq!(dropped_file(&scene), DroppedFile::Scene(filename));
+
+    let gltf = folder.join(format!("sponza.{}", GLTF_EXTENSION));
+    assert_eq!(dropped_file(&gltf), DroppedFile::Raw(gltf.clone(), folder));
+
+    assert_eq!(
+        dropped_file(Path::new("sponza.png")),
+        DroppedFile::Unsupported
+    );
+    assert_eq!(dropped_file(Path::new("sponza")), DroppedFile::Unsupported);
+    //Web browsers don't tell the name of a file until it's dropped
+    assert_eq!(dropped_file(&PathBuf::new()), DroppedFile::Unsupported);
+}
+
+#[allow(dead_code)]
+fn test_history_shortcut() {
+    let key = |code: Key, state: InputState| KeyEvent { code, state };
+
+    assert_eq!(
+        history_shortcut(&key(Key::Z, InputState::JustPressed), true),
+        Some(HistoryShortcut::Undo)
+    );
+    //Holding the keys repeats the command
+    assert_eq!(
+        history_shortcut(&key(Key::Z, InputState::Pressed), true),
+        Some(HistoryShortcut::Undo)
+    );
+    assert_eq!(
+        history_shortcut(&key(Key::Y, InputState::JustPressed), true),
+        Some(HistoryShortcut::Redo)
+    );
+    assert_eq!(
+        history_shortcut(&key(Key::Z, InputState::Released), true),
+        None
+    );
+    //Without Control the keys are left to the camera and the other shortcuts
+    assert_eq!(
+        history_shortcut(&key(Key::Z, InputState::JustPressed), false),
+        None
+    );
+    assert_eq!(
+        history_shortcut(&key(Key::C, InputState::JustPressed), true),
+        None
+    );
+}
+
+#[allow(dead_code)]
+fn test_ui_using_keyboard() {
+    let key = |code: Key, state: InputState| KeyEvent { code, state };
+    let actions = |events: &[KeyEvent], is_ui_using_keyboard: bool| {
+        let mut actions = KeyActions::default();
+        let mut is_control_pressed = false;
+        events.iter().for_each(|event| {
+            actions.add(event, &mut is_control_pressed, is_ui_using_keyboard);
+        });
+        actions
+    };
+    let delete = [key(Key::Delete, InputState::Released)];
+    let undo_redo = [
+        key(Key::Control, InputState::Pressed),
+        key(Key::Z, InputState::JustPressed),
+        key(Key::Y, InputState::JustPressed),
+    ];
+
+    assert!(actions(&delete, false).is_removing_selected);
+    assert_eq!(
+        actions(&undo_redo, false).history_shortcuts,
+        vec![HistoryShortcut::Undo, HistoryShortcut::Redo]
+    );
+    //Deleting and undoing while typing edit the text and not the scene
+    assert_eq!(actions(&delete, true), KeyActions::default());
+    assert_eq!(actions(&undo_redo, true), KeyActions::default());
+}
+
+#[test]
+fn test() {
+    test_cycle_camera();
+    test_history_shortcut();
+    test_ui_using_keyboard();
+    test_dropped_file();
 }
@@ -1,31 +1,38 @@
-use std::path::PathBuf;
+use std::{
+    path::PathBuf,
+    sync::{Arc, RwLock},
+};
 
 use inox_core::{define_plugin, ContextRc, Plugin, SystemUID, WindowSystem};
 
+use inox_filesystem::File;
 use inox_graphics::{
     platform::has_primitive_index_support, rendering_system::RenderingSystem,
-    update_system::UpdateSystem, BlitPass, ComputePbrPass, CullingPass, GBufferPass, LoadOperation,
-    OutputPass, OutputRenderPass, PBRPass, Pass, RayTracingGenerateRayPass,
-    RayTracingVisibilityPass, RenderPass, RenderTarget, Renderer, RendererRw, TextureFormat,
-    VisibilityBufferPass, WireframePass, DEFAULT_HEIGHT, DEFAULT_WIDTH, GBUFFER_PASS_NAME,
-    WIREFRAME_PASS_NAME,
+    update_system::UpdateSystem, BlitPass, BloomPass, ComputePbrPass, CullingPass, GBufferPass,
+    LoadOperation, MeshFlags, OutputPass, OutputRenderPass, PBRPass, Pass, PassGraphData,
+    PassNodeData, RayTracingGenerateRayPass, RayTracingVisibilityPass, RenderPass, RenderTarget,
+    Renderer, RendererRw, Texture, TextureFormat, TransparentPass, VisibilityBufferPass,
+    WireframePass, DEFAULT_HEIGHT, DEFAULT_WIDTH,
 };
 use inox_platform::Window;
 use inox_resources::ConfigBase;
 use inox_scene::{ObjectSystem, ScriptSystem};
 use inox_serialize::read_from_file;
-use inox_ui::{UIPass, UISystem, UI_PASS_NAME};
+use inox_ui::{UIPass, UISystem};
 
 use crate::{config::Config, systems::viewer_system::ViewerSystem};
 
 const ADD_WIREFRAME_PASS: bool = true;
 const ADD_UI_PASS: bool = true;
 const ADD_CULLING_PASS: bool = true;
+const ADD_BLOOM_PASS: bool = true;
 const USE_RAYTRACING: bool = true;
 const USE_LOW_PROFILE: bool = false;
 const USE_ALL_PASSES: bool = false;
 const USE_3DVIEW: bool = false;
 
+const PLUGIN_NAME: &str = "inox_viewer";
+
 pub struct Viewer {
     window: Option<Window>,
     renderer: RendererRw,
@@ -45,9 +52,28 @@ impl Plugin for Viewer {
                 context.message_hub(),
             )
         };
+        let pass_graph = Arc::new(RwLock::new(Self::default_pass_graph()));
+        let pass_graph_path = PassGraphData::default().get_filepath(PLUGIN_NAME);
+        if File::new(pass_graph_path.as_path()).exists() {
+            let pass_graph = pass_graph.clone();
+            read_from_file(
+                pass_graph_path.as_path(),
+                context.shared_data().serializable_registry(),
+                Box::new(move |data: PassGraphData| {
+                    *pass_graph.write().unwrap() = data;
+                }),
+            );
+        }
+
         let context_rc = context.clone();
         let renderer = Renderer::new(window.handle(), context, move |renderer| {
-            Self::create_render_passes(&context_rc, renderer, DEFAULT_WIDTH, DEFAULT_HEIGHT);
+            Self::create_render_passes(
+                &context_rc,
+                renderer,
+                &pass_graph.read().unwrap(),
+                DEFAULT_WIDTH,
+                DEFAULT_HEIGHT,
+            );
         });
 
         Viewer {
@@ -57,7 +83,7 @@ impl Plugin for Viewer {
     }
 
     fn name(&self) -> &str {
-        "inox_viewer"
+        PLUGIN_NAME
     }
 
     fn prepare(&mut self, context: &ContextRc) {
@@ -75,18 +101,14 @@ impl Plugin for Viewer {
         let script_system = ScriptSystem::new(context);
 
         context.add_system(inox_core::Phases::PlatformUpdate, window_system, None);
-        context.add_system(
-            inox_core::Phases::Render,
-            render_update_system,
-            Some(&[RenderingSystem::system_id()]),
-        );
+        context.add_system(inox_core::Phases::Render, render_update_system, None);
         context.add_system(
             inox_core::Phases::Render,
             rendering_draw_system,
             Some(&[UpdateSystem::system_id()]),
         );
 
-        context.add_system(inox_core::Phases::Update, object_system, None);
+        context.add_system(inox_core::Phases::PreUpdate, object_system, None);
         context.add_system(
             inox_core::Phases::Update,
             script_system,
@@ -100,13 +122,14 @@ impl Plugin for Viewer {
     }
 
     fn unprepare(&mut self, context: &ContextRc) {
+        context.shared_data().unwatch_config::<Config>(self.name());
         context.remove_system(inox_core::Phases::Update, &ViewerSystem::system_id());
         if ADD_UI_PASS {
             context.remove_system(inox_core::Phases::Update, &UISystem::system_id());
         }
 
         context.remove_system(inox_core::Phases::Update, &ScriptSystem::system_id());
-        context.remove_system(inox_core::Phases::Update, &ObjectSystem::system_id());
+        context.remove_system(inox_core::Phases::PreUpdate, &ObjectSystem::system_id());
 
         context.remove_system(
             inox_core::Phases::PlatformUpdate,
@@ -124,69 +147,194 @@ impl Plugin for Viewer {
             config.get_filepath(self.name()).as_path(),
             context.shared_data().serializable_registry(),
             Box::new(move |data: Config| {
-                if let Some(ui_pass) =
-                    shared_data.match_resource(|r: &RenderPass| r.name() == UI_PASS_NAME)
-                {
-                    ui_pass.get_mut().set_pipeline(&data.ui_pass_pipeline);
-                }
-                if let Some(default_pass) =
-                    shared_data.match_resource(|r: &RenderPass| r.name() == GBUFFER_PASS_NAME)
-                {
-                    default_pass
-                        .get_mut()
-                        .set_pipeline(&data.opaque_pass_pipeline);
-                }
-                if let Some(wireframe_pass) =
-                    shared_data.match_resource(|r: &RenderPass| r.name() == WIREFRAME_PASS_NAME)
-                {
-                    wireframe_pass
-                        .get_mut()
-                        .set_pipeline(&data.wireframe_pass_pipeline);
-                }
+                data.apply(&shared_data);
             }),
         );
+        context.shared_data().watch_config::<Config>(self.name());
     }
 }
 
 impl Viewer {
-    fn create_render_passes(context: &ContextRc, renderer: &mut Renderer, width: u32, height: u32) {
-        if USE_RAYTRACING {
-            let raytracing_dimension = (width / 2, height / 2);
-            if has_primitive_index_support() {
-                Self::create_culling_pass(context, renderer, ADD_CULLING_PASS);
+    //Graph used when no render.pass_graph is found in the plugin config folder
+    fn default_pass_graph() -> PassGraphData {
+        Self::pass_graph(
+            USE_RAYTRACING,
+            USE_LOW_PROFILE,
+            USE_ALL_PASSES,
+            has_primitive_index_support(),
+        )
+    }
+    fn pass_graph(
+        use_raytracing: bool,
+        use_low_profile: bool,
+        use_all_passes: bool,
+        has_primitive_index_support: bool,
+    ) -> PassGraphData {
+        let mut passes = Vec::new();
+        if use_raytracing {
+            if has_primitive_index_support {
+                passes.push(
+                    PassNodeData::new(CullingPass::static_name(), &[], &["commands"])
+                        .enabled(ADD_CULLING_PASS),
+                );
             }
-            Self::create_raytracing_pass(
-                context,
-                renderer,
-                raytracing_dimension.0,
-                raytracing_dimension.1,
-                true,
+            passes.push(PassNodeData::new(
+                RayTracingVisibilityPass::static_name(),
+                &[],
+                &["visibility"],
+            ));
+            passes.push(PassNodeData::new(
+                ComputePbrPass::static_name(),
+                &["visibility"],
+                &["radiance"],
+            ));
+            passes.push(
+                PassNodeData::new(BloomPass::static_name(), &["radiance"], &["radiance"])
+                    .enabled(ADD_BLOOM_PASS),
             );
-            //Self::create_blit_pass::<RayTracingVisibilityPass>(context, renderer, true);
-            Self::create_compute_pbr_pass::<RayTracingVisibilityPass>(
+            passes.push(PassNodeData::new(
+                BlitPass::static_name(),
+                &["radiance"],
+                &["screen"],
+            ));
+        } else {
+            if use_low_profile || use_all_passes || !has_primitive_index_support {
+                passes.push(
+                    PassNodeData::new(GBufferPass::static_name(), &[], &["gbuffer", "depth"])
+                        .with_mesh_flags(MeshFlags::Visible | MeshFlags::Opaque),
+                );
+                passes.push(PassNodeData::new(
+                    PBRPass::static_name(),
+                    &["gbuffer", "depth"],
+                    &["screen"],
+                ));
+                passes.push(PassNodeData::new(
+                    TransparentPass::static_name(),
+                    &["depth", "screen"],
+                    &["screen"],
+                ));
+            }
+            if use_all_passes || has_primitive_index_support {
+                passes.push(
+                    PassNodeData::new(CullingPass::static_name(), &[], &["commands"])
+                        .enabled(ADD_CULLING_PASS),
+                );
+                passes.push(PassNodeData::new(
+                    VisibilityBufferPass::static_name(),
+                    &[],
+                    &["visibility"],
+                ));
+                passes.push(PassNodeData::new(
+                    ComputePbrPass::static_name(),
+                    &["visibility"],
+                    &["radiance"],
+                ));
+                passes.push(
+                    PassNodeData::new(BloomPass::static_name(), &["radiance"], &["radiance"])
+                        .enabled(ADD_BLOOM_PASS),
+                );
+                passes.push(PassNodeData::new(
+                    BlitPass::static_name(),
+                    &["radiance"],
+                    &["screen"],
+                ));
+            }
+        }
+        passes.push(
+            PassNodeData::new(WireframePass::static_name(), &["screen"], &["screen"])
+                .enabled(ADD_WIREFRAME_PASS),
+        );
+        passes.push(
+            PassNodeData::new(UIPass::static_name(), &["screen"], &["screen"]).enabled(ADD_UI_PASS),
+        );
+        PassGraphData { passes }
+    }
+    fn create_render_passes(
+        context: &ContextRc,
+        renderer: &mut Renderer,
+        pass_graph: &PassGraphData,
+        width: u32,
+        height: u32,
+    ) {
+        let default_pass_graph;
+        let passes = match pass_graph.ordered_passes() {
+            Ok(passes) => passes,
+            Err(e) => {
+                eprintln!("Invalid render pass graph, using the default one - {e}");
+                default_pass_graph = Self::default_pass_graph();
+                default_pass_graph.ordered_passes().unwrap()
+            }
+        };
+        passes.into_iter().for_each(|node| {
+            Self::create_render_pass(context, renderer, node, width, height);
+        });
+        if let Err(e) = renderer.build_render_graph() {
+            eprintln!("Invalid render graph, keeping the declared passes order - {e}");
+        }
+    }
+    fn create_render_pass(
+        context: &ContextRc,
+        renderer: &mut Renderer,
+        node: &PassNodeData,
+        width: u32,
+        height: u32,
+    ) {
+        let name = node.name.as_str();
+        let is_enabled = node.is_enabled;
+        //Ray traced visibility is computed at half resolution
+        let raytracing_dimension = (width / 2, height / 2);
+        if name == CullingPass::static_name() {
+            Self::create_culling_pass(context, renderer, is_enabled);
+        } else if name == GBufferPass::static_name() {
+            Self::create_gbuffer_pass(context, renderer, width, height, is_enabled);
+        } else if name == PBRPass::static_name() {
+            Self::create_pbr_pass(context, renderer, width, height, is_enabled);
+        } else if name == TransparentPass::static_name() {
+            Self::create_transparent_pass(context, renderer, is_enabled);
+        } else if name == VisibilityBufferPass::static_name() {
+            Self::create_visibility_buffer_pass(context, renderer, width, height, is_enabled);
+        } else if name == RayTracingVisibilityPass::static_name() {
+            Self::create_raytracing_pass(
                 context,
                 renderer,
                 raytracing_dimension.0,
                 raytracing_dimension.1,
-                true,
+                is_enabled,
             );
-            Self::create_blit_pass::<ComputePbrPass>(context, renderer, true);
-        } else {
-            if USE_LOW_PROFILE || USE_ALL_PASSES || !has_primitive_index_support() {
-                Self::create_gbuffer_pass(context, renderer, width, height, true);
-                Self::create_pbr_pass(context, renderer, true);
-            }
-            if USE_ALL_PASSES || has_primitive_index_support() {
-                Self::create_culling_pass(context, renderer, ADD_CULLING_PASS);
-                Self::create_visibility_buffer_pass(context, renderer, width, height, true);
+        } else if name == ComputePbrPass::static_name() {
+            if renderer.pass::<RayTracingVisibilityPass>().is_some() {
+                Self::create_compute_pbr_pass::<RayTracingVisibilityPass>(
+                    context,
+                    renderer,
+                    raytracing_dimension.0,
+                    raytracing_dimension.1,
+                    is_enabled,
+                );
+            } else {
                 Self::create_compute_pbr_pass::<VisibilityBufferPass>(
-                    context, renderer, width, height, true,
+                    context, renderer, width, height, is_enabled,
                 );
-                Self::create_blit_pass::<ComputePbrPass>(context, renderer, true);
+            }
+        } else if name == BloomPass::static_name() {
+            Self::create_bloom_pass::<ComputePbrPass>(context, renderer, is_enabled);
+        } else if name == BlitPass::static_name() {
+            Self::create_blit_pass::<ComputePbrPass>(context, renderer, is_enabled);
+        } else if name == WireframePass::static_name() {
+            Self::create_wireframe_pass(context, renderer, is_enabled);
+        } else if name == UIPass::static_name() {
+            Self::create_ui_pass(context, renderer, width, height, is_enabled);
+        } else {
+            eprintln!("Unknown render pass {name} in render pass graph");
+            return;
+        }
+        if let Some(mesh_flags) = node.mesh_flags {
+            if let Some(render_pass) = context
+                .shared_data()
+                .match_resource(|r: &RenderPass| r.name() == name)
+            {
+                render_pass.get_mut().set_mesh_flags(mesh_flags);
             }
         }
-        Self::create_wireframe_pass(context, renderer, ADD_WIREFRAME_PASS);
-        Self::create_ui_pass(context, renderer, width, height, ADD_UI_PASS);
     }
     fn create_gbuffer_pass(
         context: &ContextRc,
@@ -248,11 +396,38 @@ impl Viewer {
                 format: TextureFormat::Depth32Float,
                 read_back: false,
             });
+        //The scene is rendered at the render scale and stretched on the surface by the pbr pass
+        {
+            let render_pass = gbuffer_pass.render_pass().get();
+            render_pass
+                .render_textures()
+                .iter()
+                .chain(render_pass.depth_texture())
+                .for_each(|texture| {
+                    renderer.add_scaled_render_target(texture);
+                });
+        }
 
         renderer.add_pass(gbuffer_pass, is_enabled);
     }
-    fn create_pbr_pass(context: &ContextRc, renderer: &mut Renderer, is_enabled: bool) {
+    fn create_pbr_pass(
+        context: &ContextRc,
+        renderer: &mut Renderer,
+        width: u32,
+        height: u32,
+        is_enabled: bool,
+    ) {
         let mut pbr_pass = PBRPass::create(context, &renderer.render_context());
+        //Depth of the gbuffer resolved at the size of the surface, for the passes drawing on it
+        pbr_pass
+            .render_pass()
+            .get_mut()
+            .add_depth_target(RenderTarget::Texture {
+                width,
+                height,
+                format: TextureFormat::Depth32Float,
+                read_back: false,
+            });
 
         if let Some(gbuffer_pass) = renderer.pass::<GBufferPass>() {
             pbr_pass.set_gbuffers_textures(
@@ -267,6 +442,19 @@ impl Viewer {
         }
         renderer.add_pass(pbr_pass, is_enabled);
     }
+    fn create_transparent_pass(context: &ContextRc, renderer: &mut Renderer, is_enabled: bool) {
+        let transparent_pass = TransparentPass::create(context, &renderer.render_context());
+
+        if let Some(pbr_pass) = renderer.pass::<PBRPass>() {
+            if let Some(depth_texture) = pbr_pass.render_pass().get().depth_texture() {
+                transparent_pass
+                    .render_pass()
+                    .get_mut()
+                    .add_depth_target_from_texture(depth_texture);
+            }
+        }
+        renderer.add_pass(transparent_pass, is_enabled);
+    }
     fn create_visibility_buffer_pass(
         context: &ContextRc,
         renderer: &mut Renderer,
@@ -290,6 +478,22 @@ impl Viewer {
                 format: TextureFormat::Depth32Float,
                 read_back: false,
             });
+        if let Some(depth_texture) = visibility_pass.render_pass().get().depth_texture() {
+            if let Some(culling_pass) = renderer.pass_mut::<CullingPass>() {
+                culling_pass.set_depth_texture(depth_texture);
+            }
+        }
+        //The scene is rendered at the render scale and stretched on the surface by the blit
+        {
+            let render_pass = visibility_pass.render_pass().get();
+            render_pass
+                .render_textures()
+                .iter()
+                .chain(render_pass.depth_texture())
+                .for_each(|texture| {
+                    renderer.add_scaled_render_target(texture);
+                });
+        }
         renderer.add_pass(visibility_pass, is_enabled);
     }
     fn create_raytracing_pass(
@@ -307,6 +511,9 @@ impl Viewer {
         compute_visibility_pass.add_render_target_with_resolution(width, height);
         compute_generate_ray_pass
             .use_render_target(compute_visibility_pass.render_target().as_ref().unwrap());
+        //Rays are allocated for the full size before the target is scaled
+        renderer
+            .add_scaled_render_target(compute_visibility_pass.render_target().as_ref().unwrap());
 
         renderer
             .add_pass(compute_generate_ray_pass, is_enabled)
@@ -321,6 +528,11 @@ impl Viewer {
     ) {
         let mut compute_pbr_pass = ComputePbrPass::create(context, &renderer.render_context());
         compute_pbr_pass.add_render_target_with_resolution(width, height);
+        compute_pbr_pass.render_targets_id().iter().for_each(|id| {
+            if let Some(texture) = context.shared_data().get_resource::<Texture>(id) {
+                renderer.add_scaled_render_target(&texture);
+            }
+        });
         if let Some(visibility_pass) = renderer.pass::<P>() {
             visibility_pass.render_targets_id().iter().for_each(|id| {
                 compute_pbr_pass.add_texture(id);
@@ -328,6 +540,22 @@ impl Viewer {
         }
         renderer.add_pass(compute_pbr_pass, is_enabled);
     }
+    fn create_bloom_pass<P: OutputPass>(
+        context: &ContextRc,
+        renderer: &mut Renderer,
+        is_enabled: bool,
+    ) {
+        let mut bloom_pass = BloomPass::create(context, &renderer.render_context());
+        if let Some(source_pass) = renderer.pass::<P>() {
+            if let Some(texture) = context
+                .shared_data()
+                .get_resource::<Texture>(source_pass.render_targets_id().first().unwrap())
+            {
+                bloom_pass.set_source(&texture);
+            }
+        }
+        renderer.add_pass(bloom_pass, is_enabled);
+    }
     fn create_blit_pass<P: OutputPass>(
         context: &ContextRc,
         renderer: &mut Renderer,
@@ -341,6 +569,16 @@ impl Viewer {
     }
     fn create_wireframe_pass(context: &ContextRc, renderer: &mut Renderer, is_enabled: bool) {
         let wireframe_pass = WireframePass::create(context, &renderer.render_context());
+
+        //Sharing the scene depth lets the shaded geometry occlude the hidden edges
+        if let Some(pbr_pass) = renderer.pass::<PBRPass>() {
+            if let Some(depth_texture) = pbr_pass.render_pass().get().depth_texture() {
+                wireframe_pass
+                    .render_pass()
+                    .get_mut()
+                    .add_depth_target_from_texture(depth_texture);
+            }
+        }
         renderer.add_pass(wireframe_pass, is_enabled);
     }
     fn create_ui_pass(
@@ -374,3 +612,160 @@ impl Viewer {
         renderer.add_pass(culling_pass, is_enabled);
     }
 }
+
+#[allow(dead_code)]
+fn test_default_pass_graph() {
+    assert!(Viewer::default_pass_graph().ordered_passes().is_ok());
+    (0..16).for_each(|flags| {
+        let graph = Viewer::pass_graph(
+            flags & 1 != 0,
+            flags & 2 != 0,
+            flags & 4 != 0,
+            flags & 8 != 0,
+        );
+        let passes = graph.ordered_passes().unwrap();
+        assert_eq!(passes.len(), graph.passes.len());
+        //Passes drawing over the screen keep the order they are declared with
+        assert_eq!(passes[passes.len() - 2].name, WireframePass::static_name());
+        assert_eq!(passes[passes.len() - 1].name, UIPass::static_name());
+    });
+}
+
+#[allow(dead_code)]
+fn test_capture_headless_frame() {
+    use inox_core::{Context, JobHandlerTrait, System};
+    use inox_graphics::{FrameCaptureData, BLIT_PASS_NAME, COMPUTE_PBR_PASS_NAME};
+    use inox_resources::DataTypeResource;
+    use inox_scene::Scene;
+    use inox_serialize::deserialize;
+    use inox_uid::generate_random_resource_uid;
+
+    let context = ContextRc::new(Context::default());
+    let context_rc = context.clone();
+    let Some(renderer) = Renderer::new_headless(&context, move |renderer| {
+        Viewer::create_render_passes(
+            &context_rc,
+            renderer,
+            &Viewer::default_pass_graph(),
+            DEFAULT_WIDTH,
+            DEFAULT_HEIGHT,
+        );
+    }) else {
+        //Nothing to capture without an adapter able to render
+        return;
+    };
+    inox_scene::register_resource_types(context.shared_data(), context.message_hub());
+    let scene_id = generate_random_resource_uid();
+    let scene = context.shared_data().add_resource(
+        context.message_hub(),
+        scene_id,
+        Scene::new(scene_id, context.shared_data(), context.message_hub()),
+    );
+    crate::systems::viewer_system::create_default_scene(&context, &scene);
+
+    let mut object_system = ObjectSystem::new(&context);
+    let mut update_system = UpdateSystem::new(renderer.clone(), &context);
+    let mut rendering_system = RenderingSystem::new(renderer.clone(), &context);
+    object_system.init();
+    update_system.init();
+    rendering_system.init();
+    let mut frame = || {
+        context.message_hub().flush();
+        object_system.run();
+        update_system.run();
+        rendering_system.run();
+        context.job_handler().execute_all_jobs();
+        context.shared_data().flush_resources(context.message_hub());
+    };
+
+    let path = std::env::temp_dir()
+        .join("inox_frame_capture")
+        .join("default_scene.json");
+    std::fs::remove_file(path.as_path()).ok();
+    //First frames load pipelines and upload meshes, the capture is of a frame drawing them
+    (0..10).for_each(|_| frame());
+    renderer.write().unwrap().capture_next_frame(path.as_path());
+    (0..2).for_each(|_| frame());
+
+    let json = std::fs::read_to_string(path.as_path()).unwrap();
+    let data: FrameCaptureData =
+        deserialize(&json, context.shared_data().serializable_registry()).unwrap();
+    let names = data
+        .passes
+        .iter()
+        .map(|p| p.name.as_str())
+        .collect::<Vec<_>>();
+    let pbr = names.iter().position(|n| *n == COMPUTE_PBR_PASS_NAME);
+    let blit = names.iter().position(|n| *n == BLIT_PASS_NAME);
+    assert!(pbr.is_some() && blit.is_some() && pbr < blit, "{names:?}");
+    assert!(data.passes.iter().map(|p| p.draw_count).sum::<u32>() > 0);
+    assert!(data.passes.iter().all(|p| !p.pipelines.is_empty()));
+
+    drop(scene);
+    rendering_system.uninit();
+    update_system.uninit();
+    object_system.uninit();
+    inox_scene::unregister_resource_types(context.shared_data(), context.message_hub());
+    std::fs::remove_file(path.as_path()).ok();
+}
+
+#[allow(dead_code)]
+fn test_gbuffer_render_scale() {
+    use inox_core::Context;
+    use inox_graphics::RenderScale;
+
+    let context = ContextRc::new(Context::default());
+    let context_rc = context.clone();
+    let Some(renderer) = Renderer::new_headless(&context, move |renderer| {
+        Viewer::create_render_passes(
+            &context_rc,
+            renderer,
+            &Viewer::pass_graph(false, true, false, false),
+            DEFAULT_WIDTH,
+            DEFAULT_HEIGHT,
+        );
+    }) else {
+        return;
+    };
+    let mut renderer = renderer.write().unwrap();
+    assert!(renderer.has_scaled_render_targets());
+    renderer.set_render_scale(0.5);
+    assert!(renderer.apply_render_scale());
+
+    let mut render_scale = RenderScale::default();
+    render_scale.set_scale(0.5);
+    let scaled_size = render_scale.scaled_size(DEFAULT_WIDTH, DEFAULT_HEIGHT);
+    let depth_id = |render_pass: &RenderPass| *render_pass.depth_texture().as_ref().unwrap().id();
+    {
+        let gbuffer_pass = renderer.pass::<GBufferPass>().unwrap().render_pass().get();
+        gbuffer_pass
+            .render_textures()
+            .iter()
+            .chain(gbuffer_pass.depth_texture())
+            .for_each(|texture| assert_eq!(texture.get().dimensions(), scaled_size));
+    }
+    //The passes drawing on the surface test against the depth resolved at its size
+    let pbr_pass = renderer.pass::<PBRPass>().unwrap().render_pass().get();
+    let pbr_depth = pbr_pass.depth_texture().as_ref().unwrap();
+    assert_eq!(
+        pbr_depth.get().dimensions(),
+        (DEFAULT_WIDTH, DEFAULT_HEIGHT)
+    );
+    let transparent_pass = renderer.pass::<TransparentPass>().unwrap();
+    assert_eq!(
+        depth_id(&transparent_pass.render_pass().get()),
+        *pbr_depth.id()
+    );
+    let wireframe_pass = renderer.pass::<WireframePass>().unwrap();
+    assert_eq!(
+        depth_id(&wireframe_pass.render_pass().get()),
+        *pbr_depth.id()
+    );
+}
+
+#[test]
+fn test() {
+    test_default_pass_graph();
+    test_capture_headless_frame();
+    test_gbuffer_render_scale();
+}
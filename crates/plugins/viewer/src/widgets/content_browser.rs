@@ -0,0 +1,503 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use inox_graphics::Texture;
+use inox_messenger::MessageHubRc;
+use inox_resources::{
+    Data, Resource, SerializableResource, SerializableResourceEvent, SharedDataRc,
+};
+use inox_scene::Scene;
+use inox_ui::{
+    implement_widget_data, Align2, Area, Button, Color32, Context, CursorIcon, DockRw, FontId,
+    Frame, Id, Order, Pos2, ProgressBar, Rect, ScrollArea, Sense, TextureId as eguiTextureId,
+    UIWidget, Ui, Vec2,
+};
+
+use crate::{
+    events::WidgetEvent,
+    widgets::{CONTENT_PANEL, VIEW_PANEL},
+};
+
+const TILE_WIDTH: f32 = 80.;
+const TILE_HEIGHT: f32 = 96.;
+const THUMBNAIL_SIZE: f32 = 64.;
+const MAX_NAME_LENGTH: usize = 12;
+const GLTF_EXTENSION: &str = "gltf";
+const IMAGE_EXTENSIONS: [&str; 4] = ["png", "jpg", "jpeg", "ktx2"];
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum AssetKind {
+    Folder,
+    Gltf,
+    Scene,
+    Image,
+}
+
+impl AssetKind {
+    //Files the browser knows, by their extension
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        if extension == GLTF_EXTENSION {
+            Some(Self::Gltf)
+        } else if extension == Scene::extension() {
+            Some(Self::Scene)
+        } else if IMAGE_EXTENSIONS.contains(&extension.as_str()) {
+            Some(Self::Image)
+        } else {
+            None
+        }
+    }
+    //Assets that can be dragged in the 3D view to be added to the scene
+    pub fn is_importable(&self) -> bool {
+        matches!(self, Self::Gltf | Self::Scene)
+    }
+    fn icon(&self) -> &'static str {
+        match self {
+            Self::Folder => "📁",
+            Self::Gltf => "📦",
+            Self::Scene => "🎬",
+            Self::Image => "🖼",
+        }
+    }
+}
+
+//Path relative to the data raw folder, or to the data one for compiled scenes
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Asset {
+    pub kind: AssetKind,
+    pub name: String,
+    pub path: PathBuf,
+}
+
+//Asset being imported, with the resources loaded out of the total once it has been compiled
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ImportProgress {
+    pub name: String,
+    pub loaded: Option<(usize, usize)>,
+}
+
+//Content of a folder of the data raw one, with the scenes compiled in the same folder of the data.
+//Folders come first, then the files sorted by name
+pub fn list_assets(raw_folder: &Path, data_folder: &Path, folder: &Path) -> Vec<Asset> {
+    let mut assets = Vec::new();
+    [(raw_folder, false), (data_folder, true)]
+        .iter()
+        .for_each(|(root, is_compiled)| {
+            let Ok(dir) = fs::read_dir(root.join(folder)) else {
+                return;
+            };
+            dir.filter_map(Result::ok).for_each(|entry| {
+                let path = entry.path();
+                let kind = if path.is_dir() {
+                    Some(AssetKind::Folder)
+                } else {
+                    AssetKind::from_path(path.as_path())
+                };
+                let Some(kind) = kind.filter(|k| (*k == AssetKind::Scene) == *is_compiled) else {
+                    return;
+                };
+                let name = entry.file_name().to_string_lossy().to_string();
+                assets.push(Asset {
+                    kind,
+                    path: folder.join(name.as_str()),
+                    name,
+                });
+            });
+        });
+    assets.sort_by_key(|a| (a.kind != AssetKind::Folder, a.name.to_lowercase()));
+    assets
+}
+
+//Scene compiled from a gltf, named after the folder of the gltf as the binarizer does
+pub fn compiled_scene_path(gltf_path: &Path) -> Option<PathBuf> {
+    let folder = gltf_path.parent()?;
+    let scene_name = folder.file_name()?.to_str()?;
+    Some(folder.join(format!("{}.{}", scene_name, Scene::extension())))
+}
+
+fn short_name(name: &str) -> String {
+    if name.chars().count() <= MAX_NAME_LENGTH {
+        name.to_string()
+    } else {
+        let mut short_name = name.chars().take(MAX_NAME_LENGTH - 1).collect::<String>();
+        short_name.push('…');
+        short_name
+    }
+}
+
+#[derive(Clone)]
+struct ContentBrowserData {
+    shared_data: SharedDataRc,
+    message_hub: MessageHubRc,
+    dock: DockRw,
+    raw_folder: PathBuf,
+    data_folder: PathBuf,
+    folder: PathBuf,
+    assets: Vec<Asset>,
+    is_changed: bool,
+    //None for images not compiled yet
+    thumbnails: HashMap<PathBuf, Option<Resource<Texture>>>,
+    dragged_asset: Option<Asset>,
+    import_progress: Option<ImportProgress>,
+}
+implement_widget_data!(ContentBrowserData);
+
+//Browses the data raw folder: gltf and scenes are dragged in the 3D view to add them to the scene
+pub struct ContentBrowser {
+    ui_page: Resource<UIWidget>,
+}
+
+unsafe impl Send for ContentBrowser {}
+unsafe impl Sync for ContentBrowser {}
+
+impl ContentBrowser {
+    pub fn new(shared_data: &SharedDataRc, message_hub: &MessageHubRc, dock: &DockRw) -> Self {
+        let data = ContentBrowserData {
+            shared_data: shared_data.clone(),
+            message_hub: message_hub.clone(),
+            dock: dock.clone(),
+            raw_folder: Data::data_raw_folder(),
+            data_folder: Data::platform_data_folder(),
+            folder: PathBuf::new(),
+            assets: Vec::new(),
+            is_changed: true,
+            thumbnails: HashMap::new(),
+            dragged_asset: None,
+            import_progress: None,
+        };
+        let ui_page = Self::create(shared_data, message_hub, data);
+        Self { ui_page }
+    }
+
+    pub fn raw_folder(&self) -> PathBuf {
+        self.ui_page
+            .get()
+            .data::<ContentBrowserData>()
+            .map(|data| data.raw_folder.clone())
+            .unwrap_or_default()
+    }
+    //Lists again the folder, as after an import that compiled new scenes
+    pub fn refresh(&self) {
+        if let Some(data) = self.ui_page.get_mut().data_mut::<ContentBrowserData>() {
+            data.is_changed = true;
+        }
+    }
+    pub fn set_import_progress(&self, import_progress: Option<ImportProgress>) {
+        let is_changed = self
+            .ui_page
+            .get()
+            .data::<ContentBrowserData>()
+            .is_some_and(|data| data.import_progress != import_progress);
+        if is_changed {
+            if let Some(data) = self.ui_page.get_mut().data_mut::<ContentBrowserData>() {
+                data.import_progress = import_progress;
+            }
+        }
+    }
+
+    fn create(
+        shared_data: &SharedDataRc,
+        message_hub: &MessageHubRc,
+        data: ContentBrowserData,
+    ) -> Resource<UIWidget> {
+        UIWidget::register(shared_data, message_hub, data, |ui_data, ui_context| {
+            if let Some(data) = ui_data.as_any_mut().downcast_mut::<ContentBrowserData>() {
+                if data.is_changed {
+                    data.is_changed = false;
+                    data.assets = list_assets(
+                        data.raw_folder.as_path(),
+                        data.data_folder.as_path(),
+                        data.folder.as_path(),
+                    );
+                    data.thumbnails.clear();
+                }
+                let mut is_interacting = Self::update_drag(ui_context, data);
+                let Some(window) = data.dock.read().unwrap().window(CONTENT_PANEL) else {
+                    return is_interacting;
+                };
+                if let Some(response) = window.show(ui_context, |ui| {
+                    Self::show_toolbar(ui, data);
+                    Self::show_import_progress(ui, data);
+                    ui.separator();
+                    ScrollArea::vertical().show(ui, |ui| {
+                        ui.horizontal_wrapped(|ui| {
+                            let assets = data.assets.clone();
+                            assets.iter().for_each(|asset| {
+                                Self::show_asset(ui, asset, data);
+                            });
+                        });
+                    });
+                }) {
+                    is_interacting |= response.response.is_pointer_button_down_on();
+                }
+                return is_interacting;
+            }
+            false
+        })
+    }
+
+    fn show_toolbar(ui: &mut Ui, data: &mut ContentBrowserData) {
+        ui.horizontal(|ui| {
+            let is_root = data.folder.as_os_str().is_empty();
+            if ui
+                .add_enabled(!is_root, Button::new("⬆"))
+                .on_hover_text("Parent folder")
+                .clicked()
+            {
+                data.folder.pop();
+                data.is_changed = true;
+            }
+            if ui.button("⟳").on_hover_text("Refresh").clicked() {
+                data.is_changed = true;
+            }
+            let root = data.raw_folder.file_name().unwrap_or_default();
+            ui.label(
+                Path::new(root)
+                    .join(data.folder.as_path())
+                    .to_string_lossy(),
+            );
+        });
+    }
+
+    fn show_import_progress(ui: &mut Ui, data: &ContentBrowserData) {
+        let Some(progress) = &data.import_progress else {
+            return;
+        };
+        ui.horizontal(|ui| match progress.loaded {
+            Some((loaded, total)) => {
+                ui.add(
+                    ProgressBar::new(loaded as f32 / total.max(1) as f32)
+                        .text(format!("Loading {} {}/{}", progress.name, loaded, total)),
+                );
+            }
+            None => {
+                ui.spinner();
+                ui.label(format!("Importing {}", progress.name));
+            }
+        });
+    }
+
+    fn show_asset(ui: &mut Ui, asset: &Asset, data: &mut ContentBrowserData) {
+        let (rect, response) =
+            ui.allocate_exact_size(Vec2::new(TILE_WIDTH, TILE_HEIGHT), Sense::click_and_drag());
+        let text_color = ui.visuals().text_color();
+        let hovered_color = ui.visuals().widgets.hovered.bg_fill;
+        let painter = ui.painter();
+        if response.hovered() || data.dragged_asset.as_ref() == Some(asset) {
+            painter.rect_filled(rect, 4., hovered_color);
+        }
+        let thumbnail_rect = Rect::from_center_size(
+            Pos2::new(rect.center().x, rect.min.y + 4. + THUMBNAIL_SIZE * 0.5),
+            Vec2::splat(THUMBNAIL_SIZE),
+        );
+        if let Some(texture_index) = Self::thumbnail(asset, data) {
+            painter.image(
+                eguiTextureId::User(texture_index as _),
+                thumbnail_rect,
+                Rect::from_min_max(Pos2::ZERO, Pos2::new(1., 1.)),
+                Color32::WHITE,
+            );
+        } else {
+            painter.text(
+                thumbnail_rect.center(),
+                Align2::CENTER_CENTER,
+                asset.kind.icon(),
+                FontId::proportional(THUMBNAIL_SIZE * 0.6),
+                text_color,
+            );
+        }
+        painter.text(
+            Pos2::new(rect.center().x, rect.max.y - 4.),
+            Align2::CENTER_BOTTOM,
+            short_name(asset.name.as_str()),
+            FontId::proportional(12.),
+            text_color,
+        );
+
+        let response = response.on_hover_text(asset.name.as_str());
+        if response.double_clicked() {
+            Self::open(asset, data);
+        }
+        if response.drag_started() && asset.kind.is_importable() {
+            data.dragged_asset = Some(asset.clone());
+        }
+    }
+
+    //Images are shown once their compiled texture is loaded
+    fn thumbnail(asset: &Asset, data: &mut ContentBrowserData) -> Option<i32> {
+        if asset.kind != AssetKind::Image {
+            return None;
+        }
+        let texture = data
+            .thumbnails
+            .entry(asset.path.clone())
+            .or_insert_with(|| {
+                if !data.data_folder.join(asset.path.as_path()).exists() {
+                    return None;
+                }
+                Some(Texture::request_load(
+                    &data.shared_data,
+                    &data.message_hub,
+                    asset.path.as_path(),
+                    None,
+                ))
+            })
+            .as_ref()?;
+        let texture_index = texture.get().texture_index();
+        (texture_index >= 0).then_some(texture_index)
+    }
+
+    //Folders are entered and scenes replace the current one
+    fn open(asset: &Asset, data: &mut ContentBrowserData) {
+        match asset.kind {
+            AssetKind::Folder => {
+                data.folder = asset.path.clone();
+                data.is_changed = true;
+            }
+            AssetKind::Scene => {
+                data.message_hub
+                    .send_event(SerializableResourceEvent::<Scene>::Load(
+                        asset.path.clone(),
+                        None,
+                    ));
+            }
+            AssetKind::Gltf | AssetKind::Image => {}
+        }
+    }
+
+    //The dragged asset follows the pointer and it's imported when released on the 3D view
+    fn update_drag(ui_context: &Context, data: &mut ContentBrowserData) -> bool {
+        let Some(asset) = data.dragged_asset.clone() else {
+            return false;
+        };
+        let (pointer, is_released) =
+            ui_context.input(|i| (i.pointer.interact_pos(), !i.pointer.primary_down()));
+        let view_rect = data.dock.read().unwrap().panel_rect(VIEW_PANEL);
+        let is_on_view = pointer.is_some_and(|pos| view_rect.is_some_and(|r| r.contains(pos)));
+        if is_released {
+            data.dragged_asset = None;
+            if is_on_view {
+                //Raw files are compiled by the binarizer, that looks for them from the data raw
+                let path = match asset.kind {
+                    AssetKind::Gltf => data.raw_folder.join(asset.path.as_path()),
+                    _ => asset.path.clone(),
+                };
+                data.message_hub.send_event(WidgetEvent::Import(path));
+            }
+            return false;
+        }
+        ui_context.set_cursor_icon(if is_on_view {
+            CursorIcon::Copy
+        } else {
+            CursorIcon::NoDrop
+        });
+        if let Some(pos) = pointer {
+            Area::new(Id::new("Dragged Asset"))
+                .order(Order::Tooltip)
+                .fixed_pos(pos + Vec2::splat(12.))
+                .interactable(false)
+                .show(ui_context, |ui| {
+                    Frame::popup(ui.style()).show(ui, |ui| {
+                        if is_on_view {
+                            ui.label(format!("Add {} to the scene", asset.name));
+                        } else {
+                            ui.label(asset.name.as_str());
+                        }
+                    });
+                });
+        }
+        true
+    }
+}
+
+#[allow(dead_code)]
+fn test_asset_kind() {
+    assert_eq!(
+        AssetKind::from_path(Path::new("sponza/Sponza.gltf")),
+        Some(AssetKind::Gltf)
+    );
+    assert_eq!(
+        AssetKind::from_path(&Path::new("sponza").join(format!("sponza.{}", Scene::extension()))),
+        Some(AssetKind::Scene)
+    );
+    assert_eq!(
+        AssetKind::from_path(Path::new("textures/Test.PNG")),
+        Some(AssetKind::Image)
+    );
+    assert_eq!(AssetKind::from_path(Path::new("sponza/Sponza.bin")), None);
+    assert_eq!(AssetKind::from_path(Path::new("sponza")), None);
+    assert!(AssetKind::Gltf.is_importable());
+    assert!(AssetKind::Scene.is_importable());
+    assert!(!AssetKind::Image.is_importable());
+    assert!(!AssetKind::Folder.is_importable());
+
+    let gltf = Path::new("models").join("sponza").join("Sponza.gltf");
+    assert_eq!(
+        compiled_scene_path(&gltf),
+        Some(
+            Path::new("models")
+                .join("sponza")
+                .join(format!("sponza.{}", Scene::extension()))
+        )
+    );
+    assert_eq!(compiled_scene_path(Path::new("Sponza.gltf")), None);
+
+    assert_eq!(short_name("Sponza.gltf"), "Sponza.gltf");
+    assert_eq!(short_name("FlightHelmet.gltf"), "FlightHelme…");
+}
+
+#[allow(dead_code)]
+fn test_list_assets() {
+    let root = std::env::temp_dir().join("inox_content_browser");
+    fs::remove_dir_all(root.as_path()).ok();
+    let raw_folder = root.join("data_raw");
+    let data_folder = root.join("data");
+    let sponza = Path::new("models").join("sponza");
+    fs::create_dir_all(raw_folder.join(sponza.as_path())).unwrap();
+    fs::create_dir_all(data_folder.join(sponza.as_path())).unwrap();
+    fs::create_dir_all(raw_folder.join("textures")).unwrap();
+    let scene_name = format!("sponza.{}", Scene::extension());
+    ["Sponza.gltf", "Sponza.bin", "floor.png"]
+        .iter()
+        .for_each(|f| fs::write(raw_folder.join(sponza.as_path()).join(f), "").unwrap());
+    fs::write(data_folder.join(sponza.as_path()).join(&scene_name), "").unwrap();
+    //Scenes are compiled, the ones of the data raw are not listed
+    fs::write(raw_folder.join(sponza.as_path()).join("old.scene"), "").unwrap();
+
+    let assets = list_assets(&raw_folder, &data_folder, Path::new(""));
+    let names = assets.iter().map(|a| a.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, vec!["models", "textures"]);
+    assert!(assets.iter().all(|a| a.kind == AssetKind::Folder));
+
+    let assets = list_assets(&raw_folder, &data_folder, sponza.as_path());
+    assert_eq!(
+        assets,
+        vec![
+            Asset {
+                kind: AssetKind::Image,
+                name: "floor.png".to_string(),
+                path: sponza.join("floor.png"),
+            },
+            Asset {
+                kind: AssetKind::Gltf,
+                name: "Sponza.gltf".to_string(),
+                path: sponza.join("Sponza.gltf"),
+            },
+            Asset {
+                kind: AssetKind::Scene,
+                name: scene_name.clone(),
+                path: sponza.join(scene_name.as_str()),
+            },
+        ]
+    );
+    assert!(list_assets(&raw_folder, &data_folder, Path::new("missing")).is_empty());
+}
+
+#[test]
+fn test() {
+    test_asset_kind();
+    test_list_assets();
+}
@@ -0,0 +1,89 @@
+use std::sync::{Arc, RwLock};
+
+use inox_messenger::MessageHubRc;
+use inox_resources::{Resource, SharedDataRc};
+use inox_ui::{implement_widget_data, Dock, DockNode, DockRw, DockSplit, DockTree, UIWidget};
+
+//Area left empty to see the scene, or showing it when rendered in the 3D view
+pub const VIEW_PANEL: &str = "View";
+pub const HIERARCHY_PANEL: &str = "Hierarchy";
+pub const DEBUG_PANEL: &str = "Debug";
+pub const GRAPHICS_PANEL: &str = "Graphics";
+pub const CONTENT_PANEL: &str = "Content";
+
+pub fn default_dock_layout() -> DockTree {
+    DockTree::new(DockNode::split(
+        DockSplit::Horizontal,
+        0.2,
+        DockNode::tabs(&[HIERARCHY_PANEL]),
+        DockNode::split(
+            DockSplit::Horizontal,
+            0.75,
+            DockNode::split(
+                DockSplit::Vertical,
+                0.75,
+                DockNode::tabs(&[VIEW_PANEL]),
+                DockNode::tabs(&[CONTENT_PANEL]),
+            ),
+            DockNode::tabs(&[DEBUG_PANEL, GRAPHICS_PANEL]),
+        ),
+    ))
+}
+
+#[derive(Clone)]
+struct DockSpaceData {
+    dock: DockRw,
+}
+implement_widget_data!(DockSpaceData);
+
+pub struct DockSpace {
+    _ui_page: Resource<UIWidget>,
+    dock: DockRw,
+}
+
+impl DockSpace {
+    pub fn new(shared_data: &SharedDataRc, message_hub: &MessageHubRc) -> Self {
+        let dock = Arc::new(RwLock::new(Dock::new(default_dock_layout())));
+        let data = DockSpaceData { dock: dock.clone() };
+        Self {
+            _ui_page: Self::create(shared_data, message_hub, data),
+            dock,
+        }
+    }
+
+    pub fn dock(&self) -> &DockRw {
+        &self.dock
+    }
+    //A loaded layout replaces the current one only when different, to not stop a drag
+    pub fn set_layout(&self, layout: &DockTree) {
+        let mut dock = self.dock.write().unwrap();
+        if dock.tree() != layout {
+            dock.set_tree(layout.clone());
+        }
+    }
+    pub fn reset_layout(&self) {
+        self.dock.write().unwrap().reset();
+    }
+    //Layout changed by the user since the last call
+    pub fn changed_layout(&self) -> Option<DockTree> {
+        let mut dock = self.dock.write().unwrap();
+        if dock.take_changed() {
+            Some(dock.tree().clone())
+        } else {
+            None
+        }
+    }
+
+    fn create(
+        shared_data: &SharedDataRc,
+        message_hub: &MessageHubRc,
+        data: DockSpaceData,
+    ) -> Resource<UIWidget> {
+        UIWidget::register(shared_data, message_hub, data, |ui_data, ui_context| {
+            if let Some(data) = ui_data.as_any_mut().downcast_mut::<DockSpaceData>() {
+                return data.dock.write().unwrap().show(ui_context);
+            }
+            false
+        })
+    }
+}
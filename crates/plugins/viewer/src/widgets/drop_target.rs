@@ -0,0 +1,76 @@
+use std::path::{Path, PathBuf};
+
+use inox_messenger::MessageHubRc;
+use inox_resources::{Resource, SharedDataRc};
+use inox_ui::{implement_widget_data, Align2, Area, Frame, Order, UIWidget};
+
+#[derive(Clone)]
+struct DropTargetData {
+    hovered_file: Option<PathBuf>,
+    is_supported: bool,
+}
+implement_widget_data!(DropTargetData);
+
+//Tells what happens to the file dragged over the window before it's dropped
+pub struct DropTarget {
+    ui_page: Resource<UIWidget>,
+}
+
+unsafe impl Send for DropTarget {}
+unsafe impl Sync for DropTarget {}
+
+impl DropTarget {
+    pub fn new(shared_data: &SharedDataRc, message_hub: &MessageHubRc) -> Self {
+        let data = DropTargetData {
+            hovered_file: None,
+            is_supported: false,
+        };
+        let ui_page = Self::create(shared_data, message_hub, data);
+        Self { ui_page }
+    }
+
+    pub fn hovered_file(&self) -> Option<PathBuf> {
+        self.ui_page
+            .get()
+            .data::<DropTargetData>()
+            .and_then(|data| data.hovered_file.clone())
+    }
+    //An empty path is a file with a name not known yet
+    pub fn set_hovered_file(&self, path: Option<&Path>, is_supported: bool) {
+        if let Some(data) = self.ui_page.get_mut().data_mut::<DropTargetData>() {
+            data.hovered_file = path.map(|p| p.to_path_buf());
+            data.is_supported = is_supported;
+        }
+    }
+
+    fn create(
+        shared_data: &SharedDataRc,
+        message_hub: &MessageHubRc,
+        data: DropTargetData,
+    ) -> Resource<UIWidget> {
+        UIWidget::register(shared_data, message_hub, data, |ui_data, ui_context| {
+            if let Some(data) = ui_data.as_any_mut().downcast_mut::<DropTargetData>() {
+                let Some(path) = &data.hovered_file else {
+                    return false;
+                };
+                let text = match path.file_name() {
+                    None => "Drop the file to load it".to_string(),
+                    Some(name) if data.is_supported => {
+                        format!("Drop {} to load it", name.to_string_lossy())
+                    }
+                    Some(name) => format!("Unable to load {}", name.to_string_lossy()),
+                };
+                Area::new("Drop Target")
+                    .anchor(Align2::CENTER_CENTER, [0., 0.])
+                    .order(Order::Foreground)
+                    .interactable(false)
+                    .show(ui_context, |ui| {
+                        Frame::popup(ui.style()).show(ui, |ui| {
+                            ui.heading(text);
+                        });
+                    });
+            }
+            false
+        })
+    }
+}
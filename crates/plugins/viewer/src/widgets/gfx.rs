@@ -1,37 +1,73 @@
 use inox_core::ContextRc;
-use inox_graphics::RendererRw;
+use inox_graphics::{RendererRw, MAX_RENDER_SCALE, MIN_RENDER_SCALE};
 
 use inox_messenger::MessageHubRc;
 use inox_resources::{Resource, SharedDataRc};
-use inox_ui::{implement_widget_data, UIWidget, Widget, Window};
+use inox_ui::{implement_widget_data, DockRw, ProgressBar, Slider, UIWidget, Ui, Widget};
+
+use super::GRAPHICS_PANEL;
+
+//Milliseconds held by the dynamic render scale when it's enabled the first time
+const DEFAULT_TARGET_FRAME_TIME: f32 = 16.6;
 
 #[derive(Clone)]
 struct GfxData {
+    dock: DockRw,
     vertices_count: usize,
     indices_count: usize,
     meshes_count: usize,
     meshlets_count: usize,
+    max_anisotropy: u16,
+    anisotropy: u16,
+    //Level of the renderer when the panel was last updated, to apply only the ones chosen here
+    renderer_anisotropy: u16,
+    has_scaled_render_targets: bool,
+    render_scale: f32,
+    is_dynamic_scale: bool,
+    target_frame_time: f32,
     passes: Vec<(String, bool)>,
+    pass_timings: Vec<(String, f32)>,
+    system_timings: Vec<(String, f32)>,
 }
 implement_widget_data!(GfxData);
 
 #[derive(Clone)]
 pub struct Gfx {
     ui_page: Resource<UIWidget>,
+    context: ContextRc,
     renderer: RendererRw,
 }
 
 impl Gfx {
-    pub fn new(context: &ContextRc, renderer: &RendererRw) -> Self {
+    pub fn new(context: &ContextRc, renderer: &RendererRw, dock: &DockRw) -> Self {
+        let (has_scaled_render_targets, render_scale, target_frame_time) = {
+            let renderer = renderer.read().unwrap();
+            (
+                renderer.has_scaled_render_targets(),
+                renderer.render_scale(),
+                renderer.target_frame_time(),
+            )
+        };
         let data = GfxData {
+            dock: dock.clone(),
             vertices_count: 0,
             indices_count: 0,
             meshes_count: 0,
             meshlets_count: 0,
+            max_anisotropy: 1,
+            anisotropy: 1,
+            renderer_anisotropy: 1,
+            has_scaled_render_targets,
+            render_scale,
+            is_dynamic_scale: target_frame_time.is_some(),
+            target_frame_time: target_frame_time.unwrap_or(DEFAULT_TARGET_FRAME_TIME),
             passes: Vec::new(),
+            pass_timings: Vec::new(),
+            system_timings: Vec::new(),
         };
         Self {
             ui_page: Self::create(context.shared_data(), context.message_hub(), data),
+            context: context.clone(),
             renderer: renderer.clone(),
         }
     }
@@ -66,24 +102,121 @@ impl Gfx {
                     .read()
                     .unwrap()
                     .item_count();
+                data.max_anisotropy = render_context.texture_handler.max_anisotropy();
+                data.pass_timings = renderer.pass_timings();
+                data.has_scaled_render_targets = renderer.has_scaled_render_targets();
+            }
+            {
+                let mut renderer = self.renderer.write().unwrap();
+                if data.anisotropy != data.renderer_anisotropy {
+                    renderer.set_anisotropy(data.anisotropy);
+                }
+                data.anisotropy = renderer.anisotropy();
+                data.renderer_anisotropy = data.anisotropy;
             }
+            data.system_timings = self
+                .context
+                .system_timings()
+                .slowest()
+                .iter()
+                .map(|timing| (timing.name.clone(), timing.average_ms))
+                .collect();
 
             if data.passes.is_empty() {
                 let renderer = self.renderer.read().unwrap();
-                for i in 0..renderer.num_passes() {
-                    let name = renderer.pass_at(i).unwrap().name().to_string();
-                    let is_enabled = renderer.is_pass_enabled(i);
-                    data.passes.push((name, is_enabled));
-                }
+                renderer.pass_names().iter().for_each(|name| {
+                    data.passes
+                        .push((name.to_string(), renderer.is_pass_enabled(name)));
+                });
             } else {
                 let mut renderer = self.renderer.write().unwrap();
-                data.passes.iter().enumerate().for_each(|(i, (_n, b))| {
-                    renderer.set_pass_enabled(i, *b);
+                data.passes.iter().for_each(|(name, is_enabled)| {
+                    renderer.set_pass_enabled(name, *is_enabled);
                 });
+                let order = data
+                    .passes
+                    .iter()
+                    .map(|(name, _)| name.as_str())
+                    .collect::<Vec<_>>();
+                if order != renderer.pass_names() {
+                    //The passes are read again from the renderer when the order isn't valid
+                    if let Err(e) = renderer.reorder_passes(&order) {
+                        inox_log::debug_log!("Unable to reorder the render passes - {e}");
+                        data.passes.clear();
+                    }
+                }
+            }
+            if data.has_scaled_render_targets {
+                let mut renderer = self.renderer.write().unwrap();
+                let target_frame_time = data.is_dynamic_scale.then_some(data.target_frame_time);
+                if renderer.target_frame_time() != target_frame_time {
+                    renderer.set_target_frame_time(target_frame_time);
+                }
+                //The dynamic scale is only shown, it's moved by the renderer
+                if data.is_dynamic_scale {
+                    data.render_scale = renderer.render_scale();
+                } else {
+                    renderer.set_render_scale(data.render_scale);
+                }
             }
         }
     }
 
+    //Bars are relative to the slowest pass
+    fn show_pass_timings(data: &GfxData, ui: &mut Ui) {
+        if data.pass_timings.is_empty() {
+            ui.label("GPU time per pass: not available");
+            return;
+        }
+        let total: f32 = data.pass_timings.iter().map(|(_, ms)| ms).sum();
+        let slowest = data
+            .pass_timings
+            .iter()
+            .fold(f32::EPSILON, |slowest, (_, ms)| slowest.max(*ms));
+        ui.label(format!("GPU time per pass: {total:.2} ms"));
+        data.pass_timings.iter().for_each(|(name, ms)| {
+            ProgressBar::new(ms / slowest)
+                .text(format!("{name}: {ms:.2} ms"))
+                .ui(ui);
+        });
+    }
+
+    fn show_render_scale(data: &mut GfxData, ui: &mut Ui) {
+        if !data.has_scaled_render_targets {
+            ui.label("Render scale: not supported by the passes in use");
+            return;
+        }
+        ui.add_enabled(
+            !data.is_dynamic_scale,
+            Slider::new(&mut data.render_scale, MIN_RENDER_SCALE..=MAX_RENDER_SCALE)
+                .text("Render scale"),
+        );
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut data.is_dynamic_scale, "Dynamic, GPU ms: ");
+            ui.add_enabled(
+                data.is_dynamic_scale,
+                inox_ui::DragValue::new(&mut data.target_frame_time)
+                    .speed(0.1)
+                    .clamp_range(1. ..=100.),
+            );
+        });
+    }
+
+    fn show_system_timings(data: &GfxData, ui: &mut Ui) {
+        if data.system_timings.is_empty() {
+            ui.label("CPU time per system: not available");
+            return;
+        }
+        //Already sorted from the slowest system
+        let slowest = data.system_timings[0].1.max(f32::EPSILON);
+        ui.label("CPU time per system (average):");
+        data.system_timings.iter().for_each(|(name, ms)| {
+            ProgressBar::new(ms / slowest)
+                .text(format!("{name}: {ms:.2} ms"))
+                .ui(ui);
+        });
+    }
+
     fn create(
         shared_data: &SharedDataRc,
         message_hub: &MessageHubRc,
@@ -91,46 +224,77 @@ impl Gfx {
     ) -> Resource<UIWidget> {
         UIWidget::register(shared_data, message_hub, data, |ui_data, ui_context| {
             if let Some(data) = ui_data.as_any_mut().downcast_mut::<GfxData>() {
-                if let Some(response) = Window::new("Graphics")
-                    .vscroll(true)
-                    .title_bar(true)
-                    .resizable(true)
-                    .show(ui_context, |ui| {
+                let Some(window) = data.dock.read().unwrap().window(GRAPHICS_PANEL) else {
+                    return false;
+                };
+                if let Some(response) = window.vscroll(true).show(ui_context, |ui| {
+                    ui.horizontal_wrapped(|ui| {
                         ui.horizontal_wrapped(|ui| {
-                            ui.horizontal_wrapped(|ui| {
-                                ui.label("Total vertices: ");
-                                inox_ui::DragValue::new(&mut data.vertices_count)
-                                    .speed(0)
-                                    .ui(ui);
-                            });
-                            ui.horizontal_wrapped(|ui| {
-                                ui.label("Total triangles: ");
-                                let mut triangles_count = data.indices_count / 3;
-                                inox_ui::DragValue::new(&mut triangles_count)
-                                    .speed(0)
-                                    .ui(ui);
-                            });
+                            ui.label("Total vertices: ");
+                            inox_ui::DragValue::new(&mut data.vertices_count)
+                                .speed(0)
+                                .ui(ui);
                         });
                         ui.horizontal_wrapped(|ui| {
-                            ui.horizontal_wrapped(|ui| {
-                                ui.label("Total meshes: ");
-                                inox_ui::DragValue::new(&mut data.meshes_count)
-                                    .speed(0)
-                                    .ui(ui);
-                            });
-                            ui.horizontal_wrapped(|ui| {
-                                ui.label("Total meshlets: ");
-                                inox_ui::DragValue::new(&mut data.meshlets_count)
-                                    .speed(0)
-                                    .ui(ui);
-                            });
+                            ui.label("Total triangles: ");
+                            let mut triangles_count = data.indices_count / 3;
+                            inox_ui::DragValue::new(&mut triangles_count)
+                                .speed(0)
+                                .ui(ui);
+                        });
+                    });
+                    ui.horizontal_wrapped(|ui| {
+                        ui.horizontal_wrapped(|ui| {
+                            ui.label("Total meshes: ");
+                            inox_ui::DragValue::new(&mut data.meshes_count)
+                                .speed(0)
+                                .ui(ui);
+                        });
+                        ui.horizontal_wrapped(|ui| {
+                            ui.label("Total meshlets: ");
+                            inox_ui::DragValue::new(&mut data.meshlets_count)
+                                .speed(0)
+                                .ui(ui);
                         });
-                        ui.separator();
-                        data.passes.iter_mut().for_each(|(name, is_enabled)| {
-                            ui.checkbox(is_enabled, name.as_str());
+                    });
+                    if data.max_anisotropy > 1 {
+                        Slider::new(&mut data.anisotropy, 1..=data.max_anisotropy)
+                            .text("Anisotropic filtering")
+                            .ui(ui);
+                    } else {
+                        ui.label("Anisotropic filtering: not supported, using trilinear");
+                    }
+                    ui.separator();
+                    Self::show_render_scale(data, ui);
+                    ui.separator();
+                    Self::show_pass_timings(data, ui);
+                    ui.separator();
+                    Self::show_system_timings(data, ui);
+                    ui.separator();
+                    let mut moved_up = None;
+                    let count = data.passes.len();
+                    data.passes
+                        .iter_mut()
+                        .enumerate()
+                        .for_each(|(i, (name, is_enabled))| {
+                            ui.horizontal(|ui| {
+                                ui.add_enabled_ui(i > 0, |ui| {
+                                    if ui.small_button("Up").clicked() {
+                                        moved_up = Some(i);
+                                    }
+                                });
+                                ui.add_enabled_ui(i + 1 < count, |ui| {
+                                    if ui.small_button("Down").clicked() {
+                                        moved_up = Some(i + 1);
+                                    }
+                                });
+                                ui.checkbox(is_enabled, name.as_str());
+                            });
                         });
-                    })
-                {
+                    if let Some(i) = moved_up {
+                        data.passes.swap(i - 1, i);
+                    }
+                }) {
                     return response.response.is_pointer_button_down_on();
                 }
             }
@@ -4,17 +4,18 @@ use inox_messenger::MessageHubRc;
 use inox_resources::{Resource, SerializableResource, SharedData, SharedDataRc};
 use inox_scene::{Object, ObjectId, Scene, SceneId};
 use inox_ui::{
-    collapsing_header::CollapsingState, implement_widget_data, CollapsingHeader, ScrollArea,
-    UIWidget, Ui, Window,
+    collapsing_header::CollapsingState, implement_widget_data, CollapsingHeader, DockRw,
+    ScrollArea, UIWidget, Ui,
 };
-use inox_uid::INVALID_UID;
+use inox_uid::{resource_uid_to_string, INVALID_RESOURCE_UID};
 
-use crate::events::WidgetEvent;
+use crate::{events::WidgetEvent, widgets::HIERARCHY_PANEL};
 
 #[derive(Clone)]
 struct HierarchyData {
     shared_data: SharedDataRc,
     message_hub: MessageHubRc,
+    dock: DockRw,
     selected_object: ObjectId,
     scene: Resource<Scene>,
 }
@@ -29,13 +30,15 @@ impl Hierarchy {
     pub fn new(
         shared_data: &SharedDataRc,
         message_hub: &MessageHubRc,
+        dock: &DockRw,
         scene_id: &SceneId,
     ) -> Option<Self> {
         if let Some(scene) = SharedData::get_resource::<Scene>(shared_data, scene_id) {
             let data = HierarchyData {
                 shared_data: shared_data.clone(),
                 message_hub: message_hub.clone(),
-                selected_object: INVALID_UID,
+                dock: dock.clone(),
+                selected_object: INVALID_RESOURCE_UID,
                 scene,
             };
             return Some(Self {
@@ -52,24 +55,22 @@ impl Hierarchy {
     ) -> Resource<UIWidget> {
         UIWidget::register(shared_data, message_hub, data, |ui_data, ui_context| {
             if let Some(data) = ui_data.as_any_mut().downcast_mut::<HierarchyData>() {
-                if let Some(response) = Window::new("Hierarchy")
-                    .vscroll(true)
-                    .title_bar(true)
-                    .resizable(true)
-                    .show(ui_context, |ui| {
-                        CollapsingHeader::new("Scene")
-                            .show_background(false)
-                            .default_open(true)
-                            .show(ui, |ui| {
-                                ScrollArea::vertical().show(ui, |ui| {
-                                    let objects = data.scene.get().objects().clone();
-                                    objects.iter().for_each(|object| {
-                                        Self::object_hierarchy(ui, object, data);
-                                    });
-                                })
+                let Some(window) = data.dock.read().unwrap().window(HIERARCHY_PANEL) else {
+                    return false;
+                };
+                if let Some(response) = window.vscroll(true).show(ui_context, |ui| {
+                    CollapsingHeader::new("Scene")
+                        .show_background(false)
+                        .default_open(true)
+                        .show(ui, |ui| {
+                            ScrollArea::vertical().show(ui, |ui| {
+                                let objects = data.scene.get().objects().clone();
+                                objects.iter().for_each(|object| {
+                                    Self::object_hierarchy(ui, object, data);
+                                });
                             })
-                    })
-                {
+                        })
+                }) {
                     return response.response.is_pointer_button_down_on();
                 }
             }
@@ -79,7 +80,7 @@ impl Hierarchy {
 
     fn select_object(data: &mut HierarchyData, object_id: &ObjectId) {
         if data.selected_object == *object_id {
-            data.selected_object = INVALID_UID;
+            data.selected_object = INVALID_RESOURCE_UID;
         } else {
             data.selected_object = *object_id;
         }
@@ -90,7 +91,7 @@ impl Hierarchy {
     fn object_hierarchy(ui: &mut Ui, object: &Resource<Object>, data: &mut HierarchyData) {
         inox_profiler::scoped_profile!("object_hierarchy");
 
-        let mut object_name = format!("Object [{:?}]", object.id().as_simple().to_string());
+        let mut object_name = format!("Object [{:?}]", resource_uid_to_string(object.id()));
         if let Some(name) = object.get().path().file_stem() {
             if let Some(name) = name.to_str() {
                 object_name = name.to_string();
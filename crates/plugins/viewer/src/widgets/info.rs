@@ -1,22 +1,33 @@
+use inox_bhv::AABB;
 use inox_core::ContextRc;
 use inox_graphics::{
-    CullingEvent, DrawEvent, Light, Mesh, MeshFlags, MeshId, RendererRw,
-    CONSTANT_DATA_FLAGS_DISPLAY_MESHLETS, CONSTANT_DATA_FLAGS_DISPLAY_MESHLETS_BOUNDING_BOX,
-    CONSTANT_DATA_FLAGS_DISPLAY_MESHLETS_SPHERE,
+    CullingEvent, DrawEvent, Light, LightType, Material, MaterialData, MaterialId, Mesh, MeshFlags,
+    MeshId, PresentMode, RendererRw, WireframePass, CONSTANT_DATA_FLAGS_DISPLAY_MESHLETS,
+    CONSTANT_DATA_FLAGS_DISPLAY_MESHLETS_BOUNDING_BOX, CONSTANT_DATA_FLAGS_DISPLAY_MESHLETS_SPHERE,
 };
 use inox_math::{
     compute_frustum, Degrees, Frustum, Mat4Ops, MatBase, Matrix4, NewAngle, Quat, VecBase,
-    VecBaseFloat, Vector3,
+    VecBaseFloat, Vector3, Vector4,
 };
 use inox_messenger::Listener;
 use inox_resources::{DataTypeResourceEvent, HashBuffer, Resource, ResourceEvent};
-use inox_scene::{Camera, Object, ObjectId, SceneId};
-use inox_ui::{implement_widget_data, ComboBox, UIWidget, Window};
-use inox_uid::INVALID_UID;
+use inox_scene::{
+    Camera, Object, ObjectId, SceneId, DEFAULT_CAMERA_ROTATION_SPEED, DEFAULT_CAMERA_SPEED,
+    MAX_CAMERA_ROTATION_SPEED, MAX_CAMERA_SPEED, MIN_CAMERA_ROTATION_SPEED, MIN_CAMERA_SPEED,
+};
+use inox_ui::{implement_widget_data, Button, ComboBox, DockRw, DragValue, UIWidget, Ui};
+use inox_uid::INVALID_RESOURCE_UID;
+
+use crate::{
+    events::WidgetEvent,
+    gizmo::{GizmoMode, GizmoSettings, GizmoSpace},
+    history::{CommandsHistoryRw, MaterialCommand},
+};
 
-use crate::events::WidgetEvent;
+use super::{Gfx, Hierarchy, DEBUG_PANEL};
 
-use super::{Gfx, Hierarchy};
+//Segments of the circle drawn at the base of the spot lights cone
+const LIGHT_GIZMO_SEGMENTS: u32 = 16;
 
 #[derive(Clone)]
 struct MeshInfo {
@@ -60,6 +71,8 @@ pub struct InfoParams {
     pub is_active: bool,
     pub scene_id: SceneId,
     pub renderer: RendererRw,
+    pub dock: DockRw,
+    pub history: CommandsHistoryRw,
 }
 
 #[derive(Default, Debug, PartialEq, Eq, Clone, Copy)]
@@ -82,8 +95,12 @@ struct Data {
     show_blas: bool,
     show_frustum: bool,
     show_lights: bool,
+    show_resources: bool,
     freeze_culling_camera: bool,
+    is_vsync: bool,
     meshlet_debug: MeshletDebug,
+    camera_speed: f32,
+    camera_rotation_speed: f32,
     fps: u32,
     dt: u128,
     cam_matrix: Matrix4,
@@ -92,6 +109,12 @@ struct Data {
     fov: Degrees,
     aspect_ratio: f32,
     selected_object_id: ObjectId,
+    select_descendants: bool,
+    show_selected_wireframe: bool,
+    wireframe_selection: Vec<MeshId>,
+    gizmo: GizmoSettings,
+    //Material being edited with its data before the edit
+    material_edit: Option<(MaterialId, MaterialData)>,
 }
 implement_widget_data!(Data);
 
@@ -103,6 +126,7 @@ pub struct Info {
 
 impl Info {
     pub fn new(context: &ContextRc, params: InfoParams) -> Self {
+        let is_vsync = params.renderer.read().unwrap().present_mode().is_vsync();
         let listener = Listener::new(context.message_hub());
         listener
             .register::<DataTypeResourceEvent<Mesh>>()
@@ -117,8 +141,12 @@ impl Info {
             show_blas: false,
             show_frustum: false,
             show_lights: false,
+            show_resources: false,
             freeze_culling_camera: false,
+            is_vsync,
             meshlet_debug: MeshletDebug::None,
+            camera_speed: DEFAULT_CAMERA_SPEED,
+            camera_rotation_speed: DEFAULT_CAMERA_ROTATION_SPEED,
             fps: 0,
             dt: 0,
             cam_matrix: Matrix4::default_identity(),
@@ -126,7 +154,12 @@ impl Info {
             far: 0.,
             fov: Degrees::new(0.),
             aspect_ratio: 1.,
-            selected_object_id: INVALID_UID,
+            selected_object_id: INVALID_RESOURCE_UID,
+            select_descendants: false,
+            show_selected_wireframe: false,
+            wireframe_selection: Vec::new(),
+            gizmo: GizmoSettings::default(),
+            material_edit: None,
         };
         Self {
             ui_page: Self::create(data),
@@ -152,14 +185,34 @@ impl Info {
         }
     }
 
+    //Movement and rotation speed of the camera, edited in the window
+    pub fn camera_speed(&self) -> Option<(f32, f32)> {
+        self.ui_page
+            .get()
+            .data::<Data>()
+            .map(|data| (data.camera_speed, data.camera_rotation_speed))
+    }
+    pub fn set_camera_speed(&self, speed: f32, rotation_speed: f32) {
+        if let Some(data) = self.ui_page.get_mut().data_mut::<Data>() {
+            data.camera_speed = speed;
+            data.camera_rotation_speed = rotation_speed;
+        }
+    }
+
+    //Mode, space and snapping of the gizmo on the selected object
+    pub fn gizmo_settings(&self) -> Option<GizmoSettings> {
+        self.ui_page.get().data::<Data>().map(|data| data.gizmo)
+    }
+
     fn update_events(&mut self) {
         inox_profiler::scoped_profile!("Info::update_events");
 
         self.listener
             .process_messages(|e: &WidgetEvent| {
-                let WidgetEvent::Selected(object_id) = e;
-                if let Some(data) = self.ui_page.get_mut().data_mut::<Data>() {
-                    data.selected_object_id = *object_id;
+                if let WidgetEvent::Selected(object_id) = e {
+                    if let Some(data) = self.ui_page.get_mut().data_mut::<Data>() {
+                        data.selected_object_id = *object_id;
+                    }
                 }
             })
             .process_messages(|e: &DataTypeResourceEvent<Mesh>| {
@@ -208,10 +261,31 @@ impl Info {
             data.fps = data.context.global_timer().fps();
             data.dt = data.context.global_timer().dt().as_millis();
 
+            let is_vsync = data
+                .params
+                .renderer
+                .read()
+                .unwrap()
+                .present_mode()
+                .is_vsync();
+            if data.is_vsync != is_vsync {
+                let present_mode = if data.is_vsync {
+                    PresentMode::AutoVsync
+                } else {
+                    PresentMode::AutoNoVsync
+                };
+                data.params
+                    .renderer
+                    .write()
+                    .unwrap()
+                    .set_present_mode(present_mode);
+            }
+
             if data.hierarchy.0 && data.hierarchy.1.is_none() {
                 data.hierarchy.1 = Hierarchy::new(
                     data.context.shared_data(),
                     data.context.message_hub(),
+                    &data.params.dock,
                     &data.params.scene_id,
                 );
             } else if !data.hierarchy.0 && data.hierarchy.1.is_some() {
@@ -219,7 +293,11 @@ impl Info {
             }
 
             if data.graphics.0 && data.graphics.1.is_none() {
-                data.graphics.1 = Some(Gfx::new(&data.context, &data.params.renderer));
+                data.graphics.1 = Some(Gfx::new(
+                    &data.context,
+                    &data.params.renderer,
+                    &data.params.dock,
+                ));
             } else if data.graphics.1.is_some() {
                 if !data.graphics.0 {
                     data.graphics.1 = None;
@@ -282,9 +360,10 @@ impl Info {
                         ));
                 });
             }
-            if !data.selected_object_id.is_nil() {
+            if data.selected_object_id != INVALID_RESOURCE_UID {
                 Self::show_meshes_of_object(data, &data.selected_object_id);
             }
+            Self::update_wireframe_selection(data);
             match data.meshlet_debug {
                 MeshletDebug::Sphere => {
                     Self::show_meshlets_sphere(data, &self.meshes);
@@ -296,34 +375,75 @@ impl Info {
         }
     }
 
-    fn show_meshes_of_object(data: &Data, object_id: &ObjectId) {
+    //Meshes of the object or, when it has none, of its closest descendants having some.
+    //Including the descendants collects the meshes of the whole hierarchy below the object
+    fn meshes_of_object(
+        data: &Data,
+        object_id: &ObjectId,
+        include_descendants: bool,
+        meshes: &mut Vec<MeshId>,
+    ) {
         if let Some(object) = data.context.shared_data().get_resource::<Object>(object_id) {
             let object = object.get();
-            let meshes = object.components_of_type::<Mesh>();
-            if meshes.is_empty() {
+            let object_meshes = object.components_of_type::<Mesh>();
+            meshes.extend(object_meshes.iter().map(|mesh| *mesh.id()));
+            if include_descendants || object_meshes.is_empty() {
                 let children = object.children();
                 children.iter().for_each(|o| {
-                    Self::show_meshes_of_object(data, o.id());
+                    Self::meshes_of_object(data, o.id(), include_descendants, meshes);
                 });
-            } else {
-                let renderer = data.params.renderer.read().unwrap();
-                let render_context = renderer.render_context();
-                let bhv = render_context.render_buffers.bhv.read().unwrap();
-                meshes.iter().for_each(|mesh| {
-                    if let Some(nodes) = bhv.items(mesh.id()) {
-                        nodes.iter().for_each(|n| {
-                            let matrix = mesh.get().matrix();
-                            data.context
-                                .message_hub()
-                                .send_event(DrawEvent::BoundingBox(
-                                    matrix.rotate_point(n.min.into()),
-                                    matrix.rotate_point(n.max.into()),
-                                    [1.0, 1.0, 0.0, 1.0].into(),
-                                ));
-                        });
-                    }
+            }
+        }
+    }
+
+    fn show_meshes_of_object(data: &Data, object_id: &ObjectId) {
+        let mut meshes = Vec::new();
+        Self::meshes_of_object(data, object_id, data.select_descendants, &mut meshes);
+        let renderer = data.params.renderer.read().unwrap();
+        let render_context = renderer.render_context();
+        let bhv = render_context.render_buffers.bhv.read().unwrap();
+        meshes.iter().for_each(|id| {
+            let Some(mesh) = data.context.shared_data().get_resource::<Mesh>(id) else {
+                return;
+            };
+            if let Some(nodes) = bhv.items(id) {
+                let matrix = mesh.get().matrix();
+                nodes.iter().for_each(|n| {
+                    let aabb = AABB::create(n.min.into(), n.max.into(), 0).transformed(&matrix);
+                    data.context
+                        .message_hub()
+                        .send_event(DrawEvent::BoundingBox(
+                            aabb.min(),
+                            aabb.max(),
+                            [1.0, 1.0, 0.0, 1.0].into(),
+                        ));
                 });
             }
+        });
+    }
+
+    //The wireframe pass is told only when the meshes of the selected object change
+    fn update_wireframe_selection(data: &mut Data) {
+        let mut selection = Vec::new();
+        if data.show_selected_wireframe && data.selected_object_id != INVALID_RESOURCE_UID {
+            Self::meshes_of_object(
+                data,
+                &data.selected_object_id,
+                data.select_descendants,
+                &mut selection,
+            );
+        }
+        if data.wireframe_selection != selection {
+            if let Some(wireframe_pass) = data
+                .params
+                .renderer
+                .write()
+                .unwrap()
+                .pass_mut::<WireframePass>()
+            {
+                wireframe_pass.set_selection(&selection);
+            }
+            data.wireframe_selection = selection;
         }
     }
 
@@ -332,12 +452,59 @@ impl Info {
             .shared_data()
             .for_each_resource(|_, l: &Light| {
                 if l.is_active() {
-                    data.context.message_hub().send_event(DrawEvent::Sphere(
-                        l.data().position.into(),
-                        l.data().range,
-                        [l.data().color[0], l.data().color[1], l.data().color[2], 1.].into(),
-                        true,
-                    ));
+                    let light = l.data();
+                    let position: Vector3 = light.position.into();
+                    let direction = Vector3::from(light.direction).normalized();
+                    let color: Vector4 =
+                        [light.color[0], light.color[1], light.color[2], 1.].into();
+                    let message_hub = data.context.message_hub();
+                    //Axes of the plane orthogonal to the light direction
+                    let up = if direction.y.abs() < 0.99 {
+                        Vector3::unit_y()
+                    } else {
+                        Vector3::unit_x()
+                    };
+                    let right = direction.cross(up).normalized();
+                    let up = right.cross(direction).normalized();
+                    if light.light_type == LightType::Spot as u32 {
+                        //Lines from the light to the border of the outer cone at its range
+                        let center = position + direction * light.range;
+                        let radius = light.range * light.outer_cone_angle.tan();
+                        let mut previous = center + right * radius;
+                        for i in 1..=LIGHT_GIZMO_SEGMENTS {
+                            let angle =
+                                i as f32 * std::f32::consts::TAU / LIGHT_GIZMO_SEGMENTS as f32;
+                            let point = center + (right * angle.cos() + up * angle.sin()) * radius;
+                            message_hub.send_event(DrawEvent::Line(previous, point, color));
+                            if i % (LIGHT_GIZMO_SEGMENTS / 4) == 0 {
+                                message_hub.send_event(DrawEvent::Line(position, point, color));
+                            }
+                            previous = point;
+                        }
+                    } else if light.light_type == LightType::Area as u32 {
+                        let right = right * light.area_size[0] * 0.5;
+                        let up = up * light.area_size[1] * 0.5;
+                        let corners = [
+                            position - right - up,
+                            position + right - up,
+                            position + right + up,
+                            position - right + up,
+                        ];
+                        for (i, corner) in corners.iter().enumerate() {
+                            let next = corners[(i + 1) % corners.len()];
+                            message_hub.send_event(DrawEvent::Line(*corner, next, color));
+                        }
+                        message_hub.send_event(DrawEvent::Arrow(position, direction, color, true));
+                    } else if light.light_type == LightType::Directional as u32 {
+                        message_hub.send_event(DrawEvent::Arrow(position, direction, color, true));
+                    } else {
+                        message_hub.send_event(DrawEvent::Sphere(
+                            position,
+                            light.range,
+                            color,
+                            true,
+                        ));
+                    }
                 }
             });
     }
@@ -364,11 +531,13 @@ impl Info {
         meshes.for_each_entry(|_id, mesh_info| {
             if mesh_info.flags.contains(MeshFlags::Visible) {
                 mesh_info.meshlets.iter().for_each(|meshlet_info| {
+                    let aabb = AABB::create(meshlet_info.min, meshlet_info.max, 0)
+                        .transformed(&mesh_info.matrix);
                     data.context
                         .message_hub()
                         .send_event(DrawEvent::BoundingBox(
-                            mesh_info.matrix.rotate_point(meshlet_info.min),
-                            mesh_info.matrix.rotate_point(meshlet_info.max),
+                            aabb.min(),
+                            aabb.max(),
                             [1.0, 1.0, 0.0, 1.0].into(),
                         ));
                 });
@@ -466,6 +635,182 @@ impl Info {
         ));
     }
 
+    fn show_resources(data: &Data, ui: &mut Ui) {
+        data.context
+            .shared_data()
+            .resource_report()
+            .iter()
+            .filter(|r| r.count() > 0)
+            .for_each(|r| {
+                let type_name = r.type_name.rsplit("::").next().unwrap_or(r.type_name);
+                ui.label(format!(
+                    "{}: {} [strong: {} - weak: {}]",
+                    type_name,
+                    r.count(),
+                    r.strong_count(),
+                    r.weak_count()
+                ));
+            });
+    }
+
+    fn show_gizmo_settings(gizmo: &mut GizmoSettings, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Gizmo");
+            ui.selectable_value(&mut gizmo.mode, GizmoMode::Translate, "Translate");
+            ui.selectable_value(&mut gizmo.mode, GizmoMode::Rotate, "Rotate");
+            ui.selectable_value(&mut gizmo.mode, GizmoMode::Scale, "Scale");
+        });
+        ui.horizontal(|ui| {
+            ui.label("Gizmo Space");
+            ui.selectable_value(&mut gizmo.space, GizmoSpace::World, "World");
+            ui.selectable_value(&mut gizmo.space, GizmoSpace::Local, "Local");
+        });
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut gizmo.is_snapping, "Snap");
+            ui.add(
+                DragValue::new(&mut gizmo.translation_snap)
+                    .clamp_range(0.01..=100.)
+                    .speed(0.1)
+                    .prefix("Grid: "),
+            );
+            ui.add(
+                DragValue::new(&mut gizmo.rotation_snap)
+                    .clamp_range(1.0..=90.)
+                    .prefix("Angle: ")
+                    .suffix("°"),
+            );
+            ui.add(
+                DragValue::new(&mut gizmo.scale_snap)
+                    .clamp_range(0.01..=10.)
+                    .speed(0.01)
+                    .prefix("Scale: "),
+            );
+        });
+    }
+
+    //Changes to the first material of the selected object are a single step of the history,
+    //pushed once the pointer is released
+    fn show_selected_material(data: &mut Data, ui: &mut Ui) {
+        let mut meshes = Vec::new();
+        if data.selected_object_id != INVALID_RESOURCE_UID {
+            Self::meshes_of_object(data, &data.selected_object_id, false, &mut meshes);
+        }
+        let shared_data = data.context.shared_data().clone();
+        let material = meshes.iter().find_map(|id| {
+            shared_data
+                .get_resource::<Mesh>(id)
+                .and_then(|mesh| mesh.get().material().clone())
+        });
+        let is_other_material = data
+            .material_edit
+            .as_ref()
+            .is_some_and(|(id, _)| material.as_ref().map(|m| m.id()) != Some(id));
+        if is_other_material {
+            Self::push_material_edit(data);
+        }
+        let Some(material) = material else {
+            return;
+        };
+        let mut material_data = material.get().data().clone();
+        let mut is_changed = false;
+        ui.collapsing("Selected Material", |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Base Color");
+                let mut base_color: [f32; 4] = material_data.base_color.into();
+                if ui
+                    .color_edit_button_rgba_unmultiplied(&mut base_color)
+                    .changed()
+                {
+                    material_data.base_color = base_color.into();
+                    is_changed = true;
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Roughness");
+                is_changed |= ui
+                    .add(
+                        DragValue::new(&mut material_data.roughness_factor)
+                            .clamp_range(0.0..=1.)
+                            .speed(0.01),
+                    )
+                    .changed();
+            });
+            ui.horizontal(|ui| {
+                ui.label("Metallic");
+                is_changed |= ui
+                    .add(
+                        DragValue::new(&mut material_data.metallic_factor)
+                            .clamp_range(0.0..=1.)
+                            .speed(0.01),
+                    )
+                    .changed();
+            });
+            ui.horizontal(|ui| {
+                ui.label("Emissive Strength");
+                is_changed |= ui
+                    .add(
+                        DragValue::new(&mut material_data.emissive_strength)
+                            .clamp_range(0.0..=100.)
+                            .speed(0.1),
+                    )
+                    .changed();
+            });
+        });
+        if is_changed {
+            if data.material_edit.is_none() {
+                data.material_edit = Some((*material.id(), material.get().data().clone()));
+            }
+            *material.get_mut().data_mut() = material_data;
+            material.get().mark_as_dirty();
+        } else if !ui.input(|i| i.pointer.any_down()) {
+            Self::push_material_edit(data);
+        }
+    }
+
+    fn push_material_edit(data: &mut Data) {
+        let Some((material_id, from)) = data.material_edit.take() else {
+            return;
+        };
+        let shared_data = data.context.shared_data();
+        if let Some(material) = shared_data.get_resource::<Material>(&material_id) {
+            let to = material.get().data().clone();
+            if from != to {
+                data.params
+                    .history
+                    .write()
+                    .unwrap()
+                    .push(Box::new(MaterialCommand::new(&material_id, from, to)));
+            }
+        }
+    }
+
+    //Commands undone are shown greyed after the ones done, the next to redo first
+    fn show_history(data: &Data, ui: &mut Ui) {
+        ui.collapsing("History [Ctrl+Z undo - Ctrl+Y redo]", |ui| {
+            let history = data.params.history.read().unwrap();
+            ui.horizontal(|ui| {
+                if ui
+                    .add_enabled(history.can_undo(), Button::new("Undo"))
+                    .clicked()
+                {
+                    data.context.message_hub().send_event(WidgetEvent::Undo);
+                }
+                if ui
+                    .add_enabled(history.can_redo(), Button::new("Redo"))
+                    .clicked()
+                {
+                    data.context.message_hub().send_event(WidgetEvent::Redo);
+                }
+            });
+            history.done_names().iter().for_each(|name| {
+                ui.label(name);
+            });
+            history.undone_names().iter().for_each(|name| {
+                ui.weak(name);
+            });
+        });
+    }
+
     fn create(data: Data) -> Resource<UIWidget> {
         let shared_data = data.context.shared_data().clone();
         let message_hub = data.context.message_hub().clone();
@@ -474,132 +819,193 @@ impl Info {
                 if !data.params.is_active {
                     return false;
                 }
-                if let Some(response) = Window::new("Debug")
-                    .vscroll(true)
-                    .title_bar(true)
-                    .resizable(true)
-                    .show(ui_context, |ui| {
-                        ui.label(format!("FPS: {} - ms: {:?}", data.fps, data.dt));
-                        ui.checkbox(&mut data.hierarchy.0, "Hierarchy");
-                        ui.checkbox(&mut data.graphics.0, "Graphics");
-                        ui.checkbox(&mut data.show_lights, "Show Lights");
-                        ui.checkbox(&mut data.show_tlas, "Show BHV TLAS");
-                        ui.checkbox(&mut data.show_blas, "Show BHV BLAS");
-                        ui.checkbox(&mut data.show_frustum, "Show Frustum");
-                        let is_freezed = data.freeze_culling_camera;
-                        ui.checkbox(&mut data.freeze_culling_camera, "Freeze Culling Camera");
-                        if is_freezed != data.freeze_culling_camera {
-                            if data.freeze_culling_camera {
-                                data.context.message_hub().send_event(CullingEvent::FreezeCamera);
-                            } else {
-                                data.context.message_hub().send_event(CullingEvent::UnfreezeCamera);
-                            }
+                let Some(window) = data.params.dock.read().unwrap().window(DEBUG_PANEL) else {
+                    return false;
+                };
+                if let Some(response) = window.vscroll(true).show(ui_context, |ui| {
+                    ui.label(format!("FPS: {} - ms: {:?}", data.fps, data.dt));
+                    ui.checkbox(&mut data.is_vsync, "VSync");
+                    if data.context.is_stepping() {
+                        ui.label("Simulation: stepping");
+                    } else if data.context.is_paused() {
+                        ui.label("Simulation: paused [F10 resume - F11 step]");
+                    }
+                    ui.horizontal(|ui| {
+                        ui.label("Camera Speed");
+                        ui.add(
+                            DragValue::new(&mut data.camera_speed)
+                                .clamp_range(MIN_CAMERA_SPEED..=MAX_CAMERA_SPEED),
+                        );
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Camera Rotation Speed");
+                        ui.add(
+                            DragValue::new(&mut data.camera_rotation_speed)
+                                .clamp_range(MIN_CAMERA_ROTATION_SPEED..=MAX_CAMERA_ROTATION_SPEED),
+                        );
+                    });
+                    ui.horizontal(|ui| {
+                        if ui.button("Reset Layout").clicked() {
+                            data.context
+                                .message_hub()
+                                .send_event(WidgetEvent::ResetLayout);
+                        }
+                        if ui.button("Save Scene As").clicked() {
+                            data.context
+                                .message_hub()
+                                .send_event(WidgetEvent::SaveSceneAs);
+                        }
+                    });
+                    ui.checkbox(&mut data.hierarchy.0, "Hierarchy");
+                    ui.checkbox(&mut data.graphics.0, "Graphics");
+                    ui.checkbox(&mut data.show_lights, "Show Lights");
+                    ui.checkbox(&mut data.show_tlas, "Show BHV TLAS");
+                    ui.checkbox(&mut data.show_blas, "Show BHV BLAS");
+                    ui.checkbox(&mut data.show_frustum, "Show Frustum");
+                    ui.checkbox(
+                        &mut data.show_selected_wireframe,
+                        "Show Selected Object Wireframe",
+                    );
+                    ui.checkbox(&mut data.select_descendants, "Select Children Meshes");
+                    Self::show_gizmo_settings(&mut data.gizmo, ui);
+                    ui.horizontal(|ui| {
+                        if ui.button("Add Object").clicked() {
+                            data.context
+                                .message_hub()
+                                .send_event(WidgetEvent::AddObject);
                         }
-                        ui.horizontal(|ui| {
-                            ui.label("Show Meshlets");
-                            let combo_box = ComboBox::from_id_source("Meshlet Debug")
-                                .selected_text(format!("{:?}", data.meshlet_debug))
-                                .show_ui(ui, |ui| {
-                                    let mut is_changed = false;
-                                    is_changed |= ui
-                                        .selectable_value(
-                                            &mut data.meshlet_debug,
-                                            MeshletDebug::None,
-                                            "None",
-                                        )
-                                        .changed();
-                                    is_changed |= ui
-                                        .selectable_value(
-                                            &mut data.meshlet_debug,
-                                            MeshletDebug::Color,
-                                            "Color",
-                                        )
-                                        .changed();
-                                    is_changed |= ui
-                                        .selectable_value(
-                                            &mut data.meshlet_debug,
-                                            MeshletDebug::Sphere,
-                                            "Sphere",
-                                        )
-                                        .changed();
-                                    is_changed |= ui
-                                        .selectable_value(
-                                            &mut data.meshlet_debug,
-                                            MeshletDebug::BoundingBox,
-                                            "Bounding Box",
-                                        )
-                                        .changed();
-                                        is_changed |= ui
-                                            .selectable_value(
-                                                &mut data.meshlet_debug,
-                                                MeshletDebug::ConeAxis,
-                                                "Cone Axis",
+                        let is_selected = data.selected_object_id != INVALID_RESOURCE_UID;
+                        if ui
+                            .add_enabled(is_selected, Button::new("Remove Selected Object"))
+                            .clicked()
+                        {
+                            data.context
+                                .message_hub()
+                                .send_event(WidgetEvent::RemoveObject(data.selected_object_id));
+                        }
+                    });
+                    Self::show_selected_material(data, ui);
+                    Self::show_history(data, ui);
+                    ui.checkbox(&mut data.show_resources, "Show Resources");
+                    if data.show_resources {
+                        Self::show_resources(data, ui);
+                    }
+                    let is_freezed = data.freeze_culling_camera;
+                    ui.checkbox(&mut data.freeze_culling_camera, "Freeze Culling Camera");
+                    if is_freezed != data.freeze_culling_camera {
+                        if data.freeze_culling_camera {
+                            data.context
+                                .message_hub()
+                                .send_event(CullingEvent::FreezeCamera);
+                        } else {
+                            data.context
+                                .message_hub()
+                                .send_event(CullingEvent::UnfreezeCamera);
+                        }
+                    }
+                    ui.horizontal(|ui| {
+                        ui.label("Show Meshlets");
+                        let combo_box = ComboBox::from_id_source("Meshlet Debug")
+                            .selected_text(format!("{:?}", data.meshlet_debug))
+                            .show_ui(ui, |ui| {
+                                let mut is_changed = false;
+                                is_changed |= ui
+                                    .selectable_value(
+                                        &mut data.meshlet_debug,
+                                        MeshletDebug::None,
+                                        "None",
+                                    )
+                                    .changed();
+                                is_changed |= ui
+                                    .selectable_value(
+                                        &mut data.meshlet_debug,
+                                        MeshletDebug::Color,
+                                        "Color",
+                                    )
+                                    .changed();
+                                is_changed |= ui
+                                    .selectable_value(
+                                        &mut data.meshlet_debug,
+                                        MeshletDebug::Sphere,
+                                        "Sphere",
+                                    )
+                                    .changed();
+                                is_changed |= ui
+                                    .selectable_value(
+                                        &mut data.meshlet_debug,
+                                        MeshletDebug::BoundingBox,
+                                        "Bounding Box",
+                                    )
+                                    .changed();
+                                is_changed |= ui
+                                    .selectable_value(
+                                        &mut data.meshlet_debug,
+                                        MeshletDebug::ConeAxis,
+                                        "Cone Axis",
+                                    )
+                                    .changed();
+                                is_changed
+                            });
+                        if let Some(is_changed) = combo_box.inner {
+                            if is_changed {
+                                let renderer = data.params.renderer.read().unwrap();
+                                let render_context = renderer.render_context();
+                                match &data.meshlet_debug {
+                                    MeshletDebug::None => {
+                                        render_context
+                                            .constant_data
+                                            .write()
+                                            .unwrap()
+                                            .remove_flag(CONSTANT_DATA_FLAGS_DISPLAY_MESHLETS)
+                                            .remove_flag(
+                                                CONSTANT_DATA_FLAGS_DISPLAY_MESHLETS_SPHERE,
+                                            )
+                                            .remove_flag(
+                                                CONSTANT_DATA_FLAGS_DISPLAY_MESHLETS_BOUNDING_BOX,
+                                            );
+                                    }
+                                    MeshletDebug::Color | MeshletDebug::ConeAxis => {
+                                        render_context
+                                            .constant_data
+                                            .write()
+                                            .unwrap()
+                                            .remove_flag(
+                                                CONSTANT_DATA_FLAGS_DISPLAY_MESHLETS_SPHERE,
+                                            )
+                                            .remove_flag(
+                                                CONSTANT_DATA_FLAGS_DISPLAY_MESHLETS_BOUNDING_BOX,
                                             )
-                                            .changed();
-                                    is_changed
-                                });
-                            if let Some(is_changed) = combo_box.inner {
-                                if is_changed {
-
-                                    let renderer = data.params.renderer.read().unwrap();
-                                    let render_context = renderer.render_context();
-                                    match &data.meshlet_debug {
-                                        MeshletDebug::None => {
-                                            render_context
-                                                .constant_data
-                                                .write()
-                                                .unwrap()
-                                                .remove_flag(CONSTANT_DATA_FLAGS_DISPLAY_MESHLETS)
-                                                .remove_flag(
-                                                    CONSTANT_DATA_FLAGS_DISPLAY_MESHLETS_SPHERE,
-                                                )
-                                                .remove_flag(
-                                                    CONSTANT_DATA_FLAGS_DISPLAY_MESHLETS_BOUNDING_BOX,
-                                                );
-                                        }
-                                        MeshletDebug::Color | MeshletDebug::ConeAxis => {
-                                            render_context
-                                                .constant_data
-                                                .write()
-                                                .unwrap()
-                                                .remove_flag(
-                                                    CONSTANT_DATA_FLAGS_DISPLAY_MESHLETS_SPHERE,
-                                                )
-                                                .remove_flag(
-                                                    CONSTANT_DATA_FLAGS_DISPLAY_MESHLETS_BOUNDING_BOX,
-                                                )
-                                                .add_flag(CONSTANT_DATA_FLAGS_DISPLAY_MESHLETS);
-                                        }
-                                        MeshletDebug::Sphere => {
-                                            render_context
-                                                .constant_data
-                                                .write()
-                                                .unwrap()
-                                                .remove_flag(
-                                                    CONSTANT_DATA_FLAGS_DISPLAY_MESHLETS_BOUNDING_BOX,
-                                                )
-                                                .add_flag(CONSTANT_DATA_FLAGS_DISPLAY_MESHLETS)
-                                                .add_flag(CONSTANT_DATA_FLAGS_DISPLAY_MESHLETS_SPHERE);
-                                        }
-                                        MeshletDebug::BoundingBox => {
-                                            render_context
-                                                .constant_data
-                                                .write()
-                                                .unwrap()
-                                                .remove_flag(
-                                                    CONSTANT_DATA_FLAGS_DISPLAY_MESHLETS_SPHERE,
-                                                )
-                                                .add_flag(CONSTANT_DATA_FLAGS_DISPLAY_MESHLETS)
-                                                .add_flag(
-                                                    CONSTANT_DATA_FLAGS_DISPLAY_MESHLETS_BOUNDING_BOX,
-                                                );
-                                        }
+                                            .add_flag(CONSTANT_DATA_FLAGS_DISPLAY_MESHLETS);
+                                    }
+                                    MeshletDebug::Sphere => {
+                                        render_context
+                                            .constant_data
+                                            .write()
+                                            .unwrap()
+                                            .remove_flag(
+                                                CONSTANT_DATA_FLAGS_DISPLAY_MESHLETS_BOUNDING_BOX,
+                                            )
+                                            .add_flag(CONSTANT_DATA_FLAGS_DISPLAY_MESHLETS)
+                                            .add_flag(CONSTANT_DATA_FLAGS_DISPLAY_MESHLETS_SPHERE);
+                                    }
+                                    MeshletDebug::BoundingBox => {
+                                        render_context
+                                            .constant_data
+                                            .write()
+                                            .unwrap()
+                                            .remove_flag(
+                                                CONSTANT_DATA_FLAGS_DISPLAY_MESHLETS_SPHERE,
+                                            )
+                                            .add_flag(CONSTANT_DATA_FLAGS_DISPLAY_MESHLETS)
+                                            .add_flag(
+                                                CONSTANT_DATA_FLAGS_DISPLAY_MESHLETS_BOUNDING_BOX,
+                                            );
                                     }
                                 }
                             }
-                        });
-                    })
-                {
+                        }
+                    });
+                }) {
                     return response.response.is_pointer_button_down_on();
                 }
             }
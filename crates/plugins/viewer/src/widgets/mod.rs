@@ -1,9 +1,17 @@
+pub use self::content_browser::*;
+pub use self::dock_space::*;
+pub use self::drop_target::*;
 pub use self::gfx::*;
 pub use self::hierarchy::*;
 pub use self::info::*;
+pub use self::save_scene_dialog::*;
 pub use self::view3d::*;
 
+pub mod content_browser;
+pub mod dock_space;
+pub mod drop_target;
 pub mod gfx;
 pub mod hierarchy;
 pub mod info;
+pub mod save_scene_dialog;
 pub mod view3d;
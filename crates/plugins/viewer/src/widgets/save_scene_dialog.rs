@@ -0,0 +1,231 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use inox_messenger::MessageHubRc;
+use inox_resources::{Data, Resource, SerializableResource, SharedDataRc};
+use inox_scene::Scene;
+use inox_ui::{
+    implement_widget_data, Align2, Button, ScrollArea, SelectableLabel, TextEdit, UIWidget, Ui,
+    Widget, Window,
+};
+
+use crate::events::WidgetEvent;
+
+const DEFAULT_SCENE_NAME: &str = "scene";
+const FOLDERS_HEIGHT: f32 = 200.;
+
+//Folders inside a folder of the root one, sorted by name
+pub fn list_folders(root: &Path, folder: &Path) -> Vec<String> {
+    let Ok(dir) = fs::read_dir(root.join(folder)) else {
+        return Vec::new();
+    };
+    let mut folders = dir
+        .filter_map(Result::ok)
+        .filter(|entry| entry.path().is_dir())
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .collect::<Vec<_>>();
+    folders.sort_by_key(|f| f.to_lowercase());
+    folders
+}
+
+//Scene file with the name in the folder, None for names that would place it in another folder
+pub fn scene_file_path(folder: &Path, name: &str) -> Option<PathBuf> {
+    let extension = format!(".{}", Scene::extension());
+    let name = name.trim();
+    let name = name.strip_suffix(extension.as_str()).unwrap_or(name);
+    if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\']) {
+        return None;
+    }
+    Some(folder.join(format!("{}{}", name, extension)))
+}
+
+#[derive(Clone)]
+struct SaveSceneDialogData {
+    message_hub: MessageHubRc,
+    data_folder: PathBuf,
+    //Relative to the data folder
+    folder: PathBuf,
+    folders: Vec<String>,
+    name: String,
+    is_open: bool,
+    is_changed: bool,
+}
+implement_widget_data!(SaveSceneDialogData);
+
+//Picks the folder of the data and the name to save the scene with
+pub struct SaveSceneDialog {
+    ui_page: Resource<UIWidget>,
+}
+
+unsafe impl Send for SaveSceneDialog {}
+unsafe impl Sync for SaveSceneDialog {}
+
+impl SaveSceneDialog {
+    pub fn new(shared_data: &SharedDataRc, message_hub: &MessageHubRc) -> Self {
+        let data = SaveSceneDialogData {
+            message_hub: message_hub.clone(),
+            data_folder: Data::platform_data_folder(),
+            folder: PathBuf::new(),
+            folders: Vec::new(),
+            name: DEFAULT_SCENE_NAME.to_string(),
+            is_open: false,
+            is_changed: true,
+        };
+        let ui_page = Self::create(shared_data, message_hub, data);
+        Self { ui_page }
+    }
+
+    //Starts from the folder and the name of the scene when it was loaded from the data folder
+    pub fn open(&self, scene_path: &Path) {
+        if let Some(data) = self.ui_page.get_mut().data_mut::<SaveSceneDialogData>() {
+            if let Ok(path) = scene_path.strip_prefix(data.data_folder.as_path()) {
+                data.folder = path.parent().map(Path::to_path_buf).unwrap_or_default();
+                data.name = path
+                    .file_stem()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_else(|| DEFAULT_SCENE_NAME.to_string());
+            }
+            data.is_open = true;
+            data.is_changed = true;
+        }
+    }
+
+    fn create(
+        shared_data: &SharedDataRc,
+        message_hub: &MessageHubRc,
+        data: SaveSceneDialogData,
+    ) -> Resource<UIWidget> {
+        UIWidget::register(shared_data, message_hub, data, |ui_data, ui_context| {
+            if let Some(data) = ui_data.as_any_mut().downcast_mut::<SaveSceneDialogData>() {
+                if !data.is_open {
+                    return false;
+                }
+                if data.is_changed {
+                    data.is_changed = false;
+                    data.folders = list_folders(data.data_folder.as_path(), data.folder.as_path());
+                }
+                let mut is_open = data.is_open;
+                if let Some(response) = Window::new("Save Scene As")
+                    .open(&mut is_open)
+                    .collapsible(false)
+                    .resizable(false)
+                    .anchor(Align2::CENTER_CENTER, [0., 0.])
+                    .show(ui_context, |ui| {
+                        Self::show_folders(ui, data);
+                        ui.separator();
+                        Self::show_name(ui, data);
+                    })
+                {
+                    data.is_open &= is_open;
+                    return response.response.is_pointer_button_down_on();
+                }
+                data.is_open &= is_open;
+            }
+            false
+        })
+    }
+
+    fn show_folders(ui: &mut Ui, data: &mut SaveSceneDialogData) {
+        ui.horizontal(|ui| {
+            let is_root = data.folder.as_os_str().is_empty();
+            if ui
+                .add_enabled(!is_root, Button::new("⬆"))
+                .on_hover_text("Parent folder")
+                .clicked()
+            {
+                data.folder.pop();
+                data.is_changed = true;
+            }
+            let root = data.data_folder.file_name().unwrap_or_default();
+            ui.label(
+                Path::new(root)
+                    .join(data.folder.as_path())
+                    .to_string_lossy(),
+            );
+        });
+        ScrollArea::vertical()
+            .max_height(FOLDERS_HEIGHT)
+            .show(ui, |ui| {
+                data.folders.clone().iter().for_each(|folder| {
+                    if SelectableLabel::new(false, format!("📁 {}", folder))
+                        .ui(ui)
+                        .double_clicked()
+                    {
+                        data.folder.push(folder);
+                        data.is_changed = true;
+                    }
+                });
+            });
+    }
+
+    fn show_name(ui: &mut Ui, data: &mut SaveSceneDialogData) {
+        let path = scene_file_path(data.folder.as_path(), data.name.as_str());
+        ui.horizontal(|ui| {
+            ui.label("Name");
+            TextEdit::singleline(&mut data.name).ui(ui);
+            ui.label(format!(".{}", Scene::extension()));
+        });
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(path.is_some(), Button::new("Save"))
+                .clicked()
+            {
+                if let Some(path) = &path {
+                    data.message_hub
+                        .send_event(WidgetEvent::SaveScene(data.data_folder.join(path)));
+                }
+                data.is_open = false;
+            }
+            if ui.button("Cancel").clicked() {
+                data.is_open = false;
+            }
+        });
+    }
+}
+
+#[allow(dead_code)]
+fn test_scene_file_path() {
+    let folder = Path::new("models").join("sponza");
+    let scene = |name: &str| folder.join(format!("{}.{}", name, Scene::extension()));
+    assert_eq!(scene_file_path(&folder, "edited"), Some(scene("edited")));
+    assert_eq!(scene_file_path(&folder, " edited "), Some(scene("edited")));
+    assert_eq!(
+        scene_file_path(&folder, format!("edited.{}", Scene::extension()).as_str()),
+        Some(scene("edited"))
+    );
+    assert_eq!(
+        scene_file_path(Path::new(""), "edited"),
+        Some(PathBuf::from(format!("edited.{}", Scene::extension())))
+    );
+    assert_eq!(scene_file_path(&folder, ""), None);
+    assert_eq!(scene_file_path(&folder, "  "), None);
+    assert_eq!(scene_file_path(&folder, "../edited"), None);
+    assert_eq!(scene_file_path(&folder, "levels/edited"), None);
+    assert_eq!(scene_file_path(&folder, ".scene"), None);
+}
+
+#[allow(dead_code)]
+fn test_list_folders() {
+    let root = std::env::temp_dir().join("inox_save_scene_dialog");
+    fs::remove_dir_all(root.as_path()).ok();
+    ["models/sponza", "models/Helmet", "levels"]
+        .iter()
+        .for_each(|f| fs::create_dir_all(root.join(f)).unwrap());
+    fs::write(root.join("models").join("readme.txt"), "").unwrap();
+
+    assert_eq!(list_folders(&root, Path::new("")), vec!["levels", "models"]);
+    assert_eq!(
+        list_folders(&root, Path::new("models")),
+        vec!["Helmet", "sponza"]
+    );
+    assert!(list_folders(&root, Path::new("missing")).is_empty());
+    fs::remove_dir_all(root.as_path()).ok();
+}
+
+#[test]
+fn test() {
+    test_scene_file_path();
+    test_list_folders();
+}
@@ -1,34 +1,97 @@
 use inox_graphics::{RenderPass, BLIT_PASS_NAME};
 
 use inox_messenger::MessageHubRc;
+use inox_platform::{MouseEvent, MouseState};
 use inox_resources::{Resource, SharedData, SharedDataRc};
 use inox_ui::{
-    implement_widget_data, CentralPanel, Image, LayerId, Sense, TextureId as eguiTextureId,
+    implement_widget_data, Area, DockRw, Id, Image, Order, Rect, Sense, TextureId as eguiTextureId,
     UIWidget, Widget,
 };
 
+use crate::widgets::VIEW_PANEL;
+
+//Area of the window where the scene is shown, in pixels
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub struct Viewport {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Viewport {
+    pub fn from_rect(rect: Rect, pixels_per_point: f32) -> Self {
+        Self {
+            x: rect.min.x * pixels_per_point,
+            y: rect.min.y * pixels_per_point,
+            width: rect.width() * pixels_per_point,
+            height: rect.height() * pixels_per_point,
+        }
+    }
+    pub fn size(&self) -> (u32, u32) {
+        (self.width.round() as _, self.height.round() as _)
+    }
+    pub fn contains(&self, x: f64, y: f64) -> bool {
+        let (x, y) = (x as f32, y as f32);
+        x >= self.x && y >= self.y && x < self.x + self.width && y < self.y + self.height
+    }
+    //Positions become relative to the viewport and movements are normalized by its size,
+    //so the camera turns the same whatever the size of the panel
+    pub fn map_mouse_event(&self, event: &MouseEvent) -> MouseEvent {
+        let width = self.width.max(1.);
+        let height = self.height.max(1.);
+        match event.state {
+            MouseState::Wheel => *event,
+            MouseState::RelativeMove => MouseEvent {
+                normalized_x: event.x as f32 / width,
+                normalized_y: event.y as f32 / height,
+                ..*event
+            },
+            _ => {
+                let x = event.x - self.x as f64;
+                let y = event.y - self.y as f64;
+                MouseEvent {
+                    x,
+                    y,
+                    normalized_x: x as f32 / width,
+                    normalized_y: y as f32 / height,
+                    ..*event
+                }
+            }
+        }
+    }
+}
+
 #[derive(Clone)]
 struct View3DData {
     shared_data: SharedDataRc,
+    dock: DockRw,
+    viewport: Option<Viewport>,
     is_interacting: bool,
 }
 implement_widget_data!(View3DData);
 
 pub struct View3D {
     _ui_page: Resource<UIWidget>,
+    size: (u32, u32),
 }
 
 unsafe impl Send for View3D {}
 unsafe impl Sync for View3D {}
 
 impl View3D {
-    pub fn new(shared_data: &SharedDataRc, message_hub: &MessageHubRc) -> Self {
+    pub fn new(shared_data: &SharedDataRc, message_hub: &MessageHubRc, dock: &DockRw) -> Self {
         let data = View3DData {
             shared_data: shared_data.clone(),
+            dock: dock.clone(),
+            viewport: None,
             is_interacting: false,
         };
         let ui_page = Self::create(shared_data, message_hub, data);
-        Self { _ui_page: ui_page }
+        Self {
+            _ui_page: ui_page,
+            size: (0, 0),
+        }
     }
 
     pub fn is_interacting(&self) -> bool {
@@ -38,6 +101,23 @@ impl View3D {
             false
         }
     }
+    //None while the view panel is in a hidden tab
+    pub fn viewport(&self) -> Option<Viewport> {
+        self._ui_page
+            .get()
+            .data::<View3DData>()
+            .and_then(|data| data.viewport)
+    }
+    //Viewport with its new size once after it has been resized
+    pub fn take_resized(&mut self) -> Option<Viewport> {
+        let viewport = self.viewport()?;
+        let size = viewport.size();
+        if size == self.size || size.0 == 0 || size.1 == 0 {
+            return None;
+        }
+        self.size = size;
+        Some(viewport)
+    }
 
     fn create(
         shared_data: &SharedDataRc,
@@ -46,27 +126,26 @@ impl View3D {
     ) -> Resource<UIWidget> {
         UIWidget::register(shared_data, message_hub, data, |ui_data, ui_context| {
             if let Some(data) = ui_data.as_any_mut().downcast_mut::<View3DData>() {
-                let response = CentralPanel::default().show(ui_context, |ui| {
-                    let view_width = ui.max_rect().width() as u32;
-                    let view_height = ui.max_rect().height() as u32;
-
-                    let texture_uniform_index =
-                        Self::get_render_pass_texture_index(&data.shared_data, 0, BLIT_PASS_NAME);
-
-                    let response = ui.with_layer_id(LayerId::background(), |ui| {
-                        let response = Image::new(
-                            eguiTextureId::User(texture_uniform_index as _),
-                            [view_width as _, view_height as _],
-                        )
-                        .sense(Sense::click_and_drag())
-                        .ui(ui);
-                        data.is_interacting = response.is_pointer_button_down_on();
-                        response
+                data.is_interacting = false;
+                let Some(rect) = data.dock.read().unwrap().panel_rect(VIEW_PANEL) else {
+                    data.viewport = None;
+                    return false;
+                };
+                data.viewport = Some(Viewport::from_rect(rect, ui_context.pixels_per_point()));
+
+                let texture_uniform_index =
+                    Self::get_render_pass_texture_index(&data.shared_data, 0, BLIT_PASS_NAME);
+
+                //Behind the other panels, to not cover a window dragged over the view
+                let response = Area::new(Id::new("View3D"))
+                    .order(Order::Background)
+                    .fixed_pos(rect.min)
+                    .show(ui_context, |ui| {
+                        Image::new(eguiTextureId::User(texture_uniform_index as _), rect.size())
+                            .sense(Sense::click_and_drag())
+                            .ui(ui)
                     });
-                    data.is_interacting |= response.response.is_pointer_button_down_on();
-                    response
-                });
-                data.is_interacting |= response.response.is_pointer_button_down_on();
+                data.is_interacting = response.inner.is_pointer_button_down_on();
                 return data.is_interacting;
             }
             false
@@ -90,3 +169,52 @@ impl View3D {
         0
     }
 }
+
+#[allow(dead_code)]
+fn test_viewport() {
+    use inox_platform::MouseButton;
+
+    let rect = Rect::from_min_max([100., 50.].into(), [300., 150.].into());
+    let viewport = Viewport::from_rect(rect, 2.);
+    assert_eq!(
+        viewport,
+        Viewport {
+            x: 200.,
+            y: 100.,
+            width: 400.,
+            height: 200.,
+        }
+    );
+    assert_eq!(viewport.size(), (400, 200));
+    assert!(viewport.contains(200., 100.));
+    assert!(viewport.contains(599., 299.));
+    assert!(!viewport.contains(600., 150.));
+    assert!(!viewport.contains(150., 150.));
+
+    let down = MouseEvent {
+        x: 400.,
+        y: 150.,
+        normalized_x: 0.5,
+        normalized_y: 0.5,
+        button: MouseButton::Right,
+        state: MouseState::Down,
+    };
+    let mapped = viewport.map_mouse_event(&down);
+    assert_eq!((mapped.x, mapped.y), (200., 50.));
+    assert_eq!((mapped.normalized_x, mapped.normalized_y), (0.5, 0.25));
+    assert_eq!(mapped.button, MouseButton::Right);
+
+    //Movements keep their size in pixels, only their normalized values change
+    let relative_move = MouseEvent::relative_move(40., 20., 800., 400.);
+    let mapped = viewport.map_mouse_event(&relative_move);
+    assert_eq!((mapped.x, mapped.y), (40., 20.));
+    assert_eq!((mapped.normalized_x, mapped.normalized_y), (0.1, 0.1));
+
+    let wheel = MouseEvent::wheel(1.);
+    assert_eq!(viewport.map_mouse_event(&wheel), wheel);
+}
+
+#[test]
+fn test() {
+    test_viewport();
+}
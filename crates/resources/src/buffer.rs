@@ -1,7 +1,7 @@
 use std::ops::Range;
 
 use crate::ResourceId;
-use inox_uid::{generate_random_uid, INVALID_UID};
+use inox_uid::{generate_random_resource_uid, INVALID_RESOURCE_UID};
 
 pub fn to_slice_mut<T: Sized, U: Sized>(a: &mut [T]) -> &mut [U] {
     if a.is_empty() {
@@ -43,7 +43,7 @@ pub struct BufferData {
 impl Default for BufferData {
     fn default() -> Self {
         Self {
-            id: INVALID_UID,
+            id: INVALID_RESOURCE_UID,
             range: 0..0,
         }
     }
@@ -92,11 +92,39 @@ impl BufferData {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BufferError {
+    Overflow {
+        id: ResourceId,
+        requested: usize,
+        max_len: usize,
+    },
+}
+
+impl std::fmt::Display for BufferError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BufferError::Overflow {
+                id,
+                requested,
+                max_len,
+            } => {
+                write!(
+                    f,
+                    "Unable to allocate {requested} items for {id:?} in a buffer limited to {max_len}"
+                )
+            }
+        }
+    }
+}
+
 pub struct Buffer<T> {
     occupied: Vec<BufferData>,
     free: Vec<BufferData>,
     data: Vec<T>,
     is_changed: bool,
+    //Unbounded when None
+    max_len: Option<usize>,
 }
 
 impl<T> Default for Buffer<T> {
@@ -106,6 +134,7 @@ impl<T> Default for Buffer<T> {
             free: Vec::new(),
             data: Vec::new(),
             is_changed: false,
+            max_len: None,
         }
     }
 }
@@ -120,6 +149,39 @@ where
     pub fn mark_as_changed(&mut self, is_changed: bool) {
         self.is_changed = is_changed;
     }
+    pub fn max_len(&self) -> Option<usize> {
+        self.max_len
+    }
+    //Limits the items the buffer can hold, only try_allocate checks it
+    pub fn set_max_len(&mut self, max_len: Option<usize>) -> &mut Self {
+        self.max_len = max_len;
+        self
+    }
+    //Like allocate but fails, without changing the buffer, when the data would make it
+    //grow past its max len
+    pub fn try_allocate(
+        &mut self,
+        id: &ResourceId,
+        data: &[T],
+    ) -> Result<(bool, Range<usize>), BufferError> {
+        if let Some(max_len) = self.max_len {
+            let size = data.len();
+            let fits = |d: &BufferData| (d.range.end + 1 - d.range.start) >= size;
+            //Merging the free ranges doesn't move the occupied ones
+            self.collapse_free();
+            if self.data.len() + size > max_len
+                && !self.free.iter().any(fits)
+                && !self.get(id).is_some_and(fits)
+            {
+                return Err(BufferError::Overflow {
+                    id: *id,
+                    requested: size,
+                    max_len,
+                });
+            }
+        }
+        Ok(self.allocate(id, data))
+    }
     pub fn allocate(&mut self, id: &ResourceId, data: &[T]) -> (bool, Range<usize>) {
         self.remove(id);
         self.collapse_free();
@@ -134,7 +196,7 @@ where
             let free_data = self.free.remove(index);
             if (free_data.range.end + 1 - free_data.range.start) > size {
                 self.free.push(BufferData::new(
-                    &generate_random_uid(),
+                    &generate_random_resource_uid(),
                     free_data.range.start + size,
                     free_data.range.end,
                 ));
@@ -386,7 +448,7 @@ fn test_buffer() {
     impl Mesh {
         fn new() -> Self {
             Self {
-                id: generate_random_uid(),
+                id: generate_random_resource_uid(),
                 data: Vec::new(),
             }
         }
@@ -539,7 +601,35 @@ fn test_buffer() {
     assert!(buffer.is_full(), "Allocator should be full now");
 }
 
+#[allow(dead_code)]
+fn test_max_len() {
+    let mut buffer = Buffer::<u32>::default();
+    buffer.set_max_len(Some(8));
+    let first = generate_random_resource_uid();
+    let second = generate_random_resource_uid();
+    assert!(buffer.try_allocate(&first, &[1, 2, 3, 4, 5, 6]).is_ok());
+    assert_eq!(
+        buffer.try_allocate(&second, &[7, 8, 9]),
+        Err(BufferError::Overflow {
+            id: second,
+            requested: 3,
+            max_len: 8
+        })
+    );
+    assert_eq!(buffer.items(&first), Some([1, 2, 3, 4, 5, 6].as_slice()));
+    assert!(buffer.get(&second).is_none());
+    assert_eq!(buffer.total_len(), 6);
+
+    //The ranges already allocated can be reused
+    assert!(buffer.try_allocate(&first, &[10, 11]).is_ok());
+    assert_eq!(buffer.items(&first), Some([10, 11].as_slice()));
+    assert!(buffer.try_allocate(&second, &[7, 8, 9]).is_ok());
+    assert_eq!(buffer.items(&second), Some([7, 8, 9].as_slice()));
+    assert_eq!(buffer.total_len(), 6);
+}
+
 #[test]
 fn test() {
     test_buffer();
+    test_max_len();
 }
@@ -1,8 +1,12 @@
-use std::path::PathBuf;
+use std::{marker::PhantomData, path::PathBuf};
 
 use inox_commands::CommandParser;
 use inox_filesystem::convert_from_local_path;
-use inox_messenger::implement_message;
+use inox_messenger::{implement_message, MessageHubRc};
+use inox_platform::{FileEvent, FileWatcher};
+use inox_serialize::{
+    inox_serializable::SerializableRegistryRc, read_from_file, Deserialize, SerializeFile,
+};
 
 use crate::Data;
 
@@ -31,6 +35,7 @@ where
     T: ConfigBase,
 {
     Loaded(String, T),
+    Reloaded(String, T),
 }
 implement_message!(
     ConfigEvent<ConfigBase>,
@@ -51,6 +56,13 @@ where
                 Self::Loaded(other_filename, other_data) => {
                     filename == other_filename && data == other_data
                 }
+                _ => false,
+            },
+            Self::Reloaded(filename, data) => match other {
+                Self::Reloaded(other_filename, other_data) => {
+                    filename == other_filename && data == other_data
+                }
+                _ => false,
             },
         }
     }
@@ -58,3 +70,69 @@ where
         None
     }
 }
+
+pub(crate) trait ConfigWatcherTrait: Send + Sync {
+    fn filepath(&self) -> PathBuf;
+    fn stop(&mut self);
+    fn update(&self, registry: &SerializableRegistryRc, message_hub: &MessageHubRc);
+}
+
+pub(crate) struct ConfigWatcher<T>
+where
+    T: ConfigBase,
+{
+    filewatcher: FileWatcher,
+    filename: String,
+    _marker: PhantomData<T>,
+}
+
+impl<T> ConfigWatcher<T>
+where
+    T: ConfigBase,
+{
+    pub(crate) fn new(filepath: PathBuf, filename: &str) -> Self {
+        Self {
+            filewatcher: FileWatcher::new(filepath),
+            filename: filename.to_string(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> ConfigWatcherTrait for ConfigWatcher<T>
+where
+    T: ConfigBase + SerializeFile + for<'de> Deserialize<'de>,
+{
+    fn filepath(&self) -> PathBuf {
+        self.filewatcher.get_path()
+    }
+    fn stop(&mut self) {
+        self.filewatcher.stop();
+    }
+    fn update(&self, registry: &SerializableRegistryRc, message_hub: &MessageHubRc) {
+        let mut is_changed = false;
+        while let Ok(event) = self.filewatcher.read_events().try_recv() {
+            match event {
+                FileEvent::Modified(path)
+                | FileEvent::Created(path)
+                | FileEvent::RenamedTo(path) => {
+                    is_changed |= self.filewatcher.get_path().eq(&path);
+                }
+                _ => {}
+            }
+        }
+        if is_changed {
+            //A config that fails to parse is only logged by read_from_file:
+            //no event is sent and listeners keep the last good config
+            let filename = self.filename.clone();
+            let message_hub = message_hub.clone();
+            read_from_file(
+                self.filewatcher.get_path().as_path(),
+                registry,
+                Box::new(move |data: T| {
+                    message_hub.send_event(ConfigEvent::Reloaded(filename.clone(), data));
+                }),
+            );
+        }
+    }
+}
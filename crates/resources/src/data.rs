@@ -5,11 +5,11 @@ use inox_messenger::MessageHubRc;
 
 use inox_log::debug_log;
 use inox_serialize::inox_serializable::SerializableRegistryRc;
-use inox_uid::generate_uid_from_string;
+use inox_uid::generate_resource_uid_from_string;
 
 use crate::{
-    DataTypeResourceEvent, OnCreateData, Resource, ResourceEvent, ResourceId, ResourceTrait,
-    SerializableResourceEvent, SharedData, SharedDataRc,
+    with_load_groups, DataTypeResourceEvent, OnCreateData, PendingLoad, Resource, ResourceEvent,
+    ResourceId, ResourceTrait, SerializableResourceEvent, SharedData, SharedDataRc,
 };
 
 pub const DATA_RAW_FOLDER: &str = "data_raw";
@@ -121,19 +121,28 @@ pub trait SerializableResource: DataTypeResource + Sized + Clone {
         let cloned_shared_data = shared_data.clone();
         let cloned_message_hub = message_hub.clone();
         let cloned_path = path.clone();
+        let resource_id = generate_resource_uid_from_string(path.as_path().to_str().unwrap());
+        let mut pending_load = PendingLoad::new(shared_data, message_hub, &resource_id);
         Self::deserialize_data(
             path.as_path(),
             shared_data.serializable_registry(),
             Box::new(move |data| {
-                let resource_id = generate_uid_from_string(cloned_path.as_path().to_str().unwrap());
-                let resource = Self::new_resource(
-                    &cloned_shared_data,
-                    &cloned_message_hub,
-                    resource_id,
-                    &data,
-                    on_create_data.take(),
-                );
+                //Resources requested while creating this one are added to its same load groups
+                let load_groups = cloned_shared_data.load_groups_of(&resource_id);
+                let mut resource = None;
+                with_load_groups(load_groups, || {
+                    resource = Some(Self::new_resource(
+                        &cloned_shared_data,
+                        &cloned_message_hub,
+                        resource_id,
+                        &data,
+                        on_create_data.take(),
+                    ));
+                });
+                let resource = resource.unwrap();
                 resource.get_mut().set_path(cloned_path.as_path());
+                pending_load.set_loaded();
+                cloned_shared_data.on_resource_loaded(&resource_id, &cloned_message_hub);
                 cloned_message_hub
                     .send_event(DataTypeResourceEvent::<Self>::Loaded(resource_id, data));
                 if crate::DEBUG_RESOURCES {
@@ -166,15 +175,19 @@ pub trait SerializableResource: DataTypeResource + Sized + Clone {
                 filepath
             );
         }
-        let resource_id = generate_uid_from_string(path.as_path().to_str().unwrap());
+        let resource_id = generate_resource_uid_from_string(path.as_path().to_str().unwrap());
         if SharedData::has::<Self>(shared_data, &resource_id) {
-            return SharedData::get_resource::<Self>(shared_data, &resource_id).unwrap();
+            let resource = SharedData::get_resource::<Self>(shared_data, &resource_id).unwrap();
+            shared_data.add_to_current_load_groups(&resource, message_hub);
+            return resource;
         }
         let resource = shared_data.add_resource(
             message_hub,
             resource_id,
             Self::new(resource_id, shared_data, message_hub),
         );
+        shared_data.mark_as_loading(&resource_id);
+        shared_data.add_to_current_load_groups(&resource, message_hub);
         message_hub.send_event(SerializableResourceEvent::<Self>::Load(
             path,
             on_create_data,
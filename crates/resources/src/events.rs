@@ -14,6 +14,8 @@ where
     Created(Resource<T>),
     Changed(ResourceId),
     Destroyed(ResourceId),
+    //The resource exists but can't be used, with the reason
+    Failed(ResourceId, String),
 }
 
 implement_message!(
@@ -43,6 +45,10 @@ where
                 Self::Destroyed(other_id) => id == other_id,
                 _ => false,
             },
+            Self::Failed(id, _) => match other {
+                Self::Failed(other_id, _) => id == other_id,
+                _ => false,
+            },
         }
     }
 
@@ -143,3 +149,38 @@ impl ReloadEvent {
         None
     }
 }
+
+//Asks the binarizer to compile a raw file that could be outside of the data raw folder.
+//The folder is where the file references are resolved from, like the textures of a gltf
+#[derive(Clone)]
+pub enum BinarizeEvent {
+    Binarize(PathBuf, PathBuf),
+}
+implement_message!(
+    BinarizeEvent,
+    message_from_command_parser,
+    compare_and_discard
+);
+
+impl BinarizeEvent {
+    fn compare_and_discard(&self, other: &Self) -> bool {
+        match self {
+            Self::Binarize(path, _folder) => match other {
+                Self::Binarize(other_path, _other_folder) => path == other_path,
+            },
+        }
+    }
+    fn message_from_command_parser(command_parser: CommandParser) -> Option<Self> {
+        if command_parser.has("binarize_file") {
+            let values = command_parser.get_values_of::<String>("binarize_file");
+            let path = PathBuf::from(values[0].as_str());
+            let folder = values
+                .get(1)
+                .map(|f| PathBuf::from(f.as_str()))
+                .or_else(|| path.parent().map(|p| p.to_path_buf()))
+                .unwrap_or_default();
+            return Some(BinarizeEvent::Binarize(path, folder));
+        }
+        None
+    }
+}
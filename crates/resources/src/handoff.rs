@@ -0,0 +1,196 @@
+use std::{any::type_name, collections::HashMap};
+
+use inox_uid::{generate_uid_from_string, Uid};
+
+use crate::{DataTypeResource, GenericResource, ResourceId, ResourceStorageRw};
+
+//A resource that can survive the reload of the plugin that registers its type:
+//its data is serialized when the type is unregistered during a handoff
+//and it's recreated from that data when the type is registered again
+pub trait HandoffResource: DataTypeResource {
+    fn handoff_data(&self) -> Self::DataType;
+}
+
+#[derive(Default)]
+pub struct ResourcesHandoff {
+    serialized: HashMap<Uid, (String, HashMap<ResourceId, String>)>,
+    restored: Vec<GenericResource>,
+    storages: HashMap<Uid, (String, ResourceStorageRw)>,
+}
+
+impl ResourcesHandoff {
+    pub fn store<T>(&mut self, resources: HashMap<ResourceId, String>)
+    where
+        T: HandoffResource + 'static,
+    {
+        if resources.is_empty() {
+            return;
+        }
+        let typeid = generate_uid_from_string(type_name::<T>());
+        self.serialized
+            .insert(typeid, (type_name::<T>().to_string(), resources));
+    }
+    pub fn take<T>(&mut self) -> HashMap<ResourceId, String>
+    where
+        T: HandoffResource + 'static,
+    {
+        let typeid = generate_uid_from_string(type_name::<T>());
+        self.serialized
+            .remove(&typeid)
+            .map(|(_, resources)| resources)
+            .unwrap_or_default()
+    }
+    //Restored resources are kept alive until the handoff ends,
+    //to give the reloaded systems the time to take them
+    pub fn hold(&mut self, resource: GenericResource) {
+        self.restored.push(resource);
+    }
+    //The storage of a host type not serialized is kept as it is,
+    //with the resources the rest of the engine may still be referencing.
+    //Storages of plugin types never get here: their code is unloaded with the plugin
+    pub fn retain_storage<T>(&mut self, storage: ResourceStorageRw)
+    where
+        T: 'static,
+    {
+        let typeid = generate_uid_from_string(type_name::<T>());
+        self.storages
+            .insert(typeid, (type_name::<T>().to_string(), storage));
+    }
+    pub fn take_storage<T>(&mut self) -> Option<ResourceStorageRw>
+    where
+        T: 'static,
+    {
+        let typeid = generate_uid_from_string(type_name::<T>());
+        self.storages.remove(&typeid).map(|(_, storage)| storage)
+    }
+    pub fn pending_types(&self) -> Vec<(&str, usize)> {
+        self.serialized
+            .values()
+            .map(|(name, resources)| (name.as_str(), resources.len()))
+            .chain(
+                self.storages
+                    .values()
+                    .map(|(name, storage)| (name.as_str(), storage.read().unwrap().count())),
+            )
+            .collect()
+    }
+    pub fn clear_storages(&mut self) {
+        self.storages.drain().for_each(|(_, (_, storage))| {
+            storage.write().unwrap().remove_all();
+        });
+    }
+}
+
+#[allow(dead_code)]
+fn test_handoff() {
+    use crate::{DataTypeResource, ResourceTrait, SerializableResource, SharedData, SharedDataRc};
+    use inox_messenger::{MessageHub, MessageHubRc};
+    use inox_serialize::{inox_serializable::SerializableRegistryRc, Deserialize, Serialize};
+    use inox_uid::generate_random_resource_uid;
+    use std::{
+        path::{Path, PathBuf},
+        sync::Arc,
+    };
+
+    #[derive(Default, Serialize, Deserialize, Clone)]
+    #[serde(crate = "inox_serialize")]
+    struct TestData {
+        value: u32,
+    }
+    #[derive(Clone)]
+    struct TestResource {
+        path: PathBuf,
+        value: u32,
+    }
+    impl ResourceTrait for TestResource {
+        fn is_initialized(&self) -> bool {
+            true
+        }
+        fn invalidate(&mut self) -> &mut Self {
+            self
+        }
+    }
+    impl DataTypeResource for TestResource {
+        type DataType = TestData;
+        fn new(_id: ResourceId, _shared_data: &SharedDataRc, _message_hub: &MessageHubRc) -> Self {
+            Self {
+                path: PathBuf::new(),
+                value: 0,
+            }
+        }
+        fn create_from_data(
+            shared_data: &SharedDataRc,
+            message_hub: &MessageHubRc,
+            id: ResourceId,
+            data: &Self::DataType,
+        ) -> Self {
+            let mut resource = Self::new(id, shared_data, message_hub);
+            resource.value = data.value;
+            resource
+        }
+    }
+    impl SerializableResource for TestResource {
+        fn set_path(&mut self, path: &Path) -> &mut Self {
+            self.path = path.to_path_buf();
+            self
+        }
+        fn path(&self) -> &Path {
+            self.path.as_path()
+        }
+        fn extension() -> &'static str {
+            "test"
+        }
+        fn deserialize_data(
+            _path: &Path,
+            _registry: &SerializableRegistryRc,
+            _f: Box<dyn FnMut(Self::DataType) + 'static>,
+        ) {
+        }
+    }
+    impl HandoffResource for TestResource {
+        fn handoff_data(&self) -> Self::DataType {
+            TestData { value: self.value }
+        }
+    }
+
+    let shared_data = Arc::new(SharedData::default());
+    let message_hub = Arc::new(MessageHub::default());
+    shared_data.register_type_serializable_with_handoff::<TestResource>(&message_hub);
+
+    let id = generate_random_resource_uid();
+    let resource = shared_data.add_resource(
+        &message_hub,
+        id,
+        TestResource::create_from_data(&shared_data, &message_hub, id, &TestData { value: 42 }),
+    );
+
+    //Without an active handoff resources are simply dropped with their type
+    shared_data.unregister_type_serializable_with_handoff::<TestResource>(&message_hub);
+    shared_data.register_type_serializable_with_handoff::<TestResource>(&message_hub);
+    assert!(!shared_data.has::<TestResource>(&id));
+
+    let resource = shared_data.add_resource(&message_hub, id, resource.get().clone());
+    shared_data.begin_handoff();
+    shared_data.unregister_type_serializable_with_handoff::<TestResource>(&message_hub);
+    drop(resource);
+    shared_data.register_type_serializable_with_handoff::<TestResource>(&message_hub);
+
+    //Restored resources survive flushes until the handoff ends
+    shared_data.flush_resources(&message_hub);
+    let restored = shared_data.get_resource::<TestResource>(&id).unwrap();
+    assert_eq!(restored.get().value, 42);
+    shared_data.end_handoff();
+    assert!(!shared_data.is_handoff_active());
+    shared_data.flush_resources(&message_hub);
+    assert!(shared_data.has::<TestResource>(&id));
+    drop(restored);
+    shared_data.flush_resources(&message_hub);
+    assert!(!shared_data.has::<TestResource>(&id));
+
+    shared_data.unregister_type_serializable_with_handoff::<TestResource>(&message_hub);
+}
+
+#[test]
+fn test() {
+    test_handoff();
+}
@@ -1,6 +1,9 @@
 use std::collections::HashMap;
 use std::hash::Hash;
 
+//Indices are stable: an item keeps its index until it's removed or explicitly moved.
+//Removed slots are left empty and reused by the next inserts, lowest index first,
+//so the indices of the other items never change. Only collapse compacts the buffer.
 pub struct HashBuffer<Id, Data, const MAX_COUNT: usize>
 where
     Id: Eq + Hash + Copy,
@@ -60,17 +63,28 @@ where
         self.is_empty.truncate(index);
     }
     fn new_index(&self) -> usize {
-        if self.map.is_empty() {
-            return 0;
+        self.is_empty
+            .iter()
+            .position(|is_empty| *is_empty)
+            .unwrap_or(self.buffer.len())
+    }
+    //Makes room for additional items so that inserting them doesn't reallocate.
+    //Buffers with a fixed MAX_COUNT are already allocated
+    pub fn reserve(&mut self, additional: usize) {
+        let free_slots = self.is_empty.iter().filter(|is_empty| **is_empty).count();
+        self.map.reserve(additional);
+        if MAX_COUNT == 0 && additional > free_slots {
+            self.buffer.reserve(additional - free_slots);
+            self.is_empty.reserve(additional - free_slots);
         }
-        for i in 0..self.buffer.len() {
-            if self.map.iter().any(|(_id, &index)| index == i) {
-                continue;
-            } else {
-                return i;
-            }
+    }
+    //Number of items that can be stored without reallocating
+    pub fn capacity(&self) -> usize {
+        if MAX_COUNT == 0 {
+            self.buffer.capacity()
+        } else {
+            MAX_COUNT
         }
-        self.buffer.len()
     }
     pub fn insert(&mut self, id: &Id, data: Data) -> usize {
         self.is_changed = true;
@@ -133,8 +147,20 @@ where
         if MAX_COUNT == 0 {
             self.buffer.clear();
             self.is_empty.clear();
+        } else {
+            self.is_empty.fill(true);
         }
     }
+    //Gives each item to f with its index and leaves the buffer empty, keeping its capacity
+    pub fn drain(&mut self, mut f: impl FnMut(&Id, usize, Data)) {
+        if !self.map.is_empty() {
+            self.is_changed = true;
+        }
+        self.map.drain().for_each(|(id, index)| {
+            f(&id, index, std::mem::take(&mut self.buffer[index]));
+        });
+        self.clear();
+    }
     pub fn index_of(&self, id: &Id) -> Option<usize> {
         self.map.get(id).copied()
     }
@@ -183,14 +209,22 @@ where
             }
         });
     }
+    pub fn for_each_entry_mut(&mut self, mut f: impl FnMut(usize, &mut Data)) {
+        let is_empty = &self.is_empty;
+        self.buffer.iter_mut().enumerate().for_each(|(i, d)| {
+            if !is_empty[i] {
+                f(i, d);
+            }
+        });
+    }
 }
 
 #[allow(dead_code)]
 fn test_resource_indexer<const SIZE: usize>() {
     let mut indexer = HashBuffer::<crate::ResourceId, u32, SIZE>::default();
-    let id1 = inox_uid::generate_random_uid();
-    let id2 = inox_uid::generate_random_uid();
-    let id3 = inox_uid::generate_random_uid();
+    let id1 = inox_uid::generate_random_resource_uid();
+    let id2 = inox_uid::generate_random_resource_uid();
+    let id3 = inox_uid::generate_random_resource_uid();
     indexer.insert(&id1, 100);
     assert_eq!(indexer.index_of(&id1), Some(0));
     assert_eq!(indexer.get(&id1), Some(&100));
@@ -237,8 +271,66 @@ fn test_resource_indexer<const SIZE: usize>() {
     assert_eq!(indexer.get(&id3), Some(&300));
 }
 
+#[allow(dead_code)]
+fn test_stable_indices<const SIZE: usize>() {
+    let mut indexer = HashBuffer::<crate::ResourceId, u32, SIZE>::default();
+    let ids = [
+        inox_uid::generate_random_resource_uid(),
+        inox_uid::generate_random_resource_uid(),
+        inox_uid::generate_random_resource_uid(),
+        inox_uid::generate_random_resource_uid(),
+    ];
+    indexer.insert(&ids[0], 0);
+    indexer.insert(&ids[1], 10);
+    indexer.insert(&ids[2], 20);
+    indexer.remove(&ids[1]);
+    indexer.remove(&ids[0]);
+    assert_eq!(indexer.index_of(&ids[2]), Some(2));
+    //Freed slots are reused starting from the lowest one
+    assert_eq!(indexer.insert(&ids[3], 30), 0);
+    assert_eq!(indexer.insert(&ids[1], 10), 1);
+    assert_eq!(indexer.index_of(&ids[2]), Some(2));
+    assert_eq!(indexer.get(&ids[2]), Some(&20));
+
+    indexer.for_each_entry_mut(|i, d| *d += i as u32);
+    let mut entries = Vec::new();
+    indexer.for_each_entry(|i, d| entries.push((i, *d)));
+    assert_eq!(entries, vec![(0, 30), (1, 11), (2, 22)]);
+
+    let mut drained = Vec::new();
+    indexer.drain(|id, i, d| drained.push((*id, i, d)));
+    drained.sort_by_key(|(_, i, _)| *i);
+    assert_eq!(
+        drained,
+        vec![(ids[3], 0, 30), (ids[1], 1, 11), (ids[2], 2, 22)]
+    );
+    assert!(indexer.is_empty());
+    assert_eq!(indexer.insert(&ids[0], 0), 0);
+}
+
+#[allow(dead_code)]
+fn test_reserve() {
+    const COUNT: usize = 64;
+    let mut indexer = HashBuffer::<crate::ResourceId, u32, 0>::default();
+    indexer.insert(&inox_uid::generate_random_resource_uid(), 0);
+    indexer.reserve(COUNT);
+    assert!(indexer.capacity() > COUNT);
+    let data = indexer.data().as_ptr();
+    for i in 0..COUNT {
+        indexer.insert(&inox_uid::generate_random_resource_uid(), i as _);
+    }
+    assert_eq!(indexer.data().as_ptr(), data);
+    assert_eq!(indexer.item_count(), COUNT + 1);
+
+    let fixed = HashBuffer::<crate::ResourceId, u32, 8>::default();
+    assert_eq!(fixed.capacity(), 8);
+}
+
 #[test]
 fn test() {
     test_resource_indexer::<3>();
     test_resource_indexer::<0>();
+    test_stable_indices::<4>();
+    test_stable_indices::<0>();
+    test_reserve();
 }
@@ -7,6 +7,8 @@ pub use crate::config::*;
 pub use crate::data::*;
 pub use crate::event_handlers::*;
 pub use crate::events::*;
+pub use crate::handoff::*;
+pub use crate::load_group::*;
 pub use crate::resource::*;
 pub use crate::shared_data::*;
 pub use crate::singleton::*;
@@ -19,6 +21,8 @@ pub mod config;
 pub mod data;
 pub mod event_handlers;
 pub mod events;
+pub mod handoff;
+pub mod load_group;
 pub mod platform;
 pub mod resource;
 pub mod shared_data;
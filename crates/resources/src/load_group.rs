@@ -0,0 +1,235 @@
+use std::{cell::RefCell, path::Path};
+
+use inox_commands::CommandParser;
+use inox_messenger::{implement_message, MessageHubRc};
+use inox_uid::Uid;
+
+use crate::{
+    DataTypeResource, GenericResource, OnCreateData, Resource, ResourceId, SerializableResource,
+    SharedDataRc,
+};
+
+pub type LoadGroupId = Uid;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LoadGroupEvent {
+    Progress(LoadGroupId, usize, usize),
+    Completed(LoadGroupId),
+    Cancelled(LoadGroupId),
+}
+implement_message!(LoadGroupEvent, compare_and_discard);
+
+impl LoadGroupEvent {
+    fn compare_and_discard(&self, other: &Self) -> bool {
+        match self {
+            Self::Progress(id, _, _) => match other {
+                Self::Progress(other_id, _, _) => id == other_id,
+                _ => false,
+            },
+            Self::Completed(id) => match other {
+                Self::Completed(other_id) => id == other_id,
+                _ => false,
+            },
+            Self::Cancelled(id) => match other {
+                Self::Cancelled(other_id) => id == other_id,
+                _ => false,
+            },
+        }
+    }
+}
+
+pub(crate) struct LoadGroupEntry {
+    pub(crate) id: ResourceId,
+    pub(crate) is_loaded: bool,
+    //Keeps the resource alive until the group is completed or cancelled
+    pub(crate) _resource: GenericResource,
+}
+
+#[derive(Default)]
+pub(crate) struct LoadGroupData {
+    pub(crate) entries: Vec<LoadGroupEntry>,
+}
+
+impl LoadGroupData {
+    pub(crate) fn progress(&self) -> (usize, usize) {
+        let loaded = self.entries.iter().filter(|e| e.is_loaded).count();
+        (loaded, self.entries.len())
+    }
+    pub(crate) fn is_completed(&self) -> bool {
+        self.entries.iter().all(|e| e.is_loaded)
+    }
+}
+
+//Created with the callback of a resource load: when it's dropped without the resource
+//being loaded, like for a missing or invalid file, the load is reported as failed
+//so that its load groups don't wait for it forever
+pub(crate) struct PendingLoad {
+    shared_data: SharedDataRc,
+    message_hub: MessageHubRc,
+    resource_id: ResourceId,
+    is_loaded: bool,
+}
+
+impl PendingLoad {
+    pub(crate) fn new(
+        shared_data: &SharedDataRc,
+        message_hub: &MessageHubRc,
+        resource_id: &ResourceId,
+    ) -> Self {
+        Self {
+            shared_data: shared_data.clone(),
+            message_hub: message_hub.clone(),
+            resource_id: *resource_id,
+            is_loaded: false,
+        }
+    }
+    pub(crate) fn set_loaded(&mut self) {
+        self.is_loaded = true;
+    }
+}
+
+impl Drop for PendingLoad {
+    fn drop(&mut self) {
+        if !self.is_loaded {
+            self.shared_data
+                .on_resource_load_failed(&self.resource_id, &self.message_hub);
+        }
+    }
+}
+
+thread_local! {
+    //Groups of the resource currently being created: nested requests inherit them
+    static CURRENT_LOAD_GROUPS: RefCell<Vec<LoadGroupId>> = RefCell::new(Vec::new());
+}
+
+pub(crate) fn current_load_groups() -> Vec<LoadGroupId> {
+    CURRENT_LOAD_GROUPS.with(|groups| groups.borrow().clone())
+}
+
+pub(crate) fn with_load_groups<F>(groups: Vec<LoadGroupId>, f: F)
+where
+    F: FnOnce(),
+{
+    let previous = CURRENT_LOAD_GROUPS.with(|g| g.replace(groups));
+    f();
+    CURRENT_LOAD_GROUPS.with(|g| g.replace(previous));
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LoadGroup {
+    id: LoadGroupId,
+}
+
+impl LoadGroup {
+    pub(crate) fn new(id: LoadGroupId) -> Self {
+        Self { id }
+    }
+    pub fn id(&self) -> &LoadGroupId {
+        &self.id
+    }
+    pub fn request_load<T>(
+        &self,
+        shared_data: &SharedDataRc,
+        message_hub: &MessageHubRc,
+        filepath: &Path,
+        on_create_data: Option<OnCreateData<T>>,
+    ) -> Resource<T>
+    where
+        T: SerializableResource + DataTypeResource + 'static,
+    {
+        let resource = T::request_load(shared_data, message_hub, filepath, on_create_data);
+        shared_data.add_to_load_group(&self.id, resource.clone(), message_hub);
+        resource
+    }
+    pub fn progress(&self, shared_data: &SharedDataRc) -> Option<(usize, usize)> {
+        shared_data.load_group_progress(&self.id)
+    }
+    pub fn cancel(&self, shared_data: &SharedDataRc, message_hub: &MessageHubRc) {
+        shared_data.cancel_load_group(&self.id, message_hub);
+    }
+}
+
+#[allow(dead_code)]
+fn test_load_groups() {
+    use crate::{ResourceTrait, SharedData};
+    use inox_messenger::{Listener, MessageHub};
+    use inox_uid::generate_random_resource_uid;
+    use std::sync::Arc;
+
+    #[derive(Clone)]
+    struct TestResource;
+    impl ResourceTrait for TestResource {
+        fn is_initialized(&self) -> bool {
+            true
+        }
+        fn invalidate(&mut self) -> &mut Self {
+            self
+        }
+    }
+
+    let shared_data = Arc::new(SharedData::default());
+    let message_hub = Arc::new(MessageHub::default());
+    shared_data.register_type::<TestResource>(&message_hub);
+    let listener = Listener::new(&message_hub);
+    listener.register::<LoadGroupEvent>();
+
+    let shared_id = generate_random_resource_uid();
+    let other_id = generate_random_resource_uid();
+    let shared = shared_data.add_resource(&message_hub, shared_id, TestResource);
+    let other = shared_data.add_resource(&message_hub, other_id, TestResource);
+    shared_data.mark_as_loading(&shared_id);
+    shared_data.mark_as_loading(&other_id);
+
+    let first = shared_data.begin_load_group(&message_hub);
+    let second = shared_data.begin_load_group(&message_hub);
+    shared_data.add_to_load_group(first.id(), shared.clone(), &message_hub);
+    shared_data.add_to_load_group(first.id(), other.clone(), &message_hub);
+    shared_data.add_to_load_group(second.id(), shared.clone(), &message_hub);
+    assert_eq!(first.progress(&shared_data), Some((0, 2)));
+    assert_eq!(second.progress(&shared_data), Some((0, 1)));
+
+    shared_data.on_resource_loaded(&shared_id, &message_hub);
+    assert_eq!(first.progress(&shared_data), Some((1, 2)));
+    assert_eq!(second.progress(&shared_data), None);
+
+    message_hub.flush();
+    let mut events = Vec::new();
+    listener.process_messages(|e: &LoadGroupEvent| events.push(*e));
+    assert!(events.contains(&LoadGroupEvent::Progress(*first.id(), 1, 2)));
+    assert!(events.contains(&LoadGroupEvent::Completed(*second.id())));
+    assert!(!events.contains(&LoadGroupEvent::Completed(*first.id())));
+
+    //Cancelling releases only the group references
+    first.cancel(&shared_data, &message_hub);
+    assert_eq!(first.progress(&shared_data), None);
+    shared_data.flush_resources(&message_hub);
+    assert!(shared_data.has::<TestResource>(&other_id));
+    drop(other);
+    drop(shared);
+    shared_data.flush_resources(&message_hub);
+    assert!(!shared_data.has::<TestResource>(&other_id));
+    assert!(!shared_data.has::<TestResource>(&shared_id));
+
+    //A failed load counts as completed, or the group would never end
+    let failed_id = generate_random_resource_uid();
+    let failed = shared_data.add_resource(&message_hub, failed_id, TestResource);
+    shared_data.mark_as_loading(&failed_id);
+    let group = shared_data.begin_load_group(&message_hub);
+    shared_data.add_to_load_group(group.id(), failed, &message_hub);
+    assert_eq!(group.progress(&shared_data), Some((0, 1)));
+    //Dropped without being set as loaded, as the callback of an invalid file
+    drop(PendingLoad::new(&shared_data, &message_hub, &failed_id));
+    assert_eq!(group.progress(&shared_data), None);
+    message_hub.flush();
+    let mut events = Vec::new();
+    listener.process_messages(|e: &LoadGroupEvent| events.push(*e));
+    assert!(events.contains(&LoadGroupEvent::Completed(*group.id())));
+
+    listener.unregister::<LoadGroupEvent>();
+    shared_data.unregister_type::<TestResource>(&message_hub);
+}
+
+#[test]
+fn test() {
+    test_load_groups();
+}
@@ -1,4 +1,4 @@
-use inox_uid::Uid;
+use inox_uid::ResourceUid;
 use std::{
     any::Any,
     sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
@@ -6,7 +6,7 @@ use std::{
 
 pub const DEBUG_RESOURCES: bool = false;
 
-pub type ResourceId = Uid;
+pub type ResourceId = ResourceUid;
 
 pub trait Function<T>: FnMut(&mut T)
 where
@@ -1,17 +1,27 @@
 use std::{
     any::{type_name, TypeId},
-    collections::HashMap,
-    sync::{Arc, RwLock},
+    collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock,
+    },
 };
 
+use inox_log::debug_log;
 use inox_messenger::MessageHubRc;
-use inox_serialize::inox_serializable::SerializableRegistryRc;
-use inox_uid::{generate_uid_from_string, Uid};
+use inox_serialize::{
+    deserialize, inox_serializable::SerializableRegistryRc, serialize, Deserialize, Serialize,
+    SerializeFile,
+};
+use inox_uid::{generate_random_uid, generate_uid_from_string, Uid};
 
 use crate::{
-    DataTypeResource, EventHandler, Handle, LoadFunction, Resource, ResourceEvent,
-    ResourceEventHandler, ResourceId, ResourceStorageRw, ResourceTrait, SerializableResource,
-    SerializableResourceEvent, SerializableResourceEventHandler, Singleton, Storage, StorageCastTo,
+    current_load_groups, ConfigBase, ConfigWatcher, ConfigWatcherTrait, DataTypeResource,
+    EventHandler, GenericResource, Handle, HandoffResource, LoadFunction, LoadGroup, LoadGroupData,
+    LoadGroupEntry, LoadGroupEvent, LoadGroupId, Resource, ResourceEvent, ResourceEventHandler,
+    ResourceId, ResourceStorageRw, ResourceTrait, ResourceTypeReport, ResourcesHandoff,
+    SerializableResource, SerializableResourceEvent, SerializableResourceEventHandler,
+    SharedDataRc, Singleton, Storage, StorageCastTo,
 };
 
 #[derive(Default)]
@@ -20,6 +30,13 @@ pub struct SharedData {
     singletons: RwLock<Vec<RwLock<Box<dyn Singleton>>>>,
     storage: RwLock<HashMap<Uid, ResourceStorageRw>>,
     event_handlers: RwLock<HashMap<Uid, Box<dyn EventHandler>>>,
+    loading_resources: RwLock<HashSet<ResourceId>>,
+    load_groups: RwLock<HashMap<LoadGroupId, LoadGroupData>>,
+    leak_detection: AtomicBool,
+    config_watchers: RwLock<Vec<Box<dyn ConfigWatcherTrait>>>,
+    handoff: RwLock<Option<ResourcesHandoff>>,
+    registering_plugin_types: AtomicBool,
+    plugin_types: RwLock<HashSet<Uid>>,
 }
 unsafe impl Send for SharedData {}
 unsafe impl Sync for SharedData {}
@@ -94,10 +111,17 @@ impl SharedData {
         );
         message_hub.register_type::<ResourceEvent<T>>();
         //debug_log("Registering resource type: {:?}", type_name::<T>(),);
-        self.storage
+        if self.registering_plugin_types.load(Ordering::SeqCst) {
+            self.plugin_types.write().unwrap().insert(typeid);
+        }
+        let storage = self
+            .handoff
             .write()
             .unwrap()
-            .insert(typeid, Arc::new(RwLock::new(Box::<Storage<T>>::default())));
+            .as_mut()
+            .and_then(|handoff| handoff.take_storage::<T>())
+            .unwrap_or_else(|| Arc::new(RwLock::new(Box::<Storage<T>>::default())));
+        self.storage.write().unwrap().insert(typeid, storage);
         self.event_handlers.write().unwrap().insert(
             typeid,
             Box::new(ResourceEventHandler::<T>::new(message_hub)),
@@ -133,7 +157,20 @@ impl SharedData {
         );
         message_hub.unregister_type::<ResourceEvent<T>>();
         //debug_log("Unregistering resource type: {:?}", type_name::<T>());
+        let is_plugin_type = self.plugin_types.write().unwrap().remove(&typeid);
         if let Some(rs) = self.storage.write().unwrap().remove(&typeid) {
+            //During a handoff the storage waits for the type to be registered again,
+            //unless its code goes away with the plugin library being unloaded
+            if !is_plugin_type {
+                if let Some(handoff) = self.handoff.write().unwrap().as_mut() {
+                    handoff.retain_storage::<T>(rs);
+                    self.event_handlers.write().unwrap().remove(&typeid);
+                    return;
+                }
+            }
+            if self.is_leak_detection_enabled() {
+                Self::log_leaks(&rs.read().unwrap().report());
+            }
             rs.write().unwrap().remove_all();
         }
         self.event_handlers.write().unwrap().remove(&typeid);
@@ -150,6 +187,100 @@ impl SharedData {
         self.event_handlers.write().unwrap().remove(&typeid);
     }
     #[inline]
+    pub fn register_type_serializable_with_handoff<T>(
+        self: &SharedDataRc,
+        message_hub: &MessageHubRc,
+    ) where
+        T: SerializableResource + HandoffResource + 'static,
+        <T as DataTypeResource>::DataType: for<'de> Deserialize<'de> + Send + Sync,
+    {
+        self.register_type_serializable::<T>(message_hub);
+        let resources = match self.handoff.write().unwrap().as_mut() {
+            Some(handoff) => handoff.take::<T>(),
+            None => return,
+        };
+        resources.into_iter().for_each(|(id, serialized_data)| {
+            let data =
+                match deserialize::<T::DataType>(&serialized_data, self.serializable_registry()) {
+                    Ok(data) => T::create_from_data(self, message_hub, id, &data),
+                    Err(e) => {
+                        debug_log!(
+                            "Unable to restore {} {:?} after reload, using default instead - {}",
+                            type_name::<T>(),
+                            id,
+                            e
+                        );
+                        T::new(id, self, message_hub)
+                    }
+                };
+            let resource: GenericResource = self.add_resource(message_hub, id, data);
+            if let Some(handoff) = self.handoff.write().unwrap().as_mut() {
+                handoff.hold(resource);
+            }
+        });
+    }
+    #[inline]
+    pub fn unregister_type_serializable_with_handoff<T>(&self, message_hub: &MessageHubRc)
+    where
+        T: SerializableResource + HandoffResource + 'static,
+        <T as DataTypeResource>::DataType: Serialize,
+    {
+        if let Some(handoff) = self.handoff.write().unwrap().as_mut() {
+            let mut resources = HashMap::new();
+            self.for_each_resource(|r: &Resource<T>, data: &T| {
+                let serialized_data = serialize(&data.handoff_data(), self.serializable_registry());
+                resources.insert(*r.id(), serialized_data);
+            });
+            handoff.store::<T>(resources);
+        }
+        self.unregister_type_serializable::<T>(message_hub);
+        //Serialized resources are created again, their old storage is not kept too
+        if let Some(storage) = self
+            .handoff
+            .write()
+            .unwrap()
+            .as_mut()
+            .and_then(|handoff| handoff.take_storage::<T>())
+        {
+            storage.write().unwrap().remove_all();
+        }
+    }
+    //Types registered while this is set belong to a dynamic plugin:
+    //their storages are dropped as soon as they are unregistered, even during a handoff
+    #[inline]
+    pub fn set_registering_plugin_types(&self, is_registering: bool) {
+        self.registering_plugin_types
+            .store(is_registering, Ordering::SeqCst);
+    }
+    //Resources of types unregistered with handoff from now on are serialized
+    //and recreated as soon as their types are registered again, until end_handoff() is called.
+    //Other types registered by the host keep their storage, resources included,
+    //and get it back when registered again
+    #[inline]
+    pub fn begin_handoff(&self) {
+        *self.handoff.write().unwrap() = Some(ResourcesHandoff::default());
+    }
+    #[inline]
+    pub fn is_handoff_active(&self) -> bool {
+        self.handoff.read().unwrap().is_some()
+    }
+    #[inline]
+    pub fn end_handoff(&self) {
+        if let Some(mut handoff) = self.handoff.write().unwrap().take() {
+            handoff
+                .pending_types()
+                .iter()
+                .for_each(|(type_name, count)| {
+                    debug_log!(
+                        "{} resources of type {} have been dropped: type not registered again",
+                        count,
+                        type_name
+                    );
+                });
+            handoff.clear_storages();
+        }
+    }
+    #[inline]
     pub fn add_resource<T>(
         &self,
         message_hub: &MessageHubRc,
@@ -330,6 +461,201 @@ impl SharedData {
         None
     }
     #[inline]
+    pub fn begin_load_group(&self, message_hub: &MessageHubRc) -> LoadGroup {
+        message_hub.register_type::<LoadGroupEvent>();
+        let id = generate_random_uid();
+        self.load_groups
+            .write()
+            .unwrap()
+            .insert(id, LoadGroupData::default());
+        LoadGroup::new(id)
+    }
+    #[inline]
+    pub fn add_to_load_group<T>(
+        &self,
+        group_id: &LoadGroupId,
+        resource: Resource<T>,
+        message_hub: &MessageHubRc,
+    ) where
+        T: ResourceTrait + 'static,
+    {
+        let resource_id = *resource.id();
+        let is_loaded = !self
+            .loading_resources
+            .read()
+            .unwrap()
+            .contains(&resource_id);
+        let mut load_groups = self.load_groups.write().unwrap();
+        if let Some(group) = load_groups.get_mut(group_id) {
+            if group.entries.iter().any(|e| e.id == resource_id) {
+                return;
+            }
+            let resource: GenericResource = resource;
+            group.entries.push(LoadGroupEntry {
+                id: resource_id,
+                is_loaded,
+                _resource: resource,
+            });
+            Self::send_load_group_events(group_id, group, message_hub);
+            if group.is_completed() {
+                load_groups.remove(group_id);
+            }
+        }
+    }
+    #[inline]
+    pub fn load_group_progress(&self, group_id: &LoadGroupId) -> Option<(usize, usize)> {
+        self.load_groups
+            .read()
+            .unwrap()
+            .get(group_id)
+            .map(|group| group.progress())
+    }
+    #[inline]
+    pub fn cancel_load_group(&self, group_id: &LoadGroupId, message_hub: &MessageHubRc) {
+        //Dropping the group only releases its own references:
+        //resources still used elsewhere are kept alive by their other owners
+        if self.load_groups.write().unwrap().remove(group_id).is_some() {
+            message_hub.send_event(LoadGroupEvent::Cancelled(*group_id));
+        }
+    }
+    #[inline]
+    pub(crate) fn mark_as_loading(&self, resource_id: &ResourceId) {
+        self.loading_resources.write().unwrap().insert(*resource_id);
+    }
+    #[inline]
+    pub(crate) fn add_to_current_load_groups<T>(
+        &self,
+        resource: &Resource<T>,
+        message_hub: &MessageHubRc,
+    ) where
+        T: ResourceTrait + 'static,
+    {
+        current_load_groups().iter().for_each(|group_id| {
+            self.add_to_load_group(group_id, resource.clone(), message_hub);
+        });
+    }
+    #[inline]
+    pub(crate) fn load_groups_of(&self, resource_id: &ResourceId) -> Vec<LoadGroupId> {
+        self.load_groups
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(_, group)| group.entries.iter().any(|e| e.id == *resource_id))
+            .map(|(id, _)| *id)
+            .collect()
+    }
+    #[inline]
+    pub(crate) fn on_resource_loaded(&self, resource_id: &ResourceId, message_hub: &MessageHubRc) {
+        self.loading_resources.write().unwrap().remove(resource_id);
+        self.load_groups.write().unwrap().retain(|group_id, group| {
+            let mut is_changed = false;
+            group.entries.iter_mut().for_each(|e| {
+                if e.id == *resource_id && !e.is_loaded {
+                    e.is_loaded = true;
+                    is_changed = true;
+                }
+            });
+            if is_changed {
+                Self::send_load_group_events(group_id, group, message_hub);
+            }
+            !group.is_completed()
+        });
+    }
+    #[inline]
+    pub(crate) fn on_resource_load_failed(
+        &self,
+        resource_id: &ResourceId,
+        message_hub: &MessageHubRc,
+    ) {
+        eprintln!("Failed to load resource with id {resource_id:?}");
+        self.on_resource_loaded(resource_id, message_hub);
+    }
+    fn send_load_group_events(
+        group_id: &LoadGroupId,
+        group: &LoadGroupData,
+        message_hub: &MessageHubRc,
+    ) {
+        let (loaded, total) = group.progress();
+        message_hub.send_event(LoadGroupEvent::Progress(*group_id, loaded, total));
+        if group.is_completed() {
+            message_hub.send_event(LoadGroupEvent::Completed(*group_id));
+        }
+    }
+    pub fn watch_config<T>(&self, plugin_name: &str)
+    where
+        T: ConfigBase + Default + SerializeFile + for<'de> Deserialize<'de>,
+    {
+        let config = T::default();
+        let filepath = config.get_filepath(plugin_name);
+        if !filepath.exists() {
+            inox_log::debug_log!("Unable to watch missing config file {:?}", filepath);
+            return;
+        }
+        let watcher = ConfigWatcher::<T>::new(filepath, config.get_filename());
+        let mut config_watchers = self.config_watchers.write().unwrap();
+        if !config_watchers
+            .iter()
+            .any(|w| w.filepath() == watcher.filepath())
+        {
+            config_watchers.push(Box::new(watcher));
+        }
+    }
+    pub fn unwatch_config<T>(&self, plugin_name: &str)
+    where
+        T: ConfigBase + Default,
+    {
+        let filepath = T::default().get_filepath(plugin_name);
+        self.config_watchers.write().unwrap().retain_mut(|w| {
+            if filepath.canonicalize().map_or(false, |p| p == w.filepath()) {
+                w.stop();
+                return false;
+            }
+            true
+        });
+    }
+    pub fn update_config_watchers(&self, message_hub: &MessageHubRc) {
+        inox_profiler::scoped_profile!("shared_data::update_config_watchers");
+        self.config_watchers
+            .read()
+            .unwrap()
+            .iter()
+            .for_each(|w| w.update(&self.serialization_registry, message_hub));
+    }
+    #[inline]
+    pub fn set_leak_detection(&self, is_enabled: bool) {
+        self.leak_detection.store(is_enabled, Ordering::SeqCst);
+    }
+    #[inline]
+    pub fn is_leak_detection_enabled(&self) -> bool {
+        self.leak_detection.load(Ordering::SeqCst)
+    }
+    pub fn resource_report(&self) -> Vec<ResourceTypeReport> {
+        let mut report = self
+            .storage
+            .read()
+            .unwrap()
+            .values()
+            .map(|rs| rs.read().unwrap().report())
+            .collect::<Vec<_>>();
+        report.sort_by(|a, b| a.type_name.cmp(b.type_name));
+        report
+    }
+    fn log_leaks(report: &ResourceTypeReport) {
+        report
+            .resources
+            .iter()
+            .filter(|r| r.is_referenced())
+            .for_each(|r| {
+                inox_log::debug_log!(
+                    "Resource {:?} with id {:?} still alive after unregistering its type [strong: {}, weak: {}]",
+                    report.type_name,
+                    r.id,
+                    r.strong_count - 1,
+                    r.weak_count
+                );
+            });
+    }
+    #[inline]
     pub fn num_resources<T: ResourceTrait>(&self) -> usize {
         let typeid = generate_uid_from_string(type_name::<T>());
         if let Some(rs) = self.storage.read().unwrap().get(&typeid) {
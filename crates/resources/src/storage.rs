@@ -21,10 +21,44 @@ pub trait TypedStorage: Send + Sync + Any {
         message_hub: &MessageHubRc,
     );
     fn count(&self) -> usize;
+    fn report(&self) -> ResourceTypeReport;
 }
 pub type ResourceStorageRw = Arc<RwLock<Box<dyn TypedStorage>>>;
 pub type ResourceStorage<T> = Arc<RwLock<Box<Storage<T>>>>;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResourceRefCount {
+    pub id: ResourceId,
+    //Includes the reference owned by the storage itself
+    pub strong_count: usize,
+    pub weak_count: usize,
+}
+
+impl ResourceRefCount {
+    //Referenced by someone else than the storage
+    pub fn is_referenced(&self) -> bool {
+        self.strong_count > 1 || self.weak_count > 0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResourceTypeReport {
+    pub type_name: &'static str,
+    pub resources: Vec<ResourceRefCount>,
+}
+
+impl ResourceTypeReport {
+    pub fn count(&self) -> usize {
+        self.resources.len()
+    }
+    pub fn strong_count(&self) -> usize {
+        self.resources.iter().map(|r| r.strong_count).sum()
+    }
+    pub fn weak_count(&self) -> usize {
+        self.resources.iter().map(|r| r.weak_count).sum()
+    }
+}
+
 pub trait StorageCastTo {
     fn of_type<T>(&self) -> ResourceStorage<T>
     where
@@ -128,6 +162,21 @@ where
     fn count(&self) -> usize {
         self.resources.len()
     }
+    #[inline]
+    fn report(&self) -> ResourceTypeReport {
+        ResourceTypeReport {
+            type_name: T::typename(),
+            resources: self
+                .resources
+                .iter()
+                .map(|r| ResourceRefCount {
+                    id: *r.id(),
+                    strong_count: Arc::strong_count(r),
+                    weak_count: Arc::weak_count(r),
+                })
+                .collect(),
+        }
+    }
 }
 
 impl<T> Storage<T>
@@ -241,3 +290,58 @@ where
         }
     }
 }
+
+#[allow(dead_code)]
+fn test_resource_report() {
+    use inox_messenger::MessageHub;
+    use inox_uid::generate_random_resource_uid;
+
+    #[derive(Clone)]
+    struct TestResource;
+    impl ResourceTrait for TestResource {
+        fn is_initialized(&self) -> bool {
+            true
+        }
+        fn invalidate(&mut self) -> &mut Self {
+            self
+        }
+    }
+
+    let shared_data = SharedData::default();
+    let message_hub = Arc::new(MessageHub::default());
+    shared_data.register_type::<TestResource>(&message_hub);
+
+    let id = generate_random_resource_uid();
+    let resource = shared_data.add_resource(&message_hub, id, TestResource);
+    let weak = Arc::downgrade(&resource);
+
+    let report = shared_data.resource_report();
+    let report = report
+        .iter()
+        .find(|r| r.type_name == TestResource::typename())
+        .unwrap();
+    assert_eq!(report.count(), 1);
+    assert_eq!(
+        report.resources[0],
+        ResourceRefCount {
+            id,
+            strong_count: 2,
+            weak_count: 1,
+        }
+    );
+    assert!(report.resources[0].is_referenced());
+
+    drop(weak);
+    drop(resource);
+    let report = shared_data.resource_report();
+    assert!(!report[0].resources[0].is_referenced());
+    shared_data.flush_resources(&message_hub);
+    assert_eq!(shared_data.resource_report()[0].count(), 0);
+
+    shared_data.unregister_type::<TestResource>(&message_hub);
+}
+
+#[test]
+fn test() {
+    test_resource_report();
+}
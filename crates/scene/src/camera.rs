@@ -2,7 +2,7 @@ use std::path::{Path, PathBuf};
 
 use inox_graphics::{DEFAULT_ASPECT_RATIO, DEFAULT_FAR, DEFAULT_FOV, DEFAULT_NEAR};
 use inox_math::{
-    convert_in_3d, Degrees, Mat4Ops, MatBase, Matrix4, NewAngle, Radians, Vector2, Vector3,
+    convert_in_3d, Degrees, Mat4Ops, MatBase, Matrix4, NewAngle, Radians, VecBase, Vector2, Vector3,
 };
 use inox_messenger::MessageHubRc;
 use inox_resources::{
@@ -11,6 +11,7 @@ use inox_resources::{
 };
 use inox_serialize::{inox_serializable::SerializableRegistryRc, read_from_file, SerializeFile};
 use inox_ui::{CollapsingHeader, UIProperties, UIPropertiesRegistry, Ui};
+use inox_uid::resource_uid_to_string;
 
 use crate::{CameraData, Object};
 
@@ -24,6 +25,11 @@ pub const OPENGL_TO_WGPU_MATRIX: Matrix4 = Matrix4::new(
 
 pub type CameraId = ResourceId;
 
+//Space left around the framed bounds, as a factor of their size
+pub const DEFAULT_FRAME_PADDING: f32 = 1.2;
+//Radius framed around a single point
+const MIN_FRAME_RADIUS: f32 = 1.;
+
 #[derive(Clone)]
 pub struct Camera {
     filepath: PathBuf,
@@ -44,7 +50,7 @@ impl UIProperties for Camera {
         ui: &mut Ui,
         collapsed: bool,
     ) {
-        CollapsingHeader::new(format!("Camera [{:?}]", id.as_simple().to_string()))
+        CollapsingHeader::new(format!("Camera [{:?}]", resource_uid_to_string(id)))
             .show_background(true)
             .default_open(!collapsed)
             .show(ui, |ui| {
@@ -154,14 +160,14 @@ impl Camera {
     #[inline]
     pub fn set_transform(&mut self, transform: Matrix4) -> &mut Self {
         if let Some(parent) = &self.parent {
-            parent.get_mut().set_transform(transform);
+            parent.get_mut().set_world_transform(transform);
         }
         self
     }
     #[inline]
     pub fn transform(&self) -> Matrix4 {
         if let Some(parent) = &self.parent {
-            let transform = parent.get().transform();
+            let transform = parent.get().world_transform();
             return transform;
         }
         Matrix4::default_identity()
@@ -253,7 +259,238 @@ impl Camera {
         self.far_plane
     }
 
+    //Moves the camera along its direction until the bounding sphere of the box fits the view,
+    //enlarged by DEFAULT_FRAME_PADDING. A box reduced to a point is framed as a small sphere
+    pub fn frame_aabb(&mut self, min: Vector3, max: Vector3) -> &mut Self {
+        let Some(parent) = &self.parent else {
+            return self;
+        };
+        let center = (min + max) * 0.5;
+        let radius = ((max - min).length() * 0.5).max(MIN_FRAME_RADIUS);
+        let half_fov_y = self.fov_in_radians().0 * 0.5;
+        let half_fov_x = (half_fov_y.tan() * self.aspect_ratio).atan();
+        let half_fov = half_fov_y.min(half_fov_x);
+        let distance = radius * DEFAULT_FRAME_PADDING / half_fov.sin();
+        {
+            let mut object = parent.get_mut();
+            let mut transform = object.world_transform();
+            transform.set_translation(center - transform.forward() * distance);
+            transform.look_at(center);
+            object.set_world_transform(transform);
+        }
+        if distance + radius > self.far_plane {
+            self.set_projection(
+                self.fov_in_degrees,
+                self.aspect_ratio,
+                1.,
+                self.near_plane,
+                distance + radius,
+            );
+        }
+        self
+    }
+
     pub fn convert_in_3d(&self, normalized_pos: Vector2) -> (Vector3, Vector3) {
         convert_in_3d(normalized_pos, &self.view_matrix(), &self.proj_matrix())
     }
 }
+
+#[allow(dead_code)]
+fn test_frame_aabb() {
+    use crate::Scene;
+    use inox_graphics::{create_cube, create_cube_from_min_max, Mesh, MeshData};
+    use inox_messenger::MessageHub;
+    use inox_resources::SharedDataRc;
+    use inox_uid::generate_random_resource_uid;
+    use std::sync::Arc;
+
+    let shared_data = SharedDataRc::default();
+    let message_hub = Arc::new(MessageHub::default());
+    shared_data.register_type_serializable::<Mesh>(&message_hub);
+    shared_data.register_type_serializable::<Object>(&message_hub);
+
+    let add_object = |position: Vector3| {
+        let id = generate_random_resource_uid();
+        let object = shared_data.add_resource(
+            &message_hub,
+            id,
+            Object::new(id, &shared_data, &message_hub),
+        );
+        object.get_mut().set_position(position);
+        object
+    };
+    let add_mesh = |object: &Resource<Object>, mesh_data: MeshData| {
+        let id = generate_random_resource_uid();
+        let mesh = shared_data.add_resource(
+            &message_hub,
+            id,
+            Mesh::create_from_data(&shared_data, &message_hub, id, &mesh_data),
+        );
+        object.get_mut().add_component(mesh);
+    };
+
+    //Empty scenes have no bounds
+    let mut scene = Scene::new(generate_random_resource_uid(), &shared_data, &message_hub);
+    assert!(scene.world_aabb().is_none());
+
+    //Objects without meshes don't enlarge the bounds, their children with meshes do
+    let cube = add_object(Vector3::new(10., 0., 0.));
+    add_mesh(&cube, create_cube(Vector3::new(1., 1., 1.), [1.; 4].into()));
+    let group = add_object(Vector3::new(-50., 0., 0.));
+    let child = add_object(Vector3::new(0., 0., 0.));
+    add_mesh(
+        &child,
+        create_cube_from_min_max(
+            Vector3::new(0., 0., 0.),
+            Vector3::new(1., 2., 3.),
+            [1.; 4].into(),
+        ),
+    );
+    scene.add_object(cube.clone());
+    scene.add_object(group.clone());
+    scene.add_child_object(&group, child.clone());
+    let (min, max) = scene.world_aabb().unwrap();
+    assert_eq!(min, Vector3::new(-50., -1., -1.));
+    assert_eq!(max, Vector3::new(11., 2., 3.));
+
+    //Children of transformed objects are bounded in world space
+    let pivot = add_object(Vector3::new(0., 0., 0.));
+    pivot.get_mut().set_transform(
+        Matrix4::from_translation(Vector3::new(0., 0., 40.))
+            * Matrix4::from_angle_y(Degrees::new(90.))
+            * Matrix4::from_scale(2.),
+    );
+    let pivot_child = add_object(Vector3::new(1., 0., 0.));
+    add_mesh(
+        &pivot_child,
+        create_cube_from_min_max(
+            Vector3::new(0., 0., 0.),
+            Vector3::new(1., 2., 3.),
+            [1.; 4].into(),
+        ),
+    );
+    scene.add_object(pivot.clone());
+    scene.add_child_object(&pivot, pivot_child.clone());
+    let (child_min, child_max) = pivot_child.get().aabb();
+    assert!((child_min - Vector3::new(0., 0., 36.)).length() < 1e-4);
+    assert!((child_max - Vector3::new(6., 4., 38.)).length() < 1e-4);
+    let (min, max) = scene.world_aabb().unwrap();
+    assert!((min - Vector3::new(-50., -1., -1.)).length() < 1e-4);
+    assert!((max - Vector3::new(11., 4., 38.)).length() < 1e-4);
+
+    let camera_object = add_object(Vector3::new(0., 5., -20.));
+    let mut camera = Camera::new(generate_random_resource_uid(), &shared_data, &message_hub);
+    camera.set_parent(&camera_object).set_projection(
+        Degrees::new(45.),
+        16.,
+        9.,
+        DEFAULT_NEAR,
+        1000.,
+    );
+    let projected = |camera: &Camera, point: Vector3| {
+        let clip = camera.proj_matrix() * camera.view_matrix() * point.extend(1.);
+        assert!(clip.w > 0.);
+        Vector2::new(clip.x / clip.w, clip.y / clip.w)
+    };
+
+    //The box is centered in the view and every corner is inside it, with the padding as margin
+    camera.frame_aabb(min, max);
+    let center = (min + max) * 0.5;
+    let radius = (max - min).length() * 0.5;
+    let distance = (camera_object.get().position() - center).length();
+    assert!(
+        (distance - radius * DEFAULT_FRAME_PADDING / (22.5f32).to_radians().sin()).abs() < 1e-3
+    );
+    assert!(projected(&camera, center).length() < 1e-4);
+    let margin = 1. / DEFAULT_FRAME_PADDING;
+    let mut extent = 0f32;
+    for i in 0..8 {
+        let corner = Vector3::new(
+            if i & 1 == 0 { min.x } else { max.x },
+            if i & 2 == 0 { min.y } else { max.y },
+            if i & 4 == 0 { min.z } else { max.z },
+        );
+        let p = projected(&camera, corner);
+        assert!(p.x.abs() <= margin && p.y.abs() <= margin);
+        extent = extent.max(p.x.abs()).max(p.y.abs());
+    }
+    assert!(extent > 0.5 * margin);
+
+    //A single point is framed at a distance that keeps it in front of the camera
+    let point = Vector3::new(3., 3., 3.);
+    camera.frame_aabb(point, point);
+    assert!(projected(&camera, point).length() < 1e-4);
+    let distance = (camera_object.get().position() - point).length();
+    assert!(distance > MIN_FRAME_RADIUS && distance.is_finite());
+
+    //A camera child of a transformed object is moved in world space
+    let rig = add_object(Vector3::new(0., 0., 0.));
+    rig.get_mut().set_transform(
+        Matrix4::from_translation(Vector3::new(100., -20., 0.))
+            * Matrix4::from_angle_y(Degrees::new(30.)),
+    );
+    camera_object.get_mut().set_parent(Some(rig.clone()));
+    camera.frame_aabb(min, max);
+    assert!(projected(&camera, center).length() < 1e-3);
+    let distance = (camera.transform().translation() - center).length();
+    assert!(
+        (distance - radius * DEFAULT_FRAME_PADDING / (22.5f32).to_radians().sin()).abs() < 1e-2
+    );
+    assert!((camera_object.get().position() - camera.transform().translation()).length() > 1.);
+
+    drop(scene);
+    shared_data.unregister_type_serializable::<Object>(&message_hub);
+    shared_data.unregister_type_serializable::<Mesh>(&message_hub);
+}
+
+#[allow(dead_code)]
+fn test_render_scale_ray() {
+    use inox_graphics::{RenderScale, MIN_RENDER_SCALE};
+    use inox_messenger::MessageHub;
+    use inox_resources::SharedDataRc;
+    use inox_uid::generate_random_resource_uid;
+    use std::sync::Arc;
+
+    let shared_data = SharedDataRc::default();
+    let message_hub = Arc::new(MessageHub::default());
+    let (width, height) = (1920, 1080);
+    let fov = Degrees::new(60.);
+    let mut camera = Camera::new(generate_random_resource_uid(), &shared_data, &message_hub);
+    camera.set_projection(fov, width as f32, height as f32, DEFAULT_NEAR, DEFAULT_FAR);
+    let positions = [
+        Vector2::new(0., 0.),
+        Vector2::new(-0.5, 0.25),
+        Vector2::new(1., -1.),
+    ];
+    let rays = positions.map(|p| camera.convert_in_3d(p));
+
+    //The scaled targets keep the proportions of the window, so the rays under the mouse
+    //are the same whatever resolution the scene is rendered at
+    let mut render_scale = RenderScale::default();
+    for scale in [0.75, 0.5, MIN_RENDER_SCALE] {
+        render_scale.set_scale(scale);
+        let (scaled_width, scaled_height) = render_scale.scaled_size(width, height);
+        assert!(scaled_width < width && scaled_height < height);
+        camera.set_projection(
+            fov,
+            scaled_width as f32,
+            scaled_height as f32,
+            DEFAULT_NEAR,
+            DEFAULT_FAR,
+        );
+        positions
+            .iter()
+            .zip(rays.iter())
+            .for_each(|(p, (start, end))| {
+                let (scaled_start, scaled_end) = camera.convert_in_3d(*p);
+                assert!((scaled_start - *start).length() <= 1e-4 * start.length().max(1.));
+                assert!((scaled_end - *end).length() <= 1e-4 * end.length().max(1.));
+            });
+    }
+}
+
+#[test]
+fn test() {
+    test_frame_aabb();
+    test_render_scale_ray();
+}
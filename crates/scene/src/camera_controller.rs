@@ -0,0 +1,551 @@
+use inox_math::{Mat4Ops, VecBase, VecBaseFloat, Vector2, Vector3};
+use inox_platform::{
+    GamepadAxis, GamepadEvent, InputState, Key, KeyEvent, MouseButton, MouseEvent, MouseState,
+    TouchEvent, TouchState,
+};
+use inox_resources::{Handle, Resource, ResourceId, ResourceTrait};
+use inox_ui::{CollapsingHeader, UIProperties, UIPropertiesRegistry, Ui};
+use inox_uid::resource_uid_to_string;
+
+use crate::Object;
+
+pub type CameraControllerId = ResourceId;
+
+pub const DEFAULT_CAMERA_SPEED: f32 = 200.;
+pub const DEFAULT_CAMERA_ROTATION_SPEED: f32 = 200.;
+pub const DEFAULT_GAMEPAD_ROTATION_SPEED: f32 = 2.;
+pub const DEFAULT_ZOOM_SPEED: f32 = 1.;
+pub const MIN_CAMERA_SPEED: f32 = 0.1;
+pub const MAX_CAMERA_SPEED: f32 = 10_000.;
+pub const MIN_CAMERA_ROTATION_SPEED: f32 = 1.;
+pub const MAX_CAMERA_ROTATION_SPEED: f32 = 1_000.;
+//Speed is multiplied by it while shift is held and divided while ctrl is held
+pub const SPEED_MODIFIER: f32 = 4.;
+//Speed boost applied by each notch of the mouse wheel
+pub const WHEEL_SPEED_STEP: f32 = 1.25;
+//Zoom applied spreading two fingers by the whole window
+pub const PINCH_ZOOM_STEP: f32 = 10.;
+const MAX_SPEED_BOOST: f32 = 16.;
+//89 degrees: looking straight up or down would make the direction parallel to the up vector
+pub const MAX_PITCH: f32 = 1.553_343;
+const MIN_ORBIT_DISTANCE: f32 = 0.1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CameraControllerMode {
+    //Moves along the view axes and looks around
+    FreeFly,
+    //Looks at the target rotating around it, moving forward and backward zooms
+    Orbit,
+    //Moves on the horizontal plane and looks around
+    FirstPerson,
+}
+
+//Moves and rotates its parent object from input events.
+//Rotation is kept as yaw and pitch so that the pitch can be clamped before the poles
+#[derive(Clone)]
+pub struct CameraController {
+    parent: Handle<Object>,
+    mode: CameraControllerMode,
+    speed: f32,
+    rotation_speed: f32,
+    gamepad_rotation_speed: f32,
+    zoom_speed: f32,
+    speed_boost: f32,
+    is_faster: bool,
+    is_slower: bool,
+    is_rotating: bool,
+    target: Vector3,
+    distance: f32,
+    yaw: f32,
+    pitch: f32,
+    left_stick: Vector2,
+    right_stick: Vector2,
+    touches: Vec<(u64, Vector2)>,
+}
+
+impl ResourceTrait for CameraController {
+    fn is_initialized(&self) -> bool {
+        self.parent.is_some()
+    }
+    fn invalidate(&mut self) -> &mut Self {
+        self
+    }
+}
+
+impl UIProperties for CameraController {
+    fn show(
+        &mut self,
+        id: &ResourceId,
+        ui_registry: &UIPropertiesRegistry,
+        ui: &mut Ui,
+        collapsed: bool,
+    ) {
+        CollapsingHeader::new(format!(
+            "CameraController [{:?}]",
+            resource_uid_to_string(id)
+        ))
+        .show_background(true)
+        .default_open(!collapsed)
+        .show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(format!("Mode: {:?}", self.mode));
+            });
+            ui.horizontal(|ui| {
+                ui.label("Speed: ");
+                self.speed.show(id, ui_registry, ui, collapsed);
+            });
+            ui.horizontal(|ui| {
+                ui.label("Rotation speed: ");
+                self.rotation_speed.show(id, ui_registry, ui, collapsed);
+            });
+        });
+    }
+}
+
+impl CameraController {
+    pub fn new(mode: CameraControllerMode) -> Self {
+        Self {
+            parent: None,
+            mode,
+            speed: DEFAULT_CAMERA_SPEED,
+            rotation_speed: DEFAULT_CAMERA_ROTATION_SPEED,
+            gamepad_rotation_speed: DEFAULT_GAMEPAD_ROTATION_SPEED,
+            zoom_speed: DEFAULT_ZOOM_SPEED,
+            speed_boost: 1.,
+            is_faster: false,
+            is_slower: false,
+            is_rotating: false,
+            target: Vector3::default_zero(),
+            distance: MIN_ORBIT_DISTANCE,
+            yaw: 0.,
+            pitch: 0.,
+            left_stick: Vector2::default_zero(),
+            right_stick: Vector2::default_zero(),
+            touches: Vec::new(),
+        }
+    }
+
+    pub fn parent(&self) -> &Handle<Object> {
+        &self.parent
+    }
+    //Yaw and pitch start from the current direction of the object
+    pub fn set_parent(&mut self, parent: &Resource<Object>) -> &mut Self {
+        let direction = parent.get().transform().direction();
+        self.pitch = direction
+            .y
+            .clamp(-1., 1.)
+            .asin()
+            .clamp(-MAX_PITCH, MAX_PITCH);
+        self.yaw = direction.x.atan2(direction.z);
+        self.parent = Some(parent.clone());
+        self.update_distance();
+        self
+    }
+
+    pub fn mode(&self) -> CameraControllerMode {
+        self.mode
+    }
+    pub fn set_mode(&mut self, mode: CameraControllerMode) -> &mut Self {
+        self.mode = mode;
+        self.update_distance();
+        self
+    }
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+    pub fn set_speed(&mut self, speed: f32) -> &mut Self {
+        self.speed = speed.clamp(MIN_CAMERA_SPEED, MAX_CAMERA_SPEED);
+        self
+    }
+    pub fn rotation_speed(&self) -> f32 {
+        self.rotation_speed
+    }
+    pub fn set_rotation_speed(&mut self, rotation_speed: f32) -> &mut Self {
+        self.rotation_speed =
+            rotation_speed.clamp(MIN_CAMERA_ROTATION_SPEED, MAX_CAMERA_ROTATION_SPEED);
+        self
+    }
+    //Speed used to move, with the wheel boost and the modifier keys held
+    pub fn current_speed(&self) -> f32 {
+        let mut speed = self.speed * self.speed_boost;
+        if self.is_faster {
+            speed *= SPEED_MODIFIER;
+        }
+        if self.is_slower {
+            speed /= SPEED_MODIFIER;
+        }
+        speed.clamp(MIN_CAMERA_SPEED, MAX_CAMERA_SPEED)
+    }
+    pub fn set_gamepad_rotation_speed(&mut self, gamepad_rotation_speed: f32) -> &mut Self {
+        self.gamepad_rotation_speed = gamepad_rotation_speed;
+        self
+    }
+    pub fn set_zoom_speed(&mut self, zoom_speed: f32) -> &mut Self {
+        self.zoom_speed = zoom_speed;
+        self
+    }
+    //Point looked at in orbit mode
+    pub fn target(&self) -> Vector3 {
+        self.target
+    }
+    pub fn set_target(&mut self, target: Vector3) -> &mut Self {
+        self.target = target;
+        self.update_distance();
+        self.apply();
+        self
+    }
+    pub fn distance(&self) -> f32 {
+        self.distance
+    }
+    pub fn set_distance(&mut self, distance: f32) -> &mut Self {
+        self.distance = distance.max(MIN_ORBIT_DISTANCE);
+        self.apply();
+        self
+    }
+    pub fn yaw(&self) -> f32 {
+        self.yaw
+    }
+    pub fn pitch(&self) -> f32 {
+        self.pitch
+    }
+    pub fn direction(&self) -> Vector3 {
+        Vector3::new(
+            self.pitch.cos() * self.yaw.sin(),
+            self.pitch.sin(),
+            self.pitch.cos() * self.yaw.cos(),
+        )
+    }
+
+    pub fn on_key_event(&mut self, event: &KeyEvent, dt: f32) -> &mut Self {
+        let is_down = event.state == InputState::Pressed || event.state == InputState::JustPressed;
+        let mut movement = Vector3::default_zero();
+        match event.code {
+            Key::Shift => self.is_faster = is_down,
+            Key::Control => self.is_slower = is_down,
+            Key::W => movement.z += 1.,
+            Key::S => movement.z -= 1.,
+            Key::A => movement.x -= 1.,
+            Key::D => movement.x += 1.,
+            Key::Q => movement.y += 1.,
+            Key::E => movement.y -= 1.,
+            _ => {}
+        }
+        self.move_by(movement * self.current_speed() * dt)
+    }
+
+    //Right or middle button has to be held to rotate, hovering the view doesn't move the camera
+    pub fn is_rotate_button(button: MouseButton) -> bool {
+        matches!(button, MouseButton::Right | MouseButton::Middle)
+    }
+    pub fn is_rotating(&self) -> bool {
+        self.is_rotating
+    }
+
+    //Relative movements keep rotating when the cursor reaches the screen edges.
+    //The wheel boosts the speed until the controller is dropped, without changing the set one
+    pub fn on_mouse_event(&mut self, event: &MouseEvent, dt: f32) -> &mut Self {
+        match event.state {
+            MouseState::Down if Self::is_rotate_button(event.button) => self.is_rotating = true,
+            MouseState::Up if Self::is_rotate_button(event.button) => self.is_rotating = false,
+            MouseState::RelativeMove if self.is_rotating => {
+                let speed = self.rotation_speed * dt;
+                self.rotate(-event.normalized_y * speed, -event.normalized_x * speed);
+            }
+            MouseState::Wheel => {
+                self.speed_boost = (self.speed_boost * WHEEL_SPEED_STEP.powf(event.y as _))
+                    .clamp(1. / MAX_SPEED_BOOST, MAX_SPEED_BOOST);
+            }
+            _ => {}
+        }
+        self
+    }
+
+    //Dragging a finger rotates like the mouse, pinching the first two fingers zooms.
+    //Positions are normalized so the same gesture behaves the same on every screen
+    pub fn on_touch_event(&mut self, event: &TouchEvent, dt: f32) -> &mut Self {
+        let position = Vector2::new(event.normalized_x, event.normalized_y);
+        let index = self.touches.iter().position(|(id, _)| *id == event.id);
+        match (event.state, index) {
+            (TouchState::Begin, None) => self.touches.push((event.id, position)),
+            (TouchState::Move, Some(index)) => {
+                let previous = self.touches[index].1;
+                self.touches[index].1 = position;
+                if self.touches.len() == 1 {
+                    let delta = position - previous;
+                    let speed = self.rotation_speed * dt;
+                    self.rotate(-delta.y * speed, -delta.x * speed);
+                } else if index < 2 {
+                    let other = self.touches[1 - index].1;
+                    let spread = (position - other).length() - (previous - other).length();
+                    self.move_by(Vector3::new(0., 0., spread * PINCH_ZOOM_STEP));
+                }
+            }
+            (TouchState::End | TouchState::Cancel, Some(index)) => {
+                self.touches.remove(index);
+            }
+            _ => {}
+        }
+        self
+    }
+
+    //Axis events are sent only when the sticks move, update applies their last value every frame
+    pub fn on_gamepad_event(&mut self, event: &GamepadEvent) -> &mut Self {
+        match *event {
+            GamepadEvent::AxisChanged(_, axis, value) => match axis {
+                GamepadAxis::LeftStickX => self.left_stick.x = value,
+                GamepadAxis::LeftStickY => self.left_stick.y = value,
+                GamepadAxis::RightStickX => self.right_stick.x = value,
+                GamepadAxis::RightStickY => self.right_stick.y = value,
+                _ => {}
+            },
+            GamepadEvent::Disconnected(_) => {
+                self.left_stick = Vector2::default_zero();
+                self.right_stick = Vector2::default_zero();
+            }
+            _ => {}
+        }
+        self
+    }
+
+    //Keys, buttons and sticks held when the window loses the focus never send their release
+    pub fn release_input(&mut self) -> &mut Self {
+        self.is_faster = false;
+        self.is_slower = false;
+        self.is_rotating = false;
+        self.left_stick = Vector2::default_zero();
+        self.right_stick = Vector2::default_zero();
+        self.touches.clear();
+        self
+    }
+
+    pub fn update(&mut self, dt: f32) -> &mut Self {
+        let movement =
+            Vector3::new(self.left_stick.x, 0., self.left_stick.y) * self.current_speed() * dt;
+        let speed = self.gamepad_rotation_speed * dt;
+        if self.right_stick != Vector2::default_zero() {
+            self.rotate(self.right_stick.y * speed, -self.right_stick.x * speed);
+        }
+        self.move_by(movement)
+    }
+
+    pub fn rotate(&mut self, pitch: f32, yaw: f32) -> &mut Self {
+        if pitch == 0. && yaw == 0. {
+            return self;
+        }
+        self.pitch = (self.pitch + pitch).clamp(-MAX_PITCH, MAX_PITCH);
+        self.yaw += yaw;
+        self.apply();
+        self
+    }
+
+    //Movement is right, up and forward
+    pub fn move_by(&mut self, movement: Vector3) -> &mut Self {
+        if movement == Vector3::default_zero() {
+            return self;
+        }
+        if let Some(parent) = &self.parent {
+            match self.mode {
+                CameraControllerMode::FreeFly => {
+                    let matrix = parent.get().transform();
+                    let translation = matrix.right() * movement.x
+                        + matrix.up() * movement.y
+                        + matrix.forward() * movement.z;
+                    parent.get_mut().translate(translation);
+                }
+                CameraControllerMode::FirstPerson => {
+                    let forward = Vector3::new(self.yaw.sin(), 0., self.yaw.cos());
+                    let right = Vector3::new(-self.yaw.cos(), 0., self.yaw.sin());
+                    parent
+                        .get_mut()
+                        .translate(right * movement.x + forward * movement.z);
+                }
+                CameraControllerMode::Orbit => {
+                    self.distance =
+                        (self.distance - movement.z * self.zoom_speed).max(MIN_ORBIT_DISTANCE);
+                    self.apply();
+                }
+            }
+        }
+        self
+    }
+
+    fn update_distance(&mut self) {
+        if let Some(parent) = &self.parent {
+            if self.mode == CameraControllerMode::Orbit {
+                let position = parent.get().position();
+                self.distance = (self.target - position).length().max(MIN_ORBIT_DISTANCE);
+                if position != self.target {
+                    let direction = (self.target - position).normalized();
+                    self.pitch = direction.y.asin().clamp(-MAX_PITCH, MAX_PITCH);
+                    self.yaw = direction.x.atan2(direction.z);
+                }
+            }
+        }
+    }
+
+    fn apply(&self) {
+        if let Some(parent) = &self.parent {
+            let direction = self.direction();
+            let mut object = parent.get_mut();
+            if self.mode == CameraControllerMode::Orbit {
+                object.set_position(self.target - direction * self.distance);
+            }
+            object.look_towards(direction);
+        }
+    }
+}
+
+#[allow(dead_code)]
+fn test_camera_controller() {
+    use crate::ObjectId;
+    use inox_messenger::MessageHub;
+    use inox_resources::{DataTypeResource, SharedDataRc};
+    use inox_uid::generate_random_resource_uid;
+    use std::sync::Arc;
+
+    let shared_data = SharedDataRc::default();
+    let message_hub = Arc::new(MessageHub::default());
+    shared_data.register_type_serializable::<Object>(&message_hub);
+
+    let id: ObjectId = generate_random_resource_uid();
+    let object = shared_data.add_resource(
+        &message_hub,
+        id,
+        Object::new(id, &shared_data, &message_hub),
+    );
+    let relative_move = |x: f32, y: f32| MouseEvent {
+        normalized_x: x,
+        normalized_y: y,
+        state: MouseState::RelativeMove,
+        ..Default::default()
+    };
+    let button = |button: MouseButton, state: MouseState| MouseEvent {
+        button,
+        state,
+        ..Default::default()
+    };
+    let is_near = |a: f32, b: f32| (a - b).abs() < 1e-3;
+
+    let mut controller = CameraController::new(CameraControllerMode::FreeFly);
+    controller.set_parent(&object).set_rotation_speed(1.);
+    assert_eq!(controller.pitch(), 0.);
+
+    //Moving the mouse without holding the right or middle button doesn't rotate
+    controller.on_mouse_event(&relative_move(-0.5, 0.5), 1.);
+    controller.on_mouse_event(&button(MouseButton::Left, MouseState::Down), 1.);
+    controller.on_mouse_event(&relative_move(-0.5, 0.5), 1.);
+    controller.on_mouse_event(&button(MouseButton::Left, MouseState::Up), 1.);
+    assert!(!controller.is_rotating());
+    assert_eq!((controller.yaw(), controller.pitch()), (0., 0.));
+
+    //Dragging with the right button rotates until it's released
+    controller.on_mouse_event(&button(MouseButton::Right, MouseState::Down), 1.);
+    assert!(controller.is_rotating());
+    controller.on_mouse_event(&relative_move(-0.5, 0.), 1.);
+    assert!(is_near(controller.yaw(), 0.5));
+    controller.on_mouse_event(&button(MouseButton::Right, MouseState::Up), 1.);
+    controller.on_mouse_event(&relative_move(-0.5, 0.), 1.);
+    assert!(!controller.is_rotating());
+    assert!(is_near(controller.yaw(), 0.5));
+
+    //Looking up stops before the pole without flipping the yaw
+    controller.on_mouse_event(&button(MouseButton::Middle, MouseState::Down), 1.);
+    let yaw = controller.yaw();
+    for _ in 0..10 {
+        controller.on_mouse_event(&relative_move(0., -1.), 1.);
+    }
+    assert_eq!(controller.pitch(), MAX_PITCH);
+    assert!(is_near(controller.yaw(), yaw));
+    let direction = object.get().transform().direction();
+    assert!(direction.y < 1.);
+    assert!(is_near(direction.x.atan2(direction.z), yaw));
+    assert!(direction.x.is_finite() && direction.z.is_finite());
+    controller.on_mouse_event(&relative_move(0., 0.5), 1.);
+    assert!(is_near(controller.pitch(), MAX_PITCH - 0.5));
+
+    //First person movement stays on the horizontal plane while looking up
+    controller
+        .set_mode(CameraControllerMode::FirstPerson)
+        .set_speed(1.);
+    controller.move_by(Vector3::new(0., 0., 10.));
+    let position = object.get().position();
+    assert!(is_near(position.y, 0.));
+    assert!(is_near(position.length(), 10.));
+
+    //Orbit keeps the distance from the target while rotating and zoom has a minimum distance
+    object.get_mut().set_position(Vector3::new(0., 0., -10.));
+    controller.set_mode(CameraControllerMode::Orbit);
+    assert!(is_near(controller.distance(), 10.));
+    controller.rotate(0.3, 1.2);
+    let position = object.get().position();
+    assert!(is_near((position - controller.target()).length(), 10.));
+    let direction = object.get().transform().direction();
+    assert!(is_near(
+        direction.dot_product((controller.target() - position).normalized()),
+        1.
+    ));
+    controller.move_by(Vector3::new(0., 0., 100.));
+    assert_eq!(controller.distance(), MIN_ORBIT_DISTANCE);
+
+    //Losing the focus releases what was held so nothing moves when it's regained
+    let mut controller = CameraController::new(CameraControllerMode::FreeFly);
+    controller
+        .set_parent(&object)
+        .set_speed(1.)
+        .on_key_event(
+            &KeyEvent {
+                code: Key::Shift,
+                state: InputState::Pressed,
+            },
+            1.,
+        )
+        .on_gamepad_event(&GamepadEvent::AxisChanged(0, GamepadAxis::LeftStickY, 1.))
+        .on_mouse_event(&button(MouseButton::Right, MouseState::Down), 1.)
+        .release_input();
+    assert!(!controller.is_rotating());
+    assert!(is_near(controller.current_speed(), 1.));
+    let position = object.get().position();
+    let yaw = controller.yaw();
+    controller
+        .update(1.)
+        .on_mouse_event(&relative_move(-0.5, 0.), 1.);
+    assert_eq!(object.get().position(), position);
+    assert!(is_near(controller.yaw(), yaw));
+
+    //Spreading two fingers zooms in, a third finger is ignored and a single one rotates
+    let touch = |id: u64, x: f32, y: f32, state: TouchState| TouchEvent {
+        id,
+        normalized_x: x,
+        normalized_y: y,
+        state,
+        ..Default::default()
+    };
+    object.get_mut().set_position(Vector3::new(0., 0., -10.));
+    let mut controller = CameraController::new(CameraControllerMode::Orbit);
+    controller.set_parent(&object).set_rotation_speed(1.);
+    assert!(is_near(controller.distance(), 10.));
+    let (yaw, pitch) = (controller.yaw(), controller.pitch());
+    controller
+        .on_touch_event(&touch(1, 0.4, 0.5, TouchState::Begin), 1.)
+        .on_touch_event(&touch(2, 0.6, 0.5, TouchState::Begin), 1.)
+        .on_touch_event(&touch(2, 0.8, 0.5, TouchState::Move), 1.);
+    assert!(is_near(controller.distance(), 10. - 0.2 * PINCH_ZOOM_STEP));
+    controller
+        .on_touch_event(&touch(3, 0.1, 0.1, TouchState::Begin), 1.)
+        .on_touch_event(&touch(3, 0.9, 0.9, TouchState::Move), 1.);
+    assert!(is_near(controller.distance(), 10. - 0.2 * PINCH_ZOOM_STEP));
+    assert!(is_near(controller.yaw(), yaw) && is_near(controller.pitch(), pitch));
+    controller
+        .on_touch_event(&touch(1, 0.4, 0.5, TouchState::End), 1.)
+        .on_touch_event(&touch(3, 0.9, 0.9, TouchState::Cancel), 1.)
+        .on_touch_event(&touch(2, 0.3, 0.5, TouchState::Move), 1.);
+    assert!(is_near(controller.yaw(), yaw + 0.5));
+    assert!(is_near(controller.distance(), 10. - 0.2 * PINCH_ZOOM_STEP));
+
+    shared_data.unregister_type_serializable::<Object>(&message_hub);
+}
+
+#[test]
+fn test() {
+    test_camera_controller();
+}
@@ -9,6 +9,12 @@ pub struct ObjectData {
     pub transform: Matrix4,
     pub components: Vec<PathBuf>,
     pub children: Vec<PathBuf>,
+    //Object file used as template, the instance overrides its transform and components
+    #[serde(default)]
+    pub prefab: Option<PathBuf>,
+    //Categories of the object that systems can filter on, like "collision" or "spawn"
+    #[serde(default)]
+    pub tags: Vec<String>,
 }
 
 impl SerializeFile for ObjectData {
@@ -23,6 +29,44 @@ impl Default for ObjectData {
             transform: Matrix4::default_identity(),
             components: Vec::new(),
             children: Vec::new(),
+            prefab: None,
+            tags: Vec::new(),
+        }
+    }
+}
+
+impl ObjectData {
+    //Applies the instance over the data of its prefab:
+    //the transform is the one of the instance, its components replace the ones
+    //of the prefab with the same extension while its children and tags are added to the prefab ones
+    pub fn instantiate(&self, prefab: &ObjectData) -> ObjectData {
+        let is_overridden = |path: &PathBuf| {
+            self.components
+                .iter()
+                .any(|c| c.extension() == path.extension())
+        };
+        ObjectData {
+            transform: self.transform,
+            components: prefab
+                .components
+                .iter()
+                .filter(|c| !is_overridden(c))
+                .chain(self.components.iter())
+                .cloned()
+                .collect(),
+            children: prefab
+                .children
+                .iter()
+                .chain(self.children.iter())
+                .cloned()
+                .collect(),
+            prefab: None,
+            tags: prefab
+                .tags
+                .iter()
+                .chain(self.tags.iter().filter(|t| !prefab.tags.contains(t)))
+                .cloned()
+                .collect(),
         }
     }
 }
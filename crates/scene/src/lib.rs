@@ -6,28 +6,34 @@ use inox_resources::SharedDataRc;
 pub use crate::data::*;
 
 pub use crate::camera::*;
+pub use crate::camera_controller::*;
 pub use crate::object::*;
 pub use crate::scene::*;
 pub use crate::script::*;
+pub use crate::spatial_grid::*;
 pub use crate::systems::*;
 
 pub mod camera;
+pub mod camera_controller;
 pub mod data;
 pub mod object;
 pub mod scene;
 pub mod script;
+pub mod spatial_grid;
 pub mod systems;
 
 pub fn register_resource_types(shared_data: &SharedDataRc, message_hub: &MessageHubRc) {
     shared_data.register_type_serializable::<Object>(message_hub);
     shared_data.register_type_serializable::<Camera>(message_hub);
+    shared_data.register_type::<CameraController>(message_hub);
     shared_data.register_type_serializable::<Script>(message_hub);
-    shared_data.register_type_serializable::<Scene>(message_hub);
+    shared_data.register_type_serializable_with_handoff::<Scene>(message_hub);
 }
 
 pub fn unregister_resource_types(shared_data: &SharedDataRc, message_hub: &MessageHubRc) {
     shared_data.unregister_type_serializable::<Object>(message_hub);
     shared_data.unregister_type_serializable::<Camera>(message_hub);
+    shared_data.unregister_type::<CameraController>(message_hub);
     shared_data.unregister_type_serializable::<Script>(message_hub);
-    shared_data.unregister_type_serializable::<Scene>(message_hub);
+    shared_data.unregister_type_serializable_with_handoff::<Scene>(message_hub);
 }
@@ -1,19 +1,20 @@
 use std::{
     any::TypeId,
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     path::{Path, PathBuf},
 };
 
-use inox_graphics::{Light, Mesh};
-use inox_math::{Mat4Ops, MatBase, Matrix4, Vector3};
+use inox_filesystem::{convert_from_local_path, convert_in_local_path};
+use inox_graphics::{Light, Mesh, LAYER_MASK_ALL};
+use inox_math::{Mat4Ops, MatBase, Matrix4, VecBase, Vector3};
 use inox_messenger::MessageHubRc;
 use inox_resources::{
-    DataTypeResource, GenericResource, Handle, OnCreateData, Resource, ResourceCastTo,
+    Data, DataTypeResource, GenericResource, Handle, OnCreateData, Resource, ResourceCastTo,
     ResourceEvent, ResourceId, ResourceTrait, SerializableResource, SharedDataRc,
 };
 use inox_serialize::{inox_serializable::SerializableRegistryRc, read_from_file, SerializeFile};
 use inox_ui::{CollapsingHeader, UIProperties, UIPropertiesRegistry, Ui};
-use inox_uid::generate_random_uid;
+use inox_uid::{generate_random_resource_uid, resource_uid_to_string};
 
 use crate::{Camera, ObjectData, Script};
 
@@ -28,8 +29,13 @@ pub struct Object {
     transform: Matrix4,
     parent: Handle<Object>,
     is_transform_dirty: bool,
+    is_visible: bool,
+    //False when an ancestor of the object is hidden
+    is_parent_visible: bool,
+    layer_mask: u32,
     children: Vec<Resource<Object>>,
     components: HashMap<TypeId, Vec<GenericResource>>,
+    tags: HashSet<String>,
 }
 
 impl UIProperties for Object {
@@ -40,7 +46,7 @@ impl UIProperties for Object {
         ui: &mut Ui,
         collapsed: bool,
     ) {
-        let mut object_name = format!("Object [{:?}]", id.as_simple().to_string());
+        let mut object_name = format!("Object [{:?}]", resource_uid_to_string(id));
         if let Some(name) = self.path().file_stem() {
             if let Some(name) = name.to_str() {
                 object_name = name.to_string();
@@ -85,12 +91,63 @@ impl SerializableResource for Object {
         ObjectData::extension()
     }
 
+    //Prefabs are resolved before creating the object, an instance of a prefab
+    //gets the already resolved data of its template with its overrides applied
     fn deserialize_data(
         path: &std::path::Path,
         registry: &SerializableRegistryRc,
         f: Box<dyn FnMut(Self::DataType) + 'static>,
     ) {
-        read_from_file::<Self::DataType>(path, registry, f);
+        Self::deserialize_with_prefabs(path, registry, Vec::new(), f);
+    }
+}
+
+impl Object {
+    //Prefabs already met while resolving the file are not read again,
+    //a prefab referencing itself is resolved without its reference
+    fn deserialize_with_prefabs(
+        path: &Path,
+        registry: &SerializableRegistryRc,
+        mut prefabs: Vec<PathBuf>,
+        f: Box<dyn FnMut(ObjectData) + 'static>,
+    ) {
+        prefabs.push(path.to_path_buf());
+        let registry_rc = registry.clone();
+        let mut f = Some(f);
+        read_from_file::<ObjectData>(
+            path,
+            registry,
+            Box::new(move |mut object_data: ObjectData| {
+                let Some(mut f) = f.take() else {
+                    return;
+                };
+                let Some(prefab_path) = object_data.prefab.take() else {
+                    f(object_data);
+                    return;
+                };
+                let prefab_path = convert_from_local_path(
+                    Data::platform_data_folder().as_path(),
+                    prefab_path.as_path(),
+                );
+                if prefabs.contains(&prefab_path) {
+                    inox_log::debug_log!(
+                        "Prefab {:?} references itself through {:?}",
+                        prefab_path,
+                        prefabs
+                    );
+                    f(object_data);
+                    return;
+                }
+                Self::deserialize_with_prefabs(
+                    prefab_path.as_path(),
+                    &registry_rc,
+                    prefabs.clone(),
+                    Box::new(move |prefab_data: ObjectData| {
+                        f(object_data.instantiate(&prefab_data));
+                    }),
+                );
+            }),
+        );
     }
 }
 
@@ -114,8 +171,12 @@ impl DataTypeResource for Object {
             transform: Matrix4::default_identity(),
             parent: None,
             is_transform_dirty: true,
+            is_visible: true,
+            is_parent_visible: true,
+            layer_mask: LAYER_MASK_ALL,
             children: Vec::new(),
             components: HashMap::new(),
+            tags: HashSet::new(),
         }
     }
 
@@ -127,6 +188,7 @@ impl DataTypeResource for Object {
     ) -> Self {
         let mut object = Self::new(id, shared_data, message_hub);
         object.transform = object_data.transform;
+        object.tags = object_data.tags.iter().cloned().collect();
 
         object_data.components.iter().for_each(|component_path| {
             let path = component_path.as_path();
@@ -139,8 +201,10 @@ impl DataTypeResource for Object {
                     path,
                     OnCreateData::create(move |mesh: &mut Mesh| {
                         if let Some(object) = shared_data_rc.get_resource::<Object>(&object_id) {
-                            let parent_matrix = object.get().transform();
+                            let parent_matrix = object.get().world_transform();
                             mesh.set_matrix(parent_matrix);
+                            mesh.set_parent_visible(object.get().is_visible());
+                            mesh.set_layer_mask(object.get().layer_mask());
                         }
                     }),
                 );
@@ -168,8 +232,9 @@ impl DataTypeResource for Object {
                     path,
                     OnCreateData::create(move |light: &mut Light| {
                         if let Some(object) = shared_data_rc.get_resource::<Object>(&object_id) {
-                            let parent_matrix = object.get().transform();
+                            let parent_matrix = object.get().world_transform();
                             light.set_position(parent_matrix.translation());
+                            light.set_direction(-parent_matrix.forward());
                         }
                     }),
                 );
@@ -217,10 +282,25 @@ impl Object {
         self.set_dirty();
         self
     }
+    //Transform relative to the parent object
     #[inline]
     pub fn transform(&self) -> Matrix4 {
         self.transform
     }
+    //Transform of the parent object in world space, identity for objects without parent
+    pub fn parent_transform(&self) -> Matrix4 {
+        self.parent
+            .as_ref()
+            .map_or_else(Matrix4::default_identity, |p| p.get().world_transform())
+    }
+    #[inline]
+    pub fn world_transform(&self) -> Matrix4 {
+        self.parent_transform() * self.transform
+    }
+    #[inline]
+    pub fn set_world_transform(&mut self, transform: Matrix4) -> &mut Self {
+        self.set_transform(self.parent_transform().inverse() * transform)
+    }
     #[inline]
     pub fn set_position(&mut self, position: Vector3) -> &mut Self {
         self.transform.set_translation(position);
@@ -290,13 +370,56 @@ impl Object {
     }
 
     #[inline]
-    fn set_parent(&mut self, parent: Handle<Object>) {
+    pub(crate) fn set_parent(&mut self, parent: Handle<Object>) {
+        let is_parent_visible = parent.as_ref().map_or(true, |p| p.get().is_visible());
         self.parent = parent;
         self.set_dirty();
+        self.set_parent_visible(is_parent_visible);
+    }
+
+    //Hiding an object hides the meshes of all its components and descendants
+    pub fn set_visible(&mut self, is_visible: bool) -> &mut Self {
+        if self.is_visible != is_visible {
+            self.is_visible = is_visible;
+            self.propagate_visibility();
+        }
+        self
+    }
+    //True when the object and all its ancestors are visible
+    pub fn is_visible(&self) -> bool {
+        self.is_visible && self.is_parent_visible
+    }
+    fn set_parent_visible(&mut self, is_parent_visible: bool) {
+        if self.is_parent_visible != is_parent_visible {
+            self.is_parent_visible = is_parent_visible;
+            self.propagate_visibility();
+        }
+    }
+    fn propagate_visibility(&self) {
+        let is_visible = self.is_visible();
+        self.components_of_type::<Mesh>().iter().for_each(|mesh| {
+            mesh.get_mut().set_parent_visible(is_visible);
+        });
+        self.children.iter().for_each(|c| {
+            c.get_mut().set_parent_visible(is_visible);
+        });
+    }
+
+    pub fn layer_mask(&self) -> u32 {
+        self.layer_mask
+    }
+    //Meshes of the object are drawn only in the views with one of these layers in their mask
+    pub fn set_layer_mask(&mut self, layer_mask: u32) -> &mut Self {
+        self.layer_mask = layer_mask;
+        self.components_of_type::<Mesh>().iter().for_each(|mesh| {
+            mesh.get_mut().set_layer_mask(layer_mask);
+        });
+        self
     }
 
     #[inline]
     pub fn add_child(&mut self, child: Resource<Object>) {
+        child.get_mut().set_parent_visible(self.is_visible());
         self.children.push(child);
     }
 
@@ -334,6 +457,21 @@ impl Object {
         &self.children
     }
 
+    pub fn add_tag(&mut self, tag: &str) -> &mut Self {
+        self.tags.insert(tag.to_string());
+        self
+    }
+    pub fn remove_tag(&mut self, tag: &str) -> &mut Self {
+        self.tags.remove(tag);
+        self
+    }
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.contains(tag)
+    }
+    pub fn tags(&self) -> &HashSet<String> {
+        &self.tags
+    }
+
     pub fn components(&self) -> &HashMap<TypeId, Vec<GenericResource>> {
         &self.components
     }
@@ -346,7 +484,7 @@ impl Object {
     where
         C: DataTypeResource + 'static,
     {
-        let id = generate_random_uid();
+        let id = generate_random_resource_uid();
         let resource =
             shared_data.add_resource(message_hub, id, C::new(id, shared_data, message_hub));
         let components = self.components.entry(TypeId::of::<C>()).or_default();
@@ -375,18 +513,319 @@ impl Object {
         result
     }
 
-    pub fn update_transform(&mut self, parent_transform: Option<Matrix4>) {
+    //Data to save the object, with its paths relative to the data folder.
+    //Components and children created at runtime have no file and are left out
+    pub fn object_data(&self) -> ObjectData {
+        let mut tags = self.tags.iter().cloned().collect::<Vec<_>>();
+        tags.sort();
+        ObjectData {
+            transform: self.transform,
+            components: [
+                self.component_paths::<Mesh>(),
+                self.component_paths::<Camera>(),
+                self.component_paths::<Light>(),
+                self.component_paths::<Script>(),
+            ]
+            .concat(),
+            children: self
+                .children
+                .iter()
+                .filter_map(|c| to_data_local_path(c.get().path()))
+                .collect(),
+            prefab: None,
+            tags,
+        }
+    }
+    fn component_paths<C>(&self) -> Vec<PathBuf>
+    where
+        C: SerializableResource + 'static,
+    {
+        self.components_of_type::<C>()
+            .iter()
+            .filter_map(|c| to_data_local_path(c.get().path()))
+            .collect()
+    }
+
+    //World space bounds of the meshes of the object, just its position when it has none
+    pub fn aabb(&self) -> (Vector3, Vector3) {
+        let transform = self.world_transform();
+        let meshes = self.components_of_type::<Mesh>();
+        if meshes.is_empty() {
+            let position = transform.translation();
+            return (position, position);
+        }
+        let mut min = Vector3::default_value(f32::INFINITY);
+        let mut max = Vector3::default_value(f32::NEG_INFINITY);
+        meshes.iter().for_each(|mesh| {
+            let (mesh_min, mesh_max) = (*mesh.get().min(), *mesh.get().max());
+            for i in 0..8 {
+                let corner = Vector3::new(
+                    if i & 1 == 0 { mesh_min.x } else { mesh_max.x },
+                    if i & 2 == 0 { mesh_min.y } else { mesh_max.y },
+                    if i & 4 == 0 { mesh_min.z } else { mesh_max.z },
+                );
+                let point = transform.rotate_point(corner);
+                min = min.min(point);
+                max = max.max(point);
+            }
+        });
+        (min, max)
+    }
+
+    //Sends the world transform to the meshes and lights of the object
+    pub fn update_transform(&mut self) {
         if self.is_dirty() {
             self.is_transform_dirty = false;
-            if let Some(parent_transform) = parent_transform {
-                self.transform = parent_transform * self.transform;
-            }
+            let transform = self.world_transform();
             self.components_of_type::<Mesh>().iter().for_each(|mesh| {
-                mesh.get_mut().set_matrix(self.transform);
+                mesh.get_mut().set_matrix(transform);
             });
+            //Lights point along the negative z axis of their object
             self.components_of_type::<Light>().iter().for_each(|light| {
-                light.get_mut().set_position(self.position());
+                light
+                    .get_mut()
+                    .set_position(transform.translation())
+                    .set_direction(-transform.forward());
             });
         }
     }
 }
+
+//Path relative to the data folder, as the binarizer writes them, None for resources not from file
+pub fn to_data_local_path(path: &Path) -> Option<PathBuf> {
+    if path.as_os_str().is_empty() {
+        return None;
+    }
+    Some(convert_in_local_path(
+        path,
+        Data::platform_data_folder().as_path(),
+    ))
+}
+
+#[allow(dead_code)]
+fn test_prefab() {
+    use inox_messenger::MessageHub;
+    use inox_serialize::serialize_to_file;
+    use std::sync::{Arc, Mutex};
+
+    let shared_data = SharedDataRc::default();
+    let message_hub = Arc::new(MessageHub::default());
+    shared_data.register_type_serializable::<Mesh>(&message_hub);
+    shared_data.register_type_serializable::<Light>(&message_hub);
+    shared_data.register_type_serializable::<Object>(&message_hub);
+    let registry = shared_data.serializable_registry();
+
+    let folder = std::env::temp_dir().join("inox_test_prefab");
+    std::fs::create_dir_all(folder.as_path()).unwrap();
+    let mesh = folder.join("prop.mesh");
+    let broken_mesh = folder.join("prop_broken.mesh");
+    let light = folder.join("prop.light");
+    for path in [&mesh, &broken_mesh, &light] {
+        std::fs::write(path, "{}").unwrap();
+    }
+    let prefab = ObjectData {
+        components: vec![mesh.clone(), light.clone()],
+        ..Default::default()
+    };
+    serialize_to_file(&prefab, folder.join("prop.object").as_path(), registry);
+
+    //Instances only store their transform and the components they override
+    let instance = |x: f32, components: Vec<PathBuf>| ObjectData {
+        transform: Matrix4::from_translation(Vector3::new(x, 0., 0.)),
+        components,
+        prefab: Some(folder.join("prop.object")),
+        ..Default::default()
+    };
+    let instances = [
+        instance(1., Vec::new()),
+        instance(2., Vec::new()),
+        instance(3., vec![broken_mesh.clone()]),
+    ];
+    let resolved = Arc::new(Mutex::new(Vec::new()));
+    instances.iter().enumerate().for_each(|(i, data)| {
+        let path = folder.join(format!("instance_{i}.object"));
+        serialize_to_file(data, path.as_path(), registry);
+        let resolved = resolved.clone();
+        Object::deserialize_data(
+            path.as_path(),
+            registry,
+            Box::new(move |data| resolved.lock().unwrap().push(data)),
+        );
+    });
+    let resolved = resolved.lock().unwrap().clone();
+    assert_eq!(resolved.len(), 3);
+    assert_eq!(resolved[0].components, vec![mesh.clone(), light.clone()]);
+    assert_eq!(resolved[2].components, vec![light, broken_mesh]);
+    assert!(resolved.iter().all(|data| data.prefab.is_none()));
+
+    let objects = resolved
+        .iter()
+        .map(|data| {
+            Object::create_from_data(
+                &shared_data,
+                &message_hub,
+                generate_random_resource_uid(),
+                data,
+            )
+        })
+        .collect::<Vec<_>>();
+    let mesh_id = |object: &Object| *object.components_of_type::<Mesh>()[0].id();
+    assert_eq!(objects[0].position(), Vector3::new(1., 0., 0.));
+    assert_eq!(objects[1].position(), Vector3::new(2., 0., 0.));
+    assert_eq!(mesh_id(&objects[0]), mesh_id(&objects[1]));
+    assert_ne!(mesh_id(&objects[0]), mesh_id(&objects[2]));
+
+    //A prefab reaching itself again stops there instead of recursing forever
+    let looped = ObjectData {
+        components: vec![light.clone()],
+        prefab: Some(folder.join("looped.object")),
+        ..Default::default()
+    };
+    serialize_to_file(&looped, folder.join("looped.object").as_path(), registry);
+    let instance = ObjectData {
+        prefab: Some(folder.join("looped.object")),
+        ..Default::default()
+    };
+    serialize_to_file(
+        &instance,
+        folder.join("looped_instance.object").as_path(),
+        registry,
+    );
+    let resolved = Arc::new(Mutex::new(Vec::new()));
+    for path in ["looped.object", "looped_instance.object"] {
+        let resolved = resolved.clone();
+        Object::deserialize_data(
+            folder.join(path).as_path(),
+            registry,
+            Box::new(move |data| resolved.lock().unwrap().push(data)),
+        );
+    }
+    let resolved = resolved.lock().unwrap().clone();
+    assert_eq!(resolved.len(), 2);
+    assert!(resolved
+        .iter()
+        .all(|data| data.prefab.is_none() && data.components == vec![light.clone()]));
+
+    drop(objects);
+    std::fs::remove_dir_all(folder).ok();
+    shared_data.unregister_type_serializable::<Object>(&message_hub);
+    shared_data.unregister_type_serializable::<Light>(&message_hub);
+    shared_data.unregister_type_serializable::<Mesh>(&message_hub);
+}
+
+#[allow(dead_code)]
+fn test_tags() {
+    use inox_messenger::MessageHub;
+    use std::sync::Arc;
+
+    let shared_data = SharedDataRc::default();
+    let message_hub = Arc::new(MessageHub::default());
+
+    let prefab = ObjectData {
+        tags: vec!["collision".to_string()],
+        ..Default::default()
+    };
+    let instance = ObjectData {
+        tags: vec!["spawn".to_string(), "collision".to_string()],
+        ..Default::default()
+    };
+    let data = instance.instantiate(&prefab);
+    assert_eq!(
+        data.tags,
+        vec!["collision".to_string(), "spawn".to_string()]
+    );
+
+    let mut object = Object::create_from_data(
+        &shared_data,
+        &message_hub,
+        generate_random_resource_uid(),
+        &data,
+    );
+    assert!(object.has_tag("collision"));
+    assert!(object.has_tag("spawn"));
+    assert!(!object.has_tag("nav"));
+
+    object.add_tag("nav").remove_tag("collision");
+    assert!(object.has_tag("nav"));
+    assert!(!object.has_tag("collision"));
+    assert_eq!(object.tags().len(), 2);
+}
+
+#[allow(dead_code)]
+fn test_visibility() {
+    use inox_graphics::{create_cube, DrawCommandType, MeshFlags, RenderBuffers};
+    use inox_messenger::MessageHub;
+    use std::sync::Arc;
+
+    let shared_data = SharedDataRc::default();
+    let message_hub = Arc::new(MessageHub::default());
+    shared_data.register_type_serializable::<Mesh>(&message_hub);
+    shared_data.register_type_serializable::<Object>(&message_hub);
+
+    let add_object = || {
+        let id = generate_random_resource_uid();
+        shared_data.add_resource(
+            &message_hub,
+            id,
+            Object::new(id, &shared_data, &message_hub),
+        )
+    };
+    let parent = add_object();
+    let child = add_object();
+    let grandchild = add_object();
+    child.get_mut().add_child(grandchild.clone());
+    parent.get_mut().add_child(child.clone());
+
+    let render_buffers = RenderBuffers::default();
+    let meshes = [&parent, &child, &grandchild]
+        .iter()
+        .map(|o| {
+            let mesh = o
+                .get_mut()
+                .add_default_component::<Mesh>(&shared_data, &message_hub);
+            render_buffers
+                .add_mesh(
+                    mesh.id(),
+                    &create_cube(Vector3::new(1., 1., 1.), [1., 1., 1., 1.].into()),
+                )
+                .unwrap();
+            mesh
+        })
+        .collect::<Vec<_>>();
+    let drawn_meshes = || {
+        meshes.iter().for_each(|mesh| {
+            render_buffers.change_mesh(mesh.id(), &mut mesh.get_mut());
+        });
+        let commands = render_buffers.commands.read().unwrap();
+        commands
+            .get(&(MeshFlags::Visible | MeshFlags::Opaque))
+            .and_then(|entry| entry.map.get(&DrawCommandType::PerMeshlet))
+            .map_or(0, |entry| entry.commands.item_count())
+    };
+    assert_eq!(drawn_meshes(), 3);
+
+    //Hiding the parent hides the meshes of all its descendants
+    child.get_mut().set_visible(false);
+    assert_eq!(drawn_meshes(), 1);
+    parent.get_mut().set_visible(false);
+    assert_eq!(drawn_meshes(), 0);
+    assert!(!grandchild.get().is_visible());
+
+    //Showing the parent restores only what is not hidden by itself
+    parent.get_mut().set_visible(true);
+    assert_eq!(drawn_meshes(), 1);
+    child.get_mut().set_visible(true);
+    assert_eq!(drawn_meshes(), 3);
+    assert!(meshes.iter().all(|mesh| mesh.get().is_parent_visible()));
+
+    drop(meshes);
+    shared_data.unregister_type_serializable::<Object>(&message_hub);
+    shared_data.unregister_type_serializable::<Mesh>(&message_hub);
+}
+
+#[test]
+fn test() {
+    test_prefab();
+    test_tags();
+    test_visibility();
+}
@@ -1,18 +1,43 @@
-use std::path::{Path, PathBuf};
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
-use inox_messenger::MessageHubRc;
+use inox_commands::CommandParser;
+use inox_messenger::{implement_message, MessageHubRc};
 use inox_nodes::LogicData;
 use inox_resources::{
     DataTypeResource, Handle, Resource, ResourceId, ResourceTrait, SerializableResource,
     SharedDataRc,
 };
 use inox_serialize::{inox_serializable::SerializableRegistryRc, read_from_file, SerializeFile};
-use inox_time::Timer;
 
 use crate::Object;
 
 pub type ScriptId = ResourceId;
 
+pub enum ScriptEvent {
+    Reloaded(ScriptId),
+}
+implement_message!(
+    ScriptEvent,
+    message_from_command_parser,
+    compare_and_discard
+);
+
+impl ScriptEvent {
+    fn compare_and_discard(&self, other: &Self) -> bool {
+        match self {
+            Self::Reloaded(id) => match other {
+                Self::Reloaded(other_id) => id == other_id,
+            },
+        }
+    }
+    fn message_from_command_parser(_command_parser: CommandParser) -> Option<Self> {
+        None
+    }
+}
+
 #[derive(Clone)]
 pub struct Script {
     filepath: PathBuf,
@@ -89,9 +114,15 @@ impl Script {
         self
     }
 
-    pub fn update(&mut self, timer: &Timer) {
+    //Swaps the logic with the one of the data.
+    //Returns false when its topology changed and the script restarted from scratch
+    pub fn reload(&mut self, data: LogicData) -> bool {
+        self.logic.reload(data)
+    }
+
+    pub fn update(&mut self, dt: &Duration) {
         if self.logic.is_initialized() {
-            self.logic.execute(timer.dt());
+            self.logic.execute(dt);
         }
     }
 }
@@ -0,0 +1,233 @@
+use std::{
+    collections::{HashMap, HashSet},
+    hash::Hash,
+};
+
+use inox_math::{VecBase, Vector3};
+
+pub const DEFAULT_CELL_SIZE: f32 = 8.;
+//Entries covering more cells than this are kept apart and tested by every query
+const MAX_CELLS_PER_ENTRY: i64 = 512;
+
+type Cell = [i32; 3];
+
+//Uniform grid over the bounds of its entries.
+//Moving an entry only updates the cells it leaves and the ones it enters
+#[derive(Clone)]
+pub struct SpatialGrid<K> {
+    cell_size: f32,
+    cells: HashMap<Cell, Vec<K>>,
+    large_entries: Vec<K>,
+    bounds: HashMap<K, (Vector3, Vector3)>,
+}
+
+impl<K> Default for SpatialGrid<K>
+where
+    K: Copy + Eq + Hash,
+{
+    fn default() -> Self {
+        Self::new(DEFAULT_CELL_SIZE)
+    }
+}
+
+impl<K> SpatialGrid<K>
+where
+    K: Copy + Eq + Hash,
+{
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size: cell_size.max(f32::EPSILON),
+            cells: HashMap::new(),
+            large_entries: Vec::new(),
+            bounds: HashMap::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.bounds.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.bounds.is_empty()
+    }
+    pub fn contains(&self, key: &K) -> bool {
+        self.bounds.contains_key(key)
+    }
+    pub fn bounds(&self, key: &K) -> Option<&(Vector3, Vector3)> {
+        self.bounds.get(key)
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.clear();
+        self.large_entries.clear();
+        self.bounds.clear();
+    }
+
+    //Returns false when the entry was already there with the same bounds
+    pub fn insert(&mut self, key: K, min: Vector3, max: Vector3) -> bool {
+        if let Some(bounds) = self.bounds.get(&key) {
+            if *bounds == (min, max) {
+                return false;
+            }
+            self.remove(&key);
+        }
+        match self.cell_range(min, max) {
+            Some((from, to)) => Self::for_each_cell(from, to, |cell| {
+                self.cells.entry(cell).or_default().push(key);
+            }),
+            None => self.large_entries.push(key),
+        }
+        self.bounds.insert(key, (min, max));
+        true
+    }
+
+    pub fn remove(&mut self, key: &K) -> bool {
+        if let Some((min, max)) = self.bounds.remove(key) {
+            match self.cell_range(min, max) {
+                Some((from, to)) => Self::for_each_cell(from, to, |cell| {
+                    if let Some(entries) = self.cells.get_mut(&cell) {
+                        entries.retain(|k| k != key);
+                        if entries.is_empty() {
+                            self.cells.remove(&cell);
+                        }
+                    }
+                }),
+                None => self.large_entries.retain(|k| k != key),
+            }
+            return true;
+        }
+        false
+    }
+
+    //Entries with bounds overlapping the box, even only partially
+    pub fn query_aabb(&self, min: Vector3, max: Vector3) -> Vec<K> {
+        self.query(min, max, |(entry_min, entry_max)| {
+            (0..3).all(|i| entry_min[i] <= max[i] && entry_max[i] >= min[i])
+        })
+    }
+
+    //Entries with bounds overlapping the sphere, even only partially
+    pub fn query_sphere(&self, center: Vector3, radius: f32) -> Vec<K> {
+        let extent = Vector3::default_value(radius);
+        self.query(
+            center - extent,
+            center + extent,
+            |(entry_min, entry_max)| {
+                let closest = center.max(*entry_min).min(*entry_max);
+                closest.squared_distance(center) <= radius * radius
+            },
+        )
+    }
+
+    fn query(
+        &self,
+        min: Vector3,
+        max: Vector3,
+        is_overlapping: impl Fn(&(Vector3, Vector3)) -> bool,
+    ) -> Vec<K> {
+        let mut visited = HashSet::new();
+        let mut result = Vec::new();
+        let mut test = |key: &K| {
+            if visited.insert(*key) && self.bounds.get(key).map_or(false, &is_overlapping) {
+                result.push(*key);
+            }
+        };
+        self.large_entries.iter().for_each(&mut test);
+        match self.cell_range(min, max) {
+            Some((from, to)) => Self::for_each_cell(from, to, |cell| {
+                if let Some(entries) = self.cells.get(&cell) {
+                    entries.iter().for_each(&mut test);
+                }
+            }),
+            None => self.bounds.keys().for_each(test),
+        }
+        result
+    }
+
+    //None when the bounds cover too many cells to be stored in them
+    fn cell_range(&self, min: Vector3, max: Vector3) -> Option<(Cell, Cell)> {
+        let mut from = [0; 3];
+        let mut to = [0; 3];
+        let mut count = 1i64;
+        for i in 0..3 {
+            let start = (min[i] / self.cell_size).floor();
+            let end = (max[i] / self.cell_size).floor();
+            if !start.is_finite() || !end.is_finite() || end < start {
+                return None;
+            }
+            count *= (end - start) as i64 + 1;
+            if count > MAX_CELLS_PER_ENTRY {
+                return None;
+            }
+            from[i] = start as i32;
+            to[i] = end as i32;
+        }
+        Some((from, to))
+    }
+
+    fn for_each_cell(from: Cell, to: Cell, mut f: impl FnMut(Cell)) {
+        for x in from[0]..=to[0] {
+            for y in from[1]..=to[1] {
+                for z in from[2]..=to[2] {
+                    f([x, y, z]);
+                }
+            }
+        }
+    }
+}
+
+#[allow(dead_code)]
+fn test_spatial_grid() {
+    let mut grid = SpatialGrid::new(1.);
+    let sorted = |mut keys: Vec<u32>| {
+        keys.sort();
+        keys
+    };
+
+    //Object 0 straddles the cells boundary at x = 1
+    grid.insert(0, Vector3::new(0.5, 0., 0.), Vector3::new(1.5, 0.5, 0.5));
+    grid.insert(1, Vector3::new(3., 0., 0.), Vector3::new(3.5, 0.5, 0.5));
+    grid.insert(
+        2,
+        Vector3::new(-10., -10., -10.),
+        Vector3::new(10., 10., 10.),
+    );
+    assert_eq!(grid.len(), 3);
+
+    //Boxes overlapping only a part of an object still find it, touching counts as overlapping
+    let query = |grid: &SpatialGrid<u32>, min: [f32; 3], max: [f32; 3]| {
+        sorted(grid.query_aabb(min.into(), max.into()))
+    };
+    assert_eq!(query(&grid, [1.2, 0., 0.], [2., 1., 1.]), vec![0, 2]);
+    assert_eq!(query(&grid, [-1., 0., 0.], [0.5, 1., 1.]), vec![0, 2]);
+    assert_eq!(query(&grid, [1.6, 0., 0.], [2.9, 1., 1.]), vec![2]);
+    assert_eq!(
+        query(&grid, [20., 20., 20.], [21., 21., 21.]),
+        Vec::<u32>::new()
+    );
+
+    //Spheres overlapping only a part of an object still find it
+    let center = Vector3::new(2., 0.25, 0.25);
+    assert_eq!(sorted(grid.query_sphere(center, 0.5)), vec![0, 2]);
+    assert_eq!(sorted(grid.query_sphere(center, 1.)), vec![0, 1, 2]);
+    assert_eq!(sorted(grid.query_sphere(center, 0.4)), vec![2]);
+
+    //Moving an object leaves its old cells
+    assert!(grid.insert(0, Vector3::new(5., 0., 0.), Vector3::new(5.5, 0.5, 0.5)));
+    assert!(!grid.insert(0, Vector3::new(5., 0., 0.), Vector3::new(5.5, 0.5, 0.5)));
+    assert_eq!(query(&grid, [1.2, 0., 0.], [2., 1., 1.]), vec![2]);
+    assert_eq!(query(&grid, [4.9, 0., 0.], [5., 1., 1.]), vec![0, 2]);
+
+    assert!(grid.remove(&2));
+    assert!(!grid.remove(&2));
+    assert_eq!(
+        query(&grid, [-100., -100., -100.], [100., 100., 100.]),
+        vec![0, 1]
+    );
+    grid.clear();
+    assert!(grid.is_empty());
+}
+
+#[test]
+fn test() {
+    test_spatial_grid();
+}
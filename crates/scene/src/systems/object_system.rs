@@ -1,16 +1,15 @@
-use std::collections::HashMap;
+use std::collections::HashSet;
 
 use inox_core::{implement_unique_system_uid, ContextRc, System};
-use inox_math::{MatBase, Matrix4};
 use inox_messenger::Listener;
 use inox_resources::{ResourceEvent, SharedDataRc};
 
-use crate::{Object, ObjectId};
+use crate::{Object, ObjectId, Scene};
 
 pub struct ObjectSystem {
     shared_data: SharedDataRc,
     listener: Listener,
-    map: HashMap<ObjectId, Matrix4>,
+    changed_objects: HashSet<ObjectId>,
 }
 
 implement_unique_system_uid!(ObjectSystem);
@@ -30,12 +29,21 @@ impl System for ObjectSystem {
 
         self.update_events();
 
-        self.map.retain(|id, m| {
-            if let Some(o) = self.shared_data.get_resource::<Object>(id) {
-                o.get_mut().update_transform(Some(*m));
+        let mut moved_objects = Vec::new();
+        self.changed_objects.drain().for_each(|id| {
+            if let Some(o) = self.shared_data.get_resource::<Object>(&id) {
+                o.get_mut().update_transform();
+                moved_objects.push(o);
             }
-            false
         });
+        if !moved_objects.is_empty() {
+            self.shared_data
+                .for_each_resource_mut(|_, scene: &mut Scene| {
+                    moved_objects.iter().for_each(|o| {
+                        scene.update_object(o);
+                    });
+                });
+        }
 
         true
     }
@@ -49,21 +57,14 @@ impl ObjectSystem {
         Self {
             shared_data: context.shared_data().clone(),
             listener: Listener::new(context.message_hub()),
-            map: HashMap::new(),
+            changed_objects: HashSet::new(),
         }
     }
     fn update_events(&mut self) {
         inox_profiler::scoped_profile!("object_system::update_events");
         self.listener.process_messages(|e: &ResourceEvent<Object>| {
             if let ResourceEvent::Changed(id) = e {
-                if let Some(o) = self.shared_data.get_resource::<Object>(id) {
-                    let parent_transform = o
-                        .get()
-                        .parent()
-                        .map(|parent| parent.get().transform())
-                        .unwrap_or_else(Matrix4::default_identity);
-                    self.map.insert(*id, parent_transform);
-                }
+                self.changed_objects.insert(*id);
             }
         });
     }
@@ -1,9 +1,19 @@
+use std::collections::HashMap;
+
 use inox_core::{implement_unique_system_uid, ContextRc, System};
+use inox_filesystem::convert_from_local_path;
+use inox_messenger::Listener;
+use inox_nodes::LogicData;
+use inox_platform::{FileEvent, FileWatcher};
+use inox_resources::{Data, SerializableResource};
+use inox_serialize::read_from_file;
 
-use crate::Script;
+use crate::{Script, ScriptEvent, ScriptId};
 
 pub struct ScriptSystem {
     context: ContextRc,
+    listener: Listener,
+    watchers: HashMap<ScriptId, FileWatcher>,
 }
 
 implement_unique_system_uid!(ScriptSystem);
@@ -13,27 +23,110 @@ impl System for ScriptSystem {
     fn should_run_when_not_focused(&self) -> bool {
         false
     }
+    fn uses_fixed_timestep(&self) -> bool {
+        true
+    }
 
-    fn init(&mut self) {}
+    fn init(&mut self) {
+        self.listener.register::<ScriptEvent>();
+    }
 
     fn run(&mut self) -> bool {
-        inox_profiler::scoped_profile!("object_system::run");
+        inox_profiler::scoped_profile!("script_system::run");
 
-        let timer = self.context.global_timer();
+        self.update_watchers();
+        self.update_events();
+
+        let fixed_dt = *self.context.global_timer().fixed_dt();
         self.context
             .shared_data()
             .for_each_resource_mut(|_, s: &mut Script| {
-                s.update(&timer);
+                s.update(&fixed_dt);
             });
         true
     }
-    fn uninit(&mut self) {}
+    fn uninit(&mut self) {
+        self.watchers.values_mut().for_each(|w| w.stop());
+        self.watchers.clear();
+        self.listener.unregister::<ScriptEvent>();
+    }
 }
 
 impl ScriptSystem {
     pub fn new(context: &ContextRc) -> Self {
         Self {
             context: context.clone(),
+            listener: Listener::new(context.message_hub()),
+            watchers: HashMap::new(),
         }
     }
+
+    fn update_watchers(&mut self) {
+        inox_profiler::scoped_profile!("script_system::update_watchers");
+
+        let shared_data = self.context.shared_data();
+        self.watchers.retain(|id, w| {
+            let is_alive = shared_data.get_resource::<Script>(id).is_some();
+            if !is_alive {
+                w.stop();
+            }
+            is_alive
+        });
+        //Scripts loaded from a file are watched to reload them when it changes
+        let mut new_scripts = Vec::new();
+        shared_data.for_each_resource(|r, s: &Script| {
+            if !self.watchers.contains_key(r.id()) && !s.path().as_os_str().is_empty() {
+                let path =
+                    convert_from_local_path(Data::platform_data_folder().as_path(), s.path());
+                if path.exists() {
+                    new_scripts.push((*r.id(), path));
+                }
+            }
+        });
+        new_scripts.into_iter().for_each(|(id, path)| {
+            self.watchers.insert(id, FileWatcher::new(path));
+        });
+
+        let message_hub = self.context.message_hub();
+        self.watchers.iter().for_each(|(id, w)| {
+            let mut is_changed = false;
+            while let Ok(event) = w.read_events().try_recv() {
+                match event {
+                    FileEvent::Modified(path)
+                    | FileEvent::Created(path)
+                    | FileEvent::RenamedTo(path) => {
+                        is_changed |= w.get_path().eq(&path);
+                    }
+                    _ => {}
+                }
+            }
+            if is_changed {
+                message_hub.send_event(ScriptEvent::Reloaded(*id));
+            }
+        });
+    }
+
+    fn update_events(&mut self) {
+        inox_profiler::scoped_profile!("script_system::update_events");
+
+        let shared_data = self.context.shared_data();
+        self.listener.process_messages(|e: &ScriptEvent| {
+            let ScriptEvent::Reloaded(id) = e;
+            let Some(path) = self.watchers.get(id).map(|w| w.get_path()) else {
+                return;
+            };
+            //A script that fails to parse is only logged by read_from_file and keeps running
+            let id = *id;
+            let shared_data_rc = shared_data.clone();
+            read_from_file(
+                path.as_path(),
+                shared_data.serializable_registry(),
+                Box::new(move |data: LogicData| {
+                    if let Some(script) = shared_data_rc.get_resource::<Script>(&id) {
+                        script.get_mut().reload(data);
+                    }
+                }),
+            );
+        });
+    }
 }
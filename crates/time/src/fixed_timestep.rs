@@ -0,0 +1,98 @@
+use std::time::Duration;
+
+pub const DEFAULT_FIXED_DT: Duration = Duration::from_micros(16_667);
+pub const DEFAULT_MAX_FIXED_STEPS_PER_FRAME: u32 = 5;
+
+pub struct FixedTimestep {
+    fixed_dt: Duration,
+    max_steps_per_frame: u32,
+    accumulator: Duration,
+    steps: u32,
+}
+
+impl Default for FixedTimestep {
+    fn default() -> Self {
+        Self {
+            fixed_dt: DEFAULT_FIXED_DT,
+            max_steps_per_frame: DEFAULT_MAX_FIXED_STEPS_PER_FRAME,
+            accumulator: Duration::default(),
+            steps: 0,
+        }
+    }
+}
+
+impl FixedTimestep {
+    pub fn new(fixed_dt: Duration, max_steps_per_frame: u32) -> Self {
+        let mut fixed_timestep = Self::default();
+        fixed_timestep
+            .set_fixed_dt(fixed_dt)
+            .set_max_steps_per_frame(max_steps_per_frame);
+        fixed_timestep
+    }
+
+    //Accumulates the frame time and returns how many fixed steps have to run this frame
+    pub fn advance(&mut self, dt: Duration) -> u32 {
+        self.accumulator += dt;
+        let available_steps = (self.accumulator.as_nanos() / self.fixed_dt.as_nanos()) as u32;
+        self.steps = available_steps.min(self.max_steps_per_frame);
+        self.accumulator -= self.fixed_dt * self.steps;
+        if available_steps > self.max_steps_per_frame {
+            //Spiral of death guard: after a stall drop the time that can't be simulated
+            //instead of trying to catch up in the next frames
+            self.accumulator = Duration::from_nanos(
+                (self.accumulator.as_nanos() % self.fixed_dt.as_nanos()) as u64,
+            );
+        }
+        self.steps
+    }
+
+    pub fn fixed_dt(&self) -> &Duration {
+        &self.fixed_dt
+    }
+    pub fn set_fixed_dt(&mut self, fixed_dt: Duration) -> &mut Self {
+        debug_assert!(!fixed_dt.is_zero(), "Fixed timestep can't be zero");
+        self.fixed_dt = fixed_dt.max(Duration::from_nanos(1));
+        self
+    }
+
+    pub fn max_steps_per_frame(&self) -> u32 {
+        self.max_steps_per_frame
+    }
+    pub fn set_max_steps_per_frame(&mut self, max_steps_per_frame: u32) -> &mut Self {
+        self.max_steps_per_frame = max_steps_per_frame.max(1);
+        self
+    }
+
+    pub fn steps(&self) -> u32 {
+        self.steps
+    }
+
+    //Fraction of a fixed step left in the accumulator, to interpolate between the last two states
+    pub fn alpha(&self) -> f32 {
+        self.accumulator.as_secs_f32() / self.fixed_dt.as_secs_f32()
+    }
+}
+
+#[allow(dead_code)]
+fn test_fixed_timestep() {
+    let fixed_dt = Duration::from_millis(10);
+    let mut fixed_timestep = FixedTimestep::new(fixed_dt, 4);
+
+    assert_eq!(fixed_timestep.advance(Duration::from_millis(5)), 0);
+    assert!((fixed_timestep.alpha() - 0.5).abs() < 1e-5);
+    assert_eq!(fixed_timestep.advance(Duration::from_millis(5)), 1);
+    assert_eq!(fixed_timestep.alpha(), 0.);
+    assert_eq!(fixed_timestep.advance(Duration::from_millis(25)), 2);
+    assert!((fixed_timestep.alpha() - 0.5).abs() < 1e-5);
+
+    //A stall is capped and the time that exceeds the cap is not carried over
+    assert_eq!(fixed_timestep.advance(Duration::from_secs(1)), 4);
+    assert!((fixed_timestep.alpha() - 0.5).abs() < 1e-5);
+    assert_eq!(fixed_timestep.advance(Duration::from_millis(5)), 1);
+    assert_eq!(fixed_timestep.alpha(), 0.);
+}
+
+#[test]
+fn test() {
+    test_fixed_timestep();
+}
@@ -1,5 +1,7 @@
+pub use crate::fixed_timestep::*;
 pub use crate::platform::*;
 pub use crate::timer::*;
 
+pub mod fixed_timestep;
 pub mod platform;
 pub mod timer;
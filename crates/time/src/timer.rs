@@ -4,6 +4,8 @@ use std::{
     time::Duration,
 };
 
+use crate::FixedTimestep;
+
 #[cfg(target_arch = "wasm32")]
 use crate::platform::wasm::SystemTime;
 #[cfg(not(target_arch = "wasm32"))]
@@ -14,6 +16,7 @@ pub struct Timer {
     current_time: SystemTime,
     dt: Duration,
     fps: VecDeque<SystemTime>,
+    fixed_timestep: FixedTimestep,
 }
 
 impl Default for Timer {
@@ -23,6 +26,7 @@ impl Default for Timer {
             fps: VecDeque::new(),
             current_time: SystemTime::now(),
             dt: Duration::default(),
+            fixed_timestep: FixedTimestep::default(),
         }
     }
 }
@@ -41,6 +45,8 @@ impl Timer {
         self.fps.push_back(self.current_time);
         self.fps.retain(|t| *t >= one_sec_before);
 
+        self.fixed_timestep.advance(self.dt);
+
         self
     }
 
@@ -69,6 +75,26 @@ impl Timer {
     pub fn fps(&self) -> u32 {
         self.fps.len() as _
     }
+
+    pub fn fixed_timestep(&self) -> &FixedTimestep {
+        &self.fixed_timestep
+    }
+
+    pub fn fixed_timestep_mut(&mut self) -> &mut FixedTimestep {
+        &mut self.fixed_timestep
+    }
+
+    pub fn fixed_dt(&self) -> &Duration {
+        self.fixed_timestep.fixed_dt()
+    }
+
+    pub fn fixed_steps(&self) -> u32 {
+        self.fixed_timestep.steps()
+    }
+
+    pub fn fixed_alpha(&self) -> f32 {
+        self.fixed_timestep.alpha()
+    }
 }
 
 pub type TimerRw = Arc<RwLock<Timer>>;
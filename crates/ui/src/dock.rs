@@ -0,0 +1,636 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use egui::{
+    Area, Color32, Context, CursorIcon, Id, LayerId, Order, Pos2, Rect, SelectableLabel, Sense,
+    Vec2, Window,
+};
+use inox_serialize::{Deserialize, Serialize};
+
+const TAB_BAR_HEIGHT: f32 = 20.;
+const SPLITTER_SIZE: f32 = 4.;
+const MIN_SPLIT_FRACTION: f32 = 0.1;
+//Part of a panel, from its borders, where a dropped tab is docked beside it
+const DOCK_BORDER_FRACTION: f32 = 0.25;
+//Frame of the docked windows around their content
+const WINDOW_MARGIN: f32 = 6.;
+const DROP_TARGET_COLOR: Color32 = Color32::from_rgba_premultiplied(25, 40, 64, 64);
+
+pub type DockRw = Arc<RwLock<Dock>>;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(crate = "inox_serialize")]
+pub enum DockSplit {
+    //Children side by side
+    Horizontal,
+    //Children one above the other
+    Vertical,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DockPosition {
+    Center,
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(crate = "inox_serialize")]
+pub enum DockNode {
+    Tabs {
+        panels: Vec<String>,
+        active: usize,
+    },
+    Split {
+        split: DockSplit,
+        //Part of the space given to the first child
+        fraction: f32,
+        children: Box<[DockNode; 2]>,
+    },
+}
+
+impl Default for DockNode {
+    fn default() -> Self {
+        Self::Tabs {
+            panels: Vec::new(),
+            active: 0,
+        }
+    }
+}
+
+impl DockNode {
+    pub fn tabs(panels: &[&str]) -> Self {
+        Self::Tabs {
+            panels: panels.iter().map(|p| p.to_string()).collect(),
+            active: 0,
+        }
+    }
+    pub fn split(split: DockSplit, fraction: f32, first: DockNode, second: DockNode) -> Self {
+        Self::Split {
+            split,
+            fraction: fraction.clamp(MIN_SPLIT_FRACTION, 1. - MIN_SPLIT_FRACTION),
+            children: Box::new([first, second]),
+        }
+    }
+    fn contains(&self, panel: &str) -> bool {
+        match self {
+            Self::Tabs { panels, .. } => panels.iter().any(|p| p == panel),
+            Self::Split { children, .. } => children.iter().any(|c| c.contains(panel)),
+        }
+    }
+    fn tabs_of_mut(&mut self, panel: &str) -> Option<&mut DockNode> {
+        if let Self::Split { children, .. } = self {
+            return children.iter_mut().find_map(|c| c.tabs_of_mut(panel));
+        }
+        if self.contains(panel) {
+            Some(self)
+        } else {
+            None
+        }
+    }
+    //Returns false when the node is left without panels.
+    //A split with an empty child is replaced by the other one
+    fn remove(&mut self, panel: &str) -> bool {
+        match self {
+            Self::Tabs { panels, active } => {
+                if let Some(index) = panels.iter().position(|p| p == panel) {
+                    panels.remove(index);
+                    if index < *active {
+                        *active -= 1;
+                    }
+                    *active = (*active).min(panels.len().saturating_sub(1));
+                }
+                !panels.is_empty()
+            }
+            Self::Split { children, .. } => {
+                match [children[0].remove(panel), children[1].remove(panel)] {
+                    [true, true] => true,
+                    [false, false] => false,
+                    [is_first_kept, _] => {
+                        let kept = std::mem::take(&mut children[if is_first_kept { 0 } else { 1 }]);
+                        *self = kept;
+                        true
+                    }
+                }
+            }
+        }
+    }
+    fn insert(&mut self, panel: &str, position: DockPosition) {
+        if let Self::Tabs { panels, active } = self {
+            if position == DockPosition::Center || panels.is_empty() {
+                panels.push(panel.to_string());
+                *active = panels.len() - 1;
+                return;
+            }
+        }
+        let (split, is_first) = match position {
+            DockPosition::Left => (DockSplit::Horizontal, true),
+            DockPosition::Top => (DockSplit::Vertical, true),
+            DockPosition::Bottom => (DockSplit::Vertical, false),
+            DockPosition::Right | DockPosition::Center => (DockSplit::Horizontal, false),
+        };
+        let new = Self::tabs(&[panel]);
+        let old = std::mem::take(self);
+        *self = if is_first {
+            Self::split(split, 0.5, new, old)
+        } else {
+            Self::split(split, 0.5, old, new)
+        };
+    }
+    fn layout(&self, rect: Rect, path: &mut Vec<usize>, areas: &mut Vec<DockArea>) {
+        match self {
+            Self::Tabs { panels, active } => {
+                let bar_bottom = (rect.min.y + TAB_BAR_HEIGHT).min(rect.max.y);
+                areas.push(DockArea::Tabs {
+                    path: path.clone(),
+                    tab_bar: Rect::from_min_max(rect.min, Pos2::new(rect.max.x, bar_bottom)),
+                    content: Rect::from_min_max(Pos2::new(rect.min.x, bar_bottom), rect.max),
+                    panels: panels.clone(),
+                    active: *active,
+                });
+            }
+            Self::Split {
+                split,
+                fraction,
+                children,
+            } => {
+                let half = SPLITTER_SIZE * 0.5;
+                let (first, handle, second) = match split {
+                    DockSplit::Horizontal => {
+                        let x = rect.min.x + rect.width() * fraction;
+                        (
+                            Rect::from_min_max(rect.min, Pos2::new(x - half, rect.max.y)),
+                            Rect::from_min_max(
+                                Pos2::new(x - half, rect.min.y),
+                                Pos2::new(x + half, rect.max.y),
+                            ),
+                            Rect::from_min_max(Pos2::new(x + half, rect.min.y), rect.max),
+                        )
+                    }
+                    DockSplit::Vertical => {
+                        let y = rect.min.y + rect.height() * fraction;
+                        (
+                            Rect::from_min_max(rect.min, Pos2::new(rect.max.x, y - half)),
+                            Rect::from_min_max(
+                                Pos2::new(rect.min.x, y - half),
+                                Pos2::new(rect.max.x, y + half),
+                            ),
+                            Rect::from_min_max(Pos2::new(rect.min.x, y + half), rect.max),
+                        )
+                    }
+                };
+                areas.push(DockArea::Splitter {
+                    path: path.clone(),
+                    handle,
+                    split: *split,
+                    rect,
+                });
+                [first, second]
+                    .into_iter()
+                    .zip(children.iter())
+                    .enumerate()
+                    .for_each(|(i, (child_rect, child))| {
+                        path.push(i);
+                        child.layout(child_rect, path, areas);
+                        path.pop();
+                    });
+            }
+        }
+    }
+}
+
+//Screen areas of a dock tree, identified by the children indices from the root to their node
+#[derive(Debug, Clone, PartialEq)]
+pub enum DockArea {
+    Tabs {
+        path: Vec<usize>,
+        tab_bar: Rect,
+        content: Rect,
+        panels: Vec<String>,
+        active: usize,
+    },
+    //Handle between the two children of the split filling rect
+    Splitter {
+        path: Vec<usize>,
+        handle: Rect,
+        split: DockSplit,
+        rect: Rect,
+    },
+}
+
+#[derive(Default, Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(crate = "inox_serialize")]
+pub struct DockTree {
+    pub root: DockNode,
+}
+
+impl DockTree {
+    pub fn new(root: DockNode) -> Self {
+        Self { root }
+    }
+    pub fn contains(&self, panel: &str) -> bool {
+        self.root.contains(panel)
+    }
+    pub fn remove(&mut self, panel: &str) -> &mut Self {
+        if !self.root.remove(panel) {
+            self.root = DockNode::default();
+        }
+        self
+    }
+    //Moves the panel beside or in the tabs of the target one.
+    //When the target isn't in the tree the panel is docked on the side of the whole tree
+    pub fn dock(&mut self, panel: &str, target: &str, position: DockPosition) -> &mut Self {
+        if panel == target {
+            return self;
+        }
+        self.remove(panel);
+        match self.root.tabs_of_mut(target) {
+            Some(node) => node.insert(panel, position),
+            None => self.root.insert(panel, position),
+        }
+        self
+    }
+    pub fn layout(&self, rect: Rect) -> Vec<DockArea> {
+        let mut areas = Vec::new();
+        self.root.layout(rect, &mut Vec::new(), &mut areas);
+        areas
+    }
+    //Content rect of the panel, none when it's not the active one of its tabs
+    pub fn panel_rect(&self, rect: Rect, panel: &str) -> Option<Rect> {
+        self.layout(rect).into_iter().find_map(|area| match area {
+            DockArea::Tabs {
+                content,
+                panels,
+                active,
+                ..
+            } if panels.get(active).is_some_and(|p| p == panel) => Some(content),
+            _ => None,
+        })
+    }
+    pub fn set_active(&mut self, path: &[usize], index: usize) -> &mut Self {
+        if let Some(DockNode::Tabs { panels, active }) = self.node_mut(path) {
+            *active = index.min(panels.len().saturating_sub(1));
+        }
+        self
+    }
+    pub fn set_fraction(&mut self, path: &[usize], fraction: f32) -> &mut Self {
+        if let Some(DockNode::Split { fraction: f, .. }) = self.node_mut(path) {
+            *f = fraction.clamp(MIN_SPLIT_FRACTION, 1. - MIN_SPLIT_FRACTION);
+        }
+        self
+    }
+    fn node_mut(&mut self, path: &[usize]) -> Option<&mut DockNode> {
+        path.iter().try_fold(&mut self.root, |node, i| match node {
+            DockNode::Split { children, .. } => children.get_mut(*i),
+            DockNode::Tabs { .. } => None,
+        })
+    }
+}
+
+//Where a tab dropped on the pos is docked: beside the panel near its borders, in its tabs otherwise
+pub fn dock_position(rect: Rect, pos: Pos2) -> DockPosition {
+    let x = (pos.x - rect.min.x) / rect.width().max(1.);
+    let y = (pos.y - rect.min.y) / rect.height().max(1.);
+    [
+        (x, DockPosition::Left),
+        (1. - x, DockPosition::Right),
+        (y, DockPosition::Top),
+        (1. - y, DockPosition::Bottom),
+    ]
+    .into_iter()
+    .filter(|(distance, _)| *distance < DOCK_BORDER_FRACTION)
+    .min_by(|a, b| a.0.total_cmp(&b.0))
+    .map_or(DockPosition::Center, |(_, position)| position)
+}
+
+fn drop_rect(rect: Rect, position: DockPosition) -> Rect {
+    let center = rect.center();
+    match position {
+        DockPosition::Center => rect,
+        DockPosition::Left => Rect::from_min_max(rect.min, Pos2::new(center.x, rect.max.y)),
+        DockPosition::Right => Rect::from_min_max(Pos2::new(center.x, rect.min.y), rect.max),
+        DockPosition::Top => Rect::from_min_max(rect.min, Pos2::new(rect.max.x, center.y)),
+        DockPosition::Bottom => Rect::from_min_max(Pos2::new(rect.min.x, center.y), rect.max),
+    }
+}
+
+fn split_fraction(rect: Rect, split: DockSplit, pos: Pos2) -> f32 {
+    match split {
+        DockSplit::Horizontal => (pos.x - rect.min.x) / rect.width().max(1.),
+        DockSplit::Vertical => (pos.y - rect.min.y) / rect.height().max(1.),
+    }
+}
+
+//Tab bars and splitters of a dock tree, with the windows of the panels placed in its areas
+pub struct Dock {
+    tree: DockTree,
+    default_tree: DockTree,
+    panels_rect: HashMap<String, Rect>,
+    dragged_panel: Option<String>,
+    is_changed: bool,
+}
+
+impl Dock {
+    pub fn new(default_tree: DockTree) -> Self {
+        Self {
+            tree: default_tree.clone(),
+            default_tree,
+            panels_rect: HashMap::new(),
+            dragged_panel: None,
+            is_changed: false,
+        }
+    }
+    pub fn tree(&self) -> &DockTree {
+        &self.tree
+    }
+    pub fn set_tree(&mut self, tree: DockTree) -> &mut Self {
+        self.tree = tree;
+        self.dragged_panel = None;
+        self
+    }
+    pub fn reset(&mut self) -> &mut Self {
+        self.set_tree(self.default_tree.clone());
+        self.is_changed = true;
+        self
+    }
+    //True once after every change of the layout made by the user
+    pub fn take_changed(&mut self) -> bool {
+        std::mem::take(&mut self.is_changed)
+    }
+
+    //Window of the panel placed in its area, none while the panel is in a hidden tab.
+    //Panels not in the dock are free windows
+    pub fn window(&self, title: &str) -> Option<Window<'static>> {
+        let window = Window::new(title.to_string());
+        if !self.tree.contains(title) {
+            return Some(window);
+        }
+        let rect = self.panels_rect.get(title)?;
+        Some(
+            window
+                .title_bar(false)
+                .collapsible(false)
+                .resizable(false)
+                .fixed_pos(rect.min)
+                .fixed_size((rect.size() - Vec2::splat(2. * WINDOW_MARGIN)).max(Vec2::ZERO)),
+        )
+    }
+    //Area of the panel content of the last shown frame, none while hidden or not docked
+    pub fn panel_rect(&self, panel: &str) -> Option<Rect> {
+        self.panels_rect.get(panel).copied()
+    }
+
+    //Returns true while a tab or a splitter is dragged
+    pub fn show(&mut self, ui_context: &Context) -> bool {
+        inox_profiler::scoped_profile!("dock::show");
+
+        let areas = self.tree.layout(ui_context.available_rect());
+        self.panels_rect.clear();
+        let mut is_interacting = false;
+        areas.iter().for_each(|area| match area {
+            DockArea::Tabs {
+                path,
+                tab_bar,
+                content,
+                panels,
+                active,
+            } => {
+                if let Some(panel) = panels.get(*active) {
+                    self.panels_rect.insert(panel.clone(), *content);
+                }
+                is_interacting |= self.show_tab_bar(ui_context, path, *tab_bar, panels, *active);
+            }
+            DockArea::Splitter {
+                path,
+                handle,
+                split,
+                rect,
+            } => {
+                is_interacting |= self.show_splitter(ui_context, path, *handle, *split, *rect);
+            }
+        });
+        if self.dragged_panel.is_some() {
+            self.show_drop_target(ui_context, &areas);
+            is_interacting = true;
+        }
+        is_interacting
+    }
+
+    fn show_tab_bar(
+        &mut self,
+        ui_context: &Context,
+        path: &[usize],
+        tab_bar: Rect,
+        panels: &[String],
+        active: usize,
+    ) -> bool {
+        let mut is_interacting = false;
+        Area::new(Id::new("Dock Tabs").with(path))
+            .fixed_pos(tab_bar.min)
+            .show(ui_context, |ui| {
+                ui.set_clip_rect(tab_bar);
+                ui.painter()
+                    .rect_filled(tab_bar, 0., ui.visuals().extreme_bg_color);
+                ui.horizontal(|ui| {
+                    panels.iter().enumerate().for_each(|(i, panel)| {
+                        let response = ui
+                            .add(SelectableLabel::new(i == active, panel.as_str()))
+                            .interact(Sense::drag());
+                        if response.clicked() && i != active {
+                            self.tree.set_active(path, i);
+                            self.is_changed = true;
+                        }
+                        if response.drag_started() {
+                            self.dragged_panel = Some(panel.clone());
+                        }
+                        is_interacting |= response.is_pointer_button_down_on();
+                    });
+                });
+            });
+        is_interacting
+    }
+
+    fn show_splitter(
+        &mut self,
+        ui_context: &Context,
+        path: &[usize],
+        handle: Rect,
+        split: DockSplit,
+        rect: Rect,
+    ) -> bool {
+        let mut is_interacting = false;
+        Area::new(Id::new("Dock Splitter").with(path))
+            .fixed_pos(handle.min)
+            .show(ui_context, |ui| {
+                let response = ui.allocate_rect(handle, Sense::drag());
+                if response.hovered() || response.dragged() {
+                    ui_context.set_cursor_icon(match split {
+                        DockSplit::Horizontal => CursorIcon::ResizeHorizontal,
+                        DockSplit::Vertical => CursorIcon::ResizeVertical,
+                    });
+                }
+                ui.painter().rect_filled(
+                    handle,
+                    0.,
+                    ui.visuals().widgets.noninteractive.bg_stroke.color,
+                );
+                if response.dragged() {
+                    if let Some(pos) = response.interact_pointer_pos() {
+                        self.tree
+                            .set_fraction(path, split_fraction(rect, split, pos));
+                    }
+                }
+                if response.drag_released() {
+                    self.is_changed = true;
+                }
+                is_interacting = response.is_pointer_button_down_on();
+            });
+        is_interacting
+    }
+
+    //The dragged tab is docked where the pointer is released, dropping it elsewhere does nothing
+    fn show_drop_target(&mut self, ui_context: &Context, areas: &[DockArea]) {
+        let Some(panel) = self.dragged_panel.clone() else {
+            return;
+        };
+        let (pointer, is_released) =
+            ui_context.input(|i| (i.pointer.interact_pos(), !i.pointer.primary_down()));
+        let target = pointer.and_then(|pos| {
+            areas.iter().find_map(|area| match area {
+                DockArea::Tabs {
+                    tab_bar,
+                    content,
+                    panels,
+                    active,
+                    ..
+                } => {
+                    let position = if tab_bar.contains(pos) {
+                        DockPosition::Center
+                    } else if content.contains(pos) {
+                        dock_position(*content, pos)
+                    } else {
+                        return None;
+                    };
+                    panels
+                        .get(*active)
+                        .map(|target| (target.clone(), *content, position))
+                }
+                DockArea::Splitter { .. } => None,
+            })
+        });
+        if let Some((target, content, position)) = &target {
+            if *target != panel {
+                ui_context
+                    .layer_painter(LayerId::new(Order::Foreground, Id::new("Dock Drop Target")))
+                    .rect_filled(drop_rect(*content, *position), 0., DROP_TARGET_COLOR);
+            }
+        }
+        if is_released {
+            if let Some((target, _, position)) = target {
+                self.tree.dock(&panel, &target, position);
+                self.is_changed = true;
+            }
+            self.dragged_panel = None;
+        }
+    }
+}
+
+#[allow(dead_code)]
+fn test_dock_tree() {
+    let mut tree = DockTree::new(DockNode::split(
+        DockSplit::Horizontal,
+        0.25,
+        DockNode::tabs(&["Hierarchy"]),
+        DockNode::tabs(&["View", "Debug"]),
+    ));
+    let screen = Rect::from_min_max(Pos2::ZERO, Pos2::new(1000., 500.));
+
+    let hierarchy = tree.panel_rect(screen, "Hierarchy").unwrap();
+    assert_eq!(hierarchy.min, Pos2::new(0., TAB_BAR_HEIGHT));
+    assert_eq!(hierarchy.max, Pos2::new(250. - SPLITTER_SIZE * 0.5, 500.));
+    //Only the active tab is shown
+    assert!(tree.panel_rect(screen, "View").is_some());
+    assert!(tree.panel_rect(screen, "Debug").is_none());
+
+    tree.dock("Debug", "Hierarchy", DockPosition::Bottom);
+    let debug = tree.panel_rect(screen, "Debug").unwrap();
+    assert!(debug.min.y > hierarchy.center().y && debug.max.x <= hierarchy.max.x);
+    assert_eq!(
+        tree.panel_rect(screen, "View").unwrap().max,
+        screen.max,
+        "The panel left alone in its tabs keeps its area"
+    );
+
+    //Splitters are moved by their path and kept away from the borders
+    tree.set_fraction(&[], 0.5).set_fraction(&[0], 2.);
+    let debug = tree.panel_rect(screen, "Debug").unwrap();
+    assert_eq!(debug.max.x, 500. - SPLITTER_SIZE * 0.5);
+    assert!(debug.min.y > 500. * (1. - MIN_SPLIT_FRACTION));
+
+    //Moving the last panel out of a split gives all the space to the other side
+    tree.dock("Hierarchy", "View", DockPosition::Center).dock(
+        "Debug",
+        "View",
+        DockPosition::Center,
+    );
+    assert_eq!(
+        tree,
+        DockTree::new(DockNode::Tabs {
+            panels: vec![
+                "View".to_string(),
+                "Hierarchy".to_string(),
+                "Debug".to_string()
+            ],
+            active: 2,
+        })
+    );
+    tree.set_active(&[], 0);
+    assert_eq!(tree.panel_rect(screen, "View").unwrap().max, screen.max);
+
+    tree.remove("View").remove("Hierarchy").remove("Debug");
+    assert_eq!(tree, DockTree::default());
+    tree.dock("Debug", "View", DockPosition::Left);
+    assert!(tree.contains("Debug"));
+    assert_eq!(tree.layout(screen).len(), 1);
+}
+
+#[allow(dead_code)]
+fn test_dock_position() {
+    let rect = Rect::from_min_max(Pos2::new(100., 100.), Pos2::new(300., 200.));
+    assert_eq!(dock_position(rect, rect.center()), DockPosition::Center);
+    assert_eq!(
+        dock_position(rect, Pos2::new(110., 150.)),
+        DockPosition::Left
+    );
+    assert_eq!(
+        dock_position(rect, Pos2::new(290., 150.)),
+        DockPosition::Right
+    );
+    assert_eq!(
+        dock_position(rect, Pos2::new(200., 105.)),
+        DockPosition::Top
+    );
+    assert_eq!(
+        dock_position(rect, Pos2::new(200., 195.)),
+        DockPosition::Bottom
+    );
+    //Near a corner the closest border wins
+    assert_eq!(
+        dock_position(rect, Pos2::new(102., 110.)),
+        DockPosition::Left
+    );
+    assert_eq!(
+        drop_rect(rect, DockPosition::Bottom),
+        Rect::from_min_max(Pos2::new(100., 150.), Pos2::new(300., 200.))
+    );
+}
+
+#[test]
+fn test() {
+    test_dock_tree();
+    test_dock_position();
+}
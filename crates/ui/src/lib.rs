@@ -1,4 +1,5 @@
 pub use crate::color::*;
+pub use crate::dock::*;
 pub use crate::resources::*;
 pub use crate::systems::*;
 pub use crate::ui_events::*;
@@ -8,6 +9,7 @@ use inox_messenger::MessageHubRc;
 use inox_resources::SharedDataRc;
 
 pub mod color;
+pub mod dock;
 pub mod resources;
 pub mod systems;
 pub mod ui_events;
@@ -3,7 +3,7 @@ use std::any::{type_name, Any};
 use egui::{CollapsingHeader, Context, Ui};
 use inox_messenger::MessageHubRc;
 use inox_resources::{Resource, ResourceId, ResourceTrait, SharedDataRc};
-use inox_uid::generate_random_uid;
+use inox_uid::{generate_random_resource_uid, resource_uid_to_string};
 
 use crate::{UIProperties, UIPropertiesRegistry};
 
@@ -90,7 +90,7 @@ impl UIProperties for UIWidget {
         CollapsingHeader::new(format!(
             "UIWidget_{:?} [{:?}]",
             self.type_name,
-            id.as_simple().to_string()
+            resource_uid_to_string(id)
         ))
         .show_background(true)
         .default_open(!collapsed)
@@ -123,7 +123,7 @@ impl UIWidget {
             func: Box::new(f),
             is_interacting: false,
         };
-        shared_data.add_resource::<UIWidget>(message_hub, generate_random_uid(), ui_page)
+        shared_data.add_resource::<UIWidget>(message_hub, generate_random_resource_uid(), ui_page)
     }
 
     pub fn data<D>(&self) -> Option<&D>
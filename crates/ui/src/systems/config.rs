@@ -5,6 +5,9 @@ use inox_serialize::{Deserialize, Serialize, SerializeFile};
 #[serde(crate = "inox_serialize")]
 pub struct Config {
     pub ui_scale: f32,
+    //Tessellate the ui shapes and build the draw data of the ui meshes in parallel jobs
+    #[serde(default)]
+    pub use_multithreading: bool,
 }
 
 impl SerializeFile for Config {
@@ -1,6 +1,8 @@
 pub use self::ui_pass::*;
+pub use self::ui_scale::*;
 pub use self::ui_system::*;
 
 mod config;
 pub mod ui_pass;
+pub mod ui_scale;
 pub mod ui_system;
@@ -5,11 +5,12 @@ use inox_graphics::{
     declare_as_binding_vector, AsBinding, BindingData, BindingInfo, CommandBuffer, ConstantDataRw,
     DrawCommandType, GpuBuffer, MeshFlags, OutputRenderPass, Pass, RenderContext,
     RenderCoreContext, RenderPass, RenderPassBeginData, RenderPassData, RenderTarget, ShaderStage,
-    StoreOperation, TextureView, TexturesBuffer, VertexBufferLayoutBuilder, VertexFormat,
+    StoreOperation, TextureAccess, TextureId, TextureView, TexturesBuffer,
+    VertexBufferLayoutBuilder, VertexFormat,
 };
 use inox_messenger::Listener;
 use inox_resources::{DataTypeResource, Resource, ResourceTrait};
-use inox_uid::generate_random_uid;
+use inox_uid::generate_random_resource_uid;
 
 use crate::UIEvent;
 
@@ -126,7 +127,7 @@ impl Pass for UIPass {
             render_pass: RenderPass::new_resource(
                 context.shared_data(),
                 context.message_hub(),
-                generate_random_uid(),
+                generate_random_resource_uid(),
                 &data,
                 None,
             ),
@@ -190,7 +191,7 @@ impl Pass for UIPass {
                     ..Default::default()
                 },
             )
-            .add_default_sampler(BindingInfo {
+            .add_material_samplers(BindingInfo {
                 group_index: 2,
                 binding_index: 0,
                 stage: ShaderStage::Fragment,
@@ -245,6 +246,7 @@ impl Pass for UIPass {
             render_targets: render_targets.as_slice(),
             surface_view,
             command_buffer,
+            view_submission: render_context.current_view(),
         };
         let mut render_pass = pass.begin(&mut self.binding_data, &pipeline, render_pass_begin_data);
         {
@@ -266,6 +268,15 @@ impl Pass for UIPass {
                 });
         }
     }
+    fn is_per_view(&self) -> bool {
+        false
+    }
+    fn read_textures(&self) -> Vec<(TextureId, TextureAccess)> {
+        self.render_pass.get().read_textures()
+    }
+    fn written_textures(&self) -> Vec<(TextureId, TextureAccess)> {
+        self.render_pass.get().written_textures()
+    }
 }
 
 impl OutputRenderPass for UIPass {
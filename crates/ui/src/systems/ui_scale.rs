@@ -0,0 +1,108 @@
+use egui::{Pos2, Rect};
+use inox_platform::WindowEvent;
+
+//Pixels for each point of the ui: the scale factor of the window by the one of the config.
+//egui works in points, the ui pass draws them back in pixels of the surface
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UIScale {
+    scale_factor: f32,
+    config_scale: f32,
+    width: u32,
+    height: u32,
+}
+
+impl Default for UIScale {
+    fn default() -> Self {
+        Self {
+            scale_factor: 1.,
+            config_scale: 2.,
+            width: 0,
+            height: 0,
+        }
+    }
+}
+
+impl UIScale {
+    pub fn pixels_per_point(&self) -> f32 {
+        self.scale_factor * self.config_scale
+    }
+    pub fn set_config_scale(&mut self, config_scale: f32) -> &mut Self {
+        self.config_scale = config_scale;
+        self
+    }
+    //Returns true when the pixels per point have changed
+    pub fn on_window_event(&mut self, event: &WindowEvent) -> bool {
+        match *event {
+            WindowEvent::SizeChanged(width, height) => {
+                self.width = width;
+                self.height = height;
+                false
+            }
+            WindowEvent::ScaleFactorChanged(scale_factor) => {
+                let is_changed = self.scale_factor != scale_factor;
+                self.scale_factor = scale_factor;
+                is_changed
+            }
+            _ => false,
+        }
+    }
+    pub fn screen_rect(&self) -> Rect {
+        Rect::from_min_size(
+            Default::default(),
+            self.to_points(self.width as _, self.height as _).to_vec2(),
+        )
+    }
+    pub fn to_points(&self, x: f32, y: f32) -> Pos2 {
+        let pixels_per_point = self.pixels_per_point();
+        [x / pixels_per_point, y / pixels_per_point].into()
+    }
+}
+
+#[allow(dead_code)]
+fn test_ui_scale() {
+    use inox_messenger::{Listener, MessageHub};
+    use std::sync::Arc;
+
+    let message_hub = Arc::new(MessageHub::default());
+    message_hub.register_type::<WindowEvent>();
+    let listener = Listener::new(&message_hub);
+    listener.register::<WindowEvent>();
+
+    let mut ui_scale = UIScale::default();
+    ui_scale.set_config_scale(1.5);
+    let mut send_and_process = |events: &[WindowEvent]| {
+        events
+            .iter()
+            .for_each(|e| message_hub.send_event(e.clone()));
+        message_hub.flush();
+        let mut is_changed = false;
+        listener.process_messages(|e: &WindowEvent| {
+            is_changed |= ui_scale.on_window_event(e);
+        });
+        (is_changed, ui_scale)
+    };
+
+    let (is_changed, scale) = send_and_process(&[WindowEvent::SizeChanged(1920, 1080)]);
+    assert!(!is_changed);
+    assert_eq!(scale.pixels_per_point(), 1.5);
+    assert_eq!(scale.screen_rect().max, Pos2::new(1280., 720.));
+    assert_eq!(scale.to_points(960., 540.), Pos2::new(640., 360.));
+
+    //Dragging the window to a 4K display with a 200% scale
+    let (is_changed, scale) = send_and_process(&[
+        WindowEvent::ScaleFactorChanged(2.),
+        WindowEvent::SizeChanged(3840, 2160),
+    ]);
+    assert!(is_changed);
+    assert_eq!(scale.pixels_per_point(), 3.);
+    assert_eq!(scale.screen_rect().max, Pos2::new(1280., 720.));
+    assert_eq!(scale.to_points(3840., 2160.), Pos2::new(1280., 720.));
+
+    let (is_changed, _) = send_and_process(&[WindowEvent::ScaleFactorChanged(2.)]);
+    assert!(!is_changed);
+}
+
+#[test]
+fn test() {
+    test_ui_scale();
+}
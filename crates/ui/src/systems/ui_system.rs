@@ -3,13 +3,14 @@ use std::{
     collections::HashMap,
     sync::{
         atomic::{AtomicUsize, Ordering},
-        Arc,
+        Arc, Mutex,
     },
 };
 
 use egui::{
-    epaint::Primitive, ClippedPrimitive, Context, Event, Modifiers, PlatformOutput, PointerButton,
-    RawInput, Rect, TextureId as eguiTextureId, TexturesDelta,
+    epaint::{ClippedShape, Mesh, Primitive},
+    ClippedPrimitive, Context, Event, Modifiers, PlatformOutput, PointerButton, RawInput,
+    TextureId as eguiTextureId, TexturesDelta,
 };
 
 use inox_core::{
@@ -22,16 +23,76 @@ use inox_graphics::{Texture, TextureData, TextureFormat, TextureUsage};
 use inox_log::debug_log;
 use inox_messenger::{Listener, MessageHubRc};
 use inox_platform::{
-    InputState, KeyEvent, KeyTextEvent, MouseButton, MouseEvent, MouseState, WindowEvent,
+    clipboard_text, open_url, set_clipboard_text, ClipboardEvent, InputState, KeyEvent,
+    KeyTextEvent, MouseButton, MouseEvent, MouseState, TouchEvent, TouchState, WindowEvent,
 };
 use inox_resources::{to_slice, ConfigBase, ConfigEvent, DataTypeResource, Resource, SharedDataRc};
 use inox_serialize::read_from_file;
-use inox_uid::generate_random_uid;
+use inox_uid::generate_random_resource_uid;
 
-use crate::{UIEvent, UIInstance, UIVertex, UIWidget};
+use crate::{UIEvent, UIInstance, UIScale, UIVertex, UIWidget};
 
 use super::config::Config;
 
+//Minimum number of meshes given to each job when the draw data is built in parallel
+const MESHES_PER_JOB: usize = 16;
+//Minimum number of shapes given to each job when they're tessellated in parallel
+const SHAPES_PER_JOB: usize = 64;
+
+#[derive(Default, PartialEq)]
+struct UIDrawData {
+    vertices: Vec<UIVertex>,
+    indices: Vec<u32>,
+    instances: Vec<UIInstance>,
+}
+
+impl UIDrawData {
+    fn create(meshes: &[(Mesh, u32)]) -> Self {
+        let mut draw_data = Self::default();
+        meshes.iter().for_each(|(mesh, texture_index)| {
+            draw_data.add_mesh(mesh, *texture_index);
+        });
+        draw_data
+    }
+    fn add_mesh(&mut self, mesh: &Mesh, texture_index: u32) -> &mut Self {
+        self.instances.push(UIInstance {
+            index_start: self.indices.len() as _,
+            index_count: mesh.indices.len() as _,
+            vertex_start: self.vertices.len() as _,
+            texture_index,
+        });
+        self.vertices
+            .extend_from_slice(to_slice(mesh.vertices.as_slice()));
+        self.indices.extend_from_slice(&mesh.indices);
+        self
+    }
+    //Instances of other are relative to its own buffers so they're moved after the current ones
+    fn append(&mut self, other: Self) -> &mut Self {
+        let index_offset = self.indices.len() as u32;
+        let vertex_offset = self.vertices.len() as u32;
+        self.instances
+            .extend(other.instances.into_iter().map(|instance| UIInstance {
+                index_start: instance.index_start + index_offset,
+                vertex_start: instance.vertex_start + vertex_offset,
+                ..instance
+            }));
+        self.vertices.extend(other.vertices);
+        self.indices.extend(other.indices);
+        self
+    }
+}
+
+//Contiguous chunks of about the same size, in the original order
+fn split_in_chunks<T>(items: Vec<T>, num_chunks: usize) -> Vec<Vec<T>> {
+    let chunk_size = items.len().div_ceil(num_chunks.max(1)).max(1);
+    let mut items = items.into_iter().peekable();
+    let mut chunks = Vec::new();
+    while items.peek().is_some() {
+        chunks.push(items.by_ref().take(chunk_size).collect());
+    }
+    chunks
+}
+
 pub struct UISystem {
     config: Config,
     shared_data: SharedDataRc,
@@ -42,8 +103,11 @@ pub struct UISystem {
     ui_textures: HashMap<eguiTextureId, Resource<Texture>>,
     ui_input: RawInput,
     ui_input_modifiers: Modifiers,
-    ui_clipboard: Option<String>,
-    ui_scale: f32,
+    //Only the first finger on the screen moves the pointer
+    ui_touch_id: Option<u64>,
+    ui_is_composing: bool,
+    ui_is_ime_allowed: bool,
+    ui_scale: UIScale,
 }
 
 impl UISystem {
@@ -62,43 +126,179 @@ impl UISystem {
             ui_textures: HashMap::new(),
             ui_input: RawInput::default(),
             ui_input_modifiers: Modifiers::default(),
-            ui_clipboard: None,
-            ui_scale: 2.,
+            ui_touch_id: None,
+            ui_is_composing: false,
+            ui_is_ime_allowed: false,
+            ui_scale: UIScale::default(),
         }
     }
 
     fn compute_mesh_data(&mut self, primitives: Vec<ClippedPrimitive>) {
         inox_profiler::scoped_profile!("ui_system::compute_mesh_data");
-        let mut vertices: Vec<UIVertex> = Vec::new();
-        let mut indices: Vec<u32> = Vec::new();
-        let mut instances: Vec<UIInstance> = Vec::new();
 
-        for primitive in primitives.into_iter() {
-            if let Primitive::Mesh(mesh) = primitive.primitive {
-                if mesh.vertices.is_empty() || mesh.indices.is_empty() {
-                    continue;
+        //Texture resources are resolved here so that jobs only touch egui data
+        let meshes = primitives
+            .into_iter()
+            .filter_map(|primitive| match primitive.primitive {
+                Primitive::Mesh(mesh) => {
+                    if mesh.vertices.is_empty() || mesh.indices.is_empty() {
+                        return None;
+                    }
+                    let texture_index = match mesh.texture_id {
+                        eguiTextureId::Managed(_) => {
+                            self.ui_textures[&mesh.texture_id].get().texture_index() as u32
+                        }
+                        eguiTextureId::User(texture_uniform_index) => texture_uniform_index as _,
+                    };
+                    Some((mesh, texture_index))
                 }
-                inox_profiler::scoped_profile!("ui_system::create_mesh_data");
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        let num_jobs = self.num_jobs(meshes.len(), MESHES_PER_JOB);
+        let draw_data = if num_jobs > 1 {
+            Self::create_draw_data_in_jobs(&self.job_handler, meshes, num_jobs)
+        } else {
+            inox_profiler::scoped_profile!("ui_system::create_mesh_data");
+            UIDrawData::create(&meshes)
+        };
+        self.message_hub.send_event(UIEvent::DrawData(
+            draw_data.vertices,
+            draw_data.indices,
+            draw_data.instances,
+        ));
+    }
 
-                let texture_index = match mesh.texture_id {
-                    eguiTextureId::Managed(_) => {
-                        self.ui_textures[&mesh.texture_id].get().texture_index()
+    //Jobs are used only when enabled, with workers and enough items to fill them
+    fn num_jobs(&self, num_items: usize, items_per_job: usize) -> usize {
+        if !self.config.use_multithreading || self.job_handler.num_workers() == 0 {
+            return 1;
+        }
+        (self.job_handler.num_workers() + 1)
+            .min(num_items / items_per_job)
+            .max(1)
+    }
+
+    fn tessellate(&self, shapes: Vec<ClippedShape>) -> Vec<ClippedPrimitive> {
+        inox_profiler::scoped_profile!("ui_context::tessellate");
+        let num_jobs = self.num_jobs(shapes.len(), SHAPES_PER_JOB);
+        if num_jobs > 1 {
+            Self::tessellate_in_jobs(&self.job_handler, &self.ui_context, shapes, num_jobs)
+        } else {
+            self.ui_context.tessellate(shapes)
+        }
+    }
+
+    //Shapes are painted layer after layer, so each job tessellates a contiguous range of them.
+    //egui merges consecutive meshes with the same clip rect and texture:
+    //the first mesh of each job is merged the same way with the last one of the previous job
+    fn tessellate_in_jobs(
+        job_handler: &JobHandlerRw,
+        ui_context: &Context,
+        shapes: Vec<ClippedShape>,
+        num_jobs: usize,
+    ) -> Vec<ClippedPrimitive> {
+        inox_profiler::scoped_profile!("ui_system::tessellate_in_jobs");
+        let ui_context = ui_context.clone();
+        let results = Self::run_in_jobs(
+            job_handler,
+            "ui_system::tessellate",
+            split_in_chunks(shapes, num_jobs),
+            move |shapes| ui_context.tessellate(shapes),
+        );
+        let mut primitives: Vec<ClippedPrimitive> = Vec::new();
+        results.into_iter().for_each(|job_primitives| {
+            let mut job_primitives = job_primitives.into_iter();
+            if let Some(first) = job_primitives.next() {
+                match (primitives.last_mut(), first) {
+                    (
+                        Some(ClippedPrimitive {
+                            clip_rect,
+                            primitive: Primitive::Mesh(last_mesh),
+                        }),
+                        ClippedPrimitive {
+                            clip_rect: first_clip_rect,
+                            primitive: Primitive::Mesh(mesh),
+                        },
+                    ) if *clip_rect == first_clip_rect
+                        && last_mesh.texture_id == mesh.texture_id =>
+                    {
+                        last_mesh.append(mesh);
                     }
-                    eguiTextureId::User(texture_uniform_index) => texture_uniform_index as _,
-                };
-
-                instances.push(UIInstance {
-                    index_start: indices.len() as _,
-                    index_count: mesh.indices.len() as _,
-                    vertex_start: vertices.len() as _,
-                    texture_index: texture_index as _,
-                });
-                vertices.extend_from_slice(to_slice(mesh.vertices.as_slice()));
-                indices.extend_from_slice(&mesh.indices);
+                    (_, first) => primitives.push(first),
+                }
+            }
+            primitives.extend(job_primitives);
+        });
+        primitives
+    }
+
+    //Each job builds the draw data of a contiguous range of meshes,
+    //then the results are merged in order to keep the same output of the single threaded path
+    fn create_draw_data_in_jobs(
+        job_handler: &JobHandlerRw,
+        meshes: Vec<(Mesh, u32)>,
+        num_jobs: usize,
+    ) -> UIDrawData {
+        inox_profiler::scoped_profile!("ui_system::create_draw_data_in_jobs");
+        let results = Self::run_in_jobs(
+            job_handler,
+            "ui_system::create_mesh_data",
+            split_in_chunks(meshes, num_jobs),
+            |meshes| UIDrawData::create(&meshes),
+        );
+        let mut draw_data = UIDrawData::default();
+        results.into_iter().for_each(|job_data| {
+            draw_data.append(job_data);
+        });
+        draw_data
+    }
+
+    //Results are returned in the order of the inputs.
+    //The calling thread executes the pending jobs while waiting, so it never stalls the workers
+    fn run_in_jobs<I, T, F>(
+        job_handler: &JobHandlerRw,
+        job_name: &str,
+        inputs: Vec<I>,
+        f: F,
+    ) -> Vec<T>
+    where
+        I: Send + Sync + 'static,
+        T: Send + 'static,
+        F: Fn(I) -> T + Send + Sync + 'static,
+    {
+        let f = Arc::new(f);
+        let results = Arc::new(Mutex::new(
+            (0..inputs.len()).map(|_| None).collect::<Vec<Option<T>>>(),
+        ));
+        let wait_count = Arc::new(AtomicUsize::new(inputs.len()));
+        inputs
+            .into_iter()
+            .enumerate()
+            .for_each(|(job_index, input)| {
+                let f = f.clone();
+                let results = results.clone();
+                let wait_count = wait_count.clone();
+                job_handler.add_job(
+                    &UISystem::system_id(),
+                    format!("{}[{}]", job_name, job_index).as_str(),
+                    JobPriority::High,
+                    move || {
+                        let result = f(input);
+                        results.lock().unwrap()[job_index] = Some(result);
+                        wait_count.fetch_sub(1, Ordering::SeqCst);
+                    },
+                );
+            });
+        while wait_count.load(Ordering::SeqCst) > 0 {
+            match job_handler.get_job_with_priority(JobPriority::High) {
+                Some(job) => job.execute(),
+                None => std::thread::yield_now(),
             }
         }
-        self.message_hub
-            .send_event(UIEvent::DrawData(vertices, indices, instances));
+        let results = results.lock().unwrap().drain(..).flatten().collect();
+        results
     }
 
     fn update_events(&mut self) -> &mut Self {
@@ -108,19 +308,11 @@ impl UISystem {
             .process_messages(|event: &MouseEvent| {
                 if event.state == MouseState::Move {
                     self.ui_input.events.push(Event::PointerMoved(
-                        [
-                            event.x as f32 / self.ui_scale,
-                            event.y as f32 / self.ui_scale,
-                        ]
-                        .into(),
+                        self.ui_scale.to_points(event.x as _, event.y as _),
                     ));
                 } else if event.state == MouseState::Down || event.state == MouseState::Up {
                     self.ui_input.events.push(Event::PointerButton {
-                        pos: [
-                            event.x as f32 / self.ui_scale,
-                            event.y as f32 / self.ui_scale,
-                        ]
-                        .into(),
+                        pos: self.ui_scale.to_points(event.x as _, event.y as _),
                         button: match event.button {
                             MouseButton::Right => PointerButton::Secondary,
                             MouseButton::Middle => PointerButton::Middle,
@@ -131,28 +323,58 @@ impl UISystem {
                     });
                 }
             })
+            .process_messages(|event: &TouchEvent| {
+                if event.state == TouchState::Begin && self.ui_touch_id.is_none() {
+                    self.ui_touch_id = Some(event.id);
+                }
+                if self.ui_touch_id != Some(event.id) {
+                    return;
+                }
+                let pos = self.ui_scale.to_points(event.x as _, event.y as _);
+                self.ui_input.events.push(Event::PointerMoved(pos));
+                if event.state != TouchState::Move {
+                    self.ui_input.events.push(Event::PointerButton {
+                        pos,
+                        button: PointerButton::Primary,
+                        pressed: event.state == TouchState::Begin,
+                        modifiers: self.ui_input_modifiers,
+                    });
+                }
+                if event.is_released() {
+                    self.ui_input.events.push(Event::PointerGone);
+                    self.ui_touch_id = None;
+                }
+            })
             .process_messages(|e: &ConfigEvent<Config>| match e {
-                ConfigEvent::Loaded(filename, config) => {
+                ConfigEvent::Loaded(filename, config) | ConfigEvent::Reloaded(filename, config) => {
                     if filename == self.config.get_filename() {
                         self.config = config.clone();
-                        self.ui_scale = self.config.ui_scale;
-                        self.message_hub.send_event(UIEvent::Scale(self.ui_scale));
+                        self.ui_scale.set_config_scale(self.config.ui_scale);
+                        let pixels_per_point = self.ui_scale.pixels_per_point();
+                        self.ui_input.pixels_per_point = Some(pixels_per_point);
+                        self.ui_input.screen_rect = Some(self.ui_scale.screen_rect());
+                        self.message_hub
+                            .send_event(UIEvent::Scale(pixels_per_point));
                     }
                 }
             })
-            .process_messages(|event: &WindowEvent| match *event {
-                WindowEvent::SizeChanged(width, height) => {
-                    self.ui_input.screen_rect = Some(Rect::from_min_size(
-                        Default::default(),
-                        [width as f32, height as f32].into(),
-                    ));
+            .process_messages(|event: &WindowEvent| {
+                //Fonts are rasterized for the new scale and the pointer is mapped with it
+                if self.ui_scale.on_window_event(event) {
+                    let pixels_per_point = self.ui_scale.pixels_per_point();
+                    self.ui_input.pixels_per_point = Some(pixels_per_point);
+                    self.message_hub
+                        .send_event(UIEvent::Scale(pixels_per_point));
                 }
-                WindowEvent::ScaleFactorChanged(v) => {
-                    self.ui_input.pixels_per_point = Some(v);
-                    self.ui_scale = v * self.config.ui_scale;
-                    self.message_hub.send_event(UIEvent::Scale(self.ui_scale));
+                if matches!(
+                    event,
+                    WindowEvent::SizeChanged(..) | WindowEvent::ScaleFactorChanged(..)
+                ) {
+                    self.ui_input.screen_rect = Some(self.ui_scale.screen_rect());
                 }
-                _ => {}
+                self.ui_input
+                    .events
+                    .extend(convert_ime_event(event, &mut self.ui_is_composing));
             })
             .process_messages(|event: &KeyEvent| {
                 let just_pressed = event.state == InputState::JustPressed;
@@ -194,11 +416,16 @@ impl UISystem {
                     && self.ui_input_modifiers.ctrl
                     && event.code == inox_platform::input::Key::V
                 {
-                    if let Some(content) = &self.ui_clipboard {
-                        self.ui_input.events.push(Event::Text(content.clone()));
+                    //Where the clipboard can't be read on request it comes with a ClipboardEvent
+                    if let Some(content) = clipboard_text() {
+                        self.ui_input.events.push(Event::Paste(content));
                     }
                 }
             })
+            .process_messages(|event: &ClipboardEvent| {
+                let ClipboardEvent::Paste(content) = event;
+                self.ui_input.events.push(Event::Paste(content.clone()));
+            })
             .process_messages(|event: &KeyTextEvent| {
                 if event.char.is_ascii_control() {
                     return;
@@ -211,53 +438,41 @@ impl UISystem {
         self
     }
 
-    fn show_ui(
-        shared_data: SharedDataRc,
-        job_handler: JobHandlerRw,
-        context: Context,
-        use_multithreading: bool,
-    ) {
+    //Widgets are always executed on the main thread, inside the egui frame,
+    //so that their order in the frame is deterministic
+    fn show_ui(shared_data: &SharedDataRc, context: &Context) {
         inox_profiler::scoped_profile!("ui_system::show_ui");
-        let wait_count = Arc::new(AtomicUsize::new(0));
-        shared_data.for_each_resource_mut(|widget_handle, widget: &mut UIWidget| {
-            if use_multithreading {
-                let context = context.clone();
-                let widget_handle = widget_handle.clone();
-                let job_name = format!("ui_system::show_ui[{:?}]", widget_handle.id());
-                let wait_count = wait_count.clone();
-                wait_count.fetch_add(1, Ordering::SeqCst);
-                job_handler.add_job(
-                    &UISystem::system_id(),
-                    job_name.as_str(),
-                    JobPriority::Medium,
-                    move || {
-                        widget_handle.get_mut().execute(&context);
-                        wait_count.fetch_sub(1, Ordering::SeqCst);
-                    },
-                );
-            } else {
-                widget.execute(&context);
-            }
+        shared_data.for_each_resource_mut(|_, widget: &mut UIWidget| {
+            widget.execute(context);
         });
-        while wait_count.load(Ordering::SeqCst) > 0 {
-            std::thread::yield_now();
-        }
     }
 
-    fn handle_output(
-        &mut self,
-        output: PlatformOutput,
-        textures_delta: TexturesDelta,
-    ) -> &mut Self {
+    fn handle_output(&mut self, output: PlatformOutput) -> &mut Self {
         if let Some(open) = output.open_url {
-            debug_log!("Trying to open url: {:?}", open.url);
+            if !open_url(open.url.as_str()) {
+                debug_log!("Unable to open url: {:?}", open.url);
+            }
+        }
+
+        if !output.copied_text.is_empty() && !set_clipboard_text(output.copied_text.as_str()) {
+            debug_log!("Unable to copy text to the clipboard");
         }
 
-        if !output.copied_text.is_empty() {
-            self.ui_clipboard = Some(output.copied_text);
+        //The input method composes text only while a text widget has the focus
+        let wants_keyboard_input = self.ui_context.wants_keyboard_input();
+        if self.ui_is_ime_allowed != wants_keyboard_input {
+            self.ui_is_ime_allowed = wants_keyboard_input;
+            self.message_hub
+                .send_event(WindowEvent::RequestImeAllowed(wants_keyboard_input));
         }
+        self
+    }
+
+    //User textures are already in the renderer, egui sends only the changes of its own ones
+    fn update_textures(&mut self, textures_delta: &TexturesDelta) -> &mut Self {
+        inox_profiler::scoped_profile!("ui_system::update_textures");
 
-        for (egui_texture_id, image_delta) in textures_delta.set {
+        for (egui_texture_id, image_delta) in textures_delta.set.iter() {
             let color32 = match &image_delta.image {
                 egui::ImageData::Color(image) => {
                     assert_eq!(
@@ -270,6 +485,19 @@ impl UISystem {
                 egui::ImageData::Font(image) => Cow::Owned(image.srgba_pixels(None).collect()),
             };
             let pixels: &[u8] = to_slice(color32.as_slice());
+            //Partial updates, like new glyphs in the font atlas, change only their region
+            if let Some([x, y]) = image_delta.pos {
+                if let Some(texture) = self.ui_textures.get(egui_texture_id) {
+                    texture.get_mut().update_region(
+                        x as _,
+                        y as _,
+                        image_delta.image.width() as _,
+                        image_delta.image.height() as _,
+                        pixels,
+                    );
+                    continue;
+                }
+            }
             let texture_data = TextureData {
                 width: image_delta.image.width() as _,
                 height: image_delta.image.height() as _,
@@ -280,13 +508,19 @@ impl UISystem {
             let texture = Texture::new_resource(
                 &self.shared_data,
                 &self.message_hub,
-                generate_random_uid(),
+                generate_random_resource_uid(),
                 &texture_data,
                 None,
             );
-            self.ui_textures.insert(egui_texture_id, texture);
+            self.ui_textures.insert(*egui_texture_id, texture);
         }
+        self
+    }
 
+    fn free_textures(&mut self, textures_delta: &TexturesDelta) -> &mut Self {
+        textures_delta.free.iter().for_each(|egui_texture_id| {
+            self.ui_textures.remove(egui_texture_id);
+        });
         self
     }
 }
@@ -315,12 +549,24 @@ impl System for UISystem {
     fn should_run_when_not_focused(&self) -> bool {
         false
     }
+    fn should_run_when_paused(&self) -> bool {
+        true
+    }
+    fn on_focus_changed(&mut self, is_focused: bool) {
+        if !is_focused {
+            self.ui_input_modifiers = Modifiers::default();
+            self.ui_touch_id = None;
+            self.ui_is_composing = false;
+        }
+    }
     fn init(&mut self) {
         self.listener
             .register::<WindowEvent>()
             .register::<KeyEvent>()
             .register::<KeyTextEvent>()
-            .register::<MouseEvent>();
+            .register::<ClipboardEvent>()
+            .register::<MouseEvent>()
+            .register::<TouchEvent>();
     }
 
     fn run(&mut self) -> bool {
@@ -329,30 +575,31 @@ impl System for UISystem {
         let output = {
             inox_profiler::scoped_profile!("ui_context::run");
             let shared_data = self.shared_data.clone();
-            let job_handler = self.job_handler.clone();
-            let ui_context = self.ui_context.clone();
-            self.ui_context.run(self.ui_input.take(), move |_| {
-                Self::show_ui(shared_data, job_handler, ui_context, false);
-            })
+            self.ui_context
+                .run(self.ui_input.take(), move |ui_context| {
+                    Self::show_ui(&shared_data, ui_context);
+                })
         };
         /*
         if !output.needs_repaint {
             return true;
         }*/
 
-        let clipped_meshes = {
-            inox_profiler::scoped_profile!("ui_context::tessellate");
-            self.ui_context.tessellate(output.shapes)
-        };
-        self.handle_output(output.platform_output, output.textures_delta)
+        let clipped_meshes = self.tessellate(output.shapes);
+        self.handle_output(output.platform_output)
+            .update_textures(&output.textures_delta)
             .compute_mesh_data(clipped_meshes);
+        //Textures are freed only after the meshes of this frame have been computed
+        self.free_textures(&output.textures_delta);
 
         true
     }
 
     fn uninit(&mut self) {
         self.listener
+            .unregister::<TouchEvent>()
             .unregister::<MouseEvent>()
+            .unregister::<ClipboardEvent>()
             .unregister::<KeyTextEvent>()
             .unregister::<KeyEvent>()
             .unregister::<WindowEvent>()
@@ -416,3 +663,193 @@ fn convert_key(key: inox_platform::input::Key) -> Option<egui::Key> {
         _ => None,
     }
 }
+
+//egui edits the composed text only between the start and the end of a composition,
+//so a commit without a preedit opens one too while an empty one closes a cancelled composition
+fn convert_ime_event(event: &WindowEvent, is_composing: &mut bool) -> Vec<Event> {
+    let mut events = Vec::new();
+    match event {
+        WindowEvent::ImePreedit(text) => {
+            if !*is_composing {
+                *is_composing = true;
+                events.push(Event::CompositionStart);
+            }
+            events.push(Event::CompositionUpdate(text.clone()));
+        }
+        WindowEvent::ImeCommit(text) => {
+            if !*is_composing && text.is_empty() {
+                return events;
+            }
+            if !*is_composing {
+                events.push(Event::CompositionStart);
+            }
+            *is_composing = false;
+            events.push(Event::CompositionEnd(text.clone()));
+        }
+        _ => {}
+    }
+    events
+}
+
+#[allow(dead_code)]
+fn test_ime_composition() {
+    fn run_frame(context: &Context, text: &mut String, events: Vec<Event>) {
+        let input = RawInput {
+            events,
+            ..Default::default()
+        };
+        let _ = context.run(input, |context| {
+            egui::CentralPanel::default().show(context, |ui| {
+                ui.text_edit_singleline(text).request_focus();
+            });
+        });
+    }
+
+    let context = Context::default();
+    let mut text = String::new();
+    let mut is_composing = false;
+    run_frame(&context, &mut text, Vec::new());
+    run_frame(&context, &mut text, Vec::new());
+    assert!(context.wants_keyboard_input());
+
+    //Japanese input of 日本 with the ending of the composition sent after the commit
+    let events = ["n", "に", "にほ", "にほん", "日本"]
+        .iter()
+        .map(|preedit| WindowEvent::ImePreedit(preedit.to_string()))
+        .chain([
+            WindowEvent::ImeCommit("日本".to_string()),
+            WindowEvent::ImeCommit(String::new()),
+        ])
+        .flat_map(|event| convert_ime_event(&event, &mut is_composing))
+        .collect::<Vec<_>>();
+    assert_eq!(events.first(), Some(&Event::CompositionStart));
+    assert_eq!(
+        events.last(),
+        Some(&Event::CompositionEnd("日本".to_string()))
+    );
+    assert!(!is_composing);
+    run_frame(&context, &mut text, events);
+    assert_eq!(text, "日本");
+
+    //A cancelled composition leaves the text as it was
+    let events = [
+        WindowEvent::ImePreedit("ご".to_string()),
+        WindowEvent::ImePreedit(String::new()),
+        WindowEvent::ImeCommit(String::new()),
+    ]
+    .iter()
+    .flat_map(|event| convert_ime_event(event, &mut is_composing))
+    .collect::<Vec<_>>();
+    run_frame(&context, &mut text, events);
+    assert_eq!(text, "日本");
+
+    //Input methods committing without a preedit still compose
+    let events = convert_ime_event(&WindowEvent::ImeCommit("é".to_string()), &mut is_composing);
+    run_frame(&context, &mut text, events);
+    assert_eq!(text, "日本é");
+}
+
+#[allow(dead_code)]
+fn test_draw_data_in_chunks() {
+    use egui::{epaint::Vertex, Color32, Pos2};
+
+    let meshes = (0..50u32)
+        .map(|i| {
+            let mut mesh = Mesh::default();
+            (0..3 + i % 4).for_each(|v| {
+                mesh.vertices.push(Vertex {
+                    pos: Pos2::new(i as _, v as _),
+                    uv: Pos2::new(0., 1.),
+                    color: Color32::from_gray(i as _),
+                });
+            });
+            mesh.indices = (0..3 * (1 + i % 3)).map(|index| index % 3).collect();
+            (mesh, i % 5)
+        })
+        .collect::<Vec<_>>();
+
+    let single = UIDrawData::create(&meshes);
+    assert_eq!(single.instances.len(), meshes.len());
+    assert_eq!(single.instances[1].index_start, 3);
+    assert_eq!(single.instances[1].vertex_start, 3);
+
+    //Appending the draw data of each chunk has to match the single threaded result
+    [1, 7, MESHES_PER_JOB, 49].iter().for_each(|&chunk_size| {
+        let mut merged = UIDrawData::default();
+        meshes.chunks(chunk_size).for_each(|chunk| {
+            merged.append(UIDrawData::create(chunk));
+        });
+        assert!(merged == single, "Mismatch with chunks of {}", chunk_size);
+    });
+
+    for_each_job_handler(|job_handler| {
+        [1, 3, 7, meshes.len()].iter().for_each(|&num_jobs| {
+            let draw_data =
+                UISystem::create_draw_data_in_jobs(job_handler, meshes.clone(), num_jobs);
+            assert!(draw_data == single, "Mismatch with {} jobs", num_jobs);
+        });
+    });
+}
+
+//Without workers the waiting thread has to execute the jobs by itself
+#[allow(dead_code)]
+fn for_each_job_handler(f: impl Fn(&JobHandlerRw)) {
+    use std::sync::atomic::AtomicBool;
+
+    let job_handler = JobHandlerRw::default();
+    job_handler.write().unwrap().set_num_workers(0);
+    f(&job_handler);
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let job_handler = JobHandlerRw::default();
+        job_handler.write().unwrap().set_num_workers(2);
+        job_handler.start(&Arc::new(AtomicBool::new(true)));
+        f(&job_handler);
+        job_handler.stop();
+    }
+}
+
+#[allow(dead_code)]
+fn test_tessellate_in_jobs() {
+    let context = Context::default();
+    let output = context.run(RawInput::default(), |ctx| {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            (0..100).for_each(|i| {
+                ui.label(format!("Label {}", i));
+                let _ = ui.button(format!("Button {}", i));
+            });
+        });
+        egui::Window::new("Window").show(ctx, |ui| {
+            ui.label("Label in a window");
+        });
+    });
+    let shapes = output.shapes;
+    assert!(shapes.len() > 2 * SHAPES_PER_JOB);
+    let single = context.tessellate(shapes.clone());
+
+    //Meshes split among the jobs are merged back as egui does
+    for_each_job_handler(|job_handler| {
+        [1, 2, 5, shapes.len()].iter().for_each(|&num_jobs| {
+            let primitives =
+                UISystem::tessellate_in_jobs(job_handler, &context, shapes.clone(), num_jobs);
+            assert_eq!(primitives.len(), single.len(), "With {} jobs", num_jobs);
+            primitives.iter().zip(single.iter()).for_each(|(p, s)| {
+                assert_eq!(p.clip_rect, s.clip_rect);
+                match (&p.primitive, &s.primitive) {
+                    (Primitive::Mesh(p), Primitive::Mesh(s)) => {
+                        assert!(p == s, "Mismatch with {} jobs", num_jobs)
+                    }
+                    _ => panic!("Only meshes are expected"),
+                }
+            });
+        });
+    });
+}
+
+#[test]
+fn test() {
+    test_draw_data_in_chunks();
+    test_tessellate_in_jobs();
+    test_ime_composition();
+}
@@ -8,6 +8,7 @@ use inox_math::{Degrees, Matrix4, Vector2, Vector3, Vector4};
 use inox_resources::{
     GenericResource, ResourceCastTo, ResourceId, ResourceTrait, SerializableResource,
 };
+use inox_uid::resource_uid_to_string;
 
 pub trait UIProperties {
     fn show(
@@ -205,7 +206,7 @@ impl UIProperties for RenderPipeline {
         ui: &mut Ui,
         collapsed: bool,
     ) {
-        CollapsingHeader::new(format!("Pipeline [{:?}]", id.as_simple().to_string()))
+        CollapsingHeader::new(format!("Pipeline [{:?}]", resource_uid_to_string(id)))
             .show_background(true)
             .default_open(!collapsed)
             .show(ui, |ui| {
@@ -258,7 +259,7 @@ impl UIProperties for Font {
         ui: &mut Ui,
         collapsed: bool,
     ) {
-        CollapsingHeader::new(format!("Font [{:?}]", id.as_simple().to_string()))
+        CollapsingHeader::new(format!("Font [{:?}]", resource_uid_to_string(id)))
             .show_background(true)
             .default_open(!collapsed)
             .show(ui, |ui| {
@@ -279,7 +280,8 @@ impl UIProperties for Material {
         ui: &mut Ui,
         collapsed: bool,
     ) {
-        CollapsingHeader::new(format!("Material [{:?}]", id.as_simple().to_string()))
+        let data = self.data().clone();
+        CollapsingHeader::new(format!("Material [{:?}]", resource_uid_to_string(id)))
             .show_background(true)
             .default_open(!collapsed)
             .show(ui, |ui| {
@@ -288,6 +290,46 @@ impl UIProperties for Material {
                     let mut path = self.path().to_str().unwrap().to_string();
                     TextEdit::singleline(&mut path).interactive(false).ui(ui);
                 });
+                ui.horizontal(|ui| {
+                    ui.label("Base color: ");
+                    self.data_mut()
+                        .base_color
+                        .show(id, ui_registry, ui, collapsed);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Roughness: ");
+                    DragValue::new(&mut self.data_mut().roughness_factor)
+                        .speed(0.01)
+                        .clamp_range(0.0..=1.0)
+                        .fixed_decimals(3)
+                        .ui(ui);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Metallic: ");
+                    DragValue::new(&mut self.data_mut().metallic_factor)
+                        .speed(0.01)
+                        .clamp_range(0.0..=1.0)
+                        .fixed_decimals(3)
+                        .ui(ui);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Emissive strength: ");
+                    DragValue::new(&mut self.data_mut().emissive_strength)
+                        .speed(0.1)
+                        .clamp_range(0.0..=f32::MAX)
+                        .fixed_decimals(3)
+                        .ui(ui);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Alpha cutoff: ");
+                    DragValue::new(&mut self.data_mut().alpha_cutoff)
+                        .speed(0.01)
+                        .clamp_range(0.0..=1.0)
+                        .fixed_decimals(3)
+                        .ui(ui);
+                });
+                ui.checkbox(&mut self.data_mut().double_sided, "Double sided");
+                ui.checkbox(&mut self.data_mut().unlit, "Unlit");
                 ui.collapsing(format!("Textures [{}]", self.textures().len()), |ui| {
                     self.textures().iter().for_each(|t| {
                         if let Some(t) = t {
@@ -296,7 +338,16 @@ impl UIProperties for Material {
                         }
                     });
                 });
+                //Only materials loaded from a file can be saved back
+                ui.add_enabled_ui(!self.path().as_os_str().is_empty(), |ui| {
+                    if ui.button("Save").clicked() && self.save() {
+                        inox_log::debug_log!("Material saved in {:?}", self.path());
+                    }
+                });
             });
+        if *self.data() != data {
+            self.mark_as_dirty();
+        }
     }
 }
 
@@ -308,7 +359,7 @@ impl UIProperties for Mesh {
         ui: &mut Ui,
         collapsed: bool,
     ) {
-        CollapsingHeader::new(format!("Mesh [{:?}]", id.as_simple().to_string()))
+        CollapsingHeader::new(format!("Mesh [{:?}]", resource_uid_to_string(id)))
             .show_background(true)
             .default_open(!collapsed)
             .show(ui, |ui| {
@@ -340,7 +391,7 @@ impl UIProperties for Texture {
         ui: &mut Ui,
         collapsed: bool,
     ) {
-        CollapsingHeader::new(format!("Texture [{:?}]", id.as_simple().to_string()))
+        CollapsingHeader::new(format!("Texture [{:?}]", resource_uid_to_string(id)))
             .show_background(true)
             .default_open(!collapsed)
             .show(ui, |ui| {
@@ -397,7 +448,8 @@ impl UIProperties for Light {
         ui: &mut Ui,
         collapsed: bool,
     ) {
-        CollapsingHeader::new(format!("Light [{:?}]", id.as_simple().to_string()))
+        let data = *self.data();
+        CollapsingHeader::new(format!("Light [{:?}]", resource_uid_to_string(id)))
             .show_background(true)
             .default_open(!collapsed)
             .show(ui, |ui| {
@@ -409,6 +461,8 @@ impl UIProperties for Light {
                         ui.label("Point");
                     } else if self.data().light_type == LightType::Spot as u32 {
                         ui.label("Spot");
+                    } else if self.data().light_type == LightType::Area as u32 {
+                        ui.label("Area");
                     }
                 });
                 ui.horizontal(|ui| {
@@ -427,6 +481,48 @@ impl UIProperties for Light {
                     ui.label("Range: ");
                     self.data_mut().range.show(id, ui_registry, ui, collapsed);
                 });
+                if self.data().light_type == LightType::Spot as u32 {
+                    ui.horizontal(|ui| {
+                        ui.label("Inner cone angle: ");
+                        self.data_mut()
+                            .inner_cone_angle
+                            .show(id, ui_registry, ui, collapsed);
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Outer cone angle: ");
+                        self.data_mut()
+                            .outer_cone_angle
+                            .show(id, ui_registry, ui, collapsed);
+                    });
+                }
+                if self.data().light_type == LightType::Area as u32 {
+                    ui.horizontal(|ui| {
+                        ui.label("Size: ");
+                        let mut size: Vector2 = self.data().area_size.into();
+                        size.show(id, ui_registry, ui, collapsed);
+                        self.data_mut().area_size = size.into();
+                    });
+                }
+                ui.horizontal(|ui| {
+                    let mut cast_shadows = self.data().cast_shadows != 0;
+                    ui.checkbox(&mut cast_shadows, "Cast shadows");
+                    self.data_mut().cast_shadows = cast_shadows as _;
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Shadow bias: ");
+                    self.data_mut()
+                        .shadow_bias
+                        .show(id, ui_registry, ui, collapsed);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Shadow normal bias: ");
+                    self.data_mut()
+                        .shadow_normal_bias
+                        .show(id, ui_registry, ui, collapsed);
+                });
             });
+        if *self.data() != data {
+            self.mark_as_dirty();
+        }
     }
 }
@@ -1,6 +1,8 @@
 #![warn(clippy::all)]
-pub use self::uuid::*;
 pub use self::crc::*;
+pub use self::uid64::*;
+pub use self::uuid::*;
 
-pub mod uuid;
 pub mod crc;
+pub mod uid64;
+pub mod uuid;
@@ -0,0 +1,161 @@
+use crate::{checksum, Uid};
+use uuid::Uuid;
+
+/// Compact 64-bit identifier.
+///
+/// Half the size of a [`Uid`] and cheaper to hash, meant for GPU-side indices and hot loops.
+/// Random ids are drawn from 64 bits of a v4 uuid: with `n` live ids the probability of a
+/// collision is roughly `n^2 / 2^65` (about 1 in 37 million for one million ids),
+/// so prefer [`Uid`] for ids that are persisted or exchanged between processes.
+pub type Uid64 = u64;
+pub const INVALID_UID64: Uid64 = 0;
+
+/// Width of the resource ids, 64 bits with the `uid64` feature and 128 bits without it.
+#[cfg(feature = "uid64")]
+pub type ResourceUid = Uid64;
+#[cfg(feature = "uid64")]
+pub const INVALID_RESOURCE_UID: ResourceUid = INVALID_UID64;
+#[cfg(not(feature = "uid64"))]
+pub type ResourceUid = Uid;
+#[cfg(not(feature = "uid64"))]
+pub const INVALID_RESOURCE_UID: ResourceUid = crate::INVALID_UID;
+
+#[cfg(feature = "uid64")]
+#[inline]
+pub fn generate_random_resource_uid() -> ResourceUid {
+    generate_random_uid64()
+}
+#[cfg(not(feature = "uid64"))]
+#[inline]
+pub fn generate_random_resource_uid() -> ResourceUid {
+    crate::generate_random_uid()
+}
+#[cfg(feature = "uid64")]
+#[inline]
+pub fn generate_resource_uid_from_string(string: &str) -> ResourceUid {
+    generate_uid64_from_string(string)
+}
+#[cfg(not(feature = "uid64"))]
+#[inline]
+pub fn generate_resource_uid_from_string(string: &str) -> ResourceUid {
+    crate::generate_uid_from_string(string)
+}
+#[cfg(feature = "uid64")]
+#[inline]
+pub const fn generate_static_resource_uid_from_string(string: &str) -> ResourceUid {
+    generate_static_uid64_from_string(string)
+}
+#[cfg(not(feature = "uid64"))]
+#[inline]
+pub const fn generate_static_resource_uid_from_string(string: &str) -> ResourceUid {
+    uid64_to_uid(generate_static_uid64_from_string(string))
+}
+//Compact text form of a resource id, without separators
+#[cfg(feature = "uid64")]
+#[inline]
+pub fn resource_uid_to_string(uid: &ResourceUid) -> String {
+    format!("{uid:016x}")
+}
+#[cfg(not(feature = "uid64"))]
+#[inline]
+pub fn resource_uid_to_string(uid: &ResourceUid) -> String {
+    uid.as_simple().to_string()
+}
+#[cfg(feature = "uid64")]
+#[inline]
+pub fn resource_uid_from_string(string: &str) -> Option<ResourceUid> {
+    u64::from_str_radix(string, 16).ok()
+}
+#[cfg(not(feature = "uid64"))]
+#[inline]
+pub fn resource_uid_from_string(string: &str) -> Option<ResourceUid> {
+    Uuid::parse_str(string).ok()
+}
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+#[inline]
+pub fn generate_random_uid64() -> Uid64 {
+    loop {
+        let uid = uid_to_uid64(&Uuid::new_v4());
+        if uid != INVALID_UID64 {
+            return uid;
+        }
+    }
+}
+//A hash that lands on the invalid id is moved to another value
+#[inline]
+const fn valid_uid64(uid: Uid64) -> Uid64 {
+    if uid == INVALID_UID64 {
+        Uid64::MAX
+    } else {
+        uid
+    }
+}
+
+#[inline]
+pub fn generate_uid64_from_string(string: &str) -> Uid64 {
+    valid_uid64(checksum(0, string.as_bytes()))
+}
+
+#[inline]
+pub const fn generate_static_uid64_from_string(string: &str) -> Uid64 {
+    //FNV-1a so that it can be evaluated at compile time
+    let bytes = string.as_bytes();
+    let mut hash = FNV_OFFSET_BASIS;
+    let mut i = 0;
+    while i < bytes.len() {
+        hash ^= bytes[i] as u64;
+        hash = hash.wrapping_mul(FNV_PRIME);
+        i += 1;
+    }
+    valid_uid64(hash)
+}
+
+#[inline]
+pub fn uid_to_uid64(uid: &Uid) -> Uid64 {
+    let bytes = uid.as_bytes();
+    let mut high = [0u8; 8];
+    let mut low = [0u8; 8];
+    high.copy_from_slice(&bytes[..8]);
+    low.copy_from_slice(&bytes[8..]);
+    u64::from_le_bytes(high) ^ u64::from_le_bytes(low)
+}
+
+#[inline]
+pub const fn uid64_to_uid(uid: Uid64) -> Uid {
+    let bytes = uid.to_le_bytes();
+    let mut bytes_to_use: [u8; 16] = [0u8; 16];
+    let mut i = 0;
+    while i < 8 {
+        bytes_to_use[i] = bytes[i];
+        i += 1;
+    }
+    Uuid::from_bytes(bytes_to_use)
+}
+
+#[test]
+fn test_uid64() {
+    assert_ne!(generate_random_uid64(), INVALID_UID64);
+    assert_ne!(generate_random_uid64(), generate_random_uid64());
+    const TLAS: Uid64 = generate_static_uid64_from_string("TLAS");
+    assert_eq!(TLAS, generate_static_uid64_from_string("TLAS"));
+    assert_ne!(TLAS, generate_static_uid64_from_string("BLAS"));
+    assert_eq!(
+        generate_uid64_from_string("mesh"),
+        generate_uid64_from_string("mesh")
+    );
+    assert_eq!(uid_to_uid64(&uid64_to_uid(TLAS)), TLAS);
+    assert_eq!(valid_uid64(INVALID_UID64), Uid64::MAX);
+    assert_eq!(valid_uid64(TLAS), TLAS);
+    assert_ne!(generate_random_resource_uid(), INVALID_RESOURCE_UID);
+    let id = generate_resource_uid_from_string("mesh");
+    assert_eq!(id, generate_resource_uid_from_string("mesh"));
+    assert_eq!(
+        resource_uid_from_string(&resource_uid_to_string(&id)),
+        Some(id)
+    );
+    const STATIC_ID: ResourceUid = generate_static_resource_uid_from_string("TLAS");
+    assert_ne!(STATIC_ID, INVALID_RESOURCE_UID);
+}
@@ -13,8 +13,12 @@
 #define TEXTURE_TYPE_OCCLUSION 4
 #define TEXTURE_TYPE_SPECULAR_GLOSSINESS 5
 #define TEXTURE_TYPE_DIFFUSE 6
-#define TEXTURE_TYPE_EMPTY_FOR_PADDING 7
-#define TEXTURE_TYPE_COUNT 8
+#define TEXTURE_TYPE_CLEARCOAT 7
+#define TEXTURE_TYPE_CLEARCOAT_ROUGHNESS 8
+#define TEXTURE_TYPE_CLEARCOAT_NORMAL 9
+#define TEXTURE_TYPE_TRANSMISSION 10
+#define TEXTURE_TYPE_EMPTY_FOR_PADDING_1 11
+#define TEXTURE_TYPE_COUNT 12
 
 #define CONSTANT_DATA_FLAGS_NONE 0
 #define CONSTANT_DATA_FLAGS_SUPPORT_SRGB 1
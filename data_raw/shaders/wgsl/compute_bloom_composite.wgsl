@@ -0,0 +1,56 @@
+struct BloomData {
+    dimensions: vec2<u32>,
+    levels_count: u32,
+    threshold: f32,
+    intensity: f32,
+    padding: u32,
+};
+
+@group(0) @binding(0)
+var<uniform> bloom_data: BloomData;
+@group(0) @binding(1)
+var<storage, read_write> bloom: array<vec4<f32>>;
+
+@group(1) @binding(0)
+var radiance: texture_storage_2d<rgba16float, read_write>;
+
+#import "bloom_utils.inc"
+
+
+fn bloom_texel(level: u32, texel: vec2<i32>) -> vec3<f32> {
+    let size = bloom_level_size(level);
+    let t = vec2<u32>(clamp(texel, vec2<i32>(0), vec2<i32>(size) - 1));
+    return bloom[bloom_level_offset(level) + t.y * size.x + t.x].rgb;
+}
+
+//Bilinear filtering of the level at the center of the pixel
+fn bloom_sample(level: u32, pixel: vec2<u32>) -> vec3<f32> {
+    let position = (vec2<f32>(pixel) + 0.5) / f32(2u << level) - 0.5;
+    let texel = vec2<i32>(floor(position));
+    let f = position - floor(position);
+    let top = mix(bloom_texel(level, texel), bloom_texel(level, texel + vec2<i32>(1, 0)), f.x);
+    let bottom = mix(
+        bloom_texel(level, texel + vec2<i32>(0, 1)),
+        bloom_texel(level, texel + vec2<i32>(1, 1)),
+        f.x
+    );
+    return mix(top, bottom, f.y);
+}
+
+//Adds the upsampled levels of the chain to the radiance, scaled by the intensity
+@compute
+@workgroup_size(8, 8, 1)
+fn main(
+    @builtin(global_invocation_id) global_invocation_id: vec3<u32>
+) {
+    let pixel = global_invocation_id.xy;
+    if (pixel.x >= bloom_data.dimensions.x || pixel.y >= bloom_data.dimensions.y) {
+        return;
+    }
+    var color = vec3<f32>(0.);
+    for(var level = 0u; level < bloom_data.levels_count; level = level + 1u) {
+        color += bloom_sample(level, pixel);
+    }
+    let radiance_color = textureLoad(radiance, vec2<i32>(pixel));
+    textureStore(radiance, vec2<i32>(pixel), vec4<f32>(radiance_color.rgb + color * bloom_data.intensity, radiance_color.a));
+}
@@ -0,0 +1,68 @@
+struct BloomData {
+    dimensions: vec2<u32>,
+    levels_count: u32,
+    threshold: f32,
+    intensity: f32,
+    padding: u32,
+};
+
+struct BloomLevel {
+    level: u32,
+};
+
+@group(0) @binding(0)
+var<uniform> bloom_data: BloomData;
+@group(0) @binding(1)
+var<storage, read_write> bloom: array<vec4<f32>>;
+@group(0) @binding(2)
+var<uniform> bloom_level: BloomLevel;
+
+@group(1) @binding(0)
+var radiance: texture_storage_2d<rgba16float, read_write>;
+
+#import "bloom_utils.inc"
+
+
+//Keeps only the part of the color brighter than the threshold
+fn bloom_prefilter(color: vec3<f32>) -> vec3<f32> {
+    let luminance = dot(color, vec3<f32>(0.2126, 0.7152, 0.0722));
+    return color * max(luminance - bloom_data.threshold, 0.) / max(luminance, 0.0001);
+}
+
+//Each texel blurs the 4x4 texels around the 2x2 ones below it with a tent filter.
+//Level 0 reads the radiance, filtered by the threshold, the others the level below
+@compute
+@workgroup_size(8, 8, 1)
+fn main(
+    @builtin(global_invocation_id) global_invocation_id: vec3<u32>
+) {
+    let level = bloom_level.level;
+    let size = bloom_level_size(level);
+    if (global_invocation_id.x >= size.x || global_invocation_id.y >= size.y) {
+        return;
+    }
+    var source_size = bloom_data.dimensions;
+    var source_offset = 0u;
+    if (level > 0u) {
+        source_size = bloom_level_size(level - 1u);
+        source_offset = bloom_level_offset(level - 1u);
+    }
+    let weights = vec4<f32>(1., 3., 3., 1.) / 8.;
+    var color = vec3<f32>(0.);
+    for(var i = 0u; i < 16u; i = i + 1u) {
+        let tap = vec2<u32>(i & 3u, i >> 2u);
+        let texel = vec2<u32>(clamp(
+            vec2<i32>(global_invocation_id.xy * 2u + tap) - 1,
+            vec2<i32>(0),
+            vec2<i32>(source_size) - 1
+        ));
+        var source = vec3<f32>(0.);
+        if (level == 0u) {
+            source = bloom_prefilter(textureLoad(radiance, vec2<i32>(texel)).rgb);
+        } else {
+            source = bloom[source_offset + texel.y * source_size.x + texel.x].rgb;
+        }
+        color += source * weights[tap.x] * weights[tap.y];
+    }
+    bloom[bloom_level_offset(level) + global_invocation_id.y * size.x + global_invocation_id.x] = vec4<f32>(color, 1.);
+}
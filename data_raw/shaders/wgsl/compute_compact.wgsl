@@ -5,10 +5,10 @@
 
 struct CullingData {
     view: mat4x4<f32>,
+    previous_view_proj: mat4x4<f32>,
     mesh_flags: u32,
-    _padding1: u32,
-    _padding2: u32,
-    _padding3: u32,
+    hi_z_levels_count: u32,
+    depth_dimensions: vec2<u32>,
 };
 
 @group(0) @binding(0)
@@ -25,6 +25,8 @@ var<storage, read> meshes: Meshes;
 var<storage, read> bhv: BHV;
 @group(0) @binding(6)
 var<storage, read> meshes_flags: MeshFlags;
+@group(0) @binding(7)
+var<storage, read> materials: Materials;
 
 @group(1) @binding(0)
 var<storage, read_write> count: atomic<u32>;
@@ -32,6 +34,8 @@ var<storage, read_write> count: atomic<u32>;
 var<storage, read_write> commands: DrawIndexedCommands;
 @group(1) @binding(2)
 var<storage, read_write> culling_result: array<atomic<u32>>;
+@group(1) @binding(3)
+var<storage, read_write> hi_z: array<f32>;
 
 
 
@@ -49,18 +53,20 @@ fn main(
         return;
     }
     
-    let draw_group_index = workgroup_id.x;
+    //The workgroup size can be tuned, so the bits are found from the meshlet and not the group
+    let word_index = meshlet_id / 32u;
+    let bit_index = meshlet_id % 32u;
 
-    let bits = atomicLoad(&culling_result[draw_group_index]);
-    let shift = 1u << local_invocation_id.x;
+    let bits = atomicLoad(&culling_result[word_index]);
+    let shift = 1u << bit_index;
     let is_visible = bits & shift;
     if (is_visible != 0u) {
-        let mask = 0xFFFFFFFFu << local_invocation_id.x;
+        let mask = 0xFFFFFFFFu << bit_index;
         let result = bits & mask;
         let group_count = countOneBits(result);
 
         var previous_count = 0u;
-        for(var i = 0u; i < draw_group_index; i = i + 1u) {
+        for(var i = 0u; i < word_index; i = i + 1u) {
             let b = atomicLoad(&culling_result[i]);
             previous_count = previous_count + countOneBits(b);
         }
@@ -72,7 +78,7 @@ fn main(
         
         let command = &commands.data[index];
         (*command).vertex_count = (*meshlet).indices_count;
-        (*command).instance_count = 1u;
+        (*command).instance_count = (*mesh).instances_count;
         (*command).base_index = (*mesh).indices_offset + (*meshlet).indices_offset;
         (*command).vertex_offset = i32((*mesh).vertex_offset);
         (*command).base_instance = meshlet_id;
@@ -5,10 +5,10 @@
 
 struct CullingData {
     view: mat4x4<f32>,
+    previous_view_proj: mat4x4<f32>,
     mesh_flags: u32,
-    _padding1: u32,
-    _padding2: u32,
-    _padding3: u32,
+    hi_z_levels_count: u32,
+    depth_dimensions: vec2<u32>,
 };
 
 @group(0) @binding(0)
@@ -25,6 +25,8 @@ var<storage, read> meshes: Meshes;
 var<storage, read> bhv: BHV;
 @group(0) @binding(6)
 var<storage, read> meshes_flags: MeshFlags;
+@group(0) @binding(7)
+var<storage, read> materials: Materials;
 
 @group(1) @binding(0)
 var<storage, read_write> count: atomic<u32>;
@@ -32,8 +34,11 @@ var<storage, read_write> count: atomic<u32>;
 var<storage, read_write> commands: DrawIndexedCommands;
 @group(1) @binding(2)
 var<storage, read_write> culling_result: array<atomic<u32>>;
+@group(1) @binding(3)
+var<storage, read_write> hi_z: array<f32>;
 
 #import "matrix_utils.inc"
+#import "hi_z_utils.inc"
 
 
 //ScreenSpace Frustum Culling
@@ -67,9 +72,74 @@ fn is_box_inside_frustum(min: vec3<f32>, max: vec3<f32>, frustum: array<vec4<f32
     return visible;
 }
 
+//With a rotation the transformed min and max are not the corners of the box anymore:
+//the bounds are the ones of all the 8 transformed corners
+fn transform_box(box_min: vec3<f32>, box_max: vec3<f32>, position: vec3<f32>, orientation: vec4<f32>, scale: vec3<f32>) -> array<vec3<f32>, 2> {
+    let first = transform_vector(box_min, position, orientation, scale);
+    var bounds = array<vec3<f32>, 2>(first, first);
+    for(var i = 1u; i < 8u; i = i + 1u) {
+        let corner = select(box_min, box_max, vec3<bool>((i & 1u) != 0u, (i & 2u) != 0u, (i & 4u) != 0u));
+        let v = transform_vector(corner, position, orientation, scale);
+        bounds[0] = min(bounds[0], v);
+        bounds[1] = max(bounds[1], v);
+    }
+    return bounds;
+}
+
+//The box is projected with the view of the previous frame, the one of the depth in the pyramid,
+//and it's hidden when it's farther than all the texels of the pyramid it covers
+fn is_box_occluded(box_min: vec3<f32>, box_max: vec3<f32>) -> bool {
+    if (culling_data.hi_z_levels_count == 0u) {
+        return false;
+    }
+    var uv_min = vec2<f32>(1.);
+    var uv_max = vec2<f32>(0.);
+    var nearest_depth = 1.;
+    for(var i = 0u; i < 8u; i = i + 1u) {
+        let corner = select(box_min, box_max, vec3<bool>((i & 1u) != 0u, (i & 2u) != 0u, (i & 4u) != 0u));
+        let clip = culling_data.previous_view_proj * vec4<f32>(corner, 1.);
+        //Crossing the camera plane the projection of the box isn't meaningful
+        if (clip.w <= 0.) {
+            return false;
+        }
+        let ndc = clip.xyz / clip.w;
+        let uv = vec2<f32>(ndc.x * 0.5 + 0.5, 0.5 - ndc.y * 0.5);
+        uv_min = min(uv_min, uv);
+        uv_max = max(uv_max, uv);
+        nearest_depth = min(nearest_depth, ndc.z);
+    }
+    if (nearest_depth <= 0.) {
+        return false;
+    }
+    let dimensions = vec2<f32>(culling_data.depth_dimensions);
+    let pixels_min = clamp(uv_min, vec2<f32>(0.), vec2<f32>(1.)) * dimensions;
+    let pixels_max = clamp(uv_max, vec2<f32>(0.), vec2<f32>(1.)) * dimensions;
+    let extent = max(pixels_max.x - pixels_min.x, pixels_max.y - pixels_min.y);
+    //Texels of a level cover 2^(level + 1) pixels, so the box covers at most 2x2 of them
+    let level = min(u32(max(ceil(log2(max(extent, 1.))) - 1., 0.)), culling_data.hi_z_levels_count - 1u);
+    let size = hi_z_level_size(level);
+    let offset = hi_z_level_offset(level);
+    let texel_size = f32(2u << level);
+    let texel_min = min(vec2<u32>(pixels_min / texel_size), size - 1u);
+    let texel_max = min(vec2<u32>(pixels_max / texel_size), size - 1u);
+    var farthest_depth = 0.;
+    for(var y = texel_min.y; y <= texel_max.y; y = y + 1u) {
+        for(var x = texel_min.x; x <= texel_max.x; x = x + 1u) {
+            farthest_depth = max(farthest_depth, hi_z[offset + y * size.x + x]);
+        }
+    }
+    return nearest_depth > farthest_depth;
+}
+
+//A meshlet is back facing when the camera is inside the cone opposite to the normals
+//of its triangles, enlarged by the bounding sphere of the meshlet
 fn is_cone_visible(center: vec3<f32>, cone_axis: vec3<f32>, cone_cutoff: f32, radius: f32) -> bool {
-    let direction = center - culling_data.view[3].xyz;
-    return dot(normalize(direction), cone_axis) < (cone_cutoff * length(direction) + radius);
+    //The view is a rigid transform, so the camera position is its inverse translation
+    let view = culling_data.view;
+    let rotation = mat3x3<f32>(view[0].xyz, view[1].xyz, view[2].xyz);
+    let camera_position = -(transpose(rotation) * view[3].xyz);
+    let direction = center - camera_position;
+    return dot(direction, cone_axis) < cone_cutoff * length(direction) + radius;
 }
 
 
@@ -103,8 +173,9 @@ fn main(
     let mesh = &meshes.data[mesh_id];
     let bb_id = (*mesh).bhv_index + (*meshlet).bhv_index;
     let bb = &bhv.data[bb_id];
-    let max = transform_vector((*bb).max, (*mesh).position, (*mesh).orientation, (*mesh).scale);
-    let min = transform_vector((*bb).min, (*mesh).position, (*mesh).orientation, (*mesh).scale);
+    let bounds = transform_box((*bb).min, (*bb).max, (*mesh).position, (*mesh).orientation, (*mesh).scale);
+    let min = bounds[0];
+    let max = bounds[1];
     let d = (max-min) * 0.5;
     let center = min + d;
     let radius = length(d);
@@ -129,13 +200,26 @@ fn main(
         return;
     }
 
+    // Back facing meshlets are visible too when the material is double sided
+    var is_double_sided = false;
+    if ((*mesh).material_index >= 0) {
+        let material = &materials.data[(*mesh).material_index];
+        is_double_sided = ((*material).flags & MATERIAL_FLAGS_DOUBLE_SIDED) != 0u;
+    }
     let cone_culling = &meshlets_culling.data[meshlet_id];
     let cone_axis_cutoff = unpack4x8snorm((*cone_culling).cone_axis_cutoff);
     let cone_axis = rotate_vector(cone_axis_cutoff.xyz, (*mesh).orientation);    
-    if (is_cone_visible((*cone_culling).center, cone_axis, cone_axis_cutoff.w, radius))
+    if (is_double_sided || is_cone_visible(center, cone_axis, cone_axis_cutoff.w, radius))
     {
-        atomicAdd(&count, 1u);
-        let draw_group_index = workgroup_id.x;
-        atomicOr(&culling_result[draw_group_index], 1u << local_invocation_id.x);
+        if is_box_occluded(min, max) {
+            return;
+        }
+        //The copies of a meshlet in all the instances are drawn by the command of the first one
+        let visible_id = meshlet_id - (*mesh).instance_index;
+        let bit = 1u << (visible_id % 32u);
+        let previous = atomicOr(&culling_result[visible_id / 32u], bit);
+        if ((previous & bit) == 0u) {
+            atomicAdd(&count, 1u);
+        }
     }
 }
\ No newline at end of file
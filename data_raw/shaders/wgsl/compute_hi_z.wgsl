@@ -0,0 +1,58 @@
+#import "utils.inc"
+#import "common.inc"
+
+struct CullingData {
+    view: mat4x4<f32>,
+    previous_view_proj: mat4x4<f32>,
+    mesh_flags: u32,
+    hi_z_levels_count: u32,
+    depth_dimensions: vec2<u32>,
+};
+
+@group(0) @binding(1)
+var<uniform> culling_data: CullingData;
+
+@group(1) @binding(3)
+var<storage, read_write> hi_z: array<f32>;
+
+struct HiZLevel {
+    level: u32,
+};
+
+@group(2) @binding(0)
+var depth_texture: texture_depth_2d;
+@group(2) @binding(1)
+var<uniform> hi_z_level: HiZLevel;
+
+#import "hi_z_utils.inc"
+
+
+//Each texel keeps the farthest depth of the 2x2 texels below it.
+//Each level is a dispatch on its own, bound with the uniform of its level
+@compute
+@workgroup_size(8, 8, 1)
+fn main(
+    @builtin(global_invocation_id) global_invocation_id: vec3<u32>,
+) {
+    let level = hi_z_level.level;
+    let size = hi_z_level_size(level);
+    if (global_invocation_id.x >= size.x || global_invocation_id.y >= size.y) {
+        return;
+    }
+    var source_size = culling_data.depth_dimensions;
+    var source_offset = 0u;
+    if (level > 0u) {
+        source_size = hi_z_level_size(level - 1u);
+        source_offset = hi_z_level_offset(level - 1u);
+    }
+    var farthest_depth = 0.;
+    for(var i = 0u; i < 4u; i = i + 1u) {
+        let texel = min(global_invocation_id.xy * 2u + vec2<u32>(i & 1u, i >> 1u), source_size - 1u);
+        if (level == 0u) {
+            farthest_depth = max(farthest_depth, textureLoad(depth_texture, vec2<i32>(texel), 0));
+        } else {
+            farthest_depth = max(farthest_depth, hi_z[source_offset + texel.y * source_size.x + texel.x]);
+        }
+    }
+    hi_z[hi_z_level_offset(level) + global_invocation_id.y * size.x + global_invocation_id.x] = farthest_depth;
+}
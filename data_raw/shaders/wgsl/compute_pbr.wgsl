@@ -43,7 +43,7 @@ var<storage, read> bhv: BHV;
 @group(3) @binding(0)
 var visibility_buffer_texture: texture_2d<f32>;
 @group(3) @binding(1)
-var render_target: texture_storage_2d<rgba8unorm, read_write>;
+var render_target: texture_storage_2d<rgba16float, read_write>;
 
 
 
@@ -51,6 +51,7 @@ var render_target: texture_storage_2d<rgba8unorm, read_write>;
 #import "texture_utils.inc"
 #import "material_utils.inc"
 #import "geom_utils.inc"
+#import "ambient_utils.inc"
 #import "pbr_utils.inc"
 
 
@@ -143,12 +144,16 @@ fn main(
     let barycentrics = compute_barycentrics(p1.xy, p2.xy, p3.xy, screen_pixel.xy);
     let deriv = compute_partial_derivatives(p1.xy, p2.xy, p3.xy);
 
-    let c1 = unpack_unorm_to_4_f32(u32(colors.data[(*v1).position_and_color_offset]));
-    let c2 = unpack_unorm_to_4_f32(u32(colors.data[(*v2).position_and_color_offset]));
-    let c3 = unpack_unorm_to_4_f32(u32(colors.data[(*v3).position_and_color_offset]));
-
-    let vertex_color = barycentrics.x * c1 + barycentrics.y * c2 + barycentrics.z * c3;        
-    let alpha = compute_alpha(material_id, vertex_color.a);
+    var vertex_color = vec4<f32>(1.);
+    if (((*mesh).flags & MESH_FLAGS_USE_VERTEX_COLOR) != 0u) {
+        let c1 = unpack_unorm_to_4_f32(u32(colors.data[(*v1).position_and_color_offset]));
+        let c2 = unpack_unorm_to_4_f32(u32(colors.data[(*v2).position_and_color_offset]));
+        let c3 = unpack_unorm_to_4_f32(u32(colors.data[(*v3).position_and_color_offset]));
+        vertex_color = barycentrics.x * c1 + barycentrics.y * c2 + barycentrics.z * c3;
+    }
+    // NOTE: uvs are not known yet, transmissive meshes are drawn by the transparent pass anyway
+    let transmission = materials.data[material_id].transmission_factor;
+    let alpha = compute_alpha(material_id, vertex_color.a, transmission);
     if alpha <= 0. {
         textureStore(render_target, vec2<i32>(pixel.xy), color);
         return;
@@ -179,9 +184,9 @@ fn main(
     let texture_color = sample_material_texture(material_id, TEXTURE_TYPE_BASE_COLOR, uv_set);
     color = vec4<f32>(vertex_color.rgb * texture_color.rgb, alpha);
 
-    let n1 = decode_as_vec3(normals.data[(*v1).normal_offset]);
-    let n2 = decode_as_vec3(normals.data[(*v2).normal_offset]);
-    let n3 = decode_as_vec3(normals.data[(*v3).normal_offset]);
+    let n1 = decode_normal(normals.data[(*v1).normal_offset]);
+    let n2 = decode_normal(normals.data[(*v2).normal_offset]);
+    let n3 = decode_normal(normals.data[(*v3).normal_offset]);
 
     let world_pos = interpolate_3d_attribute(p1.xyz, p2.xyz, p3.xyz, deriv, delta);
     let n = interpolate_3d_attribute(n1, n2, n3, deriv, delta);
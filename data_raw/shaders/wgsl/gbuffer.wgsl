@@ -67,14 +67,17 @@ fn vs_main(
     
     let p = (*aabb).min + decode_as_vec3(positions.data[v_in.position_and_color_offset]) * aabb_size;
     let world_position = vec4<f32>(transform_vector(p, (*mesh).position, (*mesh).orientation, (*mesh).scale), 1.0);
-    let color = unpack_unorm_to_4_f32(colors.data[v_in.position_and_color_offset]);
+    var color = vec4<f32>(1.);
+    if (((*mesh).flags & MESH_FLAGS_USE_VERTEX_COLOR) != 0u) {
+        color = unpack_unorm_to_4_f32(colors.data[v_in.position_and_color_offset]);
+    }
     
     var vertex_out: VertexOutput;
     vertex_out.clip_position = mvp * world_position;
     vertex_out.mesh_and_meshlet_ids = vec2<u32>(mesh_id, meshlet_id);
     vertex_out.world_pos = world_position;
     vertex_out.color = color;
-    vertex_out.normal = decode_as_vec3(normals.data[v_in.normal_offset]); 
+    vertex_out.normal = decode_normal(normals.data[v_in.normal_offset]); 
     vertex_out.uv_0 = unpack2x16float(uvs.data[v_in.uvs_offset.x]);
     vertex_out.uv_1 = unpack2x16float(uvs.data[v_in.uvs_offset.y]);
     vertex_out.uv_2 = unpack2x16float(uvs.data[v_in.uvs_offset.z]);
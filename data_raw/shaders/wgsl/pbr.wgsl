@@ -8,6 +8,7 @@ struct VertexOutput {
 
 struct FragmentOutput {
     @location(0) color: vec4<f32>,
+    @builtin(frag_depth) depth: f32,
 };
 
 
@@ -44,6 +45,7 @@ var depth_texture: texture_depth_2d;
 #import "texture_utils.inc"
 #import "material_utils.inc"
 #import "matrix_utils.inc"
+#import "ibl_utils.inc"
 #import "pbr_utils.inc"
 
 
@@ -101,14 +103,20 @@ fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> VertexOutput {
 
 
 @fragment
-fn fs_main(v_in: VertexOutput) -> @location(0) vec4<f32> {
+fn fs_main(v_in: VertexOutput) -> FragmentOutput {
     let d = vec2<f32>(textureDimensions(depth_texture));
     let pixel_coords = vec2<i32>(i32(v_in.uv.x * d.x), i32(v_in.uv.y * d.y));
+
+    //The gbuffer is rendered at the render scale, its depth is resolved here at the size
+    //of the surface for the passes drawing over it
+    var fragment_out: FragmentOutput;
+    fragment_out.depth = sample_gbuffer(7u, pixel_coords).r;
+    fragment_out.color = vec4<f32>(0., 0., 0., 0.);
     
     let vertex_color = sample_gbuffer(0u, pixel_coords);
     let meshlet_id = pack4x8unorm(sample_gbuffer(2u, pixel_coords));
     if meshlet_id == 0u {
-        return vec4<f32>(0., 0., 0., 0.);
+        return fragment_out;
     }
 
     var color = vec4<f32>(0., 0., 0., 0.);
@@ -132,7 +140,8 @@ fn fs_main(v_in: VertexOutput) -> @location(0) vec4<f32> {
         let material_id = u32((*mesh).material_index);
         let texture_color = sample_material_texture(material_id, TEXTURE_TYPE_BASE_COLOR, uv_set);
 
-        let alpha = compute_alpha(material_id, vertex_color.a);
+        let transmission = compute_transmission(material_id, uv_set);
+        let alpha = compute_alpha(material_id, vertex_color.a, transmission);
         if alpha < 0. {
             discard;
         }
@@ -146,5 +155,6 @@ fn fs_main(v_in: VertexOutput) -> @location(0) vec4<f32> {
         color = compute_brdf(world_pos, normal, material_id, color, uv_set);
     }
 
-    return color;
+    fragment_out.color = color;
+    return fragment_out;
 }
\ No newline at end of file
@@ -0,0 +1,41 @@
+#import "sdf_utils.inc"
+
+struct TextData {
+    color: vec4<f32>,
+    outline_color: vec4<f32>,
+    outline_width: f32,
+    softness: f32,
+    _padding_0: f32,
+    _padding_1: f32,
+};
+
+//Min and max corners of the glyph quad in clip space and the area of the distance field they show
+struct GlyphInput {
+    @location(0) rect: vec4<f32>,
+    @location(1) uv_rect: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+@group(0) @binding(0) var<uniform> text_data: TextData;
+@group(0) @binding(1) var glyph_texture: texture_2d<f32>;
+@group(0) @binding(2) var glyph_sampler: sampler;
+
+//Drawn as a triangle strip of 4 vertices for each glyph instance
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32, glyph: GlyphInput) -> VertexOutput {
+    let corner = vec2<f32>(f32(vertex_index & 1u), f32(vertex_index >> 1u));
+    var output: VertexOutput;
+    output.clip_position = vec4<f32>(mix(glyph.rect.xy, glyph.rect.zw, corner), 0., 1.);
+    output.uv = mix(glyph.uv_rect.xy, glyph.uv_rect.zw, corner);
+    return output;
+}
+
+@fragment
+fn fs_main(v_in: VertexOutput) -> @location(0) vec4<f32> {
+    let distance = textureSample(glyph_texture, glyph_sampler, v_in.uv).r;
+    return sdf_text_color(distance, text_data.color, text_data.outline_color, text_data.outline_width, text_data.softness);
+}
@@ -0,0 +1,107 @@
+#import "utils.inc"
+#import "common.inc"
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) @interpolate(flat) mesh_and_meshlet_ids: vec2<u32>,
+    @location(1) world_pos: vec4<f32>,
+    @location(2) color: vec4<f32>,
+    @location(3) normal: vec3<f32>,
+    @location(4) uv_0: vec2<f32>,
+    @location(5) uv_1: vec2<f32>,
+    @location(6) uv_2: vec2<f32>,
+    @location(7) uv_3: vec2<f32>,
+};
+
+
+@group(0) @binding(0)
+var<uniform> constant_data: ConstantData;
+@group(0) @binding(1)
+var<storage, read> positions: Positions;
+@group(0) @binding(2)
+var<storage, read> colors: Colors;
+@group(0) @binding(3)
+var<storage, read> normals: Normals;
+@group(0) @binding(4)
+var<storage, read> uvs: UVs;
+
+@group(1) @binding(0)
+var<storage, read> meshes: Meshes;
+@group(1) @binding(1)
+var<storage, read> materials: Materials;
+@group(1) @binding(2)
+var<storage, read> textures: Textures;
+@group(1) @binding(3)
+var<storage, read> meshlets: Meshlets;
+@group(1) @binding(4)
+var<storage, read> bhv: BHV;
+@group(1) @binding(5)
+var<storage, read> lights: Lights;
+
+#import "matrix_utils.inc"
+#import "texture_utils.inc"
+#import "material_utils.inc"
+#import "ibl_utils.inc"
+#import "pbr_utils.inc"
+
+
+@vertex
+fn vs_main(
+    @builtin(vertex_index) vertex_index: u32,
+    @builtin(instance_index) meshlet_id: u32,
+    v_in: Vertex,
+) -> VertexOutput {
+    let mvp = constant_data.proj * constant_data.view;
+
+    let mesh_id = u32(meshlets.data[meshlet_id].mesh_index);
+    let mesh = &meshes.data[mesh_id];
+    let aabb = &bhv.data[(*mesh).bhv_index];
+
+    let aabb_size = abs((*aabb).max - (*aabb).min);
+    
+    let p = (*aabb).min + decode_as_vec3(positions.data[v_in.position_and_color_offset]) * aabb_size;
+    let world_position = vec4<f32>(transform_vector(p, (*mesh).position, (*mesh).orientation, (*mesh).scale), 1.0);
+    var color = vec4<f32>(1.);
+    if (((*mesh).flags & MESH_FLAGS_USE_VERTEX_COLOR) != 0u) {
+        color = unpack_unorm_to_4_f32(colors.data[v_in.position_and_color_offset]);
+    }
+    
+    var vertex_out: VertexOutput;
+    vertex_out.clip_position = mvp * world_position;
+    vertex_out.mesh_and_meshlet_ids = vec2<u32>(mesh_id, meshlet_id);
+    vertex_out.world_pos = world_position;
+    vertex_out.color = color;
+    vertex_out.normal = decode_normal(normals.data[v_in.normal_offset]); 
+    vertex_out.uv_0 = unpack2x16float(uvs.data[v_in.uvs_offset.x]);
+    vertex_out.uv_1 = unpack2x16float(uvs.data[v_in.uvs_offset.y]);
+    vertex_out.uv_2 = unpack2x16float(uvs.data[v_in.uvs_offset.z]);
+    vertex_out.uv_3 = unpack2x16float(uvs.data[v_in.uvs_offset.w]);
+
+    return vertex_out;
+}
+
+@fragment
+fn fs_main(
+    v_in: VertexOutput,
+) -> @location(0) vec4<f32> {
+    let mesh_id = u32(v_in.mesh_and_meshlet_ids.x);
+    let mesh = &meshes.data[mesh_id];
+    let material_id = u32((*mesh).material_index);
+    let uv_set = vec4<u32>(
+        pack2x16float(v_in.uv_0),
+        pack2x16float(v_in.uv_1),
+        pack2x16float(v_in.uv_2),
+        pack2x16float(v_in.uv_3)
+    );
+
+    let texture_color = sample_material_texture(material_id, TEXTURE_TYPE_BASE_COLOR, uv_set);
+    let transmission = compute_transmission(material_id, uv_set);
+    let alpha = compute_alpha(material_id, v_in.color.a * texture_color.a, transmission);
+    if alpha <= 0. {
+        discard;
+    }
+
+    let color = vec4<f32>(v_in.color.rgb * texture_color.rgb, alpha);
+    let normal = rotate_vector(v_in.normal, (*mesh).orientation);
+    return compute_brdf(v_in.world_pos.xyz, normal, material_id, color, uv_set);
+}
\ No newline at end of file
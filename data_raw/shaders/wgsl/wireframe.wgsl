@@ -5,19 +5,29 @@ struct DebugVertex {
     @builtin(vertex_index) index: u32,
     @location(0) position: vec3<f32>,
     @location(1) color: u32,
+    @location(2) line_end: vec3<f32>,
 };
 
 struct DebugInstance {
     @builtin(instance_index) index: u32,
-    @location(2) index_start: u32,
-    @location(3) index_count: u32,
-    @location(4) vertex_start: u32,
-    @location(5) index: u32,
+    @location(3) instance_index: u32,
+    @location(4) index_start: u32,
+    @location(5) index_count: u32,
+    @location(6) vertex_start: u32,
+};
+
+struct WireframeData {
+    color: vec4<f32>,
+    thickness: f32,
+    _padding_0: f32,
+    _padding_1: f32,
+    _padding_2: f32,
 };
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) color: vec4<f32>,
+    @location(1) barycentric: vec3<f32>,
 };
 
 struct FragmentOutput {
@@ -27,6 +37,8 @@ struct FragmentOutput {
 
 @group(0) @binding(0)
 var<uniform> constant_data: ConstantData;
+@group(0) @binding(1)
+var<uniform> wireframe_data: WireframeData;
 
 #import "matrix_utils.inc"
 
@@ -35,10 +47,29 @@ fn vs_main(
     v_in: DebugVertex,
     i_in: DebugInstance,
 ) -> VertexOutput {
+    let view_proj = constant_data.proj * constant_data.view;
+    let corner = v_in.index % 3u;
 
     var vertex_out: VertexOutput;
-    vertex_out.clip_position = constant_data.proj * constant_data.view * vec4<f32>(v_in.position, 1.);
-    vertex_out.color = unpack_unorm_to_4_f32(v_in.color);
+    vertex_out.clip_position = view_proj * vec4<f32>(v_in.position, 1.);
+    vertex_out.color = unpack_unorm_to_4_f32(v_in.color) * wireframe_data.color;
+    vertex_out.barycentric = vec3<f32>(f32(corner == 0u), f32(corner == 1u), f32(corner == 2u));
+
+    if (any(v_in.line_end != v_in.position)) {
+        //Line quads are expanded in screen space by the thickness and are made only of edges
+        let screen_size = vec2<f32>(constant_data.screen_width, constant_data.screen_height);
+        let end_clip_position = view_proj * vec4<f32>(v_in.line_end, 1.);
+        let start = vertex_out.clip_position.xy / vertex_out.clip_position.w;
+        let end = end_clip_position.xy / end_clip_position.w;
+        let direction = normalize((end - start) * screen_size);
+        var side = -1.;
+        if (corner == 1u) {
+            side = 1.;
+        }
+        let offset = side * vec2<f32>(-direction.y, direction.x) * wireframe_data.thickness / screen_size;
+        vertex_out.clip_position = vec4<f32>(vertex_out.clip_position.xy + offset * vertex_out.clip_position.w, vertex_out.clip_position.zw);
+        vertex_out.barycentric = vec3<f32>(0.);
+    }
 
     return vertex_out;
 }
@@ -46,8 +77,16 @@ fn vs_main(
 @fragment
 fn fs_main(
     v_in: VertexOutput,
-) -> FragmentOutput {    
+) -> FragmentOutput {
+    //Distance from the nearest edge in pixels, compared with the thickness
+    let width = max(fwidth(v_in.barycentric) * wireframe_data.thickness, vec3<f32>(1e-5));
+    let edge = clamp(v_in.barycentric / width, vec3<f32>(0.), vec3<f32>(1.));
+    let edge_factor = 1. - min(edge.x, min(edge.y, edge.z));
+    if (edge_factor <= 0.) {
+        discard;
+    }
+
     var fragment_out: FragmentOutput;
-    fragment_out.color = v_in.color;
+    fragment_out.color = vec4<f32>(v_in.color.rgb, v_in.color.a * edge_factor);
     return fragment_out;
 }
\ No newline at end of file